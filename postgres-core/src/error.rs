@@ -0,0 +1,90 @@
+use fallible_iterator::FallibleIterator;
+use postgres_protocol::message::backend::ErrorResponseBody;
+use std::error;
+use std::fmt;
+use std::io;
+
+/// An error produced while driving the protocol.
+pub enum Error {
+    /// The server reported an error.
+    Db(ErrorResponseBody),
+    /// A configuration value required by the server's authentication request was missing.
+    Config(&'static str),
+    /// Authentication with the server failed.
+    Authentication(Box<dyn error::Error + Sync + Send>),
+    /// The server sent a message which was not expected in the current state of the connection.
+    UnexpectedMessage,
+    /// The server does not support the minimum protocol version required by the configuration.
+    UnsupportedProtocolVersion,
+    /// The server reported a client encoding other than the one requested.
+    ClientEncoding(String),
+    /// A message from the server could not be parsed.
+    Parse(io::Error),
+    /// A message to the server could not be encoded.
+    Encode(io::Error),
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Db(_) => fmt.debug_tuple("Db").field(&DbMessage(self)).finish(),
+            Error::Config(e) => fmt.debug_tuple("Config").field(e).finish(),
+            Error::Authentication(e) => fmt.debug_tuple("Authentication").field(e).finish(),
+            Error::UnexpectedMessage => fmt.write_str("UnexpectedMessage"),
+            Error::UnsupportedProtocolVersion => fmt.write_str("UnsupportedProtocolVersion"),
+            Error::ClientEncoding(e) => fmt.debug_tuple("ClientEncoding").field(e).finish(),
+            Error::Parse(e) => fmt.debug_tuple("Parse").field(e).finish(),
+            Error::Encode(e) => fmt.debug_tuple("Encode").field(e).finish(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Db(_) => write!(fmt, "db error: {}", DbMessage(self)),
+            Error::Config(e) => write!(fmt, "invalid configuration: {}", e),
+            Error::Authentication(e) => write!(fmt, "authentication error: {}", e),
+            Error::UnexpectedMessage => fmt.write_str("unexpected message from server"),
+            Error::UnsupportedProtocolVersion => {
+                fmt.write_str("server does not support the minimum protocol version")
+            }
+            Error::ClientEncoding(e) => write!(fmt, "server uses unexpected client encoding {}", e),
+            Error::Parse(e) => write!(fmt, "error parsing response from server: {}", e),
+            Error::Encode(e) => write!(fmt, "error encoding message to server: {}", e),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Authentication(e) => Some(&**e),
+            Error::Parse(e) | Error::Encode(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// Renders the message field of a database error.
+struct DbMessage<'a>(&'a Error);
+
+impl fmt::Display for DbMessage<'_> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Error::Db(body) = self.0 {
+            let mut fields = body.fields();
+            while let Ok(Some(field)) = fields.next() {
+                if field.type_() == b'M' {
+                    return fmt.write_str(field.value());
+                }
+            }
+        }
+        fmt.write_str("unknown error")
+    }
+}
+
+impl fmt::Debug for DbMessage<'_> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "{:?}", self.to_string())
+    }
+}
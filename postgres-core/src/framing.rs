@@ -0,0 +1,98 @@
+use bytes::BytesMut;
+use postgres_protocol::message::backend;
+use std::io;
+
+/// A group of backend messages split off of a buffer by a `Framer`.
+pub enum Frame {
+    /// Messages responding to requests.
+    ///
+    /// The frame ends with the `ReadyForQuery` message completing a request if `request_complete` is set.
+    Response {
+        /// The raw messages.
+        messages: BytesMut,
+        /// Whether the last message completes a request.
+        request_complete: bool,
+    },
+    /// A single raw message sent asynchronously by the server: a notice, notification, or parameter status change.
+    Async(BytesMut),
+}
+
+/// Splits the data received from the server into frames of complete messages.
+///
+/// Consecutive response messages are grouped together so they can be handed off in bulk, while asynchronous messages,
+/// which are not part of the response to any request, are split off individually.
+#[derive(Debug, Clone, Default)]
+pub struct Framer {
+    max_message_length: Option<usize>,
+}
+
+impl Framer {
+    /// Creates a new framer.
+    pub fn new() -> Framer {
+        Framer::default()
+    }
+
+    /// Sets the maximum length of a message the server may send.
+    ///
+    /// Messages with a longer length are rejected with an error as soon as their header is received, rather than
+    /// buffering them in full. Defaults to no limit.
+    pub fn max_message_length(&mut self, max_message_length: Option<usize>) -> &mut Framer {
+        self.max_message_length = max_message_length;
+        self
+    }
+
+    /// Splits the next frame off of the front of the buffer, if it contains one.
+    pub fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<Frame>> {
+        let mut idx = 0;
+        let mut request_complete = false;
+
+        while let Some(header) = backend::Header::parse(&src[idx..])? {
+            if let Some(max) = self.max_message_length {
+                if header.len() as usize > max {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "backend message length {} exceeds the maximum of {}",
+                            header.len(),
+                            max
+                        ),
+                    ));
+                }
+            }
+
+            let len = header.len() as usize + 1;
+            if src[idx..].len() < len {
+                break;
+            }
+
+            match header.tag() {
+                backend::NOTICE_RESPONSE_TAG
+                | backend::NOTIFICATION_RESPONSE_TAG
+                | backend::PARAMETER_STATUS_TAG => {
+                    if idx == 0 {
+                        return Ok(Some(Frame::Async(src.split_to(len))));
+                    } else {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+
+            idx += len;
+
+            if header.tag() == backend::READY_FOR_QUERY_TAG {
+                request_complete = true;
+                break;
+            }
+        }
+
+        if idx == 0 {
+            Ok(None)
+        } else {
+            Ok(Some(Frame::Response {
+                messages: src.split_to(idx),
+                request_complete,
+            }))
+        }
+    }
+}
@@ -0,0 +1,553 @@
+use crate::Error;
+use bytes::BytesMut;
+use fallible_iterator::FallibleIterator;
+use postgres_protocol::authentication;
+use postgres_protocol::authentication::sasl::{self, ScramSha256};
+use postgres_protocol::message::backend::{
+    AuthenticationSaslBody, Message, NegotiateProtocolVersionBody, NoticeResponseBody,
+};
+use postgres_protocol::message::frontend;
+use std::collections::HashMap;
+use std::fmt;
+use std::mem;
+
+/// Channel binding configuration.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum ChannelBinding {
+    /// Do not use channel binding.
+    Disable,
+    /// Attempt to use channel binding but allow sessions without.
+    Prefer,
+    /// Require the use of channel binding.
+    Require,
+}
+
+/// A version of the frontend/backend protocol.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum ProtocolVersion {
+    /// Version 3.0, supported by all servers since Postgres 7.4.
+    V3_0,
+    /// Version 3.2, introduced in Postgres 18, which allows longer secret keys for query cancellation.
+    V3_2,
+}
+
+impl ProtocolVersion {
+    fn minor(self) -> i32 {
+        match self {
+            ProtocolVersion::V3_0 => 0,
+            ProtocolVersion::V3_2 => 2,
+        }
+    }
+
+    // there was never a version 3.1, so servers reporting it only support 3.0
+    fn from_minor(minor: i32) -> ProtocolVersion {
+        if minor >= 2 {
+            ProtocolVersion::V3_2
+        } else {
+            ProtocolVersion::V3_0
+        }
+    }
+}
+
+/// The method used to authenticate a session.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AuthenticationMethod {
+    /// The server accepted the session without requesting credentials, as with its `trust`, `peer` and `cert` methods.
+    Trust,
+    /// A cleartext password.
+    Password,
+    /// An MD5-hashed password.
+    Md5,
+    /// SCRAM-SHA-256 without channel binding.
+    ScramSha256,
+    /// SCRAM-SHA-256 with channel binding to the TLS session.
+    ScramSha256Plus,
+}
+
+/// The settings used to authenticate with the server and start a session.
+#[derive(Clone, PartialEq)]
+pub struct HandshakeConfig {
+    user: Option<String>,
+    password: Option<Vec<u8>>,
+    params: Vec<(String, String)>,
+    client_encoding: String,
+    channel_binding: ChannelBinding,
+    tls_server_end_point: Option<Vec<u8>>,
+    min_protocol_version: ProtocolVersion,
+    max_protocol_version: ProtocolVersion,
+    protocol_extensions: Vec<(String, String)>,
+}
+
+impl Default for HandshakeConfig {
+    fn default() -> HandshakeConfig {
+        HandshakeConfig::new()
+    }
+}
+
+impl HandshakeConfig {
+    /// Creates a new configuration.
+    pub fn new() -> HandshakeConfig {
+        HandshakeConfig {
+            user: None,
+            password: None,
+            params: vec![],
+            client_encoding: "UTF8".to_string(),
+            channel_binding: ChannelBinding::Prefer,
+            tls_server_end_point: None,
+            min_protocol_version: ProtocolVersion::V3_0,
+            max_protocol_version: ProtocolVersion::V3_0,
+            protocol_extensions: vec![],
+        }
+    }
+
+    /// Sets the user to authenticate with.
+    pub fn user(&mut self, user: &str) -> &mut HandshakeConfig {
+        self.user = Some(user.to_string());
+        self
+    }
+
+    /// Sets the password to authenticate with.
+    pub fn password<T>(&mut self, password: T) -> &mut HandshakeConfig
+    where
+        T: AsRef<[u8]>,
+    {
+        self.password = Some(password.as_ref().to_vec());
+        self
+    }
+
+    /// Adds a parameter to the startup message, such as `database` or `application_name`.
+    ///
+    /// The `client_encoding` parameter is taken from the `client_encoding` method, and the `user` parameter from the
+    /// `user` method.
+    pub fn param(&mut self, name: &str, value: &str) -> &mut HandshakeConfig {
+        self.params.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Sets the character encoding requested for text exchanged with the server.
+    ///
+    /// The handshake fails if the server reports a different encoding, which can happen when a connection pooler or
+    /// per-role setting overrides the request. Defaults to `UTF8`.
+    pub fn client_encoding(&mut self, client_encoding: &str) -> &mut HandshakeConfig {
+        self.client_encoding = client_encoding.to_string();
+        self
+    }
+
+    /// Sets the channel binding behavior.
+    ///
+    /// Defaults to `prefer`.
+    pub fn channel_binding(&mut self, channel_binding: ChannelBinding) -> &mut HandshakeConfig {
+        self.channel_binding = channel_binding;
+        self
+    }
+
+    /// Sets the `tls-server-end-point` channel binding data of the TLS session the connection runs over.
+    pub fn tls_server_end_point(&mut self, tls_server_end_point: Vec<u8>) -> &mut HandshakeConfig {
+        self.tls_server_end_point = Some(tls_server_end_point);
+        self
+    }
+
+    /// Sets the oldest protocol version the server may negotiate down to.
+    ///
+    /// If this is newer than the maximum, it is requested instead. Defaults to `V3_0`.
+    pub fn min_protocol_version(&mut self, version: ProtocolVersion) -> &mut HandshakeConfig {
+        self.min_protocol_version = version;
+        self
+    }
+
+    /// Sets the protocol version requested in the startup message.
+    ///
+    /// Servers which only support an older minor version reply with the newest one they do support, which is used
+    /// instead as long as it is no older than the minimum. Defaults to `V3_0`.
+    pub fn max_protocol_version(&mut self, version: ProtocolVersion) -> &mut HandshakeConfig {
+        self.max_protocol_version = version;
+        self
+    }
+
+    /// Adds a protocol extension option to the startup message.
+    ///
+    /// The name is sent with the `_pq_.` prefix reserved for protocol extensions. Servers ignore options they don't
+    /// recognize, and report them back during the handshake.
+    pub fn protocol_extension(&mut self, name: &str, value: &str) -> &mut HandshakeConfig {
+        self.protocol_extensions
+            .push((format!("_pq_.{}", name), value.to_string()));
+        self
+    }
+
+    fn can_skip_channel_binding(&self) -> Result<(), Error> {
+        match self.channel_binding {
+            ChannelBinding::Disable | ChannelBinding::Prefer => Ok(()),
+            ChannelBinding::Require => Err(Error::Authentication(
+                "server did not use channel binding".into(),
+            )),
+        }
+    }
+
+    fn password_required(&self) -> Result<&[u8], Error> {
+        self.password
+            .as_deref()
+            .ok_or(Error::Config("password missing"))
+    }
+}
+
+impl fmt::Debug for HandshakeConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        struct Redaction {}
+        impl fmt::Debug for Redaction {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "_")
+            }
+        }
+
+        f.debug_struct("HandshakeConfig")
+            .field("user", &self.user)
+            .field("password", &self.password.as_ref().map(|_| Redaction {}))
+            .field("params", &self.params)
+            .field("client_encoding", &self.client_encoding)
+            .field("channel_binding", &self.channel_binding)
+            .field("min_protocol_version", &self.min_protocol_version)
+            .field("max_protocol_version", &self.max_protocol_version)
+            .field("protocol_extensions", &self.protocol_extensions)
+            .finish()
+    }
+}
+
+/// Information about the session, sent by the server at the end of the handshake.
+#[derive(Debug, Clone)]
+pub struct ServerInfo {
+    process_id: i32,
+    secret_key: Vec<u8>,
+    parameters: HashMap<String, String>,
+    protocol_version: ProtocolVersion,
+    unsupported_protocol_extensions: Vec<String>,
+    authentication_method: AuthenticationMethod,
+}
+
+impl ServerInfo {
+    /// Returns the process ID of the backend serving the session.
+    pub fn process_id(&self) -> i32 {
+        self.process_id
+    }
+
+    /// Returns the first four bytes of the secret key used to cancel queries running in the session as an integer.
+    ///
+    /// This is the entire key unless protocol version 3.2 is in use, which allows longer keys.
+    pub fn secret_key(&self) -> i32 {
+        match self.secret_key.get(..4) {
+            Some(key) => i32::from_be_bytes([key[0], key[1], key[2], key[3]]),
+            None => 0,
+        }
+    }
+
+    /// Returns the secret key used to cancel queries running in the session.
+    pub fn secret_key_bytes(&self) -> &[u8] {
+        &self.secret_key
+    }
+
+    /// Returns the protocol version used by the session.
+    pub fn protocol_version(&self) -> ProtocolVersion {
+        self.protocol_version
+    }
+
+    /// Returns the names of the protocol extensions requested in the startup message which the server did not
+    /// recognize, without their `_pq_.` prefix.
+    pub fn unsupported_protocol_extensions(&self) -> &[String] {
+        &self.unsupported_protocol_extensions
+    }
+
+    /// Returns the method used to authenticate the session.
+    pub fn authentication_method(&self) -> AuthenticationMethod {
+        self.authentication_method
+    }
+
+    /// Returns the runtime parameters reported by the server.
+    pub fn parameters(&self) -> &HashMap<String, String> {
+        &self.parameters
+    }
+
+    /// Consumes the information, returning the runtime parameters reported by the server.
+    pub fn into_parameters(self) -> HashMap<String, String> {
+        self.parameters
+    }
+}
+
+/// The outcome of a message handled by a `Handshake`.
+pub enum Step {
+    /// The handshake is still in progress.
+    Continue,
+    /// The server sent a notice, which should be reported once the connection is established.
+    Notice(NoticeResponseBody),
+    /// The handshake is complete, and the connection is ready for queries.
+    Ready(ServerInfo),
+}
+
+enum State {
+    Startup,
+    Authenticating,
+    SaslContinue(ScramSha256),
+    SaslFinal(ScramSha256),
+    AwaitingOk,
+    ReadingInfo(ServerInfo),
+    Done,
+}
+
+/// A state machine driving the startup and authentication of a connection.
+///
+/// The handshake is started with `start`, after which each message received from the server is passed to `handle`.
+/// Both write any messages which must be sent to the server in response to the provided buffer.
+pub struct Handshake {
+    config: HandshakeConfig,
+    state: State,
+    protocol_version: ProtocolVersion,
+    unsupported_protocol_extensions: Vec<String>,
+    authentication_method: AuthenticationMethod,
+}
+
+impl Handshake {
+    /// Creates a new handshake.
+    pub fn new(config: HandshakeConfig) -> Handshake {
+        // a minimum above the maximum raises the version requested
+        let protocol_version = config.max_protocol_version.max(config.min_protocol_version);
+        Handshake {
+            config,
+            state: State::Startup,
+            protocol_version,
+            unsupported_protocol_extensions: vec![],
+            authentication_method: AuthenticationMethod::Trust,
+        }
+    }
+
+    /// Returns the configuration of the handshake.
+    pub fn config(&self) -> &HandshakeConfig {
+        &self.config
+    }
+
+    /// Determines if the handshake has finished, either successfully or with an error.
+    pub fn is_done(&self) -> bool {
+        matches!(self.state, State::Done)
+    }
+
+    /// Writes the startup message to the buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the handshake has already been started.
+    pub fn start(&mut self, buf: &mut BytesMut) -> Result<(), Error> {
+        assert!(
+            matches!(self.state, State::Startup),
+            "handshake has already been started"
+        );
+
+        let mut params = vec![("client_encoding", &*self.config.client_encoding)];
+        if let Some(user) = &self.config.user {
+            params.push(("user", &**user));
+        }
+        params.extend(self.config.params.iter().map(|(k, v)| (&**k, &**v)));
+        params.extend(
+            self.config
+                .protocol_extensions
+                .iter()
+                .map(|(k, v)| (&**k, &**v)),
+        );
+        let version = 0x00_03_00_00 | self.protocol_version.minor();
+        frontend::startup_message_with_version(version, params, buf).map_err(Error::Encode)?;
+
+        self.state = State::Authenticating;
+        Ok(())
+    }
+
+    /// Handles a message received from the server, writing any response to the buffer.
+    pub fn handle(&mut self, message: Message, buf: &mut BytesMut) -> Result<Step, Error> {
+        if let Message::ErrorResponse(body) = message {
+            self.state = State::Done;
+            return Err(Error::Db(body));
+        }
+
+        match (mem::replace(&mut self.state, State::Done), message) {
+            (State::Authenticating, Message::NegotiateProtocolVersion(body)) => {
+                self.negotiate_protocol_version(body)?;
+                self.state = State::Authenticating;
+            }
+            (State::Authenticating, Message::AuthenticationOk) => {
+                self.config.can_skip_channel_binding()?;
+                self.state = State::ReadingInfo(self.server_info());
+            }
+            (State::Authenticating, Message::AuthenticationCleartextPassword) => {
+                self.config.can_skip_channel_binding()?;
+                let password = self.config.password_required()?;
+                frontend::password_message(password, buf).map_err(Error::Encode)?;
+                self.authentication_method = AuthenticationMethod::Password;
+                self.state = State::AwaitingOk;
+            }
+            (State::Authenticating, Message::AuthenticationMd5Password(body)) => {
+                self.config.can_skip_channel_binding()?;
+                let user = self
+                    .config
+                    .user
+                    .as_ref()
+                    .ok_or(Error::Config("user missing"))?;
+                let password = self.config.password_required()?;
+                let output = authentication::md5_hash(user.as_bytes(), password, body.salt());
+                frontend::password_message(output.as_bytes(), buf).map_err(Error::Encode)?;
+                self.authentication_method = AuthenticationMethod::Md5;
+                self.state = State::AwaitingOk;
+            }
+            (State::Authenticating, Message::AuthenticationSasl(body)) => {
+                let scram = self.start_sasl(body, buf)?;
+                self.state = State::SaslContinue(scram);
+            }
+            (State::Authenticating, Message::AuthenticationKerberosV5)
+            | (State::Authenticating, Message::AuthenticationScmCredential)
+            | (State::Authenticating, Message::AuthenticationGss)
+            | (State::Authenticating, Message::AuthenticationSspi) => {
+                return Err(Error::Authentication(
+                    "unsupported authentication method".into(),
+                ))
+            }
+            (State::SaslContinue(mut scram), Message::AuthenticationSaslContinue(body)) => {
+                scram
+                    .update(body.data())
+                    .map_err(|e| Error::Authentication(e.into()))?;
+                frontend::sasl_response(scram.message(), buf).map_err(Error::Encode)?;
+                self.state = State::SaslFinal(scram);
+            }
+            (State::SaslFinal(mut scram), Message::AuthenticationSaslFinal(body)) => {
+                scram
+                    .finish(body.data())
+                    .map_err(|e| Error::Authentication(e.into()))?;
+                self.state = State::AwaitingOk;
+            }
+            (State::AwaitingOk, Message::AuthenticationOk) => {
+                self.state = State::ReadingInfo(self.server_info());
+            }
+            (State::ReadingInfo(mut info), Message::BackendKeyData(body)) => {
+                info.process_id = body.process_id();
+                info.secret_key = body.secret_key_bytes().to_vec();
+                self.state = State::ReadingInfo(info);
+            }
+            (State::ReadingInfo(mut info), Message::ParameterStatus(body)) => {
+                info.parameters.insert(
+                    body.name().map_err(Error::Parse)?.to_string(),
+                    body.value().map_err(Error::Parse)?.to_string(),
+                );
+                self.state = State::ReadingInfo(info);
+            }
+            (State::ReadingInfo(info), Message::NoticeResponse(body)) => {
+                self.state = State::ReadingInfo(info);
+                return Ok(Step::Notice(body));
+            }
+            (State::ReadingInfo(info), Message::ReadyForQuery(_)) => {
+                if let Some(encoding) = info.parameters.get("client_encoding") {
+                    if !encoding_eq(encoding, &self.config.client_encoding) {
+                        return Err(Error::ClientEncoding(encoding.clone()));
+                    }
+                }
+                return Ok(Step::Ready(info));
+            }
+            _ => return Err(Error::UnexpectedMessage),
+        }
+
+        Ok(Step::Continue)
+    }
+
+    fn negotiate_protocol_version(
+        &mut self,
+        body: NegotiateProtocolVersionBody,
+    ) -> Result<(), Error> {
+        let version = ProtocolVersion::from_minor(body.newest_version() & 0xffff);
+        if version < self.config.min_protocol_version {
+            return Err(Error::UnsupportedProtocolVersion);
+        }
+        self.protocol_version = version.min(self.protocol_version);
+
+        let mut options = body.options();
+        while let Some(option) = options.next().map_err(Error::Parse)? {
+            let name = option.strip_prefix("_pq_.").unwrap_or(option);
+            self.unsupported_protocol_extensions.push(name.to_string());
+        }
+
+        Ok(())
+    }
+
+    fn server_info(&self) -> ServerInfo {
+        ServerInfo {
+            process_id: 0,
+            secret_key: vec![],
+            parameters: HashMap::new(),
+            protocol_version: self.protocol_version,
+            unsupported_protocol_extensions: self.unsupported_protocol_extensions.clone(),
+            authentication_method: self.authentication_method,
+        }
+    }
+
+    fn start_sasl(
+        &mut self,
+        body: AuthenticationSaslBody,
+        buf: &mut BytesMut,
+    ) -> Result<ScramSha256, Error> {
+        let password = self.config.password_required()?;
+
+        let mut has_scram = false;
+        let mut has_scram_plus = false;
+        let mut mechanisms = body.mechanisms();
+        while let Some(mechanism) = mechanisms.next().map_err(Error::Parse)? {
+            match mechanism {
+                sasl::SCRAM_SHA_256 => has_scram = true,
+                sasl::SCRAM_SHA_256_PLUS => has_scram_plus = true,
+                _ => {}
+            }
+        }
+
+        let channel_binding = self
+            .config
+            .tls_server_end_point
+            .clone()
+            .filter(|_| self.config.channel_binding != ChannelBinding::Disable)
+            .map(sasl::ChannelBinding::tls_server_end_point);
+
+        let (channel_binding, mechanism) = if has_scram_plus {
+            match channel_binding {
+                Some(channel_binding) => (channel_binding, sasl::SCRAM_SHA_256_PLUS),
+                None => (sasl::ChannelBinding::unsupported(), sasl::SCRAM_SHA_256),
+            }
+        } else if has_scram {
+            match channel_binding {
+                Some(_) => (sasl::ChannelBinding::unrequested(), sasl::SCRAM_SHA_256),
+                None => (sasl::ChannelBinding::unsupported(), sasl::SCRAM_SHA_256),
+            }
+        } else {
+            return Err(Error::Authentication("unsupported SASL mechanism".into()));
+        };
+
+        if mechanism == sasl::SCRAM_SHA_256_PLUS {
+            self.authentication_method = AuthenticationMethod::ScramSha256Plus;
+        } else {
+            self.config.can_skip_channel_binding()?;
+            self.authentication_method = AuthenticationMethod::ScramSha256;
+        }
+
+        let scram = ScramSha256::new(password, channel_binding);
+        frontend::sasl_initial_response(mechanism, scram.message(), buf).map_err(Error::Encode)?;
+        Ok(scram)
+    }
+}
+
+// Compares encoding names the way the server does, ignoring case and punctuation, so that `utf-8` matches `UTF8`.
+fn encoding_eq(a: &str, b: &str) -> bool {
+    fn normalize(name: &str) -> String {
+        let name = name
+            .chars()
+            .filter(char::is_ascii_alphanumeric)
+            .map(|c| c.to_ascii_lowercase())
+            .collect::<String>();
+        match &*name {
+            "unicode" => "utf8".to_string(),
+            _ => name,
+        }
+    }
+
+    normalize(a) == normalize(b)
+}
@@ -0,0 +1,63 @@
+//! Sans-IO handshake and framing for the Postgres client protocol.
+//!
+//! This crate contains the state machines for the startup and authentication of a Postgres connection, and for
+//! splitting the data received from the server into messages, without performing any I/O itself: bytes received from
+//! the server are fed in, and the bytes to send in response are handed back out. This allows connections to be
+//! established from custom event loops, over transports such as WASI sockets, and tested deterministically without a
+//! server.
+//!
+//! `tokio-postgres` establishes its connections with the `Handshake`, and splits up the server's responses with the
+//! `Framer`. Sending requests and routing the responses to them once connected is left to the caller.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use bytes::BytesMut;
+//! use postgres_core::{Handshake, HandshakeConfig, Step};
+//! use postgres_protocol::message::backend::Message;
+//! use std::io::{Read, Write};
+//! use std::net::TcpStream;
+//!
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut socket = TcpStream::connect("localhost:5432")?;
+//! let mut config = HandshakeConfig::new();
+//! config.user("postgres");
+//! let mut handshake = Handshake::new(config);
+//!
+//! let mut write_buf = BytesMut::new();
+//! let mut read_buf = BytesMut::new();
+//! handshake.start(&mut write_buf)?;
+//! let info = loop {
+//!     socket.write_all(&write_buf.split())?;
+//!     match Message::parse(&mut read_buf)? {
+//!         Some(message) => {
+//!             if let Step::Ready(info) = handshake.handle(message, &mut write_buf)? {
+//!                 break info;
+//!             }
+//!         }
+//!         None => {
+//!             let mut buf = [0; 4096];
+//!             let n = socket.read(&mut buf)?;
+//!             read_buf.extend_from_slice(&buf[..n]);
+//!         }
+//!     }
+//! };
+//! println!("connected to backend {}", info.process_id());
+//! # Ok(())
+//! # }
+//! ```
+#![doc(html_root_url = "https://docs.rs/postgres-core/0.1")]
+#![warn(missing_docs, rust_2018_idioms, clippy::all)]
+
+pub use crate::error::Error;
+pub use crate::framing::{Frame, Framer};
+pub use crate::handshake::{
+    AuthenticationMethod, ChannelBinding, Handshake, HandshakeConfig, ProtocolVersion, ServerInfo,
+    Step,
+};
+
+mod error;
+mod framing;
+mod handshake;
+#[cfg(test)]
+mod test;
@@ -0,0 +1,275 @@
+use crate::{
+    AuthenticationMethod, Error, Frame, Framer, Handshake, HandshakeConfig, ProtocolVersion, Step,
+};
+use bytes::{BufMut, BytesMut};
+use postgres_protocol::message::backend::Message;
+
+fn message(tag: u8, body: &[u8]) -> Vec<u8> {
+    let mut buf = vec![tag];
+    buf.put_i32(body.len() as i32 + 4);
+    buf.extend_from_slice(body);
+    buf
+}
+
+fn authentication(code: i32, data: &[u8]) -> Vec<u8> {
+    let mut body = code.to_be_bytes().to_vec();
+    body.extend_from_slice(data);
+    message(b'R', &body)
+}
+
+fn parameter_status(name: &str, value: &str) -> Vec<u8> {
+    message(b'S', format!("{}\0{}\0", name, value).as_bytes())
+}
+
+fn backend_key_data(process_id: i32, secret_key: i32) -> Vec<u8> {
+    let mut body = process_id.to_be_bytes().to_vec();
+    body.extend_from_slice(&secret_key.to_be_bytes());
+    message(b'K', &body)
+}
+
+fn ready_for_query() -> Vec<u8> {
+    message(b'Z', b"I")
+}
+
+fn command_complete(tag: &str) -> Vec<u8> {
+    message(b'C', format!("{}\0", tag).as_bytes())
+}
+
+fn error_response(message_text: &str) -> Vec<u8> {
+    message(
+        b'E',
+        format!("SERROR\0C28P01\0M{}\0\0", message_text).as_bytes(),
+    )
+}
+
+fn config() -> HandshakeConfig {
+    let mut config = HandshakeConfig::new();
+    config.user("postgres").password("secret");
+    config
+}
+
+fn start(handshake: &mut Handshake) -> BytesMut {
+    let mut buf = BytesMut::new();
+    handshake.start(&mut buf).unwrap();
+    buf
+}
+
+// Handles each of the messages in the data, returning the outcome of the last one and anything written in response.
+fn handle(handshake: &mut Handshake, data: &[u8]) -> (Result<Step, Error>, BytesMut) {
+    let mut data = BytesMut::from(data);
+    let mut buf = BytesMut::new();
+    loop {
+        let message = Message::parse(&mut data).unwrap().unwrap();
+        let step = handshake.handle(message, &mut buf);
+        if step.is_err() || data.is_empty() {
+            return (step, buf);
+        }
+    }
+}
+
+#[test]
+fn cleartext_password_handshake() {
+    let mut handshake = Handshake::new(config());
+
+    let startup = start(&mut handshake);
+    assert!(startup
+        .windows(b"user\0postgres\0".len())
+        .any(|w| w == b"user\0postgres\0"));
+
+    let (step, password) = handle(&mut handshake, &authentication(3, &[]));
+    assert!(matches!(step, Ok(Step::Continue)));
+    assert_eq!(&password[..], &message(b'p', b"secret\0")[..]);
+
+    let mut data = authentication(0, &[]);
+    data.extend(parameter_status("server_version", "14.1"));
+    data.extend(backend_key_data(1, 2));
+    data.extend(ready_for_query());
+    match handle(&mut handshake, &data).0 {
+        Ok(Step::Ready(info)) => {
+            assert_eq!(info.process_id(), 1);
+            assert_eq!(info.secret_key(), 2);
+            assert_eq!(info.parameters()["server_version"], "14.1");
+            assert_eq!(info.authentication_method(), AuthenticationMethod::Password);
+        }
+        _ => panic!("expected connection"),
+    }
+    assert!(handshake.is_done());
+}
+
+#[test]
+fn missing_password() {
+    let mut config = HandshakeConfig::new();
+    config.user("postgres");
+    let mut handshake = Handshake::new(config);
+    start(&mut handshake);
+
+    match handle(&mut handshake, &authentication(3, &[])).0 {
+        Err(Error::Config("password missing")) => {}
+        r => panic!("unexpected result {:?}", r.map(|_| ())),
+    }
+}
+
+#[test]
+fn authentication_error() {
+    let mut handshake = Handshake::new(config());
+    start(&mut handshake);
+
+    match handle(
+        &mut handshake,
+        &error_response("password authentication failed"),
+    )
+    .0
+    {
+        Err(e @ Error::Db(_)) => {
+            assert_eq!(e.to_string(), "db error: password authentication failed")
+        }
+        r => panic!("unexpected result {:?}", r.map(|_| ())),
+    }
+    assert!(handshake.is_done());
+}
+
+#[test]
+fn framer_splits_async_messages() {
+    let mut buf = BytesMut::new();
+    buf.extend(command_complete("SELECT 1"));
+    buf.extend(parameter_status("TimeZone", "UTC"));
+    buf.extend(ready_for_query());
+    buf.extend(command_complete("SELECT 2"));
+
+    let mut framer = Framer::new();
+    match framer.decode(&mut buf).unwrap() {
+        Some(Frame::Response {
+            messages,
+            request_complete: false,
+        }) => assert_eq!(messages, &command_complete("SELECT 1")[..]),
+        _ => panic!("expected response"),
+    }
+    match framer.decode(&mut buf).unwrap() {
+        Some(Frame::Async(message)) => {
+            assert_eq!(message, &parameter_status("TimeZone", "UTC")[..])
+        }
+        _ => panic!("expected async message"),
+    }
+    match framer.decode(&mut buf).unwrap() {
+        Some(Frame::Response {
+            messages,
+            request_complete: true,
+        }) => assert_eq!(messages, &ready_for_query()[..]),
+        _ => panic!("expected completed response"),
+    }
+    match framer.decode(&mut buf).unwrap() {
+        Some(Frame::Response {
+            request_complete: false,
+            ..
+        }) => {}
+        _ => panic!("expected response"),
+    }
+    assert!(framer.decode(&mut buf).unwrap().is_none());
+}
+
+#[test]
+fn framer_max_message_length() {
+    let mut buf = BytesMut::new();
+    buf.extend(&command_complete("SELECT 1")[..5]);
+
+    let mut framer = Framer::new();
+    framer.max_message_length(Some(8));
+    assert!(framer.decode(&mut buf).is_err());
+}
+
+fn negotiate_protocol_version(newest_version: i32, options: &[&str]) -> Vec<u8> {
+    let mut body = newest_version.to_be_bytes().to_vec();
+    body.extend_from_slice(&(options.len() as i32).to_be_bytes());
+    for option in options {
+        body.extend_from_slice(option.as_bytes());
+        body.push(0);
+    }
+    message(b'v', &body)
+}
+
+#[test]
+fn protocol_version_negotiation() {
+    let mut config = config();
+    config
+        .max_protocol_version(ProtocolVersion::V3_2)
+        .protocol_extension("foo", "bar");
+    let mut handshake = Handshake::new(config);
+
+    let startup = start(&mut handshake);
+    assert_eq!(&startup[4..8], &0x00_03_00_02i32.to_be_bytes());
+    assert!(startup
+        .windows(b"_pq_.foo\0bar\0".len())
+        .any(|w| w == b"_pq_.foo\0bar\0"));
+
+    let mut data = negotiate_protocol_version(0x00_03_00_00, &["_pq_.foo"]);
+    data.extend(authentication(0, &[]));
+    data.extend(backend_key_data(1, 2));
+    data.extend(ready_for_query());
+    match handle(&mut handshake, &data).0 {
+        Ok(Step::Ready(info)) => {
+            assert_eq!(info.protocol_version(), ProtocolVersion::V3_0);
+            assert_eq!(info.unsupported_protocol_extensions(), ["foo"]);
+            assert_eq!(info.secret_key_bytes(), [0, 0, 0, 2]);
+        }
+        _ => panic!("expected connection"),
+    }
+}
+
+#[test]
+fn protocol_version_3_2() {
+    let mut config = config();
+    config
+        .min_protocol_version(ProtocolVersion::V3_2)
+        .max_protocol_version(ProtocolVersion::V3_2);
+    let mut handshake = Handshake::new(config);
+    start(&mut handshake);
+
+    let key = (0..32).collect::<Vec<u8>>();
+    let mut body = 1i32.to_be_bytes().to_vec();
+    body.extend_from_slice(&key);
+
+    let mut data = authentication(0, &[]);
+    data.extend(message(b'K', &body));
+    data.extend(ready_for_query());
+    match handle(&mut handshake, &data).0 {
+        Ok(Step::Ready(info)) => {
+            assert_eq!(info.protocol_version(), ProtocolVersion::V3_2);
+            assert_eq!(info.secret_key_bytes(), &key[..]);
+        }
+        _ => panic!("expected connection"),
+    }
+}
+
+#[test]
+fn unsupported_protocol_version() {
+    let mut config = config();
+    config
+        .min_protocol_version(ProtocolVersion::V3_2)
+        .max_protocol_version(ProtocolVersion::V3_2);
+    let mut handshake = Handshake::new(config);
+    start(&mut handshake);
+
+    match handle(
+        &mut handshake,
+        &negotiate_protocol_version(0x00_03_00_00, &[]),
+    )
+    .0
+    {
+        Err(Error::UnsupportedProtocolVersion) => {}
+        r => panic!("unexpected result {:?}", r.map(|_| ())),
+    }
+}
+
+#[test]
+fn unexpected_client_encoding() {
+    let mut handshake = Handshake::new(config());
+    start(&mut handshake);
+
+    let mut data = authentication(0, &[]);
+    data.extend(parameter_status("client_encoding", "SQL_ASCII"));
+    data.extend(ready_for_query());
+    match handle(&mut handshake, &data).0 {
+        Err(Error::ClientEncoding(encoding)) => assert_eq!(encoding, "SQL_ASCII"),
+        r => panic!("unexpected result {:?}", r.map(|_| ())),
+    }
+}
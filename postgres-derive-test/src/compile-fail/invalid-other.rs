@@ -0,0 +1,18 @@
+use postgres_types::{FromSql, ToSql};
+
+#[derive(FromSql)]
+enum Foo {
+    Bar,
+    #[postgres(other)]
+    Baz,
+    #[postgres(other)]
+    Buz,
+}
+
+#[derive(ToSql)]
+#[postgres(other)]
+enum Bar {
+    Baz,
+}
+
+fn main() {}
@@ -238,3 +238,99 @@ fn raw_ident_field() {
 
     test_type(&mut conn, "inventory_item", &[(item, "ROW('foo')")]);
 }
+
+#[test]
+fn ignore_unknown_fields() {
+    #[derive(FromSql, Debug, PartialEq)]
+    #[postgres(name = "inventory_item", ignore_unknown_fields)]
+    struct InventoryItem {
+        name: String,
+        price: Option<f64>,
+    }
+
+    let mut conn = Client::connect("user=postgres host=localhost port=5433", NoTls).unwrap();
+    conn.batch_execute(
+        "CREATE TYPE pg_temp.inventory_item AS (
+            name TEXT,
+            supplier_id INT,
+            price DOUBLE PRECISION,
+            tags TEXT[]
+        );",
+    )
+    .unwrap();
+
+    let row = conn
+        .query_one(
+            "SELECT ROW('foobar', 100, 15.50, ARRAY['a'])::inventory_item",
+            &[],
+        )
+        .unwrap();
+    assert_eq!(
+        row.get::<_, InventoryItem>(0),
+        InventoryItem {
+            name: "foobar".to_owned(),
+            price: Some(15.50),
+        }
+    );
+}
+
+#[test]
+fn default_fields() {
+    #[derive(FromSql, Debug, PartialEq)]
+    #[postgres(name = "inventory_item")]
+    struct InventoryItem {
+        name: String,
+        #[postgres(default)]
+        supplier_id: i32,
+        #[postgres(default)]
+        tags: Vec<String>,
+    }
+
+    let mut conn = Client::connect("user=postgres host=localhost port=5433", NoTls).unwrap();
+    conn.batch_execute(
+        "CREATE TYPE pg_temp.inventory_item AS (
+            name TEXT,
+            supplier_id INT
+        );",
+    )
+    .unwrap();
+
+    let row = conn
+        .query_one("SELECT ROW('foobar', 100)::inventory_item", &[])
+        .unwrap();
+    assert_eq!(
+        row.get::<_, InventoryItem>(0),
+        InventoryItem {
+            name: "foobar".to_owned(),
+            supplier_id: 100,
+            tags: vec![],
+        }
+    );
+}
+
+#[test]
+fn unknown_field() {
+    #[derive(FromSql, Debug, PartialEq)]
+    #[postgres(name = "inventory_item")]
+    struct InventoryItem {
+        name: String,
+        #[postgres(default)]
+        price: Option<f64>,
+    }
+
+    let mut conn = Client::connect("user=postgres host=localhost port=5433", NoTls).unwrap();
+    conn.batch_execute(
+        "CREATE TYPE pg_temp.inventory_item AS (
+            name TEXT,
+            supplier_id INT
+        );",
+    )
+    .unwrap();
+
+    let err = conn
+        .query_one("SELECT ROW('foobar', 100)::inventory_item", &[])
+        .unwrap()
+        .try_get::<_, InventoryItem>(0)
+        .unwrap_err();
+    assert!(err.source().unwrap().is::<WrongType>());
+}
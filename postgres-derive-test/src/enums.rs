@@ -102,3 +102,50 @@ fn missing_variant() {
     let err = conn.execute("SELECT $1::foo", &[&Foo::Bar]).unwrap_err();
     assert!(err.source().unwrap().is::<WrongType>());
 }
+
+#[test]
+fn other_variant() {
+    #[derive(Debug, ToSql, FromSql, PartialEq)]
+    #[postgres(name = "foo")]
+    enum Foo {
+        Bar,
+        Baz,
+        #[postgres(other)]
+        Unknown,
+    }
+
+    let mut conn = Client::connect("user=postgres host=localhost port=5433", NoTls).unwrap();
+    conn.execute("CREATE TYPE pg_temp.foo AS ENUM ('Bar', 'Baz', 'Buz')", &[])
+        .unwrap();
+
+    test_type(
+        &mut conn,
+        "foo",
+        &[(Foo::Bar, "'Bar'"), (Foo::Baz, "'Baz'")],
+    );
+
+    let row = conn.query_one("SELECT 'Buz'::foo", &[]).unwrap();
+    assert_eq!(row.get::<_, Foo>(0), Foo::Unknown);
+
+    conn.execute("SELECT $1::foo", &[&Foo::Unknown])
+        .unwrap_err();
+}
+
+#[test]
+fn other_variant_missing_label() {
+    #[derive(Debug, ToSql, FromSql, PartialEq)]
+    #[postgres(name = "foo")]
+    enum Foo {
+        Bar,
+        Buz,
+        #[postgres(other)]
+        Unknown,
+    }
+
+    let mut conn = Client::connect("user=postgres host=localhost port=5433", NoTls).unwrap();
+    conn.execute("CREATE TYPE pg_temp.foo AS ENUM ('Bar', 'Baz')", &[])
+        .unwrap();
+
+    let err = conn.execute("SELECT $1::foo", &[&Foo::Bar]).unwrap_err();
+    assert!(err.source().unwrap().is::<WrongType>());
+}
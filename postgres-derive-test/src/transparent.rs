@@ -1,5 +1,6 @@
 use postgres::{Client, NoTls};
 use postgres_types::{FromSql, ToSql};
+use std::num::NonZeroI64;
 
 #[test]
 fn round_trip() {
@@ -16,3 +17,24 @@ fn round_trip() {
         UserId(123)
     );
 }
+
+#[test]
+fn non_zero() {
+    #[derive(FromSql, ToSql, Debug, PartialEq)]
+    #[postgres(transparent)]
+    struct UserId(NonZeroI64);
+
+    let mut conn = Client::connect("user=postgres host=localhost port=5433", NoTls).unwrap();
+    let id = UserId(NonZeroI64::new(123).unwrap());
+    assert_eq!(
+        conn.query_one("SELECT $1::bigint", &[&id])
+            .unwrap()
+            .get::<_, UserId>(0),
+        id
+    );
+    assert!(conn
+        .query_one("SELECT 0::bigint", &[])
+        .unwrap()
+        .try_get::<_, UserId>(0)
+        .is_err());
+}
@@ -33,7 +33,24 @@ pub fn domain_body(name: &str, field: &syn::Field) -> TokenStream {
 
 pub fn enum_body(name: &str, variants: &[Variant]) -> TokenStream {
     let num_variants = variants.len();
-    let variant_names = variants.iter().map(|v| &v.name);
+    let variant_names = variants.iter().filter(|v| !v.other).map(|v| &v.name);
+
+    // with a fallback variant, the server's enum may have labels which are unknown to the Rust enum
+    if variants.iter().any(|v| v.other) {
+        return quote! {
+            if type_.name() != #name {
+                return false;
+            }
+
+            match *type_.kind() {
+                ::postgres_types::Kind::Enum(ref variants) => {
+                    let names: &[&str] = &[#(#variant_names),*];
+                    names.iter().all(|name| variants.iter().any(|v| v == name))
+                }
+                _ => false,
+            }
+        };
+    }
 
     quote! {
         if type_.name() != #name {
@@ -93,3 +110,40 @@ pub fn composite_body(name: &str, trait_: &str, fields: &[Field]) -> TokenStream
         }
     }
 }
+
+// Like `composite_body`, but for `FromSql` implementations tolerating differences between the fields of the Rust and
+// Postgres types.
+pub fn evolving_composite_body(
+    name: &str,
+    fields: &[Field],
+    ignore_unknown_fields: bool,
+) -> TokenStream {
+    let field_names = fields.iter().map(|f| &f.name);
+    let field_types = fields.iter().map(|f| &f.type_);
+    let required_names = fields.iter().filter(|f| !f.default).map(|f| &f.name);
+
+    quote! {
+        if type_.name() != #name {
+            return false;
+        }
+
+        match *type_.kind() {
+            ::postgres_types::Kind::Composite(ref fields) => {
+                let known = fields.iter().all(|f| {
+                    match f.name() {
+                        #(
+                            #field_names => {
+                                <#field_types as ::postgres_types::FromSql>::accepts(f.type_())
+                            }
+                        )*
+                        _ => #ignore_unknown_fields,
+                    }
+                });
+                let required: &[&str] = &[#(#required_names),*];
+
+                known && required.iter().all(|name| fields.iter().any(|f| f.name() == *name))
+            }
+            _ => false,
+        }
+    }
+}
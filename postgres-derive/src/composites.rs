@@ -6,6 +6,7 @@ pub struct Field {
     pub name: String,
     pub ident: Ident,
     pub type_: Type,
+    pub default: bool,
 }
 
 impl Field {
@@ -23,6 +24,7 @@ impl Field {
             }),
             ident,
             type_: raw.ty.clone(),
+            default: overrides.default,
         })
     }
 }
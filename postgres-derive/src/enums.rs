@@ -5,6 +5,7 @@ use crate::overrides::Overrides;
 pub struct Variant {
     pub ident: Ident,
     pub name: String,
+    pub other: bool,
 }
 
 impl Variant {
@@ -23,6 +24,30 @@ impl Variant {
         Ok(Variant {
             ident: raw.ident.clone(),
             name: overrides.name.unwrap_or_else(|| raw.ident.to_string()),
+            other: overrides.other,
         })
     }
+
+    pub fn parse_all(data: &syn::DataEnum) -> Result<Vec<Variant>, Error> {
+        let variants = data
+            .variants
+            .iter()
+            .map(Variant::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if let Some(extra) = data
+            .variants
+            .iter()
+            .zip(&variants)
+            .filter(|(_, v)| v.other)
+            .nth(1)
+        {
+            return Err(Error::new_spanned(
+                extra.0,
+                "#[postgres(other)] may only be applied to one variant",
+            ));
+        }
+
+        Ok(variants)
+    }
 }
@@ -18,6 +18,13 @@ pub fn expand_derive_fromsql(input: DeriveInput) -> Result<TokenStream, Error> {
         ));
     }
 
+    if overrides.other {
+        return Err(Error::new_spanned(
+            &input,
+            "#[postgres(other)] may only be applied to enum variants",
+        ));
+    }
+
     let name = overrides.name.unwrap_or_else(|| input.ident.to_string());
 
     let (accepts_body, to_sql_body) = if overrides.transparent {
@@ -42,11 +49,7 @@ pub fn expand_derive_fromsql(input: DeriveInput) -> Result<TokenStream, Error> {
     } else {
         match input.data {
         Data::Enum(ref data) => {
-            let variants = data
-                .variants
-                .iter()
-                .map(Variant::parse)
-                .collect::<Result<Vec<_>, _>>()?;
+            let variants = Variant::parse_all(data)?;
             (
                 accepts::enum_body(&name, &variants),
                 enum_body(&input.ident, &variants),
@@ -71,9 +74,15 @@ pub fn expand_derive_fromsql(input: DeriveInput) -> Result<TokenStream, Error> {
                 .iter()
                 .map(Field::parse)
                 .collect::<Result<Vec<_>, _>>()?;
+            let ignore_unknown_fields = overrides.ignore_unknown_fields;
+            let accepts_body = if ignore_unknown_fields || fields.iter().any(|f| f.default) {
+                accepts::evolving_composite_body(&name, &fields, ignore_unknown_fields)
+            } else {
+                accepts::composite_body(&name, "FromSql", &fields)
+            };
             (
-                accepts::composite_body(&name, "FromSql", &fields),
-                composite_body(&input.ident, &fields),
+                accepts_body,
+                composite_body(&input.ident, &fields, ignore_unknown_fields),
             )
         }
         _ => {
@@ -113,19 +122,32 @@ fn transparent_body(ident: &Ident, field: &syn::Field) -> TokenStream {
 }
 
 fn enum_body(ident: &Ident, variants: &[Variant]) -> TokenStream {
-    let variant_names = variants.iter().map(|v| &v.name);
+    let known = variants.iter().filter(|v| !v.other);
+    let variant_names = known.clone().map(|v| &v.name);
     let idents = iter::repeat(ident);
-    let variant_idents = variants.iter().map(|v| &v.ident);
+    let variant_idents = known.map(|v| &v.ident);
+
+    let fallback = match variants.iter().find(|v| v.other) {
+        Some(other) => {
+            let other_ident = &other.ident;
+            quote! {
+                _ => std::result::Result::Ok(#ident::#other_ident),
+            }
+        }
+        None => quote! {
+            s => {
+                std::result::Result::Err(
+                    std::convert::Into::into(format!("invalid variant `{}`", s)))
+            }
+        },
+    };
 
     quote! {
         match std::str::from_utf8(buf)? {
             #(
                 #variant_names => std::result::Result::Ok(#idents::#variant_idents),
             )*
-            s => {
-                std::result::Result::Err(
-                    std::convert::Into::into(format!("invalid variant `{}`", s)))
-            }
+            #fallback
         }
     }
 }
@@ -151,13 +173,25 @@ fn domain_body(ident: &Ident, field: &syn::Field) -> TokenStream {
     }
 }
 
-fn composite_body(ident: &Ident, fields: &[Field]) -> TokenStream {
+fn composite_body(ident: &Ident, fields: &[Field], ignore_unknown_fields: bool) -> TokenStream {
     let temp_vars = &fields
         .iter()
         .map(|f| format_ident!("__{}", f.ident))
         .collect::<Vec<_>>();
     let field_names = &fields.iter().map(|f| &f.name).collect::<Vec<_>>();
     let field_idents = &fields.iter().map(|f| &f.ident).collect::<Vec<_>>();
+    let unwraps = fields.iter().map(|f| {
+        if f.default {
+            quote!(unwrap_or_default)
+        } else {
+            quote!(unwrap)
+        }
+    });
+    let unknown = if ignore_unknown_fields {
+        quote!(postgres_types::private::skip_value(&mut buf)?)
+    } else {
+        quote!(unreachable!())
+    };
 
     quote! {
         let fields = match *_type.kind() {
@@ -189,13 +223,13 @@ fn composite_body(ident: &Ident, fields: &[Field]) -> TokenStream {
                             postgres_types::private::read_value(field.type_(), &mut buf)?);
                     }
                 )*
-                _ => unreachable!(),
+                _ => #unknown,
             }
         }
 
         std::result::Result::Ok(#ident {
             #(
-                #field_idents: #temp_vars.unwrap(),
+                #field_idents: #temp_vars.#unwraps(),
             )*
         })
     }
@@ -3,6 +3,9 @@ use syn::{Attribute, Error, Lit, Meta, NestedMeta};
 pub struct Overrides {
     pub name: Option<String>,
     pub transparent: bool,
+    pub other: bool,
+    pub ignore_unknown_fields: bool,
+    pub default: bool,
 }
 
 impl Overrides {
@@ -10,6 +13,9 @@ impl Overrides {
         let mut overrides = Overrides {
             name: None,
             transparent: false,
+            other: false,
+            ignore_unknown_fields: false,
+            default: false,
         };
 
         for attr in attrs {
@@ -41,11 +47,17 @@ impl Overrides {
                         overrides.name = Some(value);
                     }
                     NestedMeta::Meta(Meta::Path(ref path)) => {
-                        if !path.is_ident("transparent") {
+                        if path.is_ident("transparent") {
+                            overrides.transparent = true;
+                        } else if path.is_ident("other") {
+                            overrides.other = true;
+                        } else if path.is_ident("ignore_unknown_fields") {
+                            overrides.ignore_unknown_fields = true;
+                        } else if path.is_ident("default") {
+                            overrides.default = true;
+                        } else {
                             return Err(Error::new_spanned(path, "unknown override"));
                         }
-
-                        overrides.transparent = true;
                     }
                     bad => return Err(Error::new_spanned(bad, "unknown attribute")),
                 }
@@ -18,6 +18,13 @@ pub fn expand_derive_tosql(input: DeriveInput) -> Result<TokenStream, Error> {
         ));
     }
 
+    if overrides.other {
+        return Err(Error::new_spanned(
+            &input,
+            "#[postgres(other)] may only be applied to enum variants",
+        ));
+    }
+
     let name = overrides.name.unwrap_or_else(|| input.ident.to_string());
 
     let (accepts_body, to_sql_body) = if overrides.transparent {
@@ -40,11 +47,7 @@ pub fn expand_derive_tosql(input: DeriveInput) -> Result<TokenStream, Error> {
     } else {
         match input.data {
             Data::Enum(ref data) => {
-                let variants = data
-                    .variants
-                    .iter()
-                    .map(Variant::parse)
-                    .collect::<Result<Vec<_>, _>>()?;
+                let variants = Variant::parse_all(data)?;
                 (
                     accepts::enum_body(&name, &variants),
                     enum_body(&input.ident, &variants),
@@ -114,7 +117,17 @@ fn transparent_body() -> TokenStream {
 fn enum_body(ident: &Ident, variants: &[Variant]) -> TokenStream {
     let idents = iter::repeat(ident);
     let variant_idents = variants.iter().map(|v| &v.ident);
-    let variant_names = variants.iter().map(|v| &v.name);
+    let variant_names = variants.iter().map(|v| {
+        if v.other {
+            quote! {
+                return std::result::Result::Err(
+                    std::convert::Into::into("the fallback variant of an enum cannot be serialized"))
+            }
+        } else {
+            let name = &v.name;
+            quote!(#name)
+        }
+    });
 
     quote! {
         let s = match *self {
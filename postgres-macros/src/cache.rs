@@ -0,0 +1,115 @@
+//! The offline cache of statement descriptions.
+//!
+//! Each description is stored in its own file in the `.postgres-queries` directory of the crate being compiled,
+//! named after a hash of the statement. The files are plain text: a `params` line listing parameter type OIDs, one
+//! `column` line per column with its type OID, nullability and name, and finally the statement itself.
+
+use crate::describe::{Column, Description};
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::PathBuf;
+
+const DIR: &str = ".postgres-queries";
+
+fn dir() -> Result<PathBuf, String> {
+    let manifest_dir = env::var_os("CARGO_MANIFEST_DIR")
+        .ok_or_else(|| "CARGO_MANIFEST_DIR is not set".to_string())?;
+    Ok(PathBuf::from(manifest_dir).join(DIR))
+}
+
+fn path(query: &str) -> Result<PathBuf, String> {
+    // FNV-1a, since the hash must be stable across compiler versions
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for byte in query.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    Ok(dir()?.join(format!("query-{:016x}.txt", hash)))
+}
+
+/// Stores a description in the cache, if the cache directory exists.
+pub fn write(query: &str, description: &Description) -> Result<(), String> {
+    if !dir()?.is_dir() {
+        return Ok(());
+    }
+
+    let mut contents = "params".to_string();
+    for param in &description.params {
+        let _ = write!(contents, " {}", param);
+    }
+    contents.push('\n');
+    for column in &description.columns {
+        let _ = writeln!(
+            contents,
+            "column {} {} {}",
+            column.type_oid, column.nullable as u8, column.name
+        );
+    }
+    contents.push_str("query\n");
+    contents.push_str(query);
+
+    let path = path(query)?;
+    if fs::read_to_string(&path).ok().as_deref() == Some(&*contents) {
+        return Ok(());
+    }
+    fs::write(&path, contents)
+        .map_err(|e| format!("error writing to query cache {}: {}", path.display(), e))
+}
+
+/// Loads a description from the cache.
+pub fn read(query: &str) -> Result<Description, String> {
+    let path = path(query)?;
+    let contents = fs::read_to_string(&path).map_err(|_| {
+        format!(
+            "the query is not in the `{}` offline cache: set DATABASE_URL to a database the query can be described \
+             against, and create the `{}` directory in the crate's root to cache its description for offline builds",
+            DIR, DIR
+        )
+    })?;
+    let invalid = || format!("invalid query cache file {}", path.display());
+
+    let mut description = Description {
+        params: vec![],
+        columns: vec![],
+    };
+    let mut lines = contents.splitn(2, "query\n");
+    let header = lines.next().ok_or_else(invalid)?;
+    if lines.next() != Some(query) {
+        return Err(format!(
+            "the query cache file {} is for a different query",
+            path.display()
+        ));
+    }
+
+    for line in header.lines() {
+        let mut parts = line.splitn(4, ' ');
+        match parts.next() {
+            Some("params") => {
+                for param in parts.flat_map(|p| p.split(' ')) {
+                    description
+                        .params
+                        .push(param.parse().map_err(|_| invalid())?);
+                }
+            }
+            Some("column") => {
+                let type_oid = parts.next().and_then(|p| p.parse().ok());
+                let nullable = parts.next().map(|p| p == "1");
+                let name = parts.next();
+                match (type_oid, nullable, name) {
+                    (Some(type_oid), Some(nullable), Some(name)) => {
+                        description.columns.push(Column {
+                            name: name.to_string(),
+                            type_oid,
+                            nullable,
+                        })
+                    }
+                    _ => return Err(invalid()),
+                }
+            }
+            _ => return Err(invalid()),
+        }
+    }
+
+    Ok(description)
+}
@@ -0,0 +1,153 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    Attribute, Data, DeriveInput, Error, Fields, GenericArgument, Lit, Meta, NestedMeta,
+    PathArguments, Type,
+};
+
+struct Overrides {
+    name: Option<String>,
+    default: bool,
+}
+
+impl Overrides {
+    fn extract(attrs: &[Attribute]) -> Result<Overrides, Error> {
+        let mut overrides = Overrides {
+            name: None,
+            default: false,
+        };
+
+        for attr in attrs {
+            let attr = attr.parse_meta()?;
+
+            if !attr.path().is_ident("row") {
+                continue;
+            }
+
+            let list = match attr {
+                Meta::List(ref list) => list,
+                bad => return Err(Error::new_spanned(bad, "expected a #[row(...)]")),
+            };
+
+            for item in &list.nested {
+                match item {
+                    NestedMeta::Meta(Meta::NameValue(meta)) => {
+                        if !meta.path.is_ident("name") {
+                            return Err(Error::new_spanned(&meta.path, "unknown override"));
+                        }
+
+                        let value = match &meta.lit {
+                            Lit::Str(s) => s.value(),
+                            bad => {
+                                return Err(Error::new_spanned(bad, "expected a string literal"))
+                            }
+                        };
+
+                        overrides.name = Some(value);
+                    }
+                    NestedMeta::Meta(Meta::Path(ref path)) => {
+                        if path.is_ident("default") {
+                            overrides.default = true;
+                        } else {
+                            return Err(Error::new_spanned(path, "unknown override"));
+                        }
+                    }
+                    bad => return Err(Error::new_spanned(bad, "unknown attribute")),
+                }
+            }
+        }
+
+        Ok(overrides)
+    }
+}
+
+pub fn expand_derive_from_row(input: DeriveInput) -> Result<TokenStream, Error> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    &input.ident,
+                    "#[derive(FromRow)] may only be applied to structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "#[derive(FromRow)] may only be applied to structs with named fields",
+            ))
+        }
+    };
+
+    let mut idents = vec![];
+    let mut values = vec![];
+    for field in fields {
+        let overrides = Overrides::extract(&field.attrs)?;
+        let ident = field.ident.as_ref().unwrap();
+        let name = overrides
+            .name
+            .unwrap_or_else(|| ident.to_string().trim_start_matches("r#").to_string());
+
+        // `Option<Option<T>>` fields distinguish a missing column (`None`) from a `NULL` value (`Some(None)`).
+        let value = if is_option_of_option(&field.ty) {
+            quote! {
+                match row.columns().iter().position(|c| c.name() == #name) {
+                    ::std::option::Option::Some(idx) => ::std::option::Option::Some(row.try_get(idx)?),
+                    ::std::option::Option::None => ::std::option::Option::None,
+                }
+            }
+        } else if overrides.default {
+            quote! {
+                match row.columns().iter().position(|c| c.name() == #name) {
+                    ::std::option::Option::Some(idx) => row.try_get(idx)?,
+                    ::std::option::Option::None => ::std::default::Default::default(),
+                }
+            }
+        } else {
+            quote!(row.try_get(#name)?)
+        };
+
+        idents.push(ident);
+        values.push(value);
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::tokio_postgres::FromRow for #ident #ty_generics #where_clause {
+            fn from_row(
+                row: &::tokio_postgres::Row,
+            ) -> ::std::result::Result<Self, ::tokio_postgres::Error> {
+                ::std::result::Result::Ok(#ident {
+                    #(#idents: #values,)*
+                })
+            }
+        }
+    })
+}
+
+fn is_option_of_option(ty: &Type) -> bool {
+    option_inner(ty).and_then(option_inner).is_some()
+}
+
+// Returns `T` if the type is spelled `Option<T>`.
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let path = match ty {
+        Type::Path(path) if path.qself.is_none() => &path.path,
+        _ => return None,
+    };
+    let segment = path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    let args = match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => args,
+        _ => return None,
+    };
+    match &args.args[0] {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    }
+}
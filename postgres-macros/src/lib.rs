@@ -0,0 +1,81 @@
+//! An internal crate for `tokio-postgres`.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use syn::parse_macro_input;
+
+mod cache;
+mod describe;
+mod from_row;
+mod query;
+mod types;
+
+/// Checks a statement against a database at compile time, producing a `TypedQuery` whose rows are decoded into a
+/// generated record type.
+///
+/// The first argument is the statement, which must be a string literal, and the remaining arguments are its
+/// parameters, which are borrowed by the query. The statement is described by the database named by the
+/// `DATABASE_URL` environment variable at compile time, which may be either a `postgresql://` URL or a `key=value`
+/// connection string. A compile error is reported if the database rejects the statement, or if the number of
+/// parameters provided does not match the number it expects. The types of parameters are checked when the query is
+/// executed.
+///
+/// The record has one public field per column, named after the column. Columns taken directly from a table with a
+/// `NOT NULL` constraint are decoded as their Rust type, and all others as an `Option` of it. This can be overridden by
+/// adding a `!` (not null) or `?` (nullable) suffix to the column's name with an alias, and the Rust type can be set
+/// with a `: Type` suffix, as in `SELECT count(*) AS "total!: i64"`. A type must be given for columns whose types
+/// have no default mapping, such as those only supported by optional features.
+///
+/// # Offline builds
+///
+/// If the crate being compiled has a `.postgres-queries` directory next to its `Cargo.toml`, the description of each
+/// statement is saved there when building with `DATABASE_URL` set. When it is not set, descriptions are loaded from
+/// that directory instead, so that the crate can be built without access to a database.
+///
+/// # Examples
+///
+/// ```ignore
+/// let user = tokio_postgres::query!("SELECT id, name FROM users WHERE id = $1", id)
+///     .fetch_one(&client)
+///     .await?;
+/// println!("{}: {:?}", user.id, user.name);
+/// ```
+#[proc_macro]
+pub fn query(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input);
+
+    query::expand_query(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// Derives `FromRow` for a struct with named fields, decoding each field from the column of the same name.
+///
+/// The column a field is decoded from can be changed with `#[row(name = "...")]`. It is an error for a column to be
+/// missing from the row, unless the field is marked `#[row(default)]`, in which case it is set to its `Default` value.
+/// Fields of type `Option<Option<T>>` distinguish the two cases on their own: they are `None` if the column is
+/// missing, and `Some(None)` if its value is `NULL`. This allows queries selecting a subset of columns to be decoded
+/// into a larger struct.
+///
+/// # Examples
+///
+/// ```ignore
+/// #[derive(FromRow)]
+/// struct User {
+///     id: i32,
+///     #[row(name = "display_name")]
+///     name: String,
+///     #[row(default)]
+///     tags: Vec<String>,
+///     email: Option<Option<String>>,
+/// }
+/// ```
+#[proc_macro_derive(FromRow, attributes(row))]
+pub fn derive_from_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input);
+
+    from_row::expand_derive_from_row(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
@@ -0,0 +1,168 @@
+use crate::describe::{self, Description};
+use crate::{cache, types};
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use std::env;
+use syn::parse::{Parse, ParseStream};
+use syn::{Error, Expr, Ident, LitStr, Token, Type};
+
+pub struct QueryInput {
+    query: LitStr,
+    params: Vec<Expr>,
+}
+
+impl Parse for QueryInput {
+    fn parse(input: ParseStream<'_>) -> syn::Result<QueryInput> {
+        let query = input.parse()?;
+        let mut params = vec![];
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            params.push(input.parse()?);
+        }
+
+        Ok(QueryInput { query, params })
+    }
+}
+
+struct Field {
+    ident: Ident,
+    ty: TokenStream,
+}
+
+pub fn expand_query(input: QueryInput) -> Result<TokenStream, Error> {
+    let query = input.query.value();
+    let span = input.query.span();
+
+    let description = load_description(&query).map_err(|e| Error::new(span, e))?;
+
+    if description.params.len() != input.params.len() {
+        return Err(Error::new(
+            span,
+            format!(
+                "expected {} parameters but {} were provided",
+                description.params.len(),
+                input.params.len()
+            ),
+        ));
+    }
+
+    let fields = description
+        .columns
+        .iter()
+        .map(|column| field(column, span))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let params = &input.params;
+    let params = quote! {
+        ::std::vec![
+            #(&(#params) as &(dyn ::tokio_postgres::types::ToSql + ::std::marker::Sync),)*
+        ]
+    };
+
+    if fields.is_empty() {
+        return Ok(quote! {
+            ::tokio_postgres::TypedQuery::<()>::new(#query, #params)
+        });
+    }
+
+    let idents = fields.iter().map(|f| &f.ident).collect::<Vec<_>>();
+    let tys = fields.iter().map(|f| &f.ty);
+    let indices = 0..fields.len();
+
+    Ok(quote! {
+        {
+            #[allow(non_snake_case)]
+            struct Record {
+                #(pub #idents: #tys,)*
+            }
+
+            impl ::tokio_postgres::FromRow for Record {
+                fn from_row(
+                    row: &::tokio_postgres::Row,
+                ) -> ::std::result::Result<Record, ::tokio_postgres::Error> {
+                    ::std::result::Result::Ok(Record {
+                        #(#idents: row.try_get(#indices)?,)*
+                    })
+                }
+            }
+
+            ::tokio_postgres::TypedQuery::<Record>::new(#query, #params)
+        }
+    })
+}
+
+// Describes the query using the database named by `DATABASE_URL` if it is set, and the offline cache otherwise.
+fn load_description(query: &str) -> Result<Description, String> {
+    match env::var("DATABASE_URL") {
+        Ok(url) => {
+            let description = describe::describe(&url, query)?;
+            cache::write(query, &description)?;
+            Ok(description)
+        }
+        Err(_) => cache::read(query),
+    }
+}
+
+// Column names may carry overrides: a trailing `!` or `?` forces the column to be treated as non-null or nullable,
+// and a `: Type` suffix sets the Rust type it is decoded as, as in `SELECT count(*) AS "total!: i64"`.
+fn field(column: &describe::Column, span: Span) -> Result<Field, Error> {
+    let (name, ty) = match column.name.find(':') {
+        Some(idx) => (
+            column.name[..idx].trim(),
+            Some(column.name[idx + 1..].trim()),
+        ),
+        None => (column.name.as_str(), None),
+    };
+    let (name, nullable) = if let Some(name) = name.strip_suffix('!') {
+        (name, false)
+    } else if let Some(name) = name.strip_suffix('?') {
+        (name, true)
+    } else {
+        (name, column.nullable)
+    };
+
+    let ident = syn::parse_str::<Ident>(name)
+        .or_else(|_| syn::parse_str::<Ident>(&format!("r#{}", name)))
+        .map_err(|_| {
+            Error::new(
+                span,
+                format!(
+                    "column `{}` is not a valid field name; give it an alias with `AS`",
+                    column.name
+                ),
+            )
+        })?;
+
+    let ty = match ty {
+        Some(ty) => {
+            let ty = syn::parse_str::<Type>(ty).map_err(|e| {
+                Error::new(
+                    span,
+                    format!("invalid type override for column `{}`: {}", name, e),
+                )
+            })?;
+            quote!(#ty)
+        }
+        None => types::rust_type(column.type_oid).ok_or_else(|| {
+            Error::new(
+                span,
+                format!(
+                    "column `{}` has a type (OID {}) with no default Rust type; \
+                     override it with an alias such as `AS \"{}: Type\"`",
+                    name, column.type_oid, name
+                ),
+            )
+        })?,
+    };
+
+    let ty = if nullable {
+        quote!(::std::option::Option<#ty>)
+    } else {
+        ty
+    };
+
+    Ok(Field { ident, ty })
+}
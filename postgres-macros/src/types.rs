@@ -0,0 +1,53 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// Returns the Rust type a column of the type with the specified OID is decoded as, if it has a default mapping.
+///
+/// Only the types supported by `postgres-types` without any features enabled are mapped, so that the generated code
+/// always compiles. Other types must be given explicitly with a column type override.
+pub fn rust_type(oid: u32) -> Option<TokenStream> {
+    let ty = match oid {
+        16 => quote!(bool),
+        17 => quote!(::std::vec::Vec<u8>),
+        18 => quote!(i8),
+        19 | 25 | 1042 | 1043 | 705 => quote!(::std::string::String),
+        20 => quote!(i64),
+        21 => quote!(i16),
+        23 => quote!(i32),
+        26 => quote!(u32),
+        700 => quote!(f32),
+        701 => quote!(f64),
+        869 => quote!(::std::net::IpAddr),
+        1184 | 1114 => quote!(::std::time::SystemTime),
+        3220 => quote!(::tokio_postgres::types::PgLsn),
+        _ => {
+            let element = array_element(oid)?;
+            let element = rust_type(element)?;
+            quote!(::std::vec::Vec<#element>)
+        }
+    };
+    Some(ty)
+}
+
+fn array_element(oid: u32) -> Option<u32> {
+    let element = match oid {
+        1000 => 16,
+        1001 => 17,
+        1002 => 18,
+        1003 => 19,
+        1005 => 21,
+        1007 => 23,
+        1009 => 25,
+        1014 => 1042,
+        1015 => 1043,
+        1016 => 20,
+        1021 => 700,
+        1022 => 701,
+        1028 => 26,
+        1041 => 869,
+        1115 => 1114,
+        1185 => 1184,
+        _ => return None,
+    };
+    Some(element)
+}
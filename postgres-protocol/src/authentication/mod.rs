@@ -19,6 +19,23 @@ pub fn md5_hash(username: &[u8], password: &[u8], salt: [u8; 4]) -> String {
     format!("md5{:x}", md5.finalize())
 }
 
+/// Hashes an MD5 password verifier in the same way as `md5_hash`.
+///
+/// The verifier is the `md5`-prefixed digest stored by the server, as produced by `password::md5`. A server can compare
+/// the result to the contents of the client's `PasswordMessage`. Returns `None` if the verifier is not of that form.
+#[inline]
+pub fn md5_hash_verifier(verifier: &str, salt: [u8; 4]) -> Option<String> {
+    let digest = verifier.strip_prefix("md5")?;
+    if digest.len() != 32 || !digest.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let mut md5 = Md5::new();
+    md5.update(digest.to_ascii_lowercase());
+    md5.update(salt);
+    Some(format!("md5{:x}", md5.finalize()))
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -34,4 +51,16 @@ mod test {
             "md562af4dd09bbb41884907a838a3233294"
         );
     }
+
+    #[test]
+    fn md5_verifier() {
+        let salt = [0x2a, 0x3d, 0x8f, 0xe0];
+        let verifier = crate::password::md5(b"password", "md5_user");
+
+        assert_eq!(
+            md5_hash_verifier(&verifier, salt).unwrap(),
+            "md562af4dd09bbb41884907a838a3233294"
+        );
+        assert_eq!(md5_hash_verifier("md5xyz", salt), None);
+    }
 }
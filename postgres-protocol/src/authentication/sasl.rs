@@ -4,7 +4,8 @@ use hmac::{Hmac, Mac};
 use rand::{self, Rng};
 use sha2::digest::FixedOutput;
 use sha2::{Digest, Sha256};
-use std::fmt::Write;
+use std::convert::TryFrom;
+use std::fmt::{self, Write};
 use std::io;
 use std::iter;
 use std::mem;
@@ -32,6 +33,27 @@ fn normalize(pass: &[u8]) -> Vec<u8> {
     }
 }
 
+fn nonce() -> String {
+    // rand 0.5's ThreadRng is cryptographically secure
+    let mut rng = rand::thread_rng();
+    (0..NONCE_LENGTH)
+        .map(|_| {
+            let mut v = rng.gen_range(0x21u8..0x7e);
+            if v == 0x2c {
+                v = 0x7e
+            }
+            v as char
+        })
+        .collect()
+}
+
+fn hmac(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut hmac =
+        Hmac::<Sha256>::new_from_slice(key).expect("HMAC is able to accept all key sizes");
+    hmac.update(message);
+    hmac.finalize().into_bytes().into()
+}
+
 pub(crate) fn hi(str: &[u8], salt: &[u8], i: u32) -> [u8; 32] {
     let mut hmac =
         Hmac::<Sha256>::new_from_slice(str).expect("HMAC is able to accept all key sizes");
@@ -132,19 +154,7 @@ pub struct ScramSha256 {
 impl ScramSha256 {
     /// Constructs a new instance which will use the provided password for authentication.
     pub fn new(password: &[u8], channel_binding: ChannelBinding) -> ScramSha256 {
-        // rand 0.5's ThreadRng is cryptographically secure
-        let mut rng = rand::thread_rng();
-        let nonce = (0..NONCE_LENGTH)
-            .map(|_| {
-                let mut v = rng.gen_range(0x21u8..0x7e);
-                if v == 0x2c {
-                    v = 0x7e
-                }
-                v as char
-            })
-            .collect::<String>();
-
-        ScramSha256::new_inner(password, channel_binding, nonce)
+        ScramSha256::new_inner(password, channel_binding, nonce())
     }
 
     fn new_inner(password: &[u8], channel_binding: ChannelBinding, nonce: String) -> ScramSha256 {
@@ -280,6 +290,286 @@ impl ScramSha256 {
     }
 }
 
+/// The secrets a server verifies SCRAM-SHA-256 authentication against.
+///
+/// These are derived from a password, and are what Postgres stores in `pg_authid.rolpassword` instead of it.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ScramSecret {
+    iterations: u32,
+    salt: Vec<u8>,
+    stored_key: [u8; 32],
+    server_key: [u8; 32],
+}
+
+impl ScramSecret {
+    /// Derives the secrets from a password.
+    pub fn new(password: &[u8], salt: &[u8], iterations: u32) -> ScramSecret {
+        let salted_password = hi(&normalize(password), salt, iterations);
+        let client_key = hmac(&salted_password, b"Client Key");
+
+        ScramSecret {
+            iterations,
+            salt: salt.to_vec(),
+            stored_key: Sha256::digest(client_key).into(),
+            server_key: hmac(&salted_password, b"Server Key"),
+        }
+    }
+
+    /// Parses the secrets from their textual representation, in the format stored by Postgres:
+    /// `SCRAM-SHA-256$<iterations>:<salt>$<stored key>:<server key>`.
+    pub fn parse(s: &str) -> io::Result<ScramSecret> {
+        fn key(s: &str) -> Option<[u8; 32]> {
+            let key = base64::decode(s).ok()?;
+            <[u8; 32]>::try_from(&*key).ok()
+        }
+
+        fn split(s: &str, delimiter: char) -> Option<(&str, &str)> {
+            let mut it = s.splitn(2, delimiter);
+            Some((it.next()?, it.next()?))
+        }
+
+        let invalid = || io::Error::new(io::ErrorKind::InvalidInput, "invalid SCRAM secret");
+
+        let s = s.strip_prefix("SCRAM-SHA-256$").ok_or_else(invalid)?;
+        let (parameters, keys) = split(s, '$').ok_or_else(invalid)?;
+        let (iterations, salt) = split(parameters, ':').ok_or_else(invalid)?;
+        let (stored_key, server_key) = split(keys, ':').ok_or_else(invalid)?;
+
+        Ok(ScramSecret {
+            iterations: iterations.parse().map_err(|_| invalid())?,
+            salt: base64::decode(salt).map_err(|_| invalid())?,
+            stored_key: key(stored_key).ok_or_else(invalid)?,
+            server_key: key(server_key).ok_or_else(invalid)?,
+        })
+    }
+}
+
+impl fmt::Display for ScramSecret {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            fmt,
+            "SCRAM-SHA-256${}:{}${}:{}",
+            self.iterations,
+            base64::encode(&self.salt),
+            base64::encode(self.stored_key),
+            base64::encode(self.server_key)
+        )
+    }
+}
+
+impl fmt::Debug for ScramSecret {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("ScramSecret")
+            .field("iterations", &self.iterations)
+            .finish()
+    }
+}
+
+enum ServerState {
+    ClientFirst {
+        nonce: String,
+    },
+    ClientFinal {
+        // the GS2 header and channel binding data the client must echo back
+        cbind_input: Vec<u8>,
+        nonce: String,
+        auth_message: String,
+    },
+    Done,
+}
+
+/// A type which handles the server side of the SCRAM-SHA-256/SCRAM-SHA-256-PLUS authentication
+/// process.
+///
+/// The server begins by sending an `AuthenticationSASL` message listing the supported mechanisms:
+/// `SCRAM-SHA-256`, preceded by `SCRAM-SHA-256-PLUS` if channel binding is supported.
+///
+/// The client will reply with a `SASLInitialResponse` message. Its mechanism and data should be
+/// passed to the `handle_client_first()` method, after which the buffer returned by the
+/// `message()` method should be sent to the client in an `AuthenticationSASLContinue` message.
+///
+/// The client will reply with a `SASLResponse` message. Its contents should be passed to the
+/// `handle_client_final()` method, which verifies the client's proof. Authentication has only
+/// succeeded if this method returns `Ok(())`, after which the buffer returned by the `message()`
+/// method should be sent to the client in an `AuthenticationSASLFinal` message.
+pub struct ScramSha256Server {
+    secret: ScramSecret,
+    tls_server_end_point: Option<Vec<u8>>,
+    message: String,
+    state: ServerState,
+}
+
+impl ScramSha256Server {
+    /// Constructs a new instance which will authenticate the client against the provided secrets.
+    ///
+    /// If the server supports channel binding, `tls_server_end_point` should contain the
+    /// `tls-server-end-point` channel binding data of the TLS session.
+    pub fn new(secret: ScramSecret, tls_server_end_point: Option<Vec<u8>>) -> ScramSha256Server {
+        ScramSha256Server::new_inner(secret, tls_server_end_point, nonce())
+    }
+
+    fn new_inner(
+        secret: ScramSecret,
+        tls_server_end_point: Option<Vec<u8>>,
+        nonce: String,
+    ) -> ScramSha256Server {
+        ScramSha256Server {
+            secret,
+            tls_server_end_point,
+            message: String::new(),
+            state: ServerState::ClientFirst { nonce },
+        }
+    }
+
+    /// Returns the message which should be sent to the client in an `AuthenticationSASLContinue`
+    /// or `AuthenticationSASLFinal` message.
+    pub fn message(&self) -> &[u8] {
+        if let ServerState::ClientFirst { .. } = self.state {
+            panic!("invalid SCRAM state");
+        }
+        self.message.as_bytes()
+    }
+
+    /// Updates the state machine with the client's first message.
+    ///
+    /// This should be called when a `SASLInitialResponse` message is received.
+    pub fn handle_client_first(&mut self, mechanism: &str, message: &[u8]) -> io::Result<()> {
+        let server_nonce = match mem::replace(&mut self.state, ServerState::Done) {
+            ServerState::ClientFirst { nonce } => nonce,
+            _ => return Err(io::Error::new(io::ErrorKind::Other, "invalid SCRAM state")),
+        };
+
+        let message =
+            str::from_utf8(message).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let parsed = Parser::new(message).client_first_message()?;
+
+        let cbind_data = match (mechanism, parsed.channel_binding) {
+            (SCRAM_SHA_256_PLUS, Some("tls-server-end-point")) => {
+                match &self.tls_server_end_point {
+                    Some(data) => &data[..],
+                    None => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            "channel binding is not supported",
+                        ))
+                    }
+                }
+            }
+            (SCRAM_SHA_256_PLUS, _) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "unsupported channel binding type",
+                ))
+            }
+            (SCRAM_SHA_256, None) => {
+                // a client which supports channel binding but thinks the server doesn't may have
+                // had the -PLUS mechanism stripped out of the server's message
+                if parsed.gs2_header.starts_with('y') && self.tls_server_end_point.is_some() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "client did not use channel binding despite server support",
+                    ));
+                }
+                &[][..]
+            }
+            (SCRAM_SHA_256, Some(_)) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "channel binding requested with a mechanism that does not support it",
+                ))
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "unsupported SASL mechanism",
+                ))
+            }
+        };
+
+        let mut cbind_input = parsed.gs2_header.as_bytes().to_vec();
+        cbind_input.extend(cbind_data);
+
+        let nonce = format!("{}{}", parsed.nonce, server_nonce);
+        self.message = format!(
+            "r={},s={},i={}",
+            nonce,
+            base64::encode(&self.secret.salt),
+            self.secret.iterations
+        );
+        let auth_message = format!("{},{}", parsed.client_first_bare, self.message);
+
+        self.state = ServerState::ClientFinal {
+            cbind_input,
+            nonce,
+            auth_message,
+        };
+        Ok(())
+    }
+
+    /// Verifies the client's final message.
+    ///
+    /// This should be called when a `SASLResponse` message is received. Authentication has only
+    /// succeeded if this method returns `Ok(())`.
+    pub fn handle_client_final(&mut self, message: &[u8]) -> io::Result<()> {
+        let (cbind_input, nonce, mut auth_message) =
+            match mem::replace(&mut self.state, ServerState::Done) {
+                ServerState::ClientFinal {
+                    cbind_input,
+                    nonce,
+                    auth_message,
+                } => (cbind_input, nonce, auth_message),
+                _ => return Err(io::Error::new(io::ErrorKind::Other, "invalid SCRAM state")),
+            };
+
+        let message =
+            str::from_utf8(message).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let parsed = Parser::new(message).client_final_message()?;
+
+        if base64::decode(parsed.channel_binding).ok().as_ref() != Some(&cbind_input) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid channel binding",
+            ));
+        }
+
+        if parsed.nonce != nonce {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid nonce"));
+        }
+
+        let proof = base64::decode(parsed.proof)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        if proof.len() != 32 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid proof"));
+        }
+
+        auth_message.push(',');
+        auth_message.push_str(parsed.without_proof);
+
+        let client_signature = hmac(&self.secret.stored_key, auth_message.as_bytes());
+        let mut client_key = client_signature;
+        for (key, proof) in client_key.iter_mut().zip(proof) {
+            *key ^= proof;
+        }
+
+        // compare in constant time to avoid leaking how much of the proof was correct
+        let stored_key: [u8; 32] = Sha256::digest(client_key).into();
+        let diff = stored_key
+            .iter()
+            .zip(&self.secret.stored_key)
+            .fold(0, |diff, (a, b)| diff | (a ^ b));
+        if diff != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "SCRAM verification error",
+            ));
+        }
+
+        let server_signature = hmac(&self.secret.server_key, auth_message.as_bytes());
+        self.message = format!("v={}", base64::encode(server_signature));
+        Ok(())
+    }
+}
+
 struct Parser<'a> {
     s: &'a str,
     it: iter::Peekable<str::CharIndices<'a>>,
@@ -416,6 +706,97 @@ impl<'a> Parser<'a> {
         self.eof()?;
         Ok(message)
     }
+
+    fn position(&mut self) -> usize {
+        match self.it.peek() {
+            Some(&(i, _)) => i,
+            None => self.s.len(),
+        }
+    }
+
+    fn gs2_header(&mut self) -> io::Result<Option<&'a str>> {
+        let channel_binding =
+            match self.it.next() {
+                Some((_, 'n')) | Some((_, 'y')) => None,
+                Some((_, 'p')) => {
+                    self.eat('=')?;
+                    Some(self.take_while(
+                        |c| matches!(c, 'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-'),
+                    )?)
+                }
+                Some((i, c)) => {
+                    let m = format!(
+                    "unexpected character at byte {}: expected channel binding flag but got `{}`",
+                    i, c
+                );
+                    return Err(io::Error::new(io::ErrorKind::InvalidInput, m));
+                }
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "unexpected EOF",
+                    ))
+                }
+            };
+        self.eat(',')?;
+
+        if let Some(&(_, 'a')) = self.it.peek() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "authorization identities are not supported",
+            ));
+        }
+        self.eat(',')?;
+
+        Ok(channel_binding)
+    }
+
+    fn client_first_message(&mut self) -> io::Result<ClientFirstMessage<'a>> {
+        let channel_binding = self.gs2_header()?;
+        let bare_start = self.position();
+
+        if let Some(&(_, 'm')) = self.it.peek() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "mandatory extensions are not supported",
+            ));
+        }
+        // Postgres ignores the username in favor of the one in the startup message
+        self.eat('n')?;
+        self.eat('=')?;
+        self.take_while(|c| c != ',')?;
+        self.eat(',')?;
+        let nonce = self.nonce()?;
+        self.eof()?;
+
+        Ok(ClientFirstMessage {
+            gs2_header: &self.s[..bare_start],
+            channel_binding,
+            client_first_bare: &self.s[bare_start..],
+            nonce,
+        })
+    }
+
+    fn client_final_message(&mut self) -> io::Result<ClientFinalMessage<'a>> {
+        self.eat('c')?;
+        self.eat('=')?;
+        let channel_binding = self.base64()?;
+        self.eat(',')?;
+        let nonce = self.nonce()?;
+        let without_proof = &self.s[..self.position()];
+        self.eat(',')?;
+        self.eat('p')?;
+        self.eat('=')?;
+        let proof = self.base64()?;
+        self.eof()?;
+
+        Ok(ClientFinalMessage {
+            channel_binding,
+            nonce,
+            without_proof,
+            proof,
+        })
+    }
 }
 
 struct ServerFirstMessage<'a> {
@@ -429,6 +810,20 @@ enum ServerFinalMessage<'a> {
     Verifier(&'a str),
 }
 
+struct ClientFirstMessage<'a> {
+    gs2_header: &'a str,
+    channel_binding: Option<&'a str>,
+    client_first_bare: &'a str,
+    nonce: &'a str,
+}
+
+struct ClientFinalMessage<'a> {
+    channel_binding: &'a str,
+    nonce: &'a str,
+    without_proof: &'a str,
+    proof: &'a str,
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -469,4 +864,103 @@ mod test {
 
         scram.finish(server_final.as_bytes()).unwrap();
     }
+
+    // the server side of the recorded exchange above
+    #[test]
+    fn server_exchange() {
+        let secret = ScramSecret::new(
+            b"foobar",
+            &base64::decode("fs3IXBy7U7+IvVjZ").unwrap(),
+            4096,
+        );
+
+        let client_first = "n,,n=,r=9IZ2O01zb9IgiIZ1WJ/zgpJB";
+        let server_first =
+            "r=9IZ2O01zb9IgiIZ1WJ/zgpJBjx/oIRLs02gGSHcw1KEty3eY,s=fs3IXBy7U7+IvVjZ,i=4096";
+        let client_final =
+            "c=biws,r=9IZ2O01zb9IgiIZ1WJ/zgpJBjx/oIRLs02gGSHcw1KEty3eY,p=AmNKosjJzS3\
+             1NTlQYNs5BTeQjdHdk7lOflDo5re2an8=";
+        let server_final = "v=U+ppxD5XUKtradnv8e2MkeupiA8FU87Sg8CXzXHDAzw=";
+
+        let mut server =
+            ScramSha256Server::new_inner(secret, None, "jx/oIRLs02gGSHcw1KEty3eY".to_string());
+        server
+            .handle_client_first(SCRAM_SHA_256, client_first.as_bytes())
+            .unwrap();
+        assert_eq!(str::from_utf8(server.message()).unwrap(), server_first);
+
+        server.handle_client_final(client_final.as_bytes()).unwrap();
+        assert_eq!(str::from_utf8(server.message()).unwrap(), server_final);
+    }
+
+    fn run_exchange(
+        password: &[u8],
+        mechanism: &str,
+        client_binding: ChannelBinding,
+        server_binding: Option<Vec<u8>>,
+    ) -> io::Result<()> {
+        let secret = ScramSecret::new(b"secret", b"salt", 4096);
+        let mut server = ScramSha256Server::new(secret, server_binding);
+        let mut client = ScramSha256::new(password, client_binding);
+
+        server.handle_client_first(mechanism, client.message())?;
+        client.update(server.message())?;
+        server.handle_client_final(client.message())?;
+        client.finish(server.message())
+    }
+
+    #[test]
+    fn server_round_trip() {
+        run_exchange(
+            b"secret",
+            SCRAM_SHA_256,
+            ChannelBinding::unsupported(),
+            None,
+        )
+        .unwrap();
+        assert!(
+            run_exchange(b"wrong", SCRAM_SHA_256, ChannelBinding::unsupported(), None).is_err()
+        );
+    }
+
+    #[test]
+    fn server_channel_binding() {
+        let end_point = b"certificate hash".to_vec();
+
+        run_exchange(
+            b"secret",
+            SCRAM_SHA_256_PLUS,
+            ChannelBinding::tls_server_end_point(end_point.clone()),
+            Some(end_point.clone()),
+        )
+        .unwrap();
+
+        // mismatched channel binding data, as seen through a man-in-the-middle
+        assert!(run_exchange(
+            b"secret",
+            SCRAM_SHA_256_PLUS,
+            ChannelBinding::tls_server_end_point(b"other hash".to_vec()),
+            Some(end_point.clone()),
+        )
+        .is_err());
+
+        // the client thinks the server doesn't support channel binding when it does
+        assert!(run_exchange(
+            b"secret",
+            SCRAM_SHA_256,
+            ChannelBinding::unrequested(),
+            Some(end_point),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn secret_round_trip() {
+        let secret = ScramSecret::new(b"secret", b"salt", 4096);
+        let parsed = ScramSecret::parse(&secret.to_string()).unwrap();
+        assert!(parsed == secret);
+
+        assert!(ScramSecret::parse("md5abc").is_err());
+        assert!(ScramSecret::parse("SCRAM-SHA-256$4096:c2FsdA==$YWJj:YWJj").is_err());
+    }
 }
@@ -22,6 +22,202 @@ pub fn escape_identifier(input: &str) -> String {
     escape_internal(input, true)
 }
 
+/// Escape an identifier only if necessary, like PostgreSQL's
+/// `quote_ident()` function.
+///
+/// Identifiers consisting only of lowercase ASCII letters, digits and
+/// underscores, not starting with a digit and not a keyword are
+/// returned unchanged. All others are escaped with
+/// `escape_identifier`.
+pub fn quote_ident(input: &str) -> String {
+    let mut chars = input.chars();
+    let safe = chars
+        .next()
+        .map_or(false, |c| c.is_ascii_lowercase() || c == '_')
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        && KEYWORDS.binary_search(&input).is_err();
+
+    if safe {
+        input.to_string()
+    } else {
+        escape_identifier(input)
+    }
+}
+
+/// Escape the contents of a string literal, without surrounding
+/// quotes, for inclusion in an existing `'...'` literal.
+///
+/// Backslashes are only escaped if `standard_conforming_strings` is
+/// off, so the result depends on the value of that setting on the
+/// server, which is reported as a parameter of the connection. Prefer
+/// `escape_literal`, which is safe regardless of the setting.
+pub fn escape_string(input: &str, standard_conforming_strings: bool) -> String {
+    let mut output = String::with_capacity(input.len());
+    for ch in input.chars() {
+        if ch == '\'' || (!standard_conforming_strings && ch == '\\') {
+            output.push(ch);
+        }
+        output.push(ch);
+    }
+    output
+}
+
+// The keywords which quote_ident() quotes: all except the unreserved
+// ones, sorted for binary search.
+const KEYWORDS: &[&str] = &[
+    "all",
+    "analyse",
+    "analyze",
+    "and",
+    "any",
+    "array",
+    "as",
+    "asc",
+    "asymmetric",
+    "authorization",
+    "between",
+    "bigint",
+    "binary",
+    "bit",
+    "boolean",
+    "both",
+    "case",
+    "cast",
+    "char",
+    "character",
+    "check",
+    "coalesce",
+    "collate",
+    "collation",
+    "column",
+    "concurrently",
+    "constraint",
+    "create",
+    "cross",
+    "current_catalog",
+    "current_date",
+    "current_role",
+    "current_schema",
+    "current_time",
+    "current_timestamp",
+    "current_user",
+    "dec",
+    "decimal",
+    "default",
+    "deferrable",
+    "desc",
+    "distinct",
+    "do",
+    "else",
+    "end",
+    "except",
+    "exists",
+    "extract",
+    "false",
+    "fetch",
+    "float",
+    "for",
+    "foreign",
+    "freeze",
+    "from",
+    "full",
+    "grant",
+    "greatest",
+    "group",
+    "grouping",
+    "having",
+    "ilike",
+    "in",
+    "initially",
+    "inner",
+    "inout",
+    "int",
+    "integer",
+    "intersect",
+    "interval",
+    "into",
+    "is",
+    "isnull",
+    "join",
+    "lateral",
+    "leading",
+    "least",
+    "left",
+    "like",
+    "limit",
+    "localtime",
+    "localtimestamp",
+    "national",
+    "natural",
+    "nchar",
+    "none",
+    "normalize",
+    "not",
+    "notnull",
+    "null",
+    "nullif",
+    "numeric",
+    "offset",
+    "on",
+    "only",
+    "or",
+    "order",
+    "out",
+    "outer",
+    "overlaps",
+    "overlay",
+    "placing",
+    "position",
+    "precision",
+    "primary",
+    "real",
+    "references",
+    "returning",
+    "right",
+    "row",
+    "select",
+    "session_user",
+    "setof",
+    "similar",
+    "smallint",
+    "some",
+    "substring",
+    "symmetric",
+    "table",
+    "tablesample",
+    "then",
+    "time",
+    "timestamp",
+    "to",
+    "trailing",
+    "treat",
+    "trim",
+    "true",
+    "union",
+    "unique",
+    "user",
+    "using",
+    "values",
+    "varchar",
+    "variadic",
+    "verbose",
+    "when",
+    "where",
+    "window",
+    "with",
+    "xmlattributes",
+    "xmlconcat",
+    "xmlelement",
+    "xmlexists",
+    "xmlforest",
+    "xmlnamespaces",
+    "xmlparse",
+    "xmlpi",
+    "xmlroot",
+    "xmlserialize",
+    "xmltable",
+];
+
 // Translation of PostgreSQL libpq's PQescapeInternal(). Does not
 // require a connection because input string is known to be valid
 // UTF-8.
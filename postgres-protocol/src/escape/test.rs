@@ -1,4 +1,4 @@
-use crate::escape::{escape_identifier, escape_literal};
+use crate::escape::{escape_identifier, escape_literal, escape_string, quote_ident};
 
 #[test]
 fn test_escape_idenifier() {
@@ -15,3 +15,23 @@ fn test_escape_literal() {
     assert_eq!(escape_literal("f'oo"), String::from("'f''oo'"));
     assert_eq!(escape_literal("f\"oo"), String::from("'f\"oo'"));
 }
+
+#[test]
+fn test_quote_ident() {
+    assert_eq!(quote_ident("foo_1"), String::from("foo_1"));
+    assert_eq!(quote_ident("_foo"), String::from("_foo"));
+    assert_eq!(quote_ident("abs"), String::from("abs"));
+    assert_eq!(quote_ident("Foo"), String::from("\"Foo\""));
+    assert_eq!(quote_ident("1foo"), String::from("\"1foo\""));
+    assert_eq!(quote_ident("foo$"), String::from("\"foo$\""));
+    assert_eq!(quote_ident("select"), String::from("\"select\""));
+    assert_eq!(quote_ident("f\"oo"), String::from("\"f\"\"oo\""));
+    assert_eq!(quote_ident(""), String::from("\"\""));
+}
+
+#[test]
+fn test_escape_string() {
+    assert_eq!(escape_string("f'oo", true), String::from("f''oo"));
+    assert_eq!(escape_string("f\\oo", true), String::from("f\\oo"));
+    assert_eq!(escape_string("f\\'oo", false), String::from("f\\\\''oo"));
+}
@@ -74,5 +74,5 @@ macro_rules! from_usize {
     };
 }
 
-from_usize!(i16);
+from_usize!(u16);
 from_usize!(i32);
@@ -1,7 +1,7 @@
 #![allow(missing_docs)]
 
 use byteorder::{BigEndian, ByteOrder, ReadBytesExt};
-use bytes::{Bytes, BytesMut};
+use bytes::{BufMut, Bytes, BytesMut};
 use fallible_iterator::FallibleIterator;
 use memchr::memchr;
 use std::cmp;
@@ -9,7 +9,8 @@ use std::io::{self, Read};
 use std::ops::Range;
 use std::str;
 
-use crate::Oid;
+use crate::message::frontend::{write_body, write_counted, write_cstr};
+use crate::{FromUsize, Lsn, Oid};
 
 pub const PARSE_COMPLETE_TAG: u8 = b'1';
 pub const BIND_COMPLETE_TAG: u8 = b'2';
@@ -22,9 +23,12 @@ pub const DATA_ROW_TAG: u8 = b'D';
 pub const ERROR_RESPONSE_TAG: u8 = b'E';
 pub const COPY_IN_RESPONSE_TAG: u8 = b'G';
 pub const COPY_OUT_RESPONSE_TAG: u8 = b'H';
+pub const COPY_BOTH_RESPONSE_TAG: u8 = b'W';
 pub const EMPTY_QUERY_RESPONSE_TAG: u8 = b'I';
+pub const FUNCTION_CALL_RESPONSE_TAG: u8 = b'V';
 pub const BACKEND_KEY_DATA_TAG: u8 = b'K';
 pub const NO_DATA_TAG: u8 = b'n';
+pub const NEGOTIATE_PROTOCOL_VERSION_TAG: u8 = b'v';
 pub const NOTICE_RESPONSE_TAG: u8 = b'N';
 pub const AUTHENTICATION_TAG: u8 = b'R';
 pub const PORTAL_SUSPENDED_TAG: u8 = b's';
@@ -33,6 +37,38 @@ pub const PARAMETER_DESCRIPTION_TAG: u8 = b't';
 pub const ROW_DESCRIPTION_TAG: u8 = b'T';
 pub const READY_FOR_QUERY_TAG: u8 = b'Z';
 
+// replication message tags
+pub const XLOG_DATA_TAG: u8 = b'w';
+pub const PRIMARY_KEEPALIVE_TAG: u8 = b'k';
+
+// logical replication message tags
+const BEGIN_TAG: u8 = b'B';
+const COMMIT_TAG: u8 = b'C';
+const ORIGIN_TAG: u8 = b'O';
+const RELATION_TAG: u8 = b'R';
+const TYPE_TAG: u8 = b'Y';
+const INSERT_TAG: u8 = b'I';
+const UPDATE_TAG: u8 = b'U';
+const DELETE_TAG: u8 = b'D';
+const TRUNCATE_TAG: u8 = b'T';
+const STREAM_START_TAG: u8 = b'S';
+const STREAM_STOP_TAG: u8 = b'E';
+const STREAM_COMMIT_TAG: u8 = b'c';
+const STREAM_ABORT_TAG: u8 = b'A';
+const TUPLE_NEW_TAG: u8 = b'N';
+const TUPLE_KEY_TAG: u8 = b'K';
+const TUPLE_OLD_TAG: u8 = b'O';
+const TUPLE_DATA_NULL_TAG: u8 = b'n';
+const TUPLE_DATA_TOAST_TAG: u8 = b'u';
+const TUPLE_DATA_TEXT_TAG: u8 = b't';
+const TUPLE_DATA_BINARY_TAG: u8 = b'b';
+
+// replica identity tags
+const REPLICA_IDENTITY_DEFAULT_TAG: u8 = b'd';
+const REPLICA_IDENTITY_NOTHING_TAG: u8 = b'n';
+const REPLICA_IDENTITY_FULL_TAG: u8 = b'f';
+const REPLICA_IDENTITY_INDEX_TAG: u8 = b'i';
+
 #[derive(Debug, Copy, Clone)]
 pub struct Header {
     tag: u8,
@@ -93,9 +129,12 @@ pub enum Message {
     CopyDone,
     CopyInResponse(CopyInResponseBody),
     CopyOutResponse(CopyOutResponseBody),
+    CopyBothResponse(CopyBothResponseBody),
     DataRow(DataRowBody),
     EmptyQueryResponse,
     ErrorResponse(ErrorResponseBody),
+    FunctionCallResponse(FunctionCallResponseBody),
+    NegotiateProtocolVersion(NegotiateProtocolVersionBody),
     NoData,
     NoticeResponse(NoticeResponseBody),
     NotificationResponse(NotificationResponseBody),
@@ -170,6 +209,11 @@ impl Message {
                 let storage = buf.read_all();
                 Message::ErrorResponse(ErrorResponseBody { storage })
             }
+            FUNCTION_CALL_RESPONSE_TAG => {
+                let len = buf.read_i32::<BigEndian>()?;
+                let storage = buf.read_all();
+                Message::FunctionCallResponse(FunctionCallResponseBody { storage, len })
+            }
             COPY_IN_RESPONSE_TAG => {
                 let format = buf.read_u8()?;
                 let len = buf.read_u16::<BigEndian>()?;
@@ -190,15 +234,42 @@ impl Message {
                     storage,
                 })
             }
+            COPY_BOTH_RESPONSE_TAG => {
+                let format = buf.read_u8()?;
+                let len = buf.read_u16::<BigEndian>()?;
+                let storage = buf.read_all();
+                Message::CopyBothResponse(CopyBothResponseBody {
+                    format,
+                    len,
+                    storage,
+                })
+            }
             EMPTY_QUERY_RESPONSE_TAG => Message::EmptyQueryResponse,
             BACKEND_KEY_DATA_TAG => {
                 let process_id = buf.read_i32::<BigEndian>()?;
-                let secret_key = buf.read_i32::<BigEndian>()?;
+                // protocol 3.2 allows keys of up to 256 bytes, while earlier versions always use 4
+                let secret_key = buf.read_all();
+                if secret_key.len() < 4 || secret_key.len() > 256 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "invalid secret key length",
+                    ));
+                }
                 Message::BackendKeyData(BackendKeyDataBody {
                     process_id,
                     secret_key,
                 })
             }
+            NEGOTIATE_PROTOCOL_VERSION_TAG => {
+                let newest_version = buf.read_i32::<BigEndian>()?;
+                let len = buf.read_i32::<BigEndian>()?;
+                let storage = buf.read_all();
+                Message::NegotiateProtocolVersion(NegotiateProtocolVersionBody {
+                    newest_version,
+                    len,
+                    storage,
+                })
+            }
             NO_DATA_TAG => Message::NoData,
             NOTICE_RESPONSE_TAG => {
                 let storage = buf.read_all();
@@ -278,24 +349,800 @@ impl Message {
     }
 }
 
-struct Buffer {
-    bytes: Bytes,
-    idx: usize,
+/// A message sent by the server inside of the `CopyData` messages of a streaming replication connection.
+#[non_exhaustive]
+pub enum ReplicationMessage<D> {
+    XLogData(XLogDataBody<D>),
+    PrimaryKeepAlive(PrimaryKeepAliveBody),
+}
+
+impl ReplicationMessage<Bytes> {
+    #[inline]
+    pub fn parse(bytes: &Bytes) -> io::Result<ReplicationMessage<Bytes>> {
+        let mut buf = Buffer {
+            bytes: bytes.clone(),
+            idx: 0,
+        };
+
+        let message = match buf.read_u8()? {
+            XLOG_DATA_TAG => {
+                let wal_start = buf.read_u64::<BigEndian>()?;
+                let wal_end = buf.read_u64::<BigEndian>()?;
+                let timestamp = buf.read_i64::<BigEndian>()?;
+                let data = buf.read_all();
+                ReplicationMessage::XLogData(XLogDataBody {
+                    wal_start,
+                    wal_end,
+                    timestamp,
+                    data,
+                })
+            }
+            PRIMARY_KEEPALIVE_TAG => {
+                let wal_end = buf.read_u64::<BigEndian>()?;
+                let timestamp = buf.read_i64::<BigEndian>()?;
+                let reply = buf.read_u8()?;
+                ReplicationMessage::PrimaryKeepAlive(PrimaryKeepAliveBody {
+                    wal_end,
+                    timestamp,
+                    reply,
+                })
+            }
+            tag => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown replication message tag `{}`", tag),
+                ));
+            }
+        };
+
+        if !buf.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid message length: expected buffer to be empty",
+            ));
+        }
+
+        Ok(message)
+    }
+}
+
+pub struct XLogDataBody<D> {
+    wal_start: Lsn,
+    wal_end: Lsn,
+    timestamp: i64,
+    data: D,
+}
+
+impl<D> XLogDataBody<D> {
+    /// The starting point of the WAL data in this message.
+    #[inline]
+    pub fn wal_start(&self) -> Lsn {
+        self.wal_start
+    }
+
+    /// The current end of WAL on the server.
+    #[inline]
+    pub fn wal_end(&self) -> Lsn {
+        self.wal_end
+    }
+
+    /// The server's system clock at the time of transmission, as microseconds since midnight on 2000-01-01.
+    #[inline]
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    #[inline]
+    pub fn data(&self) -> &D {
+        &self.data
+    }
+
+    #[inline]
+    pub fn into_data(self) -> D {
+        self.data
+    }
+
+    /// Converts the data of the message, preserving its WAL positions and timestamp.
+    #[inline]
+    pub fn map_data<F, D2, E>(self, f: F) -> Result<XLogDataBody<D2>, E>
+    where
+        F: FnOnce(D) -> Result<D2, E>,
+    {
+        let data = f(self.data)?;
+        Ok(XLogDataBody {
+            wal_start: self.wal_start,
+            wal_end: self.wal_end,
+            timestamp: self.timestamp,
+            data,
+        })
+    }
+}
+
+pub struct PrimaryKeepAliveBody {
+    wal_end: Lsn,
+    timestamp: i64,
+    reply: u8,
+}
+
+impl PrimaryKeepAliveBody {
+    /// The current end of WAL on the server.
+    #[inline]
+    pub fn wal_end(&self) -> Lsn {
+        self.wal_end
+    }
+
+    /// The server's system clock at the time of transmission, as microseconds since midnight on 2000-01-01.
+    #[inline]
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    /// Returns 1 if the server requests an immediate standby status update, and 0 otherwise.
+    #[inline]
+    pub fn reply(&self) -> u8 {
+        self.reply
+    }
+}
+
+/// A message of the `pgoutput` logical decoding output plugin.
+#[non_exhaustive]
+pub enum LogicalReplicationMessage {
+    Begin(BeginBody),
+    Commit(CommitBody),
+    Origin(OriginBody),
+    Relation(RelationBody),
+    Type(TypeBody),
+    Insert(InsertBody),
+    Update(UpdateBody),
+    Delete(DeleteBody),
+    Truncate(TruncateBody),
+    StreamStart(StreamStartBody),
+    StreamStop,
+    StreamCommit(StreamCommitBody),
+    StreamAbort(StreamAbortBody),
+}
+
+impl LogicalReplicationMessage {
+    /// Parses a `pgoutput` message.
+    ///
+    /// Messages sent between a `StreamStart` and a `StreamStop` message are prefixed with the ID of the streamed
+    /// transaction, so the caller must keep track of whether `buf` was received inside of such a block.
+    #[inline]
+    pub fn parse(
+        buf: &Bytes,
+        in_streamed_transaction: bool,
+    ) -> io::Result<LogicalReplicationMessage> {
+        let mut buf = Buffer {
+            bytes: buf.clone(),
+            idx: 0,
+        };
+
+        let tag = buf.read_u8()?;
+        let xid = match tag {
+            RELATION_TAG | TYPE_TAG | INSERT_TAG | UPDATE_TAG | DELETE_TAG | TRUNCATE_TAG
+                if in_streamed_transaction =>
+            {
+                Some(buf.read_u32::<BigEndian>()?)
+            }
+            _ => None,
+        };
+
+        let message = match tag {
+            BEGIN_TAG => LogicalReplicationMessage::Begin(BeginBody {
+                final_lsn: buf.read_u64::<BigEndian>()?,
+                timestamp: buf.read_i64::<BigEndian>()?,
+                xid: buf.read_u32::<BigEndian>()?,
+            }),
+            COMMIT_TAG => LogicalReplicationMessage::Commit(CommitBody {
+                flags: buf.read_i8()?,
+                commit_lsn: buf.read_u64::<BigEndian>()?,
+                end_lsn: buf.read_u64::<BigEndian>()?,
+                timestamp: buf.read_i64::<BigEndian>()?,
+            }),
+            ORIGIN_TAG => LogicalReplicationMessage::Origin(OriginBody {
+                commit_lsn: buf.read_u64::<BigEndian>()?,
+                name: buf.read_cstr()?,
+            }),
+            RELATION_TAG => {
+                let rel_id = buf.read_u32::<BigEndian>()?;
+                let namespace = buf.read_cstr()?;
+                let name = buf.read_cstr()?;
+                let replica_identity = match buf.read_u8()? {
+                    REPLICA_IDENTITY_DEFAULT_TAG => ReplicaIdentity::Default,
+                    REPLICA_IDENTITY_NOTHING_TAG => ReplicaIdentity::Nothing,
+                    REPLICA_IDENTITY_FULL_TAG => ReplicaIdentity::Full,
+                    REPLICA_IDENTITY_INDEX_TAG => ReplicaIdentity::Index,
+                    tag => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            format!("unknown replica identity tag `{}`", tag),
+                        ));
+                    }
+                };
+                let len = buf.read_i16::<BigEndian>()?;
+                // each column is at least 10 bytes, so don't preallocate more than the buffer can hold
+                let capacity = cmp::min(cmp::max(len, 0) as usize, buf.slice().len() / 10);
+                let mut columns = Vec::with_capacity(capacity);
+                for _ in 0..len {
+                    columns.push(Column {
+                        flags: buf.read_i8()?,
+                        name: buf.read_cstr()?,
+                        type_id: buf.read_u32::<BigEndian>()?,
+                        type_modifier: buf.read_i32::<BigEndian>()?,
+                    });
+                }
+                LogicalReplicationMessage::Relation(RelationBody {
+                    xid,
+                    rel_id,
+                    namespace,
+                    name,
+                    replica_identity,
+                    columns,
+                })
+            }
+            TYPE_TAG => LogicalReplicationMessage::Type(TypeBody {
+                xid,
+                id: buf.read_u32::<BigEndian>()?,
+                namespace: buf.read_cstr()?,
+                name: buf.read_cstr()?,
+            }),
+            INSERT_TAG => {
+                let rel_id = buf.read_u32::<BigEndian>()?;
+                let tuple = match buf.read_u8()? {
+                    TUPLE_NEW_TAG => Tuple::parse(&mut buf)?,
+                    tag => return Err(unexpected_tuple_tag(tag)),
+                };
+                LogicalReplicationMessage::Insert(InsertBody { xid, rel_id, tuple })
+            }
+            UPDATE_TAG => {
+                let rel_id = buf.read_u32::<BigEndian>()?;
+                let mut key_tuple = None;
+                let mut old_tuple = None;
+                let new_tuple = loop {
+                    match buf.read_u8()? {
+                        TUPLE_KEY_TAG if key_tuple.is_none() && old_tuple.is_none() => {
+                            key_tuple = Some(Tuple::parse(&mut buf)?)
+                        }
+                        TUPLE_OLD_TAG if key_tuple.is_none() && old_tuple.is_none() => {
+                            old_tuple = Some(Tuple::parse(&mut buf)?)
+                        }
+                        TUPLE_NEW_TAG => break Tuple::parse(&mut buf)?,
+                        tag => return Err(unexpected_tuple_tag(tag)),
+                    }
+                };
+                LogicalReplicationMessage::Update(UpdateBody {
+                    xid,
+                    rel_id,
+                    key_tuple,
+                    old_tuple,
+                    new_tuple,
+                })
+            }
+            DELETE_TAG => {
+                let rel_id = buf.read_u32::<BigEndian>()?;
+                let mut key_tuple = None;
+                let mut old_tuple = None;
+                match buf.read_u8()? {
+                    TUPLE_KEY_TAG => key_tuple = Some(Tuple::parse(&mut buf)?),
+                    TUPLE_OLD_TAG => old_tuple = Some(Tuple::parse(&mut buf)?),
+                    tag => return Err(unexpected_tuple_tag(tag)),
+                }
+                LogicalReplicationMessage::Delete(DeleteBody {
+                    xid,
+                    rel_id,
+                    key_tuple,
+                    old_tuple,
+                })
+            }
+            TRUNCATE_TAG => {
+                let len = buf.read_i32::<BigEndian>()?;
+                let options = buf.read_i8()?;
+                let capacity = cmp::min(cmp::max(len, 0) as usize, buf.slice().len() / 4);
+                let mut rel_ids = Vec::with_capacity(capacity);
+                for _ in 0..len {
+                    rel_ids.push(buf.read_u32::<BigEndian>()?);
+                }
+                LogicalReplicationMessage::Truncate(TruncateBody {
+                    xid,
+                    options,
+                    rel_ids,
+                })
+            }
+            STREAM_START_TAG => LogicalReplicationMessage::StreamStart(StreamStartBody {
+                xid: buf.read_u32::<BigEndian>()?,
+                first_segment: buf.read_u8()?,
+            }),
+            STREAM_STOP_TAG => LogicalReplicationMessage::StreamStop,
+            STREAM_COMMIT_TAG => LogicalReplicationMessage::StreamCommit(StreamCommitBody {
+                xid: buf.read_u32::<BigEndian>()?,
+                flags: buf.read_i8()?,
+                commit_lsn: buf.read_u64::<BigEndian>()?,
+                end_lsn: buf.read_u64::<BigEndian>()?,
+                timestamp: buf.read_i64::<BigEndian>()?,
+            }),
+            STREAM_ABORT_TAG => LogicalReplicationMessage::StreamAbort(StreamAbortBody {
+                xid: buf.read_u32::<BigEndian>()?,
+                subxid: buf.read_u32::<BigEndian>()?,
+            }),
+            tag => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown logical replication message tag `{}`", tag),
+                ));
+            }
+        };
+
+        if !buf.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid message length: expected buffer to be empty",
+            ));
+        }
+
+        Ok(message)
+    }
+}
+
+fn unexpected_tuple_tag(tag: u8) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("unexpected tuple tag `{}`", tag),
+    )
+}
+
+/// The values of a row in a logical replication message.
+pub struct Tuple(Vec<TupleData>);
+
+impl Tuple {
+    fn parse(buf: &mut Buffer) -> io::Result<Tuple> {
+        let len = buf.read_i16::<BigEndian>()?;
+        let capacity = cmp::min(cmp::max(len, 0) as usize, buf.slice().len());
+        let mut data = Vec::with_capacity(capacity);
+        for _ in 0..len {
+            let value = match buf.read_u8()? {
+                TUPLE_DATA_NULL_TAG => TupleData::Null,
+                TUPLE_DATA_TOAST_TAG => TupleData::UnchangedToast,
+                TUPLE_DATA_TEXT_TAG => {
+                    let len = buf.read_i32::<BigEndian>()?;
+                    TupleData::Text(buf.read_bytes(cmp::max(len, 0) as usize)?)
+                }
+                TUPLE_DATA_BINARY_TAG => {
+                    let len = buf.read_i32::<BigEndian>()?;
+                    TupleData::Binary(buf.read_bytes(cmp::max(len, 0) as usize)?)
+                }
+                tag => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("unknown tuple data tag `{}`", tag),
+                    ));
+                }
+            };
+            data.push(value);
+        }
+        Ok(Tuple(data))
+    }
+
+    #[inline]
+    pub fn tuple_data(&self) -> &[TupleData] {
+        &self.0
+    }
+}
+
+/// The value of a column in a logical replication message.
+pub enum TupleData {
+    Null,
+    /// A TOASTed value which was not changed, and so was not sent.
+    UnchangedToast,
+    /// A value in the text format.
+    Text(Bytes),
+    /// A value in the binary format.
+    Binary(Bytes),
+}
+
+pub struct BeginBody {
+    final_lsn: Lsn,
+    timestamp: i64,
+    xid: u32,
+}
+
+impl BeginBody {
+    /// The final LSN of the transaction.
+    #[inline]
+    pub fn final_lsn(&self) -> Lsn {
+        self.final_lsn
+    }
+
+    /// The commit timestamp of the transaction, as microseconds since midnight on 2000-01-01.
+    #[inline]
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    #[inline]
+    pub fn xid(&self) -> u32 {
+        self.xid
+    }
+}
+
+pub struct CommitBody {
+    flags: i8,
+    commit_lsn: Lsn,
+    end_lsn: Lsn,
+    timestamp: i64,
+}
+
+impl CommitBody {
+    #[inline]
+    pub fn flags(&self) -> i8 {
+        self.flags
+    }
+
+    /// The LSN of the commit.
+    #[inline]
+    pub fn commit_lsn(&self) -> Lsn {
+        self.commit_lsn
+    }
+
+    /// The end LSN of the transaction.
+    #[inline]
+    pub fn end_lsn(&self) -> Lsn {
+        self.end_lsn
+    }
+
+    /// The commit timestamp of the transaction, as microseconds since midnight on 2000-01-01.
+    #[inline]
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+}
+
+pub struct OriginBody {
+    commit_lsn: Lsn,
+    name: Bytes,
+}
+
+impl OriginBody {
+    /// The LSN of the commit on the origin server.
+    #[inline]
+    pub fn commit_lsn(&self) -> Lsn {
+        self.commit_lsn
+    }
+
+    #[inline]
+    pub fn name(&self) -> io::Result<&str> {
+        get_str(&self.name)
+    }
+}
+
+/// The replica identity setting of a relation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ReplicaIdentity {
+    /// The primary key columns, if any.
+    Default,
+    /// No columns.
+    Nothing,
+    /// All columns.
+    Full,
+    /// The columns of a specific index.
+    Index,
+}
+
+pub struct RelationBody {
+    xid: Option<u32>,
+    rel_id: Oid,
+    namespace: Bytes,
+    name: Bytes,
+    replica_identity: ReplicaIdentity,
+    columns: Vec<Column>,
+}
+
+impl RelationBody {
+    /// The ID of the streamed transaction, if the message was sent as part of one.
+    #[inline]
+    pub fn xid(&self) -> Option<u32> {
+        self.xid
+    }
+
+    #[inline]
+    pub fn rel_id(&self) -> Oid {
+        self.rel_id
+    }
+
+    #[inline]
+    pub fn namespace(&self) -> io::Result<&str> {
+        get_str(&self.namespace)
+    }
+
+    #[inline]
+    pub fn name(&self) -> io::Result<&str> {
+        get_str(&self.name)
+    }
+
+    #[inline]
+    pub fn replica_identity(&self) -> ReplicaIdentity {
+        self.replica_identity
+    }
+
+    #[inline]
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+}
+
+pub struct Column {
+    flags: i8,
+    name: Bytes,
+    type_id: Oid,
+    type_modifier: i32,
+}
+
+impl Column {
+    /// Returns 1 if the column is part of the relation's replica identity, and 0 otherwise.
+    #[inline]
+    pub fn flags(&self) -> i8 {
+        self.flags
+    }
+
+    #[inline]
+    pub fn name(&self) -> io::Result<&str> {
+        get_str(&self.name)
+    }
+
+    #[inline]
+    pub fn type_id(&self) -> Oid {
+        self.type_id
+    }
+
+    #[inline]
+    pub fn type_modifier(&self) -> i32 {
+        self.type_modifier
+    }
+}
+
+pub struct TypeBody {
+    xid: Option<u32>,
+    id: Oid,
+    namespace: Bytes,
+    name: Bytes,
+}
+
+impl TypeBody {
+    /// The ID of the streamed transaction, if the message was sent as part of one.
+    #[inline]
+    pub fn xid(&self) -> Option<u32> {
+        self.xid
+    }
+
+    #[inline]
+    pub fn id(&self) -> Oid {
+        self.id
+    }
+
+    #[inline]
+    pub fn namespace(&self) -> io::Result<&str> {
+        get_str(&self.namespace)
+    }
+
+    #[inline]
+    pub fn name(&self) -> io::Result<&str> {
+        get_str(&self.name)
+    }
+}
+
+pub struct InsertBody {
+    xid: Option<u32>,
+    rel_id: Oid,
+    tuple: Tuple,
+}
+
+impl InsertBody {
+    /// The ID of the streamed transaction, if the message was sent as part of one.
+    #[inline]
+    pub fn xid(&self) -> Option<u32> {
+        self.xid
+    }
+
+    #[inline]
+    pub fn rel_id(&self) -> Oid {
+        self.rel_id
+    }
+
+    /// The inserted row.
+    #[inline]
+    pub fn tuple(&self) -> &Tuple {
+        &self.tuple
+    }
+}
+
+pub struct UpdateBody {
+    xid: Option<u32>,
+    rel_id: Oid,
+    key_tuple: Option<Tuple>,
+    old_tuple: Option<Tuple>,
+    new_tuple: Tuple,
+}
+
+impl UpdateBody {
+    /// The ID of the streamed transaction, if the message was sent as part of one.
+    #[inline]
+    pub fn xid(&self) -> Option<u32> {
+        self.xid
+    }
+
+    #[inline]
+    pub fn rel_id(&self) -> Oid {
+        self.rel_id
+    }
+
+    /// The replica identity columns of the old row, if the update changed them and the relation's replica identity
+    /// is an index.
+    #[inline]
+    pub fn key_tuple(&self) -> Option<&Tuple> {
+        self.key_tuple.as_ref()
+    }
+
+    /// The old row, if the relation's replica identity is `FULL`.
+    #[inline]
+    pub fn old_tuple(&self) -> Option<&Tuple> {
+        self.old_tuple.as_ref()
+    }
+
+    /// The new row.
+    #[inline]
+    pub fn new_tuple(&self) -> &Tuple {
+        &self.new_tuple
+    }
+}
+
+pub struct DeleteBody {
+    xid: Option<u32>,
+    rel_id: Oid,
+    key_tuple: Option<Tuple>,
+    old_tuple: Option<Tuple>,
+}
+
+impl DeleteBody {
+    /// The ID of the streamed transaction, if the message was sent as part of one.
+    #[inline]
+    pub fn xid(&self) -> Option<u32> {
+        self.xid
+    }
+
+    #[inline]
+    pub fn rel_id(&self) -> Oid {
+        self.rel_id
+    }
+
+    /// The replica identity columns of the deleted row, if the relation's replica identity is an index.
+    #[inline]
+    pub fn key_tuple(&self) -> Option<&Tuple> {
+        self.key_tuple.as_ref()
+    }
+
+    /// The deleted row, if the relation's replica identity is `FULL`.
+    #[inline]
+    pub fn old_tuple(&self) -> Option<&Tuple> {
+        self.old_tuple.as_ref()
+    }
+}
+
+pub struct TruncateBody {
+    xid: Option<u32>,
+    options: i8,
+    rel_ids: Vec<Oid>,
+}
+
+impl TruncateBody {
+    /// The ID of the streamed transaction, if the message was sent as part of one.
+    #[inline]
+    pub fn xid(&self) -> Option<u32> {
+        self.xid
+    }
+
+    /// A bitmask of the truncate options: 1 for `CASCADE` and 2 for `RESTART IDENTITY`.
+    #[inline]
+    pub fn options(&self) -> i8 {
+        self.options
+    }
+
+    #[inline]
+    pub fn rel_ids(&self) -> &[Oid] {
+        &self.rel_ids
+    }
+}
+
+pub struct StreamStartBody {
+    xid: u32,
+    first_segment: u8,
+}
+
+impl StreamStartBody {
+    #[inline]
+    pub fn xid(&self) -> u32 {
+        self.xid
+    }
+
+    /// Returns 1 if this is the first block of changes streamed for the transaction, and 0 otherwise.
+    #[inline]
+    pub fn first_segment(&self) -> u8 {
+        self.first_segment
+    }
+}
+
+pub struct StreamCommitBody {
+    xid: u32,
+    flags: i8,
+    commit_lsn: Lsn,
+    end_lsn: Lsn,
+    timestamp: i64,
+}
+
+impl StreamCommitBody {
+    #[inline]
+    pub fn xid(&self) -> u32 {
+        self.xid
+    }
+
+    #[inline]
+    pub fn flags(&self) -> i8 {
+        self.flags
+    }
+
+    /// The LSN of the commit.
+    #[inline]
+    pub fn commit_lsn(&self) -> Lsn {
+        self.commit_lsn
+    }
+
+    /// The end LSN of the transaction.
+    #[inline]
+    pub fn end_lsn(&self) -> Lsn {
+        self.end_lsn
+    }
+
+    /// The commit timestamp of the transaction, as microseconds since midnight on 2000-01-01.
+    #[inline]
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+}
+
+pub struct StreamAbortBody {
+    xid: u32,
+    subxid: u32,
+}
+
+impl StreamAbortBody {
+    #[inline]
+    pub fn xid(&self) -> u32 {
+        self.xid
+    }
+
+    /// The ID of the aborted subtransaction, which is the same as `xid` if the top-level transaction was aborted.
+    #[inline]
+    pub fn subxid(&self) -> u32 {
+        self.subxid
+    }
+}
+
+pub(crate) struct Buffer {
+    pub(crate) bytes: Bytes,
+    pub(crate) idx: usize,
 }
 
 impl Buffer {
     #[inline]
-    fn slice(&self) -> &[u8] {
+    pub(crate) fn slice(&self) -> &[u8] {
         &self.bytes[self.idx..]
     }
 
     #[inline]
-    fn is_empty(&self) -> bool {
+    pub(crate) fn is_empty(&self) -> bool {
         self.slice().is_empty()
     }
 
     #[inline]
-    fn read_cstr(&mut self) -> io::Result<Bytes> {
+    pub(crate) fn read_cstr(&mut self) -> io::Result<Bytes> {
         match memchr(0, self.slice()) {
             Some(pos) => {
                 let start = self.idx;
@@ -312,7 +1159,20 @@ impl Buffer {
     }
 
     #[inline]
-    fn read_all(&mut self) -> Bytes {
+    pub(crate) fn read_bytes(&mut self, len: usize) -> io::Result<Bytes> {
+        if self.slice().len() < len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "unexpected EOF",
+            ));
+        }
+        let buf = self.bytes.slice(self.idx..self.idx + len);
+        self.idx += len;
+        Ok(buf)
+    }
+
+    #[inline]
+    pub(crate) fn read_all(&mut self) -> Bytes {
         let buf = self.bytes.slice(self.idx..);
         self.idx = self.bytes.len();
         buf
@@ -338,6 +1198,12 @@ pub struct AuthenticationMd5PasswordBody {
 }
 
 impl AuthenticationMd5PasswordBody {
+    /// Creates a new body, for use in tests.
+    #[inline]
+    pub fn new(salt: [u8; 4]) -> AuthenticationMd5PasswordBody {
+        AuthenticationMd5PasswordBody { salt }
+    }
+
     #[inline]
     pub fn salt(&self) -> [u8; 4] {
         self.salt
@@ -355,7 +1221,18 @@ impl AuthenticationGssContinueBody {
 
 pub struct AuthenticationSaslBody(Bytes);
 
-impl AuthenticationSaslBody {
+impl AuthenticationSaslBody {
+    /// Creates a new body listing the provided mechanisms, for use in tests.
+    pub fn new<'a, I>(mechanisms: I) -> io::Result<AuthenticationSaslBody>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        match build(|buf| authentication_sasl(mechanisms, buf))? {
+            Message::AuthenticationSasl(body) => Ok(body),
+            _ => unreachable!(),
+        }
+    }
+
     #[inline]
     pub fn mechanisms(&self) -> SaslMechanisms<'_> {
         SaslMechanisms(&self.0)
@@ -390,6 +1267,12 @@ impl<'a> FallibleIterator for SaslMechanisms<'a> {
 pub struct AuthenticationSaslContinueBody(Bytes);
 
 impl AuthenticationSaslContinueBody {
+    /// Creates a new body, for use in tests.
+    #[inline]
+    pub fn new(data: &[u8]) -> AuthenticationSaslContinueBody {
+        AuthenticationSaslContinueBody(Bytes::copy_from_slice(data))
+    }
+
     #[inline]
     pub fn data(&self) -> &[u8] {
         &self.0
@@ -399,6 +1282,12 @@ impl AuthenticationSaslContinueBody {
 pub struct AuthenticationSaslFinalBody(Bytes);
 
 impl AuthenticationSaslFinalBody {
+    /// Creates a new body, for use in tests.
+    #[inline]
+    pub fn new(data: &[u8]) -> AuthenticationSaslFinalBody {
+        AuthenticationSaslFinalBody(Bytes::copy_from_slice(data))
+    }
+
     #[inline]
     pub fn data(&self) -> &[u8] {
         &self.0
@@ -407,18 +1296,37 @@ impl AuthenticationSaslFinalBody {
 
 pub struct BackendKeyDataBody {
     process_id: i32,
-    secret_key: i32,
+    secret_key: Bytes,
 }
 
 impl BackendKeyDataBody {
+    /// Creates a new body, for use in tests.
+    #[inline]
+    pub fn new(process_id: i32, secret_key: i32) -> BackendKeyDataBody {
+        BackendKeyDataBody {
+            process_id,
+            secret_key: Bytes::copy_from_slice(&secret_key.to_be_bytes()),
+        }
+    }
+
     #[inline]
     pub fn process_id(&self) -> i32 {
         self.process_id
     }
 
+    /// Returns the first four bytes of the secret key as an integer.
+    ///
+    /// This is the entire key in protocol versions before 3.2, which may use longer keys. Use `secret_key_bytes` to
+    /// access the full key.
     #[inline]
     pub fn secret_key(&self) -> i32 {
-        self.secret_key
+        BigEndian::read_i32(&self.secret_key)
+    }
+
+    /// Returns the secret key.
+    #[inline]
+    pub fn secret_key_bytes(&self) -> &[u8] {
+        &self.secret_key
     }
 }
 
@@ -427,6 +1335,14 @@ pub struct CommandCompleteBody {
 }
 
 impl CommandCompleteBody {
+    /// Creates a new body, for use in tests.
+    pub fn new(tag: &str) -> io::Result<CommandCompleteBody> {
+        match build(|buf| command_complete(tag, buf))? {
+            Message::CommandComplete(body) => Ok(body),
+            _ => unreachable!(),
+        }
+    }
+
     #[inline]
     pub fn tag(&self) -> io::Result<&str> {
         get_str(&self.tag)
@@ -524,12 +1440,71 @@ impl CopyOutResponseBody {
     }
 }
 
+pub struct CopyBothResponseBody {
+    format: u8,
+    len: u16,
+    storage: Bytes,
+}
+
+impl CopyBothResponseBody {
+    #[inline]
+    pub fn format(&self) -> u8 {
+        self.format
+    }
+
+    #[inline]
+    pub fn column_formats(&self) -> ColumnFormats<'_> {
+        ColumnFormats {
+            remaining: self.len,
+            buf: &self.storage,
+        }
+    }
+}
+
+pub struct FunctionCallResponseBody {
+    storage: Bytes,
+    len: i32,
+}
+
+impl FunctionCallResponseBody {
+    #[inline]
+    pub fn value(&self) -> Option<&[u8]> {
+        if self.len < 0 {
+            None
+        } else {
+            Some(&self.storage)
+        }
+    }
+
+    #[inline]
+    pub fn into_value(self) -> Option<Bytes> {
+        if self.len < 0 {
+            None
+        } else {
+            Some(self.storage)
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct DataRowBody {
     storage: Bytes,
     len: u16,
 }
 
 impl DataRowBody {
+    /// Creates a new body containing the provided values, for use in tests.
+    pub fn new<I, T>(values: I) -> io::Result<DataRowBody>
+    where
+        I: IntoIterator<Item = Option<T>>,
+        T: AsRef<[u8]>,
+    {
+        match build(|buf| data_row(values, buf))? {
+            Message::DataRow(body) => Ok(body),
+            _ => unreachable!(),
+        }
+    }
+
     #[inline]
     pub fn ranges(&self) -> DataRowRanges<'_> {
         DataRowRanges {
@@ -543,6 +1518,11 @@ impl DataRowBody {
     pub fn buffer(&self) -> &[u8] {
         &self.storage
     }
+
+    #[inline]
+    pub fn storage_bytes(&self) -> &Bytes {
+        &self.storage
+    }
 }
 
 pub struct DataRowRanges<'a> {
@@ -598,6 +1578,17 @@ pub struct ErrorResponseBody {
 }
 
 impl ErrorResponseBody {
+    /// Creates a new body containing the provided fields, for use in tests.
+    pub fn new<'a, I>(fields: I) -> io::Result<ErrorResponseBody>
+    where
+        I: IntoIterator<Item = (u8, &'a str)>,
+    {
+        match build(|buf| error_response(fields, buf))? {
+            Message::ErrorResponse(body) => Ok(body),
+            _ => unreachable!(),
+        }
+    }
+
     #[inline]
     pub fn fields(&self) -> ErrorFields<'_> {
         ErrorFields { buf: &self.storage }
@@ -656,6 +1647,17 @@ pub struct NoticeResponseBody {
 }
 
 impl NoticeResponseBody {
+    /// Creates a new body containing the provided fields, for use in tests.
+    pub fn new<'a, I>(fields: I) -> io::Result<NoticeResponseBody>
+    where
+        I: IntoIterator<Item = (u8, &'a str)>,
+    {
+        match build(|buf| notice_response(fields, buf))? {
+            Message::NoticeResponse(body) => Ok(body),
+            _ => unreachable!(),
+        }
+    }
+
     #[inline]
     pub fn fields(&self) -> ErrorFields<'_> {
         ErrorFields { buf: &self.storage }
@@ -669,6 +1671,18 @@ pub struct NotificationResponseBody {
 }
 
 impl NotificationResponseBody {
+    /// Creates a new body, for use in tests.
+    pub fn new(
+        process_id: i32,
+        channel: &str,
+        message: &str,
+    ) -> io::Result<NotificationResponseBody> {
+        match build(|buf| notification_response(process_id, channel, message, buf))? {
+            Message::NotificationResponse(body) => Ok(body),
+            _ => unreachable!(),
+        }
+    }
+
     #[inline]
     pub fn process_id(&self) -> i32 {
         self.process_id
@@ -685,12 +1699,95 @@ impl NotificationResponseBody {
     }
 }
 
+pub struct NegotiateProtocolVersionBody {
+    newest_version: i32,
+    len: i32,
+    storage: Bytes,
+}
+
+impl NegotiateProtocolVersionBody {
+    /// Creates a new body, for use in tests.
+    pub fn new<'a, I>(newest_version: i32, options: I) -> io::Result<NegotiateProtocolVersionBody>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        match build(|buf| negotiate_protocol_version(newest_version, options, buf))? {
+            Message::NegotiateProtocolVersion(body) => Ok(body),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns the newest protocol version supported by the server for the requested major version.
+    ///
+    /// The version holds the major version in the high 16 bits and the minor version in the low 16 bits.
+    #[inline]
+    pub fn newest_version(&self) -> i32 {
+        self.newest_version
+    }
+
+    /// Returns the `_pq_.`-prefixed protocol options in the startup message which the server did not recognize.
+    #[inline]
+    pub fn options(&self) -> ProtocolOptions<'_> {
+        ProtocolOptions {
+            buf: &self.storage,
+            remaining: self.len,
+        }
+    }
+}
+
+pub struct ProtocolOptions<'a> {
+    buf: &'a [u8],
+    remaining: i32,
+}
+
+impl<'a> FallibleIterator for ProtocolOptions<'a> {
+    type Item = &'a str;
+    type Error = io::Error;
+
+    #[inline]
+    fn next(&mut self) -> io::Result<Option<&'a str>> {
+        if self.remaining <= 0 {
+            if self.buf.is_empty() {
+                return Ok(None);
+            } else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "invalid message length: protocol options are not drained",
+                ));
+            }
+        }
+
+        self.remaining -= 1;
+        let end = find_null(self.buf, 0)?;
+        let option = get_str(&self.buf[..end])?;
+        self.buf = &self.buf[end + 1..];
+        Ok(Some(option))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.remaining.max(0) as usize;
+        (len, Some(len))
+    }
+}
+
 pub struct ParameterDescriptionBody {
     storage: Bytes,
     len: u16,
 }
 
 impl ParameterDescriptionBody {
+    /// Creates a new body listing the provided parameter types, for use in tests.
+    pub fn new<I>(parameter_types: I) -> io::Result<ParameterDescriptionBody>
+    where
+        I: IntoIterator<Item = Oid>,
+    {
+        match build(|buf| parameter_description(parameter_types, buf))? {
+            Message::ParameterDescription(body) => Ok(body),
+            _ => unreachable!(),
+        }
+    }
+
     #[inline]
     pub fn parameters(&self) -> Parameters<'_> {
         Parameters {
@@ -739,6 +1836,14 @@ pub struct ParameterStatusBody {
 }
 
 impl ParameterStatusBody {
+    /// Creates a new body, for use in tests.
+    pub fn new(name: &str, value: &str) -> io::Result<ParameterStatusBody> {
+        match build(|buf| parameter_status(name, value, buf))? {
+            Message::ParameterStatus(body) => Ok(body),
+            _ => unreachable!(),
+        }
+    }
+
     #[inline]
     pub fn name(&self) -> io::Result<&str> {
         get_str(&self.name)
@@ -755,6 +1860,12 @@ pub struct ReadyForQueryBody {
 }
 
 impl ReadyForQueryBody {
+    /// Creates a new body with the provided transaction status, for use in tests.
+    #[inline]
+    pub fn new(status: u8) -> ReadyForQueryBody {
+        ReadyForQueryBody { status }
+    }
+
     #[inline]
     pub fn status(&self) -> u8 {
         self.status
@@ -767,6 +1878,17 @@ pub struct RowDescriptionBody {
 }
 
 impl RowDescriptionBody {
+    /// Creates a new body describing the provided fields, for use in tests.
+    pub fn new<'a, I>(fields: I) -> io::Result<RowDescriptionBody>
+    where
+        I: IntoIterator<Item = Field<'a>>,
+    {
+        match build(|buf| row_description(fields, buf))? {
+            Message::RowDescription(body) => Ok(body),
+            _ => unreachable!(),
+        }
+    }
+
     #[inline]
     pub fn fields(&self) -> Fields<'_> {
         Fields {
@@ -832,6 +1954,28 @@ pub struct Field<'a> {
 }
 
 impl<'a> Field<'a> {
+    /// Creates a new field description, for use with `row_description`.
+    #[inline]
+    pub fn new(
+        name: &'a str,
+        table_oid: Oid,
+        column_id: i16,
+        type_oid: Oid,
+        type_size: i16,
+        type_modifier: i32,
+        format: i16,
+    ) -> Field<'a> {
+        Field {
+            name,
+            table_oid,
+            column_id,
+            type_oid,
+            type_size,
+            type_modifier,
+            format,
+        }
+    }
+
     #[inline]
     pub fn name(&self) -> &'a str {
         self.name
@@ -868,8 +2012,379 @@ impl<'a> Field<'a> {
     }
 }
 
+// Backend message serialization, for use by servers and proxies speaking the Postgres protocol.
+
+// Parses a message written by one of the serialization functions below, so that message bodies can be constructed
+// without a server.
+fn build<F>(f: F) -> io::Result<Message>
+where
+    F: FnOnce(&mut BytesMut) -> io::Result<()>,
+{
+    let mut buf = BytesMut::new();
+    f(&mut buf)?;
+    Ok(Message::parse(&mut buf)?.expect("serialized message is complete"))
+}
+
+#[inline]
+fn write_empty(tag: u8, buf: &mut BytesMut) {
+    buf.put_u8(tag);
+    write_body(buf, |_| Ok::<(), io::Error>(())).unwrap();
+}
+
+#[inline]
+fn write_authentication(code: i32, data: &[u8], buf: &mut BytesMut) -> io::Result<()> {
+    buf.put_u8(AUTHENTICATION_TAG);
+    write_body(buf, |buf| {
+        buf.put_i32(code);
+        buf.put_slice(data);
+        Ok(())
+    })
+}
+
+#[inline]
+pub fn authentication_ok(buf: &mut BytesMut) {
+    write_authentication(0, &[], buf).unwrap();
+}
+
+#[inline]
+pub fn authentication_cleartext_password(buf: &mut BytesMut) {
+    write_authentication(3, &[], buf).unwrap();
+}
+
+#[inline]
+pub fn authentication_md5_password(salt: [u8; 4], buf: &mut BytesMut) {
+    write_authentication(5, &salt, buf).unwrap();
+}
+
+#[inline]
+pub fn authentication_sasl<'a, I>(mechanisms: I, buf: &mut BytesMut) -> io::Result<()>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    buf.put_u8(AUTHENTICATION_TAG);
+    write_body(buf, |buf| {
+        buf.put_i32(10);
+        for mechanism in mechanisms {
+            write_cstr(mechanism.as_bytes(), buf)?;
+        }
+        buf.put_u8(0);
+        Ok(())
+    })
+}
+
+#[inline]
+pub fn authentication_sasl_continue(data: &[u8], buf: &mut BytesMut) -> io::Result<()> {
+    write_authentication(11, data, buf)
+}
+
+#[inline]
+pub fn authentication_sasl_final(data: &[u8], buf: &mut BytesMut) -> io::Result<()> {
+    write_authentication(12, data, buf)
+}
+
+#[inline]
+pub fn backend_key_data(process_id: i32, secret_key: i32, buf: &mut BytesMut) {
+    buf.put_u8(BACKEND_KEY_DATA_TAG);
+    write_body(buf, |buf| {
+        buf.put_i32(process_id);
+        buf.put_i32(secret_key);
+        Ok::<_, io::Error>(())
+    })
+    .unwrap();
+}
+
+/// Serializes a `BackendKeyData` message with a secret key of any length, as used by protocol version 3.2.
+#[inline]
+pub fn backend_key_data_with_key(
+    process_id: i32,
+    secret_key: &[u8],
+    buf: &mut BytesMut,
+) -> io::Result<()> {
+    buf.put_u8(BACKEND_KEY_DATA_TAG);
+    write_body(buf, |buf| {
+        buf.put_i32(process_id);
+        buf.put_slice(secret_key);
+        Ok(())
+    })
+}
+
+#[inline]
+pub fn bind_complete(buf: &mut BytesMut) {
+    write_empty(BIND_COMPLETE_TAG, buf);
+}
+
+#[inline]
+pub fn close_complete(buf: &mut BytesMut) {
+    write_empty(CLOSE_COMPLETE_TAG, buf);
+}
+
+#[inline]
+pub fn command_complete(tag: &str, buf: &mut BytesMut) -> io::Result<()> {
+    buf.put_u8(COMMAND_COMPLETE_TAG);
+    write_body(buf, |buf| write_cstr(tag.as_bytes(), buf))
+}
+
+#[inline]
+pub fn copy_data(data: &[u8], buf: &mut BytesMut) -> io::Result<()> {
+    buf.put_u8(COPY_DATA_TAG);
+    write_body(buf, |buf| {
+        buf.put_slice(data);
+        Ok(())
+    })
+}
+
+#[inline]
+pub fn copy_done(buf: &mut BytesMut) {
+    write_empty(COPY_DONE_TAG, buf);
+}
+
+#[inline]
+fn write_copy_response<I>(
+    tag: u8,
+    format: u8,
+    column_formats: I,
+    buf: &mut BytesMut,
+) -> io::Result<()>
+where
+    I: IntoIterator<Item = i16>,
+{
+    buf.put_u8(tag);
+    write_body(buf, |buf| {
+        buf.put_u8(format);
+        write_counted(
+            column_formats,
+            |f, buf| {
+                buf.put_i16(f);
+                Ok::<_, io::Error>(())
+            },
+            buf,
+        )
+    })
+}
+
+#[inline]
+pub fn copy_in_response<I>(format: u8, column_formats: I, buf: &mut BytesMut) -> io::Result<()>
+where
+    I: IntoIterator<Item = i16>,
+{
+    write_copy_response(COPY_IN_RESPONSE_TAG, format, column_formats, buf)
+}
+
+#[inline]
+pub fn copy_out_response<I>(format: u8, column_formats: I, buf: &mut BytesMut) -> io::Result<()>
+where
+    I: IntoIterator<Item = i16>,
+{
+    write_copy_response(COPY_OUT_RESPONSE_TAG, format, column_formats, buf)
+}
+
+#[inline]
+pub fn copy_both_response<I>(format: u8, column_formats: I, buf: &mut BytesMut) -> io::Result<()>
+where
+    I: IntoIterator<Item = i16>,
+{
+    write_copy_response(COPY_BOTH_RESPONSE_TAG, format, column_formats, buf)
+}
+
+#[inline]
+fn write_value(value: Option<&[u8]>, buf: &mut BytesMut) -> io::Result<()> {
+    match value {
+        Some(value) => {
+            buf.put_i32(i32::from_usize(value.len())?);
+            buf.put_slice(value);
+        }
+        None => buf.put_i32(-1),
+    }
+    Ok(())
+}
+
+#[inline]
+pub fn data_row<I, T>(values: I, buf: &mut BytesMut) -> io::Result<()>
+where
+    I: IntoIterator<Item = Option<T>>,
+    T: AsRef<[u8]>,
+{
+    buf.put_u8(DATA_ROW_TAG);
+    write_body(buf, |buf| {
+        write_counted(
+            values,
+            |v, buf| write_value(v.as_ref().map(AsRef::as_ref), buf),
+            buf,
+        )
+    })
+}
+
+#[inline]
+pub fn empty_query_response(buf: &mut BytesMut) {
+    write_empty(EMPTY_QUERY_RESPONSE_TAG, buf);
+}
+
+#[inline]
+fn write_fields<'a, I>(tag: u8, fields: I, buf: &mut BytesMut) -> io::Result<()>
+where
+    I: IntoIterator<Item = (u8, &'a str)>,
+{
+    buf.put_u8(tag);
+    write_body(buf, |buf| {
+        for (type_, value) in fields {
+            if type_ == 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "field type must be nonzero",
+                ));
+            }
+            buf.put_u8(type_);
+            write_cstr(value.as_bytes(), buf)?;
+        }
+        buf.put_u8(0);
+        Ok(())
+    })
+}
+
+/// Serializes an error response from its fields, identified by their single byte type codes.
+#[inline]
+pub fn error_response<'a, I>(fields: I, buf: &mut BytesMut) -> io::Result<()>
+where
+    I: IntoIterator<Item = (u8, &'a str)>,
+{
+    write_fields(ERROR_RESPONSE_TAG, fields, buf)
+}
+
+#[inline]
+pub fn function_call_response(value: Option<&[u8]>, buf: &mut BytesMut) -> io::Result<()> {
+    buf.put_u8(FUNCTION_CALL_RESPONSE_TAG);
+    write_body(buf, |buf| write_value(value, buf))
+}
+
+/// Serializes a `NegotiateProtocolVersion` message, sent in response to a startup message requesting a newer minor
+/// protocol version or protocol options the server does not support.
+#[inline]
+pub fn negotiate_protocol_version<'a, I>(
+    newest_version: i32,
+    options: I,
+    buf: &mut BytesMut,
+) -> io::Result<()>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    buf.put_u8(NEGOTIATE_PROTOCOL_VERSION_TAG);
+    write_body(buf, |buf| {
+        buf.put_i32(newest_version);
+        let base = buf.len();
+        buf.put_i32(0);
+        let mut count = 0;
+        for option in options {
+            write_cstr(option.as_bytes(), buf)?;
+            count += 1;
+        }
+        let count = i32::from_usize(count)?;
+        BigEndian::write_i32(&mut buf[base..], count);
+        Ok(())
+    })
+}
+
+#[inline]
+pub fn no_data(buf: &mut BytesMut) {
+    write_empty(NO_DATA_TAG, buf);
+}
+
+/// Serializes a notice response from its fields, identified by their single byte type codes.
+#[inline]
+pub fn notice_response<'a, I>(fields: I, buf: &mut BytesMut) -> io::Result<()>
+where
+    I: IntoIterator<Item = (u8, &'a str)>,
+{
+    write_fields(NOTICE_RESPONSE_TAG, fields, buf)
+}
+
+#[inline]
+pub fn notification_response(
+    process_id: i32,
+    channel: &str,
+    message: &str,
+    buf: &mut BytesMut,
+) -> io::Result<()> {
+    buf.put_u8(NOTIFICATION_RESPONSE_TAG);
+    write_body(buf, |buf| {
+        buf.put_i32(process_id);
+        write_cstr(channel.as_bytes(), buf)?;
+        write_cstr(message.as_bytes(), buf)
+    })
+}
+
+#[inline]
+pub fn parameter_description<I>(parameter_types: I, buf: &mut BytesMut) -> io::Result<()>
+where
+    I: IntoIterator<Item = Oid>,
+{
+    buf.put_u8(PARAMETER_DESCRIPTION_TAG);
+    write_body(buf, |buf| {
+        write_counted(
+            parameter_types,
+            |t, buf| {
+                buf.put_u32(t);
+                Ok::<_, io::Error>(())
+            },
+            buf,
+        )
+    })
+}
+
+#[inline]
+pub fn parameter_status(name: &str, value: &str, buf: &mut BytesMut) -> io::Result<()> {
+    buf.put_u8(PARAMETER_STATUS_TAG);
+    write_body(buf, |buf| {
+        write_cstr(name.as_bytes(), buf)?;
+        write_cstr(value.as_bytes(), buf)
+    })
+}
+
+#[inline]
+pub fn parse_complete(buf: &mut BytesMut) {
+    write_empty(PARSE_COMPLETE_TAG, buf);
+}
+
+#[inline]
+pub fn portal_suspended(buf: &mut BytesMut) {
+    write_empty(PORTAL_SUSPENDED_TAG, buf);
+}
+
+#[inline]
+pub fn ready_for_query(status: u8, buf: &mut BytesMut) {
+    buf.put_u8(READY_FOR_QUERY_TAG);
+    write_body(buf, |buf| {
+        buf.put_u8(status);
+        Ok::<_, io::Error>(())
+    })
+    .unwrap();
+}
+
+#[inline]
+pub fn row_description<'a, I>(fields: I, buf: &mut BytesMut) -> io::Result<()>
+where
+    I: IntoIterator<Item = Field<'a>>,
+{
+    buf.put_u8(ROW_DESCRIPTION_TAG);
+    write_body(buf, |buf| {
+        write_counted(
+            fields,
+            |field, buf| {
+                write_cstr(field.name.as_bytes(), buf)?;
+                buf.put_u32(field.table_oid);
+                buf.put_i16(field.column_id);
+                buf.put_u32(field.type_oid);
+                buf.put_i16(field.type_size);
+                buf.put_i32(field.type_modifier);
+                buf.put_i16(field.format);
+                Ok(())
+            },
+            buf,
+        )
+    })
+}
+
 #[inline]
-fn find_null(buf: &[u8], start: usize) -> io::Result<usize> {
+pub(crate) fn find_null(buf: &[u8], start: usize) -> io::Result<usize> {
     match memchr(0, &buf[start..]) {
         Some(pos) => Ok(pos + start),
         None => Err(io::Error::new(
@@ -880,6 +2395,6 @@ fn find_null(buf: &[u8], start: usize) -> io::Result<usize> {
 }
 
 #[inline]
-fn get_str(buf: &[u8]) -> io::Result<&str> {
+pub(crate) fn get_str(buf: &[u8]) -> io::Result<&str> {
     str::from_utf8(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
 }
@@ -0,0 +1,145 @@
+//! Incremental message framing.
+use byteorder::{BigEndian, ByteOrder};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::cmp;
+use std::io;
+
+/// A single message split off of a buffer by `MessageFrames`.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    tag: Option<u8>,
+    body: Bytes,
+}
+
+impl Frame {
+    /// Returns the message's tag, or `None` for an untagged startup packet.
+    #[inline]
+    pub fn tag(&self) -> Option<u8> {
+        self.tag
+    }
+
+    /// Returns the message's body, following its length.
+    #[inline]
+    pub fn body(&self) -> &Bytes {
+        &self.body
+    }
+
+    /// Consumes the frame, returning its body.
+    #[inline]
+    pub fn into_body(self) -> Bytes {
+        self.body
+    }
+
+    /// Writes the message's header to `buf`.
+    ///
+    /// Together with the body, this reproduces the message as it was received, so it can be forwarded elsewhere.
+    #[inline]
+    pub fn write_header(&self, buf: &mut BytesMut) {
+        if let Some(tag) = self.tag {
+            buf.put_u8(tag);
+        }
+        buf.put_i32(self.body.len() as i32 + 4);
+    }
+}
+
+/// An incremental parser splitting a stream of messages into frames.
+///
+/// The parser works over any `Buf`, including non-contiguous ones such as chains of received chunks. The header of a
+/// message is consumed as soon as it is available, and its body once it has been received in full, so bodies held in
+/// a single contiguous chunk of a `Bytes` or `BytesMut` buffer are split off without copying. Since a partially
+/// received header is held by the parser, the same parser must be used for every call on a stream.
+#[derive(Debug, Clone)]
+pub struct MessageFrames {
+    tagged: bool,
+    max_message_length: Option<usize>,
+    header: [u8; 5],
+    header_len: usize,
+}
+
+impl Default for MessageFrames {
+    fn default() -> MessageFrames {
+        MessageFrames::new()
+    }
+}
+
+impl MessageFrames {
+    /// Creates a parser for tagged messages, which are everything but the startup packet sent by clients.
+    pub fn new() -> MessageFrames {
+        MessageFrames {
+            tagged: true,
+            max_message_length: None,
+            header: [0; 5],
+            header_len: 0,
+        }
+    }
+
+    /// Creates a parser for untagged startup packets, which clients open connections with.
+    pub fn untagged() -> MessageFrames {
+        MessageFrames {
+            tagged: false,
+            ..MessageFrames::new()
+        }
+    }
+
+    /// Sets the maximum length of a message, excluding its tag.
+    ///
+    /// Messages with a longer length are rejected with an error as soon as their header is received, rather than
+    /// waiting for their body. Defaults to no limit.
+    pub fn max_message_length(&mut self, max_message_length: Option<usize>) -> &mut MessageFrames {
+        self.max_message_length = max_message_length;
+        self
+    }
+
+    /// Splits the next message off of the front of the buffer, if it has been received in full.
+    pub fn decode<B>(&mut self, buf: &mut B) -> io::Result<Option<Frame>>
+    where
+        B: Buf,
+    {
+        let header_size = if self.tagged { 5 } else { 4 };
+        while self.header_len < header_size && buf.has_remaining() {
+            let chunk = buf.chunk();
+            let len = cmp::min(header_size - self.header_len, chunk.len());
+            self.header[self.header_len..self.header_len + len].copy_from_slice(&chunk[..len]);
+            buf.advance(len);
+            self.header_len += len;
+        }
+        if self.header_len < header_size {
+            return Ok(None);
+        }
+
+        let (tag, len, min_len) = if self.tagged {
+            (
+                Some(self.header[0]),
+                BigEndian::read_i32(&self.header[1..]),
+                4,
+            )
+        } else {
+            (None, BigEndian::read_i32(&self.header), 8)
+        };
+
+        if len < min_len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid message length: header length < {}", min_len),
+            ));
+        }
+        let len = len as usize;
+        if let Some(max) = self.max_message_length {
+            if len > max {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("message length {} exceeds the maximum of {}", len, max),
+                ));
+            }
+        }
+
+        let body_len = len - 4;
+        if buf.remaining() < body_len {
+            return Ok(None);
+        }
+
+        self.header_len = 0;
+        let body = buf.copy_to_bytes(body_len);
+        Ok(Some(Frame { tag, body }))
+    }
+}
@@ -1,17 +1,39 @@
-//! Frontend message serialization.
+//! Frontend message serialization and parsing.
 #![allow(missing_docs)]
 
-use byteorder::{BigEndian, ByteOrder};
-use bytes::{Buf, BufMut, BytesMut};
+use byteorder::{BigEndian, ByteOrder, ReadBytesExt};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use fallible_iterator::FallibleIterator;
 use std::convert::TryFrom;
 use std::error::Error;
 use std::io;
 use std::marker;
 
-use crate::{write_nullable, FromUsize, IsNull, Oid};
+use crate::message::backend::{find_null, get_str, Buffer};
+use crate::{write_nullable, FromUsize, IsNull, Lsn, Oid};
+
+pub const BIND_TAG: u8 = b'B';
+pub const CLOSE_TAG: u8 = b'C';
+pub const COPY_DATA_TAG: u8 = b'd';
+pub const COPY_DONE_TAG: u8 = b'c';
+pub const COPY_FAIL_TAG: u8 = b'f';
+pub const DESCRIBE_TAG: u8 = b'D';
+pub const EXECUTE_TAG: u8 = b'E';
+pub const FLUSH_TAG: u8 = b'H';
+pub const FUNCTION_CALL_TAG: u8 = b'F';
+pub const PARSE_TAG: u8 = b'P';
+pub const PASSWORD_MESSAGE_TAG: u8 = b'p';
+pub const QUERY_TAG: u8 = b'Q';
+pub const SYNC_TAG: u8 = b'S';
+pub const TERMINATE_TAG: u8 = b'X';
+
+// request codes sent in place of a protocol version by untagged startup packets
+const CANCEL_REQUEST_CODE: i32 = 80_877_102;
+const SSL_REQUEST_CODE: i32 = 80_877_103;
+const GSS_ENC_REQUEST_CODE: i32 = 80_877_104;
 
 #[inline]
-fn write_body<F, E>(buf: &mut BytesMut, f: F) -> Result<(), E>
+pub(crate) fn write_body<F, E>(buf: &mut BytesMut, f: F) -> Result<(), E>
 where
     F: FnOnce(&mut BytesMut) -> Result<(), E>,
     E: From<io::Error>,
@@ -61,7 +83,7 @@ where
     F: FnMut(T, &mut BytesMut) -> Result<IsNull, Box<dyn Error + marker::Sync + Send>>,
     K: IntoIterator<Item = i16>,
 {
-    buf.put_u8(b'B');
+    buf.put_u8(BIND_TAG);
 
     write_body(buf, |buf| {
         write_cstr(portal.as_bytes(), buf)?;
@@ -93,7 +115,11 @@ where
 }
 
 #[inline]
-fn write_counted<I, T, F, E>(items: I, mut serializer: F, buf: &mut BytesMut) -> Result<(), E>
+pub(crate) fn write_counted<I, T, F, E>(
+    items: I,
+    mut serializer: F,
+    buf: &mut BytesMut,
+) -> Result<(), E>
 where
     I: IntoIterator<Item = T>,
     F: FnMut(T, &mut BytesMut) -> Result<(), E>,
@@ -106,26 +132,35 @@ where
         serializer(item, buf)?;
         count += 1;
     }
-    let count = i16::from_usize(count)?;
-    BigEndian::write_i16(&mut buf[base..], count);
+    let count = u16::from_usize(count)?;
+    BigEndian::write_u16(&mut buf[base..], count);
 
     Ok(())
 }
 
 #[inline]
 pub fn cancel_request(process_id: i32, secret_key: i32, buf: &mut BytesMut) {
+    cancel_request_with_key(process_id, &secret_key.to_be_bytes(), buf).unwrap();
+}
+
+/// Serializes a cancel request with a secret key of any length, as used by protocol version 3.2.
+#[inline]
+pub fn cancel_request_with_key(
+    process_id: i32,
+    secret_key: &[u8],
+    buf: &mut BytesMut,
+) -> io::Result<()> {
     write_body(buf, |buf| {
-        buf.put_i32(80_877_102);
+        buf.put_i32(CANCEL_REQUEST_CODE);
         buf.put_i32(process_id);
-        buf.put_i32(secret_key);
-        Ok::<_, io::Error>(())
+        buf.put_slice(secret_key);
+        Ok(())
     })
-    .unwrap();
 }
 
 #[inline]
 pub fn close(variant: u8, name: &str, buf: &mut BytesMut) -> io::Result<()> {
-    buf.put_u8(b'C');
+    buf.put_u8(CLOSE_TAG);
     write_body(buf, |buf| {
         buf.put_u8(variant);
         write_cstr(name.as_bytes(), buf)
@@ -154,27 +189,33 @@ where
     }
 
     pub fn write(self, out: &mut BytesMut) {
-        out.put_u8(b'd');
+        let buf = self.write_header(out);
+        out.put(buf);
+    }
+
+    /// Writes the message's header to `out`, returning the body which must be written immediately after it.
+    pub fn write_header(self, out: &mut BytesMut) -> T {
+        out.put_u8(COPY_DATA_TAG);
         out.put_i32(self.len);
-        out.put(self.buf);
+        self.buf
     }
 }
 
 #[inline]
 pub fn copy_done(buf: &mut BytesMut) {
-    buf.put_u8(b'c');
+    buf.put_u8(COPY_DONE_TAG);
     write_body(buf, |_| Ok::<(), io::Error>(())).unwrap();
 }
 
 #[inline]
 pub fn copy_fail(message: &str, buf: &mut BytesMut) -> io::Result<()> {
-    buf.put_u8(b'f');
+    buf.put_u8(COPY_FAIL_TAG);
     write_body(buf, |buf| write_cstr(message.as_bytes(), buf))
 }
 
 #[inline]
 pub fn describe(variant: u8, name: &str, buf: &mut BytesMut) -> io::Result<()> {
-    buf.put_u8(b'D');
+    buf.put_u8(DESCRIBE_TAG);
     write_body(buf, |buf| {
         buf.put_u8(variant);
         write_cstr(name.as_bytes(), buf)
@@ -183,7 +224,7 @@ pub fn describe(variant: u8, name: &str, buf: &mut BytesMut) -> io::Result<()> {
 
 #[inline]
 pub fn execute(portal: &str, max_rows: i32, buf: &mut BytesMut) -> io::Result<()> {
-    buf.put_u8(b'E');
+    buf.put_u8(EXECUTE_TAG);
     write_body(buf, |buf| {
         write_cstr(portal.as_bytes(), buf)?;
         buf.put_i32(max_rows);
@@ -191,12 +232,55 @@ pub fn execute(portal: &str, max_rows: i32, buf: &mut BytesMut) -> io::Result<()
     })
 }
 
+#[inline]
+pub fn flush(buf: &mut BytesMut) {
+    buf.put_u8(FLUSH_TAG);
+    write_body(buf, |_| Ok::<(), io::Error>(())).unwrap();
+}
+
+#[inline]
+pub fn function_call<I, J, F, T>(
+    function: Oid,
+    formats: I,
+    values: J,
+    mut serializer: F,
+    result_format: i16,
+    buf: &mut BytesMut,
+) -> Result<(), BindError>
+where
+    I: IntoIterator<Item = i16>,
+    J: IntoIterator<Item = T>,
+    F: FnMut(T, &mut BytesMut) -> Result<IsNull, Box<dyn Error + marker::Sync + Send>>,
+{
+    buf.put_u8(FUNCTION_CALL_TAG);
+
+    write_body(buf, |buf| {
+        buf.put_u32(function);
+        write_counted(
+            formats,
+            |f, buf| {
+                buf.put_i16(f);
+                Ok::<_, io::Error>(())
+            },
+            buf,
+        )?;
+        write_counted(
+            values,
+            |v, buf| write_nullable(|buf| serializer(v, buf), buf),
+            buf,
+        )?;
+        buf.put_i16(result_format);
+
+        Ok(())
+    })
+}
+
 #[inline]
 pub fn parse<I>(name: &str, query: &str, param_types: I, buf: &mut BytesMut) -> io::Result<()>
 where
     I: IntoIterator<Item = Oid>,
 {
-    buf.put_u8(b'P');
+    buf.put_u8(PARSE_TAG);
     write_body(buf, |buf| {
         write_cstr(name.as_bytes(), buf)?;
         write_cstr(query.as_bytes(), buf)?;
@@ -214,19 +298,19 @@ where
 
 #[inline]
 pub fn password_message(password: &[u8], buf: &mut BytesMut) -> io::Result<()> {
-    buf.put_u8(b'p');
+    buf.put_u8(PASSWORD_MESSAGE_TAG);
     write_body(buf, |buf| write_cstr(password, buf))
 }
 
 #[inline]
 pub fn query(query: &str, buf: &mut BytesMut) -> io::Result<()> {
-    buf.put_u8(b'Q');
+    buf.put_u8(QUERY_TAG);
     write_body(buf, |buf| write_cstr(query.as_bytes(), buf))
 }
 
 #[inline]
 pub fn sasl_initial_response(mechanism: &str, data: &[u8], buf: &mut BytesMut) -> io::Result<()> {
-    buf.put_u8(b'p');
+    buf.put_u8(PASSWORD_MESSAGE_TAG);
     write_body(buf, |buf| {
         write_cstr(mechanism.as_bytes(), buf)?;
         let len = i32::from_usize(data.len())?;
@@ -238,7 +322,7 @@ pub fn sasl_initial_response(mechanism: &str, data: &[u8], buf: &mut BytesMut) -
 
 #[inline]
 pub fn sasl_response(data: &[u8], buf: &mut BytesMut) -> io::Result<()> {
-    buf.put_u8(b'p');
+    buf.put_u8(PASSWORD_MESSAGE_TAG);
     write_body(buf, |buf| {
         buf.put_slice(data);
         Ok(())
@@ -248,7 +332,7 @@ pub fn sasl_response(data: &[u8], buf: &mut BytesMut) -> io::Result<()> {
 #[inline]
 pub fn ssl_request(buf: &mut BytesMut) {
     write_body(buf, |buf| {
-        buf.put_i32(80_877_103);
+        buf.put_i32(SSL_REQUEST_CODE);
         Ok::<_, io::Error>(())
     })
     .unwrap();
@@ -256,12 +340,28 @@ pub fn ssl_request(buf: &mut BytesMut) {
 
 #[inline]
 pub fn startup_message<'a, I>(parameters: I, buf: &mut BytesMut) -> io::Result<()>
+where
+    I: IntoIterator<Item = (&'a str, &'a str)>,
+{
+    // postgres protocol version 3.0(196608) in bigger-endian
+    startup_message_with_version(0x00_03_00_00, parameters, buf)
+}
+
+/// Serializes a startup message requesting a specific protocol version.
+///
+/// The version holds the major version in the high 16 bits and the minor version in the low 16 bits. Servers reply
+/// with a `NegotiateProtocolVersion` message if they only support an older minor version.
+#[inline]
+pub fn startup_message_with_version<'a, I>(
+    version: i32,
+    parameters: I,
+    buf: &mut BytesMut,
+) -> io::Result<()>
 where
     I: IntoIterator<Item = (&'a str, &'a str)>,
 {
     write_body(buf, |buf| {
-        // postgres protocol version 3.0(196608) in bigger-endian
-        buf.put_i32(0x00_03_00_00);
+        buf.put_i32(version);
         for (key, value) in parameters {
             write_cstr(key.as_bytes(), buf)?;
             write_cstr(value.as_bytes(), buf)?;
@@ -271,20 +371,41 @@ where
     })
 }
 
+/// Serializes a standby status update.
+///
+/// Unlike the other functions in this module, this only writes the message payload, which must be sent to the server
+/// as the contents of a `CopyData` message on a streaming replication connection.
+#[inline]
+pub fn standby_status_update(
+    write_lsn: Lsn,
+    flush_lsn: Lsn,
+    apply_lsn: Lsn,
+    timestamp: i64,
+    reply: u8,
+    buf: &mut BytesMut,
+) {
+    buf.put_u8(b'r');
+    buf.put_u64(write_lsn);
+    buf.put_u64(flush_lsn);
+    buf.put_u64(apply_lsn);
+    buf.put_i64(timestamp);
+    buf.put_u8(reply);
+}
+
 #[inline]
 pub fn sync(buf: &mut BytesMut) {
-    buf.put_u8(b'S');
+    buf.put_u8(SYNC_TAG);
     write_body(buf, |_| Ok::<(), io::Error>(())).unwrap();
 }
 
 #[inline]
 pub fn terminate(buf: &mut BytesMut) {
-    buf.put_u8(b'X');
+    buf.put_u8(TERMINATE_TAG);
     write_body(buf, |_| Ok::<(), io::Error>(())).unwrap();
 }
 
 #[inline]
-fn write_cstr(s: &[u8], buf: &mut BytesMut) -> Result<(), io::Error> {
+pub(crate) fn write_cstr(s: &[u8], buf: &mut BytesMut) -> Result<(), io::Error> {
     if s.contains(&0) {
         return Err(io::Error::new(
             io::ErrorKind::InvalidInput,
@@ -295,3 +416,698 @@ fn write_cstr(s: &[u8], buf: &mut BytesMut) -> Result<(), io::Error> {
     buf.put_u8(0);
     Ok(())
 }
+
+/// An enum representing Postgres frontend messages.
+///
+/// This covers the tagged messages a client sends after the startup packet. See `InitialMessage` for the startup
+/// packet itself.
+#[non_exhaustive]
+pub enum Message {
+    Bind(BindBody),
+    Close(CloseBody),
+    CopyData(CopyDataBody),
+    CopyDone,
+    CopyFail(CopyFailBody),
+    Describe(DescribeBody),
+    Execute(ExecuteBody),
+    Flush,
+    FunctionCall(FunctionCallBody),
+    Parse(ParseBody),
+    PasswordMessage(PasswordMessageBody),
+    Query(QueryBody),
+    Sync,
+    Terminate,
+}
+
+impl Message {
+    #[inline]
+    pub fn parse(buf: &mut BytesMut) -> io::Result<Option<Message>> {
+        if buf.len() < 5 {
+            let to_read = 5 - buf.len();
+            buf.reserve(to_read);
+            return Ok(None);
+        }
+
+        let tag = buf[0];
+        let len = (&buf[1..5]).read_u32::<BigEndian>().unwrap();
+
+        if len < 4 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid message length: parsing u32",
+            ));
+        }
+
+        let total_len = len as usize + 1;
+        if buf.len() < total_len {
+            let to_read = total_len - buf.len();
+            buf.reserve(to_read);
+            return Ok(None);
+        }
+
+        let mut buf = Buffer {
+            bytes: buf.split_to(total_len).freeze(),
+            idx: 5,
+        };
+
+        let message = match tag {
+            BIND_TAG => {
+                let portal = buf.read_cstr()?;
+                let statement = buf.read_cstr()?;
+                let formats = read_formats(&mut buf)?;
+                let values = read_values(&mut buf)?;
+                let result_formats = read_formats(&mut buf)?;
+                Message::Bind(BindBody {
+                    portal,
+                    statement,
+                    formats,
+                    values,
+                    result_formats,
+                })
+            }
+            CLOSE_TAG => {
+                let variant = buf.read_u8()?;
+                let name = buf.read_cstr()?;
+                Message::Close(CloseBody { variant, name })
+            }
+            COPY_DATA_TAG => {
+                let storage = buf.read_all();
+                Message::CopyData(CopyDataBody { storage })
+            }
+            COPY_DONE_TAG => Message::CopyDone,
+            COPY_FAIL_TAG => {
+                let message = buf.read_cstr()?;
+                Message::CopyFail(CopyFailBody { message })
+            }
+            DESCRIBE_TAG => {
+                let variant = buf.read_u8()?;
+                let name = buf.read_cstr()?;
+                Message::Describe(DescribeBody { variant, name })
+            }
+            EXECUTE_TAG => {
+                let portal = buf.read_cstr()?;
+                let max_rows = buf.read_i32::<BigEndian>()?;
+                Message::Execute(ExecuteBody { portal, max_rows })
+            }
+            FLUSH_TAG => Message::Flush,
+            FUNCTION_CALL_TAG => {
+                let function = buf.read_u32::<BigEndian>()?;
+                let formats = read_formats(&mut buf)?;
+                let values = read_values(&mut buf)?;
+                let result_format = buf.read_i16::<BigEndian>()?;
+                Message::FunctionCall(FunctionCallBody {
+                    function,
+                    formats,
+                    values,
+                    result_format,
+                })
+            }
+            PARSE_TAG => {
+                let name = buf.read_cstr()?;
+                let query = buf.read_cstr()?;
+                let len = buf.read_u16::<BigEndian>()?;
+                let storage = buf.read_bytes(len as usize * 4)?;
+                Message::Parse(ParseBody {
+                    name,
+                    query,
+                    storage,
+                    len,
+                })
+            }
+            PASSWORD_MESSAGE_TAG => {
+                let storage = buf.read_all();
+                Message::PasswordMessage(PasswordMessageBody { storage })
+            }
+            QUERY_TAG => {
+                let query = buf.read_cstr()?;
+                Message::Query(QueryBody { query })
+            }
+            SYNC_TAG => Message::Sync,
+            TERMINATE_TAG => Message::Terminate,
+            tag => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown message tag `{}`", tag),
+                ));
+            }
+        };
+
+        if !buf.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid message length: expected buffer to be empty",
+            ));
+        }
+
+        Ok(Some(message))
+    }
+}
+
+#[inline]
+fn read_formats(buf: &mut Buffer) -> io::Result<FormatsBody> {
+    let len = buf.read_u16::<BigEndian>()?;
+    let storage = buf.read_bytes(len as usize * 2)?;
+    Ok(FormatsBody { storage, len })
+}
+
+#[inline]
+fn read_values(buf: &mut Buffer) -> io::Result<ValuesBody> {
+    let len = buf.read_u16::<BigEndian>()?;
+
+    let mut slice = buf.slice();
+    let base = slice.len();
+    for _ in 0..len {
+        let value_len = slice.read_i32::<BigEndian>()?;
+        if value_len > 0 {
+            let value_len = value_len as usize;
+            if slice.len() < value_len {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "unexpected EOF",
+                ));
+            }
+            slice = &slice[value_len..];
+        }
+    }
+    let size = base - slice.len();
+
+    let storage = buf.read_bytes(size)?;
+    Ok(ValuesBody { storage, len })
+}
+
+/// An enum representing the untagged packet a client opens a connection with.
+#[non_exhaustive]
+pub enum InitialMessage {
+    Startup(StartupMessageBody),
+    SslRequest,
+    GssEncRequest,
+    CancelRequest(CancelRequestBody),
+}
+
+impl InitialMessage {
+    #[inline]
+    pub fn parse(buf: &mut BytesMut) -> io::Result<Option<InitialMessage>> {
+        if buf.len() < 4 {
+            let to_read = 4 - buf.len();
+            buf.reserve(to_read);
+            return Ok(None);
+        }
+
+        let len = (&buf[..4]).read_u32::<BigEndian>().unwrap();
+
+        if len < 8 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid message length: startup packet length < 8",
+            ));
+        }
+
+        let total_len = len as usize;
+        if buf.len() < total_len {
+            let to_read = total_len - buf.len();
+            buf.reserve(to_read);
+            return Ok(None);
+        }
+
+        let mut buf = Buffer {
+            bytes: buf.split_to(total_len).freeze(),
+            idx: 4,
+        };
+
+        let message = match buf.read_i32::<BigEndian>()? {
+            CANCEL_REQUEST_CODE => {
+                let process_id = buf.read_i32::<BigEndian>()?;
+                let secret_key = buf.read_all();
+                if secret_key.len() < 4 || secret_key.len() > 256 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "invalid secret key length",
+                    ));
+                }
+                InitialMessage::CancelRequest(CancelRequestBody {
+                    process_id,
+                    secret_key,
+                })
+            }
+            SSL_REQUEST_CODE => InitialMessage::SslRequest,
+            GSS_ENC_REQUEST_CODE => InitialMessage::GssEncRequest,
+            version => {
+                let mut storage = buf.read_all();
+                if storage.last() != Some(&0) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "invalid startup message: missing terminator",
+                    ));
+                }
+                storage.truncate(storage.len() - 1);
+                InitialMessage::Startup(StartupMessageBody { version, storage })
+            }
+        };
+
+        if !buf.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid message length: expected buffer to be empty",
+            ));
+        }
+
+        Ok(Some(message))
+    }
+}
+
+pub struct StartupMessageBody {
+    version: i32,
+    storage: Bytes,
+}
+
+impl StartupMessageBody {
+    /// Returns the requested protocol version, with the major version in the high 16 bits and the minor version in the
+    /// low 16 bits.
+    #[inline]
+    pub fn version(&self) -> i32 {
+        self.version
+    }
+
+    #[inline]
+    pub fn parameters(&self) -> StartupParameters<'_> {
+        StartupParameters { buf: &self.storage }
+    }
+}
+
+pub struct StartupParameters<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> FallibleIterator for StartupParameters<'a> {
+    type Item = (&'a str, &'a str);
+    type Error = io::Error;
+
+    #[inline]
+    fn next(&mut self) -> io::Result<Option<(&'a str, &'a str)>> {
+        if self.buf.is_empty() {
+            return Ok(None);
+        }
+
+        let name_end = find_null(self.buf, 0)?;
+        let value_end = find_null(self.buf, name_end + 1)?;
+        let name = get_str(&self.buf[..name_end])?;
+        let value = get_str(&self.buf[name_end + 1..value_end])?;
+        self.buf = &self.buf[value_end + 1..];
+
+        Ok(Some((name, value)))
+    }
+}
+
+pub struct CancelRequestBody {
+    process_id: i32,
+    secret_key: Bytes,
+}
+
+impl CancelRequestBody {
+    #[inline]
+    pub fn process_id(&self) -> i32 {
+        self.process_id
+    }
+
+    /// Returns the first four bytes of the secret key as an integer.
+    ///
+    /// This is the entire key in protocol versions before 3.2, which may use longer keys. Use `secret_key_bytes` to
+    /// access the full key.
+    #[inline]
+    pub fn secret_key(&self) -> i32 {
+        BigEndian::read_i32(&self.secret_key)
+    }
+
+    /// Returns the secret key.
+    #[inline]
+    pub fn secret_key_bytes(&self) -> &[u8] {
+        &self.secret_key
+    }
+}
+
+struct FormatsBody {
+    storage: Bytes,
+    len: u16,
+}
+
+impl FormatsBody {
+    #[inline]
+    fn iter(&self) -> Formats<'_> {
+        Formats {
+            buf: &self.storage,
+            remaining: self.len,
+        }
+    }
+}
+
+pub struct Formats<'a> {
+    buf: &'a [u8],
+    remaining: u16,
+}
+
+impl<'a> FallibleIterator for Formats<'a> {
+    type Item = i16;
+    type Error = io::Error;
+
+    #[inline]
+    fn next(&mut self) -> io::Result<Option<i16>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+
+        self.remaining -= 1;
+        self.buf.read_i16::<BigEndian>().map(Some)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.remaining as usize;
+        (len, Some(len))
+    }
+}
+
+struct ValuesBody {
+    storage: Bytes,
+    len: u16,
+}
+
+impl ValuesBody {
+    #[inline]
+    fn iter(&self) -> Values<'_> {
+        Values {
+            buf: &self.storage,
+            remaining: self.len,
+        }
+    }
+}
+
+pub struct Values<'a> {
+    buf: &'a [u8],
+    remaining: u16,
+}
+
+impl<'a> FallibleIterator for Values<'a> {
+    type Item = Option<&'a [u8]>;
+    type Error = io::Error;
+
+    #[inline]
+    fn next(&mut self) -> io::Result<Option<Option<&'a [u8]>>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+
+        self.remaining -= 1;
+        let len = self.buf.read_i32::<BigEndian>()?;
+        if len < 0 {
+            return Ok(Some(None));
+        }
+
+        let len = len as usize;
+        if self.buf.len() < len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "unexpected EOF",
+            ));
+        }
+        let (value, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(Some(Some(value)))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.remaining as usize;
+        (len, Some(len))
+    }
+}
+
+pub struct BindBody {
+    portal: Bytes,
+    statement: Bytes,
+    formats: FormatsBody,
+    values: ValuesBody,
+    result_formats: FormatsBody,
+}
+
+impl BindBody {
+    #[inline]
+    pub fn portal(&self) -> io::Result<&str> {
+        get_str(&self.portal)
+    }
+
+    #[inline]
+    pub fn statement(&self) -> io::Result<&str> {
+        get_str(&self.statement)
+    }
+
+    #[inline]
+    pub fn formats(&self) -> Formats<'_> {
+        self.formats.iter()
+    }
+
+    #[inline]
+    pub fn values(&self) -> Values<'_> {
+        self.values.iter()
+    }
+
+    #[inline]
+    pub fn result_formats(&self) -> Formats<'_> {
+        self.result_formats.iter()
+    }
+}
+
+pub struct CloseBody {
+    variant: u8,
+    name: Bytes,
+}
+
+impl CloseBody {
+    #[inline]
+    pub fn variant(&self) -> u8 {
+        self.variant
+    }
+
+    #[inline]
+    pub fn name(&self) -> io::Result<&str> {
+        get_str(&self.name)
+    }
+}
+
+pub struct CopyDataBody {
+    storage: Bytes,
+}
+
+impl CopyDataBody {
+    #[inline]
+    pub fn data(&self) -> &[u8] {
+        &self.storage
+    }
+
+    #[inline]
+    pub fn into_bytes(self) -> Bytes {
+        self.storage
+    }
+}
+
+pub struct CopyFailBody {
+    message: Bytes,
+}
+
+impl CopyFailBody {
+    #[inline]
+    pub fn message(&self) -> io::Result<&str> {
+        get_str(&self.message)
+    }
+}
+
+pub struct DescribeBody {
+    variant: u8,
+    name: Bytes,
+}
+
+impl DescribeBody {
+    #[inline]
+    pub fn variant(&self) -> u8 {
+        self.variant
+    }
+
+    #[inline]
+    pub fn name(&self) -> io::Result<&str> {
+        get_str(&self.name)
+    }
+}
+
+pub struct ExecuteBody {
+    portal: Bytes,
+    max_rows: i32,
+}
+
+impl ExecuteBody {
+    #[inline]
+    pub fn portal(&self) -> io::Result<&str> {
+        get_str(&self.portal)
+    }
+
+    #[inline]
+    pub fn max_rows(&self) -> i32 {
+        self.max_rows
+    }
+}
+
+pub struct FunctionCallBody {
+    function: Oid,
+    formats: FormatsBody,
+    values: ValuesBody,
+    result_format: i16,
+}
+
+impl FunctionCallBody {
+    #[inline]
+    pub fn function(&self) -> Oid {
+        self.function
+    }
+
+    #[inline]
+    pub fn formats(&self) -> Formats<'_> {
+        self.formats.iter()
+    }
+
+    #[inline]
+    pub fn values(&self) -> Values<'_> {
+        self.values.iter()
+    }
+
+    #[inline]
+    pub fn result_format(&self) -> i16 {
+        self.result_format
+    }
+}
+
+pub struct ParseBody {
+    name: Bytes,
+    query: Bytes,
+    storage: Bytes,
+    len: u16,
+}
+
+impl ParseBody {
+    #[inline]
+    pub fn name(&self) -> io::Result<&str> {
+        get_str(&self.name)
+    }
+
+    #[inline]
+    pub fn query(&self) -> io::Result<&str> {
+        get_str(&self.query)
+    }
+
+    #[inline]
+    pub fn parameter_types(&self) -> ParameterTypes<'_> {
+        ParameterTypes {
+            buf: &self.storage,
+            remaining: self.len,
+        }
+    }
+}
+
+pub struct ParameterTypes<'a> {
+    buf: &'a [u8],
+    remaining: u16,
+}
+
+impl<'a> FallibleIterator for ParameterTypes<'a> {
+    type Item = Oid;
+    type Error = io::Error;
+
+    #[inline]
+    fn next(&mut self) -> io::Result<Option<Oid>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+
+        self.remaining -= 1;
+        self.buf.read_u32::<BigEndian>().map(Some)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.remaining as usize;
+        (len, Some(len))
+    }
+}
+
+/// The body of a `p` message.
+///
+/// The same tag is used for password, SASL initial response, and SASL response messages, so the body has to be
+/// interpreted based on the authentication method the server requested.
+pub struct PasswordMessageBody {
+    storage: Bytes,
+}
+
+impl PasswordMessageBody {
+    /// Interprets the body as a cleartext or MD5-hashed password.
+    #[inline]
+    pub fn password(&self) -> io::Result<&[u8]> {
+        match self.storage.split_last() {
+            Some((0, password)) if !password.contains(&0) => Ok(password),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid password message",
+            )),
+        }
+    }
+
+    /// Interprets the body as a SASL initial response.
+    #[inline]
+    pub fn sasl_initial_response(&self) -> io::Result<SaslInitialResponse<'_>> {
+        let mut buf = &self.storage[..];
+        let mechanism_end = find_null(buf, 0)?;
+        let mechanism = get_str(&buf[..mechanism_end])?;
+        buf = &buf[mechanism_end + 1..];
+
+        let len = buf.read_i32::<BigEndian>()?;
+        let data = if len < 0 { None } else { Some(buf) };
+        if buf.len() != data.map_or(0, |_| len as usize) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid message length: expected buffer to be empty",
+            ));
+        }
+
+        Ok(SaslInitialResponse { mechanism, data })
+    }
+
+    /// Interprets the body as a SASL response.
+    #[inline]
+    pub fn sasl_response(&self) -> &[u8] {
+        &self.storage
+    }
+}
+
+pub struct SaslInitialResponse<'a> {
+    mechanism: &'a str,
+    data: Option<&'a [u8]>,
+}
+
+impl<'a> SaslInitialResponse<'a> {
+    #[inline]
+    pub fn mechanism(&self) -> &'a str {
+        self.mechanism
+    }
+
+    #[inline]
+    pub fn data(&self) -> Option<&'a [u8]> {
+        self.data
+    }
+}
+
+pub struct QueryBody {
+    query: Bytes,
+}
+
+impl QueryBody {
+    #[inline]
+    pub fn query(&self) -> io::Result<&str> {
+        get_str(&self.query)
+    }
+}
@@ -5,4 +5,8 @@
 //! [docs]: https://www.postgresql.org/docs/9.5/static/protocol-flow.html
 
 pub mod backend;
+pub mod framing;
 pub mod frontend;
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,468 @@
+use bytes::{Buf, BufMut, BytesMut};
+use fallible_iterator::FallibleIterator;
+
+use crate::message::backend::{self, Field};
+use crate::message::framing::MessageFrames;
+use crate::message::frontend::{self, InitialMessage};
+use crate::IsNull;
+
+#[test]
+fn parse_extended_query() {
+    let mut buf = BytesMut::new();
+    frontend::parse("s", "SELECT $1, $2", [23, 25], &mut buf).unwrap();
+    frontend::bind(
+        "p",
+        "s",
+        [1],
+        [Some(&b"\0\0\0\x01"[..]), None],
+        |v, buf| match v {
+            Some(v) => {
+                buf.put_slice(v);
+                Ok(IsNull::No)
+            }
+            None => Ok(IsNull::Yes),
+        },
+        [0, 1],
+        &mut buf,
+    )
+    .map_err(|_| ())
+    .unwrap();
+    frontend::describe(b'P', "p", &mut buf).unwrap();
+    frontend::execute("p", 10, &mut buf).unwrap();
+    frontend::sync(&mut buf);
+
+    match frontend::Message::parse(&mut buf).unwrap() {
+        Some(frontend::Message::Parse(body)) => {
+            assert_eq!(body.name().unwrap(), "s");
+            assert_eq!(body.query().unwrap(), "SELECT $1, $2");
+            assert_eq!(
+                body.parameter_types().collect::<Vec<_>>().unwrap(),
+                [23, 25]
+            );
+        }
+        _ => panic!("expected Parse"),
+    }
+    match frontend::Message::parse(&mut buf).unwrap() {
+        Some(frontend::Message::Bind(body)) => {
+            assert_eq!(body.portal().unwrap(), "p");
+            assert_eq!(body.statement().unwrap(), "s");
+            assert_eq!(body.formats().collect::<Vec<_>>().unwrap(), [1]);
+            assert_eq!(
+                body.values().collect::<Vec<_>>().unwrap(),
+                [Some(&b"\0\0\0\x01"[..]), None]
+            );
+            assert_eq!(body.result_formats().collect::<Vec<_>>().unwrap(), [0, 1]);
+        }
+        _ => panic!("expected Bind"),
+    }
+    match frontend::Message::parse(&mut buf).unwrap() {
+        Some(frontend::Message::Describe(body)) => {
+            assert_eq!(body.variant(), b'P');
+            assert_eq!(body.name().unwrap(), "p");
+        }
+        _ => panic!("expected Describe"),
+    }
+    match frontend::Message::parse(&mut buf).unwrap() {
+        Some(frontend::Message::Execute(body)) => {
+            assert_eq!(body.portal().unwrap(), "p");
+            assert_eq!(body.max_rows(), 10);
+        }
+        _ => panic!("expected Execute"),
+    }
+    assert!(matches!(
+        frontend::Message::parse(&mut buf).unwrap(),
+        Some(frontend::Message::Sync)
+    ));
+    assert!(frontend::Message::parse(&mut buf).unwrap().is_none());
+}
+
+#[test]
+fn parse_over_i16_max_parameters() {
+    let types = vec![23; 40_000];
+    let mut buf = BytesMut::new();
+    frontend::parse("", "", types.iter().copied(), &mut buf).unwrap();
+
+    match frontend::Message::parse(&mut buf).unwrap() {
+        Some(frontend::Message::Parse(body)) => {
+            assert_eq!(body.parameter_types().collect::<Vec<_>>().unwrap(), types);
+        }
+        _ => panic!("unexpected message"),
+    }
+
+    let mut buf = BytesMut::new();
+    frontend::parse("", "", vec![23; 70_000], &mut buf).unwrap_err();
+}
+
+#[test]
+fn parse_partial_frontend_message() {
+    let mut full = BytesMut::new();
+    frontend::query("SELECT 1", &mut full).unwrap();
+
+    let mut buf = BytesMut::from(&full[..7]);
+    assert!(frontend::Message::parse(&mut buf).unwrap().is_none());
+    buf.extend_from_slice(&full[7..]);
+    match frontend::Message::parse(&mut buf).unwrap() {
+        Some(frontend::Message::Query(body)) => assert_eq!(body.query().unwrap(), "SELECT 1"),
+        _ => panic!("expected Query"),
+    }
+}
+
+#[test]
+fn parse_password_messages() {
+    let mut buf = BytesMut::new();
+    frontend::password_message(b"hunter2", &mut buf).unwrap();
+    frontend::sasl_initial_response("SCRAM-SHA-256", b"n,,n=,r=abc", &mut buf).unwrap();
+    frontend::sasl_response(b"c=biws", &mut buf).unwrap();
+
+    match frontend::Message::parse(&mut buf).unwrap() {
+        Some(frontend::Message::PasswordMessage(body)) => {
+            assert_eq!(body.password().unwrap(), b"hunter2")
+        }
+        _ => panic!("expected PasswordMessage"),
+    }
+    match frontend::Message::parse(&mut buf).unwrap() {
+        Some(frontend::Message::PasswordMessage(body)) => {
+            let response = body.sasl_initial_response().unwrap();
+            assert_eq!(response.mechanism(), "SCRAM-SHA-256");
+            assert_eq!(response.data(), Some(&b"n,,n=,r=abc"[..]));
+        }
+        _ => panic!("expected PasswordMessage"),
+    }
+    match frontend::Message::parse(&mut buf).unwrap() {
+        Some(frontend::Message::PasswordMessage(body)) => {
+            assert_eq!(body.sasl_response(), b"c=biws");
+            assert!(body.password().is_err());
+        }
+        _ => panic!("expected PasswordMessage"),
+    }
+}
+
+#[test]
+fn parse_initial_messages() {
+    let mut buf = BytesMut::new();
+    frontend::ssl_request(&mut buf);
+    frontend::startup_message([("user", "postgres"), ("database", "db")], &mut buf).unwrap();
+    frontend::cancel_request(1, 2, &mut buf);
+
+    assert!(matches!(
+        InitialMessage::parse(&mut buf).unwrap(),
+        Some(InitialMessage::SslRequest)
+    ));
+    match InitialMessage::parse(&mut buf).unwrap() {
+        Some(InitialMessage::Startup(body)) => {
+            assert_eq!(body.version(), 0x00_03_00_00);
+            assert_eq!(
+                body.parameters().collect::<Vec<_>>().unwrap(),
+                [("user", "postgres"), ("database", "db")]
+            );
+        }
+        _ => panic!("expected Startup"),
+    }
+    match InitialMessage::parse(&mut buf).unwrap() {
+        Some(InitialMessage::CancelRequest(body)) => {
+            assert_eq!(body.process_id(), 1);
+            assert_eq!(body.secret_key(), 2);
+        }
+        _ => panic!("expected CancelRequest"),
+    }
+    assert!(InitialMessage::parse(&mut buf).unwrap().is_none());
+}
+
+#[test]
+fn serialize_query_response() {
+    let mut buf = BytesMut::new();
+    backend::row_description(
+        [
+            Field::new("id", 0, 0, 23, 4, -1, 0),
+            Field::new("name", 16384, 2, 25, -1, -1, 0),
+        ],
+        &mut buf,
+    )
+    .unwrap();
+    backend::data_row([Some("1"), None], &mut buf).unwrap();
+    backend::command_complete("SELECT 1", &mut buf).unwrap();
+    backend::ready_for_query(b'I', &mut buf);
+
+    match backend::Message::parse(&mut buf).unwrap() {
+        Some(backend::Message::RowDescription(body)) => {
+            let fields = body
+                .fields()
+                .map(|f| Ok((f.name(), f.table_oid(), f.column_id(), f.type_oid())))
+                .collect::<Vec<_>>()
+                .unwrap();
+            assert_eq!(fields, [("id", 0, 0, 23), ("name", 16384, 2, 25)]);
+        }
+        _ => panic!("expected RowDescription"),
+    }
+    match backend::Message::parse(&mut buf).unwrap() {
+        Some(backend::Message::DataRow(body)) => {
+            let values = body
+                .ranges()
+                .map(|r| Ok(r.map(|r| &body.buffer()[r])))
+                .collect::<Vec<_>>()
+                .unwrap();
+            assert_eq!(values, [Some(&b"1"[..]), None]);
+        }
+        _ => panic!("expected DataRow"),
+    }
+    match backend::Message::parse(&mut buf).unwrap() {
+        Some(backend::Message::CommandComplete(body)) => {
+            assert_eq!(body.tag().unwrap(), "SELECT 1")
+        }
+        _ => panic!("expected CommandComplete"),
+    }
+    match backend::Message::parse(&mut buf).unwrap() {
+        Some(backend::Message::ReadyForQuery(body)) => assert_eq!(body.status(), b'I'),
+        _ => panic!("expected ReadyForQuery"),
+    }
+    assert!(buf.is_empty());
+}
+
+#[test]
+fn serialize_authentication() {
+    let mut buf = BytesMut::new();
+    backend::authentication_sasl(["SCRAM-SHA-256-PLUS", "SCRAM-SHA-256"], &mut buf).unwrap();
+    backend::authentication_sasl_final(b"v=abc", &mut buf).unwrap();
+    backend::authentication_ok(&mut buf);
+    backend::parameter_status("server_version", "14.1", &mut buf).unwrap();
+    backend::backend_key_data(1, 2, &mut buf);
+
+    match backend::Message::parse(&mut buf).unwrap() {
+        Some(backend::Message::AuthenticationSasl(body)) => assert_eq!(
+            body.mechanisms().collect::<Vec<_>>().unwrap(),
+            ["SCRAM-SHA-256-PLUS", "SCRAM-SHA-256"]
+        ),
+        _ => panic!("expected AuthenticationSasl"),
+    }
+    match backend::Message::parse(&mut buf).unwrap() {
+        Some(backend::Message::AuthenticationSaslFinal(body)) => assert_eq!(body.data(), b"v=abc"),
+        _ => panic!("expected AuthenticationSaslFinal"),
+    }
+    assert!(matches!(
+        backend::Message::parse(&mut buf).unwrap(),
+        Some(backend::Message::AuthenticationOk)
+    ));
+    match backend::Message::parse(&mut buf).unwrap() {
+        Some(backend::Message::ParameterStatus(body)) => {
+            assert_eq!(body.name().unwrap(), "server_version");
+            assert_eq!(body.value().unwrap(), "14.1");
+        }
+        _ => panic!("expected ParameterStatus"),
+    }
+    match backend::Message::parse(&mut buf).unwrap() {
+        Some(backend::Message::BackendKeyData(body)) => {
+            assert_eq!(body.process_id(), 1);
+            assert_eq!(body.secret_key(), 2);
+        }
+        _ => panic!("expected BackendKeyData"),
+    }
+}
+
+#[test]
+fn serialize_error_response() {
+    let mut buf = BytesMut::new();
+    backend::error_response(
+        [
+            (b'S', "ERROR"),
+            (b'C', "42P01"),
+            (b'M', "relation does not exist"),
+        ],
+        &mut buf,
+    )
+    .unwrap();
+
+    match backend::Message::parse(&mut buf).unwrap() {
+        Some(backend::Message::ErrorResponse(body)) => {
+            let fields = body
+                .fields()
+                .map(|f| Ok((f.type_(), f.value().to_string())))
+                .collect::<Vec<_>>()
+                .unwrap();
+            assert_eq!(
+                fields,
+                [
+                    (b'S', "ERROR".to_string()),
+                    (b'C', "42P01".to_string()),
+                    (b'M', "relation does not exist".to_string()),
+                ]
+            );
+        }
+        _ => panic!("expected ErrorResponse"),
+    }
+
+    assert!(backend::error_response([(0, "oops")], &mut buf).is_err());
+}
+
+#[test]
+fn frames_over_chained_buffers() {
+    let mut buf = BytesMut::new();
+    backend::command_complete("SELECT 1", &mut buf).unwrap();
+    backend::ready_for_query(b'I', &mut buf);
+    let messages = buf.freeze();
+
+    // split inside of the first message's header
+    let mut chain = messages.slice(..3).chain(messages.slice(3..));
+    let mut frames = MessageFrames::new();
+
+    let frame = frames.decode(&mut chain).unwrap().unwrap();
+    assert_eq!(frame.tag(), Some(backend::COMMAND_COMPLETE_TAG));
+    assert_eq!(&frame.body()[..], b"SELECT 1\0");
+
+    let mut header = BytesMut::new();
+    frame.write_header(&mut header);
+    header.extend_from_slice(frame.body());
+    assert_eq!(header, messages[..header.len()]);
+
+    let frame = frames.decode(&mut chain).unwrap().unwrap();
+    assert_eq!(frame.tag(), Some(backend::READY_FOR_QUERY_TAG));
+    assert_eq!(&frame.body()[..], b"I");
+
+    assert!(frames.decode(&mut chain).unwrap().is_none());
+    assert!(!chain.has_remaining());
+}
+
+#[test]
+fn frames_across_reads() {
+    let mut message = BytesMut::new();
+    frontend::query("SELECT 1", &mut message).unwrap();
+
+    let mut frames = MessageFrames::new();
+    let mut buf = BytesMut::new();
+    let (last, rest) = message.split_last().unwrap();
+    for &byte in rest {
+        buf.put_u8(byte);
+        assert!(frames.decode(&mut buf).unwrap().is_none());
+    }
+    // the header has been consumed, while the body waits in the buffer until it is complete
+    assert_eq!(&buf[..], b"SELECT 1");
+
+    buf.put_u8(*last);
+    let frame = frames.decode(&mut buf).unwrap().unwrap();
+    assert_eq!(frame.tag(), Some(frontend::QUERY_TAG));
+    assert_eq!(&frame.body()[..], b"SELECT 1\0");
+    assert!(buf.is_empty());
+}
+
+#[test]
+fn frames_untagged() {
+    let mut buf = BytesMut::new();
+    frontend::ssl_request(&mut buf);
+    frontend::startup_message([("user", "postgres")], &mut buf).unwrap();
+
+    let mut frames = MessageFrames::untagged();
+    let frame = frames.decode(&mut buf).unwrap().unwrap();
+    assert_eq!(frame.tag(), None);
+    assert_eq!(frame.body().len(), 4);
+
+    let frame = frames.decode(&mut buf).unwrap().unwrap();
+    let mut message = BytesMut::new();
+    frame.write_header(&mut message);
+    message.extend_from_slice(frame.body());
+    match InitialMessage::parse(&mut message).unwrap() {
+        Some(InitialMessage::Startup(body)) => assert_eq!(
+            body.parameters().collect::<Vec<_>>().unwrap(),
+            [("user", "postgres")]
+        ),
+        _ => panic!("expected Startup"),
+    }
+}
+
+#[test]
+fn frames_max_message_length() {
+    let mut buf = BytesMut::new();
+    frontend::query("SELECT 1", &mut buf).unwrap();
+    buf.truncate(5);
+
+    let mut frames = MessageFrames::new();
+    frames.max_message_length(Some(8));
+    assert!(frames.decode(&mut buf).is_err());
+}
+
+#[test]
+fn construct_backend_bodies() {
+    let body =
+        backend::AuthenticationSaslBody::new(["SCRAM-SHA-256-PLUS", "SCRAM-SHA-256"]).unwrap();
+    assert_eq!(
+        body.mechanisms().collect::<Vec<_>>().unwrap(),
+        ["SCRAM-SHA-256-PLUS", "SCRAM-SHA-256"]
+    );
+
+    let body = backend::DataRowBody::new([Some(&b"1"[..]), None]).unwrap();
+    let values = body
+        .ranges()
+        .map(|r| Ok(r.map(|r| &body.buffer()[r])))
+        .collect::<Vec<_>>()
+        .unwrap();
+    assert_eq!(values, [Some(&b"1"[..]), None]);
+
+    let body = backend::ErrorResponseBody::new([(b'S', "ERROR"), (b'C', "42P01")]).unwrap();
+    let fields = body
+        .fields()
+        .map(|f| Ok((f.type_(), f.value().to_string())))
+        .collect::<Vec<_>>()
+        .unwrap();
+    assert_eq!(
+        fields,
+        [(b'S', "ERROR".to_string()), (b'C', "42P01".to_string())]
+    );
+
+    let body = backend::RowDescriptionBody::new([Field::new("id", 0, 0, 23, 4, -1, 0)]).unwrap();
+    let mut fields = body.fields();
+    assert_eq!(fields.next().unwrap().unwrap().type_oid(), 23);
+    assert!(fields.next().unwrap().is_none());
+
+    let body = backend::ParameterStatusBody::new("TimeZone", "UTC").unwrap();
+    assert_eq!(body.value().unwrap(), "UTC");
+
+    assert!(backend::CommandCompleteBody::new("SELECT\0").is_err());
+
+    let body =
+        backend::NegotiateProtocolVersionBody::new(0x00_03_00_00, ["_pq_.compression", "_pq_.foo"])
+            .unwrap();
+    assert_eq!(body.newest_version(), 0x00_03_00_00);
+    assert_eq!(
+        body.options().collect::<Vec<_>>().unwrap(),
+        ["_pq_.compression", "_pq_.foo"]
+    );
+
+    let body = backend::BackendKeyDataBody::new(1, 2);
+    assert_eq!(body.secret_key_bytes(), [0, 0, 0, 2]);
+}
+
+#[test]
+fn variable_length_secret_keys() {
+    let key = (0..32).collect::<Vec<u8>>();
+
+    let mut buf = BytesMut::new();
+    backend::backend_key_data_with_key(1, &key, &mut buf).unwrap();
+    match backend::Message::parse(&mut buf).unwrap() {
+        Some(backend::Message::BackendKeyData(body)) => {
+            assert_eq!(body.process_id(), 1);
+            assert_eq!(body.secret_key_bytes(), &key[..]);
+            assert_eq!(body.secret_key(), 0x00_01_02_03);
+        }
+        _ => panic!("expected BackendKeyData"),
+    }
+
+    frontend::cancel_request_with_key(1, &key, &mut buf).unwrap();
+    match InitialMessage::parse(&mut buf).unwrap() {
+        Some(InitialMessage::CancelRequest(body)) => {
+            assert_eq!(body.secret_key_bytes(), &key[..]);
+        }
+        _ => panic!("expected CancelRequest"),
+    }
+
+    backend::backend_key_data_with_key(1, &[0; 3], &mut buf).unwrap();
+    assert!(backend::Message::parse(&mut buf).is_err());
+}
+
+#[test]
+fn parse_truncate_with_oversized_count() {
+    let mut buf = BytesMut::new();
+    buf.put_u8(b'T');
+    buf.put_i32(i32::MAX);
+    buf.put_i8(0);
+    buf.put_u32(1);
+
+    assert!(backend::LogicalReplicationMessage::parse(&buf.freeze(), false).is_err());
+}
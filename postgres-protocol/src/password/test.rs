@@ -10,6 +10,17 @@ fn test_encrypt_scram_sha_256() {
     );
 }
 
+#[test]
+fn test_encrypt_scram_sha_256_with_salt() {
+    let salt: [u8; 16] = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+    assert_eq!(
+        password::scram_sha_256_with_salt(b"secret", &salt, 4096),
+        password::scram_sha_256_salt(b"secret", salt)
+    );
+    assert!(password::scram_sha_256_with_salt(b"secret", b"salt", 1)
+        .starts_with("SCRAM-SHA-256$1:c2FsdA==$"));
+}
+
 #[test]
 fn test_encrypt_md5() {
     assert_eq!(
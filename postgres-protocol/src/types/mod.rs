@@ -10,6 +10,8 @@ use std::str;
 
 use crate::{write_nullable, FromUsize, IsNull, Lsn, Oid};
 
+pub mod text;
+
 #[cfg(test)]
 mod test;
 
@@ -421,6 +423,61 @@ pub fn time_from_sql(mut buf: &[u8]) -> Result<i64, StdBox<dyn Error + Sync + Se
     Ok(v)
 }
 
+/// Serializes an `INTERVAL` value.
+///
+/// The value is represented as separate counts of microseconds, days, and months, as the lengths of days and months
+/// vary.
+#[inline]
+pub fn interval_to_sql(microseconds: i64, days: i32, months: i32, buf: &mut BytesMut) {
+    buf.put_i64(microseconds);
+    buf.put_i32(days);
+    buf.put_i32(months);
+}
+
+/// Deserializes an `INTERVAL` value.
+#[inline]
+pub fn interval_from_sql(mut buf: &[u8]) -> Result<Interval, StdBox<dyn Error + Sync + Send>> {
+    let microseconds = buf.read_i64::<BigEndian>()?;
+    let days = buf.read_i32::<BigEndian>()?;
+    let months = buf.read_i32::<BigEndian>()?;
+    if !buf.is_empty() {
+        return Err("invalid message length: interval not drained".into());
+    }
+    Ok(Interval {
+        microseconds,
+        days,
+        months,
+    })
+}
+
+/// A Postgres interval.
+#[derive(Copy, Clone)]
+pub struct Interval {
+    microseconds: i64,
+    days: i32,
+    months: i32,
+}
+
+impl Interval {
+    /// Returns the microseconds component of the interval.
+    #[inline]
+    pub fn microseconds(&self) -> i64 {
+        self.microseconds
+    }
+
+    /// Returns the days component of the interval.
+    #[inline]
+    pub fn days(&self) -> i32 {
+        self.days
+    }
+
+    /// Returns the months component of the interval.
+    #[inline]
+    pub fn months(&self) -> i32 {
+        self.months
+    }
+}
+
 /// Serializes a `MACADDR` value.
 #[inline]
 pub fn macaddr_to_sql(v: [u8; 6], buf: &mut BytesMut) {
@@ -725,6 +782,7 @@ where
 }
 
 /// One side of a range.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum RangeBound<T> {
     /// An inclusive bound.
     Inclusive(T),
@@ -795,6 +853,127 @@ pub enum Range<'a> {
     Nonempty(RangeBound<Option<&'a [u8]>>, RangeBound<Option<&'a [u8]>>),
 }
 
+/// Serializes a record value.
+///
+/// Each field is made up of the OID of its type and a value which is written by the serializer.
+#[inline]
+pub fn record_to_sql<T, I, F>(
+    fields: I,
+    mut serializer: F,
+    buf: &mut BytesMut,
+) -> Result<(), StdBox<dyn Error + Sync + Send>>
+where
+    I: IntoIterator<Item = (Oid, T)>,
+    F: FnMut(T, &mut BytesMut) -> Result<IsNull, StdBox<dyn Error + Sync + Send>>,
+{
+    let num_fields_idx = buf.len();
+    buf.put_i32(0);
+
+    let mut num_fields = 0;
+    for (type_oid, value) in fields {
+        num_fields += 1;
+        buf.put_u32(type_oid);
+        write_nullable(|buf| serializer(value, buf), buf)?;
+    }
+
+    let num_fields = i32::from_usize(num_fields)?;
+    BigEndian::write_i32(&mut buf[num_fields_idx..], num_fields);
+
+    Ok(())
+}
+
+/// Deserializes a record value.
+#[inline]
+pub fn record_from_sql(mut buf: &[u8]) -> Result<Record<'_>, StdBox<dyn Error + Sync + Send>> {
+    let num_fields = buf.read_i32::<BigEndian>()?;
+    if num_fields < 0 {
+        return Err("invalid field count".into());
+    }
+
+    Ok(Record { num_fields, buf })
+}
+
+/// A Postgres record, the binary representation shared by anonymous records and composite types.
+pub struct Record<'a> {
+    num_fields: i32,
+    buf: &'a [u8],
+}
+
+impl<'a> Record<'a> {
+    /// Returns an iterator over the fields of the record.
+    #[inline]
+    pub fn fields(&self) -> RecordFields<'a> {
+        RecordFields {
+            remaining: self.num_fields,
+            buf: self.buf,
+        }
+    }
+}
+
+/// An iterator over the fields of a record.
+pub struct RecordFields<'a> {
+    remaining: i32,
+    buf: &'a [u8],
+}
+
+impl<'a> FallibleIterator for RecordFields<'a> {
+    type Item = RecordField<'a>;
+    type Error = StdBox<dyn Error + Sync + Send>;
+
+    #[inline]
+    fn next(&mut self) -> Result<Option<RecordField<'a>>, StdBox<dyn Error + Sync + Send>> {
+        if self.remaining == 0 {
+            if !self.buf.is_empty() {
+                return Err("invalid message length: record fields not drained".into());
+            }
+            return Ok(None);
+        }
+        self.remaining -= 1;
+
+        let type_oid = self.buf.read_u32::<BigEndian>()?;
+        let len = self.buf.read_i32::<BigEndian>()?;
+        let value = if len < 0 {
+            None
+        } else {
+            if self.buf.len() < len as usize {
+                return Err("invalid value length".into());
+            }
+
+            let (value, buf) = self.buf.split_at(len as usize);
+            self.buf = buf;
+            Some(value)
+        };
+
+        Ok(Some(RecordField { type_oid, value }))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.remaining as usize;
+        (len, Some(len))
+    }
+}
+
+/// A field of a record.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct RecordField<'a> {
+    type_oid: Oid,
+    value: Option<&'a [u8]>,
+}
+
+impl<'a> RecordField<'a> {
+    /// Returns the OID of the field's type.
+    #[inline]
+    pub fn type_oid(&self) -> Oid {
+        self.type_oid
+    }
+
+    /// Returns the field's value, or `None` if it is `NULL`.
+    #[inline]
+    pub fn value(&self) -> Option<&'a [u8]> {
+        self.value
+    }
+}
+
 /// Serializes a point value.
 #[inline]
 pub fn point_to_sql(x: f64, y: f64, buf: &mut BytesMut) {
@@ -54,6 +54,18 @@ fn float8() {
     assert_eq!(float8_from_sql(&buf).unwrap(), 10343.95);
 }
 
+#[test]
+fn interval() {
+    let mut buf = BytesMut::new();
+    interval_to_sql(-1_500_000, 3, 14, &mut buf);
+    let interval = interval_from_sql(&buf).unwrap();
+    assert_eq!(interval.microseconds(), -1_500_000);
+    assert_eq!(interval.days(), 3);
+    assert_eq!(interval.months(), 14);
+
+    assert!(interval_from_sql(&buf[..12]).is_err());
+}
+
 #[test]
 fn hstore() {
     let mut map = HashMap::new();
@@ -156,3 +168,283 @@ fn non_null_array() {
     assert_eq!(array.dimensions().collect::<Vec<_>>().unwrap(), dimensions);
     assert_eq!(array.values().collect::<Vec<_>>().unwrap(), values);
 }
+
+#[test]
+fn record() {
+    let fields = [
+        (23, Some(&b"\0\0\0\x01"[..])),
+        (25, None),
+        (25, Some(&b"hi"[..])),
+    ];
+
+    let mut buf = BytesMut::new();
+    record_to_sql(
+        fields.iter().cloned(),
+        |v, buf| match v {
+            Some(v) => {
+                buf.extend_from_slice(v);
+                Ok(IsNull::No)
+            }
+            None => Ok(IsNull::Yes),
+        },
+        &mut buf,
+    )
+    .unwrap();
+
+    let record = record_from_sql(&buf).unwrap();
+    let parsed = record
+        .fields()
+        .map(|f| Ok((f.type_oid(), f.value())))
+        .collect::<Vec<_>>()
+        .unwrap();
+    assert_eq!(parsed, fields);
+}
+
+#[test]
+fn range() {
+    let mut buf = BytesMut::new();
+    range_to_sql(
+        |buf| {
+            int4_to_sql(1, buf);
+            Ok(RangeBound::Inclusive(IsNull::No))
+        },
+        |_| Ok(RangeBound::Unbounded),
+        &mut buf,
+    )
+    .unwrap();
+
+    match range_from_sql(&buf).unwrap() {
+        Range::Nonempty(RangeBound::Inclusive(Some(lower)), RangeBound::Unbounded) => {
+            assert_eq!(int4_from_sql(lower).unwrap(), 1)
+        }
+        _ => panic!("unexpected range"),
+    }
+
+    let mut buf = BytesMut::new();
+    empty_range_to_sql(&mut buf);
+    assert!(matches!(range_from_sql(&buf).unwrap(), Range::Empty));
+}
+
+#[test]
+fn text_scalars() {
+    let mut buf = BytesMut::new();
+    text::bool_to_sql(true, &mut buf);
+    assert_eq!(&buf[..], b"t");
+    assert!(text::bool_from_sql(b"f").is_ok());
+    assert!(text::bool_from_sql(b"yes").is_err());
+
+    let mut buf = BytesMut::new();
+    text::int8_to_sql(-42, &mut buf);
+    assert_eq!(text::int8_from_sql(&buf).unwrap(), -42);
+
+    let mut buf = BytesMut::new();
+    text::float8_to_sql(f64::NEG_INFINITY, &mut buf);
+    assert_eq!(&buf[..], b"-Infinity");
+    assert!(text::float8_from_sql(b"NaN").unwrap().is_nan());
+    assert_eq!(text::float4_from_sql(b"1.5e+30").unwrap(), 1.5e30);
+
+    let mut buf = BytesMut::new();
+    text::bytea_to_sql(&[0, 0xff], &mut buf);
+    assert_eq!(&buf[..], b"\\x00ff");
+    assert_eq!(text::bytea_from_sql(&buf).unwrap(), [0, 0xff]);
+    assert_eq!(
+        text::bytea_from_sql(b"a\\\\b\\000\\377").unwrap(),
+        b"a\\b\0\xff"
+    );
+}
+
+#[test]
+fn text_numeric() {
+    assert_eq!(
+        text::numeric_from_sql(b"-12.5e+3").unwrap(),
+        text::Numeric::Finite("-12.5e+3")
+    );
+    assert_eq!(
+        text::numeric_from_sql(b"-Infinity").unwrap(),
+        text::Numeric::NegativeInfinity
+    );
+    assert!(text::numeric_from_sql(b"1.2.3").is_err());
+    assert!(text::numeric_from_sql(b"e5").is_err());
+
+    let mut buf = BytesMut::new();
+    text::numeric_to_sql(text::Numeric::NaN, &mut buf).unwrap();
+    assert_eq!(&buf[..], b"NaN");
+    assert!(text::numeric_to_sql(text::Numeric::Finite("abc"), &mut buf).is_err());
+}
+
+#[test]
+fn text_date_time() {
+    // values as formatted by the server
+    let cases: &[(&[u8], i64)] = &[
+        (b"2000-01-01 00:00:00", 0),
+        (b"1999-12-31 23:59:59.5", -500_000),
+        (b"2021-01-02 03:04:05.25", 662_871_845_250_000),
+        (b"0044-03-15 10:00:00 BC", -64_464_472_800_000_000),
+    ];
+    for &(s, v) in cases {
+        assert_eq!(text::timestamp_from_sql(s).unwrap(), v);
+        let mut buf = BytesMut::new();
+        text::timestamp_to_sql(v, &mut buf);
+        assert_eq!(&buf[..], s);
+    }
+
+    assert_eq!(
+        text::timestamptz_from_sql(b"2021-01-02 08:34:05.25+05:30").unwrap(),
+        662_871_845_250_000
+    );
+    assert_eq!(
+        text::timestamptz_from_sql(b"0044-03-15 15:53:28+05:53:28 BC").unwrap(),
+        -64_464_472_800_000_000
+    );
+    assert_eq!(text::timestamptz_from_sql(b"infinity").unwrap(), i64::MAX);
+    let mut buf = BytesMut::new();
+    text::timestamptz_to_sql(0, &mut buf);
+    assert_eq!(&buf[..], b"2000-01-01 00:00:00+00");
+    assert!(text::timestamptz_from_sql(b"2000-01-01 00:00:00").is_err());
+
+    assert_eq!(text::date_from_sql(b"0001-01-01 BC").unwrap(), -730_485);
+    let mut buf = BytesMut::new();
+    text::date_to_sql(-730_485, &mut buf);
+    assert_eq!(&buf[..], b"0001-01-01 BC");
+    assert_eq!(text::date_from_sql(b"-infinity").unwrap(), i32::MIN);
+    assert!(text::date_from_sql(b"2021-02-29").is_err());
+    assert!(text::date_from_sql(b"2020-02-29").is_ok());
+
+    assert_eq!(text::time_from_sql(b"12:00:00.25").unwrap(), 43_200_250_000);
+    let mut buf = BytesMut::new();
+    text::time_to_sql(43_200_250_000, &mut buf);
+    assert_eq!(&buf[..], b"12:00:00.25");
+    assert!(text::time_from_sql(b"12:60:00").is_err());
+}
+
+#[test]
+fn text_array() {
+    let s: &[u8] = br#"{"a b","","NULL",NULL,"x\"y","a,b","{","back\\slash"}"#;
+    let array = text::array_from_sql(s, b',').unwrap();
+    assert_eq!(
+        array.dimensions(),
+        [ArrayDimension {
+            len: 8,
+            lower_bound: 1
+        }]
+    );
+    let values = array
+        .values()
+        .iter()
+        .map(|v| v.as_deref())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        values,
+        [
+            Some("a b"),
+            Some(""),
+            Some("NULL"),
+            None,
+            Some("x\"y"),
+            Some("a,b"),
+            Some("{"),
+            Some("back\\slash")
+        ]
+    );
+
+    let mut buf = BytesMut::new();
+    text::array_to_sql(
+        array.dimensions().iter().cloned(),
+        array.values().iter().map(|v| v.as_deref()),
+        b',',
+        &mut buf,
+    )
+    .unwrap();
+    assert_eq!(&buf[..], s);
+
+    let array = text::array_from_sql(b"{{1,2},{3,4}}", b',').unwrap();
+    assert_eq!(array.dimensions().len(), 2);
+    assert_eq!(array.values().len(), 4);
+    let mut buf = BytesMut::new();
+    text::array_to_sql(
+        array.dimensions().iter().cloned(),
+        array.values().iter().map(|v| v.as_deref()),
+        b',',
+        &mut buf,
+    )
+    .unwrap();
+    assert_eq!(&buf[..], b"{{1,2},{3,4}}");
+
+    let array = text::array_from_sql(b"[2:3]={1,2}", b',').unwrap();
+    assert_eq!(
+        array.dimensions(),
+        [ArrayDimension {
+            len: 2,
+            lower_bound: 2
+        }]
+    );
+    let mut buf = BytesMut::new();
+    text::array_to_sql(
+        array.dimensions().iter().cloned(),
+        array.values().iter().map(|v| v.as_deref()),
+        b',',
+        &mut buf,
+    )
+    .unwrap();
+    assert_eq!(&buf[..], b"[2:3]={1,2}");
+
+    let array = text::array_from_sql(b"{}", b',').unwrap();
+    assert!(array.dimensions().is_empty());
+    assert!(array.values().is_empty());
+    let mut buf = BytesMut::new();
+    text::array_to_sql(vec![], Vec::<Option<&str>>::new(), b',', &mut buf).unwrap();
+    assert_eq!(&buf[..], b"{}");
+
+    let array = text::array_from_sql(b"{(0,1),(2,3);(4,5),(6,7)}", b';').unwrap();
+    assert_eq!(array.values().len(), 2);
+
+    assert!(text::array_from_sql(b"{{1,2},{3}}", b',').is_err());
+    assert!(text::array_from_sql(b"{1,{2}}", b',').is_err());
+    assert!(text::array_from_sql(b"[1:3]={1,2}", b',').is_err());
+    assert!(text::array_from_sql(b"{1,2", b',').is_err());
+}
+
+#[test]
+fn text_range() {
+    assert_eq!(text::range_from_sql(b"empty").unwrap(), text::Range::Empty);
+    assert_eq!(
+        text::range_from_sql(b"[1,5)").unwrap(),
+        text::Range::Nonempty(
+            RangeBound::Inclusive("1".into()),
+            RangeBound::Exclusive("5".into())
+        )
+    );
+    let s: &[u8] = br#"["2020-01-01 00:00:00",)"#;
+    assert_eq!(
+        text::range_from_sql(s).unwrap(),
+        text::Range::Nonempty(
+            RangeBound::Inclusive("2020-01-01 00:00:00".into()),
+            RangeBound::Unbounded
+        )
+    );
+    let mut buf = BytesMut::new();
+    text::range_to_sql(
+        RangeBound::Inclusive("2020-01-01 00:00:00"),
+        RangeBound::Unbounded,
+        &mut buf,
+    );
+    assert_eq!(&buf[..], s);
+
+    let mut buf = BytesMut::new();
+    text::range_to_sql(
+        RangeBound::Exclusive("a\"b"),
+        RangeBound::Inclusive(""),
+        &mut buf,
+    );
+    assert_eq!(&buf[..], br#"("a""b",""]"#);
+    assert_eq!(
+        text::range_from_sql(&buf).unwrap(),
+        text::Range::Nonempty(
+            RangeBound::Exclusive("a\"b".into()),
+            RangeBound::Inclusive("".into())
+        )
+    );
+
+    assert!(text::range_from_sql(b"[1,5").is_err());
+    assert!(text::range_from_sql(b"1,5]").is_err());
+}
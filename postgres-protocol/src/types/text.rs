@@ -0,0 +1,1057 @@
+//! Conversions to and from Postgres's text format.
+//!
+//! Values are in the text format when they are sent by the simple query protocol, or by the extended query protocol
+//! with format code 0. The functions here use the same representations as their binary format counterparts in the
+//! parent module. Date and time values are expected to use the `ISO` `DateStyle`, which is Postgres's default.
+use bytes::{BufMut, BytesMut};
+use std::borrow::Cow;
+use std::boxed::Box as StdBox;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt::Write;
+use std::str::{self, FromStr};
+
+use crate::types::{ArrayDimension, RangeBound};
+use crate::Oid;
+
+const USECS_PER_SEC: i64 = 1_000_000;
+const USECS_PER_DAY: i64 = 86_400 * USECS_PER_SEC;
+// the number of days between the Unix epoch and the Postgres epoch of 2000-01-01
+const POSTGRES_EPOCH_DAYS: i64 = 10_957;
+
+fn from_utf8(buf: &[u8]) -> Result<&str, StdBox<dyn Error + Sync + Send>> {
+    Ok(str::from_utf8(buf)?)
+}
+
+fn parse<T>(buf: &[u8]) -> Result<T, StdBox<dyn Error + Sync + Send>>
+where
+    T: FromStr,
+    T::Err: Error + Sync + Send + 'static,
+{
+    Ok(from_utf8(buf)?.parse()?)
+}
+
+/// Serializes a `BOOL` value.
+#[inline]
+pub fn bool_to_sql(v: bool, buf: &mut BytesMut) {
+    buf.put_u8(if v { b't' } else { b'f' });
+}
+
+/// Deserializes a `BOOL` value.
+#[inline]
+pub fn bool_from_sql(buf: &[u8]) -> Result<bool, StdBox<dyn Error + Sync + Send>> {
+    match buf {
+        b"t" => Ok(true),
+        b"f" => Ok(false),
+        _ => Err("invalid bool".into()),
+    }
+}
+
+/// Serializes a `BYTEA` value in the hex format.
+#[inline]
+pub fn bytea_to_sql(v: &[u8], buf: &mut BytesMut) {
+    buf.put_slice(b"\\x");
+    for b in v {
+        write!(buf, "{:02x}", b).unwrap();
+    }
+}
+
+/// Deserializes a `BYTEA` value in either the hex or the escape format.
+pub fn bytea_from_sql(buf: &[u8]) -> Result<Vec<u8>, StdBox<dyn Error + Sync + Send>> {
+    if let Some(hex) = buf.strip_prefix(b"\\x") {
+        fn nibble(c: u8) -> Result<u8, StdBox<dyn Error + Sync + Send>> {
+            match c {
+                b'0'..=b'9' => Ok(c - b'0'),
+                b'a'..=b'f' => Ok(c - b'a' + 10),
+                b'A'..=b'F' => Ok(c - b'A' + 10),
+                _ => Err("invalid hex digit".into()),
+            }
+        }
+
+        let mut out = Vec::with_capacity(hex.len() / 2);
+        let mut digits = hex.iter().filter(|c| !c.is_ascii_whitespace());
+        while let Some(&hi) = digits.next() {
+            let lo = *digits.next().ok_or("odd number of hex digits")?;
+            out.push(nibble(hi)? << 4 | nibble(lo)?);
+        }
+        return Ok(out);
+    }
+
+    let mut out = Vec::with_capacity(buf.len());
+    let mut it = buf.iter();
+    while let Some(&b) = it.next() {
+        if b != b'\\' {
+            out.push(b);
+            continue;
+        }
+
+        match it.as_slice() {
+            [b'\\', ..] => {
+                it.next();
+                out.push(b'\\');
+            }
+            [a @ b'0'..=b'3', b @ b'0'..=b'7', c @ b'0'..=b'7', ..] => {
+                out.push((a - b'0') << 6 | (b - b'0') << 3 | (c - b'0'));
+                it.nth(2);
+            }
+            _ => return Err("invalid bytea escape".into()),
+        }
+    }
+    Ok(out)
+}
+
+/// Serializes an `INT2` value.
+#[inline]
+pub fn int2_to_sql(v: i16, buf: &mut BytesMut) {
+    write!(buf, "{}", v).unwrap();
+}
+
+/// Deserializes an `INT2` value.
+#[inline]
+pub fn int2_from_sql(buf: &[u8]) -> Result<i16, StdBox<dyn Error + Sync + Send>> {
+    parse(buf)
+}
+
+/// Serializes an `INT4` value.
+#[inline]
+pub fn int4_to_sql(v: i32, buf: &mut BytesMut) {
+    write!(buf, "{}", v).unwrap();
+}
+
+/// Deserializes an `INT4` value.
+#[inline]
+pub fn int4_from_sql(buf: &[u8]) -> Result<i32, StdBox<dyn Error + Sync + Send>> {
+    parse(buf)
+}
+
+/// Serializes an `OID` value.
+#[inline]
+pub fn oid_to_sql(v: Oid, buf: &mut BytesMut) {
+    write!(buf, "{}", v).unwrap();
+}
+
+/// Deserializes an `OID` value.
+#[inline]
+pub fn oid_from_sql(buf: &[u8]) -> Result<Oid, StdBox<dyn Error + Sync + Send>> {
+    parse(buf)
+}
+
+/// Serializes an `INT8` value.
+#[inline]
+pub fn int8_to_sql(v: i64, buf: &mut BytesMut) {
+    write!(buf, "{}", v).unwrap();
+}
+
+/// Deserializes an `INT8` value.
+#[inline]
+pub fn int8_from_sql(buf: &[u8]) -> Result<i64, StdBox<dyn Error + Sync + Send>> {
+    parse(buf)
+}
+
+/// Serializes a `FLOAT4` value.
+#[inline]
+pub fn float4_to_sql(v: f32, buf: &mut BytesMut) {
+    float8_to_sql(f64::from(v), buf);
+}
+
+/// Deserializes a `FLOAT4` value.
+#[inline]
+pub fn float4_from_sql(buf: &[u8]) -> Result<f32, StdBox<dyn Error + Sync + Send>> {
+    parse(buf)
+}
+
+/// Serializes a `FLOAT8` value.
+#[inline]
+pub fn float8_to_sql(v: f64, buf: &mut BytesMut) {
+    if v.is_nan() {
+        buf.put_slice(b"NaN");
+    } else if v == f64::INFINITY {
+        buf.put_slice(b"Infinity");
+    } else if v == f64::NEG_INFINITY {
+        buf.put_slice(b"-Infinity");
+    } else {
+        write!(buf, "{}", v).unwrap();
+    }
+}
+
+/// Deserializes a `FLOAT8` value.
+#[inline]
+pub fn float8_from_sql(buf: &[u8]) -> Result<f64, StdBox<dyn Error + Sync + Send>> {
+    parse(buf)
+}
+
+/// A `NUMERIC` value.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Numeric<'a> {
+    /// A finite value, as a decimal number such as `-12.345`.
+    Finite(&'a str),
+    /// Not-a-number.
+    NaN,
+    /// Positive infinity.
+    Infinity,
+    /// Negative infinity.
+    NegativeInfinity,
+}
+
+fn is_decimal(s: &str) -> bool {
+    let s = s.strip_prefix(&['+', '-'][..]).unwrap_or(s);
+    let (mantissa, exponent) = match s.find(&['e', 'E'][..]) {
+        Some(i) => (&s[..i], Some(&s[i + 1..])),
+        None => (s, None),
+    };
+
+    let (int, frac) = match mantissa.find('.') {
+        Some(i) => (&mantissa[..i], &mantissa[i + 1..]),
+        None => (mantissa, ""),
+    };
+    let digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    if !digits(int) || !digits(frac) || int.len() + frac.len() == 0 {
+        return false;
+    }
+
+    match exponent {
+        Some(exponent) => {
+            let exponent = exponent.strip_prefix(&['+', '-'][..]).unwrap_or(exponent);
+            !exponent.is_empty() && digits(exponent)
+        }
+        None => true,
+    }
+}
+
+/// Serializes a `NUMERIC` value.
+#[inline]
+pub fn numeric_to_sql(
+    v: Numeric<'_>,
+    buf: &mut BytesMut,
+) -> Result<(), StdBox<dyn Error + Sync + Send>> {
+    let s = match v {
+        Numeric::Finite(s) if is_decimal(s) => s,
+        Numeric::Finite(_) => return Err("invalid numeric".into()),
+        Numeric::NaN => "NaN",
+        Numeric::Infinity => "Infinity",
+        Numeric::NegativeInfinity => "-Infinity",
+    };
+    buf.put_slice(s.as_bytes());
+    Ok(())
+}
+
+/// Deserializes a `NUMERIC` value.
+#[inline]
+pub fn numeric_from_sql(buf: &[u8]) -> Result<Numeric<'_>, StdBox<dyn Error + Sync + Send>> {
+    match from_utf8(buf)? {
+        "NaN" => Ok(Numeric::NaN),
+        "Infinity" => Ok(Numeric::Infinity),
+        "-Infinity" => Ok(Numeric::NegativeInfinity),
+        s if is_decimal(s) => Ok(Numeric::Finite(s)),
+        _ => Err("invalid numeric".into()),
+    }
+}
+
+// Converts a proleptic Gregorian calendar date to days since the Unix epoch, with years counted astronomically (1 BC
+// is year 0).
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+// The inverse of days_from_civil.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+fn parse_number(s: &str) -> Result<i64, StdBox<dyn Error + Sync + Send>> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return Err("invalid date/time field".into());
+    }
+    Ok(s.parse()?)
+}
+
+// Splits a string around the first occurrence of a delimiter.
+fn split_once(s: &str, delimiter: char) -> Option<(&str, &str)> {
+    let idx = s.find(delimiter)?;
+    Some((&s[..idx], &s[idx + delimiter.len_utf8()..]))
+}
+
+// Splits a trailing ` BC` era off of a date/time value.
+fn split_era(s: &str) -> (&str, bool) {
+    match s.strip_suffix(" BC") {
+        Some(s) => (s, true),
+        None => (s, false),
+    }
+}
+
+// Parses a `YYYY-MM-DD` date, returning days since the Postgres epoch.
+fn parse_date(s: &str, bc: bool) -> Result<i64, StdBox<dyn Error + Sync + Send>> {
+    let mut parts = s.splitn(3, '-');
+    let year = parse_number(parts.next().unwrap_or(""))?;
+    let month = parse_number(parts.next().unwrap_or(""))?;
+    let day = parse_number(parts.next().unwrap_or(""))?;
+
+    if year == 0 || !(1..=12).contains(&month) || day == 0 {
+        return Err("invalid date".into());
+    }
+    let year = if bc { 1 - year } else { year };
+
+    let days = days_from_civil(year, month, day);
+    // reject days past the end of the month, which roll over into the next one
+    if civil_from_days(days) != (year, month, day) {
+        return Err("invalid date".into());
+    }
+
+    Ok(days - POSTGRES_EPOCH_DAYS)
+}
+
+// Parses a `HH:MM:SS[.ffffff]` time, returning microseconds since midnight.
+fn parse_time(s: &str) -> Result<i64, StdBox<dyn Error + Sync + Send>> {
+    let mut parts = s.splitn(3, ':');
+    let hour = parse_number(parts.next().unwrap_or(""))?;
+    let minute = parse_number(parts.next().unwrap_or(""))?;
+    let (second, fraction) = match parts.next() {
+        Some(second) => match split_once(second, '.') {
+            Some((second, fraction)) => (parse_number(second)?, fraction),
+            None => (parse_number(second)?, ""),
+        },
+        None => (0, ""),
+    };
+
+    if fraction.len() > 6 {
+        return Err("invalid time".into());
+    }
+    let micros = if fraction.is_empty() {
+        0
+    } else {
+        parse_number(fraction)? * 10i64.pow(6 - fraction.len() as u32)
+    };
+
+    let time = ((hour * 60 + minute) * 60 + second) * USECS_PER_SEC + micros;
+    if minute > 59 || second > 60 || time > USECS_PER_DAY {
+        return Err("invalid time".into());
+    }
+    Ok(time)
+}
+
+// Parses a `+HH[:MM[:SS]]` UTC offset, returning microseconds.
+fn parse_offset(s: &str) -> Result<i64, StdBox<dyn Error + Sync + Send>> {
+    let (sign, s) = match s.as_bytes().first() {
+        Some(b'+') => (1, &s[1..]),
+        Some(b'-') => (-1, &s[1..]),
+        _ => return Err("invalid UTC offset".into()),
+    };
+
+    let mut seconds = 0;
+    let mut count = 0;
+    for part in s.split(':') {
+        count += 1;
+        if count > 3 || part.len() != 2 {
+            return Err("invalid UTC offset".into());
+        }
+        seconds = seconds * 60 + parse_number(part)?;
+    }
+    for _ in count..3 {
+        seconds *= 60;
+    }
+
+    Ok(sign * seconds * USECS_PER_SEC)
+}
+
+fn write_date(days: i64, buf: &mut BytesMut) -> bool {
+    let (year, month, day) = civil_from_days(days + POSTGRES_EPOCH_DAYS);
+    let (year, bc) = if year <= 0 {
+        (1 - year, true)
+    } else {
+        (year, false)
+    };
+    write!(buf, "{:04}-{:02}-{:02}", year, month, day).unwrap();
+    bc
+}
+
+fn write_time(time: i64, buf: &mut BytesMut) {
+    let seconds = time / USECS_PER_SEC;
+    let micros = time % USECS_PER_SEC;
+    write!(
+        buf,
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+    .unwrap();
+
+    if micros != 0 {
+        let fraction = format!("{:06}", micros);
+        write!(buf, ".{}", fraction.trim_end_matches('0')).unwrap();
+    }
+}
+
+fn write_era(bc: bool, buf: &mut BytesMut) {
+    if bc {
+        buf.put_slice(b" BC");
+    }
+}
+
+/// Serializes a `DATE` value.
+///
+/// The value is the number of days since January 1st, 2000, with `i32::MAX` and `i32::MIN` representing `infinity` and
+/// `-infinity`.
+pub fn date_to_sql(v: i32, buf: &mut BytesMut) {
+    match v {
+        i32::MAX => buf.put_slice(b"infinity"),
+        i32::MIN => buf.put_slice(b"-infinity"),
+        v => {
+            let bc = write_date(i64::from(v), buf);
+            write_era(bc, buf);
+        }
+    }
+}
+
+/// Deserializes a `DATE` value.
+pub fn date_from_sql(buf: &[u8]) -> Result<i32, StdBox<dyn Error + Sync + Send>> {
+    match from_utf8(buf)? {
+        "infinity" => Ok(i32::MAX),
+        "-infinity" => Ok(i32::MIN),
+        s => {
+            let (s, bc) = split_era(s);
+            let days = parse_date(s, bc)?;
+            Ok(i32::try_from(days)?)
+        }
+    }
+}
+
+/// Serializes a `TIME` value.
+///
+/// The value is the number of microseconds since midnight.
+pub fn time_to_sql(v: i64, buf: &mut BytesMut) {
+    write_time(v, buf);
+}
+
+/// Deserializes a `TIME` value.
+pub fn time_from_sql(buf: &[u8]) -> Result<i64, StdBox<dyn Error + Sync + Send>> {
+    parse_time(from_utf8(buf)?)
+}
+
+fn write_timestamp(v: i64, buf: &mut BytesMut) -> bool {
+    let bc = write_date(v.div_euclid(USECS_PER_DAY), buf);
+    buf.put_u8(b' ');
+    write_time(v.rem_euclid(USECS_PER_DAY), buf);
+    bc
+}
+
+fn parse_timestamp(s: &str, bc: bool) -> Result<i64, StdBox<dyn Error + Sync + Send>> {
+    let (date, time) = split_once(s, ' ').ok_or("invalid timestamp")?;
+    let days = parse_date(date, bc)?;
+    let time = parse_time(time)?;
+
+    days.checked_mul(USECS_PER_DAY)
+        .and_then(|v| v.checked_add(time))
+        .ok_or_else(|| "timestamp out of range".into())
+}
+
+/// Serializes a `TIMESTAMP` value.
+///
+/// The value is the number of microseconds since midnight on January 1st, 2000, with `i64::MAX` and `i64::MIN`
+/// representing `infinity` and `-infinity`.
+pub fn timestamp_to_sql(v: i64, buf: &mut BytesMut) {
+    match v {
+        i64::MAX => buf.put_slice(b"infinity"),
+        i64::MIN => buf.put_slice(b"-infinity"),
+        v => {
+            let bc = write_timestamp(v, buf);
+            write_era(bc, buf);
+        }
+    }
+}
+
+/// Deserializes a `TIMESTAMP` value.
+pub fn timestamp_from_sql(buf: &[u8]) -> Result<i64, StdBox<dyn Error + Sync + Send>> {
+    match from_utf8(buf)? {
+        "infinity" => Ok(i64::MAX),
+        "-infinity" => Ok(i64::MIN),
+        s => {
+            let (s, bc) = split_era(s);
+            parse_timestamp(s, bc)
+        }
+    }
+}
+
+/// Serializes a `TIMESTAMP WITH TIME ZONE` value, in UTC.
+///
+/// The value is the number of microseconds since midnight UTC on January 1st, 2000, with `i64::MAX` and `i64::MIN`
+/// representing `infinity` and `-infinity`.
+pub fn timestamptz_to_sql(v: i64, buf: &mut BytesMut) {
+    match v {
+        i64::MAX => buf.put_slice(b"infinity"),
+        i64::MIN => buf.put_slice(b"-infinity"),
+        v => {
+            let bc = write_timestamp(v, buf);
+            buf.put_slice(b"+00");
+            write_era(bc, buf);
+        }
+    }
+}
+
+/// Deserializes a `TIMESTAMP WITH TIME ZONE` value, converting it to UTC.
+pub fn timestamptz_from_sql(buf: &[u8]) -> Result<i64, StdBox<dyn Error + Sync + Send>> {
+    match from_utf8(buf)? {
+        "infinity" => Ok(i64::MAX),
+        "-infinity" => Ok(i64::MIN),
+        s => {
+            let (s, bc) = split_era(s);
+            // the offset is the only part of the value after the date containing a sign
+            let (date, time) = split_once(s, ' ').ok_or("invalid timestamp")?;
+            let offset_start = time.find(&['+', '-'][..]).ok_or("missing UTC offset")?;
+            let local = parse_timestamp(&s[..date.len() + 1 + offset_start], bc)?;
+            let offset = parse_offset(&time[offset_start..])?;
+
+            local
+                .checked_sub(offset)
+                .ok_or_else(|| "timestamp out of range".into())
+        }
+    }
+}
+
+/// A Postgres array in the text format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Array<'a> {
+    dimensions: Vec<ArrayDimension>,
+    values: Vec<Option<Cow<'a, str>>>,
+}
+
+impl<'a> Array<'a> {
+    /// Returns the dimensions of the array.
+    #[inline]
+    pub fn dimensions(&self) -> &[ArrayDimension] {
+        &self.dimensions
+    }
+
+    /// Returns the values of the array, in row-major order.
+    #[inline]
+    pub fn values(&self) -> &[Option<Cow<'a, str>>] {
+        &self.values
+    }
+
+    /// Consumes the array, returning its values.
+    #[inline]
+    pub fn into_values(self) -> Vec<Option<Cow<'a, str>>> {
+        self.values
+    }
+}
+
+struct ArrayParser<'a> {
+    s: &'a str,
+    idx: usize,
+    delimiter: u8,
+    lengths: Vec<Option<i32>>,
+    element_depth: Option<usize>,
+    values: Vec<Option<Cow<'a, str>>>,
+}
+
+impl<'a> ArrayParser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.s.as_bytes().get(self.idx).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().map_or(false, |c| c.is_ascii_whitespace()) {
+            self.idx += 1;
+        }
+    }
+
+    fn eat(&mut self, c: u8) -> Result<(), StdBox<dyn Error + Sync + Send>> {
+        if self.peek() == Some(c) {
+            self.idx += 1;
+            Ok(())
+        } else {
+            Err(format!(
+                "invalid array: expected `{}` at byte {}",
+                c as char, self.idx
+            )
+            .into())
+        }
+    }
+
+    fn array(&mut self, depth: usize) -> Result<(), StdBox<dyn Error + Sync + Send>> {
+        self.eat(b'{')?;
+        self.skip_whitespace();
+
+        if self.peek() == Some(b'}') {
+            self.idx += 1;
+            if depth == 0 {
+                return Ok(());
+            }
+            return Err("invalid array: empty sub-array".into());
+        }
+
+        if self.lengths.len() == depth {
+            self.lengths.push(None);
+        }
+
+        let mut len = 0;
+        loop {
+            self.skip_whitespace();
+            if self.peek() == Some(b'{') {
+                if self.element_depth.map_or(false, |d| d <= depth) {
+                    return Err("invalid array: inconsistent nesting".into());
+                }
+                self.array(depth + 1)?;
+            } else {
+                match self.element_depth {
+                    Some(d) if d != depth => {
+                        return Err("invalid array: inconsistent nesting".into())
+                    }
+                    _ => self.element_depth = Some(depth),
+                }
+                let value = self.element()?;
+                self.values.push(value);
+            }
+            len += 1;
+
+            self.skip_whitespace();
+            match self.peek() {
+                Some(b'}') => {
+                    self.idx += 1;
+                    break;
+                }
+                Some(c) if c == self.delimiter => self.idx += 1,
+                _ => {
+                    return Err(
+                        format!("invalid array: unexpected character at byte {}", self.idx).into(),
+                    )
+                }
+            }
+        }
+
+        match &mut self.lengths[depth] {
+            Some(l) if *l != len => {
+                return Err("invalid array: sub-arrays must have matching dimensions".into())
+            }
+            l => *l = Some(len),
+        }
+
+        Ok(())
+    }
+
+    fn element(&mut self) -> Result<Option<Cow<'a, str>>, StdBox<dyn Error + Sync + Send>> {
+        let quoted = self.peek() == Some(b'"');
+        if quoted {
+            self.idx += 1;
+        }
+
+        let start = self.idx;
+        let mut owned: Option<String> = None;
+        // the end of the value once trailing whitespace is trimmed from an unquoted element
+        let mut end = start;
+        loop {
+            let c = self
+                .peek()
+                .ok_or("invalid array: unexpected end of input")?;
+            match c {
+                b'"' if quoted => {
+                    self.idx += 1;
+                    end = self.idx - 1;
+                    break;
+                }
+                b'"' | b'{' if !quoted => {
+                    return Err("invalid array: unexpected character in unquoted element".into())
+                }
+                b'}' if !quoted => break,
+                c if c == self.delimiter && !quoted => break,
+                b'\\' => {
+                    let owned = owned.get_or_insert_with(|| self.s[start..self.idx].to_string());
+                    self.idx += 1;
+                    let escaped = self.s[self.idx..]
+                        .chars()
+                        .next()
+                        .ok_or("invalid array: unexpected end of input")?;
+                    owned.push(escaped);
+                    self.idx += escaped.len_utf8();
+                    end = self.idx;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let ch = self.s[self.idx..].chars().next().unwrap();
+            if let Some(owned) = &mut owned {
+                owned.push(ch);
+            }
+            self.idx += ch.len_utf8();
+            if quoted || !ch.is_ascii_whitespace() {
+                end = self.idx;
+            }
+        }
+
+        if quoted {
+            return Ok(Some(match owned {
+                Some(owned) => Cow::Owned(owned),
+                None => Cow::Borrowed(&self.s[start..end]),
+            }));
+        }
+
+        match owned {
+            Some(mut owned) => {
+                // escaped characters are never trimmed
+                let trailing = self.s[end..self.idx].len();
+                owned.truncate(owned.len() - trailing);
+                Ok(Some(Cow::Owned(owned)))
+            }
+            None => {
+                let value = &self.s[start..end];
+                if value.is_empty() {
+                    Err("invalid array: empty unquoted element".into())
+                } else if value.eq_ignore_ascii_case("NULL") {
+                    Ok(None)
+                } else {
+                    Ok(Some(Cow::Borrowed(value)))
+                }
+            }
+        }
+    }
+}
+
+/// Deserializes an array value.
+///
+/// The delimiter separating elements depends on the element type. It is `,` for every built-in type but `box`, which
+/// uses `;`.
+pub fn array_from_sql(
+    buf: &[u8],
+    delimiter: u8,
+) -> Result<Array<'_>, StdBox<dyn Error + Sync + Send>> {
+    let s = from_utf8(buf)?;
+
+    let mut parser = ArrayParser {
+        s,
+        idx: 0,
+        delimiter,
+        lengths: vec![],
+        element_depth: None,
+        values: vec![],
+    };
+    parser.skip_whitespace();
+
+    // explicit bounds, such as `[0:1][1:3]=`
+    let mut lower_bounds = vec![];
+    while parser.peek() == Some(b'[') {
+        let end = parser.s[parser.idx..]
+            .find(']')
+            .ok_or("invalid array dimensions")?
+            + parser.idx;
+        let (lower, upper) =
+            split_once(&parser.s[parser.idx + 1..end], ':').ok_or("invalid array dimensions")?;
+        let lower = lower.trim().parse::<i32>()?;
+        let upper = upper.trim().parse::<i32>()?;
+        lower_bounds.push((lower, upper));
+        parser.idx = end + 1;
+    }
+    if !lower_bounds.is_empty() {
+        parser.eat(b'=')?;
+        parser.skip_whitespace();
+    }
+
+    parser.array(0)?;
+    parser.skip_whitespace();
+    if parser.idx != s.len() {
+        return Err("invalid array: unexpected trailing data".into());
+    }
+
+    // every sub-array has been closed, so all of the lengths are known
+    let lengths = parser.lengths.iter().flatten().copied().collect::<Vec<_>>();
+    let dimensions = if lower_bounds.is_empty() {
+        lengths
+            .iter()
+            .map(|&len| ArrayDimension {
+                len,
+                lower_bound: 1,
+            })
+            .collect()
+    } else {
+        if lower_bounds.len() != lengths.len() {
+            return Err("invalid array: dimensions do not match its contents".into());
+        }
+        lower_bounds
+            .iter()
+            .zip(&lengths)
+            .map(|(&(lower, upper), &len)| {
+                if upper.checked_sub(lower).and_then(|d| d.checked_add(1)) == Some(len) {
+                    Ok(ArrayDimension {
+                        len,
+                        lower_bound: lower,
+                    })
+                } else {
+                    Err("invalid array: dimensions do not match its contents".into())
+                }
+            })
+            .collect::<Result<_, StdBox<dyn Error + Sync + Send>>>()?
+    };
+
+    Ok(Array {
+        dimensions,
+        values: parser.values,
+    })
+}
+
+fn write_array_element(v: &str, delimiter: u8, buf: &mut BytesMut) {
+    let needs_quotes = v.is_empty()
+        || v.eq_ignore_ascii_case("NULL")
+        || v.bytes().any(|c| {
+            matches!(c, b'{' | b'}' | b'"' | b'\\') || c == delimiter || c.is_ascii_whitespace()
+        });
+
+    if !needs_quotes {
+        buf.put_slice(v.as_bytes());
+        return;
+    }
+
+    buf.put_u8(b'"');
+    for c in v.bytes() {
+        if matches!(c, b'"' | b'\\') {
+            buf.put_u8(b'\\');
+        }
+        buf.put_u8(c);
+    }
+    buf.put_u8(b'"');
+}
+
+/// Serializes an array value.
+///
+/// See `array_from_sql` for the meaning of `delimiter`.
+pub fn array_to_sql<I, J, T>(
+    dimensions: I,
+    elements: J,
+    delimiter: u8,
+    buf: &mut BytesMut,
+) -> Result<(), StdBox<dyn Error + Sync + Send>>
+where
+    I: IntoIterator<Item = ArrayDimension>,
+    J: IntoIterator<Item = Option<T>>,
+    T: AsRef<str>,
+{
+    let dimensions = dimensions.into_iter().collect::<Vec<_>>();
+
+    if dimensions.iter().any(|d| d.lower_bound != 1) {
+        for dimension in &dimensions {
+            let upper = dimension.lower_bound + dimension.len - 1;
+            write!(buf, "[{}:{}]", dimension.lower_bound, upper).unwrap();
+        }
+        buf.put_u8(b'=');
+    }
+
+    // the number of elements enclosed by the braces at each depth
+    let mut sizes = vec![1i64; dimensions.len()];
+    let mut size = 1;
+    for (i, dimension) in dimensions.iter().enumerate().rev() {
+        size *= i64::from(dimension.len);
+        sizes[i] = size;
+    }
+    let total = if dimensions.is_empty() { 0 } else { size };
+
+    let mut count = 0;
+    for element in elements {
+        if count == total {
+            return Err("more elements than dimensions allow".into());
+        }
+
+        if count == 0 {
+            buf.put_slice(&vec![b'{'; dimensions.len()]);
+        } else {
+            let reopened = sizes[1..].iter().filter(|&&s| count % s == 0).count();
+            buf.put_slice(&vec![b'}'; reopened]);
+            buf.put_u8(delimiter);
+            buf.put_slice(&vec![b'{'; reopened]);
+        }
+
+        match element {
+            Some(v) => write_array_element(v.as_ref(), delimiter, buf),
+            None => buf.put_slice(b"NULL"),
+        }
+        count += 1;
+    }
+
+    if count != total {
+        return Err("fewer elements than dimensions require".into());
+    }
+
+    if total == 0 {
+        buf.put_slice(b"{}");
+    } else {
+        buf.put_slice(&vec![b'}'; dimensions.len()]);
+    }
+
+    Ok(())
+}
+
+/// A Postgres range in the text format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Range<'a> {
+    /// An empty range.
+    Empty,
+    /// A nonempty range.
+    Nonempty(RangeBound<Cow<'a, str>>, RangeBound<Cow<'a, str>>),
+}
+
+fn range_bound_value<'a>(
+    s: &'a str,
+    idx: &mut usize,
+) -> Result<Option<Cow<'a, str>>, StdBox<dyn Error + Sync + Send>> {
+    let start = *idx;
+    let mut owned: Option<String> = None;
+    let mut quoted = false;
+
+    let mut chars = s[start..].char_indices().peekable();
+    while let Some(&(i, c)) = chars.peek() {
+        match c {
+            ',' | ')' | ']' if !quoted => break,
+            '"' => {
+                let owned = owned.get_or_insert_with(|| s[start..start + i].to_string());
+                chars.next();
+                // a doubled quote inside of a quoted section is a literal quote
+                if quoted && chars.peek().map(|&(_, c)| c) == Some('"') {
+                    owned.push('"');
+                    chars.next();
+                } else {
+                    quoted = !quoted;
+                }
+                continue;
+            }
+            '\\' => {
+                let owned = owned.get_or_insert_with(|| s[start..start + i].to_string());
+                chars.next();
+                let (_, escaped) = chars
+                    .next()
+                    .ok_or("invalid range: unexpected end of input")?;
+                owned.push(escaped);
+                continue;
+            }
+            _ => {}
+        }
+
+        if let Some(owned) = &mut owned {
+            owned.push(c);
+        }
+        chars.next();
+    }
+
+    if quoted {
+        return Err("invalid range: unterminated quoted string".into());
+    }
+
+    let end = chars.peek().map_or(s.len(), |&(i, _)| start + i);
+    *idx = end;
+
+    match owned {
+        Some(owned) => Ok(Some(Cow::Owned(owned))),
+        None if end == start => Ok(None),
+        None => Ok(Some(Cow::Borrowed(&s[start..end]))),
+    }
+}
+
+/// Deserializes a range value.
+pub fn range_from_sql(buf: &[u8]) -> Result<Range<'_>, StdBox<dyn Error + Sync + Send>> {
+    let s = from_utf8(buf)?.trim();
+    if s.eq_ignore_ascii_case("empty") {
+        return Ok(Range::Empty);
+    }
+
+    let lower_inclusive = match s.as_bytes().first() {
+        Some(b'[') => true,
+        Some(b'(') => false,
+        _ => return Err("invalid range: missing lower bound".into()),
+    };
+
+    let mut idx = 1;
+    let lower = range_bound_value(s, &mut idx)?;
+    if s.as_bytes().get(idx) != Some(&b',') {
+        return Err("invalid range: missing delimiter".into());
+    }
+    idx += 1;
+    let upper = range_bound_value(s, &mut idx)?;
+
+    let upper_inclusive = match s.as_bytes().get(idx) {
+        Some(b']') => true,
+        Some(b')') => false,
+        _ => return Err("invalid range: missing upper bound".into()),
+    };
+    if idx + 1 != s.len() {
+        return Err("invalid range: unexpected trailing data".into());
+    }
+
+    let bound = |value, inclusive| match value {
+        Some(value) if inclusive => RangeBound::Inclusive(value),
+        Some(value) => RangeBound::Exclusive(value),
+        None => RangeBound::Unbounded,
+    };
+
+    Ok(Range::Nonempty(
+        bound(lower, lower_inclusive),
+        bound(upper, upper_inclusive),
+    ))
+}
+
+fn write_range_bound(v: &str, buf: &mut BytesMut) {
+    let needs_quotes = v.is_empty()
+        || v.bytes().any(|c| {
+            matches!(c, b'"' | b'\\' | b'(' | b')' | b'[' | b']' | b',') || c.is_ascii_whitespace()
+        });
+
+    if !needs_quotes {
+        buf.put_slice(v.as_bytes());
+        return;
+    }
+
+    buf.put_u8(b'"');
+    for c in v.bytes() {
+        if matches!(c, b'"' | b'\\') {
+            buf.put_u8(c);
+        }
+        buf.put_u8(c);
+    }
+    buf.put_u8(b'"');
+}
+
+/// Serializes an empty range.
+#[inline]
+pub fn empty_range_to_sql(buf: &mut BytesMut) {
+    buf.put_slice(b"empty");
+}
+
+/// Serializes a range value.
+pub fn range_to_sql(lower: RangeBound<&str>, upper: RangeBound<&str>, buf: &mut BytesMut) {
+    match lower {
+        RangeBound::Inclusive(v) => {
+            buf.put_u8(b'[');
+            write_range_bound(v, buf);
+        }
+        RangeBound::Exclusive(v) => {
+            buf.put_u8(b'(');
+            write_range_bound(v, buf);
+        }
+        RangeBound::Unbounded => buf.put_u8(b'('),
+    }
+
+    buf.put_u8(b',');
+
+    match upper {
+        RangeBound::Inclusive(v) => {
+            write_range_bound(v, buf);
+            buf.put_u8(b']');
+        }
+        RangeBound::Exclusive(v) => {
+            write_range_bound(v, buf);
+            buf.put_u8(b')');
+        }
+        RangeBound::Unbounded => buf.put_u8(b')'),
+    }
+}
@@ -0,0 +1,50 @@
+//! Blank-padded `CHARACTER(n)` type.
+
+use bytes::BytesMut;
+use postgres_protocol::types;
+use std::error::Error;
+use std::fmt;
+
+use crate::{FromSql, IsNull, ToSql, Type};
+
+/// A wrapper for `CHARACTER(n)` values which removes their trailing padding when decoding them.
+///
+/// The server pads `CHARACTER(n)` values with spaces up to their declared length, and strings decode them with the
+/// padding included, so `'ab'` stored in a `CHARACTER(4)` column reads as `"ab  "`. The server itself ignores the
+/// padding when comparing values, and this type does the same by trimming trailing spaces on decode. Values are
+/// encoded as-is, since the server pads them again when storing them.
+///
+/// Both `BpChar<String>` and `BpChar<&str>` can be decoded.
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BpChar<T>(pub T);
+
+impl<'a> FromSql<'a> for BpChar<&'a str> {
+    fn from_sql(_: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        let s = types::text_from_sql(raw)?;
+        Ok(BpChar(s.trim_end_matches(' ')))
+    }
+
+    accepts!(BPCHAR);
+}
+
+impl<'a> FromSql<'a> for BpChar<String> {
+    fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        BpChar::<&str>::from_sql(ty, raw).map(|s| BpChar(s.0.to_string()))
+    }
+
+    accepts!(BPCHAR);
+}
+
+impl<T> ToSql for BpChar<T>
+where
+    T: AsRef<str> + fmt::Debug,
+{
+    fn to_sql(&self, _: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        types::text_to_sql(self.0.as_ref(), out);
+        Ok(IsNull::No)
+    }
+
+    accepts!(BPCHAR);
+
+    to_sql_checked!();
+}
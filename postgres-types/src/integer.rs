@@ -0,0 +1,111 @@
+//! Range-checked conversions between Rust integers and Postgres integer types, and conversions of non-zero integers.
+
+use bytes::BytesMut;
+use postgres_protocol::types;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::num::{NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroU16, NonZeroU32, NonZeroU64};
+
+use crate::{FromSql, IsNull, PgNumeric, ToSql, Type};
+
+// Decodes a value of any of the types accepted by the checked integer conversions.
+fn int_from_sql(ty: &Type, raw: &[u8]) -> Result<i128, Box<dyn Error + Sync + Send>> {
+    let value = match *ty {
+        Type::CHAR => i128::from(types::char_from_sql(raw)?),
+        Type::INT2 => i128::from(types::int2_from_sql(raw)?),
+        Type::INT4 => i128::from(types::int4_from_sql(raw)?),
+        Type::INT8 => i128::from(types::int8_from_sql(raw)?),
+        Type::OID => i128::from(types::oid_from_sql(raw)?),
+        Type::NUMERIC => PgNumeric::from_sql(ty, raw)?
+            .to_i128()
+            .ok_or("numeric value is not an integer")?,
+        _ => return Err(format!("unexpected type {}", ty).into()),
+    };
+    Ok(value)
+}
+
+fn int_to_sql(
+    value: i128,
+    ty: &Type,
+    out: &mut BytesMut,
+) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+    let out_of_range = || format!("value {} is out of range for type {}", value, ty);
+    match *ty {
+        Type::CHAR => types::char_to_sql(i8::try_from(value).map_err(|_| out_of_range())?, out),
+        Type::INT2 => types::int2_to_sql(i16::try_from(value).map_err(|_| out_of_range())?, out),
+        Type::INT4 => types::int4_to_sql(i32::try_from(value).map_err(|_| out_of_range())?, out),
+        Type::INT8 => types::int8_to_sql(i64::try_from(value).map_err(|_| out_of_range())?, out),
+        Type::OID => types::oid_to_sql(u32::try_from(value).map_err(|_| out_of_range())?, out),
+        Type::NUMERIC => return PgNumeric::from_i128(value).to_sql(ty, out),
+        _ => return Err(format!("unexpected type {}", ty).into()),
+    }
+    Ok(IsNull::No)
+}
+
+macro_rules! checked_int {
+    ($t:ty, $($expected:ident),+) => {
+        impl<'a> FromSql<'a> for $t {
+            fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<$t, Box<dyn Error + Sync + Send>> {
+                let value = int_from_sql(ty, raw)?;
+                <$t>::try_from(value)
+                    .map_err(|_| format!("value {} is out of range for {}", value, stringify!($t)).into())
+            }
+
+            accepts!($($expected),+);
+        }
+
+        impl ToSql for $t {
+            fn to_sql(&self, ty: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+                int_to_sql(i128::from(*self), ty, out)
+            }
+
+            accepts!($($expected),+);
+
+            to_sql_checked!();
+        }
+    };
+}
+
+checked_int!(i8, CHAR, INT2, INT4, INT8, NUMERIC);
+checked_int!(u16, INT2, INT4, INT8, OID, NUMERIC);
+checked_int!(u32, OID, INT2, INT4, INT8, NUMERIC);
+checked_int!(u64, INT2, INT4, INT8, OID, NUMERIC);
+
+macro_rules! non_zero {
+    ($t:ty, $inner:ty) => {
+        impl<'a> FromSql<'a> for $t {
+            fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<$t, Box<dyn Error + Sync + Send>> {
+                let value = <$inner as FromSql>::from_sql(ty, raw)?;
+                <$t>::new(value).ok_or_else(|| "unexpected zero value".into())
+            }
+
+            fn accepts(ty: &Type) -> bool {
+                <$inner as FromSql>::accepts(ty)
+            }
+        }
+
+        impl ToSql for $t {
+            fn to_sql(
+                &self,
+                ty: &Type,
+                out: &mut BytesMut,
+            ) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+                self.get().to_sql(ty, out)
+            }
+
+            fn accepts(ty: &Type) -> bool {
+                <$inner as ToSql>::accepts(ty)
+            }
+
+            to_sql_checked!();
+        }
+    };
+}
+
+non_zero!(NonZeroI8, i8);
+non_zero!(NonZeroI16, i16);
+non_zero!(NonZeroI32, i32);
+non_zero!(NonZeroI64, i64);
+non_zero!(NonZeroU16, u16);
+non_zero!(NonZeroU32, u32);
+non_zero!(NonZeroU64, u64);
@@ -0,0 +1,79 @@
+//! SQL/JSON path `JSONPATH` type.
+
+use bytes::{BufMut, BytesMut};
+use postgres_protocol::types;
+use std::error::Error;
+use std::fmt;
+
+use crate::{FromSql, IsNull, ToSql, Type};
+
+// the only version of the binary format supported by the server
+const VERSION: u8 = 1;
+
+/// Postgres `JSONPATH` type.
+///
+/// Path expressions are held in their text form. They are only parsed by the server, so invalid expressions are
+/// reported as errors when they are sent as parameters rather than when they are created.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PgJsonPath(String);
+
+impl PgJsonPath {
+    /// Returns the text of the path expression.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for PgJsonPath {
+    fn from(path: String) -> Self {
+        PgJsonPath(path)
+    }
+}
+
+impl From<&str> for PgJsonPath {
+    fn from(path: &str) -> Self {
+        PgJsonPath(path.to_string())
+    }
+}
+
+impl From<PgJsonPath> for String {
+    fn from(path: PgJsonPath) -> String {
+        path.0
+    }
+}
+
+impl fmt::Display for PgJsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl fmt::Debug for PgJsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl<'a> FromSql<'a> for PgJsonPath {
+    fn from_sql(_: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        match raw.split_first() {
+            Some((&VERSION, raw)) => Ok(PgJsonPath(types::text_from_sql(raw)?.to_string())),
+            Some(_) => Err("unsupported JSONPATH encoding version".into()),
+            None => Err("invalid buffer size".into()),
+        }
+    }
+
+    accepts!(JSONPATH);
+}
+
+impl ToSql for PgJsonPath {
+    fn to_sql(&self, _: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        out.put_u8(VERSION);
+        types::text_to_sql(&self.0, out);
+        Ok(IsNull::No)
+    }
+
+    accepts!(JSONPATH);
+
+    to_sql_checked!();
+}
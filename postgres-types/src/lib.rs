@@ -38,6 +38,26 @@
 //! }
 //! ```
 //!
+//! By default, the labels of the Postgres enum must exactly match the variants of the Rust enum. A single variant can
+//! be marked with `#[postgres(other)]` to act as a fallback for labels which are unknown to the Rust enum, such as
+//! those added to the Postgres enum during a rolling schema upgrade. Those labels are then decoded as the fallback
+//! variant, which cannot itself be serialized:
+//!
+//! ```rust
+//! # #[cfg(feature = "derive")]
+//! use postgres_types::{ToSql, FromSql};
+//!
+//! # #[cfg(feature = "derive")]
+//! #[derive(Debug, ToSql, FromSql)]
+//! enum Mood {
+//!     Sad,
+//!     Ok,
+//!     Happy,
+//!     #[postgres(other)]
+//!     Unknown,
+//! }
+//! ```
+//!
 //! ## Domains
 //!
 //! Postgres domains correspond to tuple structs with one member in Rust:
@@ -95,6 +115,27 @@
 //! }
 //! ```
 //!
+//! By default, the fields of the Postgres type must exactly match the fields of the Rust struct. So that fields can be
+//! added to the Postgres type without breaking existing clients, the derived `FromSql` implementation can tolerate
+//! differences: the `#[postgres(ignore_unknown_fields)]` attribute on the struct skips fields of the Postgres type
+//! which are unknown to the Rust struct, and the `#[postgres(default)]` attribute on a field sets it to its `Default`
+//! value if the Postgres type does not have it. These attributes do not affect the derived `ToSql` implementation.
+//!
+//! ```rust
+//! # #[cfg(feature = "derive")]
+//! use postgres_types::FromSql;
+//!
+//! # #[cfg(feature = "derive")]
+//! #[derive(Debug, FromSql)]
+//! #[postgres(ignore_unknown_fields)]
+//! struct InventoryItem {
+//!     name: String,
+//!     supplier_id: i32,
+//!     #[postgres(default)]
+//!     price: Option<f64>,
+//! }
+//! ```
+//!
 //! ## Naming
 //!
 //! The derived implementations will enforce exact matches of type, field, and variant names between the Rust and
@@ -133,6 +174,7 @@ use postgres_protocol::types::{self, ArrayDimension};
 use std::any::type_name;
 use std::borrow::Cow;
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::error::Error;
 use std::fmt;
 use std::hash::BuildHasher;
@@ -143,6 +185,8 @@ use std::time::{Duration, SystemTime, UNIX_EPOCH};
 #[cfg(feature = "derive")]
 pub use postgres_derive::{FromSql, ToSql};
 
+#[cfg(feature = "with-pgvector")]
+pub use crate::pgvector::{HalfVector, SparseVector, Vector};
 #[cfg(feature = "with-serde_json-1")]
 pub use crate::serde_json_1::Json;
 use crate::type_gen::{Inner, Other};
@@ -150,22 +194,36 @@ use crate::type_gen::{Inner, Other};
 #[doc(inline)]
 pub use postgres_protocol::Oid;
 
+#[doc(inline)]
+pub use bpchar::BpChar;
+#[doc(inline)]
+pub use jsonpath::PgJsonPath;
+#[doc(inline)]
+pub use money::PgMoney;
+#[doc(inline)]
+pub use numeric::{Lossy, PgNumeric};
 #[doc(inline)]
 pub use pg_lsn::PgLsn;
+#[doc(inline)]
+pub use snapshot::PgSnapshot;
 
-pub use crate::special::{Date, Timestamp};
-use bytes::BytesMut;
+pub use crate::special::{Date, Interval, Timestamp};
+use bytes::{Bytes, BytesMut};
 
 // Number of seconds from 1970-01-01 to 2000-01-01
 const TIME_SEC_CONVERSION: u64 = 946_684_800;
-const USEC_PER_SEC: u64 = 1_000_000;
-const NSEC_PER_USEC: u64 = 1_000;
+
+// The lengths of days, and of months and years in intervals, as used by `EXTRACT(EPOCH FROM interval)`
+const USEC_PER_DAY: i128 = 86_400 * 1_000_000;
+const USEC_PER_MONTH: i128 = 30 * USEC_PER_DAY;
+const USEC_PER_YEAR: i128 = 31_557_600 * 1_000_000;
 
 /// Generates a simple implementation of `ToSql::accepts` which accepts the
 /// types passed to it.
 #[macro_export]
 macro_rules! accepts {
     ($($expected:ident),+) => (
+        #[inline]
         fn accepts(ty: &$crate::Type) -> bool {
             matches!(*ty, $($crate::Type::$expected)|+)
         }
@@ -212,6 +270,8 @@ where
 mod bit_vec_06;
 #[cfg(feature = "with-chrono-0_4")]
 mod chrono_04;
+#[cfg(feature = "with-cidr-0_2")]
+mod cidr_02;
 #[cfg(feature = "with-eui48-0_4")]
 mod eui48_04;
 #[cfg(feature = "with-eui48-1")]
@@ -220,6 +280,8 @@ mod eui48_1;
 mod geo_types_06;
 #[cfg(feature = "with-geo-types-0_7")]
 mod geo_types_07;
+#[cfg(feature = "with-pgvector")]
+mod pgvector;
 #[cfg(feature = "with-serde_json-1")]
 mod serde_json_1;
 #[cfg(feature = "with-time-0_2")]
@@ -233,9 +295,15 @@ mod uuid_08;
 #[cfg(feature = "with-time-0_2")]
 extern crate time_02 as time;
 
+mod bpchar;
+mod integer;
+mod jsonpath;
+mod money;
+mod numeric;
 mod pg_lsn;
 #[doc(hidden)]
 pub mod private;
+mod snapshot;
 mod special;
 mod type_gen;
 
@@ -406,10 +474,32 @@ impl WrongType {
 /// | `f64`                             | DOUBLE PRECISION                              |
 /// | `&str`/`String`                   | VARCHAR, CHAR(n), TEXT, CITEXT, NAME, UNKNOWN |
 /// | `&[u8]`/`Vec<u8>`                 | BYTEA                                         |
+/// | `Bytes`/`BytesMut`                | BYTEA                                         |
 /// | `HashMap<String, Option<String>>` | HSTORE                                        |
 /// | `SystemTime`                      | TIMESTAMP, TIMESTAMP WITH TIME ZONE           |
+/// | `Duration`                        | INTERVAL                                      |
 /// | `IpAddr`                          | INET                                          |
 ///
+/// `i8`, `u16`, `u32`, and `u64` can also be converted from SMALLINT, INT,
+/// BIGINT, and NUMERIC, and the unsigned types from OID, with values which
+/// don't fit in the target type reported as errors rather than being
+/// truncated. `u8` is not supported, since `&[u8]` and `Vec<u8>`
+/// correspond to BYTEA.
+///
+/// The `std::num::NonZero*` integer types, apart from `NonZeroU8`, are
+/// converted like the integers they wrap, with zero values reported as
+/// errors.
+///
+/// `SystemTime` values are offsets from the Unix epoch with microsecond
+/// precision. Infinite timestamps are reported as errors, and can be decoded
+/// with the `Timestamp` wrapper instead.
+///
+/// `Duration` values are decoded from intervals with no month component,
+/// counting each day as 24 hours, and negative intervals are reported as
+/// errors. `Lossy<Duration>` also accepts intervals with months, counting
+/// each year as 365.25 days and each remaining month as 30 days, like
+/// `EXTRACT(EPOCH FROM interval)`.
+///
 /// In addition, some implementations are provided for types in third party
 /// crates. These are disabled by default; to opt into one of these
 /// implementations, activate the Cargo feature corresponding to the crate's
@@ -434,9 +524,15 @@ impl WrongType {
 /// | `geo_types::LineString<f64>`    | PATH                                |
 /// | `serde_json::Value`             | JSON, JSONB                         |
 /// | `uuid::Uuid`                    | UUID                                |
+/// | `cidr::IpCidr`                  | CIDR                                |
+/// | `cidr::IpInet`                  | INET                                |
 /// | `bit_vec::BitVec`               | BIT, VARBIT                         |
 /// | `eui48::MacAddress`             | MACADDR                             |
 ///
+/// The `with-pgvector` feature similarly enables the `Vector`, `HalfVector`,
+/// and `SparseVector` types, corresponding to the `vector`, `halfvec`, and
+/// `sparsevec` types of the `pgvector` extension.
+///
 /// # Nullability
 ///
 /// In addition to the types listed above, `FromSql` is implemented for
@@ -581,6 +677,22 @@ impl<'a> FromSql<'a> for Vec<u8> {
     accepts!(BYTEA);
 }
 
+impl<'a> FromSql<'a> for Bytes {
+    fn from_sql(_: &Type, raw: &'a [u8]) -> Result<Bytes, Box<dyn Error + Sync + Send>> {
+        Ok(Bytes::copy_from_slice(types::bytea_from_sql(raw)))
+    }
+
+    accepts!(BYTEA);
+}
+
+impl<'a> FromSql<'a> for BytesMut {
+    fn from_sql(_: &Type, raw: &'a [u8]) -> Result<BytesMut, Box<dyn Error + Sync + Send>> {
+        Ok(BytesMut::from(types::bytea_from_sql(raw)))
+    }
+
+    accepts!(BYTEA);
+}
+
 impl<'a> FromSql<'a> for &'a [u8] {
     fn from_sql(_: &Type, raw: &'a [u8]) -> Result<&'a [u8], Box<dyn Error + Sync + Send>> {
         Ok(types::bytea_from_sql(raw))
@@ -590,10 +702,12 @@ impl<'a> FromSql<'a> for &'a [u8] {
 }
 
 impl<'a> FromSql<'a> for String {
+    #[inline]
     fn from_sql(_: &Type, raw: &'a [u8]) -> Result<String, Box<dyn Error + Sync + Send>> {
         types::text_from_sql(raw).map(ToString::to_string)
     }
 
+    #[inline]
     fn accepts(ty: &Type) -> bool {
         <&str as FromSql>::accepts(ty)
     }
@@ -612,10 +726,12 @@ impl<'a> FromSql<'a> for Box<str> {
 }
 
 impl<'a> FromSql<'a> for &'a str {
+    #[inline]
     fn from_sql(_: &Type, raw: &'a [u8]) -> Result<&'a str, Box<dyn Error + Sync + Send>> {
         types::text_from_sql(raw)
     }
 
+    #[inline]
     fn accepts(ty: &Type) -> bool {
         match *ty {
             Type::VARCHAR | Type::TEXT | Type::BPCHAR | Type::NAME | Type::UNKNOWN => true,
@@ -628,6 +744,7 @@ impl<'a> FromSql<'a> for &'a str {
 macro_rules! simple_from {
     ($t:ty, $f:ident, $($expected:ident),+) => {
         impl<'a> FromSql<'a> for $t {
+            #[inline]
             fn from_sql(_: &Type, raw: &'a [u8]) -> Result<$t, Box<dyn Error + Sync + Send>> {
                 types::$f(raw)
             }
@@ -638,10 +755,8 @@ macro_rules! simple_from {
 }
 
 simple_from!(bool, bool_from_sql, BOOL);
-simple_from!(i8, char_from_sql, CHAR);
 simple_from!(i16, int2_from_sql, INT2);
 simple_from!(i32, int4_from_sql, INT4);
-simple_from!(u32, oid_from_sql, OID);
 simple_from!(i64, int8_from_sql, INT8);
 simple_from!(f32, float4_from_sql, FLOAT4);
 simple_from!(f64, float8_from_sql, FLOAT8);
@@ -667,27 +782,69 @@ where
 impl<'a> FromSql<'a> for SystemTime {
     fn from_sql(_: &Type, raw: &'a [u8]) -> Result<SystemTime, Box<dyn Error + Sync + Send>> {
         let time = types::timestamp_from_sql(raw)?;
+        // i64::MAX and i64::MIN represent infinity and -infinity
+        if time == i64::MAX || time == i64::MIN {
+            return Err("timestamp is infinite".into());
+        }
         let epoch = UNIX_EPOCH + Duration::from_secs(TIME_SEC_CONVERSION);
 
-        let negative = time < 0;
-        let time = time.abs() as u64;
-
-        let secs = time / USEC_PER_SEC;
-        let nsec = (time % USEC_PER_SEC) * NSEC_PER_USEC;
-        let offset = Duration::new(secs, nsec as u32);
-
-        let time = if negative {
-            epoch - offset
+        let offset = Duration::from_micros(time.unsigned_abs());
+        let time = if time < 0 {
+            epoch.checked_sub(offset)
         } else {
-            epoch + offset
+            epoch.checked_add(offset)
         };
 
-        Ok(time)
+        time.ok_or_else(|| "timestamp out of range for SystemTime".into())
     }
 
     accepts!(TIMESTAMP, TIMESTAMPTZ);
 }
 
+impl<'a> FromSql<'a> for Duration {
+    fn from_sql(_: &Type, raw: &'a [u8]) -> Result<Duration, Box<dyn Error + Sync + Send>> {
+        duration_from_sql(raw, false)
+    }
+
+    accepts!(INTERVAL);
+}
+
+impl<'a> FromSql<'a> for Lossy<Duration> {
+    fn from_sql(_: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        duration_from_sql(raw, true).map(Lossy)
+    }
+
+    accepts!(INTERVAL);
+}
+
+fn duration_from_sql(
+    raw: &[u8],
+    approximate: bool,
+) -> Result<Duration, Box<dyn Error + Sync + Send>> {
+    let interval = types::interval_from_sql(raw)?;
+    match (interval.microseconds(), interval.days(), interval.months()) {
+        (i64::MAX, i32::MAX, i32::MAX) | (i64::MIN, i32::MIN, i32::MIN) => {
+            return Err("interval is infinite".into())
+        }
+        _ => {}
+    }
+    if interval.months() != 0 && !approximate {
+        return Err("interval has a month component".into());
+    }
+
+    let micros = i128::from(interval.microseconds())
+        + i128::from(interval.days()) * USEC_PER_DAY
+        + i128::from(interval.months() / 12) * USEC_PER_YEAR
+        + i128::from(interval.months() % 12) * USEC_PER_MONTH;
+    if micros < 0 {
+        return Err("interval is negative".into());
+    }
+
+    let secs = u64::try_from(micros / 1_000_000)?;
+    let nanos = (micros % 1_000_000) as u32 * 1_000;
+    Ok(Duration::new(secs, nanos))
+}
+
 impl<'a> FromSql<'a> for IpAddr {
     fn from_sql(_: &Type, raw: &'a [u8]) -> Result<IpAddr, Box<dyn Error + Sync + Send>> {
         let inet = types::inet_from_sql(raw)?;
@@ -724,10 +881,27 @@ pub enum IsNull {
 /// | `f64`                             | DOUBLE PRECISION                     |
 /// | `&str`/`String`                   | VARCHAR, CHAR(n), TEXT, CITEXT, NAME |
 /// | `&[u8]`/`Vec<u8>`                 | BYTEA                                |
+/// | `Bytes`/`BytesMut`                | BYTEA                                |
 /// | `HashMap<String, Option<String>>` | HSTORE                               |
 /// | `SystemTime`                      | TIMESTAMP, TIMESTAMP WITH TIME ZONE  |
+/// | `Duration`                        | INTERVAL                             |
 /// | `IpAddr`                          | INET                                 |
 ///
+/// `i8`, `u16`, `u32`, and `u64` can also be converted to SMALLINT, INT,
+/// BIGINT, and NUMERIC, and the unsigned types to OID, with values which
+/// don't fit in the target type reported as errors rather than being
+/// truncated. `u8` is not supported, since `&[u8]` and `Vec<u8>`
+/// correspond to BYTEA.
+///
+/// The `std::num::NonZero*` integer types, apart from `NonZeroU8`, are
+/// converted like the integers they wrap.
+///
+/// `SystemTime` values are truncated to microsecond precision, and values
+/// too far from the Unix epoch to be represented are reported as errors.
+///
+/// `Duration` values are encoded as intervals made up only of microseconds,
+/// and are truncated to microsecond precision.
+///
 /// In addition, some implementations are provided for types in third party
 /// crates. These are disabled by default; to opt into one of these
 /// implementations, activate the Cargo feature corresponding to the crate's
@@ -752,9 +926,15 @@ pub enum IsNull {
 /// | `geo_types::LineString<f64>`    | PATH                                |
 /// | `serde_json::Value`             | JSON, JSONB                         |
 /// | `uuid::Uuid`                    | UUID                                |
+/// | `cidr::IpCidr`                  | CIDR                                |
+/// | `cidr::IpInet`                  | INET                                |
 /// | `bit_vec::BitVec`               | BIT, VARBIT                         |
 /// | `eui48::MacAddress`             | MACADDR                             |
 ///
+/// The `with-pgvector` feature similarly enables the `Vector`, `HalfVector`,
+/// and `SparseVector` types, corresponding to the `vector`, `halfvec`, and
+/// `sparsevec` types of the `pgvector` extension.
+///
 /// # Nullability
 ///
 /// In addition to the types listed above, `ToSql` is implemented for
@@ -919,6 +1099,30 @@ impl ToSql for Vec<u8> {
     to_sql_checked!();
 }
 
+impl ToSql for Bytes {
+    fn to_sql(&self, ty: &Type, w: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        <&[u8] as ToSql>::to_sql(&&**self, ty, w)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        <&[u8] as ToSql>::accepts(ty)
+    }
+
+    to_sql_checked!();
+}
+
+impl ToSql for BytesMut {
+    fn to_sql(&self, ty: &Type, w: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        <&[u8] as ToSql>::to_sql(&&**self, ty, w)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        <&[u8] as ToSql>::accepts(ty)
+    }
+
+    to_sql_checked!();
+}
+
 impl<'a> ToSql for &'a str {
     fn to_sql(&self, _: &Type, w: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
         types::text_to_sql(*self, w);
@@ -991,10 +1195,8 @@ macro_rules! simple_to {
 }
 
 simple_to!(bool, bool_to_sql, BOOL);
-simple_to!(i8, char_to_sql, CHAR);
 simple_to!(i16, int2_to_sql, INT2);
 simple_to!(i32, int4_to_sql, INT4);
-simple_to!(u32, oid_to_sql, OID);
 simple_to!(i64, int8_to_sql, INT8);
 simple_to!(f32, float4_to_sql, FLOAT4);
 simple_to!(f64, float8_to_sql, FLOAT8);
@@ -1022,13 +1224,13 @@ impl ToSql for SystemTime {
     fn to_sql(&self, _: &Type, w: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
         let epoch = UNIX_EPOCH + Duration::from_secs(TIME_SEC_CONVERSION);
 
-        let to_usec =
-            |d: Duration| d.as_secs() * USEC_PER_SEC + u64::from(d.subsec_nanos()) / NSEC_PER_USEC;
-
         let time = match self.duration_since(epoch) {
-            Ok(duration) => to_usec(duration) as i64,
-            Err(e) => -(to_usec(e.duration()) as i64),
+            Ok(duration) => i64::try_from(duration.as_micros()).ok(),
+            Err(e) => i64::try_from(e.duration().as_micros()).ok().map(|t| -t),
         };
+        let time = time
+            .filter(|&t| t != i64::MAX && t != i64::MIN)
+            .ok_or("timestamp out of range for Postgres")?;
 
         types::timestamp_to_sql(time, w);
         Ok(IsNull::No)
@@ -1039,6 +1241,18 @@ impl ToSql for SystemTime {
     to_sql_checked!();
 }
 
+impl ToSql for Duration {
+    fn to_sql(&self, _: &Type, w: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        let micros = i64::try_from(self.as_micros()).map_err(|_| "value too large to transmit")?;
+        types::interval_to_sql(micros, 0, 0, w);
+        Ok(IsNull::No)
+    }
+
+    accepts!(INTERVAL);
+
+    to_sql_checked!();
+}
+
 impl ToSql for IpAddr {
     fn to_sql(&self, _: &Type, w: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
         let netmask = match self {
@@ -0,0 +1,136 @@
+//! Currency `MONEY` type.
+
+use bytes::BytesMut;
+use postgres_protocol::types;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{FromSql, IsNull, ToSql, Type};
+
+/// Postgres `MONEY` type, as an amount in cents.
+///
+/// The server formats money values according to its `lc_monetary` setting, which also determines the number of
+/// fractional digits the stored amount has. The `Display` and `FromStr` implementations of this type always use the
+/// format of the `C` locale, such as `-$1,234.56`, so values render the same regardless of the locale of the client
+/// or the server.
+#[derive(Clone, Copy, Eq, Ord, PartialEq, PartialOrd, Hash)]
+pub struct PgMoney(i64);
+
+/// Error parsing a `PgMoney`.
+#[derive(Debug)]
+pub struct ParseMoneyError(());
+
+impl fmt::Display for ParseMoneyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid money")
+    }
+}
+
+impl Error for ParseMoneyError {}
+
+impl From<i64> for PgMoney {
+    fn from(cents: i64) -> Self {
+        PgMoney(cents)
+    }
+}
+
+impl From<PgMoney> for i64 {
+    fn from(money: PgMoney) -> i64 {
+        money.0
+    }
+}
+
+impl FromStr for PgMoney {
+    type Err = ParseMoneyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(s) => (true, s),
+            None => (false, s),
+        };
+        let s = s.strip_prefix('$').unwrap_or(s);
+        let (units, cents) = match s.find('.') {
+            Some(i) => (&s[..i], &s[i + 1..]),
+            None => (s, ""),
+        };
+
+        let mut amount = 0i128;
+        let mut units_digits = 0;
+        for b in units.bytes() {
+            match b {
+                b'0'..=b'9' => {
+                    amount = amount * 10 + i128::from(b - b'0');
+                    units_digits += 1;
+                }
+                b',' => {}
+                _ => return Err(ParseMoneyError(())),
+            }
+            if amount > i128::from(i64::MAX) {
+                return Err(ParseMoneyError(()));
+            }
+        }
+        if cents.len() > 2 || !cents.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(ParseMoneyError(()));
+        }
+        if units_digits == 0 && cents.is_empty() {
+            return Err(ParseMoneyError(()));
+        }
+
+        let cents = format!("{:0<2}", cents);
+        amount = amount * 100 + cents.parse::<i128>().map_err(|_| ParseMoneyError(()))?;
+        if negative {
+            amount = -amount;
+        }
+        i64::try_from(amount)
+            .map(PgMoney)
+            .map_err(|_| ParseMoneyError(()))
+    }
+}
+
+impl fmt::Display for PgMoney {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 < 0 {
+            f.write_str("-")?;
+        }
+        let amount = self.0.unsigned_abs();
+        let units = (amount / 100).to_string();
+
+        f.write_str("$")?;
+        for (i, c) in units.chars().enumerate() {
+            if i > 0 && (units.len() - i) % 3 == 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}", c)?;
+        }
+        write!(f, ".{:02}", amount % 100)
+    }
+}
+
+impl fmt::Debug for PgMoney {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl<'a> FromSql<'a> for PgMoney {
+    fn from_sql(_: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        let v = types::int8_from_sql(raw)?;
+        Ok(v.into())
+    }
+
+    accepts!(MONEY);
+}
+
+impl ToSql for PgMoney {
+    fn to_sql(&self, _: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        types::int8_to_sql(self.0, out);
+        Ok(IsNull::No)
+    }
+
+    accepts!(MONEY);
+
+    to_sql_checked!();
+}
@@ -0,0 +1,365 @@
+//! Arbitrary precision `NUMERIC` type.
+
+use bytes::{BufMut, BytesMut};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt::{self, Write};
+use std::str::FromStr;
+
+use crate::{FromSql, IsNull, ToSql, Type};
+
+const POSITIVE: u16 = 0x0000;
+const NEGATIVE: u16 = 0x4000;
+const NAN: u16 = 0xC000;
+const POS_INFINITY: u16 = 0xD000;
+const NEG_INFINITY: u16 = 0xF000;
+
+// the largest display scale supported by the server
+const MAX_SCALE: u16 = 0x3FFF;
+
+/// Postgres `NUMERIC` type.
+///
+/// The value is stored exactly as the server represents it, as base 10000 digits along with the number of decimal
+/// digits to display after the decimal point. Its `Display` and `FromStr` implementations match the text format of
+/// the server, so values render exactly as they would in `psql`, independently of any locale settings.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct PgNumeric {
+    sign: u16,
+    weight: i16,
+    scale: u16,
+    digits: Vec<i16>,
+}
+
+/// A wrapper opting into lossy conversions, such as decoding `NUMERIC` values into floating point numbers.
+///
+/// `f32` and `f64` are not decoded from `NUMERIC` values on their own, since doing so loses precision. Wrapping them
+/// in this type opts into the conversion, which rounds each value to the nearest representable number. Values too
+/// large to represent become infinite, and `NaN`, `Infinity`, and `-Infinity` map to their floating point
+/// counterparts. For example, `row.get::<_, Lossy<f64>>(0)` reads a `NUMERIC` column as an `f64`.
+///
+/// `Lossy<Duration>` similarly decodes `INTERVAL` values with a month component, which `Duration` rejects since the
+/// length of a month varies.
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Default)]
+pub struct Lossy<T>(pub T);
+
+/// Error parsing a `PgNumeric`.
+#[derive(Debug)]
+pub struct ParseNumericError(());
+
+impl fmt::Display for ParseNumericError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid numeric")
+    }
+}
+
+impl Error for ParseNumericError {}
+
+impl PgNumeric {
+    /// Returns the `NaN` value.
+    pub fn nan() -> PgNumeric {
+        PgNumeric::special(NAN)
+    }
+
+    /// Returns the `Infinity` value.
+    pub fn infinity() -> PgNumeric {
+        PgNumeric::special(POS_INFINITY)
+    }
+
+    /// Returns the `-Infinity` value.
+    pub fn neg_infinity() -> PgNumeric {
+        PgNumeric::special(NEG_INFINITY)
+    }
+
+    fn special(sign: u16) -> PgNumeric {
+        PgNumeric {
+            sign,
+            weight: 0,
+            scale: 0,
+            digits: vec![],
+        }
+    }
+
+    /// Determines if the value is `NaN`.
+    pub fn is_nan(&self) -> bool {
+        self.sign == NAN
+    }
+
+    /// Determines if the value is `Infinity` or `-Infinity`.
+    pub fn is_infinite(&self) -> bool {
+        matches!(self.sign, POS_INFINITY | NEG_INFINITY)
+    }
+
+    /// Determines if the value is negative, including `-Infinity`.
+    pub fn is_sign_negative(&self) -> bool {
+        matches!(self.sign, NEGATIVE | NEG_INFINITY)
+    }
+
+    /// Returns the number of digits displayed after the decimal point.
+    pub fn scale(&self) -> u16 {
+        self.scale
+    }
+
+    pub(crate) fn from_i128(value: i128) -> PgNumeric {
+        let mut abs = value.unsigned_abs();
+        let mut digits = vec![];
+        while abs > 0 {
+            digits.push((abs % 10000) as i16);
+            abs /= 10000;
+        }
+        let weight = digits.len().saturating_sub(1) as i16;
+        digits.reverse();
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+
+        PgNumeric {
+            sign: if value < 0 { NEGATIVE } else { POSITIVE },
+            weight,
+            scale: 0,
+            digits,
+        }
+    }
+
+    // Returns the value if it is an integer which fits in an i128.
+    pub(crate) fn to_i128(&self) -> Option<i128> {
+        if !matches!(self.sign, POSITIVE | NEGATIVE) {
+            return None;
+        }
+
+        let mut value = 0i128;
+        for (i, &digit) in self.digits.iter().enumerate() {
+            if i as i64 > i64::from(self.weight) {
+                if digit != 0 {
+                    return None;
+                }
+                continue;
+            }
+            value = value.checked_mul(10000)?.checked_add(i128::from(digit))?;
+        }
+        // trailing zero digits before the decimal point are omitted
+        let missing = i64::from(self.weight) + 1 - self.digits.len() as i64;
+        for _ in 0..missing.max(0) {
+            value = value.checked_mul(10000)?;
+        }
+
+        if self.sign == NEGATIVE {
+            value = -value;
+        }
+        Some(value)
+    }
+}
+
+impl FromStr for PgNumeric {
+    type Err = ParseNumericError;
+
+    fn from_str(s: &str) -> Result<PgNumeric, ParseNumericError> {
+        let s = s.trim();
+        match &*s.to_ascii_lowercase() {
+            "nan" => return Ok(PgNumeric::nan()),
+            "infinity" | "+infinity" | "inf" | "+inf" => return Ok(PgNumeric::infinity()),
+            "-infinity" | "-inf" => return Ok(PgNumeric::neg_infinity()),
+            _ => {}
+        }
+
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(s) => (true, s),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let (mantissa, exponent) = match s.find(&['e', 'E'][..]) {
+            Some(i) => {
+                let exponent = s[i + 1..]
+                    .parse::<i64>()
+                    .map_err(|_| ParseNumericError(()))?;
+                (&s[..i], exponent)
+            }
+            None => (s, 0),
+        };
+        if exponent.abs() > i64::from(MAX_SCALE) {
+            return Err(ParseNumericError(()));
+        }
+        let (int, frac) = match mantissa.find('.') {
+            Some(i) => (&mantissa[..i], &mantissa[i + 1..]),
+            None => (mantissa, ""),
+        };
+        let is_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+        if !is_digits(int) || !is_digits(frac) || int.len() + frac.len() == 0 {
+            return Err(ParseNumericError(()));
+        }
+
+        let scale = (frac.len() as i64 - exponent).max(0);
+        let scale = u16::try_from(scale)
+            .ok()
+            .filter(|&scale| scale <= MAX_SCALE)
+            .ok_or(ParseNumericError(()))?;
+
+        // align the digits into groups of 4 around the decimal point
+        let point = int.len() as i64 + exponent;
+        let pad = (4 - point.rem_euclid(4)) % 4;
+        let mut decimal = "0".repeat(pad as usize);
+        decimal.push_str(int);
+        decimal.push_str(frac);
+        while decimal.len() % 4 != 0 {
+            decimal.push('0');
+        }
+        let mut weight = (point + pad) / 4 - 1;
+
+        let mut digits = decimal
+            .as_bytes()
+            .chunks(4)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .fold(0i16, |acc, &b| acc * 10 + i16::from(b - b'0'))
+            })
+            .collect::<Vec<_>>();
+        let leading = digits.iter().take_while(|&&d| d == 0).count();
+        digits.drain(..leading);
+        weight -= leading as i64;
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+
+        if digits.is_empty() {
+            return Ok(PgNumeric {
+                sign: POSITIVE,
+                weight: 0,
+                scale,
+                digits,
+            });
+        }
+
+        Ok(PgNumeric {
+            sign: if negative { NEGATIVE } else { POSITIVE },
+            weight: i16::try_from(weight).map_err(|_| ParseNumericError(()))?,
+            scale,
+            digits,
+        })
+    }
+}
+
+impl fmt::Display for PgNumeric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.sign {
+            NAN => return f.write_str("NaN"),
+            POS_INFINITY => return f.write_str("Infinity"),
+            NEG_INFINITY => return f.write_str("-Infinity"),
+            NEGATIVE => f.write_char('-')?,
+            _ => {}
+        }
+
+        let digit = |i: i64| {
+            usize::try_from(i)
+                .ok()
+                .and_then(|i| self.digits.get(i))
+                .copied()
+                .unwrap_or(0)
+        };
+
+        let weight = i64::from(self.weight);
+        if weight < 0 {
+            f.write_char('0')?;
+        } else {
+            write!(f, "{}", digit(0))?;
+            for i in 1..=weight {
+                write!(f, "{:04}", digit(i))?;
+            }
+        }
+
+        if self.scale > 0 {
+            let mut frac = String::new();
+            let groups = (i64::from(self.scale) + 3) / 4;
+            for i in 0..groups {
+                let _ = write!(frac, "{:04}", digit(weight + 1 + i));
+            }
+            frac.truncate(usize::from(self.scale));
+            write!(f, ".{}", frac)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for PgNumeric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl<'a> FromSql<'a> for PgNumeric {
+    fn from_sql(_: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        if raw.len() < 8 {
+            return Err("invalid buffer size".into());
+        }
+        let count = u16::from_be_bytes([raw[0], raw[1]]);
+        let weight = i16::from_be_bytes([raw[2], raw[3]]);
+        let sign = u16::from_be_bytes([raw[4], raw[5]]);
+        let scale = u16::from_be_bytes([raw[6], raw[7]]);
+        if raw.len() != 8 + usize::from(count) * 2 {
+            return Err("invalid message length: numeric not drained".into());
+        }
+        match sign {
+            POSITIVE | NEGATIVE => {}
+            // the weight and scale of special values are unspecified
+            NAN | POS_INFINITY | NEG_INFINITY => return Ok(PgNumeric::special(sign)),
+            _ => return Err("invalid numeric sign".into()),
+        }
+
+        let digits = raw[8..]
+            .chunks(2)
+            .map(|chunk| i16::from_be_bytes([chunk[0], chunk[1]]))
+            .collect::<Vec<_>>();
+        if digits.iter().any(|d| !(0..10000).contains(d)) {
+            return Err("invalid numeric digit".into());
+        }
+
+        Ok(PgNumeric {
+            sign,
+            weight,
+            scale,
+            digits,
+        })
+    }
+
+    accepts!(NUMERIC);
+}
+
+impl ToSql for PgNumeric {
+    fn to_sql(&self, _: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        let count = i16::try_from(self.digits.len()).map_err(|_| "value too large to transmit")?;
+        out.put_i16(count);
+        out.put_i16(self.weight);
+        out.put_u16(self.sign);
+        out.put_u16(self.scale);
+        for &digit in &self.digits {
+            out.put_i16(digit);
+        }
+        Ok(IsNull::No)
+    }
+
+    accepts!(NUMERIC);
+
+    to_sql_checked!();
+}
+
+macro_rules! lossy_from_sql {
+    ($t:ty) => {
+        impl<'a> FromSql<'a> for Lossy<$t> {
+            fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+                let numeric = PgNumeric::from_sql(ty, raw)?;
+                let value = match numeric.sign {
+                    NAN => <$t>::NAN,
+                    POS_INFINITY => <$t>::INFINITY,
+                    NEG_INFINITY => <$t>::NEG_INFINITY,
+                    // parsing the decimal representation rounds it correctly
+                    _ => numeric.to_string().parse()?,
+                };
+                Ok(Lossy(value))
+            }
+
+            accepts!(NUMERIC);
+        }
+    };
+}
+
+lossy_from_sql!(f32);
+lossy_from_sql!(f64);
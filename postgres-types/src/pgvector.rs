@@ -0,0 +1,208 @@
+//! Types of the `pgvector` extension.
+
+use bytes::{Buf, BufMut, BytesMut};
+use half_2::f16;
+use std::convert::TryFrom;
+use std::error::Error;
+
+use crate::{FromSql, IsNull, ToSql, Type};
+
+/// The `vector` type of the `pgvector` extension, a vector of single precision floating point numbers.
+///
+/// As the types of extensions have no fixed OIDs, values are matched with Postgres types by name.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Vector(pub Vec<f32>);
+
+/// The `halfvec` type of the `pgvector` extension, a vector of half precision floating point numbers.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct HalfVector(pub Vec<f16>);
+
+/// The `sparsevec` type of the `pgvector` extension, a vector storing only its nonzero elements.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SparseVector {
+    /// The number of dimensions of the vector.
+    pub dim: u32,
+    /// The zero-based indices of the nonzero elements, in ascending order.
+    pub indices: Vec<u32>,
+    /// The values of the nonzero elements, matching `indices`.
+    pub values: Vec<f32>,
+}
+
+impl From<Vec<f32>> for Vector {
+    fn from(v: Vec<f32>) -> Vector {
+        Vector(v)
+    }
+}
+
+impl From<Vector> for Vec<f32> {
+    fn from(v: Vector) -> Vec<f32> {
+        v.0
+    }
+}
+
+impl From<Vec<f16>> for HalfVector {
+    fn from(v: Vec<f16>) -> HalfVector {
+        HalfVector(v)
+    }
+}
+
+impl From<HalfVector> for Vec<f16> {
+    fn from(v: HalfVector) -> Vec<f16> {
+        v.0
+    }
+}
+
+impl SparseVector {
+    /// Creates a sparse vector from the elements of a dense one.
+    pub fn from_dense(v: &[f32]) -> SparseVector {
+        let (indices, values) = v
+            .iter()
+            .enumerate()
+            .filter(|(_, &value)| value != 0.)
+            .map(|(i, &value)| (i as u32, value))
+            .unzip();
+        SparseVector {
+            dim: v.len() as u32,
+            indices,
+            values,
+        }
+    }
+
+    /// Returns the elements of the vector, including its zeros.
+    pub fn to_dense(&self) -> Vec<f32> {
+        let mut v = vec![0.; self.dim as usize];
+        for (&i, &value) in self.indices.iter().zip(&self.values) {
+            if let Some(element) = v.get_mut(i as usize) {
+                *element = value;
+            }
+        }
+        v
+    }
+}
+
+// Reads the header of a vector or half vector, returning the number of dimensions.
+fn dim_from_sql(raw: &mut &[u8], size: usize) -> Result<usize, Box<dyn Error + Sync + Send>> {
+    if raw.len() < 4 {
+        return Err("invalid buffer size".into());
+    }
+    let dim = usize::from(raw.get_u16());
+    raw.advance(2);
+    if raw.len() != dim * size {
+        return Err("invalid message length: vector not drained".into());
+    }
+    Ok(dim)
+}
+
+fn dim_to_sql(dim: usize, out: &mut BytesMut) -> Result<(), Box<dyn Error + Sync + Send>> {
+    let dim = u16::try_from(dim).map_err(|_| "value too large to transmit")?;
+    out.put_u16(dim);
+    out.put_u16(0);
+    Ok(())
+}
+
+impl<'a> FromSql<'a> for Vector {
+    fn from_sql(_: &Type, mut raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        let dim = dim_from_sql(&mut raw, 4)?;
+        Ok(Vector((0..dim).map(|_| raw.get_f32()).collect()))
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        ty.name() == "vector"
+    }
+}
+
+impl ToSql for Vector {
+    fn to_sql(&self, _: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        dim_to_sql(self.0.len(), out)?;
+        for &value in &self.0 {
+            out.put_f32(value);
+        }
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        ty.name() == "vector"
+    }
+
+    to_sql_checked!();
+}
+
+impl<'a> FromSql<'a> for HalfVector {
+    fn from_sql(_: &Type, mut raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        let dim = dim_from_sql(&mut raw, 2)?;
+        Ok(HalfVector(
+            (0..dim).map(|_| f16::from_bits(raw.get_u16())).collect(),
+        ))
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        ty.name() == "halfvec"
+    }
+}
+
+impl ToSql for HalfVector {
+    fn to_sql(&self, _: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        dim_to_sql(self.0.len(), out)?;
+        for &value in &self.0 {
+            out.put_u16(value.to_bits());
+        }
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        ty.name() == "halfvec"
+    }
+
+    to_sql_checked!();
+}
+
+impl<'a> FromSql<'a> for SparseVector {
+    fn from_sql(_: &Type, mut raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        if raw.len() < 12 {
+            return Err("invalid buffer size".into());
+        }
+        let dim = raw.get_u32();
+        let nnz = usize::try_from(raw.get_u32())?;
+        raw.advance(4);
+        if raw.len() != nnz * 8 {
+            return Err("invalid message length: sparse vector not drained".into());
+        }
+        let indices = (0..nnz).map(|_| raw.get_u32()).collect();
+        let values = (0..nnz).map(|_| raw.get_f32()).collect();
+
+        Ok(SparseVector {
+            dim,
+            indices,
+            values,
+        })
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        ty.name() == "sparsevec"
+    }
+}
+
+impl ToSql for SparseVector {
+    fn to_sql(&self, _: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        if self.indices.len() != self.values.len() {
+            return Err("sparse vector indices and values have different lengths".into());
+        }
+        let nnz = u32::try_from(self.indices.len()).map_err(|_| "value too large to transmit")?;
+        out.put_u32(self.dim);
+        out.put_u32(nnz);
+        out.put_u32(0);
+        for &i in &self.indices {
+            out.put_u32(i);
+        }
+        for &value in &self.values {
+            out.put_f32(value);
+        }
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        ty.name() == "sparsevec"
+    }
+
+    to_sql_checked!();
+}
@@ -32,3 +32,14 @@ where
     };
     T::from_sql_nullable(type_, value)
 }
+
+pub fn skip_value(buf: &mut &[u8]) -> Result<(), Box<dyn Error + Sync + Send>> {
+    let len = read_be_i32(buf)?;
+    if len > 0 {
+        if len as usize > buf.len() {
+            return Err("invalid buffer size".into());
+        }
+        *buf = &buf[len as usize..];
+    }
+    Ok(())
+}
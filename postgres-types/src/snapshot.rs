@@ -0,0 +1,89 @@
+//! Transaction snapshot `PG_SNAPSHOT` and `TXID_SNAPSHOT` types.
+
+use bytes::{Buf, BufMut, BytesMut};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+
+use crate::{FromSql, IsNull, ToSql, Type};
+
+/// Postgres `PG_SNAPSHOT` type, also used for the legacy `TXID_SNAPSHOT` type.
+///
+/// A snapshot records which transactions were visible at a point in time, such as the one returned by
+/// `pg_current_snapshot()`. Transaction IDs are the 64 bit IDs returned by `pg_current_xact_id()`, which include the
+/// epoch and so never wrap around.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PgSnapshot {
+    /// The earliest transaction ID that was still active. All earlier transactions are either committed and visible,
+    /// or rolled back.
+    pub xmin: u64,
+    /// One past the latest completed transaction ID. All transactions with this ID or later had not completed.
+    pub xmax: u64,
+    /// The IDs of the transactions between `xmin` and `xmax` which were in progress, in ascending order.
+    pub xip: Vec<u64>,
+}
+
+impl PgSnapshot {
+    /// Determines if the effects of a committed transaction are visible in the snapshot.
+    ///
+    /// This matches the server's `pg_visible_in_snapshot` function.
+    pub fn is_visible(&self, xid: u64) -> bool {
+        if xid < self.xmin {
+            true
+        } else if xid >= self.xmax {
+            false
+        } else {
+            self.xip.binary_search(&xid).is_err()
+        }
+    }
+}
+
+impl fmt::Display for PgSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:", self.xmin, self.xmax)?;
+        for (i, xid) in self.xip.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}", xid)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> FromSql<'a> for PgSnapshot {
+    fn from_sql(_: &Type, mut raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        if raw.len() < 20 {
+            return Err("invalid buffer size".into());
+        }
+        let nxip = raw.get_i32();
+        let xmin = raw.get_u64();
+        let xmax = raw.get_u64();
+        let nxip = usize::try_from(nxip).map_err(|_| "invalid snapshot size")?;
+        if raw.len() != nxip * 8 {
+            return Err("invalid message length: snapshot not drained".into());
+        }
+        let xip = raw.chunks(8).map(|mut chunk| chunk.get_u64()).collect();
+
+        Ok(PgSnapshot { xmin, xmax, xip })
+    }
+
+    accepts!(PG_SNAPSHOT, TXID_SNAPSHOT);
+}
+
+impl ToSql for PgSnapshot {
+    fn to_sql(&self, _: &Type, out: &mut BytesMut) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        let nxip = i32::try_from(self.xip.len()).map_err(|_| "value too large to transmit")?;
+        out.put_i32(nxip);
+        out.put_u64(self.xmin);
+        out.put_u64(self.xmax);
+        for &xid in &self.xip {
+            out.put_u64(xid);
+        }
+        Ok(IsNull::No)
+    }
+
+    accepts!(PG_SNAPSHOT, TXID_SNAPSHOT);
+
+    to_sql_checked!();
+}
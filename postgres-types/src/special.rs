@@ -101,3 +101,53 @@ impl<T: ToSql> ToSql for Timestamp<T> {
 
     to_sql_checked!();
 }
+
+/// A wrapper that can be used to represent infinity with `Type::Interval` types.
+///
+/// Infinite intervals are supported by Postgres 17 and later.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Interval<T> {
+    /// Represents `infinity`, an interval that is longer than all other intervals.
+    PosInfinity,
+    /// Represents `-infinity`, an interval that is shorter than all other intervals.
+    NegInfinity,
+    /// The wrapped interval.
+    Value(T),
+}
+
+impl<'a, T: FromSql<'a>> FromSql<'a> for Interval<T> {
+    fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn Error + Sync + Send>> {
+        let interval = types::interval_from_sql(raw)?;
+        match (interval.microseconds(), interval.days(), interval.months()) {
+            (i64::MAX, i32::MAX, i32::MAX) => Ok(Interval::PosInfinity),
+            (i64::MIN, i32::MIN, i32::MIN) => Ok(Interval::NegInfinity),
+            _ => T::from_sql(ty, raw).map(Interval::Value),
+        }
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::INTERVAL && T::accepts(ty)
+    }
+}
+
+impl<T: ToSql> ToSql for Interval<T> {
+    fn to_sql(
+        &self,
+        ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+        match *self {
+            Interval::PosInfinity => types::interval_to_sql(i64::MAX, i32::MAX, i32::MAX, out),
+            Interval::NegInfinity => types::interval_to_sql(i64::MIN, i32::MIN, i32::MIN, out),
+            Interval::Value(ref v) => return v.to_sql(ty, out),
+        }
+
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::INTERVAL && T::accepts(ty)
+    }
+
+    to_sql_checked!();
+}
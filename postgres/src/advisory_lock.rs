@@ -0,0 +1,59 @@
+use crate::Client;
+use std::ops::{Deref, DerefMut};
+use tokio_postgres::{AdvisoryLockKey, Error};
+
+/// A guard for a session-level advisory lock.
+///
+/// The lock is released when the guard is dropped. Use the `unlock` method to release it explicitly and observe any
+/// error encountered. The guard dereferences to the `Client` which holds the lock, so it can continue to be used while
+/// the lock is held.
+pub struct AdvisoryLockGuard<'a> {
+    client: &'a mut Client,
+    key: AdvisoryLockKey,
+    done: bool,
+}
+
+impl<'a> Drop for AdvisoryLockGuard<'a> {
+    fn drop(&mut self) {
+        if !self.done {
+            let _ = self.client.advisory_unlock(self.key);
+        }
+    }
+}
+
+impl<'a> Deref for AdvisoryLockGuard<'a> {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        self.client
+    }
+}
+
+impl<'a> DerefMut for AdvisoryLockGuard<'a> {
+    fn deref_mut(&mut self) -> &mut Client {
+        self.client
+    }
+}
+
+impl<'a> AdvisoryLockGuard<'a> {
+    pub(crate) fn new(client: &'a mut Client, key: AdvisoryLockKey) -> AdvisoryLockGuard<'a> {
+        AdvisoryLockGuard {
+            client,
+            key,
+            done: false,
+        }
+    }
+
+    /// Returns the key of the lock held by this guard.
+    pub fn key(&self) -> AdvisoryLockKey {
+        self.key
+    }
+
+    /// Consumes the guard, releasing the lock.
+    ///
+    /// This is equivalent to the guard's `Drop` implementation, but provides any error encountered to the caller.
+    pub fn unlock(mut self) -> Result<(), Error> {
+        self.done = true;
+        self.client.advisory_unlock(self.key).map(|_| ())
+    }
+}
@@ -1,13 +1,22 @@
 use crate::connection::Connection;
+use crate::replication::{BaseBackupIter, IdentifySystem, ReplicationIter, TimelineHistory};
 use crate::{
-    CancelToken, Config, CopyInWriter, CopyOutReader, Notifications, RowIter, Statement,
-    ToStatement, Transaction, TransactionBuilder,
+    AdvisoryLockGuard, CancelToken, Config, CopyInWriter, CopyOutReader, Notifications, RowIter,
+    Statement, ToStatement, Transaction, TransactionBuilder,
 };
+use futures::TryStreamExt;
+use std::mem;
 use std::task::Poll;
 use std::time::Duration;
 use tokio_postgres::tls::{MakeTlsConnect, TlsConnect};
-use tokio_postgres::types::{BorrowToSql, ToSql, Type};
-use tokio_postgres::{Error, Row, SimpleQueryMessage, Socket};
+use tokio_postgres::types::{BorrowToSql, FromSqlOwned, Oid, PgLsn, ToSql, Type};
+use tokio_postgres::{
+    AdvisoryLockKey, BindParam, CommandTag, Error, MessageStats, NamedParams, ParameterScope,
+    PreparedTransaction, QueryCache, QueryLogger, RetryPolicy, Row, SimpleQueryMessage,
+    SimpleResultSet, Socket, TransactionStatus,
+};
+#[cfg(feature = "with-serde_json-1")]
+use tokio_postgres::{ExplainOptions, QueryPlan};
 
 /// A synchronous PostgreSQL client.
 pub struct Client {
@@ -57,9 +66,8 @@ impl Client {
     /// repeatedly executed (perhaps with different query parameters), consider preparing the statement up front
     /// with the `prepare` method.
     ///
-    /// # Panics
-    ///
-    /// Panics if the number of parameters provided does not match the number expected.
+    /// Returns an error if the number of parameters provided does not match the number expected, or if any of them
+    /// has a type incompatible with the statement.
     ///
     /// # Example
     ///
@@ -87,7 +95,7 @@ impl Client {
         self.connection.block_on(self.client.execute(query, params))
     }
 
-    /// Executes a statement, returning the resulting rows.
+    /// The maximally flexible version of [`execute`].
     ///
     /// A statement may contain parameters, specified by `$n`, where `n` is the index of the parameter of the list
     /// provided, 1-indexed.
@@ -96,9 +104,80 @@ impl Client {
     /// repeatedly executed (perhaps with different query parameters), consider preparing the statement up front
     /// with the `prepare` method.
     ///
-    /// # Panics
+    /// Returns an error if the number of parameters provided does not match the number expected, or if any of them
+    /// has a type incompatible with the statement.
+    ///
+    /// [`execute`]: #method.execute
+    pub fn execute_raw<T, P, I>(&mut self, query: &T, params: I) -> Result<u64, Error>
+    where
+        T: ?Sized + ToStatement,
+        P: BorrowToSql,
+        I: IntoIterator<Item = P>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        self.connection
+            .block_on(self.client.execute_raw(query, params))
+    }
+
+    /// Like `execute`, but returns the full command tag sent by the server rather than just the number of rows.
+    ///
+    /// This distinguishes the kind of command which was executed, for example `INSERT 0 1` from `UPDATE 1`.
+    pub fn execute_with_tag<T>(
+        &mut self,
+        query: &T,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<CommandTag, Error>
+    where
+        T: ?Sized + ToStatement,
+    {
+        self.connection
+            .block_on(self.client.execute_with_tag(query, params))
+    }
+
+    /// Like `execute`, but binds parameters by name rather than position.
+    ///
+    /// See `query_named` for details.
+    pub fn execute_named<P>(&mut self, query: &str, params: &P) -> Result<u64, Error>
+    where
+        P: ?Sized + NamedParams,
+    {
+        self.connection
+            .block_on(self.client.execute_named(query, params))
+    }
+
+    /// Like [`execute`], but allows some parameter values to be streamed to the server rather than encoded up front.
+    ///
+    /// Streamed values are read only as they are written to the connection, so large `BYTEA` or text values don't need
+    /// to be held in memory. If a stream fails or produces a different number of bytes than its declared length, the
+    /// statement is not executed and the error is returned.
+    ///
+    /// Returns an error if the number of parameters provided does not match the number expected, or if any of them
+    /// has a type incompatible with the statement.
+    ///
+    /// [`execute`]: #method.execute
+    pub fn execute_streamed<T>(
+        &mut self,
+        query: &T,
+        params: Vec<BindParam<'_>>,
+    ) -> Result<u64, Error>
+    where
+        T: ?Sized + ToStatement,
+    {
+        self.connection
+            .block_on(self.client.execute_streamed(query, params))
+    }
+
+    /// Executes a statement, returning the resulting rows.
+    ///
+    /// A statement may contain parameters, specified by `$n`, where `n` is the index of the parameter of the list
+    /// provided, 1-indexed.
+    ///
+    /// The `query` argument can either be a `Statement`, or a raw query string. If the same statement will be
+    /// repeatedly executed (perhaps with different query parameters), consider preparing the statement up front
+    /// with the `prepare` method.
     ///
-    /// Panics if the number of parameters provided does not match the number expected.
+    /// Returns an error if the number of parameters provided does not match the number expected, or if any of them
+    /// has a type incompatible with the statement.
     ///
     /// # Examples
     ///
@@ -123,6 +202,46 @@ impl Client {
         self.connection.block_on(self.client.query(query, params))
     }
 
+    /// Like `query`, but binds parameters by name rather than position.
+    ///
+    /// Parameters are written as `:name` in the statement, which is rewritten to use positional parameters as
+    /// described in [`NamedQuery`]. An error is returned if a value is missing for any of the statement's parameters,
+    /// or if any of the values provided is not used by the statement.
+    ///
+    /// [`NamedQuery`]: crate::NamedQuery
+    pub fn query_named<P>(&mut self, query: &str, params: &P) -> Result<Vec<Row>, Error>
+    where
+        P: ?Sized + NamedParams,
+    {
+        self.connection
+            .block_on(self.client.query_named(query, params))
+    }
+
+    /// Like `query`, but consults the client's query cache before executing the statement.
+    ///
+    /// Results are cached under the text of the statement and the values of its parameters, and are returned from the
+    /// cache until `ttl` has passed. Rows are not invalidated when the underlying data changes, so this is only suitable
+    /// for read-mostly lookups which can tolerate stale results. If no cache has been set with `set_query_cache`, the
+    /// statement is always executed.
+    ///
+    /// A raw query string must be prepared to determine the types of its parameters, even if the result is cached, so
+    /// prefer passing a `Statement` prepared up front.
+    ///
+    /// Returns an error if the number of parameters provided does not match the number expected, or if any of them
+    /// has a type incompatible with the statement.
+    pub fn query_cached<T>(
+        &mut self,
+        query: &T,
+        params: &[&(dyn ToSql + Sync)],
+        ttl: Duration,
+    ) -> Result<Vec<Row>, Error>
+    where
+        T: ?Sized + ToStatement,
+    {
+        self.connection
+            .block_on(self.client.query_cached(query, params, ttl))
+    }
+
     /// Executes a statement which returns a single row, returning it.
     ///
     /// Returns an error if the query does not return exactly one row.
@@ -134,9 +253,8 @@ impl Client {
     /// repeatedly executed (perhaps with different query parameters), consider preparing the statement up front
     /// with the `prepare` method.
     ///
-    /// # Panics
-    ///
-    /// Panics if the number of parameters provided does not match the number expected.
+    /// Returns an error if the number of parameters provided does not match the number expected, or if any of them
+    /// has a type incompatible with the statement.
     ///
     /// # Examples
     ///
@@ -172,9 +290,8 @@ impl Client {
     /// repeatedly executed (perhaps with different query parameters), consider preparing the statement up front
     /// with the `prepare` method.
     ///
-    /// # Panics
-    ///
-    /// Panics if the number of parameters provided does not match the number expected.
+    /// Returns an error if the number of parameters provided does not match the number expected, or if any of them
+    /// has a type incompatible with the statement.
     ///
     /// # Examples
     ///
@@ -208,14 +325,48 @@ impl Client {
             .block_on(self.client.query_opt(query, params))
     }
 
+    /// Executes a statement, combining its rows into a single value with the provided function as they are received.
+    ///
+    /// This is like calling `Iterator::fold` on the rows returned by `query`, but rows are processed one at a time
+    /// as they arrive from the server rather than being collected first, so memory use does not grow with the size of
+    /// the result set. If the function returns an error, the remaining rows are discarded and the error is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use postgres::{Client, NoTls};
+    ///
+    /// # fn main() -> Result<(), postgres::Error> {
+    /// let mut client = Client::connect("host=localhost user=postgres", NoTls)?;
+    ///
+    /// let total = client.query_fold("SELECT amount FROM payments", &[], 0i64, |total, row| {
+    ///     Ok(total + row.try_get::<_, i64>(0)?)
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn query_fold<T, B, F>(
+        &mut self,
+        query: &T,
+        params: &[&(dyn ToSql + Sync)],
+        init: B,
+        f: F,
+    ) -> Result<B, Error>
+    where
+        T: ?Sized + ToStatement,
+        F: FnMut(B, Row) -> Result<B, Error>,
+    {
+        self.connection
+            .block_on(self.client.query_fold(query, params, init, f))
+    }
+
     /// A maximally-flexible version of `query`.
     ///
     /// It takes an iterator of parameters rather than a slice, and returns an iterator of rows rather than collecting
     /// them into an array.
     ///
-    /// # Panics
-    ///
-    /// Panics if the number of parameters provided does not match the number expected.
+    /// Returns an error if the number of parameters provided does not match the number expected, or if any of them
+    /// has a type incompatible with the statement.
     ///
     /// # Examples
     ///
@@ -277,6 +428,47 @@ impl Client {
         Ok(RowIter::new(self.connection.as_ref(), stream))
     }
 
+    /// Like `query`, but with the type of each parameter specified explicitly.
+    ///
+    /// The query is prepared with the provided parameter types, as with `prepare_typed`, rather than having the server
+    /// infer them. The query is always prepared as the unnamed statement, and is never cached.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use postgres::{Client, NoTls};
+    /// use postgres::types::Type;
+    ///
+    /// # fn main() -> Result<(), postgres::Error> {
+    /// let mut client = Client::connect("host=localhost user=postgres", NoTls)?;
+    ///
+    /// let rows = client.query_typed("SELECT $1 || $2", &[(&"foo", Type::TEXT), (&"bar", Type::TEXT)])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn query_typed(
+        &mut self,
+        query: &str,
+        params: &[(&(dyn ToSql + Sync), Type)],
+    ) -> Result<Vec<Row>, Error> {
+        self.connection
+            .block_on(self.client.query_typed(query, params))
+    }
+
+    /// The maximally flexible version of [`query_typed`].
+    ///
+    /// [`query_typed`]: #method.query_typed
+    pub fn query_typed_raw<P, I>(&mut self, query: &str, params: I) -> Result<RowIter<'_>, Error>
+    where
+        P: BorrowToSql,
+        I: IntoIterator<Item = (P, Type)>,
+    {
+        let stream = self
+            .connection
+            .block_on(self.client.query_typed_raw(query, params))?;
+        Ok(RowIter::new(self.connection.as_ref(), stream))
+    }
+
     /// Creates a new prepared statement.
     ///
     /// Prepared statements can be executed repeatedly, and may contain query parameters (indicated by `$1`, `$2`, etc),
@@ -336,6 +528,80 @@ impl Client {
             .block_on(self.client.prepare_typed(query, types))
     }
 
+    /// Calls a server function directly using the fastpath function call protocol.
+    ///
+    /// The function is identified by its OID, and each parameter is paired with the type the function expects for it.
+    /// Unlike a query, the call requires no statement to be parsed or planned, and completes in a single round trip.
+    /// The function's return value is decoded as `result_type`.
+    ///
+    /// Only functions returning a single value, rather than a set or a row, can be called in this way.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use postgres::{Client, NoTls};
+    /// use postgres::types::Type;
+    ///
+    /// # fn main() -> Result<(), postgres::Error> {
+    /// let mut client = Client::connect("host=localhost user=postgres", NoTls)?;
+    ///
+    /// // the OID of `pg_catalog.int4pl`
+    /// let sum: i32 = client.function_call(177, &[(&1i32, Type::INT4), (&2i32, Type::INT4)], &Type::INT4)?;
+    /// assert_eq!(sum, 3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn function_call<T>(
+        &mut self,
+        function: Oid,
+        params: &[(&(dyn ToSql + Sync), Type)],
+        result_type: &Type,
+    ) -> Result<T, Error>
+    where
+        T: FromSqlOwned,
+    {
+        self.connection
+            .block_on(self.client.function_call(function, params, result_type))
+    }
+
+    /// Returns the plan chosen by the server for a statement, using `EXPLAIN`.
+    ///
+    /// The statement is inserted into the `EXPLAIN` statement verbatim, and may contain parameters. If the options
+    /// request the statement to be analyzed, it is actually executed, including any side effects.
+    ///
+    /// Requires the `with-serde_json-1` feature.
+    #[cfg(feature = "with-serde_json-1")]
+    pub fn explain(
+        &mut self,
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+        options: &ExplainOptions,
+    ) -> Result<QueryPlan, Error> {
+        self.connection
+            .block_on(self.client.explain(statement, params, options))
+    }
+
+    /// Calls a stored procedure with the `CALL` statement.
+    ///
+    /// The procedure name is inserted into the statement verbatim, so it may be schema-qualified but must not come
+    /// from untrusted input. One parameter must be provided for each of the procedure's arguments, including `OUT`
+    /// arguments, for which `None` is conventionally passed. If the procedure has `INOUT` or `OUT` arguments, their
+    /// final values are returned as a row.
+    ///
+    /// A procedure may issue `COMMIT` or `ROLLBACK` itself only when it is not called from within a transaction;
+    /// otherwise the server will report an error.
+    ///
+    /// Returns an error if the number of parameters provided does not match the number expected, or if any of them
+    /// has a type incompatible with the statement.
+    pub fn call(
+        &mut self,
+        procedure: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Option<Row>, Error> {
+        self.connection
+            .block_on(self.client.call(procedure, params))
+    }
+
     /// Executes a `COPY FROM STDIN` statement, returning the number of rows created.
     ///
     /// The `query` argument can either be a `Statement`, or a raw query string. The data in the provided reader is
@@ -395,6 +661,69 @@ impl Client {
         Ok(CopyOutReader::new(self.connection.as_ref(), stream))
     }
 
+    /// Starts streaming changes from a logical replication slot.
+    ///
+    /// Streaming begins at `start_lsn`, or at the slot's confirmed position if that is later. The `options` are passed
+    /// to the slot's output plugin. The connection must have been opened with `ReplicationMode::Logical`. Use
+    /// `ReplicationIter::logical` to decode the messages of the `pgoutput` plugin.
+    pub fn start_logical_replication(
+        &mut self,
+        slot: &str,
+        start_lsn: PgLsn,
+        options: &[(&str, &str)],
+    ) -> Result<ReplicationIter<'_>, Error> {
+        let stream = self.connection.block_on(
+            self.client
+                .start_logical_replication(slot, start_lsn, options),
+        )?;
+        Ok(ReplicationIter::new(self.connection.as_ref(), stream))
+    }
+
+    /// Starts streaming WAL from the server for physical replication.
+    ///
+    /// Streaming begins at `start_lsn` on the specified timeline, or on the server's current timeline if `None`. The
+    /// connection must have been opened with `ReplicationMode::Physical`.
+    pub fn start_physical_replication(
+        &mut self,
+        slot: Option<&str>,
+        start_lsn: PgLsn,
+        timeline: Option<u32>,
+    ) -> Result<ReplicationIter<'_>, Error> {
+        let stream = self.connection.block_on(
+            self.client
+                .start_physical_replication(slot, start_lsn, timeline),
+        )?;
+        Ok(ReplicationIter::new(self.connection.as_ref(), stream))
+    }
+
+    /// Requests identifying information about the server with the `IDENTIFY_SYSTEM` replication command.
+    ///
+    /// The connection must have been opened in replication mode.
+    pub fn identify_system(&mut self) -> Result<IdentifySystem, Error> {
+        self.connection.block_on(self.client.identify_system())
+    }
+
+    /// Requests the history file of a timeline with the `TIMELINE_HISTORY` replication command.
+    ///
+    /// The connection must have been opened in replication mode.
+    pub fn timeline_history(&mut self, timeline: u32) -> Result<TimelineHistory, Error> {
+        self.connection
+            .block_on(self.client.timeline_history(timeline))
+    }
+
+    /// Starts a base backup of the database cluster with the `BASE_BACKUP` replication command.
+    ///
+    /// Each option is a name, which must consist of only ASCII letters and underscores, and an optional value, which
+    /// is quoted as a string literal. Requires PostgreSQL 15 or newer. The connection must have been opened with
+    /// `ReplicationMode::Physical`.
+    pub fn base_backup(
+        &mut self,
+        options: &[(&str, Option<&str>)],
+    ) -> Result<BaseBackupIter<'_>, Error> {
+        let stream = self.connection.block_on(self.client.base_backup(options))?;
+        Ok(BaseBackupIter::new(self.connection.as_ref(), stream))
+    }
+
     /// Executes a sequence of SQL statements using the simple query protocol.
     ///
     /// Statements should be separated by semicolons. If an error occurs, execution of the sequence will stop at that
@@ -414,6 +743,20 @@ impl Client {
         self.connection.block_on(self.client.simple_query(query))
     }
 
+    /// Like `simple_query`, but groups the rows returned by each statement into a result set.
+    ///
+    /// Each statement in the query produces one result set, containing the columns and rows it returned, if any.
+    pub fn simple_query_result_sets(&mut self, query: &str) -> Result<Vec<SimpleResultSet>, Error> {
+        let client = &self.client;
+        self.connection.block_on(async {
+            client
+                .simple_query_result_sets(query)
+                .await?
+                .try_collect()
+                .await
+        })
+    }
+
     /// Validates the connection by performing a simple no-op query.
     ///
     /// If the specified timeout is reached before the backend responds, an error will be returned.
@@ -442,6 +785,149 @@ impl Client {
         self.connection.block_on(self.client.batch_execute(query))
     }
 
+    /// Commits a transaction previously prepared for two-phase commit with `Transaction::prepare_two_phase`.
+    ///
+    /// The prepared transaction does not need to have been created by this connection, but this method cannot be
+    /// called from within a transaction.
+    pub fn commit_prepared(&mut self, gid: &str) -> Result<(), Error> {
+        self.connection.block_on(self.client.commit_prepared(gid))
+    }
+
+    /// Rolls back a transaction previously prepared for two-phase commit with `Transaction::prepare_two_phase`.
+    ///
+    /// The prepared transaction does not need to have been created by this connection, but this method cannot be
+    /// called from within a transaction.
+    pub fn rollback_prepared(&mut self, gid: &str) -> Result<(), Error> {
+        self.connection.block_on(self.client.rollback_prepared(gid))
+    }
+
+    /// Returns the transactions currently prepared for two-phase commit in the database cluster.
+    ///
+    /// These are the "in-doubt" transactions which still need to be resolved with `commit_prepared` or
+    /// `rollback_prepared`.
+    pub fn prepared_transactions(&mut self) -> Result<Vec<PreparedTransaction>, Error> {
+        self.connection
+            .block_on(self.client.prepared_transactions())
+    }
+
+    /// Acquires a session-level advisory lock, waiting until it becomes available.
+    ///
+    /// The lock is held until the returned guard is dropped or explicitly unlocked. Advisory locks are reentrant, so
+    /// acquiring the same key multiple times requires an equal number of unlocks.
+    pub fn advisory_lock<K>(&mut self, key: K) -> Result<AdvisoryLockGuard<'_>, Error>
+    where
+        K: Into<AdvisoryLockKey>,
+    {
+        let key = key.into();
+        let guard = self.connection.block_on(self.client.advisory_lock(key))?;
+        // ownership of the lock is transferred to the blocking guard
+        mem::forget(guard);
+        Ok(AdvisoryLockGuard::new(self, key))
+    }
+
+    /// Attempts to acquire a session-level advisory lock without waiting.
+    ///
+    /// Returns `None` if the lock is currently held by another session.
+    pub fn try_advisory_lock<K>(&mut self, key: K) -> Result<Option<AdvisoryLockGuard<'_>>, Error>
+    where
+        K: Into<AdvisoryLockKey>,
+    {
+        let key = key.into();
+        let locked = self
+            .connection
+            .block_on(self.client.try_advisory_lock(key))?
+            .map(mem::forget)
+            .is_some();
+        if locked {
+            Ok(Some(AdvisoryLockGuard::new(self, key)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Releases a session-level advisory lock.
+    ///
+    /// Returns `false` if the lock was not held by this session. This is only needed for locks which are not tracked
+    /// by an `AdvisoryLockGuard`.
+    pub fn advisory_unlock<K>(&mut self, key: K) -> Result<bool, Error>
+    where
+        K: Into<AdvisoryLockKey>,
+    {
+        self.connection.block_on(self.client.advisory_unlock(key))
+    }
+
+    /// Returns the current write-ahead log insert position of the server.
+    ///
+    /// Calling this after a write on a primary returns a position which a standby must have replayed before the write
+    /// is visible there. See `wait_for_lsn`.
+    pub fn current_wal_insert_lsn(&mut self) -> Result<PgLsn, Error> {
+        self.connection
+            .block_on(self.client.current_wal_insert_lsn())
+    }
+
+    /// Returns the last write-ahead log position replayed by the server.
+    ///
+    /// Returns `None` if the server is not a standby.
+    pub fn last_wal_replay_lsn(&mut self) -> Result<Option<PgLsn>, Error> {
+        self.connection.block_on(self.client.last_wal_replay_lsn())
+    }
+
+    /// Waits until the server has replayed the write-ahead log up to the specified position.
+    ///
+    /// This can be used with a position returned by `current_wal_insert_lsn` on a primary to ensure a standby
+    /// observes the writes made before it was taken. The replay position is polled until it reaches `lsn`, and a
+    /// timeout error is returned if that doesn't happen within `timeout`. Servers which are not standbys return
+    /// immediately.
+    pub fn wait_for_lsn(&mut self, lsn: PgLsn, timeout: Duration) -> Result<(), Error> {
+        self.connection
+            .block_on(self.client.wait_for_lsn(lsn, timeout))
+    }
+
+    /// Changes the value of a configuration parameter, such as `search_path`, with the `set_config` function.
+    ///
+    /// The name and value are passed to the server as parameters, so they don't need to be quoted. The value is
+    /// interpreted as it would be in a `SET` statement, so a list such as `tenant_a, public` may be used for
+    /// `search_path`; schema names within it which require quoting must be quoted by the caller.
+    ///
+    /// Parameters changed for the session are tracked by the client so that they can later be restored with
+    /// `reset_all_parameters`.
+    pub fn set_parameter(
+        &mut self,
+        name: &str,
+        value: &str,
+        scope: ParameterScope,
+    ) -> Result<(), Error> {
+        self.connection
+            .block_on(self.client.set_parameter(name, value, scope))
+    }
+
+    /// Returns the schemas currently searched for unqualified object names, in order.
+    ///
+    /// This is the effective value of the `search_path` parameter, as returned by `current_schemas(false)`: `$user`
+    /// is replaced by the current user's schema, schemas which don't exist are omitted, and the implicitly searched
+    /// `pg_catalog` and temporary schemas are not included. The names are not quoted.
+    pub fn search_path(&mut self) -> Result<Vec<String>, Error> {
+        self.connection.block_on(self.client.search_path())
+    }
+
+    /// Resets a configuration parameter to its default value for the session, as with `RESET`.
+    pub fn reset_parameter(&mut self, name: &str) -> Result<(), Error> {
+        self.connection.block_on(self.client.reset_parameter(name))
+    }
+
+    /// Resets all of the configuration parameters changed for the session by `set_parameter` to their default
+    /// values.
+    ///
+    /// Unlike `RESET ALL`, this leaves parameters changed by other means untouched.
+    pub fn reset_all_parameters(&mut self) -> Result<(), Error> {
+        self.connection.block_on(self.client.reset_all_parameters())
+    }
+
+    /// Returns the names of the configuration parameters changed for the session by `set_parameter`, in lower case.
+    pub fn changed_parameters(&self) -> Vec<String> {
+        self.client.changed_parameters()
+    }
+
     /// Begins a new database transaction.
     ///
     /// The transaction will roll back by default - use the `commit` method to commit it.
@@ -467,6 +953,17 @@ impl Client {
         Ok(Transaction::new(self.connection.as_ref(), transaction))
     }
 
+    /// Runs a closure in a transaction, retrying it after serialization failures and deadlocks.
+    ///
+    /// This is equivalent to `client.build_transaction().run_with_retry(policy, f)`. See
+    /// [`TransactionBuilder::run_with_retry`] for details.
+    pub fn transaction_with_retry<F, T>(&mut self, policy: &RetryPolicy, f: F) -> Result<T, Error>
+    where
+        F: FnMut(&mut Transaction<'_>) -> Result<T, Error>,
+    {
+        self.build_transaction().run_with_retry(policy, f)
+    }
+
     /// Returns a builder for a transaction with custom settings.
     ///
     /// Unlike the `transaction` method, the builder can be used to control the transaction's isolation level and other
@@ -491,7 +988,7 @@ impl Client {
     /// # }
     /// ```
     pub fn build_transaction(&mut self) -> TransactionBuilder<'_> {
-        TransactionBuilder::new(self.connection.as_ref(), self.client.build_transaction())
+        TransactionBuilder::new(self.connection.as_ref(), &mut self.client)
     }
 
     /// Returns a structure providing access to asynchronous notifications.
@@ -550,6 +1047,94 @@ impl Client {
         self.client.clear_type_cache();
     }
 
+    /// Returns the number of statements in the client's statement cache.
+    ///
+    /// The cache is enabled with `Config::statement_cache_capacity`.
+    pub fn statement_cache_len(&self) -> usize {
+        self.client.statement_cache_len()
+    }
+
+    /// Clears the client's statement cache, closing the cached statements on the server unless they are still in
+    /// use.
+    pub fn clear_statement_cache(&self) {
+        self.client.clear_statement_cache();
+    }
+
+    /// Controls whether errors returned by queries record the text of the query that produced them.
+    ///
+    /// When enabled, the query is available from `Error::query` and is included in the error's `Display`
+    /// output. Disabled by default.
+    pub fn set_query_in_errors(&self, enabled: bool) {
+        self.client.set_query_in_errors(enabled);
+    }
+
+    /// Sets a function used to render query parameters into errors.
+    ///
+    /// If the query is recorded in errors (see `set_query_in_errors`), the renderer is called on each parameter
+    /// and the results are available from `Error::params`. It can be used to redact sensitive values. Parameters are
+    /// not recorded by default.
+    pub fn set_error_param_renderer<F>(&self, renderer: F)
+    where
+        F: Fn(&Type, &dyn ToSql) -> String + Sync + Send + 'static,
+    {
+        self.client.set_error_param_renderer(renderer);
+    }
+
+    /// Stops recording query parameters in errors.
+    pub fn clear_error_param_renderer(&self) {
+        self.client.clear_error_param_renderer();
+    }
+
+    /// Sets a logger notified of each statement executed by the client.
+    ///
+    /// The logger receives the text of the statement, the number of parameters, the execution time, the number of rows
+    /// affected and any error, and can be used to implement slow query logging or auditing. It is called for
+    /// statements executed through the `query`, `execute`, `simple_query` and `batch_execute` families of methods.
+    /// Statements whose rows are not completely consumed are not logged.
+    pub fn set_query_logger<L>(&self, logger: L)
+    where
+        L: QueryLogger + 'static,
+    {
+        self.client.set_query_logger(logger);
+    }
+
+    /// Removes the logger set by `set_query_logger`.
+    pub fn clear_query_logger(&self) {
+        self.client.clear_query_logger();
+    }
+
+    /// Sets a function used to render query parameters for the query logger.
+    ///
+    /// The renderer is called on each parameter of the statements passed to the logger, and the results are available
+    /// from `QueryEvent::params`. It can be used to redact sensitive values, and `render_param` can be used to render
+    /// the others as SQL literals. Parameters are not rendered by default.
+    pub fn set_log_param_renderer<F>(&self, renderer: F)
+    where
+        F: Fn(&Type, &dyn ToSql) -> String + Sync + Send + 'static,
+    {
+        self.client.set_log_param_renderer(renderer);
+    }
+
+    /// Stops rendering query parameters for the query logger.
+    pub fn clear_log_param_renderer(&self) {
+        self.client.clear_log_param_renderer();
+    }
+
+    /// Sets the cache consulted by `query_cached`.
+    ///
+    /// A cache shared between clients can be passed as an `Arc`.
+    pub fn set_query_cache<C>(&self, cache: C)
+    where
+        C: QueryCache + 'static,
+    {
+        self.client.set_query_cache(cache);
+    }
+
+    /// Removes the cache set by `set_query_cache`.
+    pub fn clear_query_cache(&self) {
+        self.client.clear_query_cache();
+    }
+
     /// Determines if the client's connection has already closed.
     ///
     /// If this returns `true`, the client is no longer usable.
@@ -557,6 +1142,21 @@ impl Client {
         self.client.is_closed()
     }
 
+    /// Returns the transaction status of the connection when the server last finished processing a request.
+    ///
+    /// This can be used to detect a connection left in a failed transaction, for example before returning it to a
+    /// pool.
+    pub fn transaction_status(&self) -> TransactionStatus {
+        self.client.transaction_status()
+    }
+
+    /// Returns a snapshot of the messages received from the server over the connection, by type.
+    ///
+    /// This can be used to diagnose queries returning unexpectedly many rows, or floods of notices from the server.
+    pub fn message_stats(&self) -> MessageStats {
+        self.client.message_stats()
+    }
+
     /// Closes the client's connection to the server.
     ///
     /// This is equivalent to `Client`'s `Drop` implementation, except that it returns any error encountered to the
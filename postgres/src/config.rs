@@ -12,10 +12,14 @@ use std::sync::Arc;
 use std::time::Duration;
 use tokio::runtime;
 #[doc(inline)]
-pub use tokio_postgres::config::{ChannelBinding, Host, SslMode, TargetSessionAttrs};
+pub use tokio_postgres::config::{
+    AddressStrategy, ChannelBinding, GssEncMode, Host, ProtocolVersion, ReplicationMode, SslMode,
+    TargetSessionAttrs,
+};
 use tokio_postgres::error::DbError;
+use tokio_postgres::runtime::Resolver;
 use tokio_postgres::tls::{MakeTlsConnect, TlsConnect};
-use tokio_postgres::{Error, Socket};
+use tokio_postgres::{ConnectionListener, Error, IsolationLevel, Metrics, Socket, Watchdog};
 
 /// Connection configuration.
 ///
@@ -33,6 +37,13 @@ use tokio_postgres::{Error, Socket};
 /// * `dbname` - The name of the database to connect to. Defaults to the username.
 /// * `options` - Command line options used to configure the server.
 /// * `application_name` - Sets the `application_name` parameter on the server.
+/// * `role` - A role to assume with `SET ROLE` as soon as the connection is established, before it is returned. The
+///   connection fails if the role can't be assumed.
+/// * `default_transaction_isolation` - The isolation level of transactions which don't specify one: `read uncommitted`,
+///   `read committed`, `repeatable read`, or `serializable`. Set with `SET SESSION CHARACTERISTICS` as soon as the
+///   connection is established.
+/// * `default_transaction_read_only` - Whether transactions which don't specify an access mode are read-only. Set
+///   along with `default_transaction_isolation`.
 /// * `sslmode` - Controls usage of TLS. If set to `disable`, TLS will not be used. If set to `prefer`, TLS will be used
 ///     if available, but not used otherwise. If set to `require`, TLS will be forced to be used. Defaults to `prefer`.
 /// * `host` - The host to connect to. On Unix platforms, if the host starts with a `/` character it is treated as the
@@ -171,6 +182,41 @@ impl Config {
         self.config.get_options()
     }
 
+    /// Adds a runtime parameter to set when the connection starts, as if with `SET`.
+    ///
+    /// The parameter is passed to the server as a `-c name=value` command line option, with spaces and backslashes
+    /// escaped as required by the server, following any options set with the `options` method.
+    pub fn server_setting(&mut self, name: &str, value: &str) -> &mut Config {
+        self.config.server_setting(name, value);
+        self
+    }
+
+    /// Gets the runtime parameters added with the `server_setting` method, as name/value pairs.
+    pub fn get_server_settings(&self) -> &[(String, String)] {
+        self.config.get_server_settings()
+    }
+
+    /// Sets the schemas searched for unqualified object names, in order.
+    ///
+    /// The schema names are quoted as necessary and applied as the `search_path` parameter when the connection starts,
+    /// following the settings added with the `server_setting` method. Unlike a `SET search_path` run after connecting,
+    /// this can't be forgotten by a connection pool or undone by `RESET ALL` or `DISCARD ALL`, which restore the
+    /// value set at startup. Pass `$user` to include the schema with the same name as the current user, as in the
+    /// server's default of `"$user", public`.
+    pub fn search_path<I, S>(&mut self, schemas: I) -> &mut Config
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.config.search_path(schemas);
+        self
+    }
+
+    /// Gets the schemas searched for unqualified object names, if they have been set with the `search_path` method.
+    pub fn get_search_path(&self) -> Option<&[String]> {
+        self.config.get_search_path()
+    }
+
     /// Sets the value of the `application_name` runtime parameter.
     pub fn application_name(&mut self, application_name: &str) -> &mut Config {
         self.config.application_name(application_name);
@@ -183,6 +229,69 @@ impl Config {
         self.config.get_application_name()
     }
 
+    /// Sets a role to assume with `SET ROLE` once the connection is established.
+    ///
+    /// This is useful when logging in as a generic user which must then assume a role to do any work. The role is set
+    /// before the connection is returned, at the cost of one extra round trip to the server.
+    pub fn role(&mut self, role: &str) -> &mut Config {
+        self.config.role(role);
+        self
+    }
+
+    /// Gets the role to assume once the connection is established, if one has been set with the `role` method.
+    pub fn get_role(&self) -> Option<&str> {
+        self.config.get_role()
+    }
+
+    /// Sets the isolation level of transactions started on the connection without an explicit isolation level.
+    ///
+    /// This is applied with `SET SESSION CHARACTERISTICS` once the connection is established, along with the access
+    /// mode set with the `default_transaction_read_only` method, before the connection is returned.
+    pub fn default_transaction_isolation(
+        &mut self,
+        isolation_level: IsolationLevel,
+    ) -> &mut Config {
+        self.config.default_transaction_isolation(isolation_level);
+        self
+    }
+
+    /// Gets the default transaction isolation level, if one has been set with the `default_transaction_isolation`
+    /// method.
+    pub fn get_default_transaction_isolation(&self) -> Option<IsolationLevel> {
+        self.config.get_default_transaction_isolation()
+    }
+
+    /// Sets whether transactions started on the connection without an explicit access mode are read-only.
+    ///
+    /// Like the default isolation level, this is applied with `SET SESSION CHARACTERISTICS` once the connection is
+    /// established. Note that a read-only default doesn't prevent transactions from explicitly requesting write access.
+    pub fn default_transaction_read_only(&mut self, read_only: bool) -> &mut Config {
+        self.config.default_transaction_read_only(read_only);
+        self
+    }
+
+    /// Gets the default transaction access mode, if one has been set with the `default_transaction_read_only` method.
+    pub fn get_default_transaction_read_only(&self) -> Option<bool> {
+        self.config.get_default_transaction_read_only()
+    }
+
+    /// Sets the character encoding requested for text exchanged with the server.
+    ///
+    /// The connection fails if the server reports a different encoding, either during the handshake or later on when
+    /// the `client_encoding` setting is changed, since text would otherwise be silently mis-decoded.
+    ///
+    /// Text is always decoded as UTF-8, so other encodings are only useful in special cases, such as `SQL_ASCII`,
+    /// which passes bytes through from a database without conversion. Defaults to `UTF8`.
+    pub fn client_encoding(&mut self, client_encoding: &str) -> &mut Config {
+        self.config.client_encoding(client_encoding);
+        self
+    }
+
+    /// Gets the character encoding requested for text exchanged with the server.
+    pub fn get_client_encoding(&self) -> &str {
+        self.config.get_client_encoding()
+    }
+
     /// Sets the SSL configuration.
     ///
     /// Defaults to `prefer`.
@@ -196,6 +305,20 @@ impl Config {
         self.config.get_ssl_mode()
     }
 
+    /// Sets the GSSAPI encryption configuration.
+    ///
+    /// GSSAPI encryption is not supported, so `prefer` behaves like `disable`, and `require` makes the connection
+    /// fail. Defaults to `prefer`.
+    pub fn gss_enc_mode(&mut self, gss_enc_mode: GssEncMode) -> &mut Config {
+        self.config.gss_enc_mode(gss_enc_mode);
+        self
+    }
+
+    /// Gets the GSSAPI encryption configuration.
+    pub fn get_gss_enc_mode(&self) -> GssEncMode {
+        self.config.get_gss_enc_mode()
+    }
+
     /// Adds a host to the configuration.
     ///
     /// Multiple hosts can be specified by calling this method multiple times, and each will be tried in order. On Unix
@@ -279,6 +402,45 @@ impl Config {
         self.config.get_keepalives_idle()
     }
 
+    /// Sets the strategy used to connect to hosts which resolve to multiple addresses.
+    ///
+    /// With the default `Sequential` strategy, an unreachable address stalls the connection until it times out, which
+    /// can take minutes without a `connect_timeout` on networks where IPv6 is broken. Defaults to `Sequential`.
+    pub fn address_strategy(&mut self, address_strategy: AddressStrategy) -> &mut Config {
+        self.config.address_strategy(address_strategy);
+        self
+    }
+
+    /// Gets the strategy used to connect to hosts which resolve to multiple addresses.
+    pub fn get_address_strategy(&self) -> AddressStrategy {
+        self.config.get_address_strategy()
+    }
+
+    /// Sets how long a connection attempt may be pending before the next address is tried alongside it.
+    ///
+    /// This only applies to the `HappyEyeballs` address strategy. A delay of zero connects to all addresses at once.
+    /// Defaults to 250 milliseconds, as recommended by RFC 8305.
+    pub fn happy_eyeballs_delay(&mut self, delay: Duration) -> &mut Config {
+        self.config.happy_eyeballs_delay(delay);
+        self
+    }
+
+    /// Gets how long a connection attempt may be pending before the next address is tried alongside it.
+    pub fn get_happy_eyeballs_delay(&self) -> Duration {
+        self.config.get_happy_eyeballs_delay()
+    }
+
+    /// Sets the resolver used to look up the addresses of hosts, overriding the runtime's.
+    pub fn resolver(&mut self, resolver: Arc<dyn Resolver>) -> &mut Config {
+        self.config.resolver(resolver);
+        self
+    }
+
+    /// Gets the resolver, if one has been configured with the `resolver` method.
+    pub fn get_resolver(&self) -> Option<&Arc<dyn Resolver>> {
+        self.config.get_resolver()
+    }
+
     /// Sets the requirements of the session.
     ///
     /// This can be used to connect to the primary server in a clustered database rather than one of the read-only
@@ -309,6 +471,222 @@ impl Config {
         self.config.get_channel_binding()
     }
 
+    /// Sets the oldest protocol version the server may negotiate down to.
+    ///
+    /// The connection fails if the server only supports older versions. If this is newer than the maximum, it is
+    /// requested instead. Defaults to `V3_0`.
+    pub fn min_protocol_version(&mut self, version: ProtocolVersion) -> &mut Config {
+        self.config.min_protocol_version(version);
+        self
+    }
+
+    /// Gets the oldest protocol version the server may negotiate down to.
+    pub fn get_min_protocol_version(&self) -> ProtocolVersion {
+        self.config.get_min_protocol_version()
+    }
+
+    /// Sets the protocol version requested from the server.
+    ///
+    /// Servers which only support an older minor version of the protocol negotiate the newest one they do support.
+    /// Defaults to `V3_0`.
+    pub fn max_protocol_version(&mut self, version: ProtocolVersion) -> &mut Config {
+        self.config.max_protocol_version(version);
+        self
+    }
+
+    /// Gets the protocol version requested from the server.
+    pub fn get_max_protocol_version(&self) -> ProtocolVersion {
+        self.config.get_max_protocol_version()
+    }
+
+    /// Adds a protocol extension option to the startup message.
+    ///
+    /// The option is sent as `_pq_.<name>`, the namespace reserved for protocol extensions. Servers which don't
+    /// recognize an extension ignore it rather than rejecting the connection.
+    pub fn protocol_extension(&mut self, name: &str, value: &str) -> &mut Config {
+        self.config.protocol_extension(name, value);
+        self
+    }
+
+    /// Gets the protocol extension options added with the `protocol_extension` method, as name/value pairs.
+    pub fn get_protocol_extensions(&self) -> &[(String, String)] {
+        self.config.get_protocol_extensions()
+    }
+
+    /// Sets the replication mode of the connection.
+    ///
+    /// Connections in replication mode accept the commands of the streaming replication protocol instead of (or, for
+    /// logical replication, in addition to) regular SQL. Defaults to no replication.
+    pub fn replication_mode(&mut self, replication_mode: ReplicationMode) -> &mut Config {
+        self.config.replication_mode(replication_mode);
+        self
+    }
+
+    /// Gets the replication mode of the connection, if one has been configured with the `replication_mode` method.
+    pub fn get_replication_mode(&self) -> Option<ReplicationMode> {
+        self.config.get_replication_mode()
+    }
+
+    /// Sets a hook to report metrics of connections made with this configuration.
+    pub fn metrics(&mut self, metrics: Arc<dyn Metrics>) -> &mut Config {
+        self.config.metrics(metrics);
+        self
+    }
+
+    /// Gets the metrics hook, if one has been configured with the `metrics` method.
+    pub fn get_metrics(&self) -> Option<&Arc<dyn Metrics>> {
+        self.config.get_metrics()
+    }
+
+    /// Sets a hook to be notified of the lifecycle events of connections made with this configuration.
+    pub fn connection_listener(&mut self, listener: Arc<dyn ConnectionListener>) -> &mut Config {
+        self.config.connection_listener(listener);
+        self
+    }
+
+    /// Gets the connection listener, if one has been configured with the `connection_listener` method.
+    pub fn get_connection_listener(&self) -> Option<&Arc<dyn ConnectionListener>> {
+        self.config.get_connection_listener()
+    }
+
+    /// Sets a watchdog monitoring connections made with this configuration for long-running transactions and queries.
+    pub fn watchdog(&mut self, watchdog: Watchdog) -> &mut Config {
+        self.config.watchdog(watchdog);
+        self
+    }
+
+    /// Gets the watchdog, if one has been configured with the `watchdog` method.
+    pub fn get_watchdog(&self) -> Option<&Watchdog> {
+        self.config.get_watchdog()
+    }
+
+    /// Requests the cancellation of queries which are abandoned before they complete.
+    ///
+    /// A cancellation request is sent over a new connection made with the provided TLS connector when the result of a
+    /// query, such as a `RowIter`, is dropped before the query's response has been received in full. Cancellation is
+    /// inherently racy: if the abandoned query finishes before the request reaches the server, the query executed after
+    /// it may be canceled instead.
+    pub fn cancel_on_drop<T>(&mut self, tls: T) -> &mut Config
+    where
+        T: MakeTlsConnect<Socket> + Clone + 'static + Sync + Send,
+        T::TlsConnect: Send,
+        T::Stream: Send,
+        <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+    {
+        self.config.cancel_on_drop(tls);
+        self
+    }
+
+    /// Determines if abandoned queries are canceled, as configured with the `cancel_on_drop` method.
+    pub fn get_cancel_on_drop(&self) -> bool {
+        self.config.get_cancel_on_drop()
+    }
+
+    /// Enables logging of every protocol message sent and received by the connection.
+    ///
+    /// Messages are logged at the `info` level to the `tokio_postgres::protocol` target, in a format similar to libpq's
+    /// `PQtrace`. This is intended for debugging, and should not be enabled in production since query text and
+    /// server messages are logged. Defaults to `false`.
+    pub fn trace_protocol(&mut self, trace_protocol: bool) -> &mut Config {
+        self.config.trace_protocol(trace_protocol);
+        self
+    }
+
+    /// Gets whether protocol messages will be logged.
+    pub fn get_trace_protocol(&self) -> bool {
+        self.config.get_trace_protocol()
+    }
+
+    /// Sets the maximum number of requests which can be queued for the connection.
+    ///
+    /// Once the queue is full, client methods will wait for space to become available rather than buffering an
+    /// unbounded number of requests in memory. Defaults to no limit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0.
+    pub fn request_queue_capacity(&mut self, capacity: usize) -> &mut Config {
+        self.config.request_queue_capacity(capacity);
+        self
+    }
+
+    /// Gets the maximum number of queued requests, if one has been configured with the `request_queue_capacity` method.
+    pub fn get_request_queue_capacity(&self) -> Option<usize> {
+        self.config.get_request_queue_capacity()
+    }
+
+    /// Controls whether prepared statements are stored on the server under a name of their own.
+    ///
+    /// Disabling this allows prepared statements to be used through connection poolers such as PgBouncer in
+    /// transaction pooling mode, at the cost of parsing each statement again every time it is executed. Defaults to
+    /// `true`.
+    pub fn named_statements(&mut self, named_statements: bool) -> &mut Config {
+        self.config.named_statements(named_statements);
+        self
+    }
+
+    /// Gets whether prepared statements are stored on the server under a name of their own.
+    pub fn get_named_statements(&self) -> bool {
+        self.config.get_named_statements()
+    }
+
+    /// Sets the prefix of the names under which statements are prepared on the server.
+    ///
+    /// Statements are named with the prefix followed by a number. Defaults to `s`.
+    pub fn statement_name_prefix(&mut self, statement_name_prefix: &str) -> &mut Config {
+        self.config.statement_name_prefix(statement_name_prefix);
+        self
+    }
+
+    /// Gets the prefix of the names under which statements are prepared on the server.
+    pub fn get_statement_name_prefix(&self) -> &str {
+        self.config.get_statement_name_prefix()
+    }
+
+    /// Sets the maximum number of statements kept in the client's statement cache.
+    ///
+    /// When enabled, the statements prepared for raw query strings are cached by the text of the query and reused.
+    /// Once the cache is full, the least recently used statement is evicted and closed on the server. If a cached
+    /// statement no longer exists on the server when it is executed outside of a transaction, it is prepared again and
+    /// the request retried once. Defaults to 0, which disables the cache.
+    pub fn statement_cache_capacity(&mut self, statement_cache_capacity: usize) -> &mut Config {
+        self.config
+            .statement_cache_capacity(statement_cache_capacity);
+        self
+    }
+
+    /// Gets the maximum number of statements kept in the client's statement cache.
+    pub fn get_statement_cache_capacity(&self) -> usize {
+        self.config.get_statement_cache_capacity()
+    }
+
+    /// Sets the initial capacity of the buffer used to read messages from the server, in bytes.
+    ///
+    /// The buffer grows as needed to hold larger messages. Defaults to 8 KiB.
+    pub fn read_buffer_size(&mut self, read_buffer_size: usize) -> &mut Config {
+        self.config.read_buffer_size(read_buffer_size);
+        self
+    }
+
+    /// Gets the initial read buffer capacity, if one has been configured with the `read_buffer_size` method.
+    pub fn get_read_buffer_size(&self) -> Option<usize> {
+        self.config.get_read_buffer_size()
+    }
+
+    /// Sets the maximum length of a message the server is allowed to send, in bytes.
+    ///
+    /// The connection fails with an error if the server announces a longer message, rather than buffering it in memory.
+    /// The limit must be large enough to hold the largest row returned by a query. Defaults to no limit.
+    pub fn max_message_length(&mut self, max_message_length: usize) -> &mut Config {
+        self.config.max_message_length(max_message_length);
+        self
+    }
+
+    /// Gets the maximum message length, if one has been configured with the `max_message_length` method.
+    pub fn get_max_message_length(&self) -> Option<usize> {
+        self.config.get_max_message_length()
+    }
+
     /// Sets the notice callback.
     ///
     /// This callback will be invoked with the contents of every
@@ -0,0 +1,82 @@
+use crate::connection::ConnectionRef;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::pin::Pin;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio_postgres::types::Oid;
+use tokio_postgres::Error;
+
+/// A handle to an open large object, returned by the `open_large_object` method.
+///
+/// The object's contents can be accessed through the `Read`, `Write` and `Seek` implementations. Each read or write
+/// corresponds to a single `loread` or `lowrite` call on the server, so it is a good idea to wrap the handle in a
+/// `BufReader` or `BufWriter` when performing small operations.
+pub struct LargeObject<'a> {
+    connection: ConnectionRef<'a>,
+    object: tokio_postgres::LargeObject<'a>,
+}
+
+impl<'a> LargeObject<'a> {
+    pub(crate) fn new(
+        connection: ConnectionRef<'a>,
+        object: tokio_postgres::LargeObject<'a>,
+    ) -> LargeObject<'a> {
+        LargeObject { connection, object }
+    }
+
+    /// Returns the OID of the object.
+    pub fn oid(&self) -> Oid {
+        self.object.oid()
+    }
+
+    /// Returns the object's current read/write position.
+    pub fn tell(&mut self) -> Result<u64, Error> {
+        self.connection.block_on(self.object.tell())
+    }
+
+    /// Truncates or extends the object to the specified length.
+    ///
+    /// The object must have been opened for writing.
+    pub fn truncate(&mut self, len: u64) -> Result<(), Error> {
+        self.connection.block_on(self.object.truncate(len))
+    }
+
+    /// Closes the handle.
+    ///
+    /// Handles are closed automatically when the transaction ends, so this only needs to be called to release the
+    /// server's resources early.
+    pub fn close(mut self) -> Result<(), Error> {
+        self.connection.block_on(self.object.close())
+    }
+}
+
+impl Read for LargeObject<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let object = &mut self.object;
+        let mut buf = ReadBuf::new(buf);
+        self.connection
+            .poll_block_on(|cx, _, _| Pin::new(&mut *object).poll_read(cx, &mut buf).map(Ok))
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))??;
+        Ok(buf.filled().len())
+    }
+}
+
+impl Write for LargeObject<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let object = &mut self.object;
+        self.connection
+            .poll_block_on(|cx, _, _| Pin::new(&mut *object).poll_write(cx, buf).map(Ok))
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for LargeObject<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.connection
+            .block_on(self.object.seek(pos))
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+    }
+}
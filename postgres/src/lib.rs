@@ -67,10 +67,19 @@
 
 pub use fallible_iterator;
 pub use tokio_postgres::{
-    error, row, tls, types, Column, IsolationLevel, Notification, Portal, SimpleQueryMessage,
-    Socket, Statement, ToStatement,
+    error, escape, render_param, row, tls, types, AdvisoryLockKey, AuthenticationMethod, BindParam,
+    Column, CommandKind, CommandTag, ConnectedEvent, ConnectionListener, CopyFormat, CopyOptions,
+    IsolationLevel, LargeObjectMode, MemoryQueryCache, MessageCount, MessageStats, MessageType,
+    Metrics, NamedParams, NamedQuery, Notification, ParameterScope, Portal, PreparedTransaction,
+    QueryBuilder, QueryCache, QueryCacheKey, QueryEvent, QueryLogger, RefCursor, RetryPolicy,
+    SimpleColumn, SimpleQueryMessage, SimpleResultSet, Socket, Statement, ToSqlStream, ToStatement,
+    TransactionStatus,
 };
 
+#[cfg(feature = "with-serde_json-1")]
+pub use tokio_postgres::{ExplainOptions, PlanNode, PlanNodes, QueryPlan};
+
+pub use crate::advisory_lock::AdvisoryLockGuard;
 pub use crate::cancel_token::CancelToken;
 pub use crate::client::*;
 pub use crate::config::Config;
@@ -79,6 +88,7 @@ pub use crate::copy_out_reader::CopyOutReader;
 #[doc(no_inline)]
 pub use crate::error::Error;
 pub use crate::generic_client::GenericClient;
+pub use crate::large_object::LargeObject;
 #[doc(inline)]
 pub use crate::notifications::Notifications;
 #[doc(no_inline)]
@@ -89,6 +99,7 @@ pub use crate::tls::NoTls;
 pub use crate::transaction::*;
 pub use crate::transaction_builder::TransactionBuilder;
 
+mod advisory_lock;
 pub mod binary_copy;
 mod cancel_token;
 mod client;
@@ -97,8 +108,10 @@ mod connection;
 mod copy_in_writer;
 mod copy_out_reader;
 mod generic_client;
+mod large_object;
 mod lazy_pin;
 pub mod notifications;
+pub mod replication;
 mod row_iter;
 mod transaction;
 mod transaction_builder;
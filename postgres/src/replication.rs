@@ -0,0 +1,207 @@
+//! Utilities for working with the PostgreSQL streaming replication protocol.
+//!
+//! Replication commands can only be issued on connections which were opened in replication mode; see
+//! `Config::replication_mode`.
+
+use crate::connection::ConnectionRef;
+use crate::lazy_pin::LazyPin;
+use crate::Error;
+use bytes::Bytes;
+use fallible_iterator::FallibleIterator;
+use futures::StreamExt;
+use std::pin::Pin;
+use std::time::SystemTime;
+use tokio_postgres::replication::{BaseBackupStream, LogicalReplicationStream, ReplicationStream};
+use tokio_postgres::types::PgLsn;
+
+pub use tokio_postgres::replication::{
+    BaseBackupMessage, BeginBody, Column, CommitBody, DeleteBody, IdentifySystem, InsertBody,
+    LogicalReplicationMessage, OriginBody, PrimaryKeepAliveBody, RelationBody, ReplicaIdentity,
+    ReplicationMessage, StreamAbortBody, StreamCommitBody, StreamStartBody, TimelineHistory,
+    TruncateBody, Tuple, TupleData, TypeBody, UpdateBody, XLogDataBody,
+};
+
+/// A blocking iterator over streaming replication messages.
+///
+/// In addition to yielding the messages sent by the server, the iterator keeps track of the WAL positions needed to
+/// send standby status updates. The server expects these updates periodically, and immediately whenever a keepalive
+/// message requests a reply; otherwise it may terminate the connection. The iterator does not send them on its own.
+pub struct ReplicationIter<'a> {
+    connection: ConnectionRef<'a>,
+    stream: LazyPin<ReplicationStream>,
+}
+
+impl<'a> ReplicationIter<'a> {
+    pub(crate) fn new(
+        connection: ConnectionRef<'a>,
+        stream: ReplicationStream,
+    ) -> ReplicationIter<'a> {
+        ReplicationIter {
+            connection,
+            stream: LazyPin::new(stream),
+        }
+    }
+
+    /// Returns the position of the most recent WAL data received from the server.
+    pub fn last_received_lsn(&self) -> PgLsn {
+        self.stream.get().last_received_lsn()
+    }
+
+    /// Returns the position reported by `set_flushed_lsn`.
+    pub fn flushed_lsn(&self) -> PgLsn {
+        self.stream.get().flushed_lsn()
+    }
+
+    /// Records that all WAL data up to the given position has been durably stored by the client.
+    pub fn set_flushed_lsn(&mut self, lsn: PgLsn) {
+        self.stream.pinned().set_flushed_lsn(lsn)
+    }
+
+    /// Sends a standby status update reporting the positions tracked by the iterator.
+    pub fn send_feedback(&mut self, reply: bool) -> Result<(), Error> {
+        let stream = self.stream.pinned();
+        self.connection.block_on(stream.send_feedback(reply))
+    }
+
+    /// Sends a standby status update with explicitly specified positions.
+    pub fn standby_status_update(
+        &mut self,
+        write_lsn: PgLsn,
+        flush_lsn: PgLsn,
+        apply_lsn: PgLsn,
+        timestamp: SystemTime,
+        reply: bool,
+    ) -> Result<(), Error> {
+        let stream = self.stream.pinned();
+        self.connection.block_on(
+            stream.standby_status_update(write_lsn, flush_lsn, apply_lsn, timestamp, reply),
+        )
+    }
+
+    /// Ends the client's half of the replication stream.
+    ///
+    /// The server will stop streaming, and the iterator will end once any messages already in flight are received.
+    pub fn close(&mut self) -> Result<(), Error> {
+        let stream = self.stream.pinned();
+        self.connection.block_on(stream.close())
+    }
+
+    /// Converts the iterator into one which decodes the messages of the `pgoutput` logical decoding plugin.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the iterator has already been used.
+    pub fn logical(self) -> LogicalReplicationIter<'a> {
+        let stream = self
+            .stream
+            .into_unpinned()
+            .expect("replication iterator has already been used");
+        LogicalReplicationIter {
+            connection: self.connection,
+            stream: Box::pin(LogicalReplicationStream::new(stream)),
+        }
+    }
+}
+
+impl FallibleIterator for ReplicationIter<'_> {
+    type Item = ReplicationMessage<Bytes>;
+    type Error = Error;
+
+    fn next(&mut self) -> Result<Option<ReplicationMessage<Bytes>>, Error> {
+        let mut stream = self.stream.pinned();
+        self.connection
+            .block_on(async { stream.next().await.transpose() })
+    }
+}
+
+/// A blocking iterator over the messages of a logical replication slot using the `pgoutput` output plugin.
+///
+/// Created by `ReplicationIter::logical`.
+pub struct LogicalReplicationIter<'a> {
+    connection: ConnectionRef<'a>,
+    stream: Pin<Box<LogicalReplicationStream>>,
+}
+
+impl LogicalReplicationIter<'_> {
+    /// Like `ReplicationIter::last_received_lsn`.
+    pub fn last_received_lsn(&self) -> PgLsn {
+        self.stream.last_received_lsn()
+    }
+
+    /// Like `ReplicationIter::flushed_lsn`.
+    pub fn flushed_lsn(&self) -> PgLsn {
+        self.stream.flushed_lsn()
+    }
+
+    /// Like `ReplicationIter::set_flushed_lsn`.
+    pub fn set_flushed_lsn(&mut self, lsn: PgLsn) {
+        self.stream.as_mut().set_flushed_lsn(lsn)
+    }
+
+    /// Like `ReplicationIter::send_feedback`.
+    pub fn send_feedback(&mut self, reply: bool) -> Result<(), Error> {
+        let stream = self.stream.as_mut();
+        self.connection.block_on(stream.send_feedback(reply))
+    }
+
+    /// Like `ReplicationIter::standby_status_update`.
+    pub fn standby_status_update(
+        &mut self,
+        write_lsn: PgLsn,
+        flush_lsn: PgLsn,
+        apply_lsn: PgLsn,
+        timestamp: SystemTime,
+        reply: bool,
+    ) -> Result<(), Error> {
+        let stream = self.stream.as_mut();
+        self.connection.block_on(
+            stream.standby_status_update(write_lsn, flush_lsn, apply_lsn, timestamp, reply),
+        )
+    }
+
+    /// Like `ReplicationIter::close`.
+    pub fn close(&mut self) -> Result<(), Error> {
+        let stream = self.stream.as_mut();
+        self.connection.block_on(stream.close())
+    }
+}
+
+impl FallibleIterator for LogicalReplicationIter<'_> {
+    type Item = ReplicationMessage<LogicalReplicationMessage>;
+    type Error = Error;
+
+    fn next(&mut self) -> Result<Option<ReplicationMessage<LogicalReplicationMessage>>, Error> {
+        let stream = &mut self.stream;
+        self.connection
+            .block_on(async { stream.next().await.transpose() })
+    }
+}
+
+/// A blocking iterator over the messages making up a base backup.
+pub struct BaseBackupIter<'a> {
+    connection: ConnectionRef<'a>,
+    stream: Pin<Box<BaseBackupStream>>,
+}
+
+impl<'a> BaseBackupIter<'a> {
+    pub(crate) fn new(
+        connection: ConnectionRef<'a>,
+        stream: BaseBackupStream,
+    ) -> BaseBackupIter<'a> {
+        BaseBackupIter {
+            connection,
+            stream: Box::pin(stream),
+        }
+    }
+}
+
+impl FallibleIterator for BaseBackupIter<'_> {
+    type Item = BaseBackupMessage;
+    type Error = Error;
+
+    fn next(&mut self) -> Result<Option<BaseBackupMessage>, Error> {
+        let stream = &mut self.stream;
+        self.connection
+            .block_on(async { stream.next().await.transpose() })
+    }
+}
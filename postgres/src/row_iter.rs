@@ -2,7 +2,7 @@ use crate::connection::ConnectionRef;
 use fallible_iterator::FallibleIterator;
 use futures::StreamExt;
 use std::pin::Pin;
-use tokio_postgres::{Error, Row, RowStream};
+use tokio_postgres::{CommandTag, Error, Row, RowStream};
 
 /// The iterator returned by `query_raw`.
 pub struct RowIter<'a> {
@@ -17,6 +17,13 @@ impl<'a> RowIter<'a> {
             it: Box::pin(stream),
         }
     }
+
+    /// Returns the tag the server sent on completion of the statement.
+    ///
+    /// This is only available once the iterator has been fully consumed.
+    pub fn command_tag(&self) -> Option<&CommandTag> {
+        self.it.command_tag()
+    }
 }
 
 impl FallibleIterator for RowIter<'_> {
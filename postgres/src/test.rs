@@ -1,6 +1,6 @@
-use std::io::{Read, Write};
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::str::FromStr;
-use std::sync::mpsc;
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 use tokio_postgres::error::SqlState;
@@ -9,6 +9,7 @@ use tokio_postgres::NoTls;
 
 use super::*;
 use crate::binary_copy::{BinaryCopyInWriter, BinaryCopyOutIter};
+use crate::replication::ReplicationMessage;
 use fallible_iterator::FallibleIterator;
 
 #[test]
@@ -229,6 +230,83 @@ fn savepoints() {
     assert_eq!(rows[2].get::<_, i32>(0), 4);
 }
 
+#[test]
+fn try_execute_or_rollback_to_savepoint() {
+    let mut client = Client::connect("host=localhost port=5433 user=postgres", NoTls).unwrap();
+
+    client
+        .batch_execute("CREATE TEMPORARY TABLE foo (id INT PRIMARY KEY, n INT)")
+        .unwrap();
+
+    let mut transaction = client.transaction().unwrap();
+
+    let insert = "INSERT INTO foo (id, n) VALUES ($1, 1)";
+    assert_eq!(
+        transaction
+            .try_execute_or_rollback_to_savepoint(insert, &[&1i32])
+            .unwrap(),
+        1
+    );
+
+    let err = transaction
+        .try_execute_or_rollback_to_savepoint(insert, &[&1i32])
+        .unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::UNIQUE_VIOLATION));
+
+    // the failure didn't abort the transaction
+    transaction
+        .execute("UPDATE foo SET n = n + 1 WHERE id = $1", &[&1i32])
+        .unwrap();
+    transaction.commit().unwrap();
+
+    let rows = client.query("SELECT id, n FROM foo", &[]).unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].get::<_, i32>(1), 2);
+}
+
+#[test]
+fn transaction_with_retry() {
+    let mut client = Client::connect("host=localhost port=5433 user=postgres", NoTls).unwrap();
+
+    client
+        .batch_execute("CREATE TEMPORARY TABLE foo (id SERIAL, name TEXT)")
+        .unwrap();
+
+    let policy = RetryPolicy::new().initial_backoff(Duration::from_millis(1));
+
+    let mut attempts = 0;
+    let id = client
+        .build_transaction()
+        .isolation_level(IsolationLevel::Serializable)
+        .run_with_retry(&policy, |transaction| {
+            attempts += 1;
+            let row = transaction
+                .query_one("INSERT INTO foo (name) VALUES ('steven') RETURNING id", &[])?;
+            if attempts < 3 {
+                transaction.batch_execute(
+                    "DO $$ BEGIN RAISE EXCEPTION USING ERRCODE = 'serialization_failure'; END $$",
+                )?;
+            }
+            Ok(row.get::<_, i32>(0))
+        })
+        .unwrap();
+    assert_eq!(attempts, 3);
+
+    let rows = client.query("SELECT id FROM foo", &[]).unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].get::<_, i32>(0), id);
+
+    let mut attempts = 0;
+    let err = client
+        .transaction_with_retry(&policy, |transaction| {
+            attempts += 1;
+            transaction.batch_execute("SELECT 1/0")
+        })
+        .unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::DIVISION_BY_ZERO));
+    assert_eq!(attempts, 1);
+}
+
 #[test]
 fn copy_in() {
     let mut client = Client::connect("host=localhost port=5433 user=postgres", NoTls).unwrap();
@@ -370,6 +448,38 @@ fn portal() {
     assert_eq!(rows[0].get::<_, i32>(0), 3);
 }
 
+#[test]
+fn ref_cursor() {
+    let mut client = Client::connect("host=localhost port=5433 user=postgres", NoTls).unwrap();
+
+    client
+        .batch_execute(
+            "CREATE TEMPORARY TABLE foo (id INT);
+             INSERT INTO foo (id) VALUES (1), (2), (3);
+             CREATE FUNCTION pg_temp.open_foo() RETURNS refcursor LANGUAGE plpgsql AS $$
+             DECLARE c refcursor := 'foo cursor';
+             BEGIN OPEN c FOR SELECT id FROM foo ORDER BY id; RETURN c; END $$;",
+        )
+        .unwrap();
+
+    let mut transaction = client.transaction().unwrap();
+    let cursor = transaction
+        .query_one("SELECT pg_temp.open_foo()", &[])
+        .unwrap()
+        .get::<_, RefCursor>(0);
+    assert_eq!(cursor.name(), "foo cursor");
+
+    let rows = transaction.fetch_cursor(&cursor, 2).unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[1].get::<_, i32>(0), 2);
+
+    let rows = transaction.fetch_cursor_all(&cursor).unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].get::<_, i32>(0), 3);
+
+    assert!(transaction.fetch_cursor(&cursor, 1).unwrap().is_empty());
+}
+
 #[test]
 fn cancel_query() {
     let mut client = Client::connect("host=localhost port=5433 user=postgres", NoTls).unwrap();
@@ -508,3 +618,195 @@ fn check_send() {
     is_send::<Statement>();
     is_send::<Transaction<'_>>();
 }
+
+#[test]
+fn advisory_locks() {
+    let mut client1 = Client::connect("host=localhost port=5433 user=postgres", NoTls).unwrap();
+    let mut client2 = Client::connect("host=localhost port=5433 user=postgres", NoTls).unwrap();
+
+    let mut guard = client1.advisory_lock(0x7e57_0002).unwrap();
+    assert!(client2.try_advisory_lock(0x7e57_0002).unwrap().is_none());
+    guard.batch_execute("SELECT 1").unwrap();
+    drop(guard);
+
+    let guard = client2.try_advisory_lock(0x7e57_0002).unwrap().unwrap();
+    guard.unlock().unwrap();
+    assert!(!client2.advisory_unlock(0x7e57_0002).unwrap());
+
+    let mut transaction = client1.transaction().unwrap();
+    transaction.advisory_xact_lock((3, 4)).unwrap();
+    assert!(client2.try_advisory_lock((3, 4)).unwrap().is_none());
+    transaction.commit().unwrap();
+
+    assert!(client2.try_advisory_lock((3, 4)).unwrap().is_some());
+}
+
+#[test]
+fn query_logger() {
+    let mut client = Client::connect("host=localhost port=5433 user=postgres", NoTls).unwrap();
+
+    let queries = Arc::new(Mutex::new(vec![]));
+    client.set_query_logger({
+        let queries = queries.clone();
+        move |event: &QueryEvent<'_>| {
+            queries
+                .lock()
+                .unwrap()
+                .push((event.query().to_string(), event.rows_affected()))
+        }
+    });
+
+    client.query("SELECT 1 UNION ALL SELECT 2", &[]).unwrap();
+    client.clear_query_logger();
+    client.query("SELECT 1", &[]).unwrap();
+
+    assert_eq!(
+        *queries.lock().unwrap(),
+        [("SELECT 1 UNION ALL SELECT 2".to_string(), Some(2))]
+    );
+}
+
+#[test]
+fn function_call() {
+    let mut client = Client::connect("host=localhost port=5433 user=postgres", NoTls).unwrap();
+
+    // int4pl
+    let sum = client
+        .function_call::<i32>(
+            177,
+            &[(&1i32, Type::INT4), (&2i32, Type::INT4)],
+            &Type::INT4,
+        )
+        .unwrap();
+    assert_eq!(sum, 3);
+
+    let mut transaction = client.transaction().unwrap();
+    let sum = transaction
+        .function_call::<i32>(
+            177,
+            &[(&3i32, Type::INT4), (&4i32, Type::INT4)],
+            &Type::INT4,
+        )
+        .unwrap();
+    assert_eq!(sum, 7);
+}
+
+#[test]
+fn large_object() {
+    let mut client = Client::connect("host=localhost port=5433 user=postgres", NoTls).unwrap();
+    let mut transaction = client.transaction().unwrap();
+
+    let oid = transaction.create_large_object().unwrap();
+    let mut object = transaction
+        .open_large_object(oid, LargeObjectMode::ReadWrite)
+        .unwrap();
+    object.write_all(b"hello world").unwrap();
+    assert_eq!(object.seek(SeekFrom::Start(6)).unwrap(), 6);
+    let mut s = String::new();
+    object.read_to_string(&mut s).unwrap();
+    assert_eq!(s, "world");
+    object.truncate(5).unwrap();
+    assert_eq!(object.tell().unwrap(), 11);
+    object.close().unwrap();
+
+    let mut object = transaction
+        .open_large_object(oid, LargeObjectMode::Read)
+        .unwrap();
+    let mut s = String::new();
+    object.read_to_string(&mut s).unwrap();
+    assert_eq!(s, "hello");
+    drop(object);
+
+    transaction.unlink_large_object(oid).unwrap();
+}
+
+#[test]
+fn execute_streamed() {
+    let mut client = Client::connect("host=localhost port=5433 user=postgres", NoTls).unwrap();
+
+    client
+        .batch_execute("CREATE TEMPORARY TABLE foo (id INT, data BYTEA)")
+        .unwrap();
+
+    let chunks = vec![
+        Ok(bytes::Bytes::from_static(b"hello ")),
+        Ok(bytes::Bytes::from_static(b"world")),
+    ];
+    let data = ToSqlStream::new(11, futures::stream::iter(chunks));
+    let rows = client
+        .execute_streamed(
+            "INSERT INTO foo (id, data) VALUES ($1, $2)",
+            vec![BindParam::from(&1i32), data.into()],
+        )
+        .unwrap();
+    assert_eq!(rows, 1);
+
+    let rows = client
+        .execute_raw("DELETE FROM foo WHERE id = $1", vec![1i32])
+        .unwrap();
+    assert_eq!(rows, 1);
+}
+
+#[test]
+fn logical_replication() {
+    let mut client = Client::connect("host=localhost port=5433 user=postgres", NoTls).unwrap();
+    client
+        .batch_execute(
+            "
+            DROP TABLE IF EXISTS sync_logical_replication;
+            CREATE TABLE sync_logical_replication (id INT);
+            ",
+        )
+        .unwrap();
+
+    let mut repl_client = Client::connect(
+        "host=localhost port=5433 user=postgres replication=database",
+        NoTls,
+    )
+    .unwrap();
+    let system = repl_client.identify_system().unwrap();
+    assert_eq!(system.dbname(), Some("postgres"));
+
+    let messages = repl_client
+        .simple_query(
+            "CREATE_REPLICATION_SLOT sync_logical_replication TEMPORARY LOGICAL test_decoding",
+        )
+        .unwrap();
+    let slot_lsn = match &messages[0] {
+        SimpleQueryMessage::Row(row) => row.get("consistent_point").unwrap().parse().unwrap(),
+        _ => panic!("unexpected message"),
+    };
+
+    client
+        .execute("INSERT INTO sync_logical_replication (id) VALUES (1)", &[])
+        .unwrap();
+
+    let mut it = repl_client
+        .start_logical_replication(
+            "sync_logical_replication",
+            slot_lsn,
+            &[("include-xids", "0"), ("skip-empty-xacts", "1")],
+        )
+        .unwrap();
+
+    // other tests may be modifying tables concurrently, so skip over their changes
+    let change = loop {
+        if let ReplicationMessage::XLogData(body) = it.next().unwrap().unwrap() {
+            let data = std::str::from_utf8(body.data()).unwrap();
+            if data.starts_with("table public.sync_logical_replication:") {
+                break data.to_string();
+            }
+        }
+    };
+    assert_eq!(
+        change,
+        "table public.sync_logical_replication: INSERT: id[integer]:1"
+    );
+
+    let received_lsn = it.last_received_lsn();
+    it.set_flushed_lsn(received_lsn);
+    assert_eq!(it.flushed_lsn(), received_lsn);
+    it.send_feedback(false).unwrap();
+    it.close().unwrap();
+    while it.next().unwrap().is_some() {}
+}
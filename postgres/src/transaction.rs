@@ -1,7 +1,15 @@
 use crate::connection::ConnectionRef;
-use crate::{CancelToken, CopyInWriter, CopyOutReader, Portal, RowIter, Statement, ToStatement};
-use tokio_postgres::types::{BorrowToSql, ToSql, Type};
-use tokio_postgres::{Error, Row, SimpleQueryMessage};
+use crate::{
+    CancelToken, CopyInWriter, CopyOutReader, LargeObject, Portal, RowIter, Statement, ToStatement,
+};
+use futures::TryStreamExt;
+use tokio_postgres::types::{BorrowToSql, FromSqlOwned, Oid, ToSql, Type};
+use tokio_postgres::{
+    AdvisoryLockKey, BindParam, CommandTag, Error, LargeObjectMode, NamedParams, ParameterScope,
+    RefCursor, Row, SimpleQueryMessage, SimpleResultSet,
+};
+#[cfg(feature = "with-serde_json-1")]
+use tokio_postgres::{ExplainOptions, QueryPlan};
 
 /// A representation of a PostgreSQL database transaction.
 ///
@@ -45,6 +53,103 @@ impl<'a> Transaction<'a> {
             .block_on(self.transaction.take().unwrap().rollback())
     }
 
+    /// Consumes the transaction, preparing it for two-phase commit with the specified global identifier.
+    ///
+    /// Once prepared, the transaction is no longer associated with this connection. It can later be finished from any
+    /// connection with `Client::commit_prepared` or `Client::rollback_prepared`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the transaction is nested (i.e. a savepoint).
+    pub fn prepare_two_phase(mut self, gid: &str) -> Result<(), Error> {
+        self.connection
+            .block_on(self.transaction.take().unwrap().prepare_two_phase(gid))
+    }
+
+    /// Acquires a transaction-level advisory lock, waiting until it becomes available.
+    ///
+    /// The lock is automatically released at the end of the top-level transaction; there is no way to release it
+    /// earlier.
+    pub fn advisory_xact_lock<K>(&mut self, key: K) -> Result<(), Error>
+    where
+        K: Into<AdvisoryLockKey>,
+    {
+        self.connection
+            .block_on(self.transaction.as_ref().unwrap().advisory_xact_lock(key))
+    }
+
+    /// Attempts to acquire a transaction-level advisory lock without waiting.
+    ///
+    /// Returns `false` if the lock is currently held by another session.
+    pub fn try_advisory_xact_lock<K>(&mut self, key: K) -> Result<bool, Error>
+    where
+        K: Into<AdvisoryLockKey>,
+    {
+        self.connection.block_on(
+            self.transaction
+                .as_ref()
+                .unwrap()
+                .try_advisory_xact_lock(key),
+        )
+    }
+
+    /// Inserts rows into a table, updating the existing rows they conflict with.
+    ///
+    /// The rows are copied into a temporary table, from which they are inserted with an
+    /// `INSERT ... ON CONFLICT (conflict_target) DO UPDATE` statement setting all columns not in the conflict target.
+    /// This is much faster than upserting the rows one at a time. Each row provides a value for each of `columns`,
+    /// encoded with the types of the table's columns. If multiple rows conflict with each other, only the last one is
+    /// kept. With an empty conflict target, or if all columns are part of it, conflicting rows are skipped instead.
+    ///
+    /// The table name is included in the statements as-is, so it may be schema-qualified but must be escaped if
+    /// necessary, while the column names are escaped. Returns the number of rows inserted or updated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `columns` is empty, or if a row does not contain one value for each column.
+    pub fn bulk_upsert(
+        &mut self,
+        table: &str,
+        columns: &[&str],
+        conflict_target: &[&str],
+        rows: &[&[&(dyn ToSql + Sync)]],
+    ) -> Result<u64, Error> {
+        self.connection
+            .block_on(self.transaction.as_ref().unwrap().bulk_upsert(
+                table,
+                columns,
+                conflict_target,
+                rows,
+            ))
+    }
+
+    /// Creates a new, empty large object, returning its OID.
+    pub fn create_large_object(&mut self) -> Result<Oid, Error> {
+        self.connection
+            .block_on(self.transaction.as_ref().unwrap().create_large_object())
+    }
+
+    /// Opens a large object for reading and/or writing.
+    ///
+    /// The returned handle can only be used until the transaction ends.
+    pub fn open_large_object(
+        &mut self,
+        oid: Oid,
+        mode: LargeObjectMode,
+    ) -> Result<LargeObject<'_>, Error> {
+        let transaction = self.transaction.as_ref().unwrap();
+        let object = self
+            .connection
+            .block_on(transaction.open_large_object(oid, mode))?;
+        Ok(LargeObject::new(self.connection.as_ref(), object))
+    }
+
+    /// Deletes a large object.
+    pub fn unlink_large_object(&mut self, oid: Oid) -> Result<(), Error> {
+        self.connection
+            .block_on(self.transaction.as_ref().unwrap().unlink_large_object(oid))
+    }
+
     /// Like `Client::prepare`.
     pub fn prepare(&mut self, query: &str) -> Result<Statement, Error> {
         self.connection
@@ -70,6 +175,103 @@ impl<'a> Transaction<'a> {
             .block_on(self.transaction.as_ref().unwrap().execute(query, params))
     }
 
+    /// Like `Client::execute`, but executes the statement within a savepoint which is rolled back if it fails.
+    ///
+    /// A failed statement normally aborts the enclosing transaction, causing all further statements to fail until it
+    /// is rolled back. Here the error is returned after rolling back to the savepoint, so the transaction remains
+    /// usable. If rolling back to the savepoint itself fails, that error is returned instead.
+    pub fn try_execute_or_rollback_to_savepoint<T>(
+        &mut self,
+        query: &T,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<u64, Error>
+    where
+        T: ?Sized + ToStatement,
+    {
+        self.connection.block_on(
+            self.transaction
+                .as_mut()
+                .unwrap()
+                .try_execute_or_rollback_to_savepoint(query, params),
+        )
+    }
+
+    /// Like `Client::execute_with_tag`.
+    pub fn execute_with_tag<T>(
+        &mut self,
+        query: &T,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<CommandTag, Error>
+    where
+        T: ?Sized + ToStatement,
+    {
+        self.connection.block_on(
+            self.transaction
+                .as_ref()
+                .unwrap()
+                .execute_with_tag(query, params),
+        )
+    }
+
+    /// Like `Client::query_named`.
+    pub fn query_named<P>(&mut self, query: &str, params: &P) -> Result<Vec<Row>, Error>
+    where
+        P: ?Sized + NamedParams,
+    {
+        self.connection.block_on(
+            self.transaction
+                .as_ref()
+                .unwrap()
+                .query_named(query, params),
+        )
+    }
+
+    /// Like `Client::execute_named`.
+    pub fn execute_named<P>(&mut self, query: &str, params: &P) -> Result<u64, Error>
+    where
+        P: ?Sized + NamedParams,
+    {
+        self.connection.block_on(
+            self.transaction
+                .as_ref()
+                .unwrap()
+                .execute_named(query, params),
+        )
+    }
+
+    /// Like `Client::execute_raw`.
+    pub fn execute_raw<T, P, I>(&mut self, query: &T, params: I) -> Result<u64, Error>
+    where
+        T: ?Sized + ToStatement,
+        P: BorrowToSql,
+        I: IntoIterator<Item = P>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        self.connection.block_on(
+            self.transaction
+                .as_ref()
+                .unwrap()
+                .execute_raw(query, params),
+        )
+    }
+
+    /// Like `Client::execute_streamed`.
+    pub fn execute_streamed<T>(
+        &mut self,
+        query: &T,
+        params: Vec<BindParam<'_>>,
+    ) -> Result<u64, Error>
+    where
+        T: ?Sized + ToStatement,
+    {
+        self.connection.block_on(
+            self.transaction
+                .as_ref()
+                .unwrap()
+                .execute_streamed(query, params),
+        )
+    }
+
     /// Like `Client::query`.
     pub fn query<T>(&mut self, query: &T, params: &[&(dyn ToSql + Sync)]) -> Result<Vec<Row>, Error>
     where
@@ -101,6 +303,26 @@ impl<'a> Transaction<'a> {
             .block_on(self.transaction.as_ref().unwrap().query_opt(query, params))
     }
 
+    /// Like `Client::query_fold`.
+    pub fn query_fold<T, B, F>(
+        &mut self,
+        query: &T,
+        params: &[&(dyn ToSql + Sync)],
+        init: B,
+        f: F,
+    ) -> Result<B, Error>
+    where
+        T: ?Sized + ToStatement,
+        F: FnMut(B, Row) -> Result<B, Error>,
+    {
+        self.connection.block_on(
+            self.transaction
+                .as_ref()
+                .unwrap()
+                .query_fold(query, params, init, f),
+        )
+    }
+
     /// Like `Client::query_raw`.
     pub fn query_raw<T, P, I>(&mut self, query: &T, params: I) -> Result<RowIter<'_>, Error>
     where
@@ -115,6 +337,35 @@ impl<'a> Transaction<'a> {
         Ok(RowIter::new(self.connection.as_ref(), stream))
     }
 
+    /// Like `Client::query_typed`.
+    pub fn query_typed(
+        &mut self,
+        query: &str,
+        params: &[(&(dyn ToSql + Sync), Type)],
+    ) -> Result<Vec<Row>, Error> {
+        self.connection.block_on(
+            self.transaction
+                .as_ref()
+                .unwrap()
+                .query_typed(query, params),
+        )
+    }
+
+    /// Like `Client::query_typed_raw`.
+    pub fn query_typed_raw<P, I>(&mut self, query: &str, params: I) -> Result<RowIter<'_>, Error>
+    where
+        P: BorrowToSql,
+        I: IntoIterator<Item = (P, Type)>,
+    {
+        let stream = self.connection.block_on(
+            self.transaction
+                .as_ref()
+                .unwrap()
+                .query_typed_raw(query, params),
+        )?;
+        Ok(RowIter::new(self.connection.as_ref(), stream))
+    }
+
     /// Binds parameters to a statement, creating a "portal".
     ///
     /// Portals can be used with the `query_portal` method to page through the results of a query without being forced
@@ -122,9 +373,8 @@ impl<'a> Transaction<'a> {
     ///
     /// Portals are automatically closed when the transaction they were created in is closed.
     ///
-    /// # Panics
-    ///
-    /// Panics if the number of parameters provided does not match the number expected.
+    /// Returns an error if the number of parameters provided does not match the number expected, or if any of them
+    /// has a type incompatible with the statement.
     pub fn bind<T>(&mut self, query: &T, params: &[&(dyn ToSql + Sync)]) -> Result<Portal, Error>
     where
         T: ?Sized + ToStatement,
@@ -146,6 +396,20 @@ impl<'a> Transaction<'a> {
         )
     }
 
+    /// Fetches up to `n` rows from a cursor opened in this transaction.
+    ///
+    /// An empty result indicates that the cursor is exhausted.
+    pub fn fetch_cursor(&mut self, cursor: &RefCursor, n: i64) -> Result<Vec<Row>, Error> {
+        self.connection
+            .block_on(cursor.fetch(self.transaction.as_ref().unwrap(), n))
+    }
+
+    /// Fetches all of the remaining rows from a cursor opened in this transaction.
+    pub fn fetch_cursor_all(&mut self, cursor: &RefCursor) -> Result<Vec<Row>, Error> {
+        self.connection
+            .block_on(cursor.fetch_all(self.transaction.as_ref().unwrap()))
+    }
+
     /// The maximally flexible version of `query_portal`.
     pub fn query_portal_raw(
         &mut self,
@@ -161,6 +425,52 @@ impl<'a> Transaction<'a> {
         Ok(RowIter::new(self.connection.as_ref(), stream))
     }
 
+    /// Like `Client::explain`.
+    #[cfg(feature = "with-serde_json-1")]
+    pub fn explain(
+        &mut self,
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+        options: &ExplainOptions,
+    ) -> Result<QueryPlan, Error> {
+        self.connection.block_on(
+            self.transaction
+                .as_ref()
+                .unwrap()
+                .explain(statement, params, options),
+        )
+    }
+
+    /// Like `Client::call`.
+    ///
+    /// The procedure runs within this transaction, so it may not issue `COMMIT` or `ROLLBACK` itself.
+    pub fn call(
+        &mut self,
+        procedure: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Option<Row>, Error> {
+        self.connection
+            .block_on(self.transaction.as_ref().unwrap().call(procedure, params))
+    }
+
+    /// Like `Client::function_call`.
+    pub fn function_call<T>(
+        &mut self,
+        function: Oid,
+        params: &[(&(dyn ToSql + Sync), Type)],
+        result_type: &Type,
+    ) -> Result<T, Error>
+    where
+        T: FromSqlOwned,
+    {
+        self.connection
+            .block_on(self.transaction.as_ref().unwrap().function_call(
+                function,
+                params,
+                result_type,
+            ))
+    }
+
     /// Like `Client::copy_in`.
     pub fn copy_in<T>(&mut self, query: &T) -> Result<CopyInWriter<'_>, Error>
     where
@@ -189,12 +499,41 @@ impl<'a> Transaction<'a> {
             .block_on(self.transaction.as_ref().unwrap().simple_query(query))
     }
 
+    /// Like `Client::simple_query_result_sets`.
+    pub fn simple_query_result_sets(&mut self, query: &str) -> Result<Vec<SimpleResultSet>, Error> {
+        let transaction = self.transaction.as_ref().unwrap();
+        self.connection.block_on(async {
+            transaction
+                .simple_query_result_sets(query)
+                .await?
+                .try_collect()
+                .await
+        })
+    }
+
     /// Like `Client::batch_execute`.
     pub fn batch_execute(&mut self, query: &str) -> Result<(), Error> {
         self.connection
             .block_on(self.transaction.as_ref().unwrap().batch_execute(query))
     }
 
+    /// Like `Client::set_parameter`.
+    ///
+    /// Parameters changed for the session are reverted if the transaction is rolled back.
+    pub fn set_parameter(
+        &mut self,
+        name: &str,
+        value: &str,
+        scope: ParameterScope,
+    ) -> Result<(), Error> {
+        self.connection.block_on(
+            self.transaction
+                .as_ref()
+                .unwrap()
+                .set_parameter(name, value, scope),
+        )
+    }
+
     /// Like `Client::cancel_token`.
     pub fn cancel_token(&self) -> CancelToken {
         CancelToken::new(self.transaction.as_ref().unwrap().cancel_token())
@@ -1,32 +1,40 @@
 use crate::connection::ConnectionRef;
 use crate::{Error, IsolationLevel, Transaction};
+use std::thread;
+use tokio_postgres::RetryPolicy;
 
 /// A builder for database transactions.
 pub struct TransactionBuilder<'a> {
     connection: ConnectionRef<'a>,
-    builder: tokio_postgres::TransactionBuilder<'a>,
+    client: &'a mut tokio_postgres::Client,
+    isolation_level: Option<IsolationLevel>,
+    read_only: Option<bool>,
+    deferrable: Option<bool>,
 }
 
 impl<'a> TransactionBuilder<'a> {
     pub(crate) fn new(
         connection: ConnectionRef<'a>,
-        builder: tokio_postgres::TransactionBuilder<'a>,
+        client: &'a mut tokio_postgres::Client,
     ) -> TransactionBuilder<'a> {
         TransactionBuilder {
             connection,
-            builder,
+            client,
+            isolation_level: None,
+            read_only: None,
+            deferrable: None,
         }
     }
 
     /// Sets the isolation level of the transaction.
     pub fn isolation_level(mut self, isolation_level: IsolationLevel) -> Self {
-        self.builder = self.builder.isolation_level(isolation_level);
+        self.isolation_level = Some(isolation_level);
         self
     }
 
     /// Sets the access mode of the transaction.
     pub fn read_only(mut self, read_only: bool) -> Self {
-        self.builder = self.builder.read_only(read_only);
+        self.read_only = Some(read_only);
         self
     }
 
@@ -36,7 +44,7 @@ impl<'a> TransactionBuilder<'a> {
     /// completes the transaction is able to run with less overhead and a guarantee that it will not be aborted due to
     /// serialization failure.
     pub fn deferrable(mut self, deferrable: bool) -> Self {
-        self.builder = self.builder.deferrable(deferrable);
+        self.deferrable = Some(deferrable);
         self
     }
 
@@ -44,7 +52,91 @@ impl<'a> TransactionBuilder<'a> {
     ///
     /// The transaction will roll back by default - use the `commit` method to commit it.
     pub fn start(mut self) -> Result<Transaction<'a>, Error> {
-        let transaction = self.connection.block_on(self.builder.start())?;
+        let builder = build(
+            self.client,
+            self.isolation_level,
+            self.read_only,
+            self.deferrable,
+        );
+        let transaction = self.connection.block_on(builder.start())?;
         Ok(Transaction::new(self.connection, transaction))
     }
+
+    /// Runs a closure in a transaction, retrying it after serialization failures and deadlocks.
+    ///
+    /// The transaction is committed if the closure succeeds and rolled back if it fails. If the closure or the commit
+    /// fails with an error the policy considers retryable, the transaction is started again after a backoff, and the
+    /// closure is rerun. Any other error is returned immediately.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use postgres::{Client, IsolationLevel, NoTls, RetryPolicy};
+    ///
+    /// # fn main() -> Result<(), postgres::Error> {
+    /// let mut client = Client::connect("host=localhost user=postgres", NoTls)?;
+    ///
+    /// let balance: i64 = client
+    ///     .build_transaction()
+    ///     .isolation_level(IsolationLevel::Serializable)
+    ///     .run_with_retry(&RetryPolicy::new(), |transaction| {
+    ///         transaction.execute("UPDATE accounts SET balance = balance - 10 WHERE id = 1", &[])?;
+    ///         let row = transaction.query_one("SELECT balance FROM accounts WHERE id = 1", &[])?;
+    ///         Ok(row.get(0))
+    ///     })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn run_with_retry<F, T>(mut self, policy: &RetryPolicy, mut f: F) -> Result<T, Error>
+    where
+        F: FnMut(&mut Transaction<'_>) -> Result<T, Error>,
+    {
+        let mut retries = 0;
+
+        loop {
+            let builder = build(
+                self.client,
+                self.isolation_level,
+                self.read_only,
+                self.deferrable,
+            );
+            let transaction = self.connection.block_on(builder.start())?;
+            let mut transaction = Transaction::new(self.connection.as_ref(), transaction);
+
+            let result = match f(&mut transaction) {
+                Ok(value) => transaction.commit().map(|()| value),
+                Err(e) => {
+                    drop(transaction);
+                    Err(e)
+                }
+            };
+
+            match result {
+                Err(e) if policy.should_retry(&e, retries) => {
+                    thread::sleep(policy.backoff(retries));
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+fn build(
+    client: &mut tokio_postgres::Client,
+    isolation_level: Option<IsolationLevel>,
+    read_only: Option<bool>,
+    deferrable: Option<bool>,
+) -> tokio_postgres::TransactionBuilder<'_> {
+    let mut builder = client.build_transaction();
+    if let Some(isolation_level) = isolation_level {
+        builder = builder.isolation_level(isolation_level);
+    }
+    if let Some(read_only) = read_only {
+        builder = builder.read_only(read_only);
+    }
+    if let Some(deferrable) = deferrable {
+        builder = builder.deferrable(deferrable);
+    }
+    builder
 }
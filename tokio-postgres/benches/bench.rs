@@ -55,5 +55,49 @@ fn query_prepared(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, query_prepared);
+fn query_wide(c: &mut Criterion) {
+    let (client, runtime) = setup();
+    let query = format!(
+        "SELECT {} FROM generate_series(1, 1000) i",
+        (0..100)
+            .map(|n| format!("repeat('x', i % 100 + {}) AS c{}", n, n))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    let statement = runtime.block_on(client.prepare(&query)).unwrap();
+    c.bench_function("query_wide", move |b| {
+        b.iter(|| {
+            let rows = runtime.block_on(client.query(&statement, &[])).unwrap();
+            rows.iter()
+                .map(|row| (0..row.len()).filter_map(|i| row.get_raw(i)).count())
+                .sum::<usize>()
+        })
+    });
+}
+
+fn decode_scalars(c: &mut Criterion) {
+    let (client, runtime) = setup();
+    let rows = runtime
+        .block_on(client.query(
+            "SELECT i::INT4, i::INT8, i::FLOAT8, i::TEXT, i % 2 = 0 FROM generate_series(1, 10000) i",
+            &[],
+        ))
+        .unwrap();
+    c.bench_function("decode_scalars", move |b| {
+        b.iter(|| {
+            rows.iter()
+                .map(|row| {
+                    let a = row.get::<_, i32>(0) as i64;
+                    let b = row.get::<_, i64>(1);
+                    let c = row.get::<_, f64>(2) as i64;
+                    let d = row.get::<_, &str>(3).len() as i64;
+                    let e = row.get::<_, bool>(4) as i64;
+                    a + b + c + d + e
+                })
+                .sum::<i64>()
+        })
+    });
+}
+
+criterion_group!(benches, query_prepared, query_wide, decode_scalars);
 criterion_main!(benches);
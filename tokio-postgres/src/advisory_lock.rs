@@ -0,0 +1,129 @@
+use crate::codec::FrontendMessage;
+use crate::connection::RequestMessages;
+use crate::{Client, Error};
+use postgres_protocol::message::frontend;
+use std::fmt;
+
+/// The key identifying a PostgreSQL advisory lock.
+///
+/// Postgres supports two independent key spaces for advisory locks: a single 64-bit key, or a pair of 32-bit keys.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AdvisoryLockKey {
+    /// A single 64-bit key.
+    Single(i64),
+    /// A pair of 32-bit keys.
+    Pair(i32, i32),
+}
+
+impl From<i64> for AdvisoryLockKey {
+    fn from(key: i64) -> AdvisoryLockKey {
+        AdvisoryLockKey::Single(key)
+    }
+}
+
+impl From<(i32, i32)> for AdvisoryLockKey {
+    fn from((key1, key2): (i32, i32)) -> AdvisoryLockKey {
+        AdvisoryLockKey::Pair(key1, key2)
+    }
+}
+
+impl fmt::Display for AdvisoryLockKey {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AdvisoryLockKey::Single(key) => write!(fmt, "{}", key),
+            AdvisoryLockKey::Pair(key1, key2) => write!(fmt, "{}, {}", key1, key2),
+        }
+    }
+}
+
+/// A guard for a session-level advisory lock.
+///
+/// The lock is released when the guard is dropped. Use the `unlock` method to release it explicitly and observe any
+/// error encountered.
+pub struct AdvisoryLockGuard<'a> {
+    client: &'a Client,
+    key: AdvisoryLockKey,
+    done: bool,
+}
+
+impl<'a> Drop for AdvisoryLockGuard<'a> {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+
+        let query = format!("SELECT pg_catalog.pg_advisory_unlock({})", self.key);
+        let buf = self.client.inner().with_buf(|buf| {
+            frontend::query(&query, buf).unwrap();
+            buf.split().freeze()
+        });
+        let _ = self
+            .client
+            .inner()
+            .send_now(RequestMessages::Single(FrontendMessage::Raw(buf)));
+    }
+}
+
+impl<'a> AdvisoryLockGuard<'a> {
+    fn new(client: &'a Client, key: AdvisoryLockKey) -> AdvisoryLockGuard<'a> {
+        AdvisoryLockGuard {
+            client,
+            key,
+            done: false,
+        }
+    }
+
+    /// Returns the key of the lock held by this guard.
+    pub fn key(&self) -> AdvisoryLockKey {
+        self.key
+    }
+
+    /// Consumes the guard, releasing the lock.
+    ///
+    /// This is equivalent to the guard's `Drop` implementation, but provides any error encountered to the caller.
+    pub async fn unlock(mut self) -> Result<(), Error> {
+        self.done = true;
+        unlock(self.client, self.key).await.map(|_| ())
+    }
+}
+
+pub(crate) async fn lock(
+    client: &Client,
+    key: AdvisoryLockKey,
+) -> Result<AdvisoryLockGuard<'_>, Error> {
+    client
+        .batch_execute(&format!("SELECT pg_catalog.pg_advisory_lock({})", key))
+        .await?;
+    Ok(AdvisoryLockGuard::new(client, key))
+}
+
+pub(crate) async fn try_lock(
+    client: &Client,
+    key: AdvisoryLockKey,
+) -> Result<Option<AdvisoryLockGuard<'_>>, Error> {
+    let locked = call(client, "pg_try_advisory_lock", key).await?;
+    Ok(if locked {
+        Some(AdvisoryLockGuard::new(client, key))
+    } else {
+        None
+    })
+}
+
+pub(crate) async fn unlock(client: &Client, key: AdvisoryLockKey) -> Result<bool, Error> {
+    call(client, "pg_advisory_unlock", key).await
+}
+
+pub(crate) async fn xact_lock(client: &Client, key: AdvisoryLockKey) -> Result<(), Error> {
+    client
+        .batch_execute(&format!("SELECT pg_catalog.pg_advisory_xact_lock({})", key))
+        .await
+}
+
+pub(crate) async fn try_xact_lock(client: &Client, key: AdvisoryLockKey) -> Result<bool, Error> {
+    call(client, "pg_try_advisory_xact_lock", key).await
+}
+
+async fn call(client: &Client, function: &str, key: AdvisoryLockKey) -> Result<bool, Error> {
+    let query = format!("SELECT pg_catalog.{}({})", function, key);
+    client.query_one(&*query, &[]).await?.try_get(0)
+}
@@ -0,0 +1,110 @@
+use crate::binary_copy::BinaryCopyInWriter;
+use crate::escape::escape_identifier;
+use crate::types::ToSql;
+use crate::{Client, Error};
+use futures::pin_mut;
+use std::fmt::Write;
+
+const TEMP_TABLE: &str = "tokio_postgres_bulk_upsert";
+
+pub(crate) async fn bulk_upsert(
+    client: &Client,
+    table: &str,
+    columns: &[&str],
+    conflict_target: &[&str],
+    rows: &[&[&(dyn ToSql + Sync)]],
+) -> Result<u64, Error> {
+    assert!(!columns.is_empty(), "expected at least one column");
+
+    let query = upsert_query(table, columns, conflict_target);
+    let columns = columns
+        .iter()
+        .map(|c| escape_identifier(c))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let temp = escape_identifier(TEMP_TABLE);
+
+    // the temporary table has the types of the target's columns, but none of its constraints
+    client
+        .batch_execute(&format!(
+            "CREATE TEMPORARY TABLE {} ON COMMIT DROP AS SELECT {} FROM {} WITH NO DATA",
+            temp, columns, table,
+        ))
+        .await?;
+
+    let types = client
+        .prepare(&format!("SELECT {} FROM {}", columns, temp))
+        .await?
+        .columns()
+        .iter()
+        .map(|c| c.type_().clone())
+        .collect::<Vec<_>>();
+    let sink = client
+        .copy_in(&format!(
+            "COPY {} ({}) FROM STDIN (FORMAT binary)",
+            temp, columns
+        ))
+        .await?;
+    let writer = BinaryCopyInWriter::new(sink, &types);
+    pin_mut!(writer);
+    for row in rows {
+        writer.as_mut().write(row).await?;
+    }
+    writer.finish().await?;
+
+    let rows = client.execute(&query, &[]).await?;
+
+    client
+        .batch_execute(&format!("DROP TABLE {}", temp))
+        .await?;
+
+    Ok(rows)
+}
+
+fn upsert_query(table: &str, columns: &[&str], conflict_target: &[&str]) -> String {
+    let temp = escape_identifier(TEMP_TABLE);
+    let list = |names: &[&str]| {
+        names
+            .iter()
+            .map(|c| escape_identifier(c))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let target = list(conflict_target);
+
+    let mut query = format!("INSERT INTO {} ({}) SELECT ", table, list(columns));
+    if conflict_target.is_empty() {
+        let _ = write!(query, "{} FROM {}", list(columns), temp);
+    } else {
+        // a row can only be updated once per statement, so only the last of any duplicates is kept
+        let _ = write!(
+            query,
+            "DISTINCT ON ({}) {} FROM {} ORDER BY {}, ctid DESC",
+            target,
+            list(columns),
+            temp,
+            target,
+        );
+    }
+
+    query.push_str(" ON CONFLICT ");
+    if !conflict_target.is_empty() {
+        let _ = write!(query, "({}) ", target);
+    }
+
+    let updates = columns
+        .iter()
+        .filter(|c| !conflict_target.contains(c))
+        .map(|c| {
+            let c = escape_identifier(c);
+            format!("{} = EXCLUDED.{}", c, c)
+        })
+        .collect::<Vec<_>>();
+    if conflict_target.is_empty() || updates.is_empty() {
+        query.push_str("DO NOTHING");
+    } else {
+        let _ = write!(query, "DO UPDATE SET {}", updates.join(", "));
+    }
+
+    query
+}
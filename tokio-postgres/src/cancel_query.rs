@@ -9,7 +9,7 @@ pub(crate) async fn cancel_query<T>(
     ssl_mode: SslMode,
     mut tls: T,
     process_id: i32,
-    secret_key: i32,
+    secret_key: &[u8],
 ) -> Result<(), Error>
 where
     T: MakeTlsConnect<Socket>,
@@ -34,14 +34,7 @@ where
         .make_tls_connect(hostname)
         .map_err(|e| Error::tls(e.into()))?;
 
-    let socket = connect_socket::connect_socket(
-        &config.host,
-        config.port,
-        config.connect_timeout,
-        config.keepalives,
-        config.keepalives_idle,
-    )
-    .await?;
+    let socket = connect_socket::connect_socket(&config).await?;
 
     cancel_query_raw::cancel_query_raw(socket, ssl_mode, tls, process_id, secret_key).await
 }
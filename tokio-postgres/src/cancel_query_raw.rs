@@ -10,7 +10,7 @@ pub async fn cancel_query_raw<S, T>(
     mode: SslMode,
     tls: T,
     process_id: i32,
-    secret_key: i32,
+    secret_key: &[u8],
 ) -> Result<(), Error>
 where
     S: AsyncRead + AsyncWrite + Unpin,
@@ -19,7 +19,7 @@ where
     let mut stream = connect_tls::connect_tls(stream, mode, tls).await?;
 
     let mut buf = BytesMut::new();
-    frontend::cancel_request(process_id, secret_key, &mut buf);
+    frontend::cancel_request_with_key(process_id, secret_key, &mut buf).map_err(Error::encode)?;
 
     stream.write_all(&buf).await.map_err(Error::io)?;
     stream.flush().await.map_err(Error::io)?;
@@ -1,19 +1,53 @@
 use crate::config::SslMode;
 use crate::tls::TlsConnect;
-#[cfg(feature = "runtime")]
 use crate::{cancel_query, client::SocketConfig, tls::MakeTlsConnect, Socket};
 use crate::{cancel_query_raw, Error};
+use futures::future::BoxFuture;
+use std::fmt;
+use std::sync::Arc;
 use tokio::io::{AsyncRead, AsyncWrite};
 
+// Sends a cancellation request with a TLS connector captured when the function was created.
+pub(crate) type CancelFn =
+    dyn Fn(CancelToken) -> BoxFuture<'static, Result<(), Error>> + Sync + Send;
+
+pub(crate) fn cancel_fn<T>(tls: T) -> Arc<CancelFn>
+where
+    T: MakeTlsConnect<Socket> + Clone + 'static + Sync + Send,
+    T::TlsConnect: Send,
+    T::Stream: Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    Arc::new(move |token: CancelToken| {
+        let tls = tls.clone();
+        Box::pin(async move { token.cancel_query(tls).await })
+    })
+}
+
+// A wrapper allowing a cancellation function to be stored in a `Config`, which is comparable and printable.
+#[derive(Clone)]
+pub(crate) struct ConfigCancelFn(pub Arc<CancelFn>);
+
+impl PartialEq for ConfigCancelFn {
+    fn eq(&self, other: &ConfigCancelFn) -> bool {
+        Arc::as_ptr(&self.0) as *const () == Arc::as_ptr(&other.0) as *const ()
+    }
+}
+
+impl fmt::Debug for ConfigCancelFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CancelFn")
+    }
+}
+
 /// The capability to request cancellation of in-progress queries on a
 /// connection.
 #[derive(Clone)]
 pub struct CancelToken {
-    #[cfg(feature = "runtime")]
     pub(crate) socket_config: Option<SocketConfig>,
     pub(crate) ssl_mode: SslMode,
     pub(crate) process_id: i32,
-    pub(crate) secret_key: i32,
+    pub(crate) secret_key: Vec<u8>,
 }
 
 impl CancelToken {
@@ -28,8 +62,7 @@ impl CancelToken {
     /// normally, or that the connection associated with this token is still
     /// active.
     ///
-    /// Requires the `runtime` Cargo feature (enabled by default).
-    #[cfg(feature = "runtime")]
+    /// The cancellation request is sent over a new connection opened using the same runtime as the client's.
     pub async fn cancel_query<T>(&self, tls: T) -> Result<(), Error>
     where
         T: MakeTlsConnect<Socket>,
@@ -39,7 +72,7 @@ impl CancelToken {
             self.ssl_mode,
             tls,
             self.process_id,
-            self.secret_key,
+            &self.secret_key,
         )
         .await
     }
@@ -56,7 +89,7 @@ impl CancelToken {
             self.ssl_mode,
             tls,
             self.process_id,
-            self.secret_key,
+            &self.secret_key,
         )
         .await
     }
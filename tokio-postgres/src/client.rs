@@ -1,49 +1,120 @@
 use crate::codec::{BackendMessages, FrontendMessage};
-use crate::config::{Host, SslMode};
+use crate::config::{AddressStrategy, Host, ProtocolVersion, SslMode};
 use crate::connection::{Request, RequestMessages};
 use crate::copy_out::CopyOutStream;
+use crate::error::ParamRenderer;
+use crate::escape::escape_literal;
+#[cfg(feature = "with-serde_json-1")]
+use crate::explain::{self, ExplainOptions, QueryPlan};
+use crate::message_stats::MessageCounter;
+use crate::metrics::{ConnectionMetrics, Metrics};
 use crate::query::RowStream;
-use crate::simple_query::SimpleQueryStream;
-#[cfg(feature = "runtime")]
+use crate::replication::{
+    self, BaseBackupStream, IdentifySystem, ReplicationStream, TimelineHistory,
+};
+use crate::runtime::{self, Resolver, Runtime};
+use crate::simple_query::{SimpleQueryStream, SimpleResultSetStream};
+use crate::statement_cache::{StatementCache, StatementConfig};
 use crate::tls::MakeTlsConnect;
 use crate::tls::TlsConnect;
-use crate::types::{Oid, ToSql, Type};
-#[cfg(feature = "runtime")]
-use crate::Socket;
+use crate::types::{FromSqlOwned, Oid, PgLsn, ToSql, Type};
 use crate::{
-    copy_in, copy_out, prepare, query, simple_query, slice_iter, CancelToken, CopyInSink, Error,
-    Row, SimpleQueryMessage, Statement, ToStatement, Transaction, TransactionBuilder,
+    advisory_lock, copy_both, copy_in, copy_out, function_call, lsn, prepare, prepared_transaction,
+    procedure, query, session_parameter, simple_query, slice_iter, to_sql_stream,
+    AdvisoryLockGuard, AdvisoryLockKey, BindParam, CancelToken, CommandTag, CopyBothDuplex,
+    CopyInSink, Error, MessageStats, NamedParams, NamedQuery, ParameterScope, PreparedTransaction,
+    QueryCache, QueryCacheKey, QueryLogger, Row, SimpleQueryMessage, Statement, ToStatement,
+    Transaction, TransactionBuilder,
 };
+use crate::{RetryPolicy, Socket};
 use bytes::{Buf, BytesMut};
 use fallible_iterator::FallibleIterator;
 use futures::channel::mpsc;
+use futures::future::BoxFuture;
+use futures::task::AtomicWaker;
 use futures::{future, pin_mut, ready, StreamExt, TryStreamExt};
 use parking_lot::Mutex;
 use postgres_protocol::message::{backend::Message, frontend};
 use postgres_types::BorrowToSql;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::Duration;
 use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 
 pub struct Responses {
     receiver: mpsc::Receiver<BackendMessages>,
     cur: BackendMessages,
+    bytes_received: usize,
+    abandon: Option<Abandon>,
+    // set for requests ending with a Sync, for which the server follows errors with a ReadyForQuery right away
+    synced: bool,
+    // an error returned once the rest of the request has been received, so that the transaction status is up to date
+    error: Option<Error>,
+}
+
+// Tells the connection that a query was abandoned while the server was still executing it, so that it can clean up
+// after it. The waker is only present if the connection is configured to cancel abandoned queries.
+struct Abandon {
+    abandoned: Arc<AtomicBool>,
+    waker: Option<Arc<AtomicWaker>>,
+    // cleared once the server reports that execution has finished
+    executing: bool,
+}
+
+impl Drop for Responses {
+    fn drop(&mut self) {
+        if let Some(abandon) = &self.abandon {
+            if abandon.executing {
+                abandon.abandoned.store(true, Ordering::Release);
+                if let Some(waker) = &abandon.waker {
+                    waker.wake();
+                }
+            }
+        }
+    }
 }
 
 impl Responses {
     pub fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Result<Message, Error>> {
         loop {
-            match self.cur.next().map_err(Error::parse)? {
+            if self.error.is_some() {
+                ready!(self.poll_complete(cx));
+                return Poll::Ready(Err(self.error.take().unwrap()));
+            }
+
+            let message = self.cur.next().map_err(Error::parse)?;
+            if let (
+                Some(abandon),
+                Some(
+                    Message::CommandComplete(_)
+                    | Message::EmptyQueryResponse
+                    | Message::PortalSuspended
+                    | Message::ErrorResponse(_)
+                    | Message::ReadyForQuery(_),
+                ),
+            ) = (&mut self.abandon, &message)
+            {
+                abandon.executing = false;
+            }
+            match message {
+                Some(Message::ErrorResponse(body)) if self.synced => {
+                    self.error = Some(Error::db(body));
+                    continue;
+                }
                 Some(Message::ErrorResponse(body)) => return Poll::Ready(Err(Error::db(body))),
                 Some(message) => return Poll::Ready(Ok(message)),
                 None => {}
             }
 
             match ready!(self.receiver.poll_next_unpin(cx)) {
-                Some(messages) => self.cur = messages,
+                Some(messages) => {
+                    self.bytes_received += messages.len();
+                    self.cur = messages;
+                }
                 None => return Poll::Ready(Err(Error::closed())),
             }
         }
@@ -52,6 +123,35 @@ impl Responses {
     pub async fn next(&mut self) -> Result<Message, Error> {
         future::poll_fn(|cx| self.poll_next(cx)).await
     }
+
+    // Buffers the remaining messages of the request, which the connection has finished with once it hangs up.
+    fn poll_complete(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        while let Some(messages) = ready!(self.receiver.poll_next_unpin(cx)) {
+            self.bytes_received += messages.len();
+            self.cur.extend(messages);
+        }
+        Poll::Ready(())
+    }
+
+    /// Waits for the server to acknowledge a Bind message.
+    ///
+    /// Binds to the unnamed statement are preceded by a Parse re-creating it, which is acknowledged first.
+    pub async fn bind_complete(&mut self) -> Result<(), Error> {
+        let mut message = self.next().await?;
+        if let Message::ParseComplete = message {
+            message = self.next().await?;
+        }
+
+        match message {
+            Message::BindComplete => Ok(()),
+            _ => Err(Error::unexpected_message()),
+        }
+    }
+
+    /// Returns the number of bytes of backend messages received so far.
+    pub fn bytes_received(&self) -> usize {
+        self.bytes_received
+    }
 }
 
 /// A cache of type info and prepared statements for fetching type info
@@ -77,22 +177,103 @@ struct CachedTypeInfo {
 pub struct InnerClient {
     sender: mpsc::UnboundedSender<Request>,
     cached_typeinfo: Mutex<CachedTypeInfo>,
+    query_in_errors: AtomicBool,
+    error_param_renderer: Mutex<Option<Arc<ParamRenderer>>>,
+    query_logger: Mutex<Option<Arc<dyn QueryLogger>>>,
+    log_param_renderer: Mutex<Option<Arc<ParamRenderer>>>,
+    query_cache: Mutex<Option<Arc<dyn QueryCache>>>,
+    /// The configuration parameters changed for the session by `Client::set_parameter`.
+    changed_parameters: Mutex<BTreeSet<String>>,
+    metrics: Option<ConnectionMetrics>,
+    /// Limits the number of requests waiting to be picked up by the connection, if configured.
+    request_permits: Option<Arc<Semaphore>>,
+    statement_config: StatementConfig,
+    /// Wakes the connection when a request is abandoned, if it is configured to cancel abandoned queries.
+    abandon_waker: Mutex<Option<Arc<AtomicWaker>>>,
+    /// The transaction status reported by the server when it last finished processing a request.
+    transaction_status: Arc<AtomicU8>,
+    /// The statements prepared for raw query strings, if caching is enabled.
+    statement_cache: Mutex<StatementCache>,
+    /// The spans of the client's open transaction and its savepoints, innermost last.
+    #[cfg(feature = "tracing")]
+    transaction_spans: Mutex<Vec<tracing::Span>>,
 
     /// A buffer to use when writing out postgres commands.
     buffer: Mutex<BytesMut>,
 }
 
 impl InnerClient {
-    pub fn send(&self, messages: RequestMessages) -> Result<Responses, Error> {
+    /// Sends a request to the connection, first waiting for space in the request queue if its capacity is limited.
+    pub async fn send(&self, messages: RequestMessages) -> Result<Responses, Error> {
+        let permit = self.acquire_permit().await?;
+        self.send_with_permit(messages, permit, false)
+    }
+
+    /// Sends a request executing a query.
+    ///
+    /// If the responses are dropped before the server finishes executing the query, the connection closes the
+    /// query's portal, and cancels the query if it is configured to do so.
+    pub async fn send_query(&self, messages: RequestMessages) -> Result<Responses, Error> {
+        let permit = self.acquire_permit().await?;
+        self.send_with_permit(messages, permit, true)
+    }
+
+    async fn acquire_permit(&self) -> Result<Option<OwnedSemaphorePermit>, Error> {
+        match &self.request_permits {
+            Some(permits) => permits
+                .clone()
+                .acquire_owned()
+                .await
+                .map(Some)
+                .map_err(|_| Error::closed()),
+            None => Ok(None),
+        }
+    }
+
+    /// Sends a request to the connection immediately, ignoring the capacity of the request queue.
+    ///
+    /// This is intended for use in destructors, which can't wait.
+    pub fn send_now(&self, messages: RequestMessages) -> Result<Responses, Error> {
+        self.send_with_permit(messages, None, false)
+    }
+
+    fn send_with_permit(
+        &self,
+        messages: RequestMessages,
+        permit: Option<OwnedSemaphorePermit>,
+        query: bool,
+    ) -> Result<Responses, Error> {
         let (sender, receiver) = mpsc::channel(1);
-        let request = Request { messages, sender };
+        let synced = matches!(messages, RequestMessages::Single(_));
+        let abandon = if query {
+            Some(Abandon {
+                abandoned: Arc::new(AtomicBool::new(false)),
+                waker: self.abandon_waker.lock().clone(),
+                executing: true,
+            })
+        } else {
+            None
+        };
+        let request = Request {
+            messages,
+            sender,
+            permit,
+            abandoned: abandon.as_ref().map(|a| a.abandoned.clone()),
+        };
         self.sender
             .unbounded_send(request)
             .map_err(|_| Error::closed())?;
+        if let Some(metrics) = &self.metrics {
+            metrics.request_queued();
+        }
 
         Ok(Responses {
             receiver,
             cur: BackendMessages::empty(),
+            bytes_received: 0,
+            abandon,
+            synced,
+            error: None,
         })
     }
 
@@ -132,8 +313,132 @@ impl InnerClient {
         self.cached_typeinfo.lock().types.clear();
     }
 
+    pub fn query_in_errors(&self) -> bool {
+        self.query_in_errors.load(Ordering::Relaxed)
+    }
+
+    pub fn set_query_in_errors(&self, enabled: bool) {
+        self.query_in_errors.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn error_param_renderer(&self) -> Option<Arc<ParamRenderer>> {
+        self.error_param_renderer.lock().clone()
+    }
+
+    pub fn set_error_param_renderer(&self, renderer: Option<Arc<ParamRenderer>>) {
+        *self.error_param_renderer.lock() = renderer;
+    }
+
+    pub fn query_logger(&self) -> Option<Arc<dyn QueryLogger>> {
+        self.query_logger.lock().clone()
+    }
+
+    pub fn set_query_logger(&self, logger: Option<Arc<dyn QueryLogger>>) {
+        *self.query_logger.lock() = logger;
+    }
+
+    pub fn log_param_renderer(&self) -> Option<Arc<ParamRenderer>> {
+        self.log_param_renderer.lock().clone()
+    }
+
+    pub fn set_log_param_renderer(&self, renderer: Option<Arc<ParamRenderer>>) {
+        *self.log_param_renderer.lock() = renderer;
+    }
+
+    pub fn query_cache(&self) -> Option<Arc<dyn QueryCache>> {
+        self.query_cache.lock().clone()
+    }
+
+    pub fn set_query_cache(&self, cache: Option<Arc<dyn QueryCache>>) {
+        *self.query_cache.lock() = cache;
+    }
+
+    pub fn changed_parameters(&self) -> Vec<String> {
+        self.changed_parameters.lock().iter().cloned().collect()
+    }
+
+    // parameter names are case insensitive
+    pub fn add_changed_parameter(&self, name: &str) {
+        self.changed_parameters
+            .lock()
+            .insert(name.to_ascii_lowercase());
+    }
+
+    pub fn remove_changed_parameter(&self, name: &str) {
+        self.changed_parameters
+            .lock()
+            .remove(&name.to_ascii_lowercase());
+    }
+
+    /// Determines if statements are prepared under names of their own, rather than as the unnamed statement.
+    pub fn named_statements(&self) -> bool {
+        self.statement_config.named
+    }
+
+    pub fn transaction_status(&self) -> &Arc<AtomicU8> {
+        &self.transaction_status
+    }
+
+    pub fn set_abandon_waker(&self, waker: Arc<AtomicWaker>) {
+        *self.abandon_waker.lock() = Some(waker);
+    }
+
+    pub fn statement_name_prefix(&self) -> &str {
+        &self.statement_config.name_prefix
+    }
+
+    pub fn cached_statement(&self, query: &str) -> Option<Statement> {
+        self.statement_cache.lock().get(query)
+    }
+
+    pub fn cache_statement(&self, query: &str, statement: &Statement) {
+        // evicted statements are closed when dropped, which must happen after the lock is released
+        let evicted = self.statement_cache.lock().insert(query, statement.clone());
+        drop(evicted);
+    }
+
+    pub fn is_cached_statement(&self, statement: &Statement) -> bool {
+        self.statement_cache.lock().contains(statement)
+    }
+
+    pub fn statement_cache_enabled(&self) -> bool {
+        self.statement_config.named && self.statement_cache.lock().is_enabled()
+    }
+
+    pub fn statement_cache_len(&self) -> usize {
+        self.statement_cache.lock().len()
+    }
+
+    pub fn clear_statement_cache(&self) {
+        let statements = self.statement_cache.lock().clear();
+        drop(statements);
+    }
+
+    pub fn metrics(&self) -> Option<&Arc<dyn Metrics>> {
+        self.metrics.as_ref().map(ConnectionMetrics::get)
+    }
+
+    #[cfg(feature = "tracing")]
+    pub fn transaction_span(&self) -> Option<tracing::Span> {
+        self.transaction_spans.lock().last().cloned()
+    }
+
+    #[cfg(feature = "tracing")]
+    pub fn push_transaction_span(&self, span: tracing::Span) {
+        self.transaction_spans.lock().push(span);
+    }
+
+    #[cfg(feature = "tracing")]
+    pub fn pop_transaction_span(&self) {
+        self.transaction_spans.lock().pop();
+    }
+
     /// Call the given function with a buffer to be used when writing out
     /// postgres commands.
+    /// Encodes messages into the client's shared buffer.
+    ///
+    /// Callers split the encoded messages off of the buffer. Once the connection has written them out, the buffer
+    /// reclaims their storage, so steady-state encoding does not allocate.
     pub fn with_buf<F, R>(&self, f: F) -> R
     where
         F: FnOnce(&mut BytesMut) -> R,
@@ -147,11 +452,28 @@ impl InnerClient {
 
 #[derive(Clone)]
 pub(crate) struct SocketConfig {
+    pub runtime: Arc<dyn Runtime>,
+    pub resolver: Option<Arc<dyn Resolver>>,
     pub host: Host,
     pub port: u16,
     pub connect_timeout: Option<Duration>,
     pub keepalives: bool,
     pub keepalives_idle: Duration,
+    pub address_strategy: AddressStrategy,
+    pub happy_eyeballs_delay: Duration,
+}
+
+/// The transaction status of a connection, as reported by the server each time it finishes processing a request.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TransactionStatus {
+    /// The connection is not in a transaction block.
+    Idle,
+    /// The connection is in a transaction block.
+    InTransaction,
+    /// The connection is in a failed transaction block.
+    ///
+    /// The server rejects all queries until the transaction block is ended, for example with `ROLLBACK`.
+    InError,
 }
 
 /// An asynchronous PostgreSQL client.
@@ -160,11 +482,12 @@ pub(crate) struct SocketConfig {
 /// through this client object.
 pub struct Client {
     inner: Arc<InnerClient>,
-    #[cfg(feature = "runtime")]
     socket_config: Option<SocketConfig>,
     ssl_mode: SslMode,
     process_id: i32,
-    secret_key: i32,
+    secret_key: Vec<u8>,
+    protocol_version: ProtocolVersion,
+    message_counter: MessageCounter,
 }
 
 impl Client {
@@ -172,19 +495,37 @@ impl Client {
         sender: mpsc::UnboundedSender<Request>,
         ssl_mode: SslMode,
         process_id: i32,
-        secret_key: i32,
+        secret_key: Vec<u8>,
+        metrics: Option<ConnectionMetrics>,
+        request_queue_capacity: Option<usize>,
+        statement_config: StatementConfig,
     ) -> Client {
         Client {
             inner: Arc::new(InnerClient {
                 sender,
                 cached_typeinfo: Default::default(),
+                query_in_errors: AtomicBool::new(false),
+                error_param_renderer: Default::default(),
+                query_logger: Default::default(),
+                log_param_renderer: Default::default(),
+                query_cache: Default::default(),
+                changed_parameters: Default::default(),
+                metrics,
+                request_permits: request_queue_capacity.map(|n| Arc::new(Semaphore::new(n))),
+                statement_cache: Mutex::new(StatementCache::new(statement_config.cache_capacity)),
+                statement_config,
+                abandon_waker: Default::default(),
+                transaction_status: Arc::new(AtomicU8::new(b'I')),
+                #[cfg(feature = "tracing")]
+                transaction_spans: Default::default(),
                 buffer: Default::default(),
             }),
-            #[cfg(feature = "runtime")]
             socket_config: None,
             ssl_mode,
             process_id,
             secret_key,
+            protocol_version: ProtocolVersion::V3_0,
+            message_counter: MessageCounter::default(),
         }
     }
 
@@ -192,11 +533,37 @@ impl Client {
         &self.inner
     }
 
-    #[cfg(feature = "runtime")]
+    pub(crate) fn set_protocol_version(&mut self, protocol_version: ProtocolVersion) {
+        self.protocol_version = protocol_version;
+    }
+
+    pub(crate) fn set_message_counter(&mut self, message_counter: MessageCounter) {
+        self.message_counter = message_counter;
+    }
+
+    #[cfg(any(
+        feature = "runtime",
+        feature = "runtime-async-std",
+        feature = "runtime-smol"
+    ))]
     pub(crate) fn set_socket_config(&mut self, socket_config: SocketConfig) {
         self.socket_config = Some(socket_config);
     }
 
+    // Determines if the connection was in a transaction when the server last finished processing a request.
+    pub(crate) fn in_transaction(&self) -> bool {
+        self.transaction_status() != TransactionStatus::Idle
+    }
+
+    // Returns the runtime the client was connected with, falling back to the default for clients connected over a raw
+    // stream.
+    pub(crate) fn runtime(&self) -> Option<Arc<dyn Runtime>> {
+        match &self.socket_config {
+            Some(config) => Some(config.runtime.clone()),
+            None => runtime::default_runtime(),
+        }
+    }
+
     /// Creates a new prepared statement.
     ///
     /// Prepared statements can be executed repeatedly, and may contain query parameters (indicated by `$1`, `$2`, etc),
@@ -205,6 +572,21 @@ impl Client {
         self.prepare_typed(query, &[]).await
     }
 
+    // Prepares a raw query string passed in place of a statement, reusing a cached statement if the statement cache
+    // is enabled.
+    pub(crate) async fn prepare_implicit(&self, query: &str) -> Result<Statement, Error> {
+        if !self.inner.statement_cache_enabled() {
+            return self.prepare(query).await;
+        }
+
+        if let Some(statement) = self.inner.cached_statement(query) {
+            return Ok(statement);
+        }
+        let statement = self.prepare(query).await?;
+        self.inner.cache_statement(query, &statement);
+        Ok(statement)
+    }
+
     /// Like `prepare`, but allows the types of query parameters to be explicitly specified.
     ///
     /// The list of types may be smaller than the number of parameters - the types of the remaining parameters will be
@@ -226,9 +608,8 @@ impl Client {
     /// repeatedly executed (perhaps with different query parameters), consider preparing the statement up front
     /// with the `prepare` method.
     ///
-    /// # Panics
-    ///
-    /// Panics if the number of parameters provided does not match the number expected.
+    /// Returns an error if the number of parameters provided does not match the number expected, or if any of them
+    /// has a type incompatible with the statement.
     pub async fn query<T>(
         &self,
         statement: &T,
@@ -243,6 +624,43 @@ impl Client {
             .await
     }
 
+    /// Like `query`, but consults the client's query cache before executing the statement.
+    ///
+    /// Results are cached under the text of the statement and the values of its parameters, and are returned from the
+    /// cache until `ttl` has passed. Rows are not invalidated when the underlying data changes, so this is only suitable
+    /// for read-mostly lookups which can tolerate stale results. If no cache has been set with `set_query_cache`, the
+    /// statement is always executed.
+    ///
+    /// A raw query string must be prepared to determine the types of its parameters, even if the result is cached, so
+    /// prefer passing a `Statement` prepared up front.
+    ///
+    /// Returns an error if the number of parameters provided does not match the number expected, or if any of them
+    /// has a type incompatible with the statement.
+    pub async fn query_cached<T>(
+        &self,
+        statement: &T,
+        params: &[&(dyn ToSql + Sync)],
+        ttl: Duration,
+    ) -> Result<Vec<Row>, Error>
+    where
+        T: ?Sized + ToStatement,
+    {
+        let cache = match self.inner().query_cache() {
+            Some(cache) => cache,
+            None => return self.query(statement, params).await,
+        };
+
+        let statement = statement.__convert().into_statement(self).await?;
+        let key = QueryCacheKey::new(&statement, params)?;
+        if let Some(rows) = cache.get(&key) {
+            return Ok(rows);
+        }
+
+        let rows = self.query(&statement, params).await?;
+        cache.insert(key, rows.clone(), ttl);
+        Ok(rows)
+    }
+
     /// Executes a statement which returns a single row, returning it.
     ///
     /// Returns an error if the query does not return exactly one row.
@@ -254,9 +672,8 @@ impl Client {
     /// repeatedly executed (perhaps with different query parameters), consider preparing the statement up front
     /// with the `prepare` method.
     ///
-    /// # Panics
-    ///
-    /// Panics if the number of parameters provided does not match the number expected.
+    /// Returns an error if the number of parameters provided does not match the number expected, or if any of them
+    /// has a type incompatible with the statement.
     pub async fn query_one<T>(
         &self,
         statement: &T,
@@ -291,9 +708,8 @@ impl Client {
     /// repeatedly executed (perhaps with different query parameters), consider preparing the statement up front
     /// with the `prepare` method.
     ///
-    /// # Panics
-    ///
-    /// Panics if the number of parameters provided does not match the number expected.
+    /// Returns an error if the number of parameters provided does not match the number expected, or if any of them
+    /// has a type incompatible with the statement.
     pub async fn query_opt<T>(
         &self,
         statement: &T,
@@ -317,7 +733,11 @@ impl Client {
         Ok(Some(row))
     }
 
-    /// The maximally flexible version of [`query`].
+    /// Executes a statement, combining its rows into a single value with the provided function as they are received.
+    ///
+    /// This is like calling `Iterator::fold` on the rows returned by `query`, but rows are processed one at a time
+    /// as they arrive from the server rather than being collected first, so memory use does not grow with the size of
+    /// the result set. If the function returns an error, the remaining rows are discarded and the error is returned.
     ///
     /// A statement may contain parameters, specified by `$n`, where `n` is the index of the parameter of the list
     /// provided, 1-indexed.
@@ -326,9 +746,54 @@ impl Client {
     /// repeatedly executed (perhaps with different query parameters), consider preparing the statement up front
     /// with the `prepare` method.
     ///
-    /// # Panics
+    /// Returns an error if the number of parameters provided does not match the number expected, or if any of them
+    /// has a type incompatible with the statement.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # async fn async_main(client: &tokio_postgres::Client) -> Result<(), tokio_postgres::Error> {
+    /// let total = client
+    ///     .query_fold("SELECT amount FROM payments", &[], 0i64, |total, row| {
+    ///         Ok(total + row.try_get::<_, i64>(0)?)
+    ///     })
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn query_fold<T, B, F>(
+        &self,
+        statement: &T,
+        params: &[&(dyn ToSql + Sync)],
+        init: B,
+        mut f: F,
+    ) -> Result<B, Error>
+    where
+        T: ?Sized + ToStatement,
+        F: FnMut(B, Row) -> Result<B, Error>,
+    {
+        let stream = self.query_raw(statement, slice_iter(params)).await?;
+        pin_mut!(stream);
+
+        let mut acc = init;
+        while let Some(row) = stream.try_next().await? {
+            acc = f(acc, row)?;
+        }
+
+        Ok(acc)
+    }
+
+    /// The maximally flexible version of [`query`].
     ///
-    /// Panics if the number of parameters provided does not match the number expected.
+    /// A statement may contain parameters, specified by `$n`, where `n` is the index of the parameter of the list
+    /// provided, 1-indexed.
+    ///
+    /// The `statement` argument can either be a `Statement`, or a raw query string. If the same statement will be
+    /// repeatedly executed (perhaps with different query parameters), consider preparing the statement up front
+    /// with the `prepare` method.
+    ///
+    /// Returns an error if the number of parameters provided does not match the number expected, or if any of them
+    /// has a type incompatible with the statement.
     ///
     /// [`query`]: #method.query
     ///
@@ -367,6 +832,47 @@ impl Client {
         query::query(&self.inner, statement, params).await
     }
 
+    /// Like `query`, but with the type of each parameter specified explicitly.
+    ///
+    /// The query is prepared with the provided parameter types, as with `prepare_typed`, rather than having the server
+    /// infer them. This avoids errors such as "could not determine data type of parameter" for queries in which the
+    /// type of a parameter is ambiguous, and allows a parameter to be sent as a different type than the server would
+    /// pick. The query is always prepared as the unnamed statement, and is never cached.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # async fn async_main(client: &tokio_postgres::Client) -> Result<(), tokio_postgres::Error> {
+    /// use tokio_postgres::types::Type;
+    ///
+    /// let rows = client
+    ///     .query_typed("SELECT $1 || $2", &[(&"foo", Type::TEXT), (&"bar", Type::TEXT)])
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn query_typed(
+        &self,
+        query: &str,
+        params: &[(&(dyn ToSql + Sync), Type)],
+    ) -> Result<Vec<Row>, Error> {
+        self.query_typed_raw(query, params.iter().map(|(v, t)| (*v, t.clone())))
+            .await?
+            .try_collect()
+            .await
+    }
+
+    /// The maximally flexible version of [`query_typed`].
+    ///
+    /// [`query_typed`]: #method.query_typed
+    pub async fn query_typed_raw<P, I>(&self, query: &str, params: I) -> Result<RowStream, Error>
+    where
+        P: BorrowToSql,
+        I: IntoIterator<Item = (P, Type)>,
+    {
+        query::query_typed(&self.inner, query, params).await
+    }
+
     /// Executes a statement, returning the number of rows modified.
     ///
     /// A statement may contain parameters, specified by `$n`, where `n` is the index of the parameter of the list
@@ -378,9 +884,8 @@ impl Client {
     ///
     /// If the statement does not modify any rows (e.g. `SELECT`), 0 is returned.
     ///
-    /// # Panics
-    ///
-    /// Panics if the number of parameters provided does not match the number expected.
+    /// Returns an error if the number of parameters provided does not match the number expected, or if any of them
+    /// has a type incompatible with the statement.
     pub async fn execute<T>(
         &self,
         statement: &T,
@@ -401,12 +906,43 @@ impl Client {
     /// repeatedly executed (perhaps with different query parameters), consider preparing the statement up front
     /// with the `prepare` method.
     ///
-    /// # Panics
-    ///
-    /// Panics if the number of parameters provided does not match the number expected.
+    /// Returns an error if the number of parameters provided does not match the number expected, or if any of them
+    /// has a type incompatible with the statement.
     ///
     /// [`execute`]: #method.execute
     pub async fn execute_raw<T, P, I>(&self, statement: &T, params: I) -> Result<u64, Error>
+    where
+        T: ?Sized + ToStatement,
+        P: BorrowToSql,
+        I: IntoIterator<Item = P>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        self.execute_raw_with_tag(statement, params)
+            .await
+            .map(|tag| tag.rows())
+    }
+
+    /// Like `execute`, but returns the full command tag sent by the server rather than just the number of rows.
+    ///
+    /// This distinguishes the kind of command which was executed, for example `INSERT 0 1` from `UPDATE 1`.
+    pub async fn execute_with_tag<T>(
+        &self,
+        statement: &T,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<CommandTag, Error>
+    where
+        T: ?Sized + ToStatement,
+    {
+        self.execute_raw_with_tag(statement, slice_iter(params))
+            .await
+    }
+
+    /// Like `execute_raw`, but returns the full command tag sent by the server rather than just the number of rows.
+    pub async fn execute_raw_with_tag<T, P, I>(
+        &self,
+        statement: &T,
+        params: I,
+    ) -> Result<CommandTag, Error>
     where
         T: ?Sized + ToStatement,
         P: BorrowToSql,
@@ -417,14 +953,131 @@ impl Client {
         query::execute(self.inner(), statement, params).await
     }
 
+    /// Like `query`, but binds parameters by name rather than position.
+    ///
+    /// Parameters are written as `:name` in the statement, which is rewritten to use positional parameters as
+    /// described in [`NamedQuery`]. An error is returned if a value is missing for any of the statement's parameters,
+    /// or if any of the values provided is not used by the statement.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # async fn f(client: &tokio_postgres::Client) -> Result<(), tokio_postgres::Error> {
+    /// let rows = client
+    ///     .query_named(
+    ///         "SELECT name FROM users WHERE id = :id OR parent_id = :id",
+    ///         &[("id", &1i32 as _)],
+    ///     )
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn query_named<P>(&self, query: &str, params: &P) -> Result<Vec<Row>, Error>
+    where
+        P: ?Sized + NamedParams,
+    {
+        let query = NamedQuery::new(query);
+        let params = query.bind(params)?;
+        self.query(query.sql(), &params).await
+    }
+
+    /// Like `execute`, but binds parameters by name rather than position.
+    ///
+    /// See `query_named` for details.
+    pub async fn execute_named<P>(&self, query: &str, params: &P) -> Result<u64, Error>
+    where
+        P: ?Sized + NamedParams,
+    {
+        let query = NamedQuery::new(query);
+        let params = query.bind(params)?;
+        self.execute(query.sql(), &params).await
+    }
+
+    /// Like [`execute`], but allows some parameter values to be streamed to the server rather than encoded up front.
+    ///
+    /// Streamed values are read only as they are written to the connection, so large `BYTEA` or text values don't need
+    /// to be held in memory. No other requests are sent on the connection until all of the values have been streamed.
+    ///
+    /// If a stream fails or produces a different number of bytes than its declared length, the statement is not
+    /// executed and the error is returned.
+    ///
+    /// Returns an error if the number of parameters provided does not match the number expected, or if any of them
+    /// has a type incompatible with the statement.
+    ///
+    /// [`execute`]: #method.execute
+    pub async fn execute_streamed<T>(
+        &self,
+        statement: &T,
+        params: Vec<BindParam<'_>>,
+    ) -> Result<u64, Error>
+    where
+        T: ?Sized + ToStatement,
+    {
+        let statement = statement.__convert().into_statement(self).await?;
+        to_sql_stream::execute(self.inner(), statement, params).await
+    }
+
+    /// Calls a server function directly using the fastpath function call protocol.
+    ///
+    /// The function is identified by its OID, and each parameter is paired with the type the function expects for it.
+    /// Unlike a query, the call requires no statement to be parsed or planned, and completes in a single round trip.
+    /// The function's return value is decoded as `result_type`.
+    ///
+    /// Only functions returning a single value, rather than a set or a row, can be called in this way.
+    pub async fn function_call<T>(
+        &self,
+        function: Oid,
+        params: &[(&(dyn ToSql + Sync), Type)],
+        result_type: &Type,
+    ) -> Result<T, Error>
+    where
+        T: FromSqlOwned,
+    {
+        function_call::function_call(self.inner(), function, params, result_type).await
+    }
+
+    /// Returns the plan chosen by the server for a statement, using `EXPLAIN`.
+    ///
+    /// The statement is inserted into the `EXPLAIN` statement verbatim, and may contain parameters. If the options
+    /// request the statement to be analyzed, it is actually executed, including any side effects.
+    ///
+    /// Requires the `with-serde_json-1` feature.
+    #[cfg(feature = "with-serde_json-1")]
+    pub async fn explain(
+        &self,
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+        options: &ExplainOptions,
+    ) -> Result<QueryPlan, Error> {
+        explain::explain(self, statement, params, options).await
+    }
+
+    /// Calls a stored procedure with the `CALL` statement.
+    ///
+    /// The procedure name is inserted into the statement verbatim, so it may be schema-qualified but must not come
+    /// from untrusted input. One parameter must be provided for each of the procedure's arguments, including `OUT`
+    /// arguments, for which `None` is conventionally passed. If the procedure has `INOUT` or `OUT` arguments, their
+    /// final values are returned as a row.
+    ///
+    /// A procedure may issue `COMMIT` or `ROLLBACK` itself only when it is not called from within a transaction;
+    /// otherwise the server will report an error.
+    ///
+    /// Returns an error if the number of parameters provided does not match the number expected, or if any of them
+    /// has a type incompatible with the statement.
+    pub async fn call(
+        &self,
+        procedure: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Option<Row>, Error> {
+        procedure::call(self, procedure, params).await
+    }
+
     /// Executes a `COPY FROM STDIN` statement, returning a sink used to write the copy data.
     ///
     /// PostgreSQL does not support parameters in `COPY` statements, so this method does not take any. The copy *must*
     /// be explicitly completed via the `Sink::close` or `finish` methods. If it is not, the copy will be aborted.
     ///
-    /// # Panics
-    ///
-    /// Panics if the statement contains parameters.
+    /// Returns an error if the statement contains parameters.
     pub async fn copy_in<T, U>(&self, statement: &T) -> Result<CopyInSink<U>, Error>
     where
         T: ?Sized + ToStatement,
@@ -436,11 +1089,10 @@ impl Client {
 
     /// Executes a `COPY TO STDOUT` statement, returning a stream of the resulting data.
     ///
-    /// PostgreSQL does not support parameters in `COPY` statements, so this method does not take any.
+    /// PostgreSQL does not support parameters in `COPY` statements, so this method does not take any. An empty query
+    /// produces an empty stream.
     ///
-    /// # Panics
-    ///
-    /// Panics if the statement contains parameters.
+    /// Returns an error if the statement contains parameters.
     pub async fn copy_out<T>(&self, statement: &T) -> Result<CopyOutStream, Error>
     where
         T: ?Sized + ToStatement,
@@ -449,6 +1101,74 @@ impl Client {
         copy_out::copy_out(self.inner(), statement).await
     }
 
+    /// Executes a command which enters `COPY BOTH` mode using the simple query protocol, returning a duplex stream
+    /// for exchanging `CopyData` messages with the server.
+    ///
+    /// This is the low-level mechanism underlying the streaming replication protocol, and is only useful on
+    /// connections opened in replication mode. Most users should use `start_logical_replication` instead.
+    pub async fn copy_both_simple<T>(&self, query: &str) -> Result<CopyBothDuplex<T>, Error>
+    where
+        T: Buf + 'static + Send,
+    {
+        copy_both::copy_both_simple(self.inner(), query).await
+    }
+
+    /// Starts streaming changes from a logical replication slot.
+    ///
+    /// Streaming begins at `start_lsn`, or at the slot's confirmed position if that is later. The `options` are passed
+    /// to the slot's output plugin. The connection must have been opened with `ReplicationMode::Logical`, and cannot
+    /// be used for anything else until the returned stream has finished.
+    pub async fn start_logical_replication(
+        &self,
+        slot: &str,
+        start_lsn: PgLsn,
+        options: &[(&str, &str)],
+    ) -> Result<ReplicationStream, Error> {
+        replication::start_logical_replication(self.inner(), slot, start_lsn, options).await
+    }
+
+    /// Starts streaming WAL from the server for physical replication.
+    ///
+    /// Streaming begins at `start_lsn` on the specified timeline, or on the server's current timeline if `None`. If a
+    /// physical replication slot is specified, the server retains the WAL which has not yet been confirmed by the
+    /// client. The connection must have been opened with `ReplicationMode::Physical`, and cannot be used for anything
+    /// else until the returned stream has finished.
+    pub async fn start_physical_replication(
+        &self,
+        slot: Option<&str>,
+        start_lsn: PgLsn,
+        timeline: Option<u32>,
+    ) -> Result<ReplicationStream, Error> {
+        replication::start_physical_replication(self.inner(), slot, start_lsn, timeline).await
+    }
+
+    /// Requests identifying information about the server with the `IDENTIFY_SYSTEM` replication command.
+    ///
+    /// The connection must have been opened in replication mode.
+    pub async fn identify_system(&self) -> Result<IdentifySystem, Error> {
+        replication::identify_system(self).await
+    }
+
+    /// Requests the history file of a timeline with the `TIMELINE_HISTORY` replication command.
+    ///
+    /// The connection must have been opened in replication mode. The initial timeline of a cluster has no history
+    /// file.
+    pub async fn timeline_history(&self, timeline: u32) -> Result<TimelineHistory, Error> {
+        replication::timeline_history(self, timeline).await
+    }
+
+    /// Starts a base backup of the database cluster with the `BASE_BACKUP` replication command.
+    ///
+    /// Each option is a name, which must consist of only ASCII letters and underscores, and an optional value, which
+    /// is quoted as a string literal. Requires PostgreSQL 15 or newer. The connection must have been opened with
+    /// `ReplicationMode::Physical`.
+    pub async fn base_backup(
+        &self,
+        options: &[(&str, Option<&str>)],
+    ) -> Result<BaseBackupStream, Error> {
+        replication::base_backup(self, options).await
+    }
+
     /// Executes a sequence of SQL statements using the simple query protocol, returning the resulting rows.
     ///
     /// Statements should be separated by semicolons. If an error occurs, execution of the sequence will stop at that
@@ -470,6 +1190,19 @@ impl Client {
         simple_query::simple_query(self.inner(), query).await
     }
 
+    /// Like `simple_query`, but groups the rows returned by each statement into a result set.
+    ///
+    /// Each statement in the query produces one result set, containing the columns and rows it returned, if any. This
+    /// makes it easy to consume queries returning several sets of rows with different columns.
+    pub async fn simple_query_result_sets(
+        &self,
+        query: &str,
+    ) -> Result<SimpleResultSetStream, Error> {
+        self.simple_query_raw(query)
+            .await
+            .map(SimpleResultSetStream::new)
+    }
+
     /// Executes a sequence of SQL statements using the simple query protocol.
     ///
     /// Statements should be separated by semicolons. If an error occurs, execution of the sequence will stop at that
@@ -506,7 +1239,7 @@ impl Client {
                 let _ = self
                     .client
                     .inner()
-                    .send(RequestMessages::Single(FrontendMessage::Raw(buf)));
+                    .send_now(RequestMessages::Single(FrontendMessage::Raw(buf)));
             }
         }
 
@@ -527,6 +1260,23 @@ impl Client {
         Ok(Transaction::new(self))
     }
 
+    /// Runs a closure in a transaction, retrying it after serialization failures and deadlocks.
+    ///
+    /// This is equivalent to `client.build_transaction().run_with_retry(policy, f)`. See
+    /// [`TransactionBuilder::run_with_retry`] for details.
+    ///
+    /// [`TransactionBuilder::run_with_retry`]: crate::TransactionBuilder::run_with_retry
+    pub async fn transaction_with_retry<F, T>(
+        &mut self,
+        policy: &RetryPolicy,
+        f: F,
+    ) -> Result<T, Error>
+    where
+        F: for<'a> FnMut(&'a mut Transaction<'_>) -> BoxFuture<'a, Result<T, Error>>,
+    {
+        self.build_transaction().run_with_retry(policy, f).await
+    }
+
     /// Returns a builder for a transaction with custom settings.
     ///
     /// Unlike the `transaction` method, the builder can be used to control the transaction's isolation level and other
@@ -535,15 +1285,141 @@ impl Client {
         TransactionBuilder::new(self)
     }
 
+    /// Commits a transaction previously prepared for two-phase commit with `Transaction::prepare_two_phase`.
+    ///
+    /// The prepared transaction does not need to have been created by this connection, but this method cannot be
+    /// called from within a transaction.
+    pub async fn commit_prepared(&self, gid: &str) -> Result<(), Error> {
+        self.batch_execute(&format!("COMMIT PREPARED {}", escape_literal(gid)))
+            .await
+    }
+
+    /// Rolls back a transaction previously prepared for two-phase commit with `Transaction::prepare_two_phase`.
+    ///
+    /// The prepared transaction does not need to have been created by this connection, but this method cannot be
+    /// called from within a transaction.
+    pub async fn rollback_prepared(&self, gid: &str) -> Result<(), Error> {
+        self.batch_execute(&format!("ROLLBACK PREPARED {}", escape_literal(gid)))
+            .await
+    }
+
+    /// Returns the transactions currently prepared for two-phase commit in the database cluster.
+    ///
+    /// These are the "in-doubt" transactions which still need to be resolved with `commit_prepared` or
+    /// `rollback_prepared`.
+    pub async fn prepared_transactions(&self) -> Result<Vec<PreparedTransaction>, Error> {
+        prepared_transaction::prepared_transactions(self).await
+    }
+
+    /// Acquires a session-level advisory lock, waiting until it becomes available.
+    ///
+    /// The lock is held until the returned guard is dropped or explicitly unlocked. Advisory locks are reentrant, so
+    /// acquiring the same key multiple times requires an equal number of unlocks.
+    pub async fn advisory_lock<K>(&self, key: K) -> Result<AdvisoryLockGuard<'_>, Error>
+    where
+        K: Into<AdvisoryLockKey>,
+    {
+        advisory_lock::lock(self, key.into()).await
+    }
+
+    /// Attempts to acquire a session-level advisory lock without waiting.
+    ///
+    /// Returns `None` if the lock is currently held by another session.
+    pub async fn try_advisory_lock<K>(&self, key: K) -> Result<Option<AdvisoryLockGuard<'_>>, Error>
+    where
+        K: Into<AdvisoryLockKey>,
+    {
+        advisory_lock::try_lock(self, key.into()).await
+    }
+
+    /// Releases a session-level advisory lock.
+    ///
+    /// Returns `false` if the lock was not held by this session. This is only needed for locks which are not tracked
+    /// by an `AdvisoryLockGuard`.
+    pub async fn advisory_unlock<K>(&self, key: K) -> Result<bool, Error>
+    where
+        K: Into<AdvisoryLockKey>,
+    {
+        advisory_lock::unlock(self, key.into()).await
+    }
+
+    /// Returns the current write-ahead log insert position of the server.
+    ///
+    /// Calling this after a write on a primary returns a position which a standby must have replayed before the write
+    /// is visible there. See `wait_for_lsn`.
+    pub async fn current_wal_insert_lsn(&self) -> Result<PgLsn, Error> {
+        lsn::current_wal_insert_lsn(self).await
+    }
+
+    /// Returns the last write-ahead log position replayed by the server.
+    ///
+    /// Returns `None` if the server is not a standby.
+    pub async fn last_wal_replay_lsn(&self) -> Result<Option<PgLsn>, Error> {
+        lsn::last_wal_replay_lsn(self).await
+    }
+
+    /// Waits until the server has replayed the write-ahead log up to the specified position.
+    ///
+    /// This can be used with a position returned by `current_wal_insert_lsn` on a primary to ensure a standby
+    /// observes the writes made before it was taken. The replay position is polled until it reaches `lsn`, and a
+    /// timeout error is returned if that doesn't happen within `timeout`. Servers which are not standbys return
+    /// immediately.
+    pub async fn wait_for_lsn(&self, lsn: PgLsn, timeout: Duration) -> Result<(), Error> {
+        lsn::wait_for_lsn(self, lsn, timeout).await
+    }
+
+    /// Changes the value of a configuration parameter, such as `search_path`, with the `set_config` function.
+    ///
+    /// The name and value are passed to the server as parameters, so they don't need to be quoted. The value is
+    /// interpreted as it would be in a `SET` statement, so a list such as `tenant_a, public` may be used for
+    /// `search_path`; schema names within it which require quoting must be quoted by the caller.
+    ///
+    /// Parameters changed for the session are tracked by the client so that they can later be restored with
+    /// `reset_all_parameters`.
+    pub async fn set_parameter(
+        &self,
+        name: &str,
+        value: &str,
+        scope: ParameterScope,
+    ) -> Result<(), Error> {
+        session_parameter::set_parameter(self, name, value, scope).await
+    }
+
+    /// Resets a configuration parameter to its default value for the session, as with `RESET`.
+    pub async fn reset_parameter(&self, name: &str) -> Result<(), Error> {
+        session_parameter::reset_parameter(self, name).await
+    }
+
+    /// Resets all of the configuration parameters changed for the session by `set_parameter` to their default
+    /// values.
+    ///
+    /// Unlike `RESET ALL`, this leaves parameters changed by other means untouched.
+    pub async fn reset_all_parameters(&self) -> Result<(), Error> {
+        session_parameter::reset_all_parameters(self).await
+    }
+
+    /// Returns the schemas currently searched for unqualified object names, in order.
+    ///
+    /// This is the effective value of the `search_path` parameter, as returned by `current_schemas(false)`: `$user`
+    /// is replaced by the current user's schema, schemas which don't exist are omitted, and the implicitly searched
+    /// `pg_catalog` and temporary schemas are not included. The names are not quoted.
+    pub async fn search_path(&self) -> Result<Vec<String>, Error> {
+        session_parameter::search_path(self).await
+    }
+
+    /// Returns the names of the configuration parameters changed for the session by `set_parameter`, in lower case.
+    pub fn changed_parameters(&self) -> Vec<String> {
+        self.inner().changed_parameters()
+    }
+
     /// Constructs a cancellation token that can later be used to request cancellation of a query running on the
     /// connection associated with this client.
     pub fn cancel_token(&self) -> CancelToken {
         CancelToken {
-            #[cfg(feature = "runtime")]
             socket_config: self.socket_config.clone(),
             ssl_mode: self.ssl_mode,
             process_id: self.process_id,
-            secret_key: self.secret_key,
+            secret_key: self.secret_key.clone(),
         }
     }
 
@@ -551,9 +1427,6 @@ impl Client {
     ///
     /// The server provides no information about whether a cancellation attempt was successful or not. An error will
     /// only be returned if the client was unable to connect to the database.
-    ///
-    /// Requires the `runtime` Cargo feature (enabled by default).
-    #[cfg(feature = "runtime")]
     #[deprecated(since = "0.6.0", note = "use Client::cancel_token() instead")]
     pub async fn cancel_query<T>(&self, tls: T) -> Result<(), Error>
     where
@@ -582,6 +1455,148 @@ impl Client {
         self.inner().clear_type_cache();
     }
 
+    /// Returns the number of statements in the client's statement cache.
+    ///
+    /// The cache is enabled with `Config::statement_cache_capacity`.
+    pub fn statement_cache_len(&self) -> usize {
+        self.inner().statement_cache_len()
+    }
+
+    /// Clears the client's statement cache, closing the cached statements on the server unless they are still in
+    /// use.
+    ///
+    /// Cached statements become invalid if the session's prepared statements are discarded, for example with `DISCARD
+    /// ALL`, and may fail to execute if the tables they reference are altered. This method can be used to prepare them
+    /// again.
+    pub fn clear_statement_cache(&self) {
+        self.inner().clear_statement_cache();
+    }
+
+    /// Controls whether errors returned by queries record the text of the query that produced them.
+    ///
+    /// When enabled, the query is available from `Error::query` and is included in the error's `Display`
+    /// output. Disabled by default.
+    pub fn set_query_in_errors(&self, enabled: bool) {
+        self.inner().set_query_in_errors(enabled);
+    }
+
+    /// Sets a function used to render query parameters into errors.
+    ///
+    /// If the query is recorded in errors (see `set_query_in_errors`), the renderer is called on each parameter
+    /// and the results are available from `Error::params`. It can be used to redact sensitive values. Parameters are
+    /// not recorded by default.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn f(client: &tokio_postgres::Client) {
+    /// use tokio_postgres::types::Type;
+    ///
+    /// client.set_query_in_errors(true);
+    /// client.set_error_param_renderer(|ty, value| {
+    ///     if *ty == Type::TEXT {
+    ///         "<redacted>".to_string()
+    ///     } else {
+    ///         format!("{:?}", value)
+    ///     }
+    /// });
+    /// # }
+    /// ```
+    pub fn set_error_param_renderer<F>(&self, renderer: F)
+    where
+        F: Fn(&Type, &dyn ToSql) -> String + Sync + Send + 'static,
+    {
+        self.inner()
+            .set_error_param_renderer(Some(Arc::new(renderer)));
+    }
+
+    /// Stops recording query parameters in errors.
+    pub fn clear_error_param_renderer(&self) {
+        self.inner().set_error_param_renderer(None);
+    }
+
+    /// Sets a logger notified of each statement executed by the client.
+    ///
+    /// The logger receives the text of the statement, the number of parameters, the execution time, the number of rows
+    /// affected and any error, and can be used to implement slow query logging or auditing. It is called for
+    /// statements executed through the `query`, `execute`, `simple_query` and `batch_execute` families of methods.
+    /// Statements whose rows are not completely consumed are not logged.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn f(client: &tokio_postgres::Client) {
+    /// use std::time::Duration;
+    ///
+    /// client.set_query_logger(|event: &tokio_postgres::QueryEvent<'_>| {
+    ///     if event.duration() > Duration::from_secs(1) {
+    ///         println!("slow query ({:?}): {}", event.duration(), event.query());
+    ///     }
+    /// });
+    /// # }
+    /// ```
+    pub fn set_query_logger<L>(&self, logger: L)
+    where
+        L: QueryLogger + 'static,
+    {
+        self.inner().set_query_logger(Some(Arc::new(logger)));
+    }
+
+    /// Removes the logger set by `set_query_logger`.
+    pub fn clear_query_logger(&self) {
+        self.inner().set_query_logger(None);
+    }
+
+    /// Sets a function used to render query parameters for the query logger.
+    ///
+    /// The renderer is called on each parameter of the statements passed to the logger, and the results are available
+    /// from `QueryEvent::params`. As with `set_error_param_renderer`, it can be used to redact sensitive values, and
+    /// `render_param` can be used to render the others as SQL literals. Parameters are not rendered by default.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn f(client: &tokio_postgres::Client) {
+    /// use tokio_postgres::render_param;
+    /// use tokio_postgres::types::Type;
+    ///
+    /// client.set_log_param_renderer(|ty, value| {
+    ///     if *ty == Type::TEXT {
+    ///         "<redacted>".to_string()
+    ///     } else {
+    ///         render_param(ty, value)
+    ///     }
+    /// });
+    /// # }
+    /// ```
+    pub fn set_log_param_renderer<F>(&self, renderer: F)
+    where
+        F: Fn(&Type, &dyn ToSql) -> String + Sync + Send + 'static,
+    {
+        self.inner()
+            .set_log_param_renderer(Some(Arc::new(renderer)));
+    }
+
+    /// Stops rendering query parameters for the query logger.
+    pub fn clear_log_param_renderer(&self) {
+        self.inner().set_log_param_renderer(None);
+    }
+
+    /// Sets the cache consulted by `query_cached`.
+    ///
+    /// A cache shared between clients can be passed as an `Arc`.
+    pub fn set_query_cache<C>(&self, cache: C)
+    where
+        C: QueryCache + 'static,
+    {
+        self.inner().set_query_cache(Some(Arc::new(cache)));
+    }
+
+    /// Removes the cache set by `set_query_cache`.
+    pub fn clear_query_cache(&self) {
+        self.inner().set_query_cache(None);
+    }
+
     /// Determines if the connection to the server has already closed.
     ///
     /// In that case, all future queries will fail.
@@ -589,6 +1604,39 @@ impl Client {
         self.inner.sender.is_closed()
     }
 
+    /// Returns the transaction status of the connection when the server last finished processing a request.
+    ///
+    /// Responses to requests still in flight are not reflected, so this is most useful when the client is idle, for
+    /// example to detect a connection left in a failed transaction before returning it to a pool:
+    ///
+    /// ```no_run
+    /// use tokio_postgres::TransactionStatus;
+    ///
+    /// # async fn f(client: &tokio_postgres::Client) -> Result<(), tokio_postgres::Error> {
+    /// if client.transaction_status() != TransactionStatus::Idle {
+    ///     client.batch_execute("ROLLBACK").await?;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn transaction_status(&self) -> TransactionStatus {
+        match self.inner.transaction_status().load(Ordering::Acquire) {
+            b'T' => TransactionStatus::InTransaction,
+            b'E' => TransactionStatus::InError,
+            _ => TransactionStatus::Idle,
+        }
+    }
+
+    /// Returns the protocol version negotiated with the server.
+    pub fn protocol_version(&self) -> ProtocolVersion {
+        self.protocol_version
+    }
+
+    /// Returns a snapshot of the messages received from the server over the connection, by type.
+    pub fn message_stats(&self) -> MessageStats {
+        self.message_counter.snapshot()
+    }
+
     #[doc(hidden)]
     pub fn __private_api_close(&mut self) {
         self.inner.sender.close_channel()
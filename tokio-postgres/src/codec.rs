@@ -1,12 +1,30 @@
-use bytes::{Buf, Bytes, BytesMut};
+use crate::message_stats::MessageCounter;
+use crate::metrics::Metrics;
+use crate::protocol_trace;
+use crate::Config;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 use fallible_iterator::FallibleIterator;
+use futures::ready;
+use postgres_core::{Frame, Framer};
 use postgres_protocol::message::backend;
 use postgres_protocol::message::frontend::CopyData;
-use std::io;
+use std::collections::VecDeque;
+use std::io::{self, IoSlice};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::AsyncWrite;
 use tokio_util::codec::{Decoder, Encoder};
 
+// messages at least this large are queued for writing as-is rather than being copied into the write buffer
+const COPY_THRESHOLD: usize = 4 * 1024;
+// the maximum number of buffers passed to a single vectored write
+const MAX_IO_SLICES: usize = 64;
+
 pub enum FrontendMessage {
     Raw(Bytes),
+    /// A piece of a message whose parts are sent separately, such as a Bind with streamed parameter values.
+    Chunk(Bytes),
     CopyData(CopyData<Box<dyn Buf + Send>>),
 }
 
@@ -24,6 +42,20 @@ impl BackendMessages {
     pub fn empty() -> BackendMessages {
         BackendMessages(BytesMut::new())
     }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Appends messages received after these ones.
+    pub fn extend(&mut self, messages: BackendMessages) {
+        self.0.unsplit(messages.0);
+    }
+
+    /// Returns the transaction status of the `ReadyForQuery` message ending the messages of a completed request.
+    pub fn transaction_status(&self) -> Option<u8> {
+        self.0.last().copied()
+    }
 }
 
 impl FallibleIterator for BackendMessages {
@@ -35,17 +67,101 @@ impl FallibleIterator for BackendMessages {
     }
 }
 
-pub struct PostgresCodec;
+pub struct PostgresCodec {
+    metrics: Option<Arc<dyn Metrics>>,
+    message_counter: MessageCounter,
+    trace_protocol: bool,
+    framer: Framer,
+}
+
+impl PostgresCodec {
+    pub fn new(config: &Config) -> PostgresCodec {
+        let mut framer = Framer::new();
+        framer.max_message_length(config.max_message_length);
+        PostgresCodec {
+            metrics: config.get_metrics().cloned(),
+            message_counter: MessageCounter::default(),
+            trace_protocol: config.trace_protocol,
+            framer,
+        }
+    }
+
+    pub fn message_counter(&self) -> &MessageCounter {
+        &self.message_counter
+    }
+
+    /// Like `Encoder::encode`, but avoids copying the contents of large messages.
+    pub fn encode_chunked(&self, item: FrontendMessage, dst: &mut WriteBuffer) {
+        match item {
+            FrontendMessage::Raw(buf) => {
+                self.written(&buf);
+                dst.push(buf);
+            }
+            FrontendMessage::Chunk(buf) => {
+                if let Some(metrics) = &self.metrics {
+                    metrics.bytes_written(buf.len());
+                }
+                dst.push(buf);
+            }
+            FrontendMessage::CopyData(data) => {
+                let start = dst.staging.len();
+                let mut body = data.write_header(&mut dst.staging);
+                let len = dst.staging.len() - start + body.remaining();
+                if let Some(metrics) = &self.metrics {
+                    metrics.bytes_written(len);
+                }
+                if self.trace_protocol {
+                    protocol_trace::frontend_header(b'd', len - 1);
+                }
+                dst.len += len;
+                if body.remaining() >= COPY_THRESHOLD {
+                    let body = body.copy_to_bytes(body.remaining());
+                    dst.push_chunk(body);
+                } else {
+                    dst.staging.put(body);
+                }
+            }
+        }
+    }
+
+    fn written(&self, buf: &[u8]) {
+        if let Some(metrics) = &self.metrics {
+            metrics.bytes_written(buf.len());
+        }
+        if self.trace_protocol {
+            protocol_trace::frontend(buf);
+        }
+    }
+
+    fn read(&self, buf: &[u8]) {
+        if let Some(metrics) = &self.metrics {
+            metrics.bytes_read(buf.len());
+        }
+        self.message_counter.count(buf);
+        if self.trace_protocol {
+            protocol_trace::backend(buf);
+        }
+    }
+}
 
 impl Encoder<FrontendMessage> for PostgresCodec {
     type Error = io::Error;
 
     fn encode(&mut self, item: FrontendMessage, dst: &mut BytesMut) -> io::Result<()> {
+        let start = dst.len();
         match item {
             FrontendMessage::Raw(buf) => dst.extend_from_slice(&buf),
+            FrontendMessage::Chunk(buf) => {
+                dst.extend_from_slice(&buf);
+                if let Some(metrics) = &self.metrics {
+                    metrics.bytes_written(buf.len());
+                }
+                return Ok(());
+            }
             FrontendMessage::CopyData(data) => data.write(dst),
         }
 
+        self.written(&dst[start..]);
         Ok(())
     }
 }
@@ -55,44 +171,109 @@ impl Decoder for PostgresCodec {
     type Error = io::Error;
 
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<BackendMessage>, io::Error> {
-        let mut idx = 0;
-        let mut request_complete = false;
-
-        while let Some(header) = backend::Header::parse(&src[idx..])? {
-            let len = header.len() as usize + 1;
-            if src[idx..].len() < len {
-                break;
+        match self.framer.decode(src)? {
+            Some(Frame::Response {
+                messages,
+                request_complete,
+            }) => {
+                self.read(&messages);
+                Ok(Some(BackendMessage::Normal {
+                    messages: BackendMessages(messages),
+                    request_complete,
+                }))
             }
-
-            match header.tag() {
-                backend::NOTICE_RESPONSE_TAG
-                | backend::NOTIFICATION_RESPONSE_TAG
-                | backend::PARAMETER_STATUS_TAG => {
-                    if idx == 0 {
-                        let message = backend::Message::parse(src)?.unwrap();
-                        return Ok(Some(BackendMessage::Async(message)));
-                    } else {
-                        break;
-                    }
-                }
-                _ => {}
+            Some(Frame::Async(mut buf)) => {
+                self.read(&buf);
+                let message = backend::Message::parse(&mut buf)?.unwrap();
+                Ok(Some(BackendMessage::Async(message)))
             }
+            None => Ok(None),
+        }
+    }
+}
 
-            idx += len;
+/// A buffer of frontend messages waiting to be written to the server.
+///
+/// Small messages are coalesced into a single buffer, while large ones are queued without being copied. The queued
+/// chunks are written with vectored writes where the stream supports them.
+pub struct WriteBuffer {
+    chunks: VecDeque<Bytes>,
+    staging: BytesMut,
+    len: usize,
+}
 
-            if header.tag() == backend::READY_FOR_QUERY_TAG {
-                request_complete = true;
-                break;
-            }
+impl WriteBuffer {
+    pub fn new() -> WriteBuffer {
+        WriteBuffer {
+            chunks: VecDeque::new(),
+            staging: BytesMut::new(),
+            len: 0,
         }
+    }
+
+    /// Returns the number of bytes waiting to be written.
+    pub fn len(&self) -> usize {
+        self.len
+    }
 
-        if idx == 0 {
-            Ok(None)
+    fn push(&mut self, buf: Bytes) {
+        self.len += buf.len();
+        if buf.len() >= COPY_THRESHOLD {
+            self.push_chunk(buf);
         } else {
-            Ok(Some(BackendMessage::Normal {
-                messages: BackendMessages(src.split_to(idx)),
-                request_complete,
-            }))
+            self.staging.extend_from_slice(&buf);
+        }
+    }
+
+    fn push_chunk(&mut self, buf: Bytes) {
+        self.stage();
+        self.chunks.push_back(buf);
+    }
+
+    fn stage(&mut self) {
+        if !self.staging.is_empty() {
+            self.chunks.push_back(self.staging.split().freeze());
+        }
+    }
+
+    /// Writes all buffered data to the stream, without flushing it.
+    pub fn poll_write<W>(&mut self, cx: &mut Context<'_>, stream: &mut W) -> Poll<io::Result<()>>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        self.stage();
+
+        while let Some(chunk) = self.chunks.front() {
+            let n = if stream.is_write_vectored() && self.chunks.len() > 1 {
+                let mut slices = [IoSlice::new(&[]); MAX_IO_SLICES];
+                let mut count = 0;
+                for (slice, chunk) in slices.iter_mut().zip(&self.chunks) {
+                    *slice = IoSlice::new(chunk);
+                    count += 1;
+                }
+                ready!(Pin::new(&mut *stream).poll_write_vectored(cx, &slices[..count]))?
+            } else {
+                ready!(Pin::new(&mut *stream).poll_write(cx, chunk))?
+            };
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.advance(n);
+        }
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn advance(&mut self, mut n: usize) {
+        self.len -= n;
+        while n > 0 {
+            let chunk = self.chunks.front_mut().unwrap();
+            if n < chunk.len() {
+                chunk.advance(n);
+                return;
+            }
+            n -= chunk.len();
+            self.chunks.pop_front();
         }
     }
 }
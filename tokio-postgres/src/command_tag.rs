@@ -0,0 +1,108 @@
+use crate::types::Oid;
+use crate::Error;
+use postgres_protocol::message::backend::CommandCompleteBody;
+use std::fmt;
+
+/// The kind of command reported by a `CommandTag`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CommandKind {
+    /// An `INSERT` statement.
+    Insert,
+    /// An `UPDATE` statement.
+    Update,
+    /// A `DELETE` statement.
+    Delete,
+    /// A `MERGE` statement.
+    Merge,
+    /// A `SELECT` or `CREATE TABLE AS` statement.
+    Select,
+    /// A `COPY` statement.
+    Copy,
+    /// A `FETCH` statement.
+    Fetch,
+    /// A `MOVE` statement.
+    Move,
+    /// Any other command, such as `CREATE TABLE` or `BEGIN`, which doesn't report a row count.
+    Other,
+}
+
+/// The tag sent by the server on completion of a statement, identifying the command and the number of rows it
+/// processed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandTag {
+    tag: String,
+    kind: CommandKind,
+    rows: u64,
+    oid: Option<Oid>,
+}
+
+impl CommandTag {
+    pub(crate) fn from_body(body: &CommandCompleteBody) -> Result<CommandTag, Error> {
+        body.tag().map(CommandTag::parse).map_err(Error::parse)
+    }
+
+    /// Parses a command tag, such as `INSERT 0 1` or `UPDATE 2`.
+    pub fn parse(tag: &str) -> CommandTag {
+        let mut words = tag.split(' ');
+        let command = words.next().unwrap_or("");
+        let kind = match command {
+            "INSERT" => CommandKind::Insert,
+            "UPDATE" => CommandKind::Update,
+            "DELETE" => CommandKind::Delete,
+            "MERGE" => CommandKind::Merge,
+            "SELECT" => CommandKind::Select,
+            "COPY" => CommandKind::Copy,
+            "FETCH" => CommandKind::Fetch,
+            "MOVE" => CommandKind::Move,
+            _ => CommandKind::Other,
+        };
+        let oid = match kind {
+            CommandKind::Insert => words.next().and_then(|oid| oid.parse().ok()),
+            _ => None,
+        };
+        // the row count is always the last word of the tag, when there is one
+        let rows = tag
+            .rsplit(' ')
+            .next()
+            .and_then(|rows| rows.parse().ok())
+            .unwrap_or(0);
+
+        CommandTag {
+            tag: tag.to_string(),
+            kind,
+            rows,
+            oid,
+        }
+    }
+
+    /// Returns the raw text of the tag.
+    pub fn as_str(&self) -> &str {
+        &self.tag
+    }
+
+    /// Returns the kind of command.
+    pub fn kind(&self) -> CommandKind {
+        self.kind
+    }
+
+    /// Returns the number of rows inserted, updated, deleted, returned or otherwise processed by the command.
+    ///
+    /// Commands which don't report a row count return 0.
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+
+    /// Returns the OID reported by an `INSERT` command.
+    ///
+    /// This has been 0 since PostgreSQL 12, which removed tables with OIDs.
+    pub fn oid(&self) -> Option<Oid> {
+        self.oid
+    }
+}
+
+impl fmt::Display for CommandTag {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str(&self.tag)
+    }
+}
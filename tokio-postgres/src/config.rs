@@ -1,14 +1,22 @@
 //! Connection configuration.
 
-#[cfg(feature = "runtime")]
+use crate::cancel_token::{self, ConfigCancelFn};
+#[cfg(any(
+    feature = "runtime",
+    feature = "runtime-async-std",
+    feature = "runtime-smol"
+))]
 use crate::connect::connect;
 use crate::connect_raw::connect_raw;
-#[cfg(feature = "runtime")]
+use crate::connection_listener::{ConfigConnectionListener, ConnectionListener};
+use crate::escape::quote_ident;
+use crate::metrics::{ConfigMetrics, Metrics};
+use crate::runtime::{ConfigResolver, ConfigRuntime, Resolver, Runtime};
 use crate::tls::MakeTlsConnect;
 use crate::tls::TlsConnect;
-#[cfg(feature = "runtime")]
+use crate::watchdog::Watchdog;
 use crate::Socket;
-use crate::{Client, Connection, Error};
+use crate::{Client, Connection, Error, IsolationLevel};
 use std::borrow::Cow;
 #[cfg(unix)]
 use std::ffi::OsStr;
@@ -18,6 +26,7 @@ use std::os::unix::ffi::OsStrExt;
 use std::path::{Path, PathBuf};
 use std::str;
 use std::str::FromStr;
+use std::sync::Arc;
 use std::time::Duration;
 use std::{error, fmt, iter, mem};
 use tokio::io::{AsyncRead, AsyncWrite};
@@ -44,6 +53,18 @@ pub enum SslMode {
     Require,
 }
 
+/// GSSAPI encryption configuration.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum GssEncMode {
+    /// Do not use GSSAPI encryption.
+    Disable,
+    /// Attempt to use GSSAPI encryption but allow sessions without.
+    Prefer,
+    /// Require the use of GSSAPI encryption.
+    Require,
+}
+
 /// Channel binding configuration.
 #[derive(Debug, Copy, Clone, PartialEq)]
 #[non_exhaustive]
@@ -56,6 +77,39 @@ pub enum ChannelBinding {
     Require,
 }
 
+/// The strategy used to connect to a host which resolves to multiple addresses.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum AddressStrategy {
+    /// Try each address in turn, waiting for each attempt to succeed, fail, or time out before starting the next.
+    Sequential,
+    /// Race the addresses as described in RFC 8305 ("Happy Eyeballs").
+    ///
+    /// IPv6 and IPv4 addresses are interleaved, and a new attempt is started whenever one fails or the previous one
+    /// has been pending for the configured delay. The first connection to succeed is used.
+    HappyEyeballs,
+}
+
+/// Frontend/backend protocol version configuration.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[non_exhaustive]
+pub enum ProtocolVersion {
+    /// Version 3.0, supported by all servers since Postgres 7.4.
+    V3_0,
+    /// Version 3.2, introduced in Postgres 18, which allows longer secret keys for query cancellation.
+    V3_2,
+}
+
+/// Replication mode configuration.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum ReplicationMode {
+    /// Physical replication.
+    Physical,
+    /// Logical replication.
+    Logical,
+}
+
 /// A host specification.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Host {
@@ -84,8 +138,22 @@ pub enum Host {
 /// * `dbname` - The name of the database to connect to. Defaults to the username.
 /// * `options` - Command line options used to configure the server.
 /// * `application_name` - Sets the `application_name` parameter on the server.
+/// * `search_path` - A comma-separated list of schemas to set as the `search_path` parameter on the server. Each schema
+///   name is quoted as necessary, so names must not be quoted.
+/// * `role` - A role to assume with `SET ROLE` as soon as the connection is established, before it is returned. The
+///   connection fails if the role can't be assumed.
+/// * `default_transaction_isolation` - The isolation level of transactions which don't specify one: `read uncommitted`,
+///   `read committed`, `repeatable read`, or `serializable`. Set with `SET SESSION CHARACTERISTICS` as soon as the
+///   connection is established.
+/// * `default_transaction_read_only` - Whether transactions which don't specify an access mode are read-only. Set
+///   along with `default_transaction_isolation`.
+/// * `client_encoding` - The character encoding requested for text exchanged with the server. The connection fails if
+///   the server reports a different one. Defaults to `UTF8`.
 /// * `sslmode` - Controls usage of TLS. If set to `disable`, TLS will not be used. If set to `prefer`, TLS will be used
 ///     if available, but not used otherwise. If set to `require`, TLS will be forced to be used. Defaults to `prefer`.
+/// * `gssencmode` - Controls usage of GSSAPI encryption. GSSAPI encryption is not supported by this client, so
+///   `disable` and `prefer` both connect without it, and `require` is rejected before connecting. Defaults to
+///   `prefer`.
 /// * `host` - The host to connect to. On Unix platforms, if the host starts with a `/` character it is treated as the
 ///     path to the directory containing Unix domain sockets. Otherwise, it is treated as a hostname. Multiple hosts
 ///     can be specified, separated by commas. Each host will be tried in turn when connecting. Required if connecting
@@ -105,6 +173,11 @@ pub enum Host {
 /// * `channel_binding` - Controls usage of channel binding in the authentication process. If set to `disable`, channel
 ///     binding will not be used. If set to `prefer`, channel binding will be used if available, but not used otherwise.
 ///     If set to `require`, the authentication process will fail if channel binding is not used. Defaults to `prefer`.
+/// * `replication` - Enables replication mode: `true` for physical and `database` for logical. Defaults to `false`.
+/// * `min_protocol_version` - The oldest protocol version the server may negotiate down to: `3.0` or `3.2`. Defaults
+///   to `3.0`.
+/// * `max_protocol_version` - The protocol version requested from the server: `3.0`, `3.2`, or `latest` for the newest
+///   version supported by the client. Defaults to `3.0`.
 ///
 /// ## Examples
 ///
@@ -150,15 +223,41 @@ pub struct Config {
     pub(crate) password: Option<Vec<u8>>,
     pub(crate) dbname: Option<String>,
     pub(crate) options: Option<String>,
+    pub(crate) server_settings: Vec<(String, String)>,
+    pub(crate) search_path: Option<Vec<String>>,
     pub(crate) application_name: Option<String>,
+    pub(crate) role: Option<String>,
+    pub(crate) default_transaction_isolation: Option<IsolationLevel>,
+    pub(crate) default_transaction_read_only: Option<bool>,
+    pub(crate) client_encoding: String,
     pub(crate) ssl_mode: SslMode,
+    pub(crate) gss_enc_mode: GssEncMode,
     pub(crate) host: Vec<Host>,
     pub(crate) port: Vec<u16>,
     pub(crate) connect_timeout: Option<Duration>,
     pub(crate) keepalives: bool,
     pub(crate) keepalives_idle: Duration,
+    pub(crate) address_strategy: AddressStrategy,
+    pub(crate) happy_eyeballs_delay: Duration,
+    pub(crate) resolver: Option<ConfigResolver>,
     pub(crate) target_session_attrs: TargetSessionAttrs,
     pub(crate) channel_binding: ChannelBinding,
+    pub(crate) replication_mode: Option<ReplicationMode>,
+    pub(crate) metrics: Option<ConfigMetrics>,
+    pub(crate) connection_listener: Option<ConfigConnectionListener>,
+    pub(crate) watchdog: Option<Watchdog>,
+    pub(crate) cancel_on_drop: Option<ConfigCancelFn>,
+    pub(crate) runtime: Option<ConfigRuntime>,
+    pub(crate) trace_protocol: bool,
+    pub(crate) request_queue_capacity: Option<usize>,
+    pub(crate) read_buffer_size: Option<usize>,
+    pub(crate) max_message_length: Option<usize>,
+    pub(crate) named_statements: bool,
+    pub(crate) statement_name_prefix: String,
+    pub(crate) statement_cache_capacity: usize,
+    pub(crate) min_protocol_version: ProtocolVersion,
+    pub(crate) max_protocol_version: ProtocolVersion,
+    pub(crate) protocol_extensions: Vec<(String, String)>,
 }
 
 impl Default for Config {
@@ -175,15 +274,41 @@ impl Config {
             password: None,
             dbname: None,
             options: None,
+            server_settings: vec![],
+            search_path: None,
             application_name: None,
+            role: None,
+            default_transaction_isolation: None,
+            default_transaction_read_only: None,
+            client_encoding: "UTF8".to_string(),
             ssl_mode: SslMode::Prefer,
+            gss_enc_mode: GssEncMode::Prefer,
             host: vec![],
             port: vec![],
             connect_timeout: None,
             keepalives: true,
             keepalives_idle: Duration::from_secs(2 * 60 * 60),
+            address_strategy: AddressStrategy::Sequential,
+            happy_eyeballs_delay: Duration::from_millis(250),
+            resolver: None,
             target_session_attrs: TargetSessionAttrs::Any,
             channel_binding: ChannelBinding::Prefer,
+            replication_mode: None,
+            metrics: None,
+            connection_listener: None,
+            watchdog: None,
+            cancel_on_drop: None,
+            runtime: None,
+            trace_protocol: false,
+            request_queue_capacity: None,
+            read_buffer_size: None,
+            max_message_length: None,
+            named_statements: true,
+            statement_name_prefix: "s".to_string(),
+            statement_cache_capacity: 0,
+            min_protocol_version: ProtocolVersion::V3_0,
+            max_protocol_version: ProtocolVersion::V3_0,
+            protocol_extensions: vec![],
         }
     }
 
@@ -242,6 +367,70 @@ impl Config {
         self.options.as_deref()
     }
 
+    /// Adds a runtime parameter to set when the connection starts, as if with `SET`.
+    ///
+    /// The parameter is passed to the server as a `-c name=value` command line option, with spaces and backslashes
+    /// escaped as required by the server, following any options set with the `options` method.
+    pub fn server_setting(&mut self, name: &str, value: &str) -> &mut Config {
+        self.server_settings
+            .push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Gets the runtime parameters added with the `server_setting` method, as name/value pairs.
+    pub fn get_server_settings(&self) -> &[(String, String)] {
+        &self.server_settings
+    }
+
+    /// Sets the schemas searched for unqualified object names, in order.
+    ///
+    /// The schema names are quoted as necessary and applied as the `search_path` parameter when the connection starts,
+    /// following the settings added with the `server_setting` method. Unlike a `SET search_path` run after connecting,
+    /// this can't be forgotten by a connection pool or undone by `RESET ALL` or `DISCARD ALL`, which restore the
+    /// value set at startup. Pass `$user` to include the schema with the same name as the current user, as in the
+    /// server's default of `"$user", public`.
+    pub fn search_path<I, S>(&mut self, schemas: I) -> &mut Config
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.search_path = Some(schemas.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Gets the schemas searched for unqualified object names, if they have been set with the `search_path` method.
+    pub fn get_search_path(&self) -> Option<&[String]> {
+        self.search_path.as_deref()
+    }
+
+    // Builds the value of the `options` startup parameter from the raw options and the server settings.
+    pub(crate) fn startup_options(&self) -> Option<String> {
+        let mut options = self.options.clone();
+        let search_path = self.search_path.as_ref().map(|schemas| {
+            let schemas = schemas
+                .iter()
+                .map(|s| quote_ident(s))
+                .collect::<Vec<_>>()
+                .join(", ");
+            ("search_path".to_string(), schemas)
+        });
+        for (name, value) in self.server_settings.iter().chain(&search_path) {
+            let options = options.get_or_insert_with(String::new);
+            if !options.is_empty() {
+                options.push(' ');
+            }
+            options.push_str("-c ");
+            for c in name.chars().chain(Some('=')).chain(value.chars()) {
+                // the server splits the options on whitespace unless it's escaped with a backslash
+                if c == '\\' || c.is_ascii_whitespace() {
+                    options.push('\\');
+                }
+                options.push(c);
+            }
+        }
+        options
+    }
+
     /// Sets the value of the `application_name` runtime parameter.
     pub fn application_name(&mut self, application_name: &str) -> &mut Config {
         self.application_name = Some(application_name.to_string());
@@ -254,6 +443,90 @@ impl Config {
         self.application_name.as_deref()
     }
 
+    /// Sets a role to assume with `SET ROLE` once the connection is established.
+    ///
+    /// This is useful when logging in as a generic user which must then assume a role to do any work. The role is set
+    /// before the connection is returned, at the cost of one extra round trip to the server.
+    pub fn role(&mut self, role: &str) -> &mut Config {
+        self.role = Some(role.to_string());
+        self
+    }
+
+    /// Gets the role to assume once the connection is established, if one has been set with the `role` method.
+    pub fn get_role(&self) -> Option<&str> {
+        self.role.as_deref()
+    }
+
+    /// Sets the isolation level of transactions started on the connection without an explicit isolation level.
+    ///
+    /// This is applied with `SET SESSION CHARACTERISTICS` once the connection is established, along with the access
+    /// mode set with the `default_transaction_read_only` method, before the connection is returned.
+    pub fn default_transaction_isolation(
+        &mut self,
+        isolation_level: IsolationLevel,
+    ) -> &mut Config {
+        self.default_transaction_isolation = Some(isolation_level);
+        self
+    }
+
+    /// Gets the default transaction isolation level, if one has been set with the `default_transaction_isolation`
+    /// method.
+    pub fn get_default_transaction_isolation(&self) -> Option<IsolationLevel> {
+        self.default_transaction_isolation
+    }
+
+    /// Sets whether transactions started on the connection without an explicit access mode are read-only.
+    ///
+    /// Like the default isolation level, this is applied with `SET SESSION CHARACTERISTICS` once the connection is
+    /// established. Note that a read-only default doesn't prevent transactions from explicitly requesting write access.
+    pub fn default_transaction_read_only(&mut self, read_only: bool) -> &mut Config {
+        self.default_transaction_read_only = Some(read_only);
+        self
+    }
+
+    /// Gets the default transaction access mode, if one has been set with the `default_transaction_read_only` method.
+    pub fn get_default_transaction_read_only(&self) -> Option<bool> {
+        self.default_transaction_read_only
+    }
+
+    // Builds the statement setting the default transaction characteristics, if any have been configured.
+    pub(crate) fn session_characteristics_query(&self) -> Option<String> {
+        let mut modes = vec![];
+        if let Some(isolation_level) = self.default_transaction_isolation {
+            modes.push(format!("ISOLATION LEVEL {}", isolation_level.to_sql()));
+        }
+        match self.default_transaction_read_only {
+            Some(true) => modes.push("READ ONLY".to_string()),
+            Some(false) => modes.push("READ WRITE".to_string()),
+            None => {}
+        }
+        if modes.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "SET SESSION CHARACTERISTICS AS TRANSACTION {}",
+            modes.join(", ")
+        ))
+    }
+
+    /// Sets the character encoding requested for text exchanged with the server.
+    ///
+    /// The connection fails if the server reports a different encoding, either during the handshake or later on when
+    /// the `client_encoding` setting is changed, since text would otherwise be silently mis-decoded. This catches
+    /// connection poolers and role or database settings which force another encoding.
+    ///
+    /// Text is always decoded as UTF-8, so other encodings are only useful in special cases, such as `SQL_ASCII`,
+    /// which passes bytes through from a database without conversion. Defaults to `UTF8`.
+    pub fn client_encoding(&mut self, client_encoding: &str) -> &mut Config {
+        self.client_encoding = client_encoding.to_string();
+        self
+    }
+
+    /// Gets the character encoding requested for text exchanged with the server.
+    pub fn get_client_encoding(&self) -> &str {
+        &self.client_encoding
+    }
+
     /// Sets the SSL configuration.
     ///
     /// Defaults to `prefer`.
@@ -267,6 +540,22 @@ impl Config {
         self.ssl_mode
     }
 
+    /// Sets the GSSAPI encryption configuration.
+    ///
+    /// GSSAPI encryption is not supported by this client, so connections are never encrypted with it. This is accepted
+    /// for compatibility with libpq connection strings: as with a libpq built without GSSAPI support, `prefer` behaves
+    /// like `disable`, and `require` makes `connect` fail before opening a connection rather than silently connecting
+    /// without it. Defaults to `prefer`.
+    pub fn gss_enc_mode(&mut self, gss_enc_mode: GssEncMode) -> &mut Config {
+        self.gss_enc_mode = gss_enc_mode;
+        self
+    }
+
+    /// Gets the GSSAPI encryption configuration.
+    pub fn get_gss_enc_mode(&self) -> GssEncMode {
+        self.gss_enc_mode
+    }
+
     /// Adds a host to the configuration.
     ///
     /// Multiple hosts can be specified by calling this method multiple times, and each will be tried in order. On Unix
@@ -357,6 +646,45 @@ impl Config {
         self.keepalives_idle
     }
 
+    /// Sets the strategy used to connect to hosts which resolve to multiple addresses.
+    ///
+    /// With the default `Sequential` strategy, an unreachable address stalls the connection until it times out, which
+    /// can take minutes without a `connect_timeout` on networks where IPv6 is broken. Defaults to `Sequential`.
+    pub fn address_strategy(&mut self, address_strategy: AddressStrategy) -> &mut Config {
+        self.address_strategy = address_strategy;
+        self
+    }
+
+    /// Gets the strategy used to connect to hosts which resolve to multiple addresses.
+    pub fn get_address_strategy(&self) -> AddressStrategy {
+        self.address_strategy
+    }
+
+    /// Sets how long a connection attempt may be pending before the next address is tried alongside it.
+    ///
+    /// This only applies to the `HappyEyeballs` address strategy. A delay of zero connects to all addresses at once.
+    /// Defaults to 250 milliseconds, as recommended by RFC 8305.
+    pub fn happy_eyeballs_delay(&mut self, delay: Duration) -> &mut Config {
+        self.happy_eyeballs_delay = delay;
+        self
+    }
+
+    /// Gets how long a connection attempt may be pending before the next address is tried alongside it.
+    pub fn get_happy_eyeballs_delay(&self) -> Duration {
+        self.happy_eyeballs_delay
+    }
+
+    /// Sets the resolver used to look up the addresses of hosts, overriding the runtime's.
+    pub fn resolver(&mut self, resolver: Arc<dyn Resolver>) -> &mut Config {
+        self.resolver = Some(ConfigResolver(resolver));
+        self
+    }
+
+    /// Gets the resolver, if one has been configured with the `resolver` method.
+    pub fn get_resolver(&self) -> Option<&Arc<dyn Resolver>> {
+        self.resolver.as_ref().map(|r| &r.0)
+    }
+
     /// Sets the requirements of the session.
     ///
     /// This can be used to connect to the primary server in a clustered database rather than one of the read-only
@@ -387,6 +715,251 @@ impl Config {
         self.channel_binding
     }
 
+    /// Sets the oldest protocol version the server may negotiate down to.
+    ///
+    /// The connection fails if the server only supports older versions. If this is newer than the maximum, it is
+    /// requested instead. Defaults to `V3_0`.
+    pub fn min_protocol_version(&mut self, version: ProtocolVersion) -> &mut Config {
+        self.min_protocol_version = version;
+        self
+    }
+
+    /// Gets the oldest protocol version the server may negotiate down to.
+    pub fn get_min_protocol_version(&self) -> ProtocolVersion {
+        self.min_protocol_version
+    }
+
+    /// Sets the protocol version requested from the server.
+    ///
+    /// Servers which only support an older minor version of the protocol negotiate the newest one they do support.
+    /// Defaults to `V3_0`.
+    pub fn max_protocol_version(&mut self, version: ProtocolVersion) -> &mut Config {
+        self.max_protocol_version = version;
+        self
+    }
+
+    /// Gets the protocol version requested from the server.
+    pub fn get_max_protocol_version(&self) -> ProtocolVersion {
+        self.max_protocol_version
+    }
+
+    /// Adds a protocol extension option to the startup message.
+    ///
+    /// The option is sent as `_pq_.<name>`, the namespace reserved for protocol extensions. Servers which don't
+    /// recognize an extension ignore it rather than rejecting the connection.
+    pub fn protocol_extension(&mut self, name: &str, value: &str) -> &mut Config {
+        self.protocol_extensions
+            .push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Gets the protocol extension options added with the `protocol_extension` method, as name/value pairs.
+    pub fn get_protocol_extensions(&self) -> &[(String, String)] {
+        &self.protocol_extensions
+    }
+
+    /// Sets the replication mode of the connection.
+    ///
+    /// Connections in replication mode accept the commands of the streaming replication protocol instead of (or, for
+    /// logical replication, in addition to) regular SQL. Defaults to no replication.
+    pub fn replication_mode(&mut self, replication_mode: ReplicationMode) -> &mut Config {
+        self.replication_mode = Some(replication_mode);
+        self
+    }
+
+    /// Gets the replication mode of the connection, if one has been configured with the `replication_mode` method.
+    pub fn get_replication_mode(&self) -> Option<ReplicationMode> {
+        self.replication_mode
+    }
+
+    /// Sets a hook to report metrics of connections made with this configuration.
+    pub fn metrics(&mut self, metrics: Arc<dyn Metrics>) -> &mut Config {
+        self.metrics = Some(ConfigMetrics(metrics));
+        self
+    }
+
+    /// Gets the metrics hook, if one has been configured with the `metrics` method.
+    pub fn get_metrics(&self) -> Option<&Arc<dyn Metrics>> {
+        self.metrics.as_ref().map(|m| &m.0)
+    }
+
+    /// Sets a hook to be notified of the lifecycle events of connections made with this configuration.
+    pub fn connection_listener(&mut self, listener: Arc<dyn ConnectionListener>) -> &mut Config {
+        self.connection_listener = Some(ConfigConnectionListener(listener));
+        self
+    }
+
+    /// Gets the connection listener, if one has been configured with the `connection_listener` method.
+    pub fn get_connection_listener(&self) -> Option<&Arc<dyn ConnectionListener>> {
+        self.connection_listener.as_ref().map(|l| &l.0)
+    }
+
+    /// Sets a watchdog monitoring connections made with this configuration for long-running transactions and queries.
+    ///
+    /// The watchdog uses the runtime to wait on its thresholds, so one must be available even when connecting with
+    /// `connect_raw`.
+    pub fn watchdog(&mut self, watchdog: Watchdog) -> &mut Config {
+        self.watchdog = Some(watchdog);
+        self
+    }
+
+    /// Gets the watchdog, if one has been configured with the `watchdog` method.
+    pub fn get_watchdog(&self) -> Option<&Watchdog> {
+        self.watchdog.as_ref()
+    }
+
+    /// Requests the cancellation of queries which are abandoned before they complete.
+    ///
+    /// When the future or stream of a query is dropped before the query's response has been received in full, the
+    /// connection reads and discards the rest of the response so that it stays in sync with the server, but the query
+    /// runs to completion. When this is set, a cancellation request is also sent over a new connection made with the
+    /// provided TLS connector, so that the server stops working on the abandoned query.
+    ///
+    /// Cancellation is inherently racy: if the abandoned query finishes before the request reaches the server, the
+    /// query executed after it may be canceled instead. Queries can only be canceled on connections made with
+    /// `connect`, as the address of the server is unknown to those made with `connect_raw`.
+    pub fn cancel_on_drop<T>(&mut self, tls: T) -> &mut Config
+    where
+        T: MakeTlsConnect<Socket> + Clone + 'static + Sync + Send,
+        T::TlsConnect: Send,
+        T::Stream: Send,
+        <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+    {
+        self.cancel_on_drop = Some(ConfigCancelFn(cancel_token::cancel_fn(tls)));
+        self
+    }
+
+    /// Determines if abandoned queries are canceled, as configured with the `cancel_on_drop` method.
+    pub fn get_cancel_on_drop(&self) -> bool {
+        self.cancel_on_drop.is_some()
+    }
+
+    /// Sets the runtime used to open connections, overriding the default selected by the enabled Cargo features.
+    pub fn runtime(&mut self, runtime: Arc<dyn Runtime>) -> &mut Config {
+        self.runtime = Some(ConfigRuntime(runtime));
+        self
+    }
+
+    /// Gets the runtime, if one has been configured with the `runtime` method.
+    pub fn get_runtime(&self) -> Option<&Arc<dyn Runtime>> {
+        self.runtime.as_ref().map(|r| &r.0)
+    }
+
+    /// Enables logging of every protocol message sent and received by the connection.
+    ///
+    /// Messages are logged at the `info` level to the `tokio_postgres::protocol` target, in a format similar to libpq's
+    /// `PQtrace`. This is intended for debugging, and should not be enabled in production since query text and
+    /// server messages are logged. Defaults to `false`.
+    pub fn trace_protocol(&mut self, trace_protocol: bool) -> &mut Config {
+        self.trace_protocol = trace_protocol;
+        self
+    }
+
+    /// Gets whether protocol messages will be logged.
+    pub fn get_trace_protocol(&self) -> bool {
+        self.trace_protocol
+    }
+
+    /// Sets the maximum number of requests which can be queued for the connection.
+    ///
+    /// Requests are queued by the `Client` until the `Connection` is ready to write them to the server. Once the queue is
+    /// full, client methods will wait for space to become available rather than buffering an unbounded number of
+    /// requests in memory. Defaults to no limit.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0.
+    pub fn request_queue_capacity(&mut self, capacity: usize) -> &mut Config {
+        assert!(capacity > 0, "request queue capacity must be positive");
+        self.request_queue_capacity = Some(capacity);
+        self
+    }
+
+    /// Gets the maximum number of queued requests, if one has been configured with the `request_queue_capacity` method.
+    pub fn get_request_queue_capacity(&self) -> Option<usize> {
+        self.request_queue_capacity
+    }
+
+    /// Controls whether prepared statements are stored on the server under a name of their own.
+    ///
+    /// Named statements only exist in the server session which prepared them. Connection poolers such as PgBouncer in
+    /// transaction pooling mode hand each transaction to whichever server session is free, so a statement prepared in
+    /// one transaction can be missing when it is executed in the next. When disabled, statements are prepared as the
+    /// server's single unnamed statement instead, and parsed again each time they are executed, at the cost of
+    /// repeating that work on the server. Defaults to `true`.
+    pub fn named_statements(&mut self, named_statements: bool) -> &mut Config {
+        self.named_statements = named_statements;
+        self
+    }
+
+    /// Gets whether prepared statements are stored on the server under a name of their own.
+    pub fn get_named_statements(&self) -> bool {
+        self.named_statements
+    }
+
+    /// Sets the prefix of the names under which statements are prepared on the server.
+    ///
+    /// Statements are named with the prefix followed by a number. A distinct prefix avoids collisions with statements
+    /// prepared in the same session by other means, such as the SQL `PREPARE` command or another client sharing the
+    /// session through a connection pooler. Defaults to `s`.
+    pub fn statement_name_prefix(&mut self, statement_name_prefix: &str) -> &mut Config {
+        self.statement_name_prefix = statement_name_prefix.to_string();
+        self
+    }
+
+    /// Gets the prefix of the names under which statements are prepared on the server.
+    pub fn get_statement_name_prefix(&self) -> &str {
+        &self.statement_name_prefix
+    }
+
+    /// Sets the maximum number of statements kept in the client's statement cache.
+    ///
+    /// When enabled, the statements prepared for raw query strings passed to methods such as `query` and `execute` are
+    /// cached by the text of the query and reused, rather than being prepared again each time. Once the cache is full,
+    /// the least recently used statement is evicted and closed on the server, so that long-lived connections don't
+    /// accumulate prepared statements without bound. The cache is not used if `named_statements` is disabled.
+    ///
+    /// If a cached statement no longer exists on the server when it is executed outside of a transaction, as happens
+    /// when a connection pooler such as PgBouncer moves the session to another server session, it is prepared again
+    /// and the request retried once. Defaults to 0, which disables the cache.
+    pub fn statement_cache_capacity(&mut self, statement_cache_capacity: usize) -> &mut Config {
+        self.statement_cache_capacity = statement_cache_capacity;
+        self
+    }
+
+    /// Gets the maximum number of statements kept in the client's statement cache.
+    pub fn get_statement_cache_capacity(&self) -> usize {
+        self.statement_cache_capacity
+    }
+
+    /// Sets the initial capacity of the buffer used to read messages from the server, in bytes.
+    ///
+    /// The buffer grows as needed to hold larger messages. Defaults to 8 KiB.
+    pub fn read_buffer_size(&mut self, read_buffer_size: usize) -> &mut Config {
+        self.read_buffer_size = Some(read_buffer_size);
+        self
+    }
+
+    /// Gets the initial read buffer capacity, if one has been configured with the `read_buffer_size` method.
+    pub fn get_read_buffer_size(&self) -> Option<usize> {
+        self.read_buffer_size
+    }
+
+    /// Sets the maximum length of a message the server is allowed to send, in bytes.
+    ///
+    /// The connection fails with an error if the server announces a longer message, rather than buffering it in memory.
+    /// This protects against a misbehaving server causing the client to allocate an arbitrary amount of memory. The
+    /// limit must be large enough to hold the largest row returned by a query. Defaults to no limit.
+    pub fn max_message_length(&mut self, max_message_length: usize) -> &mut Config {
+        self.max_message_length = Some(max_message_length);
+        self
+    }
+
+    /// Gets the maximum message length, if one has been configured with the `max_message_length` method.
+    pub fn get_max_message_length(&self) -> Option<usize> {
+        self.max_message_length
+    }
+
     fn param(&mut self, key: &str, value: &str) -> Result<(), Error> {
         match key {
             "user" => {
@@ -404,6 +977,46 @@ impl Config {
             "application_name" => {
                 self.application_name(value);
             }
+            "search_path" => {
+                let schemas = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .collect::<Vec<_>>();
+                self.search_path(schemas);
+            }
+            "role" => {
+                self.role(value);
+            }
+            "default_transaction_isolation" => {
+                let isolation_level = match &*value.to_ascii_lowercase() {
+                    "read uncommitted" => IsolationLevel::ReadUncommitted,
+                    "read committed" => IsolationLevel::ReadCommitted,
+                    "repeatable read" => IsolationLevel::RepeatableRead,
+                    "serializable" => IsolationLevel::Serializable,
+                    _ => {
+                        return Err(Error::config_parse(Box::new(InvalidValue(
+                            "default_transaction_isolation",
+                        ))))
+                    }
+                };
+                self.default_transaction_isolation(isolation_level);
+            }
+            "default_transaction_read_only" => {
+                let read_only = match value {
+                    "true" | "on" | "yes" | "1" => true,
+                    "false" | "off" | "no" | "0" => false,
+                    _ => {
+                        return Err(Error::config_parse(Box::new(InvalidValue(
+                            "default_transaction_read_only",
+                        ))))
+                    }
+                };
+                self.default_transaction_read_only(read_only);
+            }
+            "client_encoding" => {
+                self.client_encoding(value);
+            }
             "sslmode" => {
                 let mode = match value {
                     "disable" => SslMode::Disable,
@@ -413,6 +1026,15 @@ impl Config {
                 };
                 self.ssl_mode(mode);
             }
+            "gssencmode" => {
+                let mode = match value {
+                    "disable" => GssEncMode::Disable,
+                    "prefer" => GssEncMode::Prefer,
+                    "require" => GssEncMode::Require,
+                    _ => return Err(Error::config_parse(Box::new(InvalidValue("gssencmode")))),
+                };
+                self.gss_enc_mode(mode);
+            }
             "host" => {
                 for host in value.split(',') {
                     self.host(host);
@@ -476,6 +1098,39 @@ impl Config {
                 };
                 self.channel_binding(channel_binding);
             }
+            "replication" => {
+                let replication_mode = match value {
+                    "true" | "on" | "yes" | "1" => Some(ReplicationMode::Physical),
+                    "database" => Some(ReplicationMode::Logical),
+                    "false" | "off" | "no" | "0" => None,
+                    _ => return Err(Error::config_parse(Box::new(InvalidValue("replication")))),
+                };
+                self.replication_mode = replication_mode;
+            }
+            "min_protocol_version" => {
+                let version = match value {
+                    "3.0" => ProtocolVersion::V3_0,
+                    "3.2" => ProtocolVersion::V3_2,
+                    _ => {
+                        return Err(Error::config_parse(Box::new(InvalidValue(
+                            "min_protocol_version",
+                        ))))
+                    }
+                };
+                self.min_protocol_version(version);
+            }
+            "max_protocol_version" => {
+                let version = match value {
+                    "3.0" => ProtocolVersion::V3_0,
+                    "3.2" | "latest" => ProtocolVersion::V3_2,
+                    _ => {
+                        return Err(Error::config_parse(Box::new(InvalidValue(
+                            "max_protocol_version",
+                        ))))
+                    }
+                };
+                self.max_protocol_version(version);
+            }
             key => {
                 return Err(Error::config_parse(Box::new(UnknownOption(
                     key.to_string(),
@@ -488,8 +1143,16 @@ impl Config {
 
     /// Opens a connection to a PostgreSQL database.
     ///
-    /// Requires the `runtime` Cargo feature (enabled by default).
-    #[cfg(feature = "runtime")]
+    /// The connection is opened using the runtime set with the `runtime` method, or if none has been set, the runtime
+    /// enabled by the `runtime` (tokio), `runtime-async-std`, or `runtime-smol` Cargo features, in that order of
+    /// preference.
+    ///
+    /// Requires one of the `runtime`, `runtime-async-std`, or `runtime-smol` Cargo features.
+    #[cfg(any(
+        feature = "runtime",
+        feature = "runtime-async-std",
+        feature = "runtime-smol"
+    ))]
     pub async fn connect<T>(&self, tls: T) -> Result<(Client, Connection<Socket, T::Stream>), Error>
     where
         T: MakeTlsConnect<Socket>,
@@ -499,7 +1162,8 @@ impl Config {
 
     /// Connects to a PostgreSQL database over an arbitrary stream.
     ///
-    /// All of the settings other than `user`, `password`, `dbname`, `options`, and `application_name` name are ignored.
+    /// All of the settings other than `user`, `password`, `dbname`, `options`, `search_path`, `application_name`,
+    /// `role`, and `replication_mode` are ignored.
     pub async fn connect_raw<S, T>(
         &self,
         stream: S,
@@ -509,7 +1173,7 @@ impl Config {
         S: AsyncRead + AsyncWrite + Unpin,
         T: TlsConnect<S>,
     {
-        connect_raw(stream, tls, self).await
+        connect_raw(stream, tls, self, None).await
     }
 }
 
@@ -539,15 +1203,47 @@ impl fmt::Debug for Config {
             .field("password", &self.password.as_ref().map(|_| Redaction {}))
             .field("dbname", &self.dbname)
             .field("options", &self.options)
+            .field("server_settings", &self.server_settings)
+            .field("search_path", &self.search_path)
             .field("application_name", &self.application_name)
+            .field("role", &self.role)
+            .field(
+                "default_transaction_isolation",
+                &self.default_transaction_isolation,
+            )
+            .field(
+                "default_transaction_read_only",
+                &self.default_transaction_read_only,
+            )
+            .field("client_encoding", &self.client_encoding)
             .field("ssl_mode", &self.ssl_mode)
+            .field("gss_enc_mode", &self.gss_enc_mode)
             .field("host", &self.host)
             .field("port", &self.port)
             .field("connect_timeout", &self.connect_timeout)
             .field("keepalives", &self.keepalives)
             .field("keepalives_idle", &self.keepalives_idle)
+            .field("address_strategy", &self.address_strategy)
+            .field("happy_eyeballs_delay", &self.happy_eyeballs_delay)
+            .field("resolver", &self.resolver)
             .field("target_session_attrs", &self.target_session_attrs)
             .field("channel_binding", &self.channel_binding)
+            .field("replication_mode", &self.replication_mode)
+            .field("metrics", &self.metrics)
+            .field("connection_listener", &self.connection_listener)
+            .field("watchdog", &self.watchdog)
+            .field("cancel_on_drop", &self.cancel_on_drop)
+            .field("runtime", &self.runtime)
+            .field("trace_protocol", &self.trace_protocol)
+            .field("request_queue_capacity", &self.request_queue_capacity)
+            .field("read_buffer_size", &self.read_buffer_size)
+            .field("max_message_length", &self.max_message_length)
+            .field("named_statements", &self.named_statements)
+            .field("statement_name_prefix", &self.statement_name_prefix)
+            .field("statement_cache_capacity", &self.statement_cache_capacity)
+            .field("min_protocol_version", &self.min_protocol_version)
+            .field("max_protocol_version", &self.max_protocol_version)
+            .field("protocol_extensions", &self.protocol_extensions)
             .finish()
     }
 }
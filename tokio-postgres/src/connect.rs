@@ -1,16 +1,35 @@
 use crate::client::SocketConfig;
-use crate::config::{Host, TargetSessionAttrs};
+use crate::config::{GssEncMode, Host, TargetSessionAttrs};
 use crate::connect_raw::connect_raw;
 use crate::connect_socket::connect_socket;
+use crate::runtime::{self, Runtime};
 use crate::tls::{MakeTlsConnect, TlsConnect};
+use crate::trace::{self, Instrument};
 use crate::{Client, Config, Connection, Error, SimpleQueryMessage, Socket};
 use futures::{future, pin_mut, Future, FutureExt, Stream};
 use std::io;
+use std::sync::Arc;
 use std::task::Poll;
 
 pub async fn connect<T>(
+    tls: T,
+    config: &Config,
+) -> Result<(Client, Connection<Socket, T::Stream>), Error>
+where
+    T: MakeTlsConnect<Socket>,
+{
+    let span = trace::connect_span(config);
+    let result = connect_hosts(tls, config, &span)
+        .instrument(span.clone())
+        .await;
+    trace::record_result(&span, &result);
+    result
+}
+
+async fn connect_hosts<T>(
     mut tls: T,
     config: &Config,
+    span: &trace::Span,
 ) -> Result<(Client, Connection<Socket, T::Stream>), Error>
 where
     T: MakeTlsConnect<Socket>,
@@ -19,6 +38,19 @@ where
         return Err(Error::config("host missing".into()));
     }
 
+    // there is no GSSAPI implementation to negotiate encryption with, so a requirement for it can never be met
+    if config.gss_enc_mode == GssEncMode::Require {
+        return Err(Error::config(
+            "GSSAPI encryption was required but is not supported".into(),
+        ));
+    }
+
+    let runtime = match &config.runtime {
+        Some(runtime) => runtime.0.clone(),
+        None => runtime::default_runtime()
+            .ok_or_else(|| Error::config("no runtime configured".into()))?,
+    };
+
     if config.port.len() > 1 && config.port.len() != config.host.len() {
         return Err(Error::config("invalid number of ports".into()));
     }
@@ -43,7 +75,8 @@ where
             .make_tls_connect(hostname)
             .map_err(|e| Error::tls(e.into()))?;
 
-        match connect_once(host, port, tls, config).await {
+        trace::record_peer(span, host, port);
+        match connect_once(&runtime, host, port, tls, config).await {
             Ok((client, connection)) => return Ok((client, connection)),
             Err(e) => error = Some(e),
         }
@@ -53,6 +86,7 @@ where
 }
 
 async fn connect_once<T>(
+    runtime: &Arc<dyn Runtime>,
     host: &Host,
     port: u16,
     tls: T,
@@ -61,15 +95,19 @@ async fn connect_once<T>(
 where
     T: TlsConnect<Socket>,
 {
-    let socket = connect_socket(
-        host,
+    let socket_config = SocketConfig {
+        runtime: runtime.clone(),
+        resolver: config.resolver.as_ref().map(|r| r.0.clone()),
+        host: host.clone(),
         port,
-        config.connect_timeout,
-        config.keepalives,
-        config.keepalives_idle,
-    )
-    .await?;
-    let (mut client, mut connection) = connect_raw(socket, tls, config).await?;
+        connect_timeout: config.connect_timeout,
+        keepalives: config.keepalives,
+        keepalives_idle: config.keepalives_idle,
+        address_strategy: config.address_strategy,
+        happy_eyeballs_delay: config.happy_eyeballs_delay,
+    };
+    let socket = connect_socket(&socket_config).await?;
+    let (mut client, mut connection) = connect_raw(socket, tls, config, Some((host, port))).await?;
 
     if let TargetSessionAttrs::ReadWrite = config.target_session_attrs {
         let rows = client.simple_query_raw("SHOW transaction_read_only");
@@ -111,13 +149,8 @@ where
         }
     }
 
-    client.set_socket_config(SocketConfig {
-        host: host.clone(),
-        port,
-        connect_timeout: config.connect_timeout,
-        keepalives: config.keepalives,
-        keepalives_idle: config.keepalives_idle,
-    });
+    client.set_socket_config(socket_config);
+    connection.set_cancel_token(client.cancel_token());
 
     Ok((client, connection))
 }
@@ -1,22 +1,28 @@
 use crate::codec::{BackendMessage, BackendMessages, FrontendMessage, PostgresCodec};
-use crate::config::{self, Config};
+use crate::config::{self, Config, Host, ProtocolVersion, ReplicationMode};
 use crate::connect_tls::connect_tls;
+use crate::connection_listener::ConnectedEvent;
 use crate::maybe_tls_stream::MaybeTlsStream;
+use crate::metrics::ConnectionMetrics;
+use crate::runtime;
+use crate::statement_cache::StatementConfig;
 use crate::tls::{TlsConnect, TlsStream};
+use crate::trace::{self, Instrument};
+use crate::watchdog::ConnectionWatchdog;
 use crate::{Client, Connection, Error};
 use bytes::BytesMut;
 use fallible_iterator::FallibleIterator;
 use futures::channel::mpsc;
-use futures::{ready, Sink, SinkExt, Stream, TryStreamExt};
-use postgres_protocol::authentication;
-use postgres_protocol::authentication::sasl;
-use postgres_protocol::authentication::sasl::ScramSha256;
-use postgres_protocol::message::backend::{AuthenticationSaslBody, Message};
-use postgres_protocol::message::frontend;
-use std::collections::{HashMap, VecDeque};
+use futures::{future, pin_mut, ready, Future, FutureExt, Sink, SinkExt, Stream, TryStreamExt};
+use log::debug;
+use postgres_core::{ChannelBinding, Handshake, HandshakeConfig, ServerInfo, Step};
+use postgres_protocol::escape;
+use postgres_protocol::message::backend::Message;
+use std::collections::VecDeque;
 use std::io;
 use std::pin::Pin;
 use std::task::{Context, Poll};
+use std::time::Instant;
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_util::codec::Framed;
 
@@ -24,6 +30,24 @@ pub struct StartupStream<S, T> {
     inner: Framed<MaybeTlsStream<S, T>, PostgresCodec>,
     buf: BackendMessages,
     delayed: VecDeque<BackendMessage>,
+    // reused to encode each of the frontend messages of the handshake
+    scratch: BytesMut,
+}
+
+impl<S, T> StartupStream<S, T>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    // sends the messages written to the scratch buffer, if there are any
+    async fn send_scratch(&mut self) -> Result<(), Error> {
+        if self.scratch.is_empty() {
+            return Ok(());
+        }
+
+        let message = FrontendMessage::Raw(self.scratch.split().freeze());
+        self.send(message).await.map_err(Error::io)
+    }
 }
 
 impl<S, T> Sink<FrontendMessage> for StartupStream<S, T>
@@ -78,269 +102,264 @@ where
     }
 }
 
+// The peer is the host and port the stream is connected to, if known, and is only used to report the connection to
+// the configured listener.
 pub async fn connect_raw<S, T>(
     stream: S,
     tls: T,
     config: &Config,
+    peer: Option<(&Host, u16)>,
+) -> Result<(Client, Connection<S, T::Stream>), Error>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+    T: TlsConnect<S>,
+{
+    let span = trace::handshake_span();
+    let result = handshake(stream, tls, config, peer)
+        .instrument(span.clone())
+        .await;
+    trace::record_result(&span, &result);
+    result
+}
+
+async fn handshake<S, T>(
+    stream: S,
+    tls: T,
+    config: &Config,
+    peer: Option<(&Host, u16)>,
 ) -> Result<(Client, Connection<S, T::Stream>), Error>
 where
     S: AsyncRead + AsyncWrite + Unpin,
     T: TlsConnect<S>,
 {
+    let start = Instant::now();
     let stream = connect_tls(stream, config.ssl_mode, tls).await?;
+    let listener = config.get_connection_listener();
+    if let (Some(listener), MaybeTlsStream::Tls(_)) = (listener, &stream) {
+        listener.tls_established();
+    }
 
     let mut stream = StartupStream {
-        inner: Framed::new(stream, PostgresCodec),
+        inner: match config.read_buffer_size {
+            Some(capacity) => Framed::with_capacity(stream, PostgresCodec::new(config), capacity),
+            None => Framed::new(stream, PostgresCodec::new(config)),
+        },
         buf: BackendMessages::empty(),
         delayed: VecDeque::new(),
+        scratch: BytesMut::new(),
     };
 
-    startup(&mut stream, config).await?;
-    authenticate(&mut stream, config).await?;
-    let (process_id, secret_key, parameters) = read_info(&mut stream).await?;
-
-    let (sender, receiver) = mpsc::unbounded();
-    let client = Client::new(sender, config.ssl_mode, process_id, secret_key);
-    let connection = Connection::new(stream.inner, stream.delayed, parameters, receiver);
-
-    Ok((client, connection))
-}
-
-async fn startup<S, T>(stream: &mut StartupStream<S, T>, config: &Config) -> Result<(), Error>
-where
-    S: AsyncRead + AsyncWrite + Unpin,
-    T: AsyncRead + AsyncWrite + Unpin,
-{
-    let mut params = vec![("client_encoding", "UTF8")];
-    if let Some(user) = &config.user {
-        params.push(("user", &**user));
-    }
-    if let Some(dbname) = &config.dbname {
-        params.push(("database", &**dbname));
-    }
-    if let Some(options) = &config.options {
-        params.push(("options", &**options));
+    let info = drive_handshake(&mut stream, config).await?;
+    let process_id = info.process_id();
+    let secret_key = info.secret_key_bytes().to_vec();
+    let protocol_version = match info.protocol_version() {
+        postgres_core::ProtocolVersion::V3_2 => ProtocolVersion::V3_2,
+        _ => ProtocolVersion::V3_0,
+    };
+    for extension in info.unsupported_protocol_extensions() {
+        debug!("server does not support protocol extension {}", extension);
     }
-    if let Some(application_name) = &config.application_name {
-        params.push(("application_name", &**application_name));
+    if let Some(listener) = listener {
+        listener.authenticated(info.authentication_method());
+        listener.connected(&ConnectedEvent {
+            host: peer.map(|(host, _)| host),
+            port: peer.map(|(_, port)| port),
+            process_id,
+            authentication_method: info.authentication_method(),
+            parameters: info.parameters(),
+        });
     }
+    let parameters = info.into_parameters();
 
-    let mut buf = BytesMut::new();
-    frontend::startup_message(params, &mut buf).map_err(Error::encode)?;
-
-    stream
-        .send(FrontendMessage::Raw(buf.freeze()))
-        .await
-        .map_err(Error::io)
-}
+    let metrics = config.get_metrics().cloned().map(ConnectionMetrics::new);
+    if let Some(metrics) = &metrics {
+        metrics.get().handshake_completed(start.elapsed());
+    }
 
-async fn authenticate<S, T>(stream: &mut StartupStream<S, T>, config: &Config) -> Result<(), Error>
-where
-    S: AsyncRead + AsyncWrite + Unpin,
-    T: TlsStream + Unpin,
-{
-    match stream.try_next().await.map_err(Error::io)? {
-        Some(Message::AuthenticationOk) => {
-            can_skip_channel_binding(config)?;
-            return Ok(());
+    let watchdog = match &config.watchdog {
+        Some(watchdog) => {
+            let runtime = match &config.runtime {
+                Some(runtime) => runtime.0.clone(),
+                None => runtime::default_runtime()
+                    .ok_or_else(|| Error::config("no runtime configured".into()))?,
+            };
+            Some(ConnectionWatchdog::new(watchdog.clone(), runtime))
         }
-        Some(Message::AuthenticationCleartextPassword) => {
-            can_skip_channel_binding(config)?;
-
-            let pass = config
-                .password
-                .as_ref()
-                .ok_or_else(|| Error::config("password missing".into()))?;
+        None => None,
+    };
 
-            authenticate_password(stream, pass).await?;
-        }
-        Some(Message::AuthenticationMd5Password(body)) => {
-            can_skip_channel_binding(config)?;
-
-            let user = config
-                .user
-                .as_ref()
-                .ok_or_else(|| Error::config("user missing".into()))?;
-            let pass = config
-                .password
-                .as_ref()
-                .ok_or_else(|| Error::config("password missing".into()))?;
-
-            let output = authentication::md5_hash(user.as_bytes(), pass, body.salt());
-            authenticate_password(stream, output.as_bytes()).await?;
-        }
-        Some(Message::AuthenticationSasl(body)) => {
-            authenticate_sasl(stream, body, config).await?;
-        }
-        Some(Message::AuthenticationKerberosV5)
-        | Some(Message::AuthenticationScmCredential)
-        | Some(Message::AuthenticationGss)
-        | Some(Message::AuthenticationSspi) => {
-            return Err(Error::authentication(
-                "unsupported authentication method".into(),
-            ))
-        }
-        Some(Message::ErrorResponse(body)) => return Err(Error::db(body)),
-        Some(_) => return Err(Error::unexpected_message()),
-        None => return Err(Error::closed()),
+    let (sender, receiver) = mpsc::unbounded();
+    let mut client = Client::new(
+        sender,
+        config.ssl_mode,
+        process_id,
+        secret_key,
+        metrics.clone(),
+        config.request_queue_capacity,
+        StatementConfig {
+            named: config.named_statements,
+            name_prefix: config.statement_name_prefix.clone(),
+            cache_capacity: config.statement_cache_capacity,
+        },
+    );
+    client.set_protocol_version(protocol_version);
+    client.set_message_counter(stream.inner.codec().message_counter().clone());
+    let mut connection = Connection::new(
+        stream.inner,
+        stream.delayed,
+        parameters,
+        receiver,
+        metrics,
+        listener.cloned(),
+        watchdog,
+    );
+
+    connection.set_transaction_status(client.inner().transaction_status().clone());
+    if let Some(cancel_on_drop) = &config.cancel_on_drop {
+        let waker = connection.set_cancel_on_drop(cancel_on_drop.0.clone());
+        client.inner().set_abandon_waker(waker);
     }
 
-    match stream.try_next().await.map_err(Error::io)? {
-        Some(Message::AuthenticationOk) => Ok(()),
-        Some(Message::ErrorResponse(body)) => Err(Error::db(body)),
-        Some(_) => Err(Error::unexpected_message()),
-        None => Err(Error::closed()),
+    let mut setup = vec![];
+    if let Some(role) = &config.role {
+        setup.push(format!("SET ROLE {}", escape::escape_identifier(role)));
     }
-}
-
-fn can_skip_channel_binding(config: &Config) -> Result<(), Error> {
-    match config.channel_binding {
-        config::ChannelBinding::Disable | config::ChannelBinding::Prefer => Ok(()),
-        config::ChannelBinding::Require => Err(Error::authentication(
-            "server did not use channel binding".into(),
-        )),
+    if let Some(query) = config.session_characteristics_query() {
+        setup.push(query);
     }
+    if !setup.is_empty() {
+        configure_session(&client, &mut connection, &setup.join("; ")).await?;
+    }
+
+    Ok((client, connection))
 }
 
-async fn authenticate_password<S, T>(
-    stream: &mut StartupStream<S, T>,
-    password: &[u8],
+// Runs the statements assuming the configured role and setting the default transaction characteristics, driving the
+// connection until the server responds.
+async fn configure_session<S, T>(
+    client: &Client,
+    connection: &mut Connection<S, T>,
+    query: &str,
 ) -> Result<(), Error>
 where
     S: AsyncRead + AsyncWrite + Unpin,
     T: AsyncRead + AsyncWrite + Unpin,
 {
-    let mut buf = BytesMut::new();
-    frontend::password_message(password, &mut buf).map_err(Error::encode)?;
+    let configure = client.batch_execute(query);
+    pin_mut!(configure);
 
-    stream
-        .send(FrontendMessage::Raw(buf.freeze()))
-        .await
-        .map_err(Error::io)
+    future::poll_fn(|cx| {
+        if connection.poll_unpin(cx)?.is_ready() {
+            return Poll::Ready(Err(Error::closed()));
+        }
+
+        configure.as_mut().poll(cx)
+    })
+    .await
 }
 
-async fn authenticate_sasl<S, T>(
+async fn drive_handshake<S, T>(
     stream: &mut StartupStream<S, T>,
-    body: AuthenticationSaslBody,
     config: &Config,
-) -> Result<(), Error>
+) -> Result<ServerInfo, Error>
 where
     S: AsyncRead + AsyncWrite + Unpin,
     T: TlsStream + Unpin,
 {
-    let password = config
-        .password
-        .as_ref()
-        .ok_or_else(|| Error::config("password missing".into()))?;
-
-    let mut has_scram = false;
-    let mut has_scram_plus = false;
-    let mut mechanisms = body.mechanisms();
-    while let Some(mechanism) = mechanisms.next().map_err(Error::parse)? {
-        match mechanism {
-            sasl::SCRAM_SHA_256 => has_scram = true,
-            sasl::SCRAM_SHA_256_PLUS => has_scram_plus = true,
-            _ => {}
-        }
-    }
-
-    let channel_binding = stream
+    let tls_server_end_point = stream
         .inner
         .get_ref()
         .channel_binding()
-        .tls_server_end_point
-        .filter(|_| config.channel_binding != config::ChannelBinding::Disable)
-        .map(sasl::ChannelBinding::tls_server_end_point);
-
-    let (channel_binding, mechanism) = if has_scram_plus {
-        match channel_binding {
-            Some(channel_binding) => (channel_binding, sasl::SCRAM_SHA_256_PLUS),
-            None => (sasl::ChannelBinding::unsupported(), sasl::SCRAM_SHA_256),
-        }
-    } else if has_scram {
-        match channel_binding {
-            Some(_) => (sasl::ChannelBinding::unrequested(), sasl::SCRAM_SHA_256),
-            None => (sasl::ChannelBinding::unsupported(), sasl::SCRAM_SHA_256),
-        }
-    } else {
-        return Err(Error::authentication("unsupported SASL mechanism".into()));
-    };
-
-    if mechanism != sasl::SCRAM_SHA_256_PLUS {
-        can_skip_channel_binding(config)?;
-    }
+        .tls_server_end_point;
+    let mut handshake = Handshake::new(handshake_config(config, tls_server_end_point));
 
-    let mut scram = ScramSha256::new(password, channel_binding);
-
-    let mut buf = BytesMut::new();
-    frontend::sasl_initial_response(mechanism, scram.message(), &mut buf).map_err(Error::encode)?;
-    stream
-        .send(FrontendMessage::Raw(buf.freeze()))
-        .await
-        .map_err(Error::io)?;
-
-    let body = match stream.try_next().await.map_err(Error::io)? {
-        Some(Message::AuthenticationSaslContinue(body)) => body,
-        Some(Message::ErrorResponse(body)) => return Err(Error::db(body)),
-        Some(_) => return Err(Error::unexpected_message()),
-        None => return Err(Error::closed()),
-    };
+    handshake
+        .start(&mut stream.scratch)
+        .map_err(handshake_error)?;
+    stream.send_scratch().await?;
 
-    scram
-        .update(body.data())
-        .map_err(|e| Error::authentication(e.into()))?;
-
-    let mut buf = BytesMut::new();
-    frontend::sasl_response(scram.message(), &mut buf).map_err(Error::encode)?;
-    stream
-        .send(FrontendMessage::Raw(buf.freeze()))
-        .await
-        .map_err(Error::io)?;
-
-    let body = match stream.try_next().await.map_err(Error::io)? {
-        Some(Message::AuthenticationSaslFinal(body)) => body,
-        Some(Message::ErrorResponse(body)) => return Err(Error::db(body)),
-        Some(_) => return Err(Error::unexpected_message()),
-        None => return Err(Error::closed()),
-    };
-
-    scram
-        .finish(body.data())
-        .map_err(|e| Error::authentication(e.into()))?;
+    loop {
+        let message = match stream.try_next().await.map_err(Error::io)? {
+            Some(message) => message,
+            None => return Err(Error::closed()),
+        };
+
+        let step = handshake
+            .handle(message, &mut stream.scratch)
+            .map_err(handshake_error)?;
+        stream.send_scratch().await?;
+
+        match step {
+            Step::Continue => {}
+            Step::Notice(body) => stream
+                .delayed
+                .push_back(BackendMessage::Async(Message::NoticeResponse(body))),
+            Step::Ready(info) => return Ok(info),
+        }
+    }
+}
 
-    Ok(())
+fn handshake_config(config: &Config, tls_server_end_point: Option<Vec<u8>>) -> HandshakeConfig {
+    let mut handshake_config = HandshakeConfig::new();
+    if let Some(user) = &config.user {
+        handshake_config.user(user);
+    }
+    if let Some(password) = &config.password {
+        handshake_config.password(password);
+    }
+    if let Some(dbname) = &config.dbname {
+        handshake_config.param("database", dbname);
+    }
+    handshake_config.client_encoding(&config.client_encoding);
+    if let Some(options) = config.startup_options() {
+        handshake_config.param("options", &options);
+    }
+    if let Some(application_name) = &config.application_name {
+        handshake_config.param("application_name", application_name);
+    }
+    match config.replication_mode {
+        Some(ReplicationMode::Physical) => {
+            handshake_config.param("replication", "true");
+        }
+        Some(ReplicationMode::Logical) => {
+            handshake_config.param("replication", "database");
+        }
+        None => {}
+    }
+    handshake_config.channel_binding(match config.channel_binding {
+        config::ChannelBinding::Disable => ChannelBinding::Disable,
+        config::ChannelBinding::Prefer => ChannelBinding::Prefer,
+        config::ChannelBinding::Require => ChannelBinding::Require,
+    });
+    handshake_config.min_protocol_version(core_protocol_version(config.min_protocol_version));
+    handshake_config.max_protocol_version(core_protocol_version(config.max_protocol_version));
+    for (name, value) in &config.protocol_extensions {
+        handshake_config.protocol_extension(name, value);
+    }
+    if let Some(tls_server_end_point) = tls_server_end_point {
+        handshake_config.tls_server_end_point(tls_server_end_point);
+    }
+    handshake_config
 }
 
-async fn read_info<S, T>(
-    stream: &mut StartupStream<S, T>,
-) -> Result<(i32, i32, HashMap<String, String>), Error>
-where
-    S: AsyncRead + AsyncWrite + Unpin,
-    T: AsyncRead + AsyncWrite + Unpin,
-{
-    let mut process_id = 0;
-    let mut secret_key = 0;
-    let mut parameters = HashMap::new();
+fn core_protocol_version(version: ProtocolVersion) -> postgres_core::ProtocolVersion {
+    match version {
+        ProtocolVersion::V3_0 => postgres_core::ProtocolVersion::V3_0,
+        ProtocolVersion::V3_2 => postgres_core::ProtocolVersion::V3_2,
+    }
+}
 
-    loop {
-        match stream.try_next().await.map_err(Error::io)? {
-            Some(Message::BackendKeyData(body)) => {
-                process_id = body.process_id();
-                secret_key = body.secret_key();
-            }
-            Some(Message::ParameterStatus(body)) => {
-                parameters.insert(
-                    body.name().map_err(Error::parse)?.to_string(),
-                    body.value().map_err(Error::parse)?.to_string(),
-                );
-            }
-            Some(msg @ Message::NoticeResponse(_)) => {
-                stream.delayed.push_back(BackendMessage::Async(msg))
-            }
-            Some(Message::ReadyForQuery(_)) => return Ok((process_id, secret_key, parameters)),
-            Some(Message::ErrorResponse(body)) => return Err(Error::db(body)),
-            Some(_) => return Err(Error::unexpected_message()),
-            None => return Err(Error::closed()),
+fn handshake_error(e: postgres_core::Error) -> Error {
+    match e {
+        postgres_core::Error::Db(body) => Error::db(body),
+        postgres_core::Error::Config(e) => Error::config(e.into()),
+        postgres_core::Error::Authentication(e) => Error::authentication(e),
+        postgres_core::Error::UnexpectedMessage => Error::unexpected_message(),
+        postgres_core::Error::ClientEncoding(encoding) => Error::client_encoding(&encoding),
+        postgres_core::Error::UnsupportedProtocolVersion => {
+            Error::config("server does not support the minimum protocol version".into())
         }
+        postgres_core::Error::Parse(e) => Error::parse(e),
+        postgres_core::Error::Encode(e) => Error::encode(e),
     }
 }
@@ -1,81 +1,153 @@
-use crate::config::Host;
+use crate::client::SocketConfig;
+use crate::config::{AddressStrategy, Host};
+use crate::runtime::Runtime;
 use crate::{Error, Socket};
-use socket2::{SockRef, TcpKeepalive};
+use futures::future::{self, Either};
+use futures::stream::{FuturesUnordered, StreamExt};
+use std::collections::VecDeque;
 use std::future::Future;
 use std::io;
+use std::net::SocketAddr;
 use std::time::Duration;
-#[cfg(unix)]
-use tokio::net::UnixStream;
-use tokio::net::{self, TcpStream};
-use tokio::time;
-
-pub(crate) async fn connect_socket(
-    host: &Host,
-    port: u16,
-    connect_timeout: Option<Duration>,
-    keepalives: bool,
-    keepalives_idle: Duration,
-) -> Result<Socket, Error> {
-    match host {
+
+pub(crate) async fn connect_socket(config: &SocketConfig) -> Result<Socket, Error> {
+    let runtime = &*config.runtime;
+
+    match &config.host {
         Host::Tcp(host) => {
-            let addrs = net::lookup_host((&**host, port))
-                .await
-                .map_err(Error::connect)?;
-
-            let mut last_err = None;
-
-            for addr in addrs {
-                let stream =
-                    match connect_with_timeout(TcpStream::connect(addr), connect_timeout).await {
-                        Ok(stream) => stream,
-                        Err(e) => {
-                            last_err = Some(e);
-                            continue;
-                        }
-                    };
-
-                stream.set_nodelay(true).map_err(Error::connect)?;
-                if keepalives {
-                    SockRef::from(&stream)
-                        .set_tcp_keepalive(&TcpKeepalive::new().with_time(keepalives_idle))
-                        .map_err(Error::connect)?;
-                }
-
-                return Ok(Socket::new_tcp(stream));
+            let addrs = match &config.resolver {
+                Some(resolver) => resolver.lookup_host(host.clone(), config.port).await,
+                None => runtime.lookup_host(host.clone(), config.port).await,
             }
+            .map_err(Error::connect)?;
 
-            Err(last_err.unwrap_or_else(|| {
-                Error::connect(io::Error::new(
-                    io::ErrorKind::InvalidInput,
-                    "could not resolve any addresses",
-                ))
-            }))
+            let result = match config.address_strategy {
+                AddressStrategy::Sequential => connect_sequential(config, addrs).await,
+                AddressStrategy::HappyEyeballs => connect_happy_eyeballs(config, addrs).await,
+            };
+            if let (Err(_), Some(resolver)) = (&result, &config.resolver) {
+                resolver.connect_failed(host, config.port);
+            }
+            result
         }
         #[cfg(unix)]
         Host::Unix(path) => {
-            let path = path.join(format!(".s.PGSQL.{}", port));
-            let socket = connect_with_timeout(UnixStream::connect(path), connect_timeout).await?;
-            Ok(Socket::new_unix(socket))
+            let path = path.join(format!(".s.PGSQL.{}", config.port));
+            connect_with_timeout(runtime, runtime.connect_unix(path), config.connect_timeout).await
+        }
+    }
+}
+
+async fn connect_sequential(
+    config: &SocketConfig,
+    addrs: Vec<SocketAddr>,
+) -> Result<Socket, Error> {
+    let mut last_err = None;
+
+    for addr in addrs {
+        match connect_tcp(config, addr).await {
+            Ok(socket) => return Ok(socket),
+            Err(e) => last_err = Some(e),
+        }
+    }
+
+    Err(last_err.unwrap_or_else(no_addresses))
+}
+
+async fn connect_happy_eyeballs(
+    config: &SocketConfig,
+    addrs: Vec<SocketAddr>,
+) -> Result<Socket, Error> {
+    let mut addrs = interleave(addrs);
+    let mut attempts = FuturesUnordered::new();
+    let mut last_err = None;
+
+    loop {
+        if attempts.is_empty() {
+            match addrs.pop_front() {
+                Some(addr) => attempts.push(connect_tcp(config, addr)),
+                None => return Err(last_err.unwrap_or_else(no_addresses)),
+            }
+        }
+
+        // wait for an attempt to finish, or for the delay to pass if there are more addresses to try
+        let delay = if addrs.is_empty() {
+            Either::Left(future::pending())
+        } else {
+            Either::Right(config.runtime.sleep(config.happy_eyeballs_delay))
+        };
+        let finished = match future::select(attempts.next(), delay).await {
+            Either::Left((finished, _)) => finished,
+            Either::Right(_) => None,
+        };
+
+        match finished {
+            Some(Ok(socket)) => return Ok(socket),
+            Some(Err(e)) => last_err = Some(e),
+            None => {}
+        }
+        if let Some(addr) = addrs.pop_front() {
+            attempts.push(connect_tcp(config, addr));
         }
     }
 }
 
-async fn connect_with_timeout<F, T>(connect: F, timeout: Option<Duration>) -> Result<T, Error>
+// Alternates between address families, starting with the family of the first address, so that a broken network for
+// one family doesn't delay attempts on the other.
+fn interleave(addrs: Vec<SocketAddr>) -> VecDeque<SocketAddr> {
+    let first_ipv6 = addrs.first().map_or(false, SocketAddr::is_ipv6);
+    let (mut preferred, mut other): (VecDeque<_>, VecDeque<_>) = addrs
+        .into_iter()
+        .partition(|addr| addr.is_ipv6() == first_ipv6);
+
+    let mut interleaved = VecDeque::with_capacity(preferred.len() + other.len());
+    while !preferred.is_empty() || !other.is_empty() {
+        interleaved.extend(preferred.pop_front());
+        interleaved.extend(other.pop_front());
+    }
+    interleaved
+}
+
+async fn connect_tcp(config: &SocketConfig, addr: SocketAddr) -> Result<Socket, Error> {
+    let keepalive = if config.keepalives {
+        Some(config.keepalives_idle)
+    } else {
+        None
+    };
+
+    let runtime = &*config.runtime;
+    connect_with_timeout(
+        runtime,
+        runtime.connect_tcp(addr, keepalive),
+        config.connect_timeout,
+    )
+    .await
+}
+
+fn no_addresses() -> Error {
+    Error::connect(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        "could not resolve any addresses",
+    ))
+}
+
+async fn connect_with_timeout<F>(
+    runtime: &dyn Runtime,
+    connect: F,
+    timeout: Option<Duration>,
+) -> Result<Socket, Error>
 where
-    F: Future<Output = io::Result<T>>,
+    F: Future<Output = io::Result<Socket>> + Unpin,
 {
     match timeout {
-        Some(timeout) => match time::timeout(timeout, connect).await {
-            Ok(Ok(socket)) => Ok(socket),
-            Ok(Err(e)) => Err(Error::connect(e)),
-            Err(_) => Err(Error::connect(io::Error::new(
+        Some(timeout) => match future::select(connect, runtime.sleep(timeout)).await {
+            Either::Left((Ok(socket), _)) => Ok(socket),
+            Either::Left((Err(e), _)) => Err(Error::connect(e)),
+            Either::Right(_) => Err(Error::connect(io::Error::new(
                 io::ErrorKind::TimedOut,
                 "connection timed out",
             ))),
         },
-        None => match connect.await {
-            Ok(socket) => Ok(socket),
-            Err(e) => Err(Error::connect(e)),
-        },
+        None => connect.await.map_err(Error::connect),
     }
 }
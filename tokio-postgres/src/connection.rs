@@ -1,35 +1,81 @@
-use crate::codec::{BackendMessage, BackendMessages, FrontendMessage, PostgresCodec};
+use crate::cancel_token::CancelFn;
+use crate::codec::{BackendMessage, BackendMessages, FrontendMessage, PostgresCodec, WriteBuffer};
+use crate::connection_listener::ConnectionListener;
+use crate::copy_both::CopyBothReceiver;
 use crate::copy_in::CopyInReceiver;
 use crate::error::DbError;
 use crate::maybe_tls_stream::MaybeTlsStream;
-use crate::{AsyncMessage, Error, Notification};
+use crate::metrics::ConnectionMetrics;
+use crate::shutdown::{DisconnectCause, ShutdownHandle, ShutdownState};
+use crate::to_sql_stream::ParamStreamReceiver;
+use crate::watchdog::ConnectionWatchdog;
+use crate::{AsyncMessage, CancelToken, Error, MessageStats, Notification};
 use bytes::BytesMut;
 use fallible_iterator::FallibleIterator;
 use futures::channel::mpsc;
+use futures::future::BoxFuture;
 use futures::stream::FusedStream;
-use futures::{ready, Sink, Stream, StreamExt};
-use log::{info, trace};
+use futures::task::AtomicWaker;
+use futures::{ready, Stream, StreamExt};
+use log::{info, trace, warn};
 use postgres_protocol::message::backend::Message;
 use postgres_protocol::message::frontend;
 use std::collections::{HashMap, VecDeque};
 use std::future::Future;
+use std::io;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::OwnedSemaphorePermit;
 use tokio_util::codec::Framed;
 
 pub enum RequestMessages {
     Single(FrontendMessage),
     CopyIn(CopyInReceiver),
+    CopyBoth(CopyBothReceiver),
+    ParamStream(ParamStreamReceiver),
 }
 
 pub struct Request {
     pub messages: RequestMessages,
     pub sender: mpsc::Sender<BackendMessages>,
+    pub permit: Option<OwnedSemaphorePermit>,
+    /// Set by the client if the request is abandoned while the server is executing it.
+    pub abandoned: Option<Arc<AtomicBool>>,
 }
 
+// the amount of buffered data beyond which no further requests will be picked up until it has been written
+const BACKPRESSURE_BOUNDARY: usize = 8 * 1024;
+
+// the amount of data written before the connection checks for incoming messages again, so that large outgoing
+// requests such as `COPY ... FROM STDIN` don't hold up the delivery of notices and responses
+const WRITE_BUDGET: usize = 128 * 1024;
+
 pub struct Response {
     sender: mpsc::Sender<BackendMessages>,
+    abandoned: Option<Arc<AtomicBool>>,
+    canceled: bool,
+}
+
+impl Response {
+    fn is_abandoned(&self) -> bool {
+        self.abandoned
+            .as_ref()
+            .map_or(false, |abandoned| abandoned.load(Ordering::Acquire))
+    }
+}
+
+// The outcome of driving the write half of the connection.
+#[derive(PartialEq, Debug)]
+enum WriteStatus {
+    // no more requests are ready, and the data written should be flushed
+    Idle,
+    // the socket isn't ready for more data, or the connection is closing
+    Blocked,
+    // the write budget has been used up, and more requests may be ready
+    Yielded,
 }
 
 #[derive(PartialEq, Debug)]
@@ -45,16 +91,33 @@ enum State {
 /// server, and should generally be spawned off onto an executor to run in the background.
 ///
 /// `Connection` implements `Future`, and only resolves when the connection is closed, either because a fatal error has
-/// occurred, or because its associated `Client` has dropped and all outstanding work has completed.
+/// occurred, or because its associated `Client` has dropped or shutdown has been requested with a `ShutdownHandle`,
+/// and all outstanding work has completed.
 #[must_use = "futures do nothing unless polled"]
 pub struct Connection<S, T> {
     stream: Framed<MaybeTlsStream<S, T>, PostgresCodec>,
+    write_buf: WriteBuffer,
     parameters: HashMap<String, String>,
     receiver: mpsc::UnboundedReceiver<Request>,
     pending_request: Option<RequestMessages>,
     pending_responses: VecDeque<BackendMessage>,
     responses: VecDeque<Response>,
     state: State,
+    metrics: Option<ConnectionMetrics>,
+    // taken once the connection closes so that the listener is only notified once
+    listener: Option<Arc<dyn ConnectionListener>>,
+    watchdog: Option<ConnectionWatchdog>,
+    cancel_token: Option<CancelToken>,
+    cancel_on_drop: Option<Arc<CancelFn>>,
+    // woken by the client when a request is abandoned
+    abandon_waker: Arc<AtomicWaker>,
+    // the cancellation request sent for an abandoned request, if one is in flight
+    cancel: Option<BoxFuture<'static, Result<(), Error>>>,
+    // set when the unnamed portal of an abandoned request must be closed
+    close_unnamed_portal: bool,
+    shutdown: ShutdownState,
+    // shared with the client
+    transaction_status: Option<Arc<AtomicU8>>,
 }
 
 impl<S, T> Connection<S, T>
@@ -67,16 +130,52 @@ where
         pending_responses: VecDeque<BackendMessage>,
         parameters: HashMap<String, String>,
         receiver: mpsc::UnboundedReceiver<Request>,
+        metrics: Option<ConnectionMetrics>,
+        listener: Option<Arc<dyn ConnectionListener>>,
+        watchdog: Option<ConnectionWatchdog>,
     ) -> Connection<S, T> {
         Connection {
             stream,
+            write_buf: WriteBuffer::new(),
             parameters,
             receiver,
             pending_request: None,
             pending_responses,
             responses: VecDeque::new(),
             state: State::Active,
+            metrics,
+            listener,
+            watchdog,
+            cancel_token: None,
+            cancel_on_drop: None,
+            abandon_waker: Arc::new(AtomicWaker::new()),
+            cancel: None,
+            close_unnamed_portal: false,
+            shutdown: ShutdownState::new(),
+            transaction_status: None,
+        }
+    }
+
+    #[cfg(any(
+        feature = "runtime",
+        feature = "runtime-async-std",
+        feature = "runtime-smol"
+    ))]
+    pub(crate) fn set_cancel_token(&mut self, cancel_token: CancelToken) {
+        if let Some(watchdog) = &mut self.watchdog {
+            watchdog.set_cancel_token(cancel_token.clone());
         }
+        self.cancel_token = Some(cancel_token);
+    }
+
+    pub(crate) fn set_transaction_status(&mut self, transaction_status: Arc<AtomicU8>) {
+        self.transaction_status = Some(transaction_status);
+    }
+
+    /// Enables the cancellation of abandoned requests, returning the waker the client should use to report them.
+    pub(crate) fn set_cancel_on_drop(&mut self, cancel_on_drop: Arc<CancelFn>) -> Arc<AtomicWaker> {
+        self.cancel_on_drop = Some(cancel_on_drop);
+        self.abandon_waker.clone()
     }
 
     fn poll_response(
@@ -123,10 +222,16 @@ where
                     return Ok(Some(AsyncMessage::Notification(notification)));
                 }
                 BackendMessage::Async(Message::ParameterStatus(body)) => {
-                    self.parameters.insert(
-                        body.name().map_err(Error::parse)?.to_string(),
-                        body.value().map_err(Error::parse)?.to_string(),
-                    );
+                    let name = body.name().map_err(Error::parse)?;
+                    let value = body.value().map_err(Error::parse)?;
+                    // strings are always decoded as the encoding validated during the handshake, so a change made
+                    // with `SET client_encoding` would silently corrupt them
+                    if name == "client_encoding"
+                        && self.parameters.get(name).map_or(false, |v| v != value)
+                    {
+                        return Err(Error::client_encoding(value));
+                    }
+                    self.parameters.insert(name.to_string(), value.to_string());
                     continue;
                 }
                 BackendMessage::Async(_) => unreachable!(),
@@ -144,6 +249,19 @@ where
                 },
             };
 
+            let transaction_status = if request_complete {
+                messages.transaction_status()
+            } else {
+                None
+            };
+            if let (Some(shared), Some(status)) = (&self.transaction_status, transaction_status) {
+                shared.store(status, Ordering::Release);
+            }
+            // the unnamed portal of an abandoned query outlives it until the end of the transaction
+            if transaction_status == Some(b'T') && response.is_abandoned() {
+                self.close_unnamed_portal = true;
+            }
+
             match response.sender.poll_ready(cx) {
                 Poll::Ready(Ok(())) => {
                     let _ = response.sender.start_send(messages);
@@ -167,6 +285,10 @@ where
                     return Ok(None);
                 }
             }
+
+            if let (Some(watchdog), Some(status)) = (&mut self.watchdog, transaction_status) {
+                watchdog.request_completed(status, !self.responses.is_empty());
+            }
         }
     }
 
@@ -183,8 +305,18 @@ where
         match self.receiver.poll_next_unpin(cx) {
             Poll::Ready(Some(request)) => {
                 trace!("polled new request");
+                if let Some(metrics) = &self.metrics {
+                    metrics.request_dequeued();
+                }
+                // make room in the client's request queue now that the request has been picked up
+                drop(request.permit);
+                if let Some(watchdog) = &mut self.watchdog {
+                    watchdog.request_started();
+                }
                 self.responses.push_back(Response {
                     sender: request.sender,
+                    abandoned: request.abandoned,
+                    canceled: false,
                 });
                 Poll::Ready(Some(request.messages))
             }
@@ -193,20 +325,46 @@ where
         }
     }
 
-    fn poll_write(&mut self, cx: &mut Context<'_>) -> Result<bool, Error> {
+    fn poll_write(&mut self, cx: &mut Context<'_>) -> Result<WriteStatus, Error> {
+        let mut written = 0;
         loop {
             if self.state == State::Closing {
                 trace!("poll_write: done");
-                return Ok(false);
+                return Ok(WriteStatus::Blocked);
             }
 
-            if Pin::new(&mut self.stream)
-                .poll_ready(cx)
-                .map_err(Error::io)?
-                .is_pending()
-            {
-                trace!("poll_write: waiting on socket");
-                return Ok(false);
+            if self.write_buf.len() >= BACKPRESSURE_BOUNDARY {
+                let len = self.write_buf.len();
+                if self
+                    .write_buf
+                    .poll_write(cx, self.stream.get_mut())
+                    .map_err(Error::io)?
+                    .is_pending()
+                {
+                    trace!("poll_write: waiting on socket");
+                    return Ok(WriteStatus::Blocked);
+                }
+                written += len;
+                if written >= WRITE_BUDGET {
+                    trace!("poll_write: write budget used up");
+                    return Ok(WriteStatus::Yielded);
+                }
+            }
+
+            if self.close_unnamed_portal && self.pending_request.is_none() {
+                trace!("poll_write: closing unnamed portal");
+                self.close_unnamed_portal = false;
+                let mut buf = BytesMut::new();
+                frontend::close(b'P', "", &mut buf).map_err(Error::encode)?;
+                frontend::sync(&mut buf);
+                let (sender, _) = mpsc::channel(1);
+                self.responses.push_back(Response {
+                    sender,
+                    abandoned: None,
+                    canceled: false,
+                });
+                self.send(FrontendMessage::Raw(buf.freeze()));
+                continue;
             }
 
             let request = match self.poll_request(cx) {
@@ -223,19 +381,17 @@ where
                         "poll_write: at eof, pending responses {}",
                         self.responses.len()
                     );
-                    return Ok(true);
+                    return Ok(WriteStatus::Idle);
                 }
                 Poll::Pending => {
                     trace!("poll_write: waiting on request");
-                    return Ok(true);
+                    return Ok(WriteStatus::Idle);
                 }
             };
 
             match request {
                 RequestMessages::Single(request) => {
-                    Pin::new(&mut self.stream)
-                        .start_send(request)
-                        .map_err(Error::io)?;
+                    self.send(request);
                     if self.state == State::Terminating {
                         trace!("poll_write: sent eof, closing");
                         self.state = State::Closing;
@@ -251,23 +407,61 @@ where
                         Poll::Pending => {
                             trace!("poll_write: waiting on copy_in stream");
                             self.pending_request = Some(RequestMessages::CopyIn(receiver));
-                            return Ok(true);
+                            return Ok(WriteStatus::Idle);
                         }
                     };
-                    Pin::new(&mut self.stream)
-                        .start_send(message)
-                        .map_err(Error::io)?;
+                    self.send(message);
                     self.pending_request = Some(RequestMessages::CopyIn(receiver));
                 }
+                RequestMessages::CopyBoth(mut receiver) => {
+                    let message = match receiver.poll_next_unpin(cx) {
+                        Poll::Ready(Some(message)) => message,
+                        Poll::Ready(None) => {
+                            trace!("poll_write: finished copy_both request");
+                            continue;
+                        }
+                        Poll::Pending => {
+                            trace!("poll_write: waiting on copy_both stream");
+                            self.pending_request = Some(RequestMessages::CopyBoth(receiver));
+                            return Ok(WriteStatus::Idle);
+                        }
+                    };
+                    self.send(message);
+                    self.pending_request = Some(RequestMessages::CopyBoth(receiver));
+                }
+                RequestMessages::ParamStream(mut receiver) => {
+                    let message = match receiver.poll_next_unpin(cx) {
+                        Poll::Ready(Some(message)) => message,
+                        Poll::Ready(None) => {
+                            trace!("poll_write: finished param_stream request");
+                            continue;
+                        }
+                        Poll::Pending => {
+                            trace!("poll_write: waiting on param_stream stream");
+                            self.pending_request = Some(RequestMessages::ParamStream(receiver));
+                            return Ok(WriteStatus::Idle);
+                        }
+                    };
+                    self.send(message);
+                    self.pending_request = Some(RequestMessages::ParamStream(receiver));
+                }
             }
         }
     }
 
+    fn send(&mut self, message: FrontendMessage) {
+        self.stream
+            .codec()
+            .encode_chunked(message, &mut self.write_buf);
+    }
+
+    fn poll_flush_buf(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.write_buf.poll_write(cx, self.stream.get_mut()))?;
+        Pin::new(self.stream.get_mut()).poll_flush(cx)
+    }
+
     fn poll_flush(&mut self, cx: &mut Context<'_>) -> Result<(), Error> {
-        match Pin::new(&mut self.stream)
-            .poll_flush(cx)
-            .map_err(Error::io)?
-        {
+        match self.poll_flush_buf(cx).map_err(Error::io)? {
             Poll::Ready(()) => trace!("poll_flush: flushed"),
             Poll::Pending => trace!("poll_flush: waiting on socket"),
         }
@@ -279,8 +473,9 @@ where
             return Poll::Pending;
         }
 
-        match Pin::new(&mut self.stream)
-            .poll_close(cx)
+        ready!(self.poll_flush_buf(cx)).map_err(Error::io)?;
+        match Pin::new(self.stream.get_mut())
+            .poll_shutdown(cx)
             .map_err(Error::io)?
         {
             Poll::Ready(()) => {
@@ -299,6 +494,18 @@ where
         self.parameters.get(name).map(|s| &**s)
     }
 
+    /// Returns a snapshot of the messages received from the server over the connection, by type.
+    ///
+    /// This is also available from `Client::message_stats` once the connection has been spawned.
+    pub fn message_stats(&self) -> MessageStats {
+        self.stream.codec().message_counter().snapshot()
+    }
+
+    /// Returns a handle which can be used to shut down the connection and observe why it closed.
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.handle()
+    }
+
     /// Polls for asynchronous messages from the server.
     ///
     /// The server can send notices as well as notifications asynchronously to the client. Applications that wish to
@@ -307,11 +514,88 @@ where
         &mut self,
         cx: &mut Context<'_>,
     ) -> Poll<Option<Result<AsyncMessage, Error>>> {
-        let message = self.poll_read(cx)?;
-        let want_flush = self.poll_write(cx)?;
-        if want_flush {
-            self.poll_flush(cx)?;
+        let result = self.poll_message_inner(cx);
+        match &result {
+            Poll::Ready(None) => {
+                self.notify_disconnected(None);
+                if self.shutdown.is_requested() {
+                    self.shutdown.closed(DisconnectCause::Shutdown);
+                } else {
+                    self.shutdown.closed(DisconnectCause::ClientDropped);
+                }
+            }
+            Poll::Ready(Some(Err(e))) => {
+                self.notify_disconnected(Some(e));
+                self.shutdown
+                    .closed(DisconnectCause::Error(Arc::new(e.duplicate())));
+            }
+            _ => {}
+        }
+        result
+    }
+
+    // Cancels the request the server is executing if it has been abandoned by the client.
+    fn poll_abandoned(&mut self, cx: &mut Context<'_>) {
+        let cancel_on_drop = match &self.cancel_on_drop {
+            Some(cancel_on_drop) => cancel_on_drop,
+            None => return,
+        };
+        self.abandon_waker.register(cx.waker());
+
+        if let Some(response) = self.responses.front_mut() {
+            if !response.canceled && response.is_abandoned() {
+                trace!("poll_abandoned: canceling abandoned request");
+                response.canceled = true;
+                if let (Some(cancel_token), None) = (&self.cancel_token, &self.cancel) {
+                    self.cancel = Some(cancel_on_drop(cancel_token.clone()));
+                }
+            }
+        }
+
+        if let Some(cancel) = &mut self.cancel {
+            if let Poll::Ready(result) = cancel.as_mut().poll(cx) {
+                if let Err(e) = result {
+                    warn!("error canceling abandoned query: {}", e);
+                }
+                self.cancel = None;
+            }
+        }
+    }
+
+    fn notify_disconnected(&mut self, error: Option<&Error>) {
+        if let Some(listener) = self.listener.take() {
+            listener.disconnected(error);
+        }
+    }
+
+    fn poll_message_inner(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<AsyncMessage, Error>>> {
+        self.shutdown.register(cx.waker());
+        if self.shutdown.is_requested() {
+            // requests which have already been made are still drained from the channel
+            self.receiver.close();
         }
+        // the read and write halves are driven alternately until neither can make progress, so that incoming messages
+        // are handled promptly even while a large amount of data is being sent
+        let message = loop {
+            let message = self.poll_read(cx)?;
+            match self.poll_write(cx)? {
+                WriteStatus::Idle => self.poll_flush(cx)?,
+                WriteStatus::Blocked => {}
+                WriteStatus::Yielded if message.is_some() => {
+                    // make sure we're polled again to pick up where the write half left off
+                    cx.waker().wake_by_ref();
+                }
+                WriteStatus::Yielded => continue,
+            }
+            break message;
+        };
+        if let Some(watchdog) = &mut self.watchdog {
+            watchdog.poll(cx, self.listener.as_ref());
+        }
+        self.poll_abandoned(cx);
         match message {
             Some(message) => Poll::Ready(Some(Ok(message))),
             None => match self.poll_shutdown(cx) {
@@ -323,6 +607,12 @@ where
     }
 }
 
+impl<S, T> Drop for Connection<S, T> {
+    fn drop(&mut self) {
+        self.shutdown.closed(DisconnectCause::Dropped);
+    }
+}
+
 impl<S, T> Future for Connection<S, T>
 where
     S: AsyncRead + AsyncWrite + Unpin,
@@ -0,0 +1,100 @@
+use crate::config::Host;
+use crate::{Error, WatchdogEvent};
+use postgres_core::AuthenticationMethod;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// A hook notified of the events in the lifecycle of a connection.
+///
+/// Listeners are registered with `Config::connection_listener`, and apply to every connection made with that
+/// configuration, allowing connection events to be logged or alerted on in one place. All methods have no-op default
+/// implementations, so implementors only need to override those they are interested in.
+///
+/// Methods are called from the task establishing or driving a connection, and should avoid blocking.
+#[allow(unused_variables)]
+pub trait ConnectionListener: Sync + Send {
+    /// Called when a TLS session has been negotiated with the server.
+    fn tls_established(&self) {}
+
+    /// Called when the server has accepted the credentials of a connection, with the method used to authenticate.
+    fn authenticated(&self, method: AuthenticationMethod) {}
+
+    /// Called when a connection has been established and is ready for queries.
+    fn connected(&self, event: &ConnectedEvent<'_>) {}
+
+    /// Called when an established connection closes, with the error that caused it to close, if any.
+    ///
+    /// A connection closes without an error once its `Client` has been dropped and all outstanding work has completed.
+    fn disconnected(&self, error: Option<&Error>) {}
+
+    /// Called when the watchdog registered with `Config::watchdog` detects a transaction or query exceeding its
+    /// thresholds.
+    fn watchdog_triggered(&self, event: &WatchdogEvent) {}
+}
+
+/// Information about a newly established connection, passed to `ConnectionListener::connected`.
+pub struct ConnectedEvent<'a> {
+    pub(crate) host: Option<&'a Host>,
+    pub(crate) port: Option<u16>,
+    pub(crate) process_id: i32,
+    pub(crate) authentication_method: AuthenticationMethod,
+    pub(crate) parameters: &'a HashMap<String, String>,
+}
+
+impl<'a> ConnectedEvent<'a> {
+    /// Returns the host the connection was made to.
+    ///
+    /// This is `None` for connections made over a caller-provided stream with `Config::connect_raw`.
+    pub fn host(&self) -> Option<&'a Host> {
+        self.host
+    }
+
+    /// Returns the port the connection was made to.
+    ///
+    /// This is `None` for connections made over a caller-provided stream with `Config::connect_raw`.
+    pub fn port(&self) -> Option<u16> {
+        self.port
+    }
+
+    /// Returns the process ID of the backend serving the connection.
+    pub fn process_id(&self) -> i32 {
+        self.process_id
+    }
+
+    /// Returns the method used to authenticate the connection.
+    pub fn authentication_method(&self) -> AuthenticationMethod {
+        self.authentication_method
+    }
+
+    /// Returns the version of the server, as reported by its `server_version` parameter.
+    pub fn server_version(&self) -> Option<&'a str> {
+        self.parameter("server_version")
+    }
+
+    /// Returns the value of a runtime parameter reported by the server when the connection was established.
+    pub fn parameter(&self, name: &str) -> Option<&'a str> {
+        self.parameters.get(name).map(|s| &**s)
+    }
+
+    /// Returns all of the runtime parameters reported by the server when the connection was established.
+    pub fn parameters(&self) -> &'a HashMap<String, String> {
+        self.parameters
+    }
+}
+
+// A wrapper allowing a listener to be stored in a `Config`, which is comparable and printable.
+#[derive(Clone)]
+pub(crate) struct ConfigConnectionListener(pub Arc<dyn ConnectionListener>);
+
+impl PartialEq for ConfigConnectionListener {
+    fn eq(&self, other: &ConfigConnectionListener) -> bool {
+        Arc::as_ptr(&self.0) as *const () == Arc::as_ptr(&other.0) as *const ()
+    }
+}
+
+impl fmt::Debug for ConfigConnectionListener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ConnectionListener")
+    }
+}
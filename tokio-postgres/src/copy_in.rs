@@ -205,17 +205,14 @@ where
 
     let (mut sender, receiver) = mpsc::channel(1);
     let receiver = CopyInReceiver::new(receiver);
-    let mut responses = client.send(RequestMessages::CopyIn(receiver))?;
+    let mut responses = client.send(RequestMessages::CopyIn(receiver)).await?;
 
     sender
         .send(CopyInMessage::Message(FrontendMessage::Raw(buf)))
         .await
         .map_err(|_| Error::closed())?;
 
-    match responses.next().await? {
-        Message::BindComplete => {}
-        _ => return Err(Error::unexpected_message()),
-    }
+    responses.bind_complete().await?;
 
     match responses.next().await? {
         Message::CopyInResponse(_) => {}
@@ -0,0 +1,221 @@
+use crate::escape::{escape_identifier, escape_literal};
+use std::fmt::Write;
+
+/// The data format of a `COPY` statement.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CopyFormat {
+    /// Tab-separated text, one row per line.
+    Text,
+    /// Comma-separated values.
+    Csv,
+    /// The PostgreSQL binary copy format, as handled by the `binary_copy` module.
+    Binary,
+}
+
+/// A builder for `COPY` statements.
+///
+/// Table and column names are quoted as identifiers and option values as literals, so the generated statements are
+/// safe to use with arbitrary names. The statement is not validated, so options which don't apply to the chosen
+/// direction or format are reported as errors by the server.
+///
+/// # Examples
+///
+/// ```
+/// use tokio_postgres::{CopyFormat, CopyOptions};
+///
+/// let query = CopyOptions::new("items")
+///     .columns(&["id", "name"])
+///     .format(CopyFormat::Csv)
+///     .header(true)
+///     .copy_in_query();
+/// assert_eq!(
+///     query,
+///     r#"COPY "items" ("id", "name") FROM STDIN (FORMAT csv, HEADER true)"#,
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct CopyOptions {
+    schema: Option<String>,
+    table: String,
+    columns: Vec<String>,
+    format: Option<CopyFormat>,
+    freeze: Option<bool>,
+    header: Option<bool>,
+    delimiter: Option<char>,
+    null: Option<String>,
+    quote: Option<char>,
+    escape: Option<char>,
+    force_quote: Vec<String>,
+}
+
+impl CopyOptions {
+    /// Creates a builder copying to or from the specified table.
+    pub fn new(table: &str) -> CopyOptions {
+        CopyOptions {
+            schema: None,
+            table: table.to_string(),
+            columns: vec![],
+            format: None,
+            freeze: None,
+            header: None,
+            delimiter: None,
+            null: None,
+            quote: None,
+            escape: None,
+            force_quote: vec![],
+        }
+    }
+
+    /// Sets the schema containing the table.
+    ///
+    /// If unset, the table is looked up in the search path.
+    pub fn schema(mut self, schema: &str) -> Self {
+        self.schema = Some(schema.to_string());
+        self
+    }
+
+    /// Sets the columns to copy.
+    ///
+    /// If unset, all columns of the table are copied.
+    pub fn columns(mut self, columns: &[&str]) -> Self {
+        self.columns = columns.iter().map(|c| c.to_string()).collect();
+        self
+    }
+
+    /// Sets the data format.
+    ///
+    /// Defaults to `CopyFormat::Text`.
+    pub fn format(mut self, format: CopyFormat) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Requests that copied rows be frozen, as if after a `VACUUM FREEZE`.
+    ///
+    /// Only applies when copying into a table created or truncated in the current transaction.
+    pub fn freeze(mut self, freeze: bool) -> Self {
+        self.freeze = Some(freeze);
+        self
+    }
+
+    /// Sets whether the data has a header line containing the column names.
+    ///
+    /// Not supported by the binary format.
+    pub fn header(mut self, header: bool) -> Self {
+        self.header = Some(header);
+        self
+    }
+
+    /// Sets the character separating columns within a row.
+    ///
+    /// Defaults to a tab in the text format and a comma in the CSV format.
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = Some(delimiter);
+        self
+    }
+
+    /// Sets the string representing a null value.
+    ///
+    /// Defaults to `\N` in the text format and an unquoted empty string in the CSV format.
+    pub fn null(mut self, null: &str) -> Self {
+        self.null = Some(null.to_string());
+        self
+    }
+
+    /// Sets the quoting character of the CSV format.
+    ///
+    /// Defaults to `"`.
+    pub fn quote(mut self, quote: char) -> Self {
+        self.quote = Some(quote);
+        self
+    }
+
+    /// Sets the character escaping the quoting character in the CSV format.
+    ///
+    /// Defaults to the quoting character.
+    pub fn escape(mut self, escape: char) -> Self {
+        self.escape = Some(escape);
+        self
+    }
+
+    /// Sets columns whose non-null values are always quoted when copying out in the CSV format.
+    pub fn force_quote(mut self, columns: &[&str]) -> Self {
+        self.force_quote = columns.iter().map(|c| c.to_string()).collect();
+        self
+    }
+
+    /// Returns a `COPY ... FROM STDIN` statement, for use with `Client::copy_in`.
+    pub fn copy_in_query(&self) -> String {
+        self.query("FROM STDIN")
+    }
+
+    /// Returns a `COPY ... TO STDOUT` statement, for use with `Client::copy_out`.
+    pub fn copy_out_query(&self) -> String {
+        self.query("TO STDOUT")
+    }
+
+    fn query(&self, direction: &str) -> String {
+        let mut query = "COPY ".to_string();
+        if let Some(schema) = &self.schema {
+            query.push_str(&escape_identifier(schema));
+            query.push('.');
+        }
+        query.push_str(&escape_identifier(&self.table));
+        if !self.columns.is_empty() {
+            let _ = write!(query, " ({})", identifier_list(&self.columns));
+        }
+        query.push(' ');
+        query.push_str(direction);
+
+        let mut options = vec![];
+        if let Some(format) = self.format {
+            let format = match format {
+                CopyFormat::Text => "text",
+                CopyFormat::Csv => "csv",
+                CopyFormat::Binary => "binary",
+            };
+            options.push(format!("FORMAT {}", format));
+        }
+        if let Some(freeze) = self.freeze {
+            options.push(format!("FREEZE {}", freeze));
+        }
+        if let Some(header) = self.header {
+            options.push(format!("HEADER {}", header));
+        }
+        if let Some(delimiter) = self.delimiter {
+            options.push(format!(
+                "DELIMITER {}",
+                escape_literal(&delimiter.to_string())
+            ));
+        }
+        if let Some(null) = &self.null {
+            options.push(format!("NULL {}", escape_literal(null)));
+        }
+        if let Some(quote) = self.quote {
+            options.push(format!("QUOTE {}", escape_literal(&quote.to_string())));
+        }
+        if let Some(escape) = self.escape {
+            options.push(format!("ESCAPE {}", escape_literal(&escape.to_string())));
+        }
+        if !self.force_quote.is_empty() {
+            options.push(format!(
+                "FORCE_QUOTE ({})",
+                identifier_list(&self.force_quote)
+            ));
+        }
+
+        if !options.is_empty() {
+            let _ = write!(query, " ({})", options.join(", "));
+        }
+        query
+    }
+}
+
+fn identifier_list(identifiers: &[String]) -> String {
+    identifiers
+        .iter()
+        .map(|i| escape_identifier(i))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -23,15 +23,15 @@ pub async fn copy_out(client: &InnerClient, statement: Statement) -> Result<Copy
 }
 
 async fn start(client: &InnerClient, buf: Bytes) -> Result<Responses, Error> {
-    let mut responses = client.send(RequestMessages::Single(FrontendMessage::Raw(buf)))?;
+    let mut responses = client
+        .send_query(RequestMessages::Single(FrontendMessage::Raw(buf)))
+        .await?;
 
-    match responses.next().await? {
-        Message::BindComplete => {}
-        _ => return Err(Error::unexpected_message()),
-    }
+    responses.bind_complete().await?;
 
     match responses.next().await? {
-        Message::CopyOutResponse(_) => {}
+        // an empty query produces no data, which the stream reports once it reaches the end of the response
+        Message::CopyOutResponse(_) | Message::EmptyQueryResponse => {}
         _ => return Err(Error::unexpected_message()),
     }
 
@@ -55,7 +55,7 @@ impl Stream for CopyOutStream {
 
         match ready!(this.responses.poll_next(cx)?) {
             Message::CopyData(body) => Poll::Ready(Some(Ok(body.into_bytes()))),
-            Message::CopyDone => Poll::Ready(None),
+            Message::CopyDone | Message::ReadyForQuery(_) => Poll::Ready(None),
             _ => Poll::Ready(Some(Err(Error::unexpected_message()))),
         }
     }
@@ -0,0 +1,130 @@
+use super::DbError;
+use std::ops::Range;
+
+/// The input row which caused a `COPY ... FROM STDIN` statement to fail.
+///
+/// This is parsed from the context of the server's error, allowing a loader to skip or fix the offending row and
+/// retry the copy without the overhead of a savepoint per row. The context is only recognized if the server reports
+/// errors in English (the `lc_messages` setting).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CopyError {
+    table: String,
+    line: u64,
+    column: Option<String>,
+    value: Option<String>,
+    text: Option<String>,
+}
+
+impl CopyError {
+    pub(crate) fn parse(error: &DbError) -> Option<CopyError> {
+        // the COPY context follows those of any triggers or functions called while inserting the row
+        let context = error
+            .where_()?
+            .lines()
+            .rev()
+            .find_map(|l| l.strip_prefix("COPY "))?;
+
+        let idx = context.find(", line ")?;
+        let table = context[..idx].to_string();
+        let rest = &context[idx + ", line ".len()..];
+        let end = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let line = rest[..end].parse().ok()?;
+        let rest = &rest[end..];
+
+        let mut copy_error = CopyError {
+            table,
+            line,
+            column: None,
+            value: None,
+            text: None,
+        };
+
+        if let Some(rest) = rest.strip_prefix(", column ") {
+            match rest.find(": ") {
+                Some(idx) if rest[idx..] == *": null input" => {
+                    copy_error.column = Some(rest[..idx].to_string())
+                }
+                Some(idx) if is_quoted(&rest[idx + 2..]) => {
+                    copy_error.column = Some(rest[..idx].to_string());
+                    copy_error.value = Some(unquote(&rest[idx + 2..]));
+                }
+                _ => copy_error.column = Some(rest.to_string()),
+            }
+        } else if let Some(rest) = rest.strip_prefix(": ") {
+            if !is_quoted(rest) {
+                return None;
+            }
+            copy_error.text = Some(unquote(rest));
+        } else if !rest.is_empty() {
+            return None;
+        }
+
+        Some(copy_error)
+    }
+
+    /// Returns the name of the table being copied into.
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+
+    /// Returns the one-based number of the input line which failed.
+    ///
+    /// In the text and CSV formats a header line is included in the count, and a CSV row with quoted newlines counts
+    /// as a single line. In the binary format, this is the number of the row.
+    pub fn line(&self) -> u64 {
+        self.line
+    }
+
+    /// Returns the name of the column which failed, if the error was associated with a single column.
+    pub fn column(&self) -> Option<&str> {
+        self.column.as_deref()
+    }
+
+    /// Returns the value of the column which failed in the text and CSV formats.
+    ///
+    /// This is `None` if the column's value was `NULL`. Long values are truncated by the server.
+    pub fn value(&self) -> Option<&str> {
+        self.value.as_deref()
+    }
+
+    /// Returns the contents of the line which failed in the text and CSV formats, if the error was not associated with
+    /// a single column.
+    ///
+    /// Long lines are truncated by the server.
+    pub fn text(&self) -> Option<&str> {
+        self.text.as_deref()
+    }
+
+    /// Returns the byte range of the line which failed within the text or CSV format data sent to the server,
+    /// including its line terminator.
+    ///
+    /// Lines are split on newlines, so this is only accurate if no values contain quoted newlines. Returns `None` if
+    /// the data has fewer lines.
+    pub fn line_range(&self, data: &[u8]) -> Option<Range<usize>> {
+        if self.line == 0 {
+            return None;
+        }
+        let mut start = 0;
+        for _ in 1..self.line {
+            start += data[start..].iter().position(|&b| b == b'\n')? + 1;
+        }
+        if start >= data.len() {
+            return None;
+        }
+        let end = data[start..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(data.len(), |i| start + i + 1);
+        Some(start..end)
+    }
+}
+
+fn is_quoted(s: &str) -> bool {
+    s.len() >= 2 && s.starts_with('"') && s.ends_with('"')
+}
+
+fn unquote(s: &str) -> String {
+    s[1..s.len() - 1].to_string()
+}
@@ -1,13 +1,18 @@
 //! Errors.
 
+use crate::types::{ToSql, Type};
+use crate::Statement;
 use fallible_iterator::FallibleIterator;
 use postgres_protocol::message::backend::{ErrorFields, ErrorResponseBody};
 use std::error::{self, Error as _Error};
 use std::fmt;
 use std::io;
+use std::sync::Arc;
 
+pub use self::copy::CopyError;
 pub use self::sqlstate::*;
 
+mod copy;
 #[allow(clippy::unreadable_literal)]
 mod sqlstate;
 
@@ -305,6 +310,11 @@ impl DbError {
     pub fn routine(&self) -> Option<&str> {
         self.routine.as_deref()
     }
+
+    /// Parses the input row which caused a `COPY ... FROM STDIN` statement to fail from the error's context.
+    pub fn copy_error(&self) -> Option<CopyError> {
+        CopyError::parse(self)
+    }
 }
 
 impl fmt::Display for DbError {
@@ -336,14 +346,16 @@ pub enum ErrorPosition {
     },
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 enum Kind {
     Io,
     UnexpectedMessage,
     Tls,
     ToSql(usize),
+    Parameters,
     FromSql(usize),
     Column(String),
+    NamedParameter(String),
     Closed,
     Db,
     Parse,
@@ -352,14 +364,54 @@ enum Kind {
     ConfigParse,
     Config,
     RowCount,
-    #[cfg(feature = "runtime")]
     Connect,
     Timeout,
+    ClientEncoding,
+    ReadOnly,
+}
+
+/// A hook used to render query parameters recorded in errors.
+///
+/// It is passed the type and value of each parameter, and can be used to redact sensitive values.
+///
+/// See `Client::set_error_param_renderer`.
+pub type ParamRenderer = dyn Fn(&Type, &dyn ToSql) -> String + Sync + Send;
+
+/// The query information recorded in an error.
+pub(crate) struct QueryContext {
+    pub(crate) query: QueryText,
+    pub(crate) params: Option<String>,
+}
+
+impl QueryContext {
+    fn query(&self) -> &str {
+        match &self.query {
+            QueryText::Statement(statement) => statement.query(),
+            QueryText::Unprepared(query) => query,
+        }
+    }
+}
+
+impl fmt::Debug for QueryContext {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("QueryContext")
+            .field("query", &self.query())
+            .field("params", &self.params)
+            .finish()
+    }
+}
+
+/// The text of a query recorded in an error.
+pub(crate) enum QueryText {
+    Statement(Statement),
+    // the query failed to prepare, so there is no statement holding its text
+    Unprepared(String),
 }
 
 struct ErrorInner {
     kind: Kind,
     cause: Option<Box<dyn error::Error + Sync + Send>>,
+    context: Option<Arc<QueryContext>>,
 }
 
 /// An error communicating with the Postgres server.
@@ -367,10 +419,12 @@ pub struct Error(Box<ErrorInner>);
 
 impl fmt::Debug for Error {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
-        fmt.debug_struct("Error")
-            .field("kind", &self.0.kind)
-            .field("cause", &self.0.cause)
-            .finish()
+        let mut s = fmt.debug_struct("Error");
+        s.field("kind", &self.0.kind).field("cause", &self.0.cause);
+        if let Some(context) = &self.0.context {
+            s.field("context", context);
+        }
+        s.finish()
     }
 }
 
@@ -381,8 +435,10 @@ impl fmt::Display for Error {
             Kind::UnexpectedMessage => fmt.write_str("unexpected message from server")?,
             Kind::Tls => fmt.write_str("error performing TLS handshake")?,
             Kind::ToSql(idx) => write!(fmt, "error serializing parameter {}", idx)?,
+            Kind::Parameters => fmt.write_str("invalid query parameters")?,
             Kind::FromSql(idx) => write!(fmt, "error deserializing column {}", idx)?,
             Kind::Column(column) => write!(fmt, "invalid column `{}`", column)?,
+            Kind::NamedParameter(name) => write!(fmt, "error binding parameter `:{}`", name)?,
             Kind::Closed => fmt.write_str("connection closed")?,
             Kind::Db => fmt.write_str("db error")?,
             Kind::Parse => fmt.write_str("error parsing response from server")?,
@@ -391,13 +447,20 @@ impl fmt::Display for Error {
             Kind::ConfigParse => fmt.write_str("invalid connection string")?,
             Kind::Config => fmt.write_str("invalid configuration")?,
             Kind::RowCount => fmt.write_str("query returned an unexpected number of rows")?,
-            #[cfg(feature = "runtime")]
             Kind::Connect => fmt.write_str("error connecting to server")?,
             Kind::Timeout => fmt.write_str("timeout waiting for server")?,
+            Kind::ClientEncoding => fmt.write_str("server uses an unexpected client encoding")?,
+            Kind::ReadOnly => fmt.write_str("statement rejected by a read-only client")?,
         };
         if let Some(ref cause) = self.0.cause {
             write!(fmt, ": {}", cause)?;
         }
+        if let Some(context) = &self.0.context {
+            write!(fmt, "\nQUERY: {}", context.query())?;
+            if let Some(params) = &context.params {
+                write!(fmt, "\nPARAMETERS: {}", params)?;
+            }
+        }
         Ok(())
     }
 }
@@ -426,6 +489,19 @@ impl Error {
         self.0.kind == Kind::Closed
     }
 
+    // Determines if the error indicates that the connection to the server was lost or couldn't be established.
+    pub(crate) fn is_connection_loss(&self) -> bool {
+        match self.0.kind {
+            Kind::Closed | Kind::Io | Kind::Connect => true,
+            // the server terminated the session, for example because it's shutting down
+            Kind::Db => matches!(
+                self.as_db_error().and_then(DbError::parsed_severity),
+                Some(Severity::Fatal) | Some(Severity::Panic)
+            ),
+            _ => false,
+        }
+    }
+
     /// Returns the SQLSTATE error code associated with the error.
     ///
     /// This is a convenience method that downcasts the cause to a `DbError` and returns its code.
@@ -433,8 +509,54 @@ impl Error {
         self.as_db_error().map(DbError::code)
     }
 
+    /// Returns the input row which caused a `COPY ... FROM STDIN` statement to fail.
+    ///
+    /// This is a convenience method that downcasts the cause to a `DbError` and parses its context.
+    pub fn copy_error(&self) -> Option<CopyError> {
+        self.as_db_error().and_then(DbError::copy_error)
+    }
+
+    /// Returns the text of the query which produced this error, if it was recorded.
+    ///
+    /// Queries are only recorded if enabled with `Client::set_query_in_errors`.
+    pub fn query(&self) -> Option<&str> {
+        self.0.context.as_ref().map(|c| c.query())
+    }
+
+    /// Returns a rendering of the parameters of the query which produced this error, if they were recorded.
+    ///
+    /// Parameters are only recorded if a renderer has been set with `Client::set_error_param_renderer`.
+    pub fn params(&self) -> Option<&str> {
+        self.0.context.as_ref().and_then(|c| c.params.as_deref())
+    }
+
+    // Creates a copy of the error so that it can be reported in more than one place. Causes other than database errors
+    // are only preserved as their descriptions.
+    pub(crate) fn duplicate(&self) -> Error {
+        let cause = match self.as_db_error() {
+            Some(e) => Some(Box::new(e.clone()) as _),
+            None => self.0.cause.as_ref().map(|e| e.to_string().into()),
+        };
+        Error(Box::new(ErrorInner {
+            kind: self.0.kind.clone(),
+            cause,
+            context: self.0.context.clone(),
+        }))
+    }
+
     fn new(kind: Kind, cause: Option<Box<dyn error::Error + Sync + Send>>) -> Error {
-        Error(Box::new(ErrorInner { kind, cause }))
+        Error(Box::new(ErrorInner {
+            kind,
+            cause,
+            context: None,
+        }))
+    }
+
+    pub(crate) fn with_context(mut self, context: Option<&Arc<QueryContext>>) -> Error {
+        if self.0.context.is_none() {
+            self.0.context = context.cloned();
+        }
+        self
     }
 
     pub(crate) fn closed() -> Error {
@@ -466,6 +588,10 @@ impl Error {
         Error::new(Kind::ToSql(idx), Some(e))
     }
 
+    pub(crate) fn parameters(e: Box<dyn error::Error + Sync + Send>) -> Error {
+        Error::new(Kind::Parameters, Some(e))
+    }
+
     pub(crate) fn from_sql(e: Box<dyn error::Error + Sync + Send>, idx: usize) -> Error {
         Error::new(Kind::FromSql(idx), Some(e))
     }
@@ -474,6 +600,10 @@ impl Error {
         Error::new(Kind::Column(column), None)
     }
 
+    pub(crate) fn named_parameter(name: &str, e: Box<dyn error::Error + Sync + Send>) -> Error {
+        Error::new(Kind::NamedParameter(name.to_string()), Some(e))
+    }
+
     pub(crate) fn tls(e: Box<dyn error::Error + Sync + Send>) -> Error {
         Error::new(Kind::Tls, Some(e))
     }
@@ -494,17 +624,31 @@ impl Error {
         Error::new(Kind::Config, Some(e))
     }
 
+    pub(crate) fn client_encoding(encoding: &str) -> Error {
+        Error::new(Kind::ClientEncoding, Some(encoding.into()))
+    }
+
     pub(crate) fn row_count() -> Error {
         Error::new(Kind::RowCount, None)
     }
 
-    #[cfg(feature = "runtime")]
     pub(crate) fn connect(e: io::Error) -> Error {
         Error::new(Kind::Connect, Some(Box::new(e)))
     }
 
+    pub(crate) fn timeout() -> Error {
+        Error::new(Kind::Timeout, None)
+    }
+
+    pub(crate) fn read_only(command: &str) -> Error {
+        Error::new(
+            Kind::ReadOnly,
+            Some(format!("{} statement", command).into()),
+        )
+    }
+
     #[doc(hidden)]
     pub fn __private_api_timeout() -> Error {
-        Error::new(Kind::Timeout, None)
+        Error::timeout()
     }
 }
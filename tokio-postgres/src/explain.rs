@@ -0,0 +1,271 @@
+use crate::types::ToSql;
+use crate::{Client, Error};
+use serde_json_1::{Map, Value};
+use std::fmt::Write;
+use std::time::Duration;
+
+/// Options for an `EXPLAIN` statement.
+///
+/// The plan is always requested in the JSON format, so that it can be returned as a [`QueryPlan`].
+#[derive(Debug, Clone)]
+pub struct ExplainOptions {
+    analyze: bool,
+    verbose: bool,
+    costs: bool,
+    buffers: bool,
+    timing: Option<bool>,
+}
+
+impl Default for ExplainOptions {
+    fn default() -> ExplainOptions {
+        ExplainOptions::new()
+    }
+}
+
+impl ExplainOptions {
+    /// Creates options requesting only the estimated plan.
+    pub fn new() -> ExplainOptions {
+        ExplainOptions {
+            analyze: false,
+            verbose: false,
+            costs: true,
+            buffers: false,
+            timing: None,
+        }
+    }
+
+    /// Sets whether the statement is actually executed, so that the plan includes actual row counts and run times.
+    ///
+    /// The statement's side effects are not undone, so statements which modify data should be explained within a
+    /// transaction which is rolled back afterwards.
+    ///
+    /// Defaults to `false`.
+    pub fn analyze(mut self, analyze: bool) -> ExplainOptions {
+        self.analyze = analyze;
+        self
+    }
+
+    /// Sets whether additional information, such as the output columns of each node, is included in the plan.
+    ///
+    /// Defaults to `false`.
+    pub fn verbose(mut self, verbose: bool) -> ExplainOptions {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Sets whether estimated costs and row counts are included in the plan.
+    ///
+    /// Defaults to `true`.
+    pub fn costs(mut self, costs: bool) -> ExplainOptions {
+        self.costs = costs;
+        self
+    }
+
+    /// Sets whether buffer usage is included in the plan.
+    ///
+    /// Defaults to `false`.
+    pub fn buffers(mut self, buffers: bool) -> ExplainOptions {
+        self.buffers = buffers;
+        self
+    }
+
+    /// Sets whether the actual time spent in each node is included in the plan, when it is analyzed.
+    ///
+    /// Defaults to the server's default.
+    pub fn timing(mut self, timing: bool) -> ExplainOptions {
+        self.timing = Some(timing);
+        self
+    }
+
+    fn query(&self, statement: &str) -> String {
+        let mut query = format!(
+            "EXPLAIN (FORMAT JSON, ANALYZE {}, VERBOSE {}, COSTS {}, BUFFERS {}",
+            self.analyze, self.verbose, self.costs, self.buffers
+        );
+        if let Some(timing) = self.timing {
+            let _ = write!(query, ", TIMING {}", timing);
+        }
+        let _ = write!(query, ") {}", statement);
+        query
+    }
+}
+
+/// The plan chosen by the server for a statement, as reported by `EXPLAIN`.
+#[derive(Debug, Clone)]
+pub struct QueryPlan {
+    root: PlanNode,
+    planning_time: Option<Duration>,
+    execution_time: Option<Duration>,
+    raw: Value,
+}
+
+impl QueryPlan {
+    fn parse(raw: Value) -> Result<QueryPlan, Error> {
+        let explain = raw
+            .as_array()
+            .and_then(|a| a.first())
+            .and_then(Value::as_object)
+            .ok_or_else(|| invalid_plan("expected an array of objects"))?;
+        let root = explain
+            .get("Plan")
+            .ok_or_else(|| invalid_plan("missing `Plan` field"))?;
+
+        Ok(QueryPlan {
+            root: PlanNode::parse(root)?,
+            planning_time: explain.get("Planning Time").and_then(millis),
+            execution_time: explain.get("Execution Time").and_then(millis),
+            raw,
+        })
+    }
+
+    /// Returns the root node of the plan.
+    pub fn root(&self) -> &PlanNode {
+        &self.root
+    }
+
+    /// Returns an iterator over all of the nodes of the plan, in depth-first order.
+    pub fn nodes(&self) -> PlanNodes<'_> {
+        self.root.nodes()
+    }
+
+    /// Returns the time spent planning the statement, if it was reported.
+    pub fn planning_time(&self) -> Option<Duration> {
+        self.planning_time
+    }
+
+    /// Returns the time spent executing the statement, if it was analyzed.
+    pub fn execution_time(&self) -> Option<Duration> {
+        self.execution_time
+    }
+
+    /// Returns the JSON output of `EXPLAIN`, as returned by the server.
+    pub fn raw(&self) -> &Value {
+        &self.raw
+    }
+}
+
+/// A node of a [`QueryPlan`].
+#[derive(Debug, Clone)]
+pub struct PlanNode {
+    properties: Map<String, Value>,
+    children: Vec<PlanNode>,
+}
+
+impl PlanNode {
+    fn parse(value: &Value) -> Result<PlanNode, Error> {
+        let mut properties = value
+            .as_object()
+            .cloned()
+            .ok_or_else(|| invalid_plan("expected a plan node object"))?;
+        if !properties.get("Node Type").map_or(false, Value::is_string) {
+            return Err(invalid_plan("missing `Node Type` field"));
+        }
+
+        let children = match properties.remove("Plans") {
+            Some(Value::Array(plans)) => plans
+                .iter()
+                .map(PlanNode::parse)
+                .collect::<Result<_, _>>()?,
+            Some(_) => return Err(invalid_plan("expected `Plans` to be an array")),
+            None => vec![],
+        };
+
+        Ok(PlanNode {
+            properties,
+            children,
+        })
+    }
+
+    /// Returns the type of the node, such as `Seq Scan` or `Index Only Scan`.
+    pub fn node_type(&self) -> &str {
+        self.get_str("Node Type").unwrap_or_default()
+    }
+
+    /// Returns the name of the relation scanned by the node, if any.
+    pub fn relation_name(&self) -> Option<&str> {
+        self.get_str("Relation Name")
+    }
+
+    /// Returns the name of the index used by the node, if any.
+    pub fn index_name(&self) -> Option<&str> {
+        self.get_str("Index Name")
+    }
+
+    /// Returns the estimated total cost of the node, if costs were requested.
+    pub fn total_cost(&self) -> Option<f64> {
+        self.get("Total Cost").and_then(Value::as_f64)
+    }
+
+    /// Returns the estimated number of rows produced by the node, if costs were requested.
+    pub fn plan_rows(&self) -> Option<f64> {
+        self.get("Plan Rows").and_then(Value::as_f64)
+    }
+
+    /// Returns the actual number of rows produced by the node per loop, if the statement was analyzed.
+    pub fn actual_rows(&self) -> Option<f64> {
+        self.get("Actual Rows").and_then(Value::as_f64)
+    }
+
+    /// Returns the value of a property of the node, by the name used in the JSON output of `EXPLAIN`.
+    pub fn get(&self, property: &str) -> Option<&Value> {
+        self.properties.get(property)
+    }
+
+    /// Returns all of the properties of the node other than its children.
+    pub fn properties(&self) -> &Map<String, Value> {
+        &self.properties
+    }
+
+    /// Returns the child nodes of the node.
+    pub fn children(&self) -> &[PlanNode] {
+        &self.children
+    }
+
+    /// Returns an iterator over this node and all of its descendants, in depth-first order.
+    pub fn nodes(&self) -> PlanNodes<'_> {
+        PlanNodes { stack: vec![self] }
+    }
+
+    fn get_str(&self, property: &str) -> Option<&str> {
+        self.get(property).and_then(Value::as_str)
+    }
+}
+
+/// An iterator over the nodes of a [`QueryPlan`].
+pub struct PlanNodes<'a> {
+    stack: Vec<&'a PlanNode>,
+}
+
+impl<'a> Iterator for PlanNodes<'a> {
+    type Item = &'a PlanNode;
+
+    fn next(&mut self) -> Option<&'a PlanNode> {
+        let node = self.stack.pop()?;
+        self.stack.extend(node.children.iter().rev());
+        Some(node)
+    }
+}
+
+pub(crate) async fn explain(
+    client: &Client,
+    statement: &str,
+    params: &[&(dyn ToSql + Sync)],
+    options: &ExplainOptions,
+) -> Result<QueryPlan, Error> {
+    let query = options.query(statement);
+    let raw = client
+        .query_one(query.as_str(), params)
+        .await?
+        .try_get::<_, Value>(0)?;
+    QueryPlan::parse(raw)
+}
+
+fn millis(value: &Value) -> Option<Duration> {
+    value
+        .as_f64()
+        .map(|millis| Duration::from_secs_f64(millis / 1000.))
+}
+
+fn invalid_plan(message: &str) -> Error {
+    Error::from_sql(format!("invalid query plan: {}", message).into(), 0)
+}
@@ -0,0 +1,82 @@
+use crate::client::InnerClient;
+use crate::codec::FrontendMessage;
+use crate::connection::RequestMessages;
+use crate::types::{FromSqlOwned, IsNull, Oid, ToSql, Type, WrongType};
+use crate::Error;
+use bytes::Bytes;
+use log::debug;
+use postgres_protocol::message::backend::Message;
+use postgres_protocol::message::frontend;
+
+pub async fn function_call<T>(
+    client: &InnerClient,
+    function: Oid,
+    params: &[(&(dyn ToSql + Sync), Type)],
+    result_type: &Type,
+) -> Result<T, Error>
+where
+    T: FromSqlOwned,
+{
+    if !T::accepts(result_type) {
+        return Err(Error::from_sql(
+            Box::new(WrongType::new::<T>(result_type.clone())),
+            0,
+        ));
+    }
+
+    let value = function_call_raw(client, function, params).await?;
+    T::from_sql_nullable(result_type, value.as_deref()).map_err(|e| Error::from_sql(e, 0))
+}
+
+async fn function_call_raw(
+    client: &InnerClient,
+    function: Oid,
+    params: &[(&(dyn ToSql + Sync), Type)],
+) -> Result<Option<Bytes>, Error> {
+    debug!("executing function call: {}", function);
+
+    let buf = encode(client, function, params)?;
+    let mut responses = client
+        .send(RequestMessages::Single(FrontendMessage::Raw(buf)))
+        .await?;
+
+    let value = match responses.next().await? {
+        Message::FunctionCallResponse(body) => body.into_value(),
+        _ => return Err(Error::unexpected_message()),
+    };
+
+    match responses.next().await? {
+        Message::ReadyForQuery(_) => Ok(value),
+        _ => Err(Error::unexpected_message()),
+    }
+}
+
+fn encode(
+    client: &InnerClient,
+    function: Oid,
+    params: &[(&(dyn ToSql + Sync), Type)],
+) -> Result<Bytes, Error> {
+    client.with_buf(|buf| {
+        let mut error_idx = 0;
+        let r = frontend::function_call(
+            function,
+            Some(1),
+            params.iter().enumerate(),
+            |(idx, (param, ty)), buf| match param.to_sql_checked(ty, buf) {
+                Ok(IsNull::No) => Ok(postgres_protocol::IsNull::No),
+                Ok(IsNull::Yes) => Ok(postgres_protocol::IsNull::Yes),
+                Err(e) => {
+                    error_idx = idx;
+                    Err(e)
+                }
+            },
+            1,
+            buf,
+        );
+        match r {
+            Ok(()) => Ok(buf.split().freeze()),
+            Err(frontend::BindError::Conversion(e)) => Err(Error::to_sql(e, error_idx)),
+            Err(frontend::BindError::Serialization(e)) => Err(Error::encode(e)),
+        }
+    })
+}
@@ -0,0 +1,524 @@
+//! Introspection of the objects defined in a database.
+//!
+//! The functions in this module query the system catalogs for the tables, columns, types, indexes and constraints of
+//! a schema, returning them as structs rather than rows. They work with any [`GenericClient`], so a consistent view of
+//! the schema can be obtained by calling them from within a single repeatable read transaction.
+//!
+//! Objects are identified by their unquoted names, which are matched exactly, as stored in the catalogs.
+
+use crate::types::Oid;
+use crate::{Error, GenericClient, Row};
+
+/// The kind of a relation returned by [`tables`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TableKind {
+    /// An ordinary table.
+    Table,
+    /// A partitioned table.
+    PartitionedTable,
+    /// A view.
+    View,
+    /// A materialized view.
+    MaterializedView,
+    /// A foreign table.
+    ForeignTable,
+}
+
+impl TableKind {
+    fn from_relkind(relkind: &str) -> Option<TableKind> {
+        match relkind {
+            "r" => Some(TableKind::Table),
+            "p" => Some(TableKind::PartitionedTable),
+            "v" => Some(TableKind::View),
+            "m" => Some(TableKind::MaterializedView),
+            "f" => Some(TableKind::ForeignTable),
+            _ => None,
+        }
+    }
+}
+
+/// A table or table-like relation.
+#[derive(Debug, Clone)]
+pub struct Table {
+    oid: Oid,
+    schema: String,
+    name: String,
+    kind: TableKind,
+    comment: Option<String>,
+}
+
+impl Table {
+    /// Returns the OID of the relation.
+    pub fn oid(&self) -> Oid {
+        self.oid
+    }
+
+    /// Returns the schema containing the relation.
+    pub fn schema(&self) -> &str {
+        &self.schema
+    }
+
+    /// Returns the name of the relation.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the kind of the relation.
+    pub fn kind(&self) -> TableKind {
+        self.kind
+    }
+
+    /// Returns the comment on the relation, if any.
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+}
+
+/// A column of a table.
+#[derive(Debug, Clone)]
+pub struct Column {
+    name: String,
+    position: i16,
+    type_oid: Oid,
+    type_name: String,
+    nullable: bool,
+    default: Option<String>,
+    identity: bool,
+    generated: bool,
+    comment: Option<String>,
+}
+
+impl Column {
+    /// Returns the name of the column.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the 1-based position of the column in the table.
+    pub fn position(&self) -> i16 {
+        self.position
+    }
+
+    /// Returns the OID of the column's type.
+    pub fn type_oid(&self) -> Oid {
+        self.type_oid
+    }
+
+    /// Returns the SQL name of the column's type, including any modifiers, such as `character varying(20)`.
+    pub fn type_name(&self) -> &str {
+        &self.type_name
+    }
+
+    /// Determines if the column may contain null values.
+    pub fn is_nullable(&self) -> bool {
+        self.nullable
+    }
+
+    /// Returns the expression of the column's default value, if any.
+    pub fn default(&self) -> Option<&str> {
+        self.default.as_deref()
+    }
+
+    /// Determines if the column is an identity column.
+    pub fn is_identity(&self) -> bool {
+        self.identity
+    }
+
+    /// Determines if the column is a generated column, in which case `default` returns its generation expression.
+    pub fn is_generated(&self) -> bool {
+        self.generated
+    }
+
+    /// Returns the comment on the column, if any.
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+}
+
+/// The kind of a type returned by [`types`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TypeKind {
+    /// A base type.
+    Base,
+    /// A composite type.
+    Composite,
+    /// A domain.
+    Domain,
+    /// An enum.
+    Enum,
+    /// A range type.
+    Range,
+    /// A multirange type.
+    Multirange,
+}
+
+impl TypeKind {
+    fn from_typtype(typtype: &str) -> Option<TypeKind> {
+        match typtype {
+            "b" => Some(TypeKind::Base),
+            "c" => Some(TypeKind::Composite),
+            "d" => Some(TypeKind::Domain),
+            "e" => Some(TypeKind::Enum),
+            "r" => Some(TypeKind::Range),
+            "m" => Some(TypeKind::Multirange),
+            _ => None,
+        }
+    }
+}
+
+/// A user-defined type.
+#[derive(Debug, Clone)]
+pub struct TypeInfo {
+    oid: Oid,
+    schema: String,
+    name: String,
+    kind: TypeKind,
+    enum_labels: Vec<String>,
+    comment: Option<String>,
+}
+
+impl TypeInfo {
+    /// Returns the OID of the type.
+    pub fn oid(&self) -> Oid {
+        self.oid
+    }
+
+    /// Returns the schema containing the type.
+    pub fn schema(&self) -> &str {
+        &self.schema
+    }
+
+    /// Returns the name of the type.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the kind of the type.
+    pub fn kind(&self) -> TypeKind {
+        self.kind
+    }
+
+    /// Returns the labels of an enum type, in their sort order.
+    ///
+    /// This is empty for other kinds of types.
+    pub fn enum_labels(&self) -> &[String] {
+        &self.enum_labels
+    }
+
+    /// Returns the comment on the type, if any.
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+}
+
+/// An index of a table.
+#[derive(Debug, Clone)]
+pub struct Index {
+    name: String,
+    columns: Vec<String>,
+    unique: bool,
+    primary: bool,
+    definition: String,
+}
+
+impl Index {
+    /// Returns the name of the index.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the names of the table columns covered by the index, in order.
+    ///
+    /// Expressions in the index are not included; the full definition is available from `definition`.
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// Determines if the index is unique.
+    pub fn is_unique(&self) -> bool {
+        self.unique
+    }
+
+    /// Determines if the index backs the table's primary key.
+    pub fn is_primary(&self) -> bool {
+        self.primary
+    }
+
+    /// Returns the `CREATE INDEX` statement defining the index.
+    pub fn definition(&self) -> &str {
+        &self.definition
+    }
+}
+
+/// The kind of a constraint returned by [`constraints`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConstraintKind {
+    /// A primary key constraint.
+    PrimaryKey,
+    /// A foreign key constraint.
+    ForeignKey,
+    /// A unique constraint.
+    Unique,
+    /// A check constraint.
+    Check,
+    /// An exclusion constraint.
+    Exclusion,
+    /// A constraint trigger.
+    Trigger,
+}
+
+impl ConstraintKind {
+    fn from_contype(contype: &str) -> Option<ConstraintKind> {
+        match contype {
+            "p" => Some(ConstraintKind::PrimaryKey),
+            "f" => Some(ConstraintKind::ForeignKey),
+            "u" => Some(ConstraintKind::Unique),
+            "c" => Some(ConstraintKind::Check),
+            "x" => Some(ConstraintKind::Exclusion),
+            "t" => Some(ConstraintKind::Trigger),
+            _ => None,
+        }
+    }
+}
+
+/// A constraint on a table.
+#[derive(Debug, Clone)]
+pub struct Constraint {
+    name: String,
+    kind: ConstraintKind,
+    columns: Vec<String>,
+    definition: String,
+    referenced_schema: Option<String>,
+    referenced_table: Option<String>,
+    referenced_columns: Vec<String>,
+}
+
+impl Constraint {
+    /// Returns the name of the constraint.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the kind of the constraint.
+    pub fn kind(&self) -> ConstraintKind {
+        self.kind
+    }
+
+    /// Returns the names of the columns constrained, in order.
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// Returns the definition of the constraint, such as `CHECK ((price > 0))`.
+    pub fn definition(&self) -> &str {
+        &self.definition
+    }
+
+    /// Returns the schema of the table referenced by a foreign key constraint.
+    pub fn referenced_schema(&self) -> Option<&str> {
+        self.referenced_schema.as_deref()
+    }
+
+    /// Returns the name of the table referenced by a foreign key constraint.
+    pub fn referenced_table(&self) -> Option<&str> {
+        self.referenced_table.as_deref()
+    }
+
+    /// Returns the names of the columns referenced by a foreign key constraint, in the order of `columns`.
+    pub fn referenced_columns(&self) -> &[String] {
+        &self.referenced_columns
+    }
+}
+
+const TABLES_QUERY: &str = "\
+SELECT c.oid, n.nspname::text, c.relname::text, c.relkind::text, pg_catalog.obj_description(c.oid, 'pg_class')
+FROM pg_catalog.pg_class c
+JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+WHERE n.nspname = $1 AND c.relkind IN ('r', 'p', 'v', 'm', 'f')
+ORDER BY c.relname
+";
+
+const COLUMNS_QUERY: &str = "\
+SELECT a.attname::text, a.attnum, a.atttypid, pg_catalog.format_type(a.atttypid, a.atttypmod), NOT a.attnotnull,
+    pg_catalog.pg_get_expr(d.adbin, d.adrelid), a.attidentity <> '', a.attgenerated <> '',
+    pg_catalog.col_description(a.attrelid, a.attnum)
+FROM pg_catalog.pg_attribute a
+JOIN pg_catalog.pg_class c ON c.oid = a.attrelid
+JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+LEFT JOIN pg_catalog.pg_attrdef d ON d.adrelid = a.attrelid AND d.adnum = a.attnum
+WHERE n.nspname = $1 AND c.relname = $2 AND a.attnum > 0 AND NOT a.attisdropped
+ORDER BY a.attnum
+";
+
+// array types and the row types of tables are created implicitly, so they are excluded
+const TYPES_QUERY: &str = "\
+SELECT t.oid, n.nspname::text, t.typname::text, t.typtype::text,
+    ARRAY(SELECT e.enumlabel::text FROM pg_catalog.pg_enum e WHERE e.enumtypid = t.oid ORDER BY e.enumsortorder),
+    pg_catalog.obj_description(t.oid, 'pg_type')
+FROM pg_catalog.pg_type t
+JOIN pg_catalog.pg_namespace n ON n.oid = t.typnamespace
+LEFT JOIN pg_catalog.pg_class c ON c.oid = t.typrelid
+WHERE n.nspname = $1
+    AND NOT EXISTS (SELECT 1 FROM pg_catalog.pg_type el WHERE el.oid = t.typelem AND el.typarray = t.oid)
+    AND (c.relkind IS NULL OR c.relkind = 'c')
+    AND t.typtype <> 'p'
+ORDER BY t.typname
+";
+
+const INDEXES_QUERY: &str = "\
+SELECT i.relname::text,
+    ARRAY(
+        SELECT a.attname::text
+        FROM unnest(x.indkey::int2[]) WITH ORDINALITY k(attnum, ord)
+        JOIN pg_catalog.pg_attribute a ON a.attrelid = x.indrelid AND a.attnum = k.attnum
+        ORDER BY k.ord
+    ),
+    x.indisunique, x.indisprimary, pg_catalog.pg_get_indexdef(x.indexrelid)
+FROM pg_catalog.pg_index x
+JOIN pg_catalog.pg_class i ON i.oid = x.indexrelid
+JOIN pg_catalog.pg_class c ON c.oid = x.indrelid
+JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+WHERE n.nspname = $1 AND c.relname = $2
+ORDER BY i.relname
+";
+
+const CONSTRAINTS_QUERY: &str = "\
+SELECT con.conname::text, con.contype::text,
+    ARRAY(
+        SELECT a.attname::text
+        FROM unnest(con.conkey) WITH ORDINALITY k(attnum, ord)
+        JOIN pg_catalog.pg_attribute a ON a.attrelid = con.conrelid AND a.attnum = k.attnum
+        ORDER BY k.ord
+    ),
+    pg_catalog.pg_get_constraintdef(con.oid), fn.nspname::text, fc.relname::text,
+    ARRAY(
+        SELECT a.attname::text
+        FROM unnest(con.confkey) WITH ORDINALITY k(attnum, ord)
+        JOIN pg_catalog.pg_attribute a ON a.attrelid = con.confrelid AND a.attnum = k.attnum
+        ORDER BY k.ord
+    )
+FROM pg_catalog.pg_constraint con
+JOIN pg_catalog.pg_class c ON c.oid = con.conrelid
+JOIN pg_catalog.pg_namespace n ON n.oid = c.relnamespace
+LEFT JOIN pg_catalog.pg_class fc ON fc.oid = con.confrelid
+LEFT JOIN pg_catalog.pg_namespace fn ON fn.oid = fc.relnamespace
+WHERE n.nspname = $1 AND c.relname = $2
+ORDER BY con.conname
+";
+
+/// Returns the tables, views, materialized views and foreign tables in a schema, ordered by name.
+pub async fn tables<C>(client: &C, schema: &str) -> Result<Vec<Table>, Error>
+where
+    C: GenericClient + Sync,
+{
+    let rows = client.query(TABLES_QUERY, &[&schema]).await?;
+    rows.iter()
+        .map(|row| {
+            Ok(Table {
+                oid: row.try_get(0)?,
+                schema: row.try_get(1)?,
+                name: row.try_get(2)?,
+                kind: TableKind::from_relkind(row.try_get(3)?).ok_or_else(|| unknown(row, 3))?,
+                comment: row.try_get(4)?,
+            })
+        })
+        .collect()
+}
+
+/// Returns the columns of a table, ordered by position.
+///
+/// An empty list is returned if the table doesn't exist.
+pub async fn columns<C>(client: &C, schema: &str, table: &str) -> Result<Vec<Column>, Error>
+where
+    C: GenericClient + Sync,
+{
+    let rows = client.query(COLUMNS_QUERY, &[&schema, &table]).await?;
+    rows.iter()
+        .map(|row| {
+            Ok(Column {
+                name: row.try_get(0)?,
+                position: row.try_get(1)?,
+                type_oid: row.try_get(2)?,
+                type_name: row.try_get(3)?,
+                nullable: row.try_get(4)?,
+                default: row.try_get(5)?,
+                identity: row.try_get(6)?,
+                generated: row.try_get(7)?,
+                comment: row.try_get(8)?,
+            })
+        })
+        .collect()
+}
+
+/// Returns the types defined in a schema, ordered by name.
+///
+/// The array types and table row types created implicitly by the server are not included.
+pub async fn types<C>(client: &C, schema: &str) -> Result<Vec<TypeInfo>, Error>
+where
+    C: GenericClient + Sync,
+{
+    let rows = client.query(TYPES_QUERY, &[&schema]).await?;
+    rows.iter()
+        .map(|row| {
+            Ok(TypeInfo {
+                oid: row.try_get(0)?,
+                schema: row.try_get(1)?,
+                name: row.try_get(2)?,
+                kind: TypeKind::from_typtype(row.try_get(3)?).ok_or_else(|| unknown(row, 3))?,
+                enum_labels: row.try_get(4)?,
+                comment: row.try_get(5)?,
+            })
+        })
+        .collect()
+}
+
+/// Returns the indexes of a table, ordered by name.
+pub async fn indexes<C>(client: &C, schema: &str, table: &str) -> Result<Vec<Index>, Error>
+where
+    C: GenericClient + Sync,
+{
+    let rows = client.query(INDEXES_QUERY, &[&schema, &table]).await?;
+    rows.iter()
+        .map(|row| {
+            Ok(Index {
+                name: row.try_get(0)?,
+                columns: row.try_get(1)?,
+                unique: row.try_get(2)?,
+                primary: row.try_get(3)?,
+                definition: row.try_get(4)?,
+            })
+        })
+        .collect()
+}
+
+/// Returns the constraints of a table, ordered by name.
+///
+/// `NOT NULL` constraints are reported by [`columns`] instead.
+pub async fn constraints<C>(client: &C, schema: &str, table: &str) -> Result<Vec<Constraint>, Error>
+where
+    C: GenericClient + Sync,
+{
+    let rows = client.query(CONSTRAINTS_QUERY, &[&schema, &table]).await?;
+    rows.iter()
+        .map(|row| {
+            Ok(Constraint {
+                name: row.try_get(0)?,
+                kind: ConstraintKind::from_contype(row.try_get(1)?)
+                    .ok_or_else(|| unknown(row, 1))?,
+                columns: row.try_get(2)?,
+                definition: row.try_get(3)?,
+                referenced_schema: row.try_get(4)?,
+                referenced_table: row.try_get(5)?,
+                referenced_columns: row.try_get(6)?,
+            })
+        })
+        .collect()
+}
+
+fn unknown(row: &Row, idx: usize) -> Error {
+    let value = row.get::<_, &str>(idx);
+    Error::from_sql(format!("unknown catalog value `{}`", value).into(), idx)
+}
@@ -0,0 +1,309 @@
+use crate::types::{Oid, Type};
+use crate::{Client, Error};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::ready;
+use std::cmp;
+use std::io::{self, SeekFrom};
+use std::mem;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+// the OIDs of the large object functions, which are fixed in the system catalogs
+const LO_CREATE: Oid = 715;
+const LO_OPEN: Oid = 952;
+const LO_CLOSE: Oid = 953;
+const LOREAD: Oid = 954;
+const LOWRITE: Oid = 955;
+const LO_UNLINK: Oid = 964;
+const LO_LSEEK64: Oid = 3170;
+const LO_TELL64: Oid = 3171;
+const LO_TRUNCATE64: Oid = 3172;
+
+const INV_WRITE: i32 = 0x0002_0000;
+const INV_READ: i32 = 0x0004_0000;
+
+const SEEK_SET: i32 = 0;
+const SEEK_CUR: i32 = 1;
+const SEEK_END: i32 = 2;
+
+/// The mode in which a large object is opened.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LargeObjectMode {
+    /// The object is opened for reading only.
+    ///
+    /// Reads will observe the contents of the object as of the transaction's snapshot.
+    Read,
+    /// The object is opened for writing only.
+    Write,
+    /// The object is opened for both reading and writing.
+    ReadWrite,
+}
+
+impl LargeObjectMode {
+    fn flags(self) -> i32 {
+        match self {
+            LargeObjectMode::Read => INV_READ,
+            LargeObjectMode::Write => INV_WRITE,
+            LargeObjectMode::ReadWrite => INV_READ | INV_WRITE,
+        }
+    }
+}
+
+enum State<'a> {
+    Idle,
+    Reading(BoxFuture<'a, Result<Vec<u8>, Error>>),
+    Writing(BoxFuture<'a, Result<usize, Error>>),
+}
+
+/// A handle to an open large object.
+///
+/// Large objects can only be accessed inside of a transaction, and the handle is closed when the transaction ends. The
+/// object's contents can be read and written through the `AsyncRead` and `AsyncWrite` implementations, each call of
+/// which corresponds to a single `loread` or `lowrite` fastpath function call on the server.
+///
+/// Reads fail while a write whose future was dropped before it completed is still pending, until it is finished with a
+/// flush or a seek.
+pub struct LargeObject<'a> {
+    client: &'a Client,
+    oid: Oid,
+    fd: i32,
+    state: State<'a>,
+    // data read from the server which didn't fit in the caller's buffer
+    buf: Bytes,
+}
+
+impl<'a> LargeObject<'a> {
+    /// Returns the OID of the object.
+    pub fn oid(&self) -> Oid {
+        self.oid
+    }
+
+    /// Moves the object's read/write position, returning the new position from the start of the object.
+    pub async fn seek(&mut self, pos: SeekFrom) -> Result<u64, Error> {
+        self.finish_pending().await?;
+        let (offset, whence) = match pos {
+            SeekFrom::Start(offset) => (offset as i64, SEEK_SET),
+            SeekFrom::Current(offset) => (offset - self.buf.len() as i64, SEEK_CUR),
+            SeekFrom::End(offset) => (offset, SEEK_END),
+        };
+        let pos = self
+            .client
+            .function_call::<i64>(
+                LO_LSEEK64,
+                &[
+                    (&self.fd, Type::INT4),
+                    (&offset, Type::INT8),
+                    (&whence, Type::INT4),
+                ],
+                &Type::INT8,
+            )
+            .await?;
+        self.buf.clear();
+        Ok(pos as u64)
+    }
+
+    /// Returns the object's current read/write position.
+    pub async fn tell(&mut self) -> Result<u64, Error> {
+        self.finish_pending().await?;
+        let pos = self
+            .client
+            .function_call::<i64>(LO_TELL64, &[(&self.fd, Type::INT4)], &Type::INT8)
+            .await?;
+        Ok(pos as u64 - self.buf.len() as u64)
+    }
+
+    /// Truncates or extends the object to the specified length.
+    ///
+    /// The object must have been opened for writing.
+    pub async fn truncate(&mut self, len: u64) -> Result<(), Error> {
+        self.finish_pending().await?;
+        self.client
+            .function_call::<i32>(
+                LO_TRUNCATE64,
+                &[(&self.fd, Type::INT4), (&(len as i64), Type::INT8)],
+                &Type::INT4,
+            )
+            .await
+            .map(|_| ())
+    }
+
+    /// Closes the handle.
+    ///
+    /// Handles are closed automatically when the transaction ends, so this only needs to be called to release the
+    /// server's resources early.
+    pub async fn close(self) -> Result<(), Error> {
+        self.client
+            .function_call::<i32>(LO_CLOSE, &[(&self.fd, Type::INT4)], &Type::INT4)
+            .await
+            .map(|_| ())
+    }
+
+    // A read or write future dropped before it completed leaves its call in flight, which moves the object's position
+    // on the server, so it has to be finished before the position is used.
+    async fn finish_pending(&mut self) -> Result<(), Error> {
+        match mem::replace(&mut self.state, State::Idle) {
+            State::Idle => {}
+            // the data is buffered so that positions account for it as having been read ahead
+            State::Reading(future) => self.buf = Bytes::from(future.await?),
+            State::Writing(future) => {
+                future.await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl AsyncRead for LargeObject<'_> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        loop {
+            match &mut this.state {
+                State::Idle if !this.buf.is_empty() => {
+                    let len = cmp::min(buf.remaining(), this.buf.len());
+                    buf.put_slice(&this.buf.split_to(len));
+                    return Poll::Ready(Ok(()));
+                }
+                State::Idle => {
+                    let client = this.client;
+                    let fd = this.fd;
+                    let len = cmp::min(buf.remaining(), i32::MAX as usize) as i32;
+                    this.state = State::Reading(Box::pin(async move {
+                        client
+                            .function_call(
+                                LOREAD,
+                                &[(&fd, Type::INT4), (&len, Type::INT4)],
+                                &Type::BYTEA,
+                            )
+                            .await
+                    }));
+                }
+                State::Reading(future) => {
+                    let result = ready!(future.as_mut().poll(cx));
+                    this.state = State::Idle;
+                    let data = result.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                    if data.is_empty() {
+                        return Poll::Ready(Ok(()));
+                    }
+                    this.buf = Bytes::from(data);
+                }
+                // completing the write here would lose the number of bytes written, which only `poll_write` can report
+                State::Writing(_) => {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::Other,
+                        "a write to the large object is still in progress",
+                    )));
+                }
+            }
+        }
+    }
+}
+
+impl AsyncWrite for LargeObject<'_> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        loop {
+            match &mut self.state {
+                State::Idle => {
+                    let client = self.client;
+                    let fd = self.fd;
+                    // the server's position is past any data we've read ahead, so move it back before writing
+                    let read_ahead = self.buf.len() as i64;
+                    self.buf.clear();
+                    let buf = buf[..cmp::min(buf.len(), i32::MAX as usize)].to_vec();
+                    self.state = State::Writing(Box::pin(async move {
+                        if read_ahead != 0 {
+                            client
+                                .function_call::<i64>(
+                                    LO_LSEEK64,
+                                    &[
+                                        (&fd, Type::INT4),
+                                        (&-read_ahead, Type::INT8),
+                                        (&SEEK_CUR, Type::INT4),
+                                    ],
+                                    &Type::INT8,
+                                )
+                                .await?;
+                        }
+                        client
+                            .function_call::<i32>(
+                                LOWRITE,
+                                &[(&fd, Type::INT4), (&buf, Type::BYTEA)],
+                                &Type::INT4,
+                            )
+                            .await
+                            .map(|n| n as usize)
+                    }));
+                }
+                State::Writing(future) => {
+                    let result = ready!(future.as_mut().poll(cx));
+                    self.state = State::Idle;
+                    return Poll::Ready(
+                        result.map_err(|e| io::Error::new(io::ErrorKind::Other, e)),
+                    );
+                }
+                State::Reading(future) => {
+                    let result = ready!(future.as_mut().poll(cx));
+                    self.state = State::Idle;
+                    let data = result.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                    self.buf = Bytes::from(data);
+                }
+            }
+        }
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if let State::Writing(future) = &mut self.state {
+            let result = ready!(future.as_mut().poll(cx));
+            self.state = State::Idle;
+            result.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+pub(crate) async fn create(client: &Client) -> Result<Oid, Error> {
+    client
+        .function_call(LO_CREATE, &[(&0u32, Type::OID)], &Type::OID)
+        .await
+}
+
+pub(crate) async fn open(
+    client: &Client,
+    oid: Oid,
+    mode: LargeObjectMode,
+) -> Result<LargeObject<'_>, Error> {
+    let fd = client
+        .function_call(
+            LO_OPEN,
+            &[(&oid, Type::OID), (&mode.flags(), Type::INT4)],
+            &Type::INT4,
+        )
+        .await?;
+    Ok(LargeObject {
+        client,
+        oid,
+        fd,
+        state: State::Idle,
+        buf: Bytes::new(),
+    })
+}
+
+pub(crate) async fn unlink(client: &Client, oid: Oid) -> Result<(), Error> {
+    client
+        .function_call::<i32>(LO_UNLINK, &[(&oid, Type::OID)], &Type::INT4)
+        .await
+        .map(|_| ())
+}
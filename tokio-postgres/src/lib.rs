@@ -87,8 +87,10 @@
 //! # Runtime
 //!
 //! The client works with arbitrary `AsyncRead + AsyncWrite` streams. Convenience APIs are provided to handle the
-//! connection process, but these are gated by the `runtime` Cargo feature, which is enabled by default. If disabled,
-//! all dependence on the tokio runtime is removed.
+//! connection process, which open sockets through the [`runtime::Runtime`] trait. The tokio runtime is used by default,
+//! and is gated by the `runtime` Cargo feature, which is enabled by default. If disabled, all dependence on the tokio
+//! runtime is removed, and the `runtime-async-std` or `runtime-smol` features can be enabled to connect using those
+//! runtimes instead.
 //!
 //! # SSL/TLS support
 //!
@@ -104,86 +106,173 @@
 //! | Feature | Description | Extra dependencies | Default |
 //! | ------- | ----------- | ------------------ | ------- |
 //! | `runtime` | Enable convenience API for the connection process based on the `tokio` crate. | [tokio](https://crates.io/crates/tokio) 1.0 with the features `net` and `time` | yes |
+//! | `runtime-async-std` | Enable the connection process on the `async-std` runtime. | [async-std](https://crates.io/crates/async-std) 1.6 | no |
+//! | `runtime-smol` | Enable the connection process on the `smol` runtime. | [smol](https://crates.io/crates/smol) 1.2 | no |
 //! | `with-bit-vec-0_6` | Enable support for the `bit-vec` crate. | [bit-vec](https://crates.io/crates/bit-vec) 0.6 | no |
 //! | `with-chrono-0_4` | Enable support for the `chrono` crate. | [chrono](https://crates.io/crates/chrono) 0.4 | no |
 //! | `with-eui48-0_4` | Enable support for the 0.4 version of the `eui48` crate. | [eui48](https://crates.io/crates/eui48) 0.4 | no |
 //! | `with-eui48-1` | Enable support for the 1.0 version of the `eui48` crate. | [eui48](https://crates.io/crates/eui48) 1.0 | no |
 //! | `with-geo-types-0_6` | Enable support for the 0.6 version of the `geo-types` crate. | [geo-types](https://crates.io/crates/geo-types/0.6.0) 0.6 | no |
 //! | `with-geo-types-0_7` | Enable support for the 0.7 version of the `geo-types` crate. | [geo-types](https://crates.io/crates/geo-types/0.7.0) 0.7 | no |
-//! | `with-serde_json-1` | Enable support for the `serde_json` crate. | [serde_json](https://crates.io/crates/serde_json) 1.0 | no |
+//! | `with-serde_json-1` | Enable support for the `serde_json` crate and `EXPLAIN` plans. | [serde_json](https://crates.io/crates/serde_json) 1.0 | no |
 //! | `with-uuid-0_8` | Enable support for the `uuid` crate. | [uuid](https://crates.io/crates/uuid) 0.8 | no |
 //! | `with-time-0_2` | Enable support for the 0.2 version of the `time` crate. | [time](https://crates.io/crates/time/0.2.0) 0.2 | no |
 //! | `with-time-0_3` | Enable support for the 0.3 version of the `time` crate. | [time](https://crates.io/crates/time/0.3.0) 0.3 | no |
+//! | `macros` | Enable the `query!` macro, which checks statements against a database at compile time, and `#[derive(FromRow)]`. | | no |
+//! | `test-harness` | Enable the [`testing`] module, which creates temporary databases for tests. | [tokio](https://crates.io/crates/tokio) 1.0 with the features `net`, `time` and `rt` | no |
+//! | `tracing` | Emit `tracing` spans for connections, statements and transactions, following the OpenTelemetry semantic conventions for database clients. | [tracing](https://crates.io/crates/tracing) 0.1 | no |
+//! | `wal2json` | Enable decoding of logical replication streams using the `wal2json` output plugin. | [serde](https://crates.io/crates/serde) 1.0, [serde_json](https://crates.io/crates/serde_json) 1.0 | no |
 #![doc(html_root_url = "https://docs.rs/tokio-postgres/0.7")]
 #![warn(rust_2018_idioms, clippy::all, missing_docs)]
 
+pub use crate::advisory_lock::{AdvisoryLockGuard, AdvisoryLockKey};
 pub use crate::cancel_token::CancelToken;
-pub use crate::client::Client;
+pub use crate::client::{Client, TransactionStatus};
+pub use crate::command_tag::{CommandKind, CommandTag};
 pub use crate::config::Config;
 pub use crate::connection::Connection;
+pub use crate::connection_listener::{ConnectedEvent, ConnectionListener};
+pub use crate::copy_both::CopyBothDuplex;
 pub use crate::copy_in::CopyInSink;
+pub use crate::copy_options::{CopyFormat, CopyOptions};
 pub use crate::copy_out::CopyOutStream;
 use crate::error::DbError;
 pub use crate::error::Error;
+#[cfg(feature = "with-serde_json-1")]
+pub use crate::explain::{ExplainOptions, PlanNode, PlanNodes, QueryPlan};
 pub use crate::generic_client::GenericClient;
+pub use crate::large_object::{LargeObject, LargeObjectMode};
+pub use crate::message_stats::{MessageCount, MessageStats, MessageType};
+pub use crate::metrics::Metrics;
+pub use crate::named_params::{NamedParams, NamedQuery};
+pub use crate::paginator::{PaginatedRowStream, Paginator};
 pub use crate::portal::Portal;
-pub use crate::query::RowStream;
+pub use crate::prepared_transaction::PreparedTransaction;
+pub use crate::query::{PrefetchRowStream, RowStream};
+pub use crate::query_builder::QueryBuilder;
+pub use crate::query_cache::{MemoryQueryCache, QueryCache, QueryCacheKey};
+pub use crate::query_logger::{render_param, QueryEvent, QueryLogger};
+pub use crate::read_only_client::ReadOnlyClient;
+pub use crate::reconnecting_client::{ReconnectPolicy, ReconnectingClient};
+pub use crate::ref_cursor::RefCursor;
+pub use crate::retry_policy::RetryPolicy;
 pub use crate::row::{Row, SimpleQueryRow};
-pub use crate::simple_query::SimpleQueryStream;
-#[cfg(feature = "runtime")]
+pub use crate::session_parameter::ParameterScope;
+pub use crate::shutdown::{DisconnectCause, ShutdownHandle};
+pub use crate::simple_query::{
+    SimpleColumn, SimpleQueryStream, SimpleResultSet, SimpleResultSetStream,
+};
 pub use crate::socket::Socket;
 pub use crate::statement::{Column, Statement};
-#[cfg(feature = "runtime")]
+#[cfg(any(
+    feature = "runtime",
+    feature = "runtime-async-std",
+    feature = "runtime-smol"
+))]
 use crate::tls::MakeTlsConnect;
 pub use crate::tls::NoTls;
+pub use crate::to_sql_stream::{BindParam, ToSqlStream};
 pub use crate::to_statement::ToStatement;
 pub use crate::transaction::Transaction;
 pub use crate::transaction_builder::{IsolationLevel, TransactionBuilder};
+pub use crate::typed_query::{FromRow, TypedQuery};
 use crate::types::ToSql;
+pub use crate::watchdog::{Watchdog, WatchdogEvent, WatchdogEventKind};
+pub use postgres_core::AuthenticationMethod;
+#[cfg(feature = "macros")]
+pub use postgres_macros::{query, FromRow};
+pub use postgres_protocol::escape;
 
+mod advisory_lock;
 pub mod binary_copy;
 mod bind;
-#[cfg(feature = "runtime")]
+mod bulk_upsert;
 mod cancel_query;
 mod cancel_query_raw;
 mod cancel_token;
 mod client;
 mod codec;
+mod command_tag;
 pub mod config;
-#[cfg(feature = "runtime")]
+#[cfg(any(
+    feature = "runtime",
+    feature = "runtime-async-std",
+    feature = "runtime-smol"
+))]
 mod connect;
 mod connect_raw;
-#[cfg(feature = "runtime")]
 mod connect_socket;
 mod connect_tls;
 mod connection;
+mod connection_listener;
+mod copy_both;
 mod copy_in;
+mod copy_options;
 mod copy_out;
 pub mod error;
+#[cfg(feature = "with-serde_json-1")]
+mod explain;
+mod function_call;
 mod generic_client;
+pub mod introspect;
+mod large_object;
+mod lsn;
 mod maybe_tls_stream;
+mod message_stats;
+mod metrics;
+pub mod mock;
+mod named_params;
+mod paginator;
 mod portal;
 mod prepare;
+mod prepared_transaction;
+mod procedure;
+mod protocol_trace;
 mod query;
+mod query_builder;
+mod query_cache;
+mod query_logger;
+mod read_only_client;
+mod reconnecting_client;
+mod ref_cursor;
+pub mod replication;
+mod retry_policy;
 pub mod row;
+mod row_text;
+pub mod runtime;
+mod session_parameter;
+mod shutdown;
 mod simple_query;
-#[cfg(feature = "runtime")]
 mod socket;
 mod statement;
+mod statement_cache;
+#[cfg(feature = "test-harness")]
+pub mod testing;
 pub mod tls;
+mod to_sql_stream;
 mod to_statement;
+mod trace;
 mod transaction;
 mod transaction_builder;
+mod typed_query;
 pub mod types;
+mod watchdog;
 
 /// A convenience function which parses a connection string and connects to the database.
 ///
 /// See the documentation for [`Config`] for details on the connection string format.
 ///
-/// Requires the `runtime` Cargo feature (enabled by default).
+/// The connection is opened using the default runtime selected by the enabled Cargo features. Use `Config::runtime`
+/// to connect with a different one; see the [`runtime`] module for details.
+///
+/// Requires one of the `runtime`, `runtime-async-std`, or `runtime-smol` Cargo features.
 ///
 /// [`Config`]: config/struct.Config.html
-#[cfg(feature = "runtime")]
+#[cfg(any(
+    feature = "runtime",
+    feature = "runtime-async-std",
+    feature = "runtime-smol"
+))]
 pub async fn connect<T>(
     config: &str,
     tls: T,
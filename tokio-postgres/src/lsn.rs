@@ -0,0 +1,52 @@
+use crate::types::PgLsn;
+use crate::{Client, Error};
+use std::time::{Duration, Instant};
+
+// The interval between checks of the replay position while waiting for a standby to catch up.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+pub(crate) async fn current_wal_insert_lsn(client: &Client) -> Result<PgLsn, Error> {
+    client
+        .query_one("SELECT pg_catalog.pg_current_wal_insert_lsn()", &[])
+        .await?
+        .try_get(0)
+}
+
+pub(crate) async fn last_wal_replay_lsn(client: &Client) -> Result<Option<PgLsn>, Error> {
+    client
+        .query_one("SELECT pg_catalog.pg_last_wal_replay_lsn()", &[])
+        .await?
+        .try_get(0)
+}
+
+pub(crate) async fn wait_for_lsn(
+    client: &Client,
+    lsn: PgLsn,
+    timeout: Duration,
+) -> Result<(), Error> {
+    let deadline = Instant::now() + timeout;
+    let statement = client
+        .prepare("SELECT pg_catalog.pg_last_wal_replay_lsn()")
+        .await?;
+
+    loop {
+        let replayed = client
+            .query_one(&statement, &[])
+            .await?
+            .try_get::<_, Option<PgLsn>>(0)?;
+        match replayed {
+            // the server isn't a standby, so everything it has committed is already visible
+            None => return Ok(()),
+            Some(replayed) if replayed >= lsn => return Ok(()),
+            Some(_) => {}
+        }
+
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(Error::timeout());
+        }
+        if let Some(runtime) = client.runtime() {
+            runtime.sleep(POLL_INTERVAL.min(deadline - now)).await;
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use parking_lot::Mutex;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// The type of a message sent by the server.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum MessageType {
+    /// An `Authentication` request or response.
+    Authentication,
+    /// `BackendKeyData`
+    BackendKeyData,
+    /// `BindComplete`
+    BindComplete,
+    /// `CloseComplete`
+    CloseComplete,
+    /// `CommandComplete`
+    CommandComplete,
+    /// `CopyData`
+    CopyData,
+    /// `CopyDone`
+    CopyDone,
+    /// `CopyInResponse`
+    CopyInResponse,
+    /// `CopyOutResponse`
+    CopyOutResponse,
+    /// `CopyBothResponse`
+    CopyBothResponse,
+    /// `DataRow`
+    DataRow,
+    /// `EmptyQueryResponse`
+    EmptyQueryResponse,
+    /// `ErrorResponse`
+    ErrorResponse,
+    /// `FunctionCallResponse`
+    FunctionCallResponse,
+    /// `NegotiateProtocolVersion`
+    NegotiateProtocolVersion,
+    /// `NoData`
+    NoData,
+    /// `NoticeResponse`
+    NoticeResponse,
+    /// `NotificationResponse`
+    NotificationResponse,
+    /// `ParameterDescription`
+    ParameterDescription,
+    /// `ParameterStatus`
+    ParameterStatus,
+    /// `ParseComplete`
+    ParseComplete,
+    /// `PortalSuspended`
+    PortalSuspended,
+    /// `ReadyForQuery`
+    ReadyForQuery,
+    /// `RowDescription`
+    RowDescription,
+    /// A message with an unrecognized tag byte.
+    Other(u8),
+}
+
+impl MessageType {
+    /// Returns the message type identified by a tag byte.
+    pub fn from_tag(tag: u8) -> MessageType {
+        match tag {
+            b'R' => MessageType::Authentication,
+            b'K' => MessageType::BackendKeyData,
+            b'2' => MessageType::BindComplete,
+            b'3' => MessageType::CloseComplete,
+            b'C' => MessageType::CommandComplete,
+            b'd' => MessageType::CopyData,
+            b'c' => MessageType::CopyDone,
+            b'G' => MessageType::CopyInResponse,
+            b'H' => MessageType::CopyOutResponse,
+            b'W' => MessageType::CopyBothResponse,
+            b'D' => MessageType::DataRow,
+            b'I' => MessageType::EmptyQueryResponse,
+            b'E' => MessageType::ErrorResponse,
+            b'V' => MessageType::FunctionCallResponse,
+            b'v' => MessageType::NegotiateProtocolVersion,
+            b'n' => MessageType::NoData,
+            b'N' => MessageType::NoticeResponse,
+            b'A' => MessageType::NotificationResponse,
+            b't' => MessageType::ParameterDescription,
+            b'S' => MessageType::ParameterStatus,
+            b'1' => MessageType::ParseComplete,
+            b's' => MessageType::PortalSuspended,
+            b'Z' => MessageType::ReadyForQuery,
+            b'T' => MessageType::RowDescription,
+            tag => MessageType::Other(tag),
+        }
+    }
+}
+
+impl fmt::Display for MessageType {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MessageType::Other(tag) => write!(fmt, "Unknown({:?})", *tag as char),
+            other => fmt::Debug::fmt(other, fmt),
+        }
+    }
+}
+
+/// The number and total size of the messages of a type received from the server.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct MessageCount {
+    messages: u64,
+    bytes: u64,
+}
+
+impl MessageCount {
+    /// Returns the number of messages received.
+    pub fn messages(&self) -> u64 {
+        self.messages
+    }
+
+    /// Returns the total size of the messages received in bytes, including their headers.
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+}
+
+/// A snapshot of the messages received from the server over a connection, by type.
+///
+/// Statistics are collected for every connection, and can be used to diagnose queries returning unexpectedly many
+/// rows, or floods of notices from the server. They are returned by `Client::message_stats` and
+/// `Connection::message_stats`, and count all messages read by the connection since it was established, including
+/// those of the startup handshake.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MessageStats {
+    counts: BTreeMap<MessageType, MessageCount>,
+}
+
+impl MessageStats {
+    /// Returns the messages received of a type.
+    pub fn get(&self, ty: MessageType) -> MessageCount {
+        self.counts.get(&ty).copied().unwrap_or_default()
+    }
+
+    /// Returns an iterator over the types of messages received, and their counts.
+    pub fn iter(&self) -> impl Iterator<Item = (MessageType, MessageCount)> + '_ {
+        self.counts.iter().map(|(ty, count)| (*ty, *count))
+    }
+
+    /// Returns the messages received of all types.
+    pub fn total(&self) -> MessageCount {
+        self.counts
+            .values()
+            .fold(MessageCount::default(), |total, count| MessageCount {
+                messages: total.messages + count.messages,
+                bytes: total.bytes + count.bytes,
+            })
+    }
+}
+
+/// The message statistics of a single connection, shared between its client and connection halves.
+#[derive(Clone, Default)]
+pub(crate) struct MessageCounter(Arc<Mutex<MessageStats>>);
+
+impl MessageCounter {
+    /// Counts the complete backend messages in a buffer.
+    pub fn count(&self, mut buf: &[u8]) {
+        let mut stats = self.0.lock();
+        while buf.len() >= 5 {
+            let len = u32::from_be_bytes([buf[1], buf[2], buf[3], buf[4]]) as usize + 1;
+            if buf.len() < len {
+                break;
+            }
+
+            let count = stats
+                .counts
+                .entry(MessageType::from_tag(buf[0]))
+                .or_default();
+            count.messages += 1;
+            count.bytes += len as u64;
+            buf = &buf[len..];
+        }
+    }
+
+    pub fn snapshot(&self) -> MessageStats {
+        self.0.lock().clone()
+    }
+}
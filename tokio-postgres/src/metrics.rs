@@ -0,0 +1,83 @@
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A hook reporting measurements of the health of a connection.
+///
+/// Metrics are registered with `Config::metrics`, and apply to every connection made with that configuration. All
+/// methods have no-op default implementations, so implementors only need to override those they are interested in.
+///
+/// Methods are called from the client and connection halves of a connection, potentially concurrently, and should
+/// avoid blocking.
+#[allow(unused_variables)]
+pub trait Metrics: Sync + Send {
+    /// Called when the startup handshake of a connection completes successfully.
+    ///
+    /// The duration includes TLS negotiation and authentication, but not the establishment of the underlying socket.
+    fn handshake_completed(&self, duration: Duration) {}
+
+    /// Called when a statement executes successfully, with the number of rows it returned or modified.
+    fn query_completed(&self, duration: Duration, rows: u64) {}
+
+    /// Called when a statement fails.
+    fn query_failed(&self, duration: Duration) {}
+
+    /// Called when bytes are written to the connection's send buffer.
+    fn bytes_written(&self, bytes: usize) {}
+
+    /// Called when bytes are read from the server.
+    fn bytes_read(&self, bytes: usize) {}
+
+    /// Called when the number of requests sent by the client but not yet picked up by the connection changes.
+    ///
+    /// A persistently large queue indicates that the `Connection` future is not being polled often enough, or that the
+    /// server can't keep up with the client.
+    fn queue_depth(&self, depth: usize) {}
+}
+
+// A wrapper allowing metrics to be stored in a `Config`, which is comparable and printable.
+#[derive(Clone)]
+pub(crate) struct ConfigMetrics(pub Arc<dyn Metrics>);
+
+impl PartialEq for ConfigMetrics {
+    fn eq(&self, other: &ConfigMetrics) -> bool {
+        Arc::as_ptr(&self.0) as *const () == Arc::as_ptr(&other.0) as *const ()
+    }
+}
+
+impl fmt::Debug for ConfigMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Metrics")
+    }
+}
+
+/// The metrics of a single connection, shared between its client and connection halves.
+#[derive(Clone)]
+pub(crate) struct ConnectionMetrics {
+    metrics: Arc<dyn Metrics>,
+    queue_depth: Arc<AtomicUsize>,
+}
+
+impl ConnectionMetrics {
+    pub fn new(metrics: Arc<dyn Metrics>) -> ConnectionMetrics {
+        ConnectionMetrics {
+            metrics,
+            queue_depth: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn get(&self) -> &Arc<dyn Metrics> {
+        &self.metrics
+    }
+
+    pub fn request_queued(&self) {
+        let depth = self.queue_depth.fetch_add(1, Ordering::Relaxed) + 1;
+        self.metrics.queue_depth(depth);
+    }
+
+    pub fn request_dequeued(&self) {
+        let depth = self.queue_depth.fetch_sub(1, Ordering::Relaxed) - 1;
+        self.metrics.queue_depth(depth);
+    }
+}
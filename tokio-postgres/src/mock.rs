@@ -0,0 +1,606 @@
+//! An in-memory mock server for testing code which uses a `Client`.
+//!
+//! A [`MockServer`] is scripted with the statements it should expect, in order, along with the rows or error each one
+//! responds with. [`MockServer::connect`] returns a real `Client` talking to the mock over an in-memory stream, so the
+//! code under test runs through the same protocol handling as it would against a database.
+//!
+//! ```no_run
+//! use tokio_postgres::mock::{Expectation, MockServer};
+//! use tokio_postgres::types::Type;
+//!
+//! # async fn f() -> Result<(), tokio_postgres::Error> {
+//! let mock = MockServer::new();
+//!
+//! let mut expectation = Expectation::new("SELECT name FROM users WHERE id = $1");
+//! expectation
+//!     .param_types(&[Type::INT4])
+//!     .params(&[&1i32])
+//!     .column("name", Type::TEXT)
+//!     .row(&[&"alice"]);
+//! mock.expect(expectation);
+//!
+//! let (client, connection) = mock.connect().await?;
+//! tokio::spawn(connection);
+//!
+//! let row = client
+//!     .query_one("SELECT name FROM users WHERE id = $1", &[&1i32])
+//!     .await?;
+//! assert_eq!(row.get::<_, &str>(0), "alice");
+//!
+//! mock.assert_done();
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Only types built into Postgres can be used for parameters and columns, since the client would otherwise look up
+//! their definitions with queries of its own. Transaction control statements such as `BEGIN` are sent as simple
+//! queries, and need to be expected like any other.
+use crate::error::SqlState;
+use crate::tls::NoTlsStream;
+use crate::types::{IsNull, Oid, ToSql, Type};
+use crate::{Client, Config, Connection, Error, NoTls};
+use bytes::BytesMut;
+use fallible_iterator::FallibleIterator;
+use futures::future;
+use parking_lot::Mutex;
+use postgres_protocol::message::backend::{self, Field};
+use postgres_protocol::message::frontend::{InitialMessage, Message};
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+
+/// A statement expected by a `MockServer`, along with its response.
+#[derive(Debug, Clone)]
+pub struct Expectation {
+    query: String,
+    param_types: Vec<Type>,
+    params: Option<Vec<Option<Vec<u8>>>>,
+    columns: Vec<(String, Type)>,
+    rows: Vec<Vec<Option<Vec<u8>>>>,
+    text_rows: Vec<Vec<Option<String>>>,
+    rows_affected: u64,
+    tag: Option<String>,
+    error: Option<(SqlState, String)>,
+}
+
+impl Expectation {
+    /// Creates a new expectation of a statement.
+    ///
+    /// Statements are matched against the query text, ignoring differences in whitespace.
+    pub fn new(query: &str) -> Expectation {
+        Expectation {
+            query: query.to_string(),
+            param_types: vec![],
+            params: None,
+            columns: vec![],
+            rows: vec![],
+            text_rows: vec![],
+            rows_affected: 0,
+            tag: None,
+            error: None,
+        }
+    }
+
+    /// Sets the types of the statement's parameters, reported to the client when it prepares the statement.
+    ///
+    /// Types explicitly specified by the client when preparing the statement take precedence.
+    pub fn param_types(&mut self, param_types: &[Type]) -> &mut Expectation {
+        self.param_types = param_types.to_vec();
+        self
+    }
+
+    /// Sets the parameter values the statement is expected to be executed with.
+    ///
+    /// By default, the statement may be executed with any values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of values does not match the parameter types, or if a value can't be encoded as its type.
+    pub fn params(&mut self, params: &[&(dyn ToSql + Sync)]) -> &mut Expectation {
+        self.params = Some(encode(params, &self.param_types));
+        self
+    }
+
+    /// Adds a column to the statement's results.
+    pub fn column(&mut self, name: &str, type_: Type) -> &mut Expectation {
+        self.columns.push((name.to_string(), type_));
+        self
+    }
+
+    /// Adds a row returned when the statement is executed with the extended query protocol, as the `query` family of
+    /// methods does.
+    ///
+    /// The columns must be added first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of values does not match the columns, or if a value can't be encoded as its column's
+    /// type.
+    pub fn row(&mut self, values: &[&(dyn ToSql + Sync)]) -> &mut Expectation {
+        let types = self
+            .columns
+            .iter()
+            .map(|(_, type_)| type_.clone())
+            .collect::<Vec<_>>();
+        self.rows.push(encode(values, &types));
+        self
+    }
+
+    /// Adds a row returned when the statement is executed with the simple query protocol, as `simple_query` does.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of values does not match the columns.
+    pub fn text_row(&mut self, values: &[Option<&str>]) -> &mut Expectation {
+        assert_eq!(
+            values.len(),
+            self.columns.len(),
+            "expected {} values",
+            self.columns.len()
+        );
+        self.text_rows
+            .push(values.iter().map(|v| v.map(str::to_string)).collect());
+        self
+    }
+
+    /// Sets the number of rows reported as modified by the statement.
+    ///
+    /// Defaults to 0.
+    pub fn rows_affected(&mut self, rows_affected: u64) -> &mut Expectation {
+        self.rows_affected = rows_affected;
+        self
+    }
+
+    /// Sets the command tag reported when the statement completes.
+    ///
+    /// By default, the tag is derived from the statement's first keyword and the number of rows it returned or
+    /// affected.
+    pub fn tag(&mut self, tag: &str) -> &mut Expectation {
+        self.tag = Some(tag.to_string());
+        self
+    }
+
+    /// Makes the statement fail with an error rather than returning rows.
+    pub fn error(&mut self, code: SqlState, message: &str) -> &mut Expectation {
+        self.error = Some((code, message.to_string()));
+        self
+    }
+
+    fn matches(&self, query: &str) -> bool {
+        self.query.split_whitespace().eq(query.split_whitespace())
+    }
+
+    fn command_tag(&self, rows: usize) -> String {
+        if let Some(tag) = &self.tag {
+            return tag.clone();
+        }
+
+        if !self.columns.is_empty() {
+            return format!("SELECT {}", rows);
+        }
+
+        let command = self
+            .query
+            .split_whitespace()
+            .next()
+            .unwrap_or("")
+            .to_ascii_uppercase();
+        match &*command {
+            "INSERT" => format!("INSERT 0 {}", self.rows_affected),
+            "UPDATE" | "DELETE" | "MERGE" | "COPY" | "FETCH" | "MOVE" => {
+                format!("{} {}", command, self.rows_affected)
+            }
+            _ => command,
+        }
+    }
+}
+
+fn encode(values: &[&(dyn ToSql + Sync)], types: &[Type]) -> Vec<Option<Vec<u8>>> {
+    assert_eq!(values.len(), types.len(), "expected {} values", types.len());
+
+    values
+        .iter()
+        .zip(types)
+        .map(|(value, type_)| {
+            let mut buf = BytesMut::new();
+            match value.to_sql_checked(type_, &mut buf) {
+                Ok(IsNull::No) => Some(buf.to_vec()),
+                Ok(IsNull::Yes) => None,
+                Err(e) => panic!("error encoding value of type {}: {}", type_, e),
+            }
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct State {
+    expectations: VecDeque<Expectation>,
+    failures: Vec<String>,
+}
+
+/// An in-memory mock of a Postgres server.
+///
+/// Clones of the server share the same expectations, so a clone can be kept to check them once the server is
+/// connected.
+#[derive(Clone, Default)]
+pub struct MockServer {
+    state: Arc<Mutex<State>>,
+}
+
+impl MockServer {
+    /// Creates a new server with no expectations.
+    pub fn new() -> MockServer {
+        MockServer::default()
+    }
+
+    /// Adds a statement to the end of those the server expects.
+    ///
+    /// Statements must be executed in the order they were expected in. A statement which does not match the next
+    /// expectation fails with an error, and is reported by `assert_done`.
+    pub fn expect(&self, expectation: Expectation) {
+        self.state.lock().expectations.push_back(expectation);
+    }
+
+    /// Returns the number of expected statements which have not been executed yet.
+    pub fn remaining(&self) -> usize {
+        self.state.lock().expectations.len()
+    }
+
+    /// Asserts that every expected statement was executed, and that nothing unexpected was.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either is not the case.
+    pub fn assert_done(&self) {
+        let state = self.state.lock();
+        assert!(
+            state.failures.is_empty(),
+            "mock server received unexpected requests: {:?}",
+            state.failures
+        );
+        let remaining = state
+            .expectations
+            .iter()
+            .map(|e| &*e.query)
+            .collect::<Vec<_>>();
+        assert!(
+            remaining.is_empty(),
+            "mock server expected statements which were not executed: {:?}",
+            remaining
+        );
+    }
+
+    /// Connects a new client to the server.
+    ///
+    /// Like a `Connection`, the returned `MockConnection` performs the actual IO, and should generally be spawned off
+    /// onto an executor.
+    pub async fn connect(&self) -> Result<(Client, MockConnection), Error> {
+        let (client_stream, server_stream) = tokio::io::duplex(8 * 1024);
+        let mut server: Option<Pin<Box<dyn Future<Output = io::Result<()>> + Send>>> =
+            Some(Box::pin(serve(server_stream, self.state.clone())));
+
+        let mut config = Config::new();
+        config.user("postgres");
+        let connect = config.connect_raw(client_stream, NoTls);
+        futures::pin_mut!(connect);
+
+        let (client, connection) = future::poll_fn(|cx| {
+            poll_server(&mut server, cx);
+            connect.as_mut().poll(cx)
+        })
+        .await?;
+
+        Ok((client, MockConnection { connection, server }))
+    }
+}
+
+fn poll_server(
+    server: &mut Option<Pin<Box<dyn Future<Output = io::Result<()>> + Send>>>,
+    cx: &mut Context<'_>,
+) {
+    if let Some(f) = server {
+        // the server closes its end of the stream on exit, which the client will notice
+        if f.as_mut().poll(cx).is_ready() {
+            *server = None;
+        }
+    }
+}
+
+/// A connection to a `MockServer`.
+///
+/// The future drives both the client's connection and the server, and resolves when the client's connection closes.
+#[must_use = "futures do nothing unless polled"]
+pub struct MockConnection {
+    connection: Connection<DuplexStream, NoTlsStream>,
+    server: Option<Pin<Box<dyn Future<Output = io::Result<()>> + Send>>>,
+}
+
+impl Future for MockConnection {
+    type Output = Result<(), Error>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        poll_server(&mut self.server, cx);
+        Pin::new(&mut self.connection).poll(cx)
+    }
+}
+
+async fn serve(mut stream: DuplexStream, state: Arc<Mutex<State>>) -> io::Result<()> {
+    let mut buf = BytesMut::new();
+    let mut out = BytesMut::new();
+
+    loop {
+        match InitialMessage::parse(&mut buf)? {
+            Some(InitialMessage::Startup(_)) => break,
+            Some(InitialMessage::SslRequest) | Some(InitialMessage::GssEncRequest) => {
+                stream.write_all(b"N").await?
+            }
+            // there is nothing to cancel
+            Some(_) => return Ok(()),
+            None => {
+                if stream.read_buf(&mut buf).await? == 0 {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    backend::authentication_ok(&mut out);
+    backend::parameter_status("server_version", "14.0", &mut out)?;
+    backend::parameter_status("client_encoding", "UTF8", &mut out)?;
+    backend::parameter_status("DateStyle", "ISO, MDY", &mut out)?;
+    backend::parameter_status("integer_datetimes", "on", &mut out)?;
+    backend::backend_key_data(1, 0, &mut out);
+    backend::ready_for_query(b'I', &mut out);
+
+    let mut session = Session {
+        state,
+        statements: HashMap::new(),
+        portals: HashMap::new(),
+        failed: false,
+    };
+
+    loop {
+        while let Some(message) = Message::parse(&mut buf)? {
+            if !session.handle(message, &mut out)? {
+                return stream.write_all(&out).await;
+            }
+        }
+
+        if !out.is_empty() {
+            stream.write_all(&out).await?;
+            out.clear();
+        }
+
+        if stream.read_buf(&mut buf).await? == 0 {
+            return Ok(());
+        }
+    }
+}
+
+struct Prepared {
+    query: String,
+    param_types: Vec<Oid>,
+    columns: Vec<(String, Type)>,
+}
+
+struct Portal {
+    query: String,
+    params: Vec<Option<Vec<u8>>>,
+    columns: Vec<(String, Type)>,
+}
+
+struct Session {
+    state: Arc<Mutex<State>>,
+    statements: HashMap<String, Prepared>,
+    portals: HashMap<String, Portal>,
+    // set after an error in the extended query protocol, until the next Sync
+    failed: bool,
+}
+
+impl Session {
+    // returns false once the client terminates the session
+    fn handle(&mut self, message: Message, out: &mut BytesMut) -> io::Result<bool> {
+        match message {
+            Message::Query(body) => {
+                self.simple_query(body.query()?, out)?;
+                backend::ready_for_query(b'I', out);
+            }
+            Message::Sync => {
+                self.failed = false;
+                backend::ready_for_query(b'I', out);
+            }
+            Message::Flush => {}
+            Message::Terminate => return Ok(false),
+            _ if self.failed => {}
+            Message::Parse(body) => {
+                let query = body.query()?;
+                let expectation = self
+                    .state
+                    .lock()
+                    .expectations
+                    .iter()
+                    .find(|e| e.matches(query))
+                    .map(|e| (e.param_types.clone(), e.columns.clone()));
+                let (expected_types, columns) = match expectation {
+                    Some(expectation) => expectation,
+                    None => return self.fail(format!("unexpected query `{}`", query), out),
+                };
+
+                let mut param_types = body.parameter_types().collect::<Vec<_>>()?;
+                for (i, type_) in expected_types.iter().enumerate() {
+                    match param_types.get_mut(i) {
+                        Some(oid @ 0) => *oid = type_.oid(),
+                        Some(_) => {}
+                        None => param_types.push(type_.oid()),
+                    }
+                }
+
+                self.statements.insert(
+                    body.name()?.to_string(),
+                    Prepared {
+                        query: query.to_string(),
+                        param_types,
+                        columns,
+                    },
+                );
+                backend::parse_complete(out);
+            }
+            Message::Describe(body) => {
+                let name = body.name()?;
+                let columns = if body.variant() == b'S' {
+                    let statement = match self.statements.get(name) {
+                        Some(statement) => statement,
+                        None => return self.fail(format!("unknown statement `{}`", name), out),
+                    };
+                    backend::parameter_description(statement.param_types.iter().copied(), out)?;
+                    &statement.columns
+                } else {
+                    match self.portals.get(name) {
+                        Some(portal) => &portal.columns,
+                        None => return self.fail(format!("unknown portal `{}`", name), out),
+                    }
+                };
+                let format = if body.variant() == b'S' { 0 } else { 1 };
+                write_row_description(columns, format, out)?;
+            }
+            Message::Bind(body) => {
+                let name = body.statement()?;
+                let statement = match self.statements.get(name) {
+                    Some(statement) => statement,
+                    None => return self.fail(format!("unknown statement `{}`", name), out),
+                };
+                let params = body.values().map(|v| Ok(v.map(|v| v.to_vec()))).collect()?;
+
+                let portal = Portal {
+                    query: statement.query.clone(),
+                    params,
+                    columns: statement.columns.clone(),
+                };
+                self.portals.insert(body.portal()?.to_string(), portal);
+                backend::bind_complete(out);
+            }
+            Message::Execute(body) => {
+                let name = body.portal()?;
+                let portal = match self.portals.get(name) {
+                    Some(portal) => portal,
+                    None => return self.fail(format!("unknown portal `{}`", name), out),
+                };
+                let expectation = match self.next_expectation(&portal.query) {
+                    Ok(expectation) => expectation,
+                    Err(e) => return self.fail(e, out),
+                };
+                if let Some(params) = &expectation.params {
+                    if *params != portal.params {
+                        let message =
+                            format!("unexpected parameters for query `{}`", expectation.query);
+                        return self.fail(message, out);
+                    }
+                }
+
+                if let Some((code, message)) = &expectation.error {
+                    self.failed = true;
+                    write_error(code, message, out)?;
+                } else {
+                    for row in &expectation.rows {
+                        backend::data_row(row.iter().map(Option::as_ref), out)?;
+                    }
+                    backend::command_complete(
+                        &expectation.command_tag(expectation.rows.len()),
+                        out,
+                    )?;
+                }
+            }
+            Message::Close(body) => {
+                let name = body.name()?;
+                if body.variant() == b'S' {
+                    self.statements.remove(name);
+                } else {
+                    self.portals.remove(name);
+                }
+                backend::close_complete(out);
+            }
+            _ => return self.fail("unsupported message".to_string(), out),
+        }
+
+        Ok(true)
+    }
+
+    fn simple_query(&mut self, query: &str, out: &mut BytesMut) -> io::Result<()> {
+        let expectation = match self.next_expectation(query) {
+            Ok(expectation) => expectation,
+            Err(e) => {
+                self.record_failure(&e);
+                return write_error(&SqlState::INTERNAL_ERROR, &e, out);
+            }
+        };
+
+        if let Some((code, message)) = &expectation.error {
+            return write_error(code, message, out);
+        }
+
+        if !expectation.columns.is_empty() {
+            write_row_description(&expectation.columns, 0, out)?;
+        }
+        for row in &expectation.text_rows {
+            backend::data_row(row.iter().map(|v| v.as_ref().map(String::as_bytes)), out)?;
+        }
+        backend::command_complete(&expectation.command_tag(expectation.text_rows.len()), out)
+    }
+
+    fn next_expectation(&self, query: &str) -> Result<Expectation, String> {
+        let mut state = self.state.lock();
+        match state.expectations.front() {
+            Some(expectation) if expectation.matches(query) => {
+                Ok(state.expectations.pop_front().unwrap())
+            }
+            Some(expectation) => Err(format!(
+                "expected query `{}` but received `{}`",
+                expectation.query, query
+            )),
+            None => Err(format!("unexpected query `{}`", query)),
+        }
+    }
+
+    fn record_failure(&self, message: &str) {
+        self.state.lock().failures.push(message.to_string());
+    }
+
+    fn fail(&mut self, message: String, out: &mut BytesMut) -> io::Result<bool> {
+        self.record_failure(&message);
+        self.failed = true;
+        write_error(&SqlState::INTERNAL_ERROR, &message, out)?;
+        Ok(true)
+    }
+}
+
+fn write_row_description(
+    columns: &[(String, Type)],
+    format: i16,
+    out: &mut BytesMut,
+) -> io::Result<()> {
+    if columns.is_empty() {
+        backend::no_data(out);
+        return Ok(());
+    }
+
+    let fields = columns
+        .iter()
+        .map(|(name, type_)| Field::new(name, 0, 0, type_.oid(), -1, -1, format));
+    backend::row_description(fields, out)
+}
+
+fn write_error(code: &SqlState, message: &str, out: &mut BytesMut) -> io::Result<()> {
+    backend::error_response(
+        [
+            (b'S', "ERROR"),
+            (b'V', "ERROR"),
+            (b'C', code.code()),
+            (b'M', message),
+        ],
+        out,
+    )
+}
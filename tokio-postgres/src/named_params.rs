@@ -0,0 +1,198 @@
+use crate::types::ToSql;
+use crate::Error;
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::hash::{BuildHasher, Hash};
+
+/// A set of parameter values identified by name.
+///
+/// This is implemented for slices and arrays of `(name, value)` pairs as well as maps from names to values. Structs
+/// can implement it to be bound by their fields.
+///
+/// # Examples
+///
+/// ```
+/// use tokio_postgres::types::ToSql;
+/// use tokio_postgres::NamedParams;
+///
+/// struct User {
+///     id: i32,
+///     name: String,
+/// }
+///
+/// impl NamedParams for User {
+///     fn get(&self, name: &str) -> Option<&(dyn ToSql + Sync)> {
+///         match name {
+///             "id" => Some(&self.id),
+///             "name" => Some(&self.name),
+///             _ => None,
+///         }
+///     }
+///
+///     fn names(&self) -> Vec<&str> {
+///         vec!["id", "name"]
+///     }
+/// }
+/// ```
+pub trait NamedParams {
+    /// Returns the value of the named parameter, if present.
+    fn get(&self, name: &str) -> Option<&(dyn ToSql + Sync)>;
+
+    /// Returns the names of all parameters.
+    fn names(&self) -> Vec<&str>;
+}
+
+impl<'a> NamedParams for [(&'a str, &'a (dyn ToSql + Sync))] {
+    fn get(&self, name: &str) -> Option<&(dyn ToSql + Sync)> {
+        self.iter().find(|(n, _)| *n == name).map(|(_, v)| *v)
+    }
+
+    fn names(&self) -> Vec<&str> {
+        self.iter().map(|(n, _)| *n).collect()
+    }
+}
+
+impl<'a, const N: usize> NamedParams for [(&'a str, &'a (dyn ToSql + Sync)); N] {
+    fn get(&self, name: &str) -> Option<&(dyn ToSql + Sync)> {
+        NamedParams::get(&self[..], name)
+    }
+
+    fn names(&self) -> Vec<&str> {
+        NamedParams::names(&self[..])
+    }
+}
+
+impl<K, V, S> NamedParams for HashMap<K, V, S>
+where
+    K: Borrow<str> + Hash + Eq,
+    V: ToSql + Sync,
+    S: BuildHasher,
+{
+    fn get(&self, name: &str) -> Option<&(dyn ToSql + Sync)> {
+        HashMap::get(self, name).map(|v| v as _)
+    }
+
+    fn names(&self) -> Vec<&str> {
+        self.keys().map(Borrow::borrow).collect()
+    }
+}
+
+impl<K, V> NamedParams for BTreeMap<K, V>
+where
+    K: Borrow<str> + Ord,
+    V: ToSql + Sync,
+{
+    fn get(&self, name: &str) -> Option<&(dyn ToSql + Sync)> {
+        BTreeMap::get(self, name).map(|v| v as _)
+    }
+
+    fn names(&self) -> Vec<&str> {
+        self.keys().map(Borrow::borrow).collect()
+    }
+}
+
+/// A statement using named parameters, rewritten to use positional parameters.
+///
+/// Parameters are written as `:name`, where the name consists of ASCII letters, digits and underscores and doesn't
+/// start with a digit. Each distinct name is assigned a positional parameter `$n` in order of first appearance, so a
+/// name may be referenced multiple times. Casts written as `::type`, and colons within quoted literals and identifiers
+/// are left untouched. Colons within comments, dollar-quoted strings and array slices are not detected, so avoid them
+/// or use positional parameters in those statements.
+#[derive(Debug, Clone)]
+pub struct NamedQuery {
+    sql: String,
+    names: Vec<String>,
+}
+
+impl NamedQuery {
+    /// Rewrites a statement using named parameters.
+    pub fn new(query: &str) -> NamedQuery {
+        let mut sql = String::with_capacity(query.len());
+        let mut names: Vec<String> = vec![];
+        let mut quote = None;
+        let mut chars = query.char_indices().peekable();
+
+        while let Some((i, c)) = chars.next() {
+            match quote {
+                Some(q) if c == q => quote = None,
+                Some(_) => {}
+                None if c == '\'' || c == '"' => quote = Some(c),
+                None if c == ':' => match chars.peek() {
+                    // a cast
+                    Some((_, ':')) => {
+                        sql.push_str("::");
+                        chars.next();
+                        continue;
+                    }
+                    Some(&(_, d)) if d.is_ascii_alphabetic() || d == '_' => {
+                        let start = i + 1;
+                        let mut end = start;
+                        while let Some(&(j, d)) = chars.peek() {
+                            if !(d.is_ascii_alphanumeric() || d == '_') {
+                                break;
+                            }
+                            end = j + 1;
+                            chars.next();
+                        }
+
+                        let name = &query[start..end];
+                        let idx = match names.iter().position(|n| n == name) {
+                            Some(idx) => idx,
+                            None => {
+                                names.push(name.to_string());
+                                names.len() - 1
+                            }
+                        };
+                        let _ = write!(sql, "${}", idx + 1);
+                        continue;
+                    }
+                    _ => {}
+                },
+                None => {}
+            }
+            sql.push(c);
+        }
+
+        NamedQuery { sql, names }
+    }
+
+    /// Returns the rewritten statement, using positional parameters.
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    /// Returns the names of the parameters, in positional order.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Returns the values of the parameters in positional order.
+    ///
+    /// Returns an error if a value is missing for any of the statement's parameters, or if any of the values provided
+    /// is not used by the statement.
+    pub fn bind<'a, P>(&self, params: &'a P) -> Result<Vec<&'a (dyn ToSql + Sync)>, Error>
+    where
+        P: ?Sized + NamedParams,
+    {
+        if let Some(unused) = params
+            .names()
+            .into_iter()
+            .find(|name| !self.names.iter().any(|n| n == name))
+        {
+            return Err(Error::named_parameter(
+                unused,
+                "parameter is not used by the statement".into(),
+            ));
+        }
+
+        self.names
+            .iter()
+            .map(|name| {
+                params
+                    .get(name)
+                    .ok_or_else(|| Error::named_parameter(name, "no value provided".into()))
+            })
+            .collect()
+    }
+}
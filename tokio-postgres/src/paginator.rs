@@ -0,0 +1,201 @@
+use crate::types::{FromSqlOwned, ToSql};
+use crate::{Error, GenericClient, Row, Statement};
+use futures::stream::{self, BoxStream};
+use futures::{Stream, StreamExt};
+use std::collections::VecDeque;
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// A helper which reads the results of a query through successive keyset-paginated queries.
+///
+/// Keyset pagination reads a large ordered result one page at a time, starting each page after the key of the last
+/// row of the previous one. Unlike `OFFSET`, each page can be found through an index, and no locks or open
+/// transactions are held between pages.
+///
+/// The query is passed the key of the last row read as its `$1` parameter, which is `NULL` for the first page, and the
+/// page size as its `$2` parameter, as an `INT8`. Any parameters added with the `param` method follow them, starting
+/// at `$3`. The query must order its rows by the key column, and return at most the page size number of rows:
+///
+/// ```no_run
+/// use futures::TryStreamExt;
+/// use tokio_postgres::Paginator;
+///
+/// # async fn f(client: &tokio_postgres::Client) -> Result<(), tokio_postgres::Error> {
+/// let rows = Paginator::<i64>::new(
+///     "SELECT id, name FROM users
+///      WHERE ($1::INT8 IS NULL OR id > $1) AND active = $3
+///      ORDER BY id
+///      LIMIT $2",
+///     "id",
+/// )
+/// .page_size(500)
+/// .param(true)
+/// .rows(client);
+/// futures::pin_mut!(rows);
+///
+/// while let Some(row) = rows.try_next().await? {
+///     let name: &str = row.get("name");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Since the key is taken from a single column, it must be unique for every row to be read exactly once.
+pub struct Paginator<K> {
+    query: String,
+    key_column: String,
+    page_size: i64,
+    params: Vec<Box<dyn ToSql + Sync + Send>>,
+    last_key: Option<K>,
+    statement: Option<Statement>,
+    done: bool,
+}
+
+impl<K> fmt::Debug for Paginator<K>
+where
+    K: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Paginator")
+            .field("query", &self.query)
+            .field("key_column", &self.key_column)
+            .field("page_size", &self.page_size)
+            .field("params", &self.params)
+            .field("last_key", &self.last_key)
+            .field("done", &self.done)
+            .finish()
+    }
+}
+
+impl<K> Paginator<K>
+where
+    K: FromSqlOwned + ToSql + Sync + Send + 'static,
+{
+    /// Creates a new paginator reading the results of a query ordered by the specified column.
+    pub fn new(query: &str, key_column: &str) -> Paginator<K> {
+        Paginator {
+            query: query.to_string(),
+            key_column: key_column.to_string(),
+            page_size: 1000,
+            params: vec![],
+            last_key: None,
+            statement: None,
+            done: false,
+        }
+    }
+
+    /// Sets the maximum number of rows read by each query.
+    ///
+    /// Defaults to 1000.
+    pub fn page_size(mut self, page_size: i64) -> Paginator<K> {
+        self.page_size = page_size;
+        self
+    }
+
+    /// Starts reading after the specified key rather than from the first row.
+    ///
+    /// Together with `last_key`, this allows reading to be resumed later.
+    pub fn start_after(mut self, key: K) -> Paginator<K> {
+        self.last_key = Some(key);
+        self
+    }
+
+    /// Adds a parameter to the query, following the parameters added previously.
+    pub fn param<T>(mut self, value: T) -> Paginator<K>
+    where
+        T: ToSql + Sync + Send + 'static,
+    {
+        self.params.push(Box::new(value));
+        self
+    }
+
+    /// Returns the key of the last row read, or the key passed to `start_after` if no rows have been read.
+    pub fn last_key(&self) -> Option<&K> {
+        self.last_key.as_ref()
+    }
+
+    /// Determines if all pages have been read.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Reads the next page of rows, returning `None` once all pages have been read.
+    ///
+    /// The query is prepared when the first page is read.
+    pub async fn next_page<C>(&mut self, client: &C) -> Result<Option<Vec<Row>>, Error>
+    where
+        C: GenericClient + Sync,
+    {
+        if self.done {
+            return Ok(None);
+        }
+
+        let statement = match &self.statement {
+            Some(statement) => statement.clone(),
+            None => {
+                let statement = client.prepare(&self.query).await?;
+                self.statement = Some(statement.clone());
+                statement
+            }
+        };
+
+        let mut params: Vec<&(dyn ToSql + Sync)> = vec![&self.last_key, &self.page_size];
+        params.extend(self.params.iter().map(|p| &**p as &(dyn ToSql + Sync)));
+        let rows = client.query(&statement, &params).await?;
+
+        // a short page is the last one
+        if rows.len() < self.page_size.max(1) as usize {
+            self.done = true;
+        }
+        match rows.last() {
+            Some(row) => self.last_key = Some(row.try_get(&*self.key_column)?),
+            None => {
+                self.done = true;
+                return Ok(None);
+            }
+        }
+
+        Ok(Some(rows))
+    }
+
+    /// Returns a stream over the rows of all remaining pages.
+    ///
+    /// Each page is read once all rows of the previous one have been consumed.
+    pub fn rows<'a, C>(self, client: &'a C) -> PaginatedRowStream<'a>
+    where
+        C: GenericClient + Sync,
+    {
+        let inner = stream::try_unfold(
+            (self, VecDeque::new()),
+            move |(mut paginator, mut rows)| async move {
+                loop {
+                    if let Some(row) = rows.pop_front() {
+                        return Ok(Some((row, (paginator, rows))));
+                    }
+                    match paginator.next_page(client).await? {
+                        Some(page) => rows.extend(page),
+                        None => return Ok(None),
+                    }
+                }
+            },
+        );
+
+        PaginatedRowStream {
+            inner: inner.boxed(),
+        }
+    }
+}
+
+/// A stream of the rows of a paginated query, returned by `Paginator::rows`.
+pub struct PaginatedRowStream<'a> {
+    inner: BoxStream<'a, Result<Row, Error>>,
+}
+
+impl Stream for PaginatedRowStream<'_> {
+    type Item = Result<Row, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx)
+    }
+}
@@ -1,7 +1,8 @@
 use crate::client::InnerClient;
 use crate::codec::FrontendMessage;
 use crate::connection::RequestMessages;
-use crate::error::SqlState;
+use crate::error::{QueryContext, QueryText, SqlState};
+use crate::trace::{self, Instrument};
 use crate::types::{Field, Kind, Oid, Type};
 use crate::{query, slice_iter};
 use crate::{Column, Error, Statement};
@@ -63,13 +64,56 @@ pub async fn prepare(
     query: &str,
     types: &[Type],
 ) -> Result<Statement, Error> {
-    let name = format!("s{}", NEXT_ID.fetch_add(1, Ordering::SeqCst));
+    let name = if client.named_statements() {
+        format!(
+            "{}{}",
+            client.statement_name_prefix(),
+            NEXT_ID.fetch_add(1, Ordering::SeqCst)
+        )
+    } else {
+        String::new()
+    };
+    prepare_named(client, name, query, types).await
+}
+
+/// Prepares a query as the unnamed statement, regardless of whether the client is configured to use named statements.
+pub async fn prepare_unnamed(
+    client: &Arc<InnerClient>,
+    query: &str,
+    types: &[Type],
+) -> Result<Statement, Error> {
+    prepare_named(client, String::new(), query, types).await
+}
+
+async fn prepare_named(
+    client: &Arc<InnerClient>,
+    name: String,
+    query: &str,
+    types: &[Type],
+) -> Result<Statement, Error> {
+    let span = trace::prepare_span(client, query);
+    let result = prepare_statement(client, name, query, types)
+        .instrument(span.clone())
+        .await;
+    trace::record_result(&span, &result);
+    result
+}
+
+async fn prepare_statement(
+    client: &Arc<InnerClient>,
+    name: String,
+    query: &str,
+    types: &[Type],
+) -> Result<Statement, Error> {
     let buf = encode(client, &name, query, types)?;
-    let mut responses = client.send(RequestMessages::Single(FrontendMessage::Raw(buf)))?;
+    let mut responses = client
+        .send(RequestMessages::Single(FrontendMessage::Raw(buf)))
+        .await?;
 
-    match responses.next().await? {
-        Message::ParseComplete => {}
-        _ => return Err(Error::unexpected_message()),
+    match responses.next().await {
+        Ok(Message::ParseComplete) => {}
+        Ok(_) => return Err(Error::unexpected_message()),
+        Err(e) => return Err(e.with_context(query_context(client, query).as_ref())),
     }
 
     let parameter_description = match responses.next().await? {
@@ -83,9 +127,17 @@ pub async fn prepare(
         _ => return Err(Error::unexpected_message()),
     };
 
+    // the server truncates the parameter count of statements referencing more parameters than can be bound, which
+    // leaves the remaining types undrained
+    let oids = parameter_description
+        .parameters()
+        .collect::<Vec<_>>()
+        .map_err(|_| {
+            query::param_limit_error("the statement has too many parameters".to_string())
+        })?;
+
     let mut parameters = vec![];
-    let mut it = parameter_description.parameters();
-    while let Some(oid) = it.next().map_err(Error::parse)? {
+    for oid in oids {
         let type_ = get_type(client, oid).await?;
         parameters.push(type_);
     }
@@ -100,7 +152,24 @@ pub async fn prepare(
         }
     }
 
-    Ok(Statement::new(client, name, parameters, columns))
+    Ok(Statement::new(
+        client,
+        name,
+        query.to_string(),
+        parameters,
+        columns,
+    ))
+}
+
+fn query_context(client: &InnerClient, query: &str) -> Option<Arc<QueryContext>> {
+    if client.query_in_errors() {
+        Some(Arc::new(QueryContext {
+            query: QueryText::Unprepared(query.to_string()),
+            params: None,
+        }))
+    } else {
+        None
+    }
 }
 
 fn prepare_rec<'a>(
@@ -112,6 +181,8 @@ fn prepare_rec<'a>(
 }
 
 fn encode(client: &InnerClient, name: &str, query: &str, types: &[Type]) -> Result<Bytes, Error> {
+    query::check_param_limit(types.len())?;
+
     if types.is_empty() {
         debug!("preparing query {}: {}", name, query);
     } else {
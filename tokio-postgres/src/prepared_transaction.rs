@@ -0,0 +1,64 @@
+use crate::{Client, Error, Row};
+use std::time::SystemTime;
+
+const PREPARED_TRANSACTIONS_QUERY: &str = "\
+SELECT transaction::text::oid, gid, prepared, owner::text, database::text
+FROM pg_catalog.pg_prepared_xacts
+ORDER BY prepared
+";
+
+/// A transaction which has been prepared for two-phase commit but not yet committed or rolled back.
+#[derive(Debug, Clone)]
+pub struct PreparedTransaction {
+    transaction: u32,
+    gid: String,
+    prepared: SystemTime,
+    owner: String,
+    database: String,
+}
+
+impl PreparedTransaction {
+    fn from_row(row: &Row) -> Result<PreparedTransaction, Error> {
+        Ok(PreparedTransaction {
+            transaction: row.try_get(0)?,
+            gid: row.try_get(1)?,
+            prepared: row.try_get(2)?,
+            owner: row.try_get(3)?,
+            database: row.try_get(4)?,
+        })
+    }
+
+    /// Returns the numeric transaction identifier of the prepared transaction.
+    pub fn transaction(&self) -> u32 {
+        self.transaction
+    }
+
+    /// Returns the global transaction identifier that was assigned to the transaction.
+    pub fn gid(&self) -> &str {
+        &self.gid
+    }
+
+    /// Returns the time at which the transaction was prepared.
+    pub fn prepared(&self) -> SystemTime {
+        self.prepared
+    }
+
+    /// Returns the name of the user that executed the transaction.
+    pub fn owner(&self) -> &str {
+        &self.owner
+    }
+
+    /// Returns the name of the database in which the transaction was executed.
+    pub fn database(&self) -> &str {
+        &self.database
+    }
+}
+
+pub async fn prepared_transactions(client: &Client) -> Result<Vec<PreparedTransaction>, Error> {
+    client
+        .query(PREPARED_TRANSACTIONS_QUERY, &[])
+        .await?
+        .iter()
+        .map(PreparedTransaction::from_row)
+        .collect()
+}
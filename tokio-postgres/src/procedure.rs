@@ -0,0 +1,25 @@
+use crate::types::ToSql;
+use crate::{Client, Error, Row};
+use std::fmt::Write;
+
+pub(crate) async fn call(
+    client: &Client,
+    procedure: &str,
+    params: &[&(dyn ToSql + Sync)],
+) -> Result<Option<Row>, Error> {
+    let query = call_query(procedure, params.len());
+    let statement = client.prepare(&query).await?;
+    client.query_opt(&statement, params).await
+}
+
+fn call_query(procedure: &str, params: usize) -> String {
+    let mut query = format!("CALL {}(", procedure);
+    for i in 1..=params {
+        if i > 1 {
+            query.push_str(", ");
+        }
+        let _ = write!(query, "${}", i);
+    }
+    query.push(')');
+    query
+}
@@ -0,0 +1,311 @@
+//! Logging of raw protocol messages, enabled by `Config::trace_protocol`.
+//!
+//! Each message is logged at the `info` level to the `tokio_postgres::protocol` target, in a format modeled after
+//! libpq's `PQtrace`: a timestamp, the direction (`F` for frontend, `B` for backend), the message length, its name,
+//! and a summary of its contents. Passwords and other authentication data are never logged.
+
+use log::info;
+use std::fmt::Write;
+use std::str;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const TARGET: &str = "tokio_postgres::protocol";
+
+// protocol codes of the untagged messages sent at the start of a connection
+const STARTUP_MESSAGE: i32 = 196_608;
+const CANCEL_REQUEST: i32 = 80_877_102;
+const SSL_REQUEST: i32 = 80_877_103;
+const GSSENC_REQUEST: i32 = 80_877_104;
+
+/// Logs a buffer of frontend messages, the last of which may be incomplete.
+pub fn frontend(buf: &[u8]) {
+    let mut buf = buf;
+    while !buf.is_empty() {
+        // untagged messages start with their length, whose high byte is always zero
+        let (tag, header) = if buf[0] == 0 {
+            (None, 0)
+        } else {
+            (Some(buf[0]), 1)
+        };
+        let len = match read_len(&buf[header..]) {
+            Some(len) => len,
+            None => return,
+        };
+        // the rest of a message may follow in later writes, in which case what's here is summarized
+        let end = (header + len).min(buf.len());
+        let mut body = Reader(buf.get(header + 4..end).unwrap_or_default());
+        let summary = match tag {
+            Some(tag) => frontend_summary(tag, &mut body),
+            None => untagged_summary(&mut body),
+        };
+        log('F', len, &summary);
+        buf = &buf[end..];
+    }
+}
+
+/// Logs a frontend message whose body is written separately from its header.
+pub fn frontend_header(tag: u8, len: usize) {
+    log('F', len, &frontend_summary(tag, &mut Reader(&[])));
+}
+
+/// Logs a buffer of complete backend messages.
+pub fn backend(buf: &[u8]) {
+    let mut buf = buf;
+    while buf.len() > 1 {
+        let len = match read_len(&buf[1..]) {
+            Some(len) => len,
+            None => return,
+        };
+        let end = 1 + len;
+        if buf.len() < end {
+            return;
+        }
+        let summary = backend_summary(buf[0], &mut Reader(&buf[5..end]));
+        log('B', len, &summary);
+        buf = &buf[end..];
+    }
+}
+
+fn log(direction: char, len: usize, summary: &str) {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    info!(
+        target: TARGET,
+        "{}.{:06}\t{}\t{}\t{}",
+        timestamp.as_secs(),
+        timestamp.subsec_micros(),
+        direction,
+        len,
+        summary,
+    );
+}
+
+fn read_len(buf: &[u8]) -> Option<usize> {
+    let len = Reader(buf).i32()?;
+    if len < 4 {
+        None
+    } else {
+        Some(len as usize)
+    }
+}
+
+fn untagged_summary(body: &mut Reader<'_>) -> String {
+    let mut out = String::new();
+    match body.i32() {
+        Some(STARTUP_MESSAGE) => {
+            out.push_str("StartupMessage");
+            while let Some(name) = body.cstr().filter(|s| !s.is_empty()) {
+                let value = body.cstr().unwrap_or_default();
+                let _ = write!(out, " {} {:?}", name, value);
+            }
+        }
+        Some(CANCEL_REQUEST) => {
+            out.push_str("CancelRequest");
+            if let Some(process_id) = body.i32() {
+                let _ = write!(out, " {}", process_id);
+            }
+        }
+        Some(SSL_REQUEST) => out.push_str("SSLRequest"),
+        Some(GSSENC_REQUEST) => out.push_str("GSSENCRequest"),
+        Some(code) => {
+            let _ = write!(out, "Unknown message {}", code);
+        }
+        None => out.push_str("Unknown message"),
+    }
+    out
+}
+
+fn frontend_summary(tag: u8, body: &mut Reader<'_>) -> String {
+    let mut out = String::new();
+    match tag {
+        b'B' => {
+            out.push_str("Bind");
+            let _ = write!(out, " {:?}", body.cstr().unwrap_or_default());
+            let _ = write!(out, " {:?}", body.cstr().unwrap_or_default());
+        }
+        b'C' => {
+            out.push_str("Close");
+            close_or_describe(&mut out, body);
+        }
+        b'D' => {
+            out.push_str("Describe");
+            close_or_describe(&mut out, body);
+        }
+        b'E' => {
+            out.push_str("Execute");
+            let _ = write!(out, " {:?}", body.cstr().unwrap_or_default());
+            if let Some(max_rows) = body.i32() {
+                let _ = write!(out, " {}", max_rows);
+            }
+        }
+        b'F' => {
+            out.push_str("FunctionCall");
+            if let Some(oid) = body.i32() {
+                let _ = write!(out, " {}", oid as u32);
+            }
+        }
+        b'H' => out.push_str("Flush"),
+        b'P' => {
+            out.push_str("Parse");
+            let _ = write!(out, " {:?}", body.cstr().unwrap_or_default());
+            let _ = write!(out, " {:?}", body.cstr().unwrap_or_default());
+        }
+        b'p' => out.push_str("PasswordMessage"),
+        b'Q' => {
+            let _ = write!(out, "Query {:?}", body.cstr().unwrap_or_default());
+        }
+        b'S' => out.push_str("Sync"),
+        b'X' => out.push_str("Terminate"),
+        b'd' => out.push_str("CopyData"),
+        b'c' => out.push_str("CopyDone"),
+        b'f' => {
+            let _ = write!(out, "CopyFail {:?}", body.cstr().unwrap_or_default());
+        }
+        tag => {
+            let _ = write!(out, "Unknown message {:?}", tag as char);
+        }
+    }
+    out
+}
+
+fn backend_summary(tag: u8, body: &mut Reader<'_>) -> String {
+    let mut out = String::new();
+    match tag {
+        b'R' => {
+            let kind = match body.i32() {
+                Some(0) => "Ok",
+                Some(2) => "KerberosV5",
+                Some(3) => "CleartextPassword",
+                Some(5) => "MD5Password",
+                Some(6) => "SCMCredential",
+                Some(7) => "GSS",
+                Some(8) => "GSSContinue",
+                Some(9) => "SSPI",
+                Some(10) => "SASL",
+                Some(11) => "SASLContinue",
+                Some(12) => "SASLFinal",
+                _ => "Unknown",
+            };
+            let _ = write!(out, "Authentication{}", kind);
+        }
+        b'K' => {
+            out.push_str("BackendKeyData");
+            if let Some(process_id) = body.i32() {
+                let _ = write!(out, " {}", process_id);
+            }
+        }
+        b'2' => out.push_str("BindComplete"),
+        b'3' => out.push_str("CloseComplete"),
+        b'C' => {
+            let _ = write!(out, "CommandComplete {:?}", body.cstr().unwrap_or_default());
+        }
+        b'd' => out.push_str("CopyData"),
+        b'c' => out.push_str("CopyDone"),
+        b'G' => out.push_str("CopyInResponse"),
+        b'H' => out.push_str("CopyOutResponse"),
+        b'W' => out.push_str("CopyBothResponse"),
+        b'D' => {
+            out.push_str("DataRow");
+            if let Some(columns) = body.i16() {
+                let _ = write!(out, " {}", columns);
+            }
+        }
+        b'I' => out.push_str("EmptyQueryResponse"),
+        b'E' => {
+            out.push_str("ErrorResponse");
+            fields(&mut out, body);
+        }
+        b'N' => {
+            out.push_str("NoticeResponse");
+            fields(&mut out, body);
+        }
+        b'V' => out.push_str("FunctionCallResponse"),
+        b'v' => out.push_str("NegotiateProtocolVersion"),
+        b'n' => out.push_str("NoData"),
+        b'A' => {
+            out.push_str("NotificationResponse");
+            if let Some(process_id) = body.i32() {
+                let _ = write!(out, " {}", process_id);
+            }
+            let _ = write!(out, " {:?}", body.cstr().unwrap_or_default());
+            let _ = write!(out, " {:?}", body.cstr().unwrap_or_default());
+        }
+        b't' => {
+            out.push_str("ParameterDescription");
+            if let Some(params) = body.i16() {
+                let _ = write!(out, " {}", params);
+            }
+        }
+        b'S' => {
+            out.push_str("ParameterStatus");
+            let _ = write!(out, " {:?}", body.cstr().unwrap_or_default());
+            let _ = write!(out, " {:?}", body.cstr().unwrap_or_default());
+        }
+        b'1' => out.push_str("ParseComplete"),
+        b's' => out.push_str("PortalSuspended"),
+        b'Z' => {
+            out.push_str("ReadyForQuery");
+            if let Some(status) = body.byte() {
+                let _ = write!(out, " {}", status as char);
+            }
+        }
+        b'T' => {
+            out.push_str("RowDescription");
+            if let Some(fields) = body.i16() {
+                let _ = write!(out, " {}", fields);
+            }
+        }
+        tag => {
+            let _ = write!(out, "Unknown message {:?}", tag as char);
+        }
+    }
+    out
+}
+
+fn close_or_describe(out: &mut String, body: &mut Reader<'_>) {
+    if let Some(kind) = body.byte() {
+        let _ = write!(out, " {}", kind as char);
+    }
+    let _ = write!(out, " {:?}", body.cstr().unwrap_or_default());
+}
+
+fn fields(out: &mut String, body: &mut Reader<'_>) {
+    while let Some(code) = body.byte().filter(|&b| b != 0) {
+        let _ = write!(
+            out,
+            " {} {:?}",
+            code as char,
+            body.cstr().unwrap_or_default()
+        );
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Option<u8> {
+        let (&b, rest) = self.0.split_first()?;
+        self.0 = rest;
+        Some(b)
+    }
+
+    fn i16(&mut self) -> Option<i16> {
+        let b = self.0.get(..2)?;
+        self.0 = &self.0[2..];
+        Some(i16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn i32(&mut self) -> Option<i32> {
+        let b = self.0.get(..4)?;
+        self.0 = &self.0[4..];
+        Some(i32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn cstr(&mut self) -> Option<&'a str> {
+        let end = self.0.iter().position(|&b| b == 0)?;
+        let s = str::from_utf8(&self.0[..end]).ok()?;
+        self.0 = &self.0[end + 1..];
+        Some(s)
+    }
+}
@@ -1,17 +1,23 @@
 use crate::client::{InnerClient, Responses};
 use crate::codec::FrontendMessage;
 use crate::connection::RequestMessages;
-use crate::types::{BorrowToSql, IsNull};
-use crate::{Error, Portal, Row, Statement};
+use crate::error::{ParamRenderer, QueryContext, QueryText, SqlState};
+use crate::query_logger::QueryLog;
+use crate::trace::{self, Instrument};
+use crate::types::{BorrowToSql, IsNull, Type, WrongType};
+use crate::{prepare, CommandTag, Error, Portal, Row, Statement};
 use bytes::{Bytes, BytesMut};
+use futures::future::BoxFuture;
 use futures::{ready, Stream};
 use log::{debug, log_enabled, Level};
 use pin_project_lite::pin_project;
-use postgres_protocol::message::backend::Message;
+use postgres_protocol::message::backend::{CommandCompleteBody, Message};
 use postgres_protocol::message::frontend;
 use std::fmt;
 use std::marker::PhantomPinned;
 use std::pin::Pin;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 
 struct BorrowToSqlParamsDebug<'a, T>(&'a [T]);
@@ -37,23 +43,52 @@ where
     I: IntoIterator<Item = P>,
     I::IntoIter: ExactSizeIterator,
 {
-    let buf = if log_enabled!(Level::Debug) {
-        let params = params.into_iter().collect::<Vec<_>>();
-        debug!(
-            "executing statement {} with parameters: {:?}",
-            statement.name(),
-            BorrowToSqlParamsDebug(params.as_slice()),
-        );
-        encode(client, &statement, params)?
-    } else {
-        encode(client, &statement, params)?
-    };
-    let responses = start(client, buf).await?;
-    Ok(RowStream {
-        statement,
-        responses,
-        _p: PhantomPinned,
-    })
+    let span = trace::query_span(client, statement.query());
+    let mut log = QueryLog::start(client, statement.params().len());
+    let result = async {
+        let (buf, context) = encode_with_context(client, &statement, params, log.as_mut())?;
+        let bytes_sent = buf.len();
+        let responses = start(client, &statement, buf)
+            .await
+            .map_err(|e| e.with_context(context.as_ref()))?;
+        Ok((responses, context, bytes_sent))
+    }
+    .instrument(span.clone())
+    .await;
+
+    match result {
+        Ok((responses, context, bytes_sent)) => Ok(RowStream {
+            statement,
+            responses,
+            context,
+            log,
+            tag: None,
+            span,
+            bytes_sent,
+            _p: PhantomPinned,
+        }),
+        Err(e) => {
+            trace::record_error(&span, &e);
+            if let Some(log) = log {
+                log.finish(statement.query(), None, Some(&e));
+            }
+            Err(e)
+        }
+    }
+}
+
+pub async fn query_typed<P, I>(
+    client: &Arc<InnerClient>,
+    query: &str,
+    params: I,
+) -> Result<RowStream, Error>
+where
+    P: BorrowToSql,
+    I: IntoIterator<Item = (P, Type)>,
+{
+    let (params, types): (Vec<P>, Vec<Type>) = params.into_iter().unzip();
+    let statement = prepare::prepare_unnamed(client, query, &types).await?;
+    self::query(client, statement, params).await
 }
 
 pub async fn query_portal(
@@ -67,11 +102,19 @@ pub async fn query_portal(
         Ok(buf.split().freeze())
     })?;
 
-    let responses = client.send(RequestMessages::Single(FrontendMessage::Raw(buf)))?;
+    let bytes_sent = buf.len();
+    let responses = client
+        .send_query(RequestMessages::Single(FrontendMessage::Raw(buf)))
+        .await?;
 
     Ok(RowStream {
         statement: portal.statement().clone(),
         responses,
+        context: None,
+        log: None,
+        tag: None,
+        span: trace::query_span(client, portal.statement().query()),
+        bytes_sent,
         _p: PhantomPinned,
     })
 }
@@ -80,53 +123,185 @@ pub async fn execute<P, I>(
     client: &InnerClient,
     statement: Statement,
     params: I,
-) -> Result<u64, Error>
+) -> Result<CommandTag, Error>
+where
+    P: BorrowToSql,
+    I: IntoIterator<Item = P>,
+    I::IntoIter: ExactSizeIterator,
+{
+    let span = trace::query_span(client, statement.query());
+    let mut log = QueryLog::start(client, statement.params().len());
+    let result = async {
+        let (buf, context) = encode_with_context(client, &statement, params, log.as_mut())?;
+        let bytes_sent = buf.len();
+        let mut responses = start(client, &statement, buf)
+            .await
+            .map_err(|e| e.with_context(context.as_ref()))?;
+
+        let mut tag = CommandTag::parse("");
+        loop {
+            let message = responses
+                .next()
+                .await
+                .map_err(|e| e.with_context(context.as_ref()))?;
+            match message {
+                Message::DataRow(_) => {}
+                Message::CommandComplete(body) => tag = CommandTag::from_body(&body)?,
+                Message::EmptyQueryResponse => tag = CommandTag::parse(""),
+                Message::ReadyForQuery(_) => {
+                    trace::record_bytes(&span, bytes_sent, responses.bytes_received());
+                    return Ok(tag);
+                }
+                _ => return Err(Error::unexpected_message()),
+            }
+        }
+    }
+    .instrument(span.clone())
+    .await;
+
+    trace::record_result(&span, &result);
+    if let Ok(tag) = &result {
+        trace::record_rows(&span, tag.rows());
+    }
+
+    if let Some(log) = log {
+        log.finish(
+            statement.query(),
+            result.as_ref().ok().map(CommandTag::rows),
+            result.as_ref().err(),
+        );
+    }
+    result
+}
+
+pub(crate) fn extract_row_affected(body: &CommandCompleteBody) -> Result<u64, Error> {
+    CommandTag::from_body(body).map(|tag| tag.rows())
+}
+
+// Encodes a statement's parameters, rendering them for errors and the query log if configured.
+fn encode_with_context<P, I>(
+    client: &InnerClient,
+    statement: &Statement,
+    params: I,
+    log: Option<&mut QueryLog>,
+) -> Result<(Bytes, Option<Arc<QueryContext>>), Error>
 where
     P: BorrowToSql,
     I: IntoIterator<Item = P>,
     I::IntoIter: ExactSizeIterator,
 {
-    let buf = if log_enabled!(Level::Debug) {
+    // the error renderer is only looked up when queries are recorded in errors at all
+    let query_in_errors = client.query_in_errors();
+    let error_renderer = if query_in_errors {
+        client.error_param_renderer()
+    } else {
+        None
+    };
+    let log = log.and_then(|log| {
+        log.param_renderer()
+            .cloned()
+            .map(|renderer| (log, renderer))
+    });
+    if log_enabled!(Level::Debug) || error_renderer.is_some() || log.is_some() {
         let params = params.into_iter().collect::<Vec<_>>();
         debug!(
             "executing statement {} with parameters: {:?}",
             statement.name(),
             BorrowToSqlParamsDebug(params.as_slice()),
         );
-        encode(client, &statement, params)?
-    } else {
-        encode(client, &statement, params)?
-    };
-    let mut responses = start(client, buf).await?;
-
-    let mut rows = 0;
-    loop {
-        match responses.next().await? {
-            Message::DataRow(_) => {}
-            Message::CommandComplete(body) => {
-                rows = body
-                    .tag()
-                    .map_err(Error::parse)?
-                    .rsplit(' ')
-                    .next()
-                    .unwrap()
-                    .parse()
-                    .unwrap_or(0);
-            }
-            Message::EmptyQueryResponse => rows = 0,
-            Message::ReadyForQuery(_) => return Ok(rows),
-            _ => return Err(Error::unexpected_message()),
+        if let Some((log, renderer)) = log {
+            log.set_params(render_params(&*renderer, statement.params(), &params));
         }
+        let context = query_context(
+            query_in_errors,
+            error_renderer.as_deref(),
+            statement,
+            &params,
+        );
+        let buf =
+            encode(client, statement, params).map_err(|e| e.with_context(context.as_ref()))?;
+        Ok((buf, context))
+    } else {
+        let context = query_context::<P>(query_in_errors, None, statement, &[]);
+        let buf =
+            encode(client, statement, params).map_err(|e| e.with_context(context.as_ref()))?;
+        Ok((buf, context))
     }
 }
 
-async fn start(client: &InnerClient, buf: Bytes) -> Result<Responses, Error> {
-    let mut responses = client.send(RequestMessages::Single(FrontendMessage::Raw(buf)))?;
+fn query_context<P>(
+    query_in_errors: bool,
+    renderer: Option<&ParamRenderer>,
+    statement: &Statement,
+    params: &[P],
+) -> Option<Arc<QueryContext>>
+where
+    P: BorrowToSql,
+{
+    if !query_in_errors {
+        return None;
+    }
+
+    let params = renderer.map(|renderer| render_params(renderer, statement.params(), params));
 
-    match responses.next().await? {
-        Message::BindComplete => {}
-        _ => return Err(Error::unexpected_message()),
+    Some(Arc::new(QueryContext {
+        query: QueryText::Statement(statement.clone()),
+        params,
+    }))
+}
+
+fn render_params<P>(renderer: &ParamRenderer, types: &[Type], params: &[P]) -> String
+where
+    P: BorrowToSql,
+{
+    let mut out = "[".to_string();
+    for (i, (param, ty)) in params.iter().zip(types).enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        out.push_str(&renderer(ty, param.borrow_to_sql()));
     }
+    out.push(']');
+    out
+}
+
+async fn start(
+    client: &InnerClient,
+    statement: &Statement,
+    request: Bytes,
+) -> Result<Responses, Error> {
+    let idle = client.transaction_status().load(Ordering::Acquire) == b'I';
+    match send(client, request.clone()).await {
+        Err(e)
+            if idle
+                && e.code() == Some(&SqlState::INVALID_SQL_STATEMENT_NAME)
+                && client.is_cached_statement(statement) =>
+        {
+            // a connection pooler has moved the session to a server session on which the cached statement was never
+            // prepared, so it is prepared again under the same name and the request retried once
+            let buf = client.with_buf(|buf| {
+                frontend::parse(
+                    statement.name(),
+                    statement.query(),
+                    statement.params().iter().map(Type::oid),
+                    buf,
+                )
+                .map_err(Error::encode)?;
+                buf.extend_from_slice(&request);
+                Ok::<_, Error>(buf.split().freeze())
+            })?;
+            send(client, buf).await
+        }
+        result => result,
+    }
+}
+
+async fn send(client: &InnerClient, buf: Bytes) -> Result<Responses, Error> {
+    let mut responses = client
+        .send_query(RequestMessages::Single(FrontendMessage::Raw(buf)))
+        .await?;
+
+    responses.bind_complete().await?;
 
     Ok(responses)
 }
@@ -157,20 +332,19 @@ where
     I::IntoIter: ExactSizeIterator,
 {
     let params = params.into_iter();
+    check_param_count(statement, params.len())?;
 
-    assert!(
-        statement.params().len() == params.len(),
-        "expected {} parameters but got {}",
-        statement.params().len(),
-        params.len()
-    );
+    if statement.name().is_empty() {
+        encode_parse(statement, buf)?;
+    }
 
+    let mut params = params.zip(statement.params()).enumerate();
     let mut error_idx = 0;
     let r = frontend::bind(
         portal,
         statement.name(),
         Some(1),
-        params.zip(statement.params()).enumerate(),
+        params.by_ref(),
         |(idx, (param, ty)), buf| match param.borrow_to_sql().to_sql_checked(ty, buf) {
             Ok(IsNull::No) => Ok(postgres_protocol::IsNull::No),
             Ok(IsNull::Yes) => Ok(postgres_protocol::IsNull::Yes),
@@ -184,36 +358,255 @@ where
     );
     match r {
         Ok(()) => Ok(()),
-        Err(frontend::BindError::Conversion(e)) => Err(Error::to_sql(e, error_idx)),
+        Err(frontend::BindError::Conversion(e)) => Err(conversion_error(e, error_idx, params)),
         Err(frontend::BindError::Serialization(e)) => Err(Error::encode(e)),
     }
 }
 
+/// The maximum number of parameters which can be bound to a statement, limited by the 16 bit counts of the protocol.
+const MAX_PARAMETERS: usize = u16::MAX as usize;
+
+/// Returns an error if the number of parameters provided doesn't match the number expected by the statement.
+pub(crate) fn check_param_count(statement: &Statement, count: usize) -> Result<(), Error> {
+    // the server truncates the parameter count it reports for statements referencing more parameters than can be
+    // bound, so the provided count is checked against the limit first
+    check_param_limit(count)?;
+    if statement.params().len() == count {
+        return Ok(());
+    }
+
+    let message = format!(
+        "expected {} parameters but got {}",
+        statement.params().len(),
+        count
+    );
+    Err(Error::parameters(message.into()))
+}
+
+/// Returns an error if more parameters are provided than the protocol can bind to a statement.
+pub(crate) fn check_param_limit(count: usize) -> Result<(), Error> {
+    if count <= MAX_PARAMETERS {
+        return Ok(());
+    }
+
+    Err(param_limit_error(format!("got {} parameters", count)))
+}
+
+pub(crate) fn param_limit_error(problem: String) -> Error {
+    let message = format!(
+        "{} but at most {} can be bound to a statement; consider binding the values as arrays, for example with \
+         `UNNEST`",
+        problem, MAX_PARAMETERS,
+    );
+    Error::parameters(message.into())
+}
+
+// If the first parameter which failed to serialize has the wrong type, the remaining parameters are checked as well
+// so that all type mismatches are reported together.
+fn conversion_error<'a, P, I>(
+    e: Box<dyn std::error::Error + Sync + Send>,
+    idx: usize,
+    remaining: I,
+) -> Error
+where
+    P: BorrowToSql,
+    I: Iterator<Item = (usize, (P, &'a Type))>,
+{
+    if !e.is::<WrongType>() {
+        return Error::to_sql(e, idx);
+    }
+
+    let mut scratch = BytesMut::new();
+    let mismatches = remaining
+        .filter_map(|(idx, (param, ty))| {
+            scratch.clear();
+            match param.borrow_to_sql().to_sql_checked(ty, &mut scratch) {
+                Err(e) if e.is::<WrongType>() => Some(format!("parameter {}: {}", idx, e)),
+                _ => None,
+            }
+        })
+        .collect::<Vec<_>>();
+    if mismatches.is_empty() {
+        return Error::to_sql(e, idx);
+    }
+
+    let mut message = format!("parameter {}: {}", idx, e);
+    for mismatch in mismatches {
+        message.push_str("; ");
+        message.push_str(&mismatch);
+    }
+    Error::parameters(message.into())
+}
+
+/// Encodes a Parse message re-creating the unnamed statement, which may have been replaced since it was prepared.
+pub(crate) fn encode_parse(statement: &Statement, buf: &mut BytesMut) -> Result<(), Error> {
+    frontend::parse(
+        "",
+        statement.query(),
+        statement.params().iter().map(Type::oid),
+        buf,
+    )
+    .map_err(Error::encode)
+}
+
 pin_project! {
     /// A stream of table rows.
     pub struct RowStream {
         statement: Statement,
         responses: Responses,
+        context: Option<Arc<QueryContext>>,
+        log: Option<QueryLog>,
+        tag: Option<CommandTag>,
+        span: trace::Span,
+        bytes_sent: usize,
         #[pin]
         _p: PhantomPinned,
     }
 }
 
+impl RowStream {
+    /// Returns the tag the server sent on completion of the statement.
+    ///
+    /// This is only available once the stream has been fully consumed.
+    pub fn command_tag(&self) -> Option<&CommandTag> {
+        self.tag.as_ref()
+    }
+}
+
 impl Stream for RowStream {
     type Item = Result<Row, Error>;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let this = self.project();
-        loop {
-            match ready!(this.responses.poll_next(cx)?) {
+        let result = loop {
+            let message = match ready!(this.responses.poll_next(cx)) {
+                Ok(message) => message,
+                Err(e) => break Some(Err(e.with_context(this.context.as_ref()))),
+            };
+            match message {
                 Message::DataRow(body) => {
-                    return Poll::Ready(Some(Ok(Row::new(this.statement.clone(), body)?)))
+                    return Poll::Ready(Some(Row::new(this.statement.clone(), body)))
                 }
-                Message::EmptyQueryResponse
-                | Message::CommandComplete(_)
-                | Message::PortalSuspended => {}
-                Message::ReadyForQuery(_) => return Poll::Ready(None),
-                _ => return Poll::Ready(Some(Err(Error::unexpected_message()))),
+                Message::CommandComplete(body) => match CommandTag::from_body(&body) {
+                    Ok(tag) => *this.tag = Some(tag),
+                    Err(e) => break Some(Err(e)),
+                },
+                Message::EmptyQueryResponse | Message::PortalSuspended => {}
+                Message::ReadyForQuery(_) => break None,
+                _ => break Some(Err(Error::unexpected_message())),
+            }
+        };
+
+        trace::record_bytes(this.span, *this.bytes_sent, this.responses.bytes_received());
+        let rows = this.tag.as_ref().map(CommandTag::rows);
+        if let Some(rows) = rows {
+            trace::record_rows(this.span, rows);
+        }
+        match &result {
+            Some(Err(e)) => trace::record_error(this.span, e),
+            _ => trace::record_ok(this.span),
+        }
+
+        if let Some(log) = this.log.take() {
+            let error = match &result {
+                Some(Err(e)) => Some(e),
+                _ => None,
+            };
+            log.finish(this.statement.query(), rows, error);
+        }
+        Poll::Ready(result)
+    }
+}
+
+enum PrefetchState {
+    Executing(BoxFuture<'static, Result<RowStream, Error>>),
+    Streaming(Pin<Box<RowStream>>),
+    Done,
+}
+
+/// A stream of table rows fetched from a portal in batches, returned by `Transaction::query_prefetch`.
+pub struct PrefetchRowStream {
+    client: Arc<InnerClient>,
+    portal: Portal,
+    prefetch: i32,
+    state: PrefetchState,
+    batch_rows: usize,
+    tag: Option<CommandTag>,
+}
+
+impl PrefetchRowStream {
+    pub(crate) fn new(
+        client: Arc<InnerClient>,
+        portal: Portal,
+        prefetch: i32,
+    ) -> PrefetchRowStream {
+        let mut stream = PrefetchRowStream {
+            client,
+            portal,
+            prefetch,
+            state: PrefetchState::Done,
+            batch_rows: 0,
+            tag: None,
+        };
+        stream.state = stream.execute();
+        stream
+    }
+
+    fn execute(&self) -> PrefetchState {
+        let client = self.client.clone();
+        let portal = self.portal.clone();
+        let prefetch = self.prefetch;
+        PrefetchState::Executing(Box::pin(async move {
+            query_portal(&client, &portal, prefetch).await
+        }))
+    }
+
+    /// Returns the tag the server sent on completion of the statement.
+    ///
+    /// This is only available once the stream has been fully consumed.
+    pub fn command_tag(&self) -> Option<&CommandTag> {
+        self.tag.as_ref()
+    }
+}
+
+impl Stream for PrefetchRowStream {
+    type Item = Result<Row, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match &mut self.state {
+                PrefetchState::Executing(future) => match ready!(future.as_mut().poll(cx)) {
+                    Ok(stream) => {
+                        self.state = PrefetchState::Streaming(Box::pin(stream));
+                        self.batch_rows = 0;
+                    }
+                    Err(e) => {
+                        self.state = PrefetchState::Done;
+                        return Poll::Ready(Some(Err(e)));
+                    }
+                },
+                PrefetchState::Streaming(stream) => match ready!(stream.as_mut().poll_next(cx)) {
+                    Some(Ok(row)) => {
+                        self.batch_rows += 1;
+                        return Poll::Ready(Some(Ok(row)));
+                    }
+                    Some(Err(e)) => {
+                        self.state = PrefetchState::Done;
+                        return Poll::Ready(Some(Err(e)));
+                    }
+                    None => {
+                        let tag = stream.command_tag().cloned();
+                        // the portal was suspended if the batch was cut short before the statement completed, and
+                        // is only resumed once all of the batch's rows have been consumed
+                        self.state = if tag.is_none() && self.batch_rows > 0 {
+                            self.execute()
+                        } else {
+                            PrefetchState::Done
+                        };
+                        self.tag = tag;
+                    }
+                },
+                PrefetchState::Done => return Poll::Ready(None),
             }
         }
     }
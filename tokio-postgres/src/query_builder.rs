@@ -0,0 +1,177 @@
+use crate::types::ToSql;
+use crate::Error;
+use std::fmt::{self, Write};
+
+/// A builder for statements with a dynamic set of parameters.
+///
+/// The builder assembles the text of a statement from fragments, numbering the `$n` placeholders of parameters as
+/// they are added, so dynamic filters don't require manual bookkeeping of parameter indices. The statement and its
+/// parameters are then passed to any of the client's query methods.
+///
+/// Fragments are appended verbatim, so they must never contain untrusted input. Use parameters for values, and the
+/// functions of the [`escape`](crate::escape) module for dynamic identifiers.
+///
+/// # Examples
+///
+/// ```no_run
+/// # async fn f(client: &tokio_postgres::Client) -> Result<(), tokio_postgres::Error> {
+/// use tokio_postgres::QueryBuilder;
+///
+/// let name = Some("bob");
+/// let ids = vec![1i32, 2, 3];
+///
+/// let mut query = QueryBuilder::new("SELECT * FROM users WHERE id = ");
+/// query.push_any(&ids);
+/// if let Some(name) = &name {
+///     query.push(" AND name = ").push_param(name);
+/// }
+/// let rows = client.query(query.sql(), &query.params()).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct QueryBuilder<'a> {
+    sql: String,
+    params: Vec<Param<'a>>,
+}
+
+enum Param<'a> {
+    Borrowed(&'a (dyn ToSql + Sync)),
+    Owned(Box<dyn ToSql + Sync + 'a>),
+}
+
+impl<'a> fmt::Debug for QueryBuilder<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_struct("QueryBuilder")
+            .field("sql", &self.sql)
+            .field("params", &self.params())
+            .finish()
+    }
+}
+
+impl<'a> QueryBuilder<'a> {
+    /// Creates a new builder starting with the provided text.
+    pub fn new(sql: &str) -> QueryBuilder<'a> {
+        QueryBuilder {
+            sql: sql.to_string(),
+            params: vec![],
+        }
+    }
+
+    /// Appends text to the statement.
+    pub fn push(&mut self, sql: &str) -> &mut Self {
+        self.sql.push_str(sql);
+        self
+    }
+
+    /// Appends a placeholder for a parameter to the statement.
+    pub fn push_param(&mut self, param: &'a (dyn ToSql + Sync)) -> &mut Self {
+        self.params.push(Param::Borrowed(param));
+        self.push_placeholder()
+    }
+
+    /// Appends `ANY($n)` to the statement, with the values bound as a single array parameter.
+    ///
+    /// This is the equivalent of an `IN` list with a variable number of values, which still requires only a single
+    /// parameter. An empty slice matches no rows.
+    pub fn push_any<T>(&mut self, values: &'a [T]) -> &mut Self
+    where
+        T: ToSql + Sync,
+    {
+        self.params.push(Param::Owned(Box::new(values)));
+        self.push("ANY(").push_placeholder().push(")")
+    }
+
+    /// Appends a fragment with parameters of its own to the statement.
+    ///
+    /// The fragment's parameters are referenced as `$1`, `$2`, etc, and are renumbered to follow the parameters
+    /// already added to the builder. Placeholders within quoted literals and identifiers are left untouched, but
+    /// those within dollar-quoted strings are not detected.
+    ///
+    /// Returns an error, leaving the builder unchanged, if the fragment references a parameter which was not provided.
+    pub fn push_fragment(
+        &mut self,
+        sql: &str,
+        params: &[&'a (dyn ToSql + Sync)],
+    ) -> Result<&mut Self, Error> {
+        let sql = renumber(sql, self.params.len(), params.len())?;
+        self.sql.push_str(&sql);
+        self.params
+            .extend(params.iter().map(|&param| Param::Borrowed(param)));
+        Ok(self)
+    }
+
+    /// Returns the text of the statement.
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    /// Returns the parameters of the statement, in order.
+    pub fn params(&self) -> Vec<&(dyn ToSql + Sync)> {
+        self.params
+            .iter()
+            .map(|param| match param {
+                Param::Borrowed(param) => *param,
+                Param::Owned(param) => &**param,
+            })
+            .collect()
+    }
+
+    fn push_placeholder(&mut self) -> &mut Self {
+        let _ = write!(self.sql, "${}", self.params.len());
+        self
+    }
+}
+
+// Offsets the index of each placeholder in `sql` outside of quotes.
+fn renumber(sql: &str, offset: usize, count: usize) -> Result<String, Error> {
+    let mut out = String::with_capacity(sql.len());
+    let mut quote = None;
+    let mut prev = None;
+    let mut chars = sql.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None if c == '$' && !prev.map_or(false, is_identifier_char) => {
+                let start = i + 1;
+                let mut end = start;
+                while let Some((j, d)) = chars.peek().copied() {
+                    if !d.is_ascii_digit() {
+                        break;
+                    }
+                    end = j + 1;
+                    chars.next();
+                }
+                if end > start {
+                    let idx = match sql[start..end].parse::<usize>() {
+                        Ok(idx) if idx >= 1 && idx <= count => idx,
+                        _ => {
+                            return Err(Error::parameters(
+                                format!(
+                                    "fragment references parameter ${} but {} were provided",
+                                    &sql[start..end],
+                                    count
+                                )
+                                .into(),
+                            ))
+                        }
+                    };
+                    let _ = write!(out, "${}", idx + offset);
+                    prev = Some('0');
+                    continue;
+                }
+            }
+            None => {}
+        }
+        out.push(c);
+        prev = Some(c);
+    }
+
+    Ok(out)
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
@@ -0,0 +1,142 @@
+use crate::query::check_param_count;
+use crate::types::{IsNull, ToSql};
+use crate::{Error, Row, Statement};
+use bytes::{Bytes, BytesMut};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The key identifying the result of a query in a `QueryCache`.
+///
+/// Keys consist of the text of the statement and the binary encoding of each of its parameters, so the same query
+/// executed with equal parameters produces equal keys, even when prepared separately or by different clients.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QueryCacheKey {
+    query: String,
+    params: Vec<Option<Bytes>>,
+}
+
+impl QueryCacheKey {
+    pub(crate) fn new(
+        statement: &Statement,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<QueryCacheKey, Error> {
+        check_param_count(statement, params.len())?;
+
+        let mut buf = BytesMut::new();
+        let params = params
+            .iter()
+            .zip(statement.params())
+            .enumerate()
+            .map(
+                |(idx, (param, ty))| match param.to_sql_checked(ty, &mut buf) {
+                    Ok(IsNull::No) => Ok(Some(buf.split().freeze())),
+                    Ok(IsNull::Yes) => {
+                        buf.clear();
+                        Ok(None)
+                    }
+                    Err(e) => Err(Error::to_sql(e, idx)),
+                },
+            )
+            .collect::<Result<_, _>>()?;
+
+        Ok(QueryCacheKey {
+            query: statement.query().to_string(),
+            params,
+        })
+    }
+
+    /// Returns the text of the statement.
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    /// Returns the binary encoding of each of the statement's parameters, with `None` representing `NULL`.
+    pub fn params(&self) -> &[Option<Bytes>] {
+        &self.params
+    }
+}
+
+/// A cache of query results, consulted by `Client::query_cached`.
+///
+/// Caches are registered with `Client::set_query_cache`. Implementations are responsible for expiring entries once
+/// their time to live has passed.
+pub trait QueryCache: Sync + Send {
+    /// Returns the cached rows for a query, if present and not expired.
+    fn get(&self, key: &QueryCacheKey) -> Option<Vec<Row>>;
+
+    /// Stores the rows returned by a query, to be returned by `get` until `ttl` has passed.
+    fn insert(&self, key: QueryCacheKey, rows: Vec<Row>, ttl: Duration);
+}
+
+impl<T> QueryCache for Arc<T>
+where
+    T: ?Sized + QueryCache,
+{
+    fn get(&self, key: &QueryCacheKey) -> Option<Vec<Row>> {
+        (**self).get(key)
+    }
+
+    fn insert(&self, key: QueryCacheKey, rows: Vec<Row>, ttl: Duration) {
+        (**self).insert(key, rows, ttl)
+    }
+}
+
+/// A simple in-memory `QueryCache`.
+///
+/// Expired entries are removed when they are looked up. The cache can be shared between clients by wrapping it in an
+/// `Arc`.
+#[derive(Default)]
+pub struct MemoryQueryCache {
+    entries: Mutex<HashMap<QueryCacheKey, Entry>>,
+}
+
+struct Entry {
+    // entries whose time to live overflows an `Instant` never expire
+    expires: Option<Instant>,
+    rows: Vec<Row>,
+}
+
+impl Entry {
+    fn expired(&self, now: Instant) -> bool {
+        matches!(self.expires, Some(expires) if expires <= now)
+    }
+}
+
+impl MemoryQueryCache {
+    /// Creates a new, empty cache.
+    pub fn new() -> MemoryQueryCache {
+        MemoryQueryCache::default()
+    }
+
+    /// Removes all entries from the cache.
+    pub fn clear(&self) {
+        self.entries.lock().clear();
+    }
+
+    /// Removes all expired entries from the cache.
+    pub fn purge_expired(&self) {
+        let now = Instant::now();
+        self.entries.lock().retain(|_, entry| !entry.expired(now));
+    }
+}
+
+impl QueryCache for MemoryQueryCache {
+    fn get(&self, key: &QueryCacheKey) -> Option<Vec<Row>> {
+        let mut entries = self.entries.lock();
+        match entries.get(key) {
+            Some(entry) if !entry.expired(Instant::now()) => Some(entry.rows.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, key: QueryCacheKey, rows: Vec<Row>, ttl: Duration) {
+        let expires = Instant::now().checked_add(ttl);
+        self.entries.lock().insert(key, Entry { expires, rows });
+    }
+}
@@ -0,0 +1,204 @@
+use crate::client::InnerClient;
+use crate::error::ParamRenderer;
+use crate::escape::escape_literal;
+use crate::metrics::Metrics;
+use crate::types::{IsNull, ToSql, Type};
+use crate::Error;
+use bytes::BytesMut;
+use postgres_protocol::types;
+use std::fmt::{self, Write};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A hook notified of each statement executed by a client.
+///
+/// Loggers are registered with `Client::set_query_logger`. Any `Fn(&QueryEvent<'_>)` closure can be used as a logger.
+pub trait QueryLogger: Sync + Send {
+    /// Called once a statement has finished executing, successfully or not.
+    fn log(&self, event: &QueryEvent<'_>);
+}
+
+impl<F> QueryLogger for F
+where
+    F: Fn(&QueryEvent<'_>) + Sync + Send,
+{
+    fn log(&self, event: &QueryEvent<'_>) {
+        self(event)
+    }
+}
+
+/// Information about the execution of a statement, passed to a `QueryLogger`.
+pub struct QueryEvent<'a> {
+    query: &'a str,
+    param_count: usize,
+    params: Option<&'a str>,
+    duration: Duration,
+    rows_affected: Option<u64>,
+    error: Option<&'a Error>,
+}
+
+impl<'a> QueryEvent<'a> {
+    /// Returns the text of the statement.
+    ///
+    /// For simple queries this may contain multiple statements.
+    pub fn query(&self) -> &'a str {
+        self.query
+    }
+
+    /// Returns the number of parameters bound to the statement.
+    pub fn param_count(&self) -> usize {
+        self.param_count
+    }
+
+    /// Returns the parameters bound to the statement, rendered by the client's log parameter renderer.
+    ///
+    /// This is `None` if no renderer has been set with `Client::set_log_param_renderer`.
+    pub fn params(&self) -> Option<&'a str> {
+        self.params
+    }
+
+    /// Returns the time taken to execute the statement.
+    ///
+    /// For queries returning rows, this is measured until the last row has been received, and so includes any time
+    /// spent by the caller between polls of the row stream.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Returns the number of rows returned or modified by the statement, if it completed.
+    ///
+    /// For simple queries containing multiple statements, this is the total across all of them.
+    pub fn rows_affected(&self) -> Option<u64> {
+        self.rows_affected
+    }
+
+    /// Returns the error produced by the statement, if it failed.
+    pub fn error(&self) -> Option<&'a Error> {
+        self.error
+    }
+}
+
+// Tracks the execution of a statement on behalf of the client's query logger and metrics.
+pub(crate) struct QueryLog {
+    logger: Option<Arc<dyn QueryLogger>>,
+    metrics: Option<Arc<dyn Metrics>>,
+    param_count: usize,
+    param_renderer: Option<Arc<ParamRenderer>>,
+    params: Option<String>,
+    start: Instant,
+}
+
+impl QueryLog {
+    pub fn start(client: &InnerClient, param_count: usize) -> Option<QueryLog> {
+        let logger = client.query_logger();
+        let metrics = client.metrics().cloned();
+        if logger.is_none() && metrics.is_none() {
+            return None;
+        }
+
+        let param_renderer = match logger {
+            Some(_) => client.log_param_renderer(),
+            None => None,
+        };
+
+        Some(QueryLog {
+            logger,
+            metrics,
+            param_count,
+            param_renderer,
+            params: None,
+            start: Instant::now(),
+        })
+    }
+
+    pub fn param_renderer(&self) -> Option<&Arc<ParamRenderer>> {
+        self.param_renderer.as_ref()
+    }
+
+    pub fn set_params(&mut self, params: String) {
+        self.params = Some(params);
+    }
+
+    pub fn finish(self, query: &str, rows_affected: Option<u64>, error: Option<&Error>) {
+        let duration = self.start.elapsed();
+
+        if let Some(metrics) = &self.metrics {
+            match error {
+                Some(_) => metrics.query_failed(duration),
+                None => metrics.query_completed(duration, rows_affected.unwrap_or(0)),
+            }
+        }
+
+        if let Some(logger) = &self.logger {
+            logger.log(&QueryEvent {
+                query,
+                param_count: self.param_count,
+                params: self.params.as_deref(),
+                duration,
+                rows_affected,
+                error,
+            });
+        }
+    }
+}
+
+/// Renders a query parameter as an SQL literal, for use by parameter renderers.
+///
+/// Booleans, numbers, strings, JSON, and byte strings are rendered as literals which could be pasted into the text of
+/// a query, and `NULL` values as `NULL`. Values of other types are rendered with their `Debug` implementations.
+///
+/// See `Client::set_log_param_renderer` and `Client::set_error_param_renderer`.
+pub fn render_param(ty: &Type, value: &dyn ToSql) -> String {
+    let mut buf = BytesMut::new();
+    match value.to_sql_checked(ty, &mut buf) {
+        Ok(IsNull::Yes) => return "NULL".to_string(),
+        Ok(IsNull::No) => {}
+        Err(_) => return format!("{:?}", value),
+    }
+
+    let literal = match *ty {
+        Type::BOOL => types::bool_from_sql(&buf).map(|v| v.to_string()).ok(),
+        Type::INT2 => types::int2_from_sql(&buf).map(|v| v.to_string()).ok(),
+        Type::INT4 => types::int4_from_sql(&buf).map(|v| v.to_string()).ok(),
+        Type::INT8 => types::int8_from_sql(&buf).map(|v| v.to_string()).ok(),
+        Type::OID => types::oid_from_sql(&buf).map(|v| v.to_string()).ok(),
+        Type::FLOAT4 => types::float4_from_sql(&buf)
+            .map(|v| float_literal(v, v.is_finite()))
+            .ok(),
+        Type::FLOAT8 => types::float8_from_sql(&buf)
+            .map(|v| float_literal(v, v.is_finite()))
+            .ok(),
+        Type::TEXT | Type::VARCHAR | Type::BPCHAR | Type::NAME | Type::UNKNOWN | Type::JSON => {
+            types::text_from_sql(&buf).map(escape_literal).ok()
+        }
+        // binary JSONB values are prefixed with a version number
+        Type::JSONB => buf
+            .split_first()
+            .filter(|(version, _)| **version == 1)
+            .and_then(|(_, json)| types::text_from_sql(json).ok())
+            .map(escape_literal),
+        Type::BYTEA => {
+            let mut literal = "'\\x".to_string();
+            for b in types::bytea_from_sql(&buf) {
+                let _ = write!(literal, "{:02x}", b);
+            }
+            literal.push('\'');
+            Some(literal)
+        }
+        _ => None,
+    };
+
+    literal.unwrap_or_else(|| format!("{:?}", value))
+}
+
+// Special float values are only valid as quoted literals.
+fn float_literal<T>(v: T, finite: bool) -> String
+where
+    T: fmt::Display,
+{
+    if finite {
+        v.to_string()
+    } else {
+        format!("'{}'", v)
+    }
+}
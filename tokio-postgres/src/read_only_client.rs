@@ -0,0 +1,229 @@
+use crate::to_statement::private::ToStatementType;
+use crate::types::{BorrowToSql, ToSql};
+use crate::{
+    Client, CopyOutStream, Error, Row, RowStream, SimpleQueryMessage, Statement, ToStatement,
+    Transaction,
+};
+
+// Statements which always write, identified by their first keyword.
+const WRITE_COMMANDS: &[&str] = &[
+    "ALTER", "CLUSTER", "COMMENT", "CREATE", "DELETE", "DROP", "GRANT", "INSERT", "MERGE",
+    "REFRESH", "REINDEX", "REVOKE", "TRUNCATE", "UPDATE", "VACUUM",
+];
+
+// Keywords which write wherever they appear, such as in a data-modifying `WITH` query.
+const WRITE_KEYWORDS: &[&str] = &["DELETE", "INSERT", "MERGE", "UPDATE"];
+
+/// A client which guards against writes, for example when connected to a replica.
+///
+/// On creation, the session's transactions are made read-only by default, so that the server rejects writes made by
+/// any statement, including those of functions. In addition, statements which obviously write, such as `INSERT`,
+/// `UPDATE`, `DELETE` and `MERGE` (also within `WITH` queries), DDL, and `COPY ... FROM`, are rejected before they are
+/// sent to the server. This check is a heuristic based on keywords, so a column named `update` can't be referenced
+/// without quoting it.
+///
+/// This is a guardrail against mistakes rather than a security boundary: a statement can still explicitly start a
+/// read-write transaction. Use a role without write privileges to enforce that no writes are made.
+#[derive(Debug)]
+pub struct ReadOnlyClient {
+    client: Client,
+}
+
+impl ReadOnlyClient {
+    /// Wraps a client, making the transactions of its session read-only by default.
+    pub async fn new(client: Client) -> Result<ReadOnlyClient, Error> {
+        client
+            .batch_execute("SET SESSION CHARACTERISTICS AS TRANSACTION READ ONLY")
+            .await?;
+        Ok(ReadOnlyClient { client })
+    }
+
+    /// Returns the wrapped client.
+    ///
+    /// Its session's transactions remain read-only by default.
+    pub fn into_inner(self) -> Client {
+        self.client
+    }
+
+    /// Like `Client::prepare`, but rejecting statements which write.
+    pub async fn prepare(&self, query: &str) -> Result<Statement, Error> {
+        check(query)?;
+        self.client.prepare(query).await
+    }
+
+    /// Like `Client::query`, but rejecting statements which write.
+    pub async fn query<T>(
+        &self,
+        statement: &T,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Vec<Row>, Error>
+    where
+        T: ?Sized + ToStatement,
+    {
+        check_statement(statement)?;
+        self.client.query(statement, params).await
+    }
+
+    /// Like `Client::query_one`, but rejecting statements which write.
+    pub async fn query_one<T>(
+        &self,
+        statement: &T,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Row, Error>
+    where
+        T: ?Sized + ToStatement,
+    {
+        check_statement(statement)?;
+        self.client.query_one(statement, params).await
+    }
+
+    /// Like `Client::query_opt`, but rejecting statements which write.
+    pub async fn query_opt<T>(
+        &self,
+        statement: &T,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Option<Row>, Error>
+    where
+        T: ?Sized + ToStatement,
+    {
+        check_statement(statement)?;
+        self.client.query_opt(statement, params).await
+    }
+
+    /// Like `Client::query_raw`, but rejecting statements which write.
+    pub async fn query_raw<T, P, I>(&self, statement: &T, params: I) -> Result<RowStream, Error>
+    where
+        T: ?Sized + ToStatement,
+        P: BorrowToSql,
+        I: IntoIterator<Item = P>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        check_statement(statement)?;
+        self.client.query_raw(statement, params).await
+    }
+
+    /// Like `Client::copy_out`, but rejecting statements which write.
+    pub async fn copy_out<T>(&self, statement: &T) -> Result<CopyOutStream, Error>
+    where
+        T: ?Sized + ToStatement,
+    {
+        check_statement(statement)?;
+        self.client.copy_out(statement).await
+    }
+
+    /// Like `Client::simple_query`, but rejecting queries containing statements which write.
+    pub async fn simple_query(&self, query: &str) -> Result<Vec<SimpleQueryMessage>, Error> {
+        check(query)?;
+        self.client.simple_query(query).await
+    }
+
+    /// Like `Client::batch_execute`, but rejecting queries containing statements which write.
+    pub async fn batch_execute(&self, query: &str) -> Result<(), Error> {
+        check(query)?;
+        self.client.batch_execute(query).await
+    }
+
+    /// Begins a read-only transaction.
+    ///
+    /// Statements run within the transaction are not checked by the client, but the server rejects any writes.
+    pub async fn transaction(&mut self) -> Result<Transaction<'_>, Error> {
+        self.client
+            .build_transaction()
+            .read_only(true)
+            .start()
+            .await
+    }
+}
+
+fn check_statement<T>(statement: &T) -> Result<(), Error>
+where
+    T: ?Sized + ToStatement,
+{
+    match statement.__convert() {
+        ToStatementType::Statement(statement) => check(statement.query()),
+        ToStatementType::Query(query) => check(query),
+    }
+}
+
+// Rejects queries containing a statement which obviously writes.
+fn check(query: &str) -> Result<(), Error> {
+    for statement in statements(query) {
+        let command = match statement.first() {
+            Some((command, _)) => command,
+            None => continue,
+        };
+
+        // `COPY (SELECT ... FROM ...) TO` only reads, so only a `FROM` outside of parentheses is a write
+        let copy_in = command == "COPY"
+            && statement
+                .iter()
+                .any(|(word, depth)| word == "FROM" && *depth == 0);
+        let write = copy_in
+            || WRITE_COMMANDS.contains(&&**command)
+            || statement
+                .iter()
+                .any(|(word, _)| WRITE_KEYWORDS.contains(&&**word));
+        if write {
+            return Err(Error::read_only(command));
+        }
+    }
+
+    Ok(())
+}
+
+// Splits a query into its statements, each a list of its unquoted words in upper case and their parenthesis depth.
+fn statements(query: &str) -> Vec<Vec<(String, usize)>> {
+    let mut statements = vec![vec![]];
+    let mut depth = 0usize;
+    let mut chars = query.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                // doubled quotes are escapes, which this skips over as two adjacent quoted strings
+                for d in chars.by_ref() {
+                    if d == c {
+                        break;
+                    }
+                }
+            }
+            '-' if chars.peek() == Some(&'-') => {
+                for d in chars.by_ref() {
+                    if d == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut prev = ' ';
+                for d in chars.by_ref() {
+                    if prev == '*' && d == '/' {
+                        break;
+                    }
+                    prev = d;
+                }
+            }
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ';' => {
+                statements.push(vec![]);
+                depth = 0;
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut word = c.to_uppercase().collect::<String>();
+                while let Some(&d) = chars.peek() {
+                    if !(d.is_alphanumeric() || d == '_' || d == '$') {
+                        break;
+                    }
+                    word.extend(d.to_uppercase());
+                    chars.next();
+                }
+                statements.last_mut().unwrap().push((word, depth));
+            }
+            _ => {}
+        }
+    }
+
+    statements
+}
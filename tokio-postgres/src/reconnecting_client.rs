@@ -0,0 +1,293 @@
+use crate::runtime::{self, CachingResolver};
+use crate::types::ToSql;
+use crate::{Client, Error, Row};
+use futures::future::BoxFuture;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+/// A policy controlling how idempotent queries run with a `ReconnectingClient` are retried after the connection to the
+/// server is lost.
+///
+/// Queries are only retried after errors indicating that the connection was lost, and never if the connection was in a
+/// transaction at the time, since the work done earlier in the transaction was lost with it. The delay before each
+/// retry starts at the initial backoff and doubles on each attempt, up to the maximum backoff.
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> ReconnectPolicy {
+        ReconnectPolicy::new()
+    }
+}
+
+impl ReconnectPolicy {
+    /// Creates a new policy.
+    ///
+    /// By default, queries are retried up to 3 times, with a backoff starting at 100 milliseconds and capped at 5
+    /// seconds.
+    pub fn new() -> ReconnectPolicy {
+        ReconnectPolicy {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+
+    /// Sets the maximum number of times a query will be retried.
+    ///
+    /// Defaults to 3.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets the delay before the first retry.
+    ///
+    /// Defaults to 100 milliseconds.
+    pub fn initial_backoff(mut self, initial_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    /// Sets the maximum delay between retries.
+    ///
+    /// Defaults to 5 seconds.
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Determines if a query which failed with the specified error after `retries` previous retries should be retried.
+    pub fn should_retry(&self, error: &Error, retries: u32) -> bool {
+        retries < self.max_retries && error.is_connection_loss()
+    }
+
+    /// Returns the delay to wait before the retry following `retries` previous retries.
+    pub fn backoff(&self, retries: u32) -> Duration {
+        let factor = 1u32.checked_shl(retries).unwrap_or(u32::MAX);
+        self.initial_backoff
+            .checked_mul(factor)
+            .map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff))
+    }
+}
+
+type ConnectFn = dyn Fn() -> BoxFuture<'static, Result<Client, Error>> + Sync + Send;
+
+/// A client which reconnects to the server once its connection has been lost.
+///
+/// Connections are made with the provided function when the client is first used, and whenever the previous
+/// connection has closed. The function is responsible for spawning the connection onto an executor:
+///
+/// ```no_run
+/// use tokio_postgres::{NoTls, ReconnectingClient};
+///
+/// # async fn f() -> Result<(), tokio_postgres::Error> {
+/// let client = ReconnectingClient::new(|| async {
+///     let (client, connection) = tokio_postgres::connect("host=localhost user=postgres", NoTls).await?;
+///     tokio::spawn(connection);
+///     Ok(client)
+/// });
+///
+/// let rows = client
+///     .query_idempotent("SELECT name FROM people WHERE id = $1", &[&1i32])
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Queries run with the `_idempotent` methods are transparently retried on a new connection, as configured by the
+/// client's `ReconnectPolicy`, if the connection is lost before they complete. As the server may have executed a query
+/// before the connection was lost, they should only be used for queries which are safe to run more than once. Other
+/// queries fail with the error that caused the connection to be lost, and the next query made reconnects.
+pub struct ReconnectingClient {
+    connect: Box<ConnectFn>,
+    policy: ReconnectPolicy,
+    resolver: Option<CachingResolver>,
+    client: Mutex<Option<Arc<Client>>>,
+}
+
+impl ReconnectingClient {
+    /// Creates a new client which connects with the provided function.
+    ///
+    /// No connection is made until the client is first used.
+    pub fn new<F, Fut>(connect: F) -> ReconnectingClient
+    where
+        F: Fn() -> Fut + Sync + Send + 'static,
+        Fut: Future<Output = Result<Client, Error>> + Send + 'static,
+    {
+        ReconnectingClient {
+            connect: Box::new(move || Box::pin(connect())),
+            policy: ReconnectPolicy::new(),
+            resolver: None,
+            client: Mutex::new(None),
+        }
+    }
+
+    /// Sets the policy controlling how idempotent queries are retried.
+    pub fn reconnect_policy(mut self, policy: ReconnectPolicy) -> ReconnectingClient {
+        self.policy = policy;
+        self
+    }
+
+    /// Sets a resolver whose cached addresses are discarded whenever the connection is lost.
+    ///
+    /// The resolver should also be registered with `Config::resolver` in the connect function, so that a host which
+    /// moved to a new address is re-resolved when reconnecting, while connections made with an intact cache, such as
+    /// those of other clients sharing the resolver, still avoid a lookup.
+    pub fn resolver(mut self, resolver: CachingResolver) -> ReconnectingClient {
+        self.resolver = Some(resolver);
+        self
+    }
+
+    /// Returns the client of the current connection, connecting if there is none or it has closed.
+    ///
+    /// Work done directly with the returned client is not retried. If the previous connection was lost in the middle
+    /// of a transaction, a closed connection error is returned once rather than silently carrying on outside of the
+    /// transaction.
+    pub async fn client(&self) -> Result<Arc<Client>, Error> {
+        self.current().await.map_err(|(e, _)| e)
+    }
+
+    // Like `client`, but also returning whether the operation can be retried after an error.
+    async fn current(&self) -> Result<Arc<Client>, (Error, bool)> {
+        let mut client = self.client.lock().await;
+        if let Some(old) = client.take() {
+            if !old.is_closed() {
+                *client = Some(old.clone());
+                return Ok(old);
+            }
+            self.connection_lost();
+            if old.in_transaction() {
+                return Err((Error::closed(), false));
+            }
+        }
+
+        let new = Arc::new((self.connect)().await.map_err(|e| (e, true))?);
+        *client = Some(new.clone());
+        Ok(new)
+    }
+
+    /// Like `Client::query`, but connecting first if necessary.
+    pub async fn query(
+        &self,
+        query: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Vec<Row>, Error> {
+        let client = self.client().await?;
+        self.run(
+            client,
+            |client| async move { client.query(query, params).await },
+        )
+        .await
+    }
+
+    /// Like `Client::execute`, but connecting first if necessary.
+    pub async fn execute(&self, query: &str, params: &[&(dyn ToSql + Sync)]) -> Result<u64, Error> {
+        let client = self.client().await?;
+        self.run(client, |client| async move {
+            client.execute(query, params).await
+        })
+        .await
+    }
+
+    /// Like `Client::query`, but retrying the query on a new connection if the connection is lost.
+    pub async fn query_idempotent(
+        &self,
+        query: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Vec<Row>, Error> {
+        self.retry(|client| async move { client.query(query, params).await })
+            .await
+    }
+
+    /// Like `Client::query_one`, but retrying the query on a new connection if the connection is lost.
+    pub async fn query_one_idempotent(
+        &self,
+        query: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Row, Error> {
+        self.retry(|client| async move { client.query_one(query, params).await })
+            .await
+    }
+
+    /// Like `Client::execute`, but retrying the statement on a new connection if the connection is lost.
+    ///
+    /// This should only be used for statements whose effects are the same no matter how many times they run, such as
+    /// upserts setting fixed values.
+    pub async fn execute_idempotent(
+        &self,
+        query: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<u64, Error> {
+        self.retry(|client| async move { client.execute(query, params).await })
+            .await
+    }
+
+    async fn retry<F, Fut, T>(&self, mut f: F) -> Result<T, Error>
+    where
+        F: FnMut(Arc<Client>) -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let mut retries = 0;
+
+        loop {
+            let (result, runtime) = match self.current().await {
+                Ok(client) => {
+                    let in_transaction = client.in_transaction();
+                    let runtime = client.runtime();
+                    let result = self.run(client, &mut f).await;
+                    // the rest of the transaction's work is gone along with the connection
+                    if in_transaction {
+                        return result;
+                    }
+                    (result, runtime)
+                }
+                Err((e, true)) => (Err(e), runtime::default_runtime()),
+                Err((e, false)) => return Err(e),
+            };
+
+            match result {
+                Err(e) if self.policy.should_retry(&e, retries) => {
+                    if let Some(runtime) = runtime {
+                        runtime.sleep(self.policy.backoff(retries)).await;
+                    }
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    // Runs an operation with a client, discarding the client if its connection is lost so that the error is only
+    // reported once.
+    async fn run<F, Fut, T>(&self, client: Arc<Client>, f: F) -> Result<T, Error>
+    where
+        F: FnOnce(Arc<Client>) -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let result = f(client.clone()).await;
+        if let Err(e) = &result {
+            if e.is_connection_loss() {
+                let mut current = self.client.lock().await;
+                if current.as_ref().map_or(false, |c| Arc::ptr_eq(c, &client)) {
+                    *current = None;
+                    self.connection_lost();
+                }
+            }
+        }
+        result
+    }
+
+    // Discards cached addresses so that reconnecting re-resolves hosts.
+    fn connection_lost(&self) {
+        if let Some(resolver) = &self.resolver {
+            resolver.clear();
+        }
+    }
+}
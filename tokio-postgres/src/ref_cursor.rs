@@ -0,0 +1,94 @@
+use crate::escape::escape_identifier;
+use crate::types::{FromSql, IsNull, ToSql, Type};
+use crate::{Error, GenericClient, Row};
+use bytes::BytesMut;
+use postgres_protocol::types;
+use std::error;
+use std::fmt;
+
+/// A reference to a cursor, as stored in a `refcursor` value.
+///
+/// Functions and procedures commonly return result sets by opening a cursor and returning its name. The rows of the
+/// cursor can then be retrieved with [`fetch`](#method.fetch) or [`fetch_all`](#method.fetch_all). Unless it was
+/// declared `WITH HOLD`, a cursor is closed at the end of the transaction in which it was opened, so it must be
+/// fetched from within that same transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RefCursor {
+    name: String,
+}
+
+impl RefCursor {
+    /// Creates a reference to the cursor with the specified name.
+    ///
+    /// This is useful to choose the name of a cursor opened by a procedure, by passing it as an argument.
+    pub fn new(name: impl Into<String>) -> RefCursor {
+        RefCursor { name: name.into() }
+    }
+
+    /// Returns the name of the cursor.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Fetches up to `n` rows from the cursor.
+    ///
+    /// An empty result indicates that the cursor is exhausted.
+    pub async fn fetch<C>(&self, client: &C, n: i64) -> Result<Vec<Row>, Error>
+    where
+        C: GenericClient + Sync,
+    {
+        let query = format!("FETCH FORWARD {} FROM {}", n, escape_identifier(&self.name));
+        client.query(query.as_str(), &[]).await
+    }
+
+    /// Fetches all of the remaining rows from the cursor.
+    pub async fn fetch_all<C>(&self, client: &C) -> Result<Vec<Row>, Error>
+    where
+        C: GenericClient + Sync,
+    {
+        let query = format!("FETCH ALL FROM {}", escape_identifier(&self.name));
+        client.query(query.as_str(), &[]).await
+    }
+
+    /// Closes the cursor, releasing its resources before the end of the transaction.
+    pub async fn close<C>(&self, client: &C) -> Result<(), Error>
+    where
+        C: GenericClient + Sync,
+    {
+        let query = format!("CLOSE {}", escape_identifier(&self.name));
+        client.execute(query.as_str(), &[]).await.map(|_| ())
+    }
+}
+
+impl fmt::Display for RefCursor {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_str(&self.name)
+    }
+}
+
+impl<'a> FromSql<'a> for RefCursor {
+    fn from_sql(_: &Type, raw: &'a [u8]) -> Result<RefCursor, Box<dyn error::Error + Sync + Send>> {
+        types::text_from_sql(raw).map(RefCursor::new)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::REFCURSOR
+    }
+}
+
+impl ToSql for RefCursor {
+    fn to_sql(
+        &self,
+        _: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn error::Error + Sync + Send>> {
+        types::text_to_sql(&self.name, out);
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        *ty == Type::REFCURSOR
+    }
+
+    postgres_types::to_sql_checked!();
+}
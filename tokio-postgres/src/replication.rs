@@ -0,0 +1,520 @@
+//! Utilities for working with the PostgreSQL streaming replication protocol.
+//!
+//! Replication commands can only be issued on connections which were opened in replication mode; see
+//! `Config::replication_mode`.
+
+use crate::client::{InnerClient, Responses};
+use crate::codec::FrontendMessage;
+use crate::connection::RequestMessages;
+use crate::copy_both::{self, CopyBothDuplex};
+use crate::escape::escape_identifier;
+use crate::simple_query::{self, SimpleColumn};
+use crate::types::PgLsn;
+use crate::{Client, Error, SimpleQueryMessage, SimpleQueryRow};
+use bytes::{Bytes, BytesMut};
+use fallible_iterator::FallibleIterator;
+use futures::{ready, SinkExt, Stream};
+use log::debug;
+use pin_project_lite::pin_project;
+use postgres_protocol::message::backend::Message;
+use postgres_protocol::message::frontend;
+use std::cmp;
+use std::fmt::Write;
+use std::io;
+use std::marker::PhantomPinned;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub use postgres_protocol::message::backend::{
+    BeginBody, Column, CommitBody, DeleteBody, InsertBody, LogicalReplicationMessage, OriginBody,
+    PrimaryKeepAliveBody, RelationBody, ReplicaIdentity, ReplicationMessage, StreamAbortBody,
+    StreamCommitBody, StreamStartBody, TruncateBody, Tuple, TupleData, TypeBody, UpdateBody,
+    XLogDataBody,
+};
+
+#[cfg(feature = "wal2json")]
+pub mod wal2json;
+
+// the number of seconds between the Unix epoch and the Postgres epoch of 2000-01-01
+const PG_EPOCH_OFFSET: u64 = 946_684_800;
+
+/// Information about the server, as returned by the `IDENTIFY_SYSTEM` replication command.
+#[derive(Debug, Clone)]
+pub struct IdentifySystem {
+    system_id: u64,
+    timeline: u32,
+    xlog_pos: PgLsn,
+    dbname: Option<String>,
+}
+
+impl IdentifySystem {
+    /// Returns the unique identifier of the database cluster.
+    pub fn system_id(&self) -> u64 {
+        self.system_id
+    }
+
+    /// Returns the server's current timeline.
+    pub fn timeline(&self) -> u32 {
+        self.timeline
+    }
+
+    /// Returns the server's current WAL flush position.
+    pub fn xlog_pos(&self) -> PgLsn {
+        self.xlog_pos
+    }
+
+    /// Returns the database connected to, or `None` for physical replication connections.
+    pub fn dbname(&self) -> Option<&str> {
+        self.dbname.as_deref()
+    }
+}
+
+/// A timeline history file, as returned by the `TIMELINE_HISTORY` replication command.
+#[derive(Debug, Clone)]
+pub struct TimelineHistory {
+    filename: String,
+    content: String,
+}
+
+impl TimelineHistory {
+    /// Returns the name of the history file.
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+
+    /// Returns the contents of the history file.
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+}
+
+/// A message sent by the server in response to the `BASE_BACKUP` replication command.
+#[non_exhaustive]
+pub enum BaseBackupMessage {
+    /// A row of one of the result sets which describe the backup, such as its starting and ending positions.
+    Row(SimpleQueryRow),
+    /// The start of a `COPY` of backup data.
+    ///
+    /// The server sends a single `COPY` whose data is framed with a message type byte.
+    CopyStart,
+    /// A chunk of backup data.
+    CopyData(Bytes),
+    /// The end of a `COPY` of backup data.
+    CopyDone,
+}
+
+pin_project! {
+    /// A stream of the messages making up a base backup.
+    pub struct BaseBackupStream {
+        responses: Responses,
+        columns: Option<Arc<[SimpleColumn]>>,
+        #[pin]
+        _p: PhantomPinned,
+    }
+}
+
+impl Stream for BaseBackupStream {
+    type Item = Result<BaseBackupMessage, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        loop {
+            let message = match ready!(this.responses.poll_next(cx)?) {
+                Message::RowDescription(body) => {
+                    let columns = body
+                        .fields()
+                        .map(|f| Ok(SimpleColumn::new(f.name().to_string())))
+                        .collect::<Vec<_>>()
+                        .map_err(Error::parse)?
+                        .into();
+                    *this.columns = Some(columns);
+                    continue;
+                }
+                Message::DataRow(body) => match &this.columns {
+                    Some(columns) => {
+                        BaseBackupMessage::Row(SimpleQueryRow::new(columns.clone(), body)?)
+                    }
+                    None => return Poll::Ready(Some(Err(Error::unexpected_message()))),
+                },
+                Message::CopyOutResponse(_) => BaseBackupMessage::CopyStart,
+                Message::CopyData(body) => BaseBackupMessage::CopyData(body.into_bytes()),
+                Message::CopyDone => BaseBackupMessage::CopyDone,
+                Message::CommandComplete(_) => continue,
+                Message::ReadyForQuery(_) => return Poll::Ready(None),
+                _ => return Poll::Ready(Some(Err(Error::unexpected_message()))),
+            };
+            return Poll::Ready(Some(Ok(message)));
+        }
+    }
+}
+
+pin_project! {
+    /// A stream of streaming replication messages.
+    ///
+    /// In addition to yielding the messages sent by the server, the stream keeps track of the WAL positions needed to
+    /// send standby status updates. The server expects these updates periodically, and immediately whenever a
+    /// keepalive message requests a reply; otherwise it may terminate the connection. The stream does not send them on
+    /// its own.
+    pub struct ReplicationStream {
+        #[pin]
+        stream: CopyBothDuplex<Bytes>,
+        physical: bool,
+        received_lsn: PgLsn,
+        flushed_lsn: PgLsn,
+        // reused to encode standby status updates
+        buf: BytesMut,
+    }
+}
+
+impl ReplicationStream {
+    pub(crate) fn new(stream: CopyBothDuplex<Bytes>, physical: bool) -> ReplicationStream {
+        ReplicationStream {
+            stream,
+            physical,
+            received_lsn: PgLsn::from(0),
+            flushed_lsn: PgLsn::from(0),
+            buf: BytesMut::new(),
+        }
+    }
+
+    /// Returns the position of the most recent WAL data received from the server.
+    ///
+    /// For physical replication this is the end of the data in the most recent `XLogData` message, and for logical
+    /// replication it is the end of WAL reported by the server in the most recent `XLogData` or keepalive message.
+    pub fn last_received_lsn(&self) -> PgLsn {
+        self.received_lsn
+    }
+
+    /// Returns the position reported by `set_flushed_lsn`.
+    pub fn flushed_lsn(&self) -> PgLsn {
+        self.flushed_lsn
+    }
+
+    /// Records that all data up to the specified position has been durably processed by the client.
+    ///
+    /// The position is reported to the server by the next call to `send_feedback`, which allows it to discard the WAL
+    /// which is no longer needed by this client.
+    pub fn set_flushed_lsn(self: Pin<&mut Self>, lsn: PgLsn) {
+        *self.project().flushed_lsn = lsn;
+    }
+
+    /// Sends a standby status update reporting the positions tracked by this stream.
+    ///
+    /// The last received position is reported as written, and the position set with `set_flushed_lsn` is reported as
+    /// both flushed and applied. If `reply` is set, the server will immediately respond with a keepalive message.
+    pub async fn send_feedback(self: Pin<&mut Self>, reply: bool) -> Result<(), Error> {
+        let write_lsn = self.received_lsn;
+        let flush_lsn = self.flushed_lsn;
+        self.standby_status_update(write_lsn, flush_lsn, flush_lsn, SystemTime::now(), reply)
+            .await
+    }
+
+    /// Sends a standby status update with explicitly specified positions.
+    pub async fn standby_status_update(
+        self: Pin<&mut Self>,
+        write_lsn: PgLsn,
+        flush_lsn: PgLsn,
+        apply_lsn: PgLsn,
+        timestamp: SystemTime,
+        reply: bool,
+    ) -> Result<(), Error> {
+        let mut this = self.project();
+        frontend::standby_status_update(
+            write_lsn.into(),
+            flush_lsn.into(),
+            apply_lsn.into(),
+            pg_timestamp(timestamp),
+            reply as u8,
+            this.buf,
+        );
+        let buf = this.buf.split().freeze();
+        this.stream.send(buf).await
+    }
+
+    /// Ends the client's half of the replication stream.
+    ///
+    /// The server will stop streaming, and the stream will end once any messages already in flight are received.
+    pub async fn close(self: Pin<&mut Self>) -> Result<(), Error> {
+        self.project().stream.close().await
+    }
+}
+
+impl Stream for ReplicationStream {
+    type Item = Result<ReplicationMessage<Bytes>, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+
+        let buf = match ready!(this.stream.poll_next(cx)) {
+            Some(Ok(buf)) => buf,
+            Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+            None => return Poll::Ready(None),
+        };
+
+        let message = ReplicationMessage::parse(&buf).map_err(Error::parse)?;
+        // logical messages are decoded from WAL the server has already read, so everything up to the end of WAL it
+        // reports has been received, while physical data is only received up to the end of each message
+        let lsn = match &message {
+            ReplicationMessage::XLogData(body) if *this.physical => {
+                Some(body.wal_start() + body.data().len() as u64)
+            }
+            ReplicationMessage::XLogData(body) => Some(body.wal_end()),
+            ReplicationMessage::PrimaryKeepAlive(body) if !*this.physical => Some(body.wal_end()),
+            _ => None,
+        };
+        if let Some(lsn) = lsn {
+            *this.received_lsn = cmp::max(*this.received_lsn, PgLsn::from(lsn));
+        }
+        Poll::Ready(Some(Ok(message)))
+    }
+}
+
+pin_project! {
+    /// A stream of messages from a logical replication slot using the `pgoutput` output plugin.
+    ///
+    /// This wraps a `ReplicationStream`, decoding the contents of its `XLogData` messages. The stream must be started
+    /// with the plugin's `proto_version` and `publication_names` options, and the `streaming` option to receive large
+    /// transactions before they commit.
+    pub struct LogicalReplicationStream {
+        #[pin]
+        stream: ReplicationStream,
+        in_streamed_transaction: bool,
+    }
+}
+
+impl LogicalReplicationStream {
+    /// Creates a new `LogicalReplicationStream` decoding the messages of a `ReplicationStream`.
+    pub fn new(stream: ReplicationStream) -> LogicalReplicationStream {
+        LogicalReplicationStream {
+            stream,
+            in_streamed_transaction: false,
+        }
+    }
+
+    /// Like `ReplicationStream::last_received_lsn`.
+    pub fn last_received_lsn(&self) -> PgLsn {
+        self.stream.last_received_lsn()
+    }
+
+    /// Like `ReplicationStream::flushed_lsn`.
+    pub fn flushed_lsn(&self) -> PgLsn {
+        self.stream.flushed_lsn()
+    }
+
+    /// Like `ReplicationStream::set_flushed_lsn`.
+    pub fn set_flushed_lsn(self: Pin<&mut Self>, lsn: PgLsn) {
+        self.project().stream.set_flushed_lsn(lsn)
+    }
+
+    /// Like `ReplicationStream::send_feedback`.
+    pub async fn send_feedback(self: Pin<&mut Self>, reply: bool) -> Result<(), Error> {
+        self.project().stream.send_feedback(reply).await
+    }
+
+    /// Like `ReplicationStream::standby_status_update`.
+    pub async fn standby_status_update(
+        self: Pin<&mut Self>,
+        write_lsn: PgLsn,
+        flush_lsn: PgLsn,
+        apply_lsn: PgLsn,
+        timestamp: SystemTime,
+        reply: bool,
+    ) -> Result<(), Error> {
+        self.project()
+            .stream
+            .standby_status_update(write_lsn, flush_lsn, apply_lsn, timestamp, reply)
+            .await
+    }
+
+    /// Like `ReplicationStream::close`.
+    pub async fn close(self: Pin<&mut Self>) -> Result<(), Error> {
+        self.project().stream.close().await
+    }
+}
+
+impl Stream for LogicalReplicationStream {
+    type Item = Result<ReplicationMessage<LogicalReplicationMessage>, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+
+        let message = match ready!(this.stream.poll_next(cx)) {
+            Some(Ok(message)) => message,
+            Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+            None => return Poll::Ready(None),
+        };
+
+        let message = match message {
+            ReplicationMessage::XLogData(body) => {
+                let in_streamed_transaction = *this.in_streamed_transaction;
+                let body = body
+                    .map_data(|data| {
+                        LogicalReplicationMessage::parse(&data, in_streamed_transaction)
+                    })
+                    .map_err(Error::parse)?;
+                match body.data() {
+                    LogicalReplicationMessage::StreamStart(_) => {
+                        *this.in_streamed_transaction = true
+                    }
+                    LogicalReplicationMessage::StreamStop => *this.in_streamed_transaction = false,
+                    _ => {}
+                }
+                ReplicationMessage::XLogData(body)
+            }
+            ReplicationMessage::PrimaryKeepAlive(body) => {
+                ReplicationMessage::PrimaryKeepAlive(body)
+            }
+            _ => return Poll::Ready(Some(Err(Error::unexpected_message()))),
+        };
+        Poll::Ready(Some(Ok(message)))
+    }
+}
+
+pub(crate) async fn identify_system(client: &Client) -> Result<IdentifySystem, Error> {
+    let row = single_row(client, "IDENTIFY_SYSTEM").await?;
+    Ok(IdentifySystem {
+        system_id: parse_column(&row, "systemid")?,
+        timeline: parse_column(&row, "timeline")?,
+        xlog_pos: parse_column(&row, "xlogpos")?,
+        dbname: row.try_get("dbname")?.map(|s| s.to_string()),
+    })
+}
+
+pub(crate) async fn timeline_history(
+    client: &Client,
+    timeline: u32,
+) -> Result<TimelineHistory, Error> {
+    let row = single_row(client, &format!("TIMELINE_HISTORY {}", timeline)).await?;
+    Ok(TimelineHistory {
+        filename: parse_column(&row, "filename")?,
+        content: parse_column(&row, "content")?,
+    })
+}
+
+async fn single_row(client: &Client, query: &str) -> Result<SimpleQueryRow, Error> {
+    for message in client.simple_query(query).await? {
+        if let SimpleQueryMessage::Row(row) = message {
+            return Ok(row);
+        }
+    }
+    Err(Error::unexpected_message())
+}
+
+fn parse_column<T>(row: &SimpleQueryRow, column: &str) -> Result<T, Error>
+where
+    T: FromStr,
+{
+    row.try_get(column)?
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(Error::unexpected_message)
+}
+
+pub(crate) async fn start_logical_replication(
+    client: &InnerClient,
+    slot: &str,
+    start_lsn: PgLsn,
+    options: &[(&str, &str)],
+) -> Result<ReplicationStream, Error> {
+    let mut query = format!(
+        "START_REPLICATION SLOT {} LOGICAL {}",
+        escape_identifier(slot),
+        start_lsn
+    );
+    if !options.is_empty() {
+        query.push_str(" (");
+        for (i, (name, value)) in options.iter().enumerate() {
+            if i > 0 {
+                query.push_str(", ");
+            }
+            // the replication command lexer only understands standard string literals
+            let _ = write!(
+                query,
+                "{} '{}'",
+                escape_identifier(name),
+                value.replace('\'', "''")
+            );
+        }
+        query.push(')');
+    }
+
+    let stream = copy_both::copy_both_simple(client, &query).await?;
+    Ok(ReplicationStream::new(stream, false))
+}
+
+pub(crate) async fn start_physical_replication(
+    client: &InnerClient,
+    slot: Option<&str>,
+    start_lsn: PgLsn,
+    timeline: Option<u32>,
+) -> Result<ReplicationStream, Error> {
+    let mut query = "START_REPLICATION".to_string();
+    if let Some(slot) = slot {
+        let _ = write!(query, " SLOT {}", escape_identifier(slot));
+    }
+    let _ = write!(query, " PHYSICAL {}", start_lsn);
+    if let Some(timeline) = timeline {
+        let _ = write!(query, " TIMELINE {}", timeline);
+    }
+
+    let stream = copy_both::copy_both_simple(client, &query).await?;
+    Ok(ReplicationStream::new(stream, true))
+}
+
+pub(crate) async fn base_backup(
+    client: &Client,
+    options: &[(&str, Option<&str>)],
+) -> Result<BaseBackupStream, Error> {
+    let mut query = "BASE_BACKUP".to_string();
+    if !options.is_empty() {
+        query.push_str(" (");
+        for (i, (name, value)) in options.iter().enumerate() {
+            // names can't be quoted, so only bare keywords are accepted
+            if name.is_empty() || !name.bytes().all(|b| b.is_ascii_alphabetic() || b == b'_') {
+                return Err(Error::encode(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("invalid BASE_BACKUP option name `{}`", name),
+                )));
+            }
+            if i > 0 {
+                query.push_str(", ");
+            }
+            query.push_str(name);
+            if let Some(value) = value {
+                let _ = write!(query, " '{}'", value.replace('\'', "''"));
+            }
+        }
+        query.push(')');
+    }
+
+    // older servers use a different syntax for options, and don't frame the backup data
+    let row = single_row(client, "SHOW server_version_num").await?;
+    let version = parse_column::<u32>(&row, "server_version_num")?;
+    if version < 150_000 {
+        return Err(Error::config(
+            "BASE_BACKUP requires PostgreSQL 15 or newer".into(),
+        ));
+    }
+
+    debug!("executing base backup: {}", query);
+    let buf = simple_query::encode(client.inner(), &query)?;
+    let responses = client
+        .inner()
+        .send(RequestMessages::Single(FrontendMessage::Raw(buf)))
+        .await?;
+    Ok(BaseBackupStream {
+        responses,
+        columns: None,
+        _p: PhantomPinned,
+    })
+}
+
+fn pg_timestamp(time: SystemTime) -> i64 {
+    let epoch = UNIX_EPOCH + Duration::from_secs(PG_EPOCH_OFFSET);
+    match time.duration_since(epoch) {
+        Ok(duration) => duration.as_micros() as i64,
+        Err(e) => -(e.duration().as_micros() as i64),
+    }
+}
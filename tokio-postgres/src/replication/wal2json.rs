@@ -0,0 +1,388 @@
+//! Decoding of logical replication streams using the `wal2json` output plugin.
+//!
+//! Only version 2 of the plugin's output format is supported, in which each change is sent as a separate JSON
+//! object. The stream must be started with the `format-version` option set to `2`. The `include-xids`,
+//! `include-timestamp`, `include-lsn`, `include-origin`, `include-type-oids`, `include-not-null` and `include-pk`
+//! options control which of the optional fields below are populated.
+
+use crate::replication::{ReplicationMessage, ReplicationStream};
+use crate::types::{Oid, PgLsn};
+use crate::Error;
+use futures::{ready, Stream};
+use pin_project_lite::pin_project;
+use serde_1::de::Error as _;
+use serde_1::{Deserialize, Deserializer};
+use serde_json_1::Value;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::SystemTime;
+
+/// A message produced by the `wal2json` output plugin.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(crate = "serde_1", tag = "action")]
+#[non_exhaustive]
+pub enum Wal2JsonMessage {
+    /// The start of a transaction.
+    #[serde(rename = "B")]
+    Begin(TransactionBody),
+    /// The end of a transaction.
+    #[serde(rename = "C")]
+    Commit(TransactionBody),
+    /// A row was inserted.
+    #[serde(rename = "I")]
+    Insert(ChangeBody),
+    /// A row was updated.
+    #[serde(rename = "U")]
+    Update(ChangeBody),
+    /// A row was deleted.
+    #[serde(rename = "D")]
+    Delete(ChangeBody),
+    /// A table was truncated.
+    #[serde(rename = "T")]
+    Truncate(ChangeBody),
+    /// A message emitted by `pg_logical_emit_message`.
+    #[serde(rename = "M")]
+    Message(MessageBody),
+}
+
+impl Wal2JsonMessage {
+    /// Parses a message from the contents of an `XLogData` message.
+    pub fn parse(buf: &[u8]) -> io::Result<Wal2JsonMessage> {
+        serde_json_1::from_slice(buf).map_err(io::Error::from)
+    }
+}
+
+/// The body of a `Begin` or `Commit` message.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(crate = "serde_1")]
+pub struct TransactionBody {
+    xid: Option<u32>,
+    timestamp: Option<String>,
+    origin: Option<u32>,
+    #[serde(default, deserialize_with = "deserialize_lsn")]
+    lsn: Option<PgLsn>,
+    #[serde(default, rename = "nextlsn", deserialize_with = "deserialize_lsn")]
+    next_lsn: Option<PgLsn>,
+}
+
+impl TransactionBody {
+    /// Returns the ID of the transaction, if `include-xids` is set.
+    pub fn xid(&self) -> Option<u32> {
+        self.xid
+    }
+
+    /// Returns the commit timestamp of the transaction, if `include-timestamp` is set.
+    ///
+    /// The timestamp is formatted as by the server's `timestamptz` output function.
+    pub fn timestamp(&self) -> Option<&str> {
+        self.timestamp.as_deref()
+    }
+
+    /// Returns the origin of the transaction, if `include-origin` is set and the transaction has one.
+    pub fn origin(&self) -> Option<u32> {
+        self.origin
+    }
+
+    /// Returns the position of the message, if `include-lsn` is set.
+    pub fn lsn(&self) -> Option<PgLsn> {
+        self.lsn
+    }
+
+    /// Returns the position following the end of the transaction, if `include-lsn` is set.
+    ///
+    /// This is only present in `Begin` messages.
+    pub fn next_lsn(&self) -> Option<PgLsn> {
+        self.next_lsn
+    }
+}
+
+/// The body of an `Insert`, `Update`, `Delete` or `Truncate` message.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(crate = "serde_1")]
+pub struct ChangeBody {
+    xid: Option<u32>,
+    timestamp: Option<String>,
+    origin: Option<u32>,
+    #[serde(default, deserialize_with = "deserialize_lsn")]
+    lsn: Option<PgLsn>,
+    schema: String,
+    table: String,
+    #[serde(default)]
+    columns: Vec<Column>,
+    #[serde(default)]
+    identity: Vec<Column>,
+    #[serde(default)]
+    pk: Vec<KeyColumn>,
+}
+
+impl ChangeBody {
+    /// Returns the ID of the transaction, if `include-xids` is set.
+    pub fn xid(&self) -> Option<u32> {
+        self.xid
+    }
+
+    /// Returns the commit timestamp of the transaction, if `include-timestamp` is set.
+    pub fn timestamp(&self) -> Option<&str> {
+        self.timestamp.as_deref()
+    }
+
+    /// Returns the origin of the transaction, if `include-origin` is set and the transaction has one.
+    pub fn origin(&self) -> Option<u32> {
+        self.origin
+    }
+
+    /// Returns the position of the change, if `include-lsn` is set.
+    pub fn lsn(&self) -> Option<PgLsn> {
+        self.lsn
+    }
+
+    /// Returns the schema of the table.
+    pub fn schema(&self) -> &str {
+        &self.schema
+    }
+
+    /// Returns the name of the table.
+    pub fn table(&self) -> &str {
+        &self.table
+    }
+
+    /// Returns the new values of the row's columns.
+    ///
+    /// This is empty for `Delete` and `Truncate` messages.
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    /// Returns the values of the row's replica identity columns before the change.
+    ///
+    /// This is only present in `Update` and `Delete` messages for tables with a replica identity.
+    pub fn identity(&self) -> &[Column] {
+        &self.identity
+    }
+
+    /// Returns the table's primary key columns, if `include-pk` is set.
+    pub fn pk(&self) -> &[KeyColumn] {
+        &self.pk
+    }
+}
+
+/// The body of a `Message` message.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(crate = "serde_1")]
+pub struct MessageBody {
+    xid: Option<u32>,
+    timestamp: Option<String>,
+    origin: Option<u32>,
+    #[serde(default, deserialize_with = "deserialize_lsn")]
+    lsn: Option<PgLsn>,
+    transactional: bool,
+    prefix: String,
+    content: String,
+}
+
+impl MessageBody {
+    /// Returns the ID of the transaction, if `include-xids` is set and the message is transactional.
+    pub fn xid(&self) -> Option<u32> {
+        self.xid
+    }
+
+    /// Returns the commit timestamp of the transaction, if `include-timestamp` is set and the message is
+    /// transactional.
+    pub fn timestamp(&self) -> Option<&str> {
+        self.timestamp.as_deref()
+    }
+
+    /// Returns the origin of the transaction, if `include-origin` is set and the message has one.
+    pub fn origin(&self) -> Option<u32> {
+        self.origin
+    }
+
+    /// Returns the position of the message, if `include-lsn` is set.
+    pub fn lsn(&self) -> Option<PgLsn> {
+        self.lsn
+    }
+
+    /// Determines if the message was emitted as part of a transaction.
+    pub fn transactional(&self) -> bool {
+        self.transactional
+    }
+
+    /// Returns the prefix of the message.
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Returns the content of the message.
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+}
+
+/// A column value in a `wal2json` change.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(crate = "serde_1")]
+pub struct Column {
+    name: String,
+    #[serde(rename = "type")]
+    type_name: String,
+    #[serde(rename = "typeoid")]
+    type_oid: Option<Oid>,
+    optional: Option<bool>,
+    #[serde(default)]
+    value: Value,
+}
+
+impl Column {
+    /// Returns the name of the column.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the name of the column's type.
+    pub fn type_name(&self) -> &str {
+        &self.type_name
+    }
+
+    /// Returns the OID of the column's type, if `include-type-oids` is set.
+    pub fn type_oid(&self) -> Option<Oid> {
+        self.type_oid
+    }
+
+    /// Returns whether the column is nullable, if `include-not-null` is set.
+    pub fn optional(&self) -> Option<bool> {
+        self.optional
+    }
+
+    /// Returns the value of the column.
+    ///
+    /// Numeric and boolean values are represented as the corresponding JSON types, `NULL` as JSON `null`, and all other
+    /// values as strings in the type's text format.
+    pub fn value(&self) -> &Value {
+        &self.value
+    }
+}
+
+/// A primary key column of the table of a `wal2json` change.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(crate = "serde_1")]
+pub struct KeyColumn {
+    name: String,
+    #[serde(rename = "type")]
+    type_name: String,
+    #[serde(rename = "typeoid")]
+    type_oid: Option<Oid>,
+}
+
+impl KeyColumn {
+    /// Returns the name of the column.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the name of the column's type.
+    pub fn type_name(&self) -> &str {
+        &self.type_name
+    }
+
+    /// Returns the OID of the column's type, if `include-type-oids` is set.
+    pub fn type_oid(&self) -> Option<Oid> {
+        self.type_oid
+    }
+}
+
+fn deserialize_lsn<'de, D>(deserializer: D) -> Result<Option<PgLsn>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<String>::deserialize(deserializer)? {
+        Some(s) => s
+            .parse()
+            .map(Some)
+            .map_err(|_| D::Error::custom(format!("invalid LSN `{}`", s))),
+        None => Ok(None),
+    }
+}
+
+pin_project! {
+    /// A stream of messages from a logical replication slot using the `wal2json` output plugin.
+    ///
+    /// This wraps a `ReplicationStream`, decoding the contents of its `XLogData` messages.
+    pub struct Wal2JsonStream {
+        #[pin]
+        stream: ReplicationStream,
+    }
+}
+
+impl Wal2JsonStream {
+    /// Creates a new `Wal2JsonStream` decoding the messages of a `ReplicationStream`.
+    pub fn new(stream: ReplicationStream) -> Wal2JsonStream {
+        Wal2JsonStream { stream }
+    }
+
+    /// Like `ReplicationStream::last_received_lsn`.
+    pub fn last_received_lsn(&self) -> PgLsn {
+        self.stream.last_received_lsn()
+    }
+
+    /// Like `ReplicationStream::flushed_lsn`.
+    pub fn flushed_lsn(&self) -> PgLsn {
+        self.stream.flushed_lsn()
+    }
+
+    /// Like `ReplicationStream::set_flushed_lsn`.
+    pub fn set_flushed_lsn(self: Pin<&mut Self>, lsn: PgLsn) {
+        self.project().stream.set_flushed_lsn(lsn)
+    }
+
+    /// Like `ReplicationStream::send_feedback`.
+    pub async fn send_feedback(self: Pin<&mut Self>, reply: bool) -> Result<(), Error> {
+        self.project().stream.send_feedback(reply).await
+    }
+
+    /// Like `ReplicationStream::standby_status_update`.
+    pub async fn standby_status_update(
+        self: Pin<&mut Self>,
+        write_lsn: PgLsn,
+        flush_lsn: PgLsn,
+        apply_lsn: PgLsn,
+        timestamp: SystemTime,
+        reply: bool,
+    ) -> Result<(), Error> {
+        self.project()
+            .stream
+            .standby_status_update(write_lsn, flush_lsn, apply_lsn, timestamp, reply)
+            .await
+    }
+
+    /// Like `ReplicationStream::close`.
+    pub async fn close(self: Pin<&mut Self>) -> Result<(), Error> {
+        self.project().stream.close().await
+    }
+}
+
+impl Stream for Wal2JsonStream {
+    type Item = Result<ReplicationMessage<Wal2JsonMessage>, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let message = match ready!(self.project().stream.poll_next(cx)) {
+            Some(Ok(message)) => message,
+            Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+            None => return Poll::Ready(None),
+        };
+
+        let message = match message {
+            ReplicationMessage::XLogData(body) => {
+                let body = body
+                    .map_data(|data| Wal2JsonMessage::parse(&data))
+                    .map_err(Error::parse)?;
+                ReplicationMessage::XLogData(body)
+            }
+            ReplicationMessage::PrimaryKeepAlive(body) => {
+                ReplicationMessage::PrimaryKeepAlive(body)
+            }
+            _ => return Poll::Ready(Some(Err(Error::unexpected_message()))),
+        };
+        Poll::Ready(Some(Ok(message)))
+    }
+}
@@ -0,0 +1,77 @@
+use crate::error::SqlState;
+use crate::Error;
+use std::time::Duration;
+
+/// A policy controlling how transactions are retried after serialization failures and deadlocks.
+///
+/// Transactions are retried when they fail with a `serialization_failure` (`40001`) or `deadlock_detected` (`40P01`)
+/// error. The delay before each retry starts at the initial backoff and doubles on each attempt, up to the maximum
+/// backoff.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy::new()
+    }
+}
+
+impl RetryPolicy {
+    /// Creates a new policy.
+    ///
+    /// By default, transactions are retried up to 5 times, with a backoff starting at 10 milliseconds and capped at 1
+    /// second.
+    pub fn new() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+        }
+    }
+
+    /// Sets the maximum number of times a transaction will be retried.
+    ///
+    /// Defaults to 5.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets the delay before the first retry.
+    ///
+    /// Defaults to 10 milliseconds.
+    pub fn initial_backoff(mut self, initial_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self
+    }
+
+    /// Sets the maximum delay between retries.
+    ///
+    /// Defaults to 1 second.
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Determines if a transaction which failed with the specified error after `retries` previous retries should be
+    /// retried.
+    pub fn should_retry(&self, error: &Error, retries: u32) -> bool {
+        retries < self.max_retries
+            && matches!(
+                error.code(),
+                Some(&SqlState::T_R_SERIALIZATION_FAILURE) | Some(&SqlState::T_R_DEADLOCK_DETECTED)
+            )
+    }
+
+    /// Returns the delay to wait before the retry following `retries` previous retries.
+    pub fn backoff(&self, retries: u32) -> Duration {
+        let factor = 1u32.checked_shl(retries).unwrap_or(u32::MAX);
+        self.initial_backoff
+            .checked_mul(factor)
+            .map_or(self.max_backoff, |backoff| backoff.min(self.max_backoff))
+    }
+}
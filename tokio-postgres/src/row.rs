@@ -1,12 +1,15 @@
 //! Rows.
 
 use crate::row::sealed::{AsName, Sealed};
+use crate::row_text;
 use crate::simple_query::SimpleColumn;
 use crate::statement::Column;
 use crate::types::{FromSql, Type, WrongType};
 use crate::{Error, Statement};
+use bytes::Bytes;
 use fallible_iterator::FallibleIterator;
 use postgres_protocol::message::backend::DataRowBody;
+use std::collections::HashMap;
 use std::fmt;
 use std::ops::Range;
 use std::str;
@@ -95,6 +98,7 @@ where
 }
 
 /// A row of data returned from the database by a query.
+#[derive(Clone)]
 pub struct Row {
     statement: Statement,
     body: DataRowBody,
@@ -120,6 +124,7 @@ impl Row {
     }
 
     /// Returns information about the columns of data in the row.
+    #[inline]
     pub fn columns(&self) -> &[Column] {
         self.statement.columns()
     }
@@ -182,7 +187,84 @@ impl Row {
         FromSql::from_sql_nullable(ty, self.col_buffer(idx)).map_err(|e| Error::from_sql(e, idx))
     }
 
+    /// Returns the raw binary representation of a value in the row, or `None` if it is `NULL`.
+    ///
+    /// The value can be specified either by its numeric index in the row, or by its column name. The returned buffer
+    /// is a reference-counted slice of the row's storage, so no data is copied and it can outlive the row.
+    /// For `BYTEA` values, the raw representation is the value itself, so this avoids the copy made when decoding them
+    /// as `Bytes`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is out of bounds.
+    pub fn get_raw<I>(&self, idx: I) -> Option<Bytes>
+    where
+        I: RowIndex + fmt::Display,
+    {
+        match self.get_raw_inner(&idx) {
+            Ok(ok) => ok,
+            Err(err) => panic!("error retrieving column {}: {}", idx, err),
+        }
+    }
+
+    /// Like `Row::get_raw`, but returns a `Result` rather than panicking.
+    pub fn try_get_raw<I>(&self, idx: I) -> Result<Option<Bytes>, Error>
+    where
+        I: RowIndex + fmt::Display,
+    {
+        self.get_raw_inner(&idx)
+    }
+
+    fn get_raw_inner<I>(&self, idx: &I) -> Result<Option<Bytes>, Error>
+    where
+        I: RowIndex + fmt::Display,
+    {
+        let idx = match idx.__idx(self.columns()) {
+            Some(idx) => idx,
+            None => return Err(Error::column(idx.to_string())),
+        };
+
+        let range = self.ranges[idx].clone();
+        Ok(range.map(|range| self.body.storage_bytes().slice(range)))
+    }
+
+    /// Converts the row into a map from column names to values rendered as text, with `NULL` values as `None`.
+    ///
+    /// This is intended for tools which display or export the results of arbitrary queries. Values are rendered as
+    /// described in `Row::into_text_vec`. If several columns have the same name, the map holds the value of the last
+    /// one.
+    pub fn into_map(self) -> HashMap<String, Option<String>> {
+        let values = self.text_values();
+        self.columns()
+            .iter()
+            .map(|column| column.name().to_string())
+            .zip(values)
+            .collect()
+    }
+
+    /// Converts the row into its values rendered as text, with `NULL` values as `None`.
+    ///
+    /// Values of the built-in scalar types, along with enums, domains, and arrays of them, are rendered in the text
+    /// format the server would use, except that `TIMESTAMP WITH TIME ZONE` values are always rendered in UTC and
+    /// floating point numbers may differ in how they are formatted. Values of other types, such as composites and
+    /// ranges, are rendered as their raw binary representation in the hexadecimal format of `BYTEA` values.
+    pub fn into_text_vec(self) -> Vec<Option<String>> {
+        self.text_values()
+    }
+
+    fn text_values(&self) -> Vec<Option<String>> {
+        self.columns()
+            .iter()
+            .enumerate()
+            .map(|(idx, column)| {
+                self.col_buffer(idx)
+                    .map(|raw| row_text::render(column.type_(), raw))
+            })
+            .collect()
+    }
+
     /// Get the raw bytes for the column at the given index.
+    #[inline]
     fn col_buffer(&self, idx: usize) -> Option<&[u8]> {
         let range = self.ranges[idx].to_owned()?;
         Some(&self.body.buffer()[range])
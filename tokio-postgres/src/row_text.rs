@@ -0,0 +1,134 @@
+use crate::types::{FromSql, Kind, PgJsonPath, PgLsn, PgMoney, PgNumeric, Type};
+use bytes::BytesMut;
+use fallible_iterator::FallibleIterator;
+use postgres_protocol::types::{self, text};
+use std::fmt::Write;
+
+// Renders a non-NULL value in its text format, falling back to its binary format in the text format of `BYTEA`.
+pub(crate) fn render(ty: &Type, raw: &[u8]) -> String {
+    try_render(ty, raw).unwrap_or_else(|| format!("\\x{}", hex(raw)))
+}
+
+fn try_render(ty: &Type, raw: &[u8]) -> Option<String> {
+    let text = match *ty {
+        Type::BOOL => {
+            let v = types::bool_from_sql(raw).ok()?;
+            if v { "t" } else { "f" }.to_string()
+        }
+        Type::CHAR => (raw.first().copied()? as char).to_string(),
+        Type::INT2 => types::int2_from_sql(raw).ok()?.to_string(),
+        Type::INT4 => types::int4_from_sql(raw).ok()?.to_string(),
+        Type::INT8 => types::int8_from_sql(raw).ok()?.to_string(),
+        Type::OID => types::oid_from_sql(raw).ok()?.to_string(),
+        Type::FLOAT4 => float(f64::from(types::float4_from_sql(raw).ok()?)),
+        Type::FLOAT8 => float(types::float8_from_sql(raw).ok()?),
+        Type::NUMERIC => PgNumeric::from_sql(ty, raw).ok()?.to_string(),
+        Type::MONEY => PgMoney::from_sql(ty, raw).ok()?.to_string(),
+        Type::PG_LSN => PgLsn::from_sql(ty, raw).ok()?.to_string(),
+        Type::JSONPATH => PgJsonPath::from_sql(ty, raw).ok()?.to_string(),
+        Type::TEXT
+        | Type::VARCHAR
+        | Type::BPCHAR
+        | Type::NAME
+        | Type::UNKNOWN
+        | Type::JSON
+        | Type::XML => types::text_from_sql(raw).ok()?.to_string(),
+        // binary JSONB values are prefixed with a version number
+        Type::JSONB => match raw.split_first()? {
+            (1, json) => types::text_from_sql(json).ok()?.to_string(),
+            _ => return None,
+        },
+        Type::BYTEA => format!("\\x{}", hex(raw)),
+        Type::UUID => {
+            let hex = hex(raw);
+            if hex.len() != 32 {
+                return None;
+            }
+            format!(
+                "{}-{}-{}-{}-{}",
+                &hex[..8],
+                &hex[8..12],
+                &hex[12..16],
+                &hex[16..20],
+                &hex[20..]
+            )
+        }
+        Type::INET | Type::CIDR => {
+            let inet = types::inet_from_sql(raw).ok()?;
+            let full = if inet.addr().is_ipv4() { 32 } else { 128 };
+            if *ty == Type::INET && inet.netmask() == full {
+                inet.addr().to_string()
+            } else {
+                format!("{}/{}", inet.addr(), inet.netmask())
+            }
+        }
+        Type::DATE => {
+            let v = types::date_from_sql(raw).ok()?;
+            serialize(|buf| text::date_to_sql(v, buf))
+        }
+        Type::TIME => {
+            let v = types::time_from_sql(raw).ok()?;
+            serialize(|buf| text::time_to_sql(v, buf))
+        }
+        Type::TIMESTAMP => {
+            let v = types::timestamp_from_sql(raw).ok()?;
+            serialize(|buf| text::timestamp_to_sql(v, buf))
+        }
+        // TIMESTAMPTZ values are rendered in UTC
+        Type::TIMESTAMPTZ => {
+            let v = types::timestamp_from_sql(raw).ok()?;
+            serialize(|buf| text::timestamptz_to_sql(v, buf))
+        }
+        _ => match ty.kind() {
+            Kind::Enum(_) => types::text_from_sql(raw).ok()?.to_string(),
+            Kind::Domain(base) => return try_render(base, raw),
+            Kind::Array(member) => return array(member, raw),
+            _ if ty.name() == "citext" => types::text_from_sql(raw).ok()?.to_string(),
+            _ => return None,
+        },
+    };
+
+    Some(text)
+}
+
+fn hex(raw: &[u8]) -> String {
+    let mut hex = String::with_capacity(raw.len() * 2);
+    for b in raw {
+        let _ = write!(hex, "{:02x}", b);
+    }
+    hex
+}
+
+fn float(v: f64) -> String {
+    if v.is_nan() {
+        "NaN".to_string()
+    } else if v.is_infinite() {
+        if v > 0. { "Infinity" } else { "-Infinity" }.to_string()
+    } else {
+        v.to_string()
+    }
+}
+
+// Renders a value with one of the serializers of the text format.
+fn serialize<F>(f: F) -> String
+where
+    F: FnOnce(&mut BytesMut),
+{
+    let mut buf = BytesMut::new();
+    f(&mut buf);
+    String::from_utf8_lossy(&buf).into_owned()
+}
+
+fn array(member: &Type, raw: &[u8]) -> Option<String> {
+    let array = types::array_from_sql(raw).ok()?;
+    let dimensions = array.dimensions().collect::<Vec<_>>().ok()?;
+    let values = array
+        .values()
+        .map(|v| Ok(v.map(|raw| render(member, raw))))
+        .collect::<Vec<_>>()
+        .ok()?;
+
+    let mut buf = BytesMut::new();
+    text::array_to_sql(dimensions, values, b',', &mut buf).ok()?;
+    Some(String::from_utf8_lossy(&buf).into_owned())
+}
@@ -0,0 +1,369 @@
+//! Abstraction over the asynchronous runtime used to open sockets and wait on timers.
+//!
+//! Only opening connections (and the backoff between transaction retries) depends on the runtime; the `Connection`
+//! future itself can be polled by any executor. The tokio runtime is used by default when the `runtime` Cargo feature
+//! is enabled, and the `runtime-async-std` and `runtime-smol` features provide implementations for those runtimes.
+//! Other runtimes can be supported by implementing the `Runtime` trait and registering it with `Config::runtime`.
+//!
+//! Hostnames are resolved with the runtime's `lookup_host` method unless a `Resolver` is registered with
+//! `Config::resolver`, which allows DNS libraries such as `trust-dns` to be used without replacing the runtime. Lookups
+//! are made for every connection unless a `CachingResolver` is used.
+
+use crate::Socket;
+use futures::future::BoxFuture;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// An asynchronous runtime capable of opening sockets and waiting on timers.
+pub trait Runtime: Sync + Send {
+    /// Resolves a hostname to the addresses it refers to.
+    fn lookup_host(
+        &self,
+        host: String,
+        port: u16,
+    ) -> BoxFuture<'static, io::Result<Vec<SocketAddr>>>;
+
+    /// Opens a TCP connection.
+    ///
+    /// The socket should have `TCP_NODELAY` set, and if `keepalive` is set, TCP keepalives should be enabled with that
+    /// idle time.
+    fn connect_tcp(
+        &self,
+        addr: SocketAddr,
+        keepalive: Option<Duration>,
+    ) -> BoxFuture<'static, io::Result<Socket>>;
+
+    /// Opens a connection to a Unix domain socket.
+    #[cfg(unix)]
+    fn connect_unix(&self, path: PathBuf) -> BoxFuture<'static, io::Result<Socket>>;
+
+    /// Returns a future which completes after the specified duration.
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+}
+
+/// A DNS resolver used to look up the addresses of hosts when connecting.
+pub trait Resolver: Sync + Send {
+    /// Resolves a hostname to the addresses it refers to.
+    ///
+    /// The addresses are tried in the order they are returned, subject to the configured `AddressStrategy`.
+    fn lookup_host(
+        &self,
+        host: String,
+        port: u16,
+    ) -> BoxFuture<'static, io::Result<Vec<SocketAddr>>>;
+
+    /// Called when a connection could not be made to any of the addresses returned by a lookup.
+    ///
+    /// Resolvers caching addresses can use this to discard addresses which may no longer be valid. The default
+    /// implementation does nothing.
+    #[allow(unused_variables)]
+    fn connect_failed(&self, host: &str, port: u16) {}
+}
+
+type CacheEntries = HashMap<(String, u16), (Instant, Vec<SocketAddr>)>;
+
+/// A resolver which caches the addresses of hosts for a limited time.
+///
+/// Cached addresses are used until their time to live has passed, and are discarded early if connecting to all of
+/// them fails, so that a host moving to a new address, for example after a failover or when a Kubernetes service is
+/// recreated, is picked up by the next connection. A resolver can be shared between the configurations of multiple
+/// connections, such as those of a pool, by cloning it.
+///
+/// A `ReconnectingClient` registered with the resolver through `ReconnectingClient::resolver` also discards the cache
+/// whenever its connection is lost, so that it always re-resolves hosts when reconnecting.
+#[derive(Clone)]
+pub struct CachingResolver {
+    resolver: Option<Arc<dyn Resolver>>,
+    ttl: Duration,
+    entries: Arc<Mutex<CacheEntries>>,
+}
+
+impl fmt::Debug for CachingResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CachingResolver")
+            .field("ttl", &self.ttl)
+            .field("entries", &self.entries.lock().len())
+            .finish()
+    }
+}
+
+impl CachingResolver {
+    /// Creates a resolver which caches the addresses looked up with the default runtime for the specified time.
+    pub fn new(ttl: Duration) -> CachingResolver {
+        CachingResolver {
+            resolver: None,
+            ttl,
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Creates a resolver which caches the addresses looked up with another resolver for the specified time.
+    pub fn with_resolver(resolver: Arc<dyn Resolver>, ttl: Duration) -> CachingResolver {
+        CachingResolver {
+            resolver: Some(resolver),
+            ..CachingResolver::new(ttl)
+        }
+    }
+
+    /// Returns the time for which addresses are cached.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Discards the cached addresses of a host.
+    pub fn invalidate(&self, host: &str, port: u16) {
+        self.entries.lock().remove(&(host.to_string(), port));
+    }
+
+    /// Discards all cached addresses.
+    pub fn clear(&self) {
+        self.entries.lock().clear();
+    }
+}
+
+impl Resolver for CachingResolver {
+    fn lookup_host(
+        &self,
+        host: String,
+        port: u16,
+    ) -> BoxFuture<'static, io::Result<Vec<SocketAddr>>> {
+        let key = (host, port);
+        if let Some((expires, addrs)) = self.entries.lock().get(&key) {
+            if Instant::now() < *expires {
+                let addrs = addrs.clone();
+                return Box::pin(async move { Ok(addrs) });
+            }
+        }
+
+        let lookup = match &self.resolver {
+            Some(resolver) => resolver.lookup_host(key.0.clone(), port),
+            None => match default_runtime() {
+                Some(runtime) => runtime.lookup_host(key.0.clone(), port),
+                None => {
+                    return Box::pin(async {
+                        Err(io::Error::new(
+                            io::ErrorKind::Other,
+                            "no runtime configured",
+                        ))
+                    })
+                }
+            },
+        };
+        let ttl = self.ttl;
+        let entries = self.entries.clone();
+        Box::pin(async move {
+            let addrs = lookup.await?;
+            if let Some(expires) = Instant::now().checked_add(ttl) {
+                entries.lock().insert(key, (expires, addrs.clone()));
+            }
+            Ok(addrs)
+        })
+    }
+
+    fn connect_failed(&self, host: &str, port: u16) {
+        self.invalidate(host, port);
+        if let Some(resolver) = &self.resolver {
+            resolver.connect_failed(host, port);
+        }
+    }
+}
+
+// A wrapper allowing a resolver to be stored in a `Config`, which is comparable and printable.
+#[derive(Clone)]
+pub(crate) struct ConfigResolver(pub Arc<dyn Resolver>);
+
+impl PartialEq for ConfigResolver {
+    fn eq(&self, other: &ConfigResolver) -> bool {
+        Arc::as_ptr(&self.0) as *const () == Arc::as_ptr(&other.0) as *const ()
+    }
+}
+
+impl fmt::Debug for ConfigResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Resolver")
+    }
+}
+
+// A wrapper allowing a runtime to be stored in a `Config`, which is comparable and printable.
+#[derive(Clone)]
+pub(crate) struct ConfigRuntime(pub Arc<dyn Runtime>);
+
+impl PartialEq for ConfigRuntime {
+    fn eq(&self, other: &ConfigRuntime) -> bool {
+        Arc::as_ptr(&self.0) as *const () == Arc::as_ptr(&other.0) as *const ()
+    }
+}
+
+impl fmt::Debug for ConfigRuntime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Runtime")
+    }
+}
+
+/// Returns the runtime selected by the enabled Cargo features, preferring tokio.
+#[allow(unreachable_code)]
+pub(crate) fn default_runtime() -> Option<Arc<dyn Runtime>> {
+    #[cfg(feature = "runtime")]
+    return Some(Arc::new(TokioRuntime));
+    #[cfg(feature = "runtime-async-std")]
+    return Some(Arc::new(AsyncStdRuntime));
+    #[cfg(feature = "runtime-smol")]
+    return Some(Arc::new(SmolRuntime));
+    None
+}
+
+#[cfg(any(
+    feature = "runtime",
+    feature = "runtime-async-std",
+    feature = "runtime-smol"
+))]
+fn set_keepalive<'a, S>(stream: &'a S, keepalive: Option<Duration>) -> io::Result<()>
+where
+    socket2::SockRef<'a>: From<&'a S>,
+{
+    if let Some(idle) = keepalive {
+        socket2::SockRef::from(stream)
+            .set_tcp_keepalive(&socket2::TcpKeepalive::new().with_time(idle))?;
+    }
+    Ok(())
+}
+
+/// The tokio runtime.
+///
+/// Requires the `runtime` Cargo feature (enabled by default).
+#[cfg(feature = "runtime")]
+#[derive(Debug, Copy, Clone, Default)]
+pub struct TokioRuntime;
+
+#[cfg(feature = "runtime")]
+impl Runtime for TokioRuntime {
+    fn lookup_host(
+        &self,
+        host: String,
+        port: u16,
+    ) -> BoxFuture<'static, io::Result<Vec<SocketAddr>>> {
+        Box::pin(async move { Ok(tokio::net::lookup_host((&*host, port)).await?.collect()) })
+    }
+
+    fn connect_tcp(
+        &self,
+        addr: SocketAddr,
+        keepalive: Option<Duration>,
+    ) -> BoxFuture<'static, io::Result<Socket>> {
+        Box::pin(async move {
+            let stream = tokio::net::TcpStream::connect(addr).await?;
+            stream.set_nodelay(true)?;
+            set_keepalive(&stream, keepalive)?;
+            Ok(Socket::new_tcp(stream))
+        })
+    }
+
+    #[cfg(unix)]
+    fn connect_unix(&self, path: PathBuf) -> BoxFuture<'static, io::Result<Socket>> {
+        Box::pin(async move {
+            let stream = tokio::net::UnixStream::connect(path).await?;
+            Ok(Socket::new_unix(stream))
+        })
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// The async-std runtime.
+///
+/// Requires the `runtime-async-std` Cargo feature.
+#[cfg(feature = "runtime-async-std")]
+#[derive(Debug, Copy, Clone, Default)]
+pub struct AsyncStdRuntime;
+
+#[cfg(feature = "runtime-async-std")]
+impl Runtime for AsyncStdRuntime {
+    fn lookup_host(
+        &self,
+        host: String,
+        port: u16,
+    ) -> BoxFuture<'static, io::Result<Vec<SocketAddr>>> {
+        use async_std::net::ToSocketAddrs;
+
+        Box::pin(async move { Ok((&*host, port).to_socket_addrs().await?.collect()) })
+    }
+
+    fn connect_tcp(
+        &self,
+        addr: SocketAddr,
+        keepalive: Option<Duration>,
+    ) -> BoxFuture<'static, io::Result<Socket>> {
+        Box::pin(async move {
+            let stream = async_std::net::TcpStream::connect(addr).await?;
+            stream.set_nodelay(true)?;
+            set_keepalive(&stream, keepalive)?;
+            Ok(Socket::new(stream))
+        })
+    }
+
+    #[cfg(unix)]
+    fn connect_unix(&self, path: PathBuf) -> BoxFuture<'static, io::Result<Socket>> {
+        Box::pin(async move {
+            let stream = async_std::os::unix::net::UnixStream::connect(path).await?;
+            Ok(Socket::new(stream))
+        })
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(async_std::task::sleep(duration))
+    }
+}
+
+/// The smol runtime.
+///
+/// Requires the `runtime-smol` Cargo feature.
+#[cfg(feature = "runtime-smol")]
+#[derive(Debug, Copy, Clone, Default)]
+pub struct SmolRuntime;
+
+#[cfg(feature = "runtime-smol")]
+impl Runtime for SmolRuntime {
+    fn lookup_host(
+        &self,
+        host: String,
+        port: u16,
+    ) -> BoxFuture<'static, io::Result<Vec<SocketAddr>>> {
+        Box::pin(async move { smol::net::resolve((&*host, port)).await })
+    }
+
+    fn connect_tcp(
+        &self,
+        addr: SocketAddr,
+        keepalive: Option<Duration>,
+    ) -> BoxFuture<'static, io::Result<Socket>> {
+        Box::pin(async move {
+            let stream = smol::net::TcpStream::connect(addr).await?;
+            stream.set_nodelay(true)?;
+            set_keepalive(&stream, keepalive)?;
+            Ok(Socket::new(stream))
+        })
+    }
+
+    #[cfg(unix)]
+    fn connect_unix(&self, path: PathBuf) -> BoxFuture<'static, io::Result<Socket>> {
+        Box::pin(async move {
+            let stream = smol::net::unix::UnixStream::connect(path).await?;
+            Ok(Socket::new(stream))
+        })
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(async move {
+            smol::Timer::after(duration).await;
+        })
+    }
+}
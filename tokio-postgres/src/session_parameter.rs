@@ -0,0 +1,54 @@
+use crate::{Client, Error};
+
+/// The scope of a change to a configuration parameter.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParameterScope {
+    /// The change lasts for the rest of the session, unless the transaction in which it is made is rolled back.
+    Session,
+    /// The change lasts only until the end of the current transaction, as with `SET LOCAL`.
+    Local,
+}
+
+pub(crate) async fn set_parameter(
+    client: &Client,
+    name: &str,
+    value: &str,
+    scope: ParameterScope,
+) -> Result<(), Error> {
+    let local = scope == ParameterScope::Local;
+    client
+        .query_one(
+            "SELECT pg_catalog.set_config($1, $2, $3)",
+            &[&name, &value, &local],
+        )
+        .await?;
+
+    if scope == ParameterScope::Session {
+        client.inner().add_changed_parameter(name);
+    }
+    Ok(())
+}
+
+pub(crate) async fn reset_parameter(client: &Client, name: &str) -> Result<(), Error> {
+    // set_config resets the parameter to its default value when given a null value
+    client
+        .query_one("SELECT pg_catalog.set_config($1, NULL, false)", &[&name])
+        .await?;
+    client.inner().remove_changed_parameter(name);
+    Ok(())
+}
+
+pub(crate) async fn search_path(client: &Client) -> Result<Vec<String>, Error> {
+    client
+        .query_one("SELECT pg_catalog.current_schemas(false)", &[])
+        .await?
+        .try_get(0)
+}
+
+pub(crate) async fn reset_all_parameters(client: &Client) -> Result<(), Error> {
+    for name in client.inner().changed_parameters() {
+        reset_parameter(client, &name).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,129 @@
+use crate::Error;
+use futures::task::AtomicWaker;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::Waker;
+use tokio::sync::watch;
+
+/// The reason a connection closed, as reported by a `ShutdownHandle`.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum DisconnectCause {
+    /// The connection's `Client` was dropped, and all outstanding work completed.
+    ClientDropped,
+    /// Shutdown was requested with `ShutdownHandle::shutdown`, and all outstanding work completed.
+    Shutdown,
+    /// The connection failed with an error.
+    ///
+    /// Causes other than database errors are only preserved as their descriptions.
+    Error(Arc<Error>),
+    /// The `Connection` was dropped before it closed.
+    Dropped,
+}
+
+/// A handle used to gracefully shut down a `Connection` and observe why it closed.
+///
+/// Handles are created with `Connection::shutdown_handle`, and can be used after the connection has been spawned onto
+/// an executor, for example to close it cleanly when the application exits:
+///
+/// ```no_run
+/// # async fn f() -> Result<(), tokio_postgres::Error> {
+/// let (client, connection) = tokio_postgres::connect("host=localhost user=postgres", tokio_postgres::NoTls).await?;
+/// let handle = connection.shutdown_handle();
+/// tokio::spawn(connection);
+///
+/// // ...
+///
+/// handle.shutdown();
+/// let cause = handle.closed().await;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    shared: Arc<Shared>,
+    cause: watch::Receiver<Option<DisconnectCause>>,
+}
+
+impl ShutdownHandle {
+    /// Requests that the connection shut down.
+    ///
+    /// The connection stops accepting new requests, so that further use of its `Client` fails with a closed
+    /// connection error. Requests which have already been made are completed before the connection terminates its
+    /// session with the server and closes.
+    pub fn shutdown(&self) {
+        self.shared.requested.store(true, Ordering::Release);
+        self.shared.waker.wake();
+    }
+
+    /// Determines if the connection has closed.
+    pub fn is_closed(&self) -> bool {
+        self.cause.borrow().is_some()
+    }
+
+    /// Returns the reason the connection closed, or `None` if it is still running.
+    pub fn disconnect_cause(&self) -> Option<DisconnectCause> {
+        self.cause.borrow().clone()
+    }
+
+    /// Waits for the connection to close, returning the reason it closed.
+    pub async fn closed(&self) -> DisconnectCause {
+        let mut cause = self.cause.clone();
+        loop {
+            if let Some(cause) = &*cause.borrow() {
+                return cause.clone();
+            }
+            if cause.changed().await.is_err() {
+                return DisconnectCause::Dropped;
+            }
+        }
+    }
+}
+
+struct Shared {
+    requested: AtomicBool,
+    waker: AtomicWaker,
+}
+
+// The connection's side of its shutdown handles.
+pub(crate) struct ShutdownState {
+    shared: Arc<Shared>,
+    sender: watch::Sender<Option<DisconnectCause>>,
+    receiver: watch::Receiver<Option<DisconnectCause>>,
+}
+
+impl ShutdownState {
+    pub fn new() -> ShutdownState {
+        let (sender, receiver) = watch::channel(None);
+        ShutdownState {
+            shared: Arc::new(Shared {
+                requested: AtomicBool::new(false),
+                waker: AtomicWaker::new(),
+            }),
+            sender,
+            receiver,
+        }
+    }
+
+    pub fn handle(&self) -> ShutdownHandle {
+        ShutdownHandle {
+            shared: self.shared.clone(),
+            cause: self.receiver.clone(),
+        }
+    }
+
+    pub fn register(&self, waker: &Waker) {
+        self.shared.waker.register(waker);
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.shared.requested.load(Ordering::Acquire)
+    }
+
+    // Records the cause of the connection closing, if it has not already been recorded.
+    pub fn closed(&self, cause: DisconnectCause) {
+        if self.receiver.borrow().is_none() {
+            let _ = self.sender.send(Some(cause));
+        }
+    }
+}
@@ -1,6 +1,9 @@
 use crate::client::{InnerClient, Responses};
 use crate::codec::FrontendMessage;
 use crate::connection::RequestMessages;
+use crate::query::extract_row_affected;
+use crate::query_logger::QueryLog;
+use crate::trace::{self, Instrument};
 use crate::{Error, SimpleQueryMessage, SimpleQueryRow};
 use bytes::Bytes;
 use fallible_iterator::FallibleIterator;
@@ -33,35 +36,78 @@ impl SimpleColumn {
 pub async fn simple_query(client: &InnerClient, query: &str) -> Result<SimpleQueryStream, Error> {
     debug!("executing simple query: {}", query);
 
-    let buf = encode(client, query)?;
-    let responses = client.send(RequestMessages::Single(FrontendMessage::Raw(buf)))?;
+    let span = trace::query_span(client, query);
+    let log = QueryLog::start(client, 0);
+    let result = async {
+        let buf = encode(client, query)?;
+        let bytes_sent = buf.len();
+        let responses = client
+            .send(RequestMessages::Single(FrontendMessage::Raw(buf)))
+            .await?;
+        Ok((responses, bytes_sent))
+    }
+    .await;
 
-    Ok(SimpleQueryStream {
-        responses,
-        columns: None,
-        _p: PhantomPinned,
-    })
+    match result {
+        Ok((responses, bytes_sent)) => Ok(SimpleQueryStream {
+            responses,
+            columns: None,
+            completed_columns: None,
+            log: log.map(|log| (log, query.to_string())),
+            rows: 0,
+            span,
+            bytes_sent,
+            _p: PhantomPinned,
+        }),
+        Err(e) => {
+            trace::record_error(&span, &e);
+            if let Some(log) = log {
+                log.finish(query, None, Some(&e));
+            }
+            Err(e)
+        }
+    }
 }
 
 pub async fn batch_execute(client: &InnerClient, query: &str) -> Result<(), Error> {
     debug!("executing statement batch: {}", query);
 
-    let buf = encode(client, query)?;
-    let mut responses = client.send(RequestMessages::Single(FrontendMessage::Raw(buf)))?;
-
-    loop {
-        match responses.next().await? {
-            Message::ReadyForQuery(_) => return Ok(()),
-            Message::CommandComplete(_)
-            | Message::EmptyQueryResponse
-            | Message::RowDescription(_)
-            | Message::DataRow(_) => {}
-            _ => return Err(Error::unexpected_message()),
+    let span = trace::query_span(client, query);
+    let log = QueryLog::start(client, 0);
+    let result = async {
+        let buf = encode(client, query)?;
+        let bytes_sent = buf.len();
+        let mut responses = client
+            .send(RequestMessages::Single(FrontendMessage::Raw(buf)))
+            .await?;
+
+        let mut rows = 0;
+        loop {
+            match responses.next().await? {
+                Message::ReadyForQuery(_) => {
+                    trace::record_bytes(&span, bytes_sent, responses.bytes_received());
+                    return Ok(rows);
+                }
+                Message::CommandComplete(body) => rows += extract_row_affected(&body)?,
+                Message::EmptyQueryResponse | Message::RowDescription(_) | Message::DataRow(_) => {}
+                _ => return Err(Error::unexpected_message()),
+            }
         }
     }
+    .instrument(span.clone())
+    .await;
+
+    trace::record_result(&span, &result);
+    if let Ok(rows) = result {
+        trace::record_rows(&span, rows);
+    }
+    if let Some(log) = log {
+        log.finish(query, result.as_ref().ok().copied(), result.as_ref().err());
+    }
+    result.map(|_| ())
 }
 
-fn encode(client: &InnerClient, query: &str) -> Result<Bytes, Error> {
+pub fn encode(client: &InnerClient, query: &str) -> Result<Bytes, Error> {
     client.with_buf(|buf| {
         frontend::query(query, buf).map_err(Error::encode)?;
         Ok(buf.split().freeze())
@@ -73,6 +119,12 @@ pin_project! {
     pub struct SimpleQueryStream {
         responses: Responses,
         columns: Option<Arc<[SimpleColumn]>>,
+        // the columns of the statement which most recently completed
+        completed_columns: Option<Arc<[SimpleColumn]>>,
+        log: Option<(QueryLog, String)>,
+        rows: u64,
+        span: trace::Span,
+        bytes_sent: usize,
         #[pin]
         _p: PhantomPinned,
     }
@@ -83,20 +135,22 @@ impl Stream for SimpleQueryStream {
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let this = self.project();
-        loop {
-            match ready!(this.responses.poll_next(cx)?) {
-                Message::CommandComplete(body) => {
-                    let rows = body
-                        .tag()
-                        .map_err(Error::parse)?
-                        .rsplit(' ')
-                        .next()
-                        .unwrap()
-                        .parse()
-                        .unwrap_or(0);
-                    return Poll::Ready(Some(Ok(SimpleQueryMessage::CommandComplete(rows))));
-                }
+        let result = loop {
+            let message = match ready!(this.responses.poll_next(cx)) {
+                Ok(message) => message,
+                Err(e) => break Some(Err(e)),
+            };
+            match message {
+                Message::CommandComplete(body) => match extract_row_affected(&body) {
+                    Ok(rows) => {
+                        *this.rows += rows;
+                        *this.completed_columns = this.columns.take();
+                        return Poll::Ready(Some(Ok(SimpleQueryMessage::CommandComplete(rows))));
+                    }
+                    Err(e) => break Some(Err(e)),
+                },
                 Message::EmptyQueryResponse => {
+                    *this.completed_columns = None;
                     return Poll::Ready(Some(Ok(SimpleQueryMessage::CommandComplete(0))));
                 }
                 Message::RowDescription(body) => {
@@ -116,8 +170,102 @@ impl Stream for SimpleQueryStream {
                     };
                     return Poll::Ready(Some(Ok(SimpleQueryMessage::Row(row))));
                 }
-                Message::ReadyForQuery(_) => return Poll::Ready(None),
-                _ => return Poll::Ready(Some(Err(Error::unexpected_message()))),
+                Message::ReadyForQuery(_) => break None,
+                _ => break Some(Err(Error::unexpected_message())),
+            }
+        };
+
+        trace::record_bytes(this.span, *this.bytes_sent, this.responses.bytes_received());
+        match &result {
+            Some(Err(e)) => trace::record_error(this.span, e),
+            _ => {
+                trace::record_rows(this.span, *this.rows);
+                trace::record_ok(this.span);
+            }
+        }
+
+        if let Some((log, query)) = this.log.take() {
+            match &result {
+                Some(Err(e)) => log.finish(&query, None, Some(e)),
+                _ => log.finish(&query, Some(*this.rows), None),
+            }
+        }
+        Poll::Ready(result)
+    }
+}
+
+/// The rows returned by one of the statements executed by a simple query.
+pub struct SimpleResultSet {
+    columns: Arc<[SimpleColumn]>,
+    rows: Vec<SimpleQueryRow>,
+    rows_affected: u64,
+}
+
+impl SimpleResultSet {
+    /// Returns information about the columns of the result set.
+    ///
+    /// This is empty for statements which don't return rows.
+    pub fn columns(&self) -> &[SimpleColumn] {
+        &self.columns
+    }
+
+    /// Returns the rows of the result set.
+    pub fn rows(&self) -> &[SimpleQueryRow] {
+        &self.rows
+    }
+
+    /// Consumes the result set, returning its rows.
+    pub fn into_rows(self) -> Vec<SimpleQueryRow> {
+        self.rows
+    }
+
+    /// Returns the number of rows modified or selected by the statement.
+    pub fn rows_affected(&self) -> u64 {
+        self.rows_affected
+    }
+}
+
+pin_project! {
+    /// A stream of the result sets of the statements executed by a simple query.
+    pub struct SimpleResultSetStream {
+        #[pin]
+        inner: SimpleQueryStream,
+        rows: Vec<SimpleQueryRow>,
+    }
+}
+
+impl SimpleResultSetStream {
+    pub(crate) fn new(inner: SimpleQueryStream) -> SimpleResultSetStream {
+        SimpleResultSetStream {
+            inner,
+            rows: vec![],
+        }
+    }
+}
+
+impl Stream for SimpleResultSetStream {
+    type Item = Result<SimpleResultSet, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        loop {
+            match ready!(this.inner.as_mut().poll_next(cx)?) {
+                Some(SimpleQueryMessage::Row(row)) => this.rows.push(row),
+                Some(SimpleQueryMessage::CommandComplete(rows_affected)) => {
+                    let columns = this
+                        .inner
+                        .as_mut()
+                        .project()
+                        .completed_columns
+                        .take()
+                        .unwrap_or_else(|| Arc::new([]));
+                    return Poll::Ready(Some(Ok(SimpleResultSet {
+                        columns,
+                        rows: std::mem::take(this.rows),
+                        rows_affected,
+                    })));
+                }
+                None => return Poll::Ready(None),
             }
         }
     }
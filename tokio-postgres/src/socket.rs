@@ -1,30 +1,59 @@
-use std::io;
+use futures::ready;
+use std::fmt;
+use std::io::{self, IoSlice};
 use std::pin::Pin;
 use std::task::{Context, Poll};
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+#[cfg(feature = "runtime")]
 use tokio::net::TcpStream;
-#[cfg(unix)]
+#[cfg(all(unix, feature = "runtime"))]
 use tokio::net::UnixStream;
 
+trait FuturesIo: futures::io::AsyncRead + futures::io::AsyncWrite + Send + Sync {}
+
+impl<T> FuturesIo for T where T: futures::io::AsyncRead + futures::io::AsyncWrite + Send + Sync {}
+
+struct Io(Pin<Box<dyn FuturesIo>>);
+
+impl fmt::Debug for Io {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Io")
+    }
+}
+
 #[derive(Debug)]
 enum Inner {
+    #[cfg(feature = "runtime")]
     Tcp(TcpStream),
-    #[cfg(unix)]
+    #[cfg(all(unix, feature = "runtime"))]
     Unix(UnixStream),
+    Io(Io),
 }
 
 /// The standard stream type used by the crate.
 ///
-/// Requires the `runtime` Cargo feature (enabled by default).
+/// Sockets opened by the tokio runtime are used directly, while those of other runtimes implement the `futures` I/O
+/// traits and are adapted to tokio's.
 #[derive(Debug)]
 pub struct Socket(Inner);
 
 impl Socket {
+    /// Creates a socket from a stream implementing the `futures` I/O traits.
+    ///
+    /// This is intended for use by implementations of `Runtime` for runtimes other than tokio.
+    pub fn new<S>(stream: S) -> Socket
+    where
+        S: futures::io::AsyncRead + futures::io::AsyncWrite + Send + Sync + 'static,
+    {
+        Socket(Inner::Io(Io(Box::pin(stream))))
+    }
+
+    #[cfg(feature = "runtime")]
     pub(crate) fn new_tcp(stream: TcpStream) -> Socket {
         Socket(Inner::Tcp(stream))
     }
 
-    #[cfg(unix)]
+    #[cfg(all(unix, feature = "runtime"))]
     pub(crate) fn new_unix(stream: UnixStream) -> Socket {
         Socket(Inner::Unix(stream))
     }
@@ -37,9 +66,15 @@ impl AsyncRead for Socket {
         buf: &mut ReadBuf<'_>,
     ) -> Poll<io::Result<()>> {
         match &mut self.0 {
+            #[cfg(feature = "runtime")]
             Inner::Tcp(s) => Pin::new(s).poll_read(cx, buf),
-            #[cfg(unix)]
+            #[cfg(all(unix, feature = "runtime"))]
             Inner::Unix(s) => Pin::new(s).poll_read(cx, buf),
+            Inner::Io(s) => {
+                let n = ready!(s.0.as_mut().poll_read(cx, buf.initialize_unfilled()))?;
+                buf.advance(n);
+                Poll::Ready(Ok(()))
+            }
         }
     }
 }
@@ -51,25 +86,55 @@ impl AsyncWrite for Socket {
         buf: &[u8],
     ) -> Poll<io::Result<usize>> {
         match &mut self.0 {
+            #[cfg(feature = "runtime")]
             Inner::Tcp(s) => Pin::new(s).poll_write(cx, buf),
-            #[cfg(unix)]
+            #[cfg(all(unix, feature = "runtime"))]
             Inner::Unix(s) => Pin::new(s).poll_write(cx, buf),
+            Inner::Io(s) => s.0.as_mut().poll_write(cx, buf),
+        }
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        match &mut self.0 {
+            #[cfg(feature = "runtime")]
+            Inner::Tcp(s) => Pin::new(s).poll_write_vectored(cx, bufs),
+            #[cfg(all(unix, feature = "runtime"))]
+            Inner::Unix(s) => Pin::new(s).poll_write_vectored(cx, bufs),
+            Inner::Io(s) => s.0.as_mut().poll_write_vectored(cx, bufs),
+        }
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        match &self.0 {
+            #[cfg(feature = "runtime")]
+            Inner::Tcp(s) => s.is_write_vectored(),
+            #[cfg(all(unix, feature = "runtime"))]
+            Inner::Unix(s) => s.is_write_vectored(),
+            Inner::Io(_) => true,
         }
     }
 
     fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
         match &mut self.0 {
+            #[cfg(feature = "runtime")]
             Inner::Tcp(s) => Pin::new(s).poll_flush(cx),
-            #[cfg(unix)]
+            #[cfg(all(unix, feature = "runtime"))]
             Inner::Unix(s) => Pin::new(s).poll_flush(cx),
+            Inner::Io(s) => s.0.as_mut().poll_flush(cx),
         }
     }
 
     fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
         match &mut self.0 {
+            #[cfg(feature = "runtime")]
             Inner::Tcp(s) => Pin::new(s).poll_shutdown(cx),
-            #[cfg(unix)]
+            #[cfg(all(unix, feature = "runtime"))]
             Inner::Unix(s) => Pin::new(s).poll_shutdown(cx),
+            Inner::Io(s) => s.0.as_mut().poll_close(cx),
         }
     }
 }
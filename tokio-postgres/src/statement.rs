@@ -11,19 +11,25 @@ use std::{
 struct StatementInner {
     client: Weak<InnerClient>,
     name: String,
+    query: String,
     params: Vec<Type>,
     columns: Vec<Column>,
 }
 
 impl Drop for StatementInner {
     fn drop(&mut self) {
+        // the unnamed statement is replaced by the next one rather than closed
+        if self.name.is_empty() {
+            return;
+        }
+
         if let Some(client) = self.client.upgrade() {
             let buf = client.with_buf(|buf| {
                 frontend::close(b'S', &self.name, buf).unwrap();
                 frontend::sync(buf);
                 buf.split().freeze()
             });
-            let _ = client.send(RequestMessages::Single(FrontendMessage::Raw(buf)));
+            let _ = client.send_now(RequestMessages::Single(FrontendMessage::Raw(buf)));
         }
     }
 }
@@ -38,12 +44,14 @@ impl Statement {
     pub(crate) fn new(
         inner: &Arc<InnerClient>,
         name: String,
+        query: String,
         params: Vec<Type>,
         columns: Vec<Column>,
     ) -> Statement {
         Statement(Arc::new(StatementInner {
             client: Arc::downgrade(inner),
             name,
+            query,
             params,
             columns,
         }))
@@ -53,12 +61,17 @@ impl Statement {
         &self.0.name
     }
 
+    pub(crate) fn query(&self) -> &str {
+        &self.0.query
+    }
+
     /// Returns the expected types of the statement's parameters.
     pub fn params(&self) -> &[Type] {
         &self.0.params
     }
 
     /// Returns information about the columns returned when the statement is queried.
+    #[inline]
     pub fn columns(&self) -> &[Column] {
         &self.0.columns
     }
@@ -81,6 +94,7 @@ impl Column {
     }
 
     /// Returns the type of the column.
+    #[inline]
     pub fn type_(&self) -> &Type {
         &self.type_
     }
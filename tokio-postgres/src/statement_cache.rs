@@ -0,0 +1,90 @@
+use crate::Statement;
+use std::collections::HashMap;
+
+/// The settings controlling how a client names and caches its prepared statements.
+#[derive(Clone)]
+pub struct StatementConfig {
+    pub named: bool,
+    pub name_prefix: String,
+    pub cache_capacity: usize,
+}
+
+struct Entry {
+    statement: Statement,
+    last_used: u64,
+}
+
+/// A cache of the statements prepared for raw query strings, keyed by their text.
+///
+/// Once the cache is full, the least recently used statement is evicted. Dropping the last reference to an evicted
+/// statement closes it on the server.
+pub struct StatementCache {
+    capacity: usize,
+    clock: u64,
+    entries: HashMap<String, Entry>,
+}
+
+impl StatementCache {
+    pub fn new(capacity: usize) -> StatementCache {
+        StatementCache {
+            capacity,
+            clock: 0,
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn get(&mut self, query: &str) -> Option<Statement> {
+        self.clock += 1;
+        let entry = self.entries.get_mut(query)?;
+        entry.last_used = self.clock;
+        Some(entry.statement.clone())
+    }
+
+    /// Determines if a statement is the one cached for its query.
+    pub fn contains(&self, statement: &Statement) -> bool {
+        self.entries
+            .get(statement.query())
+            .map_or(false, |entry| entry.statement.name() == statement.name())
+    }
+
+    /// Adds a statement to the cache, returning the statement evicted to make room for it, if any.
+    pub fn insert(&mut self, query: &str, statement: Statement) -> Option<Statement> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        self.clock += 1;
+        let entry = Entry {
+            statement,
+            last_used: self.clock,
+        };
+        if let Some(old) = self.entries.insert(query.to_string(), entry) {
+            return Some(old.statement);
+        }
+        if self.entries.len() <= self.capacity {
+            return None;
+        }
+
+        let oldest = self
+            .entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(query, _)| query.clone())?;
+        self.entries.remove(&oldest).map(|entry| entry.statement)
+    }
+
+    pub fn clear(&mut self) -> Vec<Statement> {
+        self.entries
+            .drain()
+            .map(|(_, entry)| entry.statement)
+            .collect()
+    }
+}
@@ -0,0 +1,294 @@
+//! Helpers for tests which need a real database.
+//!
+//! Requires the `test-harness` Cargo feature.
+//!
+//! A [`TestDatabase`] is a freshly created database which is dropped again once the test is done with it, so tests
+//! running concurrently against the same server don't see each other's tables. By default, it's created on the server
+//! identified by the `PG_TEST_URI` environment variable, which takes a connection string in either of the formats
+//! accepted by [`Config`]. Alternatively, a [`TestCluster`] starts a temporary server of its own from the Postgres
+//! binaries installed locally.
+//!
+//! ```no_run
+//! use tokio_postgres::testing::TestDatabase;
+//!
+//! #[tokio::test]
+//! async fn creates_users() {
+//!     let db = TestDatabase::new().await.unwrap();
+//!     let client = db.connect().await.unwrap();
+//!
+//!     client.batch_execute("CREATE TABLE users (id INT)").await.unwrap();
+//!     // ...
+//!
+//!     db.drop_database().await.unwrap();
+//! }
+//! ```
+use crate::{Client, Config, Error, NoTls};
+use std::env;
+use std::fs;
+use std::io;
+use std::net::{Ipv4Addr, TcpListener};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+// Returns a name which is unique across the processes and test runs sharing a server or temporary directory.
+fn unique_name(prefix: &str) -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.subsec_nanos());
+    format!(
+        "{}_{}_{}_{}",
+        prefix,
+        process::id(),
+        NEXT_ID.fetch_add(1, Ordering::SeqCst),
+        nanos
+    )
+}
+
+async fn connect(config: &Config) -> Result<Client, Error> {
+    let (client, connection) = config.connect(NoTls).await?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            log::error!("test database connection error: {}", e);
+        }
+    });
+    Ok(client)
+}
+
+async fn drop_database(admin: &Config, name: &str) -> Result<(), Error> {
+    let client = connect(admin).await?;
+    // DROP DATABASE fails while there are still sessions connected to it
+    client
+        .execute(
+            "SELECT pg_terminate_backend(pid) FROM pg_catalog.pg_stat_activity \
+             WHERE datname = $1 AND pid <> pg_backend_pid()",
+            &[&name],
+        )
+        .await?;
+    client
+        .batch_execute(&format!("DROP DATABASE IF EXISTS \"{}\"", name))
+        .await
+}
+
+/// A database created for the duration of a test.
+///
+/// The database should be dropped with `drop_database` at the end of the test. If the value is dropped instead, as it
+/// is when the test panics, the database is dropped on a background thread which the destructor blocks on.
+#[derive(Debug)]
+pub struct TestDatabase {
+    admin: Config,
+    config: Config,
+    name: String,
+    dropped: bool,
+}
+
+impl TestDatabase {
+    /// Creates a database on the server identified by the `PG_TEST_URI` environment variable.
+    pub async fn new() -> Result<TestDatabase, Error> {
+        let uri = env::var("PG_TEST_URI").map_err(|_| {
+            Error::config(
+                "the PG_TEST_URI environment variable must be set to use TestDatabase::new".into(),
+            )
+        })?;
+        let config = uri.parse::<Config>()?;
+        TestDatabase::with_config(config).await
+    }
+
+    /// Creates a database on the server identified by a configuration.
+    ///
+    /// The configuration is used to connect when creating and dropping the database, so its user must have permission
+    /// to do so. Connections to the new database use the same settings other than the database name.
+    pub async fn with_config(admin: Config) -> Result<TestDatabase, Error> {
+        let name = unique_name("test");
+
+        let client = connect(&admin).await?;
+        client
+            .batch_execute(&format!("CREATE DATABASE \"{}\"", name))
+            .await?;
+
+        let mut config = admin.clone();
+        config.dbname(&name);
+
+        Ok(TestDatabase {
+            admin,
+            config,
+            name,
+            dropped: false,
+        })
+    }
+
+    /// Returns the name of the database.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the configuration used to connect to the database.
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Opens a new connection to the database.
+    ///
+    /// The connection is spawned onto the current Tokio runtime.
+    pub async fn connect(&self) -> Result<Client, Error> {
+        connect(&self.config).await
+    }
+
+    /// Drops the database, disconnecting any sessions still connected to it.
+    pub async fn drop_database(mut self) -> Result<(), Error> {
+        self.dropped = true;
+        drop_database(&self.admin, &self.name).await
+    }
+}
+
+impl Drop for TestDatabase {
+    fn drop(&mut self) {
+        if self.dropped {
+            return;
+        }
+
+        // the destructor may be running on a runtime's thread, which can't block on a future itself
+        let admin = self.admin.clone();
+        let name = self.name.clone();
+        let result = thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()?;
+            runtime
+                .block_on(drop_database(&admin, &name))
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))
+        })
+        .join();
+
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => log::warn!("error dropping test database {}: {}", self.name, e),
+            Err(_) => log::warn!("panic dropping test database {}", self.name),
+        }
+    }
+}
+
+/// A temporary Postgres server.
+///
+/// The server's data directory is created with `initdb` in the system's temporary directory, and the server is run
+/// with `pg_ctl`. Both are looked up in the directory named by the `PG_BIN_DIR` environment variable if it is set, and
+/// on the `PATH` otherwise. The server only accepts connections from the local machine, and trusts all of them.
+///
+/// The server is stopped and its data directory deleted when the value is dropped.
+#[derive(Debug)]
+pub struct TestCluster {
+    dir: PathBuf,
+    port: u16,
+}
+
+impl TestCluster {
+    /// Initializes and starts a new server.
+    pub fn start() -> io::Result<TestCluster> {
+        let dir = env::temp_dir().join(unique_name("tokio-postgres"));
+        fs::create_dir_all(&dir)?;
+
+        // from here on, dropping the cluster cleans up after a partial start
+        let mut cluster = TestCluster { dir, port: 0 };
+
+        let data = cluster.data_dir();
+        run(Command::new(tool("initdb")).arg("-D").arg(&data).args(&[
+            "-U",
+            "postgres",
+            "-A",
+            "trust",
+            "-E",
+            "UTF8",
+            "--no-sync",
+        ]))?;
+
+        // there's a small window for another process to take the port, but Postgres has no way of picking one itself
+        cluster.port = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))?
+            .local_addr()?
+            .port();
+        let options = format!(
+            "-p {} -c listen_addresses=127.0.0.1 -k '{}' -F",
+            cluster.port,
+            cluster.dir.display()
+        );
+        run(Command::new(tool("pg_ctl"))
+            .arg("-D")
+            .arg(&data)
+            .arg("-l")
+            .arg(cluster.dir.join("postgres.log"))
+            .arg("-o")
+            .arg(options)
+            .args(&["-w", "start"]))?;
+
+        Ok(cluster)
+    }
+
+    /// Returns the port the server is listening on.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Returns a configuration connecting to the server's `postgres` database as the `postgres` superuser.
+    pub fn config(&self) -> Config {
+        let mut config = Config::new();
+        config
+            .host("127.0.0.1")
+            .port(self.port)
+            .user("postgres")
+            .dbname("postgres");
+        config
+    }
+
+    fn data_dir(&self) -> PathBuf {
+        self.dir.join("data")
+    }
+}
+
+impl Drop for TestCluster {
+    fn drop(&mut self) {
+        if self.data_dir().join("postmaster.pid").exists() {
+            let result = run(Command::new(tool("pg_ctl"))
+                .arg("-D")
+                .arg(self.data_dir())
+                .args(&["-m", "immediate", "-w", "stop"]));
+            if let Err(e) = result {
+                log::warn!("error stopping test cluster: {}", e);
+            }
+        }
+
+        if let Err(e) = fs::remove_dir_all(&self.dir) {
+            log::warn!("error removing {}: {}", self.dir.display(), e);
+        }
+    }
+}
+
+fn tool(name: &str) -> PathBuf {
+    match env::var_os("PG_BIN_DIR") {
+        Some(dir) => Path::new(&dir).join(name),
+        None => PathBuf::from(name),
+    }
+}
+
+fn run(command: &mut Command) -> io::Result<()> {
+    let output = command
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| io::Error::new(e.kind(), format!("error running {:?}: {}", command, e)))?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "{:?} failed with {}: {}",
+                command,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ))
+    }
+}
@@ -33,9 +33,6 @@ impl ChannelBinding {
 }
 
 /// A constructor of `TlsConnect`ors.
-///
-/// Requires the `runtime` Cargo feature (enabled by default).
-#[cfg(feature = "runtime")]
 pub trait MakeTlsConnect<S> {
     /// The stream type created by the `TlsConnect` implementation.
     type Stream: TlsStream + Unpin;
@@ -80,7 +77,6 @@ pub trait TlsStream: AsyncRead + AsyncWrite {
 #[derive(Debug, Copy, Clone)]
 pub struct NoTls;
 
-#[cfg(feature = "runtime")]
 impl<S> MakeTlsConnect<S> for NoTls {
     type Stream = NoTlsStream;
     type TlsConnect = NoTls;
@@ -0,0 +1,348 @@
+use crate::client::InnerClient;
+use crate::codec::FrontendMessage;
+use crate::connection::RequestMessages;
+use crate::query::{check_param_count, encode_parse, extract_row_affected};
+use crate::query_logger::QueryLog;
+use crate::trace::{self, Instrument};
+use crate::types::{IsNull, ToSql, WrongType};
+use crate::{Error, Statement};
+use byteorder::{BigEndian, ByteOrder};
+use bytes::{BufMut, Bytes, BytesMut};
+use futures::channel::mpsc;
+use futures::stream::{self, BoxStream};
+use futures::{future, ready, SinkExt, Stream, StreamExt, TryStreamExt};
+use postgres_protocol::message::backend::Message;
+use postgres_protocol::message::frontend;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+// the size of the chunks read from an `AsyncRead` value
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+// Filler sent in place of the rest of a value whose stream failed part way through. Spaces are valid in both the
+// bytea and text types, so the Bind itself doesn't fail.
+static PADDING: [u8; 4096] = [b' '; 4096];
+
+/// A parameter value which is streamed to the server in chunks rather than being encoded up front.
+///
+/// The value is sent in the binary format exactly as produced by the stream, so it can only be bound to parameters of
+/// types whose binary representation is the raw value: `BYTEA` and the text types. The protocol requires the length of
+/// a value to precede it, so the total length of the stream must be known in advance.
+pub struct ToSqlStream<'a> {
+    len: usize,
+    stream: BoxStream<'a, io::Result<Bytes>>,
+}
+
+impl<'a> ToSqlStream<'a> {
+    /// Creates a value from a stream of chunks whose lengths add up to `len`.
+    pub fn new<S>(len: usize, stream: S) -> ToSqlStream<'a>
+    where
+        S: Stream<Item = io::Result<Bytes>> + Send + 'a,
+    {
+        ToSqlStream {
+            len,
+            stream: stream.boxed(),
+        }
+    }
+
+    /// Creates a value from a buffer.
+    ///
+    /// Unlike values encoded by their `ToSql` implementation, large buffers are written to the server without being
+    /// copied into the connection's write buffer first.
+    pub fn from_bytes(bytes: Bytes) -> ToSqlStream<'a> {
+        ToSqlStream::new(bytes.len(), stream::once(future::ready(Ok(bytes))))
+    }
+
+    /// Creates a value from the first `len` bytes of a reader.
+    pub fn from_reader<R>(len: usize, reader: R) -> ToSqlStream<'a>
+    where
+        R: AsyncRead + Send + 'a,
+    {
+        let reader = Box::pin(reader.take(len as u64));
+        let stream = stream::try_unfold(reader, |mut reader| async move {
+            let mut buf = BytesMut::with_capacity(READ_CHUNK_SIZE);
+            match reader.read_buf(&mut buf).await? {
+                0 => Ok(None),
+                _ => Ok(Some((buf.freeze(), reader))),
+            }
+        });
+        ToSqlStream::new(len, stream)
+    }
+}
+
+impl fmt::Debug for ToSqlStream<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ToSqlStream")
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+/// A parameter passed to `Client::execute_streamed`.
+#[derive(Debug)]
+pub enum BindParam<'a> {
+    /// A value encoded up front by its `ToSql` implementation.
+    Value(&'a (dyn ToSql + Sync)),
+    /// A value streamed to the server.
+    Stream(ToSqlStream<'a>),
+}
+
+impl<'a, T> From<&'a T> for BindParam<'a>
+where
+    T: ToSql + Sync,
+{
+    fn from(value: &'a T) -> BindParam<'a> {
+        BindParam::Value(value)
+    }
+}
+
+impl<'a> From<ToSqlStream<'a>> for BindParam<'a> {
+    fn from(stream: ToSqlStream<'a>) -> BindParam<'a> {
+        BindParam::Stream(stream)
+    }
+}
+
+enum Part {
+    // the start of the message, which is traced like any other
+    Header(Bytes),
+    Chunk(Bytes),
+    Value(usize),
+}
+
+/// Interleaves the encoded parts of a Bind message with the chunks of its streamed values.
+///
+/// If the client stops sending chunks before a value is complete, the rest of the message is padded out so the
+/// connection stays in sync with the server, and the statement is not executed.
+pub struct ParamStreamReceiver {
+    receiver: mpsc::Receiver<Bytes>,
+    parts: VecDeque<Part>,
+    remaining: usize,
+    aborted: bool,
+    done: bool,
+}
+
+impl Stream for ParamStreamReceiver {
+    type Item = FrontendMessage;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<FrontendMessage>> {
+        loop {
+            if self.remaining > 0 {
+                if self.aborted {
+                    let len = self.remaining.min(PADDING.len());
+                    self.remaining -= len;
+                    let padding = Bytes::from_static(&PADDING[..len]);
+                    return Poll::Ready(Some(FrontendMessage::Chunk(padding)));
+                }
+
+                match ready!(self.receiver.poll_next_unpin(cx)) {
+                    Some(chunk) => {
+                        self.remaining -= chunk.len();
+                        return Poll::Ready(Some(FrontendMessage::Chunk(chunk)));
+                    }
+                    None => {
+                        self.aborted = true;
+                        continue;
+                    }
+                }
+            }
+
+            match self.parts.pop_front() {
+                Some(Part::Header(buf)) => return Poll::Ready(Some(FrontendMessage::Raw(buf))),
+                Some(Part::Chunk(buf)) => return Poll::Ready(Some(FrontendMessage::Chunk(buf))),
+                Some(Part::Value(len)) => self.remaining = len,
+                None if self.done => return Poll::Ready(None),
+                None => {
+                    self.done = true;
+                    let mut buf = BytesMut::new();
+                    if !self.aborted {
+                        frontend::execute("", 0, &mut buf).unwrap();
+                    }
+                    frontend::sync(&mut buf);
+                    return Poll::Ready(Some(FrontendMessage::Raw(buf.freeze())));
+                }
+            }
+        }
+    }
+}
+
+pub async fn execute(
+    client: &InnerClient,
+    statement: Statement,
+    params: Vec<BindParam<'_>>,
+) -> Result<u64, Error> {
+    let span = trace::query_span(client, statement.query());
+    let log = QueryLog::start(client, statement.params().len());
+    let result = execute_inner(client, &statement, params)
+        .instrument(span.clone())
+        .await;
+
+    trace::record_result(&span, &result);
+    if let Ok(rows) = result {
+        trace::record_rows(&span, rows);
+    }
+
+    if let Some(log) = log {
+        log.finish(
+            statement.query(),
+            result.as_ref().ok().copied(),
+            result.as_ref().err(),
+        );
+    }
+    result
+}
+
+async fn execute_inner(
+    client: &InnerClient,
+    statement: &Statement,
+    params: Vec<BindParam<'_>>,
+) -> Result<u64, Error> {
+    check_param_count(statement, params.len())?;
+
+    let (parts, streams) = client.with_buf(|buf| encode(statement, params, buf))?;
+
+    let (mut sender, receiver) = mpsc::channel(1);
+    let receiver = ParamStreamReceiver {
+        receiver,
+        parts,
+        remaining: 0,
+        aborted: false,
+        done: false,
+    };
+    let mut responses = client.send(RequestMessages::ParamStream(receiver)).await?;
+
+    for (idx, stream) in streams {
+        let mut remaining = stream.len;
+        let mut chunks = stream.stream;
+        while let Some(chunk) = chunks
+            .try_next()
+            .await
+            .map_err(|e| Error::to_sql(Box::new(e), idx))?
+        {
+            if chunk.len() > remaining {
+                let message = format!(
+                    "stream produced more than its length of {} bytes",
+                    stream.len
+                );
+                return Err(Error::to_sql(message.into(), idx));
+            }
+            remaining -= chunk.len();
+            if !chunk.is_empty() {
+                sender.send(chunk).await.map_err(|_| Error::closed())?;
+            }
+        }
+        if remaining > 0 {
+            let message = format!(
+                "stream ended after {} of its {} bytes",
+                stream.len - remaining,
+                stream.len
+            );
+            return Err(Error::to_sql(message.into(), idx));
+        }
+    }
+
+    responses.bind_complete().await?;
+
+    let mut rows = 0;
+    loop {
+        match responses.next().await? {
+            Message::DataRow(_) => {}
+            Message::CommandComplete(body) => rows = extract_row_affected(&body)?,
+            Message::EmptyQueryResponse => rows = 0,
+            Message::ReadyForQuery(_) => return Ok(rows),
+            _ => return Err(Error::unexpected_message()),
+        }
+    }
+}
+
+// Encodes the Bind message for the statement, split around each of the streamed values.
+#[allow(clippy::type_complexity)]
+fn encode<'a>(
+    statement: &Statement,
+    params: Vec<BindParam<'a>>,
+    buf: &mut BytesMut,
+) -> Result<(VecDeque<Part>, Vec<(usize, ToSqlStream<'a>)>), Error> {
+    if statement.name().is_empty() {
+        encode_parse(statement, buf)?;
+    }
+
+    buf.put_u8(b'B');
+    buf.put_i32(0);
+    // unnamed portal
+    buf.put_u8(0);
+    buf.put_slice(statement.name().as_bytes());
+    buf.put_u8(0);
+    // all parameters use the binary format
+    buf.put_i16(1);
+    buf.put_i16(1);
+    let count = i16::try_from(params.len()).map_err(|_| {
+        Error::encode(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "too many parameters",
+        ))
+    })?;
+    buf.put_i16(count);
+
+    let mut segments = vec![];
+    let mut streams = vec![];
+    for (idx, (param, ty)) in params.into_iter().zip(statement.params()).enumerate() {
+        match param {
+            BindParam::Value(value) => {
+                let base = buf.len();
+                buf.put_i32(0);
+                let len = match value.to_sql_checked(ty, buf) {
+                    Ok(IsNull::No) => i32::try_from(buf.len() - base - 4)
+                        .map_err(|_| Error::to_sql("value too large to transmit".into(), idx))?,
+                    Ok(IsNull::Yes) => -1,
+                    Err(e) => return Err(Error::to_sql(e, idx)),
+                };
+                BigEndian::write_i32(&mut buf[base..], len);
+            }
+            BindParam::Stream(stream) => {
+                if !<&[u8] as ToSql>::accepts(ty) && !<&str as ToSql>::accepts(ty) {
+                    let e = WrongType::new::<ToSqlStream<'_>>(ty.clone());
+                    return Err(Error::to_sql(Box::new(e), idx));
+                }
+                let len = i32::try_from(stream.len)
+                    .map_err(|_| Error::to_sql("value too large to transmit".into(), idx))?;
+                buf.put_i32(len);
+                segments.push(buf.split());
+                streams.push((idx, stream));
+            }
+        }
+    }
+    // all results use the binary format
+    buf.put_i16(1);
+    buf.put_i16(1);
+    segments.push(buf.split());
+
+    let len = segments.iter().map(|s| s.len()).sum::<usize>() - 1
+        + streams.iter().map(|(_, s)| s.len).sum::<usize>();
+    let len = i32::try_from(len).map_err(|_| {
+        Error::encode(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "value too large to transmit",
+        ))
+    })?;
+    BigEndian::write_i32(&mut segments[0][1..], len);
+
+    let mut parts = VecDeque::new();
+    let mut lens = streams.iter().map(|(_, s)| s.len);
+    for (i, segment) in segments.into_iter().enumerate() {
+        let segment = segment.freeze();
+        parts.push_back(if i == 0 {
+            Part::Header(segment)
+        } else {
+            Part::Chunk(segment)
+        });
+        if let Some(len) = lens.next() {
+            parts.push_back(Part::Value(len));
+        }
+    }
+
+    Ok((parts, streams))
+}
@@ -1,7 +1,7 @@
 use crate::to_statement::private::{Sealed, ToStatementType};
 use crate::Statement;
 
-mod private {
+pub(crate) mod private {
     use crate::{Client, Error, Statement};
 
     pub trait Sealed {}
@@ -15,7 +15,7 @@ mod private {
         pub async fn into_statement(self, client: &Client) -> Result<Statement, Error> {
             match self {
                 ToStatementType::Statement(s) => Ok(s.clone()),
-                ToStatementType::Query(s) => client.prepare(s).await,
+                ToStatementType::Query(s) => client.prepare_implicit(s).await,
             }
         }
     }
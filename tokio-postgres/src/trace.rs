@@ -0,0 +1,243 @@
+//! Instrumentation emitted with the `tracing` feature.
+//!
+//! Spans follow the OpenTelemetry semantic conventions for database clients. Without the feature, the span type and
+//! all of the functions here are no-ops.
+
+pub use self::imp::*;
+
+#[cfg(feature = "tracing")]
+mod imp {
+    use crate::client::InnerClient;
+    #[cfg(any(
+        feature = "runtime",
+        feature = "runtime-async-std",
+        feature = "runtime-smol"
+    ))]
+    use crate::config::Host;
+    #[cfg(any(
+        feature = "runtime",
+        feature = "runtime-async-std",
+        feature = "runtime-smol"
+    ))]
+    use crate::Config;
+    use crate::Error;
+    pub use tracing::{Instrument, Span};
+
+    // Spans for statements are parented to the client's open transaction, if any, rather than the current span.
+    macro_rules! statement_span {
+        ($client:expr, $name:expr, $($fields:tt)*) => {
+            match $client.transaction_span() {
+                Some(parent) => tracing::info_span!(parent: &parent, $name, $($fields)*),
+                None => tracing::info_span!($name, $($fields)*),
+            }
+        };
+    }
+
+    #[cfg(any(
+        feature = "runtime",
+        feature = "runtime-async-std",
+        feature = "runtime-smol"
+    ))]
+    pub fn connect_span(config: &Config) -> Span {
+        tracing::info_span!(
+            "connect",
+            otel.kind = "client",
+            otel.status_code = tracing::field::Empty,
+            otel.status_description = tracing::field::Empty,
+            db.system = "postgresql",
+            db.user = config.get_user(),
+            db.name = config.get_dbname(),
+            db.response.status_code = tracing::field::Empty,
+            net.peer.name = tracing::field::Empty,
+            net.peer.port = tracing::field::Empty,
+        )
+    }
+
+    #[cfg(any(
+        feature = "runtime",
+        feature = "runtime-async-std",
+        feature = "runtime-smol"
+    ))]
+    pub fn record_peer(span: &Span, host: &Host, port: u16) {
+        match host {
+            Host::Tcp(host) => span.record("net.peer.name", host.as_str()),
+            #[cfg(unix)]
+            Host::Unix(path) => {
+                span.record("net.peer.name", tracing::field::display(path.display()))
+            }
+        };
+        span.record("net.peer.port", port);
+    }
+
+    pub fn handshake_span() -> Span {
+        tracing::info_span!(
+            "handshake",
+            otel.status_code = tracing::field::Empty,
+            otel.status_description = tracing::field::Empty,
+            db.response.status_code = tracing::field::Empty,
+        )
+    }
+
+    pub fn prepare_span(client: &InnerClient, query: &str) -> Span {
+        statement_span!(
+            client,
+            "prepare",
+            otel.kind = "client",
+            otel.status_code = tracing::field::Empty,
+            otel.status_description = tracing::field::Empty,
+            db.system = "postgresql",
+            db.statement = query,
+            db.response.status_code = tracing::field::Empty,
+        )
+    }
+
+    pub fn query_span(client: &InnerClient, query: &str) -> Span {
+        statement_span!(
+            client,
+            "query",
+            otel.kind = "client",
+            otel.status_code = tracing::field::Empty,
+            otel.status_description = tracing::field::Empty,
+            db.system = "postgresql",
+            db.statement = query,
+            db.operation = operation(query),
+            db.response.status_code = tracing::field::Empty,
+            db.response.returned_rows = tracing::field::Empty,
+            db.postgresql.bytes_sent = tracing::field::Empty,
+            db.postgresql.bytes_received = tracing::field::Empty,
+        )
+    }
+
+    pub fn transaction_span(client: &InnerClient, savepoint: Option<&str>) -> Span {
+        statement_span!(
+            client,
+            "transaction",
+            otel.status_code = tracing::field::Empty,
+            otel.status_description = tracing::field::Empty,
+            db.system = "postgresql",
+            db.postgresql.savepoint = savepoint,
+            db.postgresql.transaction.outcome = tracing::field::Empty,
+        )
+    }
+
+    pub fn record_result<T>(span: &Span, result: &Result<T, Error>) {
+        match result {
+            Ok(_) => record_ok(span),
+            Err(e) => record_error(span, e),
+        }
+    }
+
+    pub fn record_ok(span: &Span) {
+        span.record("otel.status_code", "OK");
+    }
+
+    pub fn record_error(span: &Span, error: &Error) {
+        span.record("otel.status_code", "ERROR");
+        span.record("otel.status_description", tracing::field::display(error));
+        if let Some(code) = error.code() {
+            span.record("db.response.status_code", code.code());
+        }
+    }
+
+    pub fn record_rows(span: &Span, rows: u64) {
+        span.record("db.response.returned_rows", rows);
+    }
+
+    pub fn record_bytes(span: &Span, sent: usize, received: usize) {
+        span.record("db.postgresql.bytes_sent", sent);
+        span.record("db.postgresql.bytes_received", received);
+    }
+
+    pub fn record_outcome(span: &Span, outcome: &'static str) {
+        span.record("db.postgresql.transaction.outcome", outcome);
+    }
+
+    pub fn enter_transaction(client: &InnerClient, span: &Span) {
+        client.push_transaction_span(span.clone());
+    }
+
+    pub fn exit_transaction(client: &InnerClient) {
+        client.pop_transaction_span();
+    }
+
+    fn operation(query: &str) -> &str {
+        query.split_whitespace().next().unwrap_or("")
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+mod imp {
+    use crate::client::InnerClient;
+    #[cfg(any(
+        feature = "runtime",
+        feature = "runtime-async-std",
+        feature = "runtime-smol"
+    ))]
+    use crate::config::Host;
+    #[cfg(any(
+        feature = "runtime",
+        feature = "runtime-async-std",
+        feature = "runtime-smol"
+    ))]
+    use crate::Config;
+    use crate::Error;
+
+    #[derive(Clone)]
+    pub struct Span;
+
+    pub trait Instrument: Sized {
+        fn instrument(self, _: Span) -> Self {
+            self
+        }
+    }
+
+    impl<T> Instrument for T {}
+
+    #[cfg(any(
+        feature = "runtime",
+        feature = "runtime-async-std",
+        feature = "runtime-smol"
+    ))]
+    pub fn connect_span(_: &Config) -> Span {
+        Span
+    }
+
+    #[cfg(any(
+        feature = "runtime",
+        feature = "runtime-async-std",
+        feature = "runtime-smol"
+    ))]
+    pub fn record_peer(_: &Span, _: &Host, _: u16) {}
+
+    pub fn handshake_span() -> Span {
+        Span
+    }
+
+    pub fn prepare_span(_: &InnerClient, _: &str) -> Span {
+        Span
+    }
+
+    pub fn query_span(_: &InnerClient, _: &str) -> Span {
+        Span
+    }
+
+    pub fn transaction_span(_: &InnerClient, _: Option<&str>) -> Span {
+        Span
+    }
+
+    pub fn record_result<T>(_: &Span, _: &Result<T, Error>) {}
+
+    pub fn record_ok(_: &Span) {}
+
+    pub fn record_error(_: &Span, _: &Error) {}
+
+    pub fn record_rows(_: &Span, _: u64) {}
+
+    pub fn record_bytes(_: &Span, _: usize, _: usize) {}
+
+    pub fn record_outcome(_: &Span, _: &'static str) {}
+
+    pub fn enter_transaction(_: &InnerClient, _: &Span) {}
+
+    pub fn exit_transaction(_: &InnerClient) {}
+}
@@ -1,17 +1,20 @@
 use crate::codec::FrontendMessage;
 use crate::connection::RequestMessages;
 use crate::copy_out::CopyOutStream;
-use crate::query::RowStream;
-#[cfg(feature = "runtime")]
+use crate::escape::escape_literal;
+use crate::query::{PrefetchRowStream, RowStream};
 use crate::tls::MakeTlsConnect;
 use crate::tls::TlsConnect;
-use crate::types::{BorrowToSql, ToSql, Type};
-#[cfg(feature = "runtime")]
+use crate::trace;
+use crate::types::{BorrowToSql, FromSqlOwned, Oid, ToSql, Type};
 use crate::Socket;
 use crate::{
-    bind, query, slice_iter, CancelToken, Client, CopyInSink, Error, Portal, Row,
-    SimpleQueryMessage, Statement, ToStatement,
+    advisory_lock, bind, bulk_upsert, large_object, query, slice_iter, AdvisoryLockKey, BindParam,
+    CancelToken, Client, CommandTag, CopyInSink, Error, LargeObject, LargeObjectMode, NamedParams,
+    ParameterScope, Portal, Row, SimpleQueryMessage, SimpleResultSetStream, Statement, ToStatement,
 };
+#[cfg(feature = "with-serde_json-1")]
+use crate::{ExplainOptions, QueryPlan};
 use bytes::Buf;
 use futures::TryStreamExt;
 use postgres_protocol::message::frontend;
@@ -25,6 +28,7 @@ pub struct Transaction<'a> {
     client: &'a mut Client,
     savepoint: Option<Savepoint>,
     done: bool,
+    span: trace::Span,
 }
 
 /// A representation of a PostgreSQL database savepoint.
@@ -35,9 +39,11 @@ struct Savepoint {
 
 impl<'a> Drop for Transaction<'a> {
     fn drop(&mut self) {
+        trace::exit_transaction(self.client.inner());
         if self.done {
             return;
         }
+        trace::record_outcome(&self.span, "rollback");
 
         let query = if let Some(sp) = self.savepoint.as_ref() {
             format!("ROLLBACK TO {}", sp.name)
@@ -51,41 +57,133 @@ impl<'a> Drop for Transaction<'a> {
         let _ = self
             .client
             .inner()
-            .send(RequestMessages::Single(FrontendMessage::Raw(buf)));
+            .send_now(RequestMessages::Single(FrontendMessage::Raw(buf)));
     }
 }
 
 impl<'a> Transaction<'a> {
     pub(crate) fn new(client: &'a mut Client) -> Transaction<'a> {
+        let span = trace::transaction_span(client.inner(), None);
+        trace::enter_transaction(client.inner(), &span);
         Transaction {
             client,
             savepoint: None,
             done: false,
+            span,
         }
     }
 
+    async fn finish(&mut self, query: &str, outcome: &'static str) -> Result<(), Error> {
+        self.done = true;
+        trace::record_outcome(&self.span, outcome);
+        let result = self.client.batch_execute(query).await;
+        trace::record_result(&self.span, &result);
+        result
+    }
+
     /// Consumes the transaction, committing all changes made within it.
     pub async fn commit(mut self) -> Result<(), Error> {
-        self.done = true;
         let query = if let Some(sp) = self.savepoint.as_ref() {
             format!("RELEASE {}", sp.name)
         } else {
             "COMMIT".to_string()
         };
-        self.client.batch_execute(&query).await
+        self.finish(&query, "commit").await
     }
 
     /// Rolls the transaction back, discarding all changes made within it.
     ///
     /// This is equivalent to `Transaction`'s `Drop` implementation, but provides any error encountered to the caller.
     pub async fn rollback(mut self) -> Result<(), Error> {
-        self.done = true;
         let query = if let Some(sp) = self.savepoint.as_ref() {
             format!("ROLLBACK TO {}", sp.name)
         } else {
             "ROLLBACK".to_string()
         };
-        self.client.batch_execute(&query).await
+        self.finish(&query, "rollback").await
+    }
+
+    /// Consumes the transaction, preparing it for two-phase commit with the specified global identifier.
+    ///
+    /// Once prepared, the transaction is no longer associated with this connection. It can later be finished from any
+    /// connection with `Client::commit_prepared` or `Client::rollback_prepared`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the transaction is nested (i.e. a savepoint).
+    pub async fn prepare_two_phase(mut self, gid: &str) -> Result<(), Error> {
+        assert!(
+            self.savepoint.is_none(),
+            "nested transactions cannot be prepared for two-phase commit"
+        );
+        let query = format!("PREPARE TRANSACTION {}", escape_literal(gid));
+        self.finish(&query, "prepare").await
+    }
+
+    /// Acquires a transaction-level advisory lock, waiting until it becomes available.
+    ///
+    /// The lock is automatically released at the end of the top-level transaction; there is no way to release it
+    /// earlier.
+    pub async fn advisory_xact_lock<K>(&self, key: K) -> Result<(), Error>
+    where
+        K: Into<AdvisoryLockKey>,
+    {
+        advisory_lock::xact_lock(self.client, key.into()).await
+    }
+
+    /// Attempts to acquire a transaction-level advisory lock without waiting.
+    ///
+    /// Returns `false` if the lock is currently held by another session.
+    pub async fn try_advisory_xact_lock<K>(&self, key: K) -> Result<bool, Error>
+    where
+        K: Into<AdvisoryLockKey>,
+    {
+        advisory_lock::try_xact_lock(self.client, key.into()).await
+    }
+
+    /// Creates a new, empty large object, returning its OID.
+    pub async fn create_large_object(&self) -> Result<Oid, Error> {
+        large_object::create(self.client).await
+    }
+
+    /// Opens a large object for reading and/or writing.
+    ///
+    /// The returned handle can only be used until the transaction ends.
+    pub async fn open_large_object(
+        &self,
+        oid: Oid,
+        mode: LargeObjectMode,
+    ) -> Result<LargeObject<'_>, Error> {
+        large_object::open(self.client, oid, mode).await
+    }
+
+    /// Deletes a large object.
+    pub async fn unlink_large_object(&self, oid: Oid) -> Result<(), Error> {
+        large_object::unlink(self.client, oid).await
+    }
+
+    /// Inserts rows into a table, updating the existing rows they conflict with.
+    ///
+    /// The rows are copied into a temporary table, from which they are inserted with an
+    /// `INSERT ... ON CONFLICT (conflict_target) DO UPDATE` statement setting all columns not in the conflict target.
+    /// This is much faster than upserting the rows one at a time. Each row provides a value for each of `columns`,
+    /// encoded with the types of the table's columns. If multiple rows conflict with each other, only the last one is
+    /// kept. With an empty conflict target, or if all columns are part of it, conflicting rows are skipped instead.
+    ///
+    /// The table name is included in the statements as-is, so it may be schema-qualified but must be escaped if
+    /// necessary, while the column names are escaped. Returns the number of rows inserted or updated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `columns` is empty, or if a row does not contain one value for each column.
+    pub async fn bulk_upsert(
+        &self,
+        table: &str,
+        columns: &[&str],
+        conflict_target: &[&str],
+        rows: &[&[&(dyn ToSql + Sync)]],
+    ) -> Result<u64, Error> {
+        bulk_upsert::bulk_upsert(self.client, table, columns, conflict_target, rows).await
     }
 
     /// Like `Client::prepare`.
@@ -138,6 +236,21 @@ impl<'a> Transaction<'a> {
         self.client.query_opt(statement, params).await
     }
 
+    /// Like `Client::query_fold`.
+    pub async fn query_fold<T, B, F>(
+        &self,
+        statement: &T,
+        params: &[&(dyn ToSql + Sync)],
+        init: B,
+        f: F,
+    ) -> Result<B, Error>
+    where
+        T: ?Sized + ToStatement,
+        F: FnMut(B, Row) -> Result<B, Error>,
+    {
+        self.client.query_fold(statement, params, init, f).await
+    }
+
     /// Like `Client::query_raw`.
     pub async fn query_raw<T, P, I>(&self, statement: &T, params: I) -> Result<RowStream, Error>
     where
@@ -149,6 +262,24 @@ impl<'a> Transaction<'a> {
         self.client.query_raw(statement, params).await
     }
 
+    /// Like `Client::query_typed`.
+    pub async fn query_typed(
+        &self,
+        query: &str,
+        params: &[(&(dyn ToSql + Sync), Type)],
+    ) -> Result<Vec<Row>, Error> {
+        self.client.query_typed(query, params).await
+    }
+
+    /// Like `Client::query_typed_raw`.
+    pub async fn query_typed_raw<P, I>(&self, query: &str, params: I) -> Result<RowStream, Error>
+    where
+        P: BorrowToSql,
+        I: IntoIterator<Item = (P, Type)>,
+    {
+        self.client.query_typed_raw(query, params).await
+    }
+
     /// Like `Client::execute`.
     pub async fn execute<T>(
         &self,
@@ -161,6 +292,33 @@ impl<'a> Transaction<'a> {
         self.client.execute(statement, params).await
     }
 
+    /// Like `Client::execute`, but executes the statement within a savepoint which is rolled back if it fails.
+    ///
+    /// A failed statement normally aborts the enclosing transaction, causing all further statements to fail until it
+    /// is rolled back. Here the error is returned after rolling back to the savepoint, so the transaction remains
+    /// usable. This supports patterns such as attempting an insert and falling back to an update on a unique
+    /// violation. If rolling back to the savepoint itself fails, that error is returned instead.
+    pub async fn try_execute_or_rollback_to_savepoint<T>(
+        &mut self,
+        statement: &T,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<u64, Error>
+    where
+        T: ?Sized + ToStatement,
+    {
+        let transaction = self.transaction().await?;
+        match transaction.execute(statement, params).await {
+            Ok(rows) => {
+                transaction.commit().await?;
+                Ok(rows)
+            }
+            Err(e) => {
+                transaction.rollback().await?;
+                Err(e)
+            }
+        }
+    }
+
     /// Like `Client::execute_iter`.
     pub async fn execute_raw<P, I, T>(&self, statement: &T, params: I) -> Result<u64, Error>
     where
@@ -172,14 +330,68 @@ impl<'a> Transaction<'a> {
         self.client.execute_raw(statement, params).await
     }
 
+    /// Like `Client::execute_with_tag`.
+    pub async fn execute_with_tag<T>(
+        &self,
+        statement: &T,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<CommandTag, Error>
+    where
+        T: ?Sized + ToStatement,
+    {
+        self.client.execute_with_tag(statement, params).await
+    }
+
+    /// Like `Client::execute_raw_with_tag`.
+    pub async fn execute_raw_with_tag<T, P, I>(
+        &self,
+        statement: &T,
+        params: I,
+    ) -> Result<CommandTag, Error>
+    where
+        T: ?Sized + ToStatement,
+        P: BorrowToSql,
+        I: IntoIterator<Item = P>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        self.client.execute_raw_with_tag(statement, params).await
+    }
+
+    /// Like `Client::query_named`.
+    pub async fn query_named<P>(&self, query: &str, params: &P) -> Result<Vec<Row>, Error>
+    where
+        P: ?Sized + NamedParams,
+    {
+        self.client.query_named(query, params).await
+    }
+
+    /// Like `Client::execute_named`.
+    pub async fn execute_named<P>(&self, query: &str, params: &P) -> Result<u64, Error>
+    where
+        P: ?Sized + NamedParams,
+    {
+        self.client.execute_named(query, params).await
+    }
+
+    /// Like `Client::execute_streamed`.
+    pub async fn execute_streamed<T>(
+        &self,
+        statement: &T,
+        params: Vec<BindParam<'_>>,
+    ) -> Result<u64, Error>
+    where
+        T: ?Sized + ToStatement,
+    {
+        self.client.execute_streamed(statement, params).await
+    }
+
     /// Binds a statement to a set of parameters, creating a `Portal` which can be incrementally queried.
     ///
     /// Portals only last for the duration of the transaction in which they are created, and can only be used on the
     /// connection that created them.
     ///
-    /// # Panics
-    ///
-    /// Panics if the number of parameters provided does not match the number expected.
+    /// Returns an error if the number of parameters provided does not match the number expected, or if any of them
+    /// has a type incompatible with the statement.
     pub async fn bind<T>(
         &self,
         statement: &T,
@@ -205,6 +417,35 @@ impl<'a> Transaction<'a> {
         bind::bind(self.client.inner(), statement, params).await
     }
 
+    /// Executes a statement, returning a stream of the resulting rows which are fetched from the server in batches.
+    ///
+    /// A stream returned by `query_raw` receives rows as fast as the server sends them, and once the consumer falls
+    /// behind the connection stops reading from the socket, holding up responses to any other requests. This method
+    /// instead binds the statement to a portal and executes it `prefetch` rows at a time, requesting the next batch
+    /// only once all of the rows of the previous one have been consumed. At most `prefetch` rows are buffered, at the
+    /// cost of a round trip to the server per batch. If `prefetch` is negative or 0, all rows are fetched at once.
+    ///
+    /// The portal is closed when the stream is dropped.
+    pub async fn query_prefetch<T, P, I>(
+        &self,
+        statement: &T,
+        params: I,
+        prefetch: i32,
+    ) -> Result<PrefetchRowStream, Error>
+    where
+        T: ?Sized + ToStatement,
+        P: BorrowToSql,
+        I: IntoIterator<Item = P>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let portal = self.bind_raw(statement, params).await?;
+        Ok(PrefetchRowStream::new(
+            self.client.inner().clone(),
+            portal,
+            prefetch,
+        ))
+    }
+
     /// Continues execution of a portal, returning a stream of the resulting rows.
     ///
     /// Unlike `query`, portals can be incrementally evaluated by limiting the number of rows returned in each call to
@@ -227,6 +468,43 @@ impl<'a> Transaction<'a> {
         query::query_portal(self.client.inner(), portal, max_rows).await
     }
 
+    /// Like `Client::function_call`.
+    pub async fn function_call<T>(
+        &self,
+        function: Oid,
+        params: &[(&(dyn ToSql + Sync), Type)],
+        result_type: &Type,
+    ) -> Result<T, Error>
+    where
+        T: FromSqlOwned,
+    {
+        self.client
+            .function_call(function, params, result_type)
+            .await
+    }
+
+    /// Like `Client::explain`.
+    #[cfg(feature = "with-serde_json-1")]
+    pub async fn explain(
+        &self,
+        statement: &str,
+        params: &[&(dyn ToSql + Sync)],
+        options: &ExplainOptions,
+    ) -> Result<QueryPlan, Error> {
+        self.client.explain(statement, params, options).await
+    }
+
+    /// Like `Client::call`.
+    ///
+    /// The procedure runs within this transaction, so it may not issue `COMMIT` or `ROLLBACK` itself.
+    pub async fn call(
+        &self,
+        procedure: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Option<Row>, Error> {
+        self.client.call(procedure, params).await
+    }
+
     /// Like `Client::copy_in`.
     pub async fn copy_in<T, U>(&self, statement: &T) -> Result<CopyInSink<U>, Error>
     where
@@ -249,18 +527,37 @@ impl<'a> Transaction<'a> {
         self.client.simple_query(query).await
     }
 
+    /// Like `Client::simple_query_result_sets`.
+    pub async fn simple_query_result_sets(
+        &self,
+        query: &str,
+    ) -> Result<SimpleResultSetStream, Error> {
+        self.client.simple_query_result_sets(query).await
+    }
+
     /// Like `Client::batch_execute`.
     pub async fn batch_execute(&self, query: &str) -> Result<(), Error> {
         self.client.batch_execute(query).await
     }
 
+    /// Like `Client::set_parameter`.
+    ///
+    /// Parameters changed for the session are reverted if the transaction is rolled back.
+    pub async fn set_parameter(
+        &self,
+        name: &str,
+        value: &str,
+        scope: ParameterScope,
+    ) -> Result<(), Error> {
+        self.client.set_parameter(name, value, scope).await
+    }
+
     /// Like `Client::cancel_token`.
     pub fn cancel_token(&self) -> CancelToken {
         self.client.cancel_token()
     }
 
     /// Like `Client::cancel_query`.
-    #[cfg(feature = "runtime")]
     #[deprecated(since = "0.6.0", note = "use Transaction::cancel_token() instead")]
     pub async fn cancel_query<T>(&self, tls: T) -> Result<(), Error>
     where
@@ -300,10 +597,13 @@ impl<'a> Transaction<'a> {
         let query = format!("SAVEPOINT {}", name);
         self.batch_execute(&query).await?;
 
+        let span = trace::transaction_span(self.client.inner(), Some(&name));
+        trace::enter_transaction(self.client.inner(), &span);
         Ok(Transaction {
             client: self.client,
             savepoint: Some(Savepoint { name, depth }),
             done: false,
+            span,
         })
     }
 
@@ -1,7 +1,9 @@
+use crate::RetryPolicy;
 use crate::{Client, Error, Transaction};
+use futures::future::BoxFuture;
 
 /// The isolation level of a database transaction.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum IsolationLevel {
     /// Equivalent to `ReadCommitted`.
@@ -19,6 +21,17 @@ pub enum IsolationLevel {
     Serializable,
 }
 
+impl IsolationLevel {
+    pub(crate) fn to_sql(self) -> &'static str {
+        match self {
+            IsolationLevel::ReadUncommitted => "READ UNCOMMITTED",
+            IsolationLevel::ReadCommitted => "READ COMMITTED",
+            IsolationLevel::RepeatableRead => "REPEATABLE READ",
+            IsolationLevel::Serializable => "SERIALIZABLE",
+        }
+    }
+}
+
 /// A builder for database transactions.
 pub struct TransactionBuilder<'a> {
     client: &'a mut Client,
@@ -63,6 +76,76 @@ impl<'a> TransactionBuilder<'a> {
     ///
     /// The transaction will roll back by default - use the `commit` method to commit it.
     pub async fn start(self) -> Result<Transaction<'a>, Error> {
+        self.client.batch_execute(&self.start_query()).await?;
+
+        Ok(Transaction::new(self.client))
+    }
+
+    /// Runs a closure in a transaction, retrying it after serialization failures and deadlocks.
+    ///
+    /// The transaction is committed if the closure succeeds and rolled back if it fails. If the closure or the commit
+    /// fails with an error the policy considers retryable, the transaction is started again after a backoff, and the
+    /// closure is rerun. Any other error is returned immediately.
+    ///
+    /// The backoff is waited out using the runtime the client was connected with, or the default runtime if it was
+    /// connected with `Config::connect_raw`. If no runtime is available, the transaction is retried immediately.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # async fn f(client: &mut tokio_postgres::Client) -> Result<(), tokio_postgres::Error> {
+    /// use tokio_postgres::{IsolationLevel, RetryPolicy};
+    ///
+    /// let balance: i64 = client
+    ///     .build_transaction()
+    ///     .isolation_level(IsolationLevel::Serializable)
+    ///     .run_with_retry(&RetryPolicy::new(), |transaction| {
+    ///         Box::pin(async move {
+    ///             transaction
+    ///                 .execute("UPDATE accounts SET balance = balance - 10 WHERE id = 1", &[])
+    ///                 .await?;
+    ///             let row = transaction
+    ///                 .query_one("SELECT balance FROM accounts WHERE id = 1", &[])
+    ///                 .await?;
+    ///             Ok(row.get(0))
+    ///         })
+    ///     })
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn run_with_retry<F, T>(self, policy: &RetryPolicy, mut f: F) -> Result<T, Error>
+    where
+        F: for<'b> FnMut(&'b mut Transaction<'_>) -> BoxFuture<'b, Result<T, Error>>,
+    {
+        let query = self.start_query();
+        let mut retries = 0;
+
+        loop {
+            self.client.batch_execute(&query).await?;
+            let mut transaction = Transaction::new(self.client);
+
+            let result = match f(&mut transaction).await {
+                Ok(value) => transaction.commit().await.map(|()| value),
+                Err(e) => {
+                    drop(transaction);
+                    Err(e)
+                }
+            };
+
+            match result {
+                Err(e) if policy.should_retry(&e, retries) => {
+                    if let Some(runtime) = self.client.runtime() {
+                        runtime.sleep(policy.backoff(retries)).await;
+                    }
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn start_query(&self) -> String {
         let mut query = "START TRANSACTION".to_string();
         let mut first = true;
 
@@ -70,13 +153,7 @@ impl<'a> TransactionBuilder<'a> {
             first = false;
 
             query.push_str(" ISOLATION LEVEL ");
-            let level = match level {
-                IsolationLevel::ReadUncommitted => "READ UNCOMMITTED",
-                IsolationLevel::ReadCommitted => "READ COMMITTED",
-                IsolationLevel::RepeatableRead => "REPEATABLE READ",
-                IsolationLevel::Serializable => "SERIALIZABLE",
-            };
-            query.push_str(level);
+            query.push_str(level.to_sql());
         }
 
         if let Some(read_only) = self.read_only {
@@ -106,8 +183,6 @@ impl<'a> TransactionBuilder<'a> {
             query.push_str(s);
         }
 
-        self.client.batch_execute(&query).await?;
-
-        Ok(Transaction::new(self.client))
+        query
     }
 }
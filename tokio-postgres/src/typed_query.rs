@@ -0,0 +1,91 @@
+use crate::types::ToSql;
+use crate::{Error, GenericClient, Row};
+use std::marker::PhantomData;
+
+/// A type which can be constructed from a row.
+///
+/// This is implemented by the record types generated by the `query!` macro, and may be derived with
+/// `#[derive(FromRow)]` or implemented manually for use with [`TypedQuery`].
+pub trait FromRow: Sized {
+    /// Constructs a value from a row.
+    fn from_row(row: &Row) -> Result<Self, Error>;
+}
+
+impl FromRow for () {
+    fn from_row(_: &Row) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// A statement along with its parameters, whose rows are decoded as values of type `R`.
+///
+/// This is the type of the `query!` macro's output.
+pub struct TypedQuery<'a, R> {
+    query: &'a str,
+    params: Vec<&'a (dyn ToSql + Sync)>,
+    _p: PhantomData<fn() -> R>,
+}
+
+impl<'a, R> TypedQuery<'a, R>
+where
+    R: FromRow,
+{
+    /// Creates a new query.
+    pub fn new(query: &'a str, params: Vec<&'a (dyn ToSql + Sync)>) -> TypedQuery<'a, R> {
+        TypedQuery {
+            query,
+            params,
+            _p: PhantomData,
+        }
+    }
+
+    /// Returns the SQL of the query.
+    pub fn sql(&self) -> &str {
+        self.query
+    }
+
+    /// Executes the query, returning all of the resulting rows.
+    pub async fn fetch_all<C>(&self, client: &C) -> Result<Vec<R>, Error>
+    where
+        C: GenericClient + Sync,
+    {
+        client
+            .query(self.query, &self.params)
+            .await?
+            .iter()
+            .map(R::from_row)
+            .collect()
+    }
+
+    /// Executes the query, returning the single resulting row.
+    ///
+    /// Returns an error if the query does not return exactly one row.
+    pub async fn fetch_one<C>(&self, client: &C) -> Result<R, Error>
+    where
+        C: GenericClient + Sync,
+    {
+        let row = client.query_one(self.query, &self.params).await?;
+        R::from_row(&row)
+    }
+
+    /// Executes the query, returning zero or one rows.
+    ///
+    /// Returns an error if the query returns more than one row.
+    pub async fn fetch_opt<C>(&self, client: &C) -> Result<Option<R>, Error>
+    where
+        C: GenericClient + Sync,
+    {
+        match client.query_opt(self.query, &self.params).await? {
+            Some(row) => R::from_row(&row).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Executes the query, returning the number of rows modified.
+    pub async fn execute<C>(&self, client: &C) -> Result<u64, Error>
+    where
+        C: GenericClient + Sync,
+    {
+        client.execute(self.query, &self.params).await
+    }
+}
@@ -0,0 +1,299 @@
+use crate::cancel_token::{self, CancelFn};
+use crate::connection_listener::ConnectionListener;
+use crate::runtime::Runtime;
+use crate::tls::{MakeTlsConnect, TlsConnect};
+use crate::{CancelToken, Error, Socket};
+use futures::future::BoxFuture;
+use log::warn;
+use std::fmt;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// The thresholds of a watchdog monitoring connections for long-running transactions and queries.
+///
+/// Transactions held open for a long time, whether or not they are idle, prevent the server from cleaning up dead
+/// rows and are a common cause of table bloat. A watchdog registered with `Config::watchdog` reports transactions and
+/// queries which exceed its thresholds to the configured `ConnectionListener` and logs a warning, and can optionally
+/// cancel queries which run for too long.
+///
+/// Each transaction and query is reported at most once. Note that streaming replication and `COPY` operations count as
+/// a single long-running query.
+#[derive(Clone, Default)]
+pub struct Watchdog {
+    transaction_threshold: Option<Duration>,
+    query_threshold: Option<Duration>,
+    cancel: Option<Arc<CancelFn>>,
+}
+
+impl Watchdog {
+    /// Creates a new watchdog with no thresholds.
+    pub fn new() -> Watchdog {
+        Watchdog::default()
+    }
+
+    /// Sets the time a transaction may remain open before it is reported.
+    pub fn transaction_threshold(mut self, threshold: Duration) -> Watchdog {
+        self.transaction_threshold = Some(threshold);
+        self
+    }
+
+    /// Sets the time a query may run before it is reported.
+    ///
+    /// With pipelined requests, a query is considered to start running once the response to the previous one has
+    /// been received.
+    pub fn query_threshold(mut self, threshold: Duration) -> Watchdog {
+        self.query_threshold = Some(threshold);
+        self
+    }
+
+    /// Requests the cancellation of queries which exceed the query threshold, in addition to reporting them.
+    ///
+    /// The cancellation request is sent over a new connection made with the provided TLS connector. Queries can only
+    /// be canceled on connections made with `Config::connect`, as the address of the server is unknown to those made
+    /// with `Config::connect_raw`.
+    pub fn cancel_long_queries<T>(mut self, tls: T) -> Watchdog
+    where
+        T: MakeTlsConnect<Socket> + Clone + 'static + Sync + Send,
+        T::TlsConnect: Send,
+        T::Stream: Send,
+        <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+    {
+        self.cancel = Some(cancel_token::cancel_fn(tls));
+        self
+    }
+
+    /// Gets the transaction threshold, if one has been set with the `transaction_threshold` method.
+    pub fn get_transaction_threshold(&self) -> Option<Duration> {
+        self.transaction_threshold
+    }
+
+    /// Gets the query threshold, if one has been set with the `query_threshold` method.
+    pub fn get_query_threshold(&self) -> Option<Duration> {
+        self.query_threshold
+    }
+
+    /// Determines if long-running queries are canceled.
+    pub fn get_cancel_long_queries(&self) -> bool {
+        self.cancel.is_some()
+    }
+}
+
+impl PartialEq for Watchdog {
+    fn eq(&self, other: &Watchdog) -> bool {
+        let cancel_eq = match (&self.cancel, &other.cancel) {
+            (Some(a), Some(b)) => Arc::as_ptr(a) as *const () == Arc::as_ptr(b) as *const (),
+            (None, None) => true,
+            _ => false,
+        };
+        self.transaction_threshold == other.transaction_threshold
+            && self.query_threshold == other.query_threshold
+            && cancel_eq
+    }
+}
+
+impl fmt::Debug for Watchdog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Watchdog")
+            .field("transaction_threshold", &self.transaction_threshold)
+            .field("query_threshold", &self.query_threshold)
+            .field("cancel_long_queries", &self.cancel.is_some())
+            .finish()
+    }
+}
+
+/// The kind of a `WatchdogEvent`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WatchdogEventKind {
+    /// A transaction has been open for longer than the transaction threshold.
+    LongTransaction,
+    /// A query has been running for longer than the query threshold.
+    LongQuery,
+}
+
+/// A report of a long-running transaction or query, passed to `ConnectionListener::watchdog_triggered`.
+#[derive(Debug, Clone)]
+pub struct WatchdogEvent {
+    kind: WatchdogEventKind,
+    elapsed: Duration,
+    canceled: bool,
+}
+
+impl WatchdogEvent {
+    /// Returns the kind of the event.
+    pub fn kind(&self) -> WatchdogEventKind {
+        self.kind
+    }
+
+    /// Returns how long the transaction has been open or the query has been running.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Determines if the watchdog requested the cancellation of the query.
+    pub fn canceled(&self) -> bool {
+        self.canceled
+    }
+}
+
+// The state of the watchdog of a single connection, updated by the connection as it sends requests and receives
+// their responses.
+pub(crate) struct ConnectionWatchdog {
+    watchdog: Watchdog,
+    runtime: Arc<dyn Runtime>,
+    cancel_token: Option<CancelToken>,
+    transaction_start: Option<Instant>,
+    transaction_reported: bool,
+    query_start: Option<Instant>,
+    query_reported: bool,
+    timer: Option<(Instant, BoxFuture<'static, ()>)>,
+    cancel: Option<BoxFuture<'static, Result<(), Error>>>,
+}
+
+impl ConnectionWatchdog {
+    pub fn new(watchdog: Watchdog, runtime: Arc<dyn Runtime>) -> ConnectionWatchdog {
+        ConnectionWatchdog {
+            watchdog,
+            runtime,
+            cancel_token: None,
+            transaction_start: None,
+            transaction_reported: false,
+            query_start: None,
+            query_reported: false,
+            timer: None,
+            cancel: None,
+        }
+    }
+
+    #[cfg(any(
+        feature = "runtime",
+        feature = "runtime-async-std",
+        feature = "runtime-smol"
+    ))]
+    pub fn set_cancel_token(&mut self, cancel_token: CancelToken) {
+        self.cancel_token = Some(cancel_token);
+    }
+
+    pub fn request_started(&mut self) {
+        if self.query_start.is_none() {
+            self.query_start = Some(Instant::now());
+            self.query_reported = false;
+        }
+    }
+
+    // Called with the transaction status of the ReadyForQuery message completing a request.
+    pub fn request_completed(&mut self, transaction_status: u8, requests_pending: bool) {
+        self.query_start = if requests_pending {
+            Some(Instant::now())
+        } else {
+            None
+        };
+        self.query_reported = false;
+
+        if transaction_status == b'I' {
+            self.transaction_start = None;
+            self.transaction_reported = false;
+        } else if self.transaction_start.is_none() {
+            self.transaction_start = Some(Instant::now());
+        }
+    }
+
+    pub fn poll(&mut self, cx: &mut Context<'_>, listener: Option<&Arc<dyn ConnectionListener>>) {
+        loop {
+            let now = Instant::now();
+            let mut deadline = None;
+
+            if let (Some(threshold), Some(start)) =
+                (self.watchdog.transaction_threshold, self.transaction_start)
+            {
+                if !self.transaction_reported {
+                    let elapsed = now.saturating_duration_since(start);
+                    if elapsed >= threshold {
+                        self.transaction_reported = true;
+                        report(WatchdogEventKind::LongTransaction, elapsed, false, listener);
+                    } else {
+                        deadline = Some(start + threshold);
+                    }
+                }
+            }
+
+            if let (Some(threshold), Some(start)) =
+                (self.watchdog.query_threshold, self.query_start)
+            {
+                if !self.query_reported {
+                    let elapsed = now.saturating_duration_since(start);
+                    if elapsed >= threshold {
+                        self.query_reported = true;
+                        let canceled = self.start_cancel();
+                        report(WatchdogEventKind::LongQuery, elapsed, canceled, listener);
+                    } else {
+                        let query_deadline = start + threshold;
+                        deadline = Some(deadline.map_or(query_deadline, |d| query_deadline.min(d)));
+                    }
+                }
+            }
+
+            let deadline = match deadline {
+                Some(deadline) => deadline,
+                None => {
+                    self.timer = None;
+                    break;
+                }
+            };
+            if self.timer.as_ref().map(|(d, _)| *d) != Some(deadline) {
+                let sleep = self.runtime.sleep(deadline.saturating_duration_since(now));
+                self.timer = Some((deadline, sleep));
+            }
+            match self.timer.as_mut().unwrap().1.as_mut().poll(cx) {
+                // the thresholds are checked again now that the deadline has passed
+                Poll::Ready(()) => self.timer = None,
+                Poll::Pending => break,
+            }
+        }
+
+        if let Some(cancel) = &mut self.cancel {
+            if let Poll::Ready(result) = cancel.as_mut().poll(cx) {
+                if let Err(e) = result {
+                    warn!("error canceling long-running query: {}", e);
+                }
+                self.cancel = None;
+            }
+        }
+    }
+
+    fn start_cancel(&mut self) -> bool {
+        match (&self.watchdog.cancel, &self.cancel_token) {
+            (Some(cancel), Some(cancel_token)) if self.cancel.is_none() => {
+                self.cancel = Some(cancel(cancel_token.clone()));
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+fn report(
+    kind: WatchdogEventKind,
+    elapsed: Duration,
+    canceled: bool,
+    listener: Option<&Arc<dyn ConnectionListener>>,
+) {
+    match kind {
+        WatchdogEventKind::LongTransaction => {
+            warn!("transaction has been open for {:?}", elapsed)
+        }
+        WatchdogEventKind::LongQuery if canceled => {
+            warn!("canceling query which has been running for {:?}", elapsed)
+        }
+        WatchdogEventKind::LongQuery => warn!("query has been running for {:?}", elapsed),
+    }
+
+    if let Some(listener) = listener {
+        listener.watchdog_triggered(&WatchdogEvent {
+            kind,
+            elapsed,
+            canceled,
+        });
+    }
+}
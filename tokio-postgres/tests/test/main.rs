@@ -6,23 +6,39 @@ use futures::{
     future, join, pin_mut, stream, try_join, Future, FutureExt, SinkExt, StreamExt, TryStreamExt,
 };
 use pin_project_lite::pin_project;
+use std::collections::HashMap;
+use std::error::Error as _;
 use std::fmt::Write;
+use std::io::SeekFrom;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll};
 use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
 use tokio::time;
+use tokio_postgres::config::ProtocolVersion;
 use tokio_postgres::error::SqlState;
+use tokio_postgres::escape;
 use tokio_postgres::tls::{NoTls, NoTlsStream};
-use tokio_postgres::types::{Kind, Type};
+use tokio_postgres::types::{Kind, ToSql, Type, WrongType};
 use tokio_postgres::{
-    AsyncMessage, Client, Config, Connection, Error, IsolationLevel, SimpleQueryMessage,
+    render_param, AdvisoryLockKey, AsyncMessage, BindParam, Client, CommandKind, CommandTag,
+    Config, Connection, CopyFormat, CopyOptions, Error, IsolationLevel, LargeObjectMode,
+    MemoryQueryCache, MessageType, Metrics, NamedQuery, Paginator, ParameterScope, QueryBuilder,
+    QueryEvent, ReadOnlyClient, RefCursor, RetryPolicy, SimpleQueryMessage, ToSqlStream,
+    TransactionStatus,
 };
 
 mod binary_copy;
+mod mock;
 mod parse;
+mod replication;
 #[cfg(feature = "runtime")]
 mod runtime;
+#[cfg(feature = "test-harness")]
+mod testing;
 mod types;
 
 pin_project! {
@@ -163,6 +179,117 @@ async fn pipelined_prepare() {
     assert_eq!(statement2.columns()[0].type_(), &Type::INT8);
 }
 
+#[tokio::test]
+async fn pipelined_large_params() {
+    let client = connect("user=postgres").await;
+
+    let statement = client.prepare("SELECT $1::TEXT").await.unwrap();
+    let values = (0..50)
+        .map(|i| "x".repeat(if i % 3 == 0 { 100_000 } else { i }))
+        .collect::<Vec<_>>();
+    let queries = values.iter().map(|value| {
+        let client = &client;
+        let statement = &statement;
+        async move { client.query_one(statement, &[value]).await }
+    });
+    let rows = future::try_join_all(queries).await.unwrap();
+
+    for (row, value) in rows.iter().zip(&values) {
+        assert_eq!(row.get::<_, &str>(0), value);
+    }
+}
+
+#[tokio::test]
+async fn execute_streamed() {
+    let client = connect("user=postgres").await;
+
+    client
+        .batch_execute("CREATE TEMPORARY TABLE foo (id INT, data BYTEA, name TEXT)")
+        .await
+        .unwrap();
+
+    let data = (0..1_000_000).map(|i| i as u8).collect::<Vec<_>>();
+    let chunks = data
+        .chunks(100_000)
+        .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+        .collect::<Vec<_>>();
+    let name = "a".repeat(10_000);
+
+    let rows = client
+        .execute_streamed(
+            "INSERT INTO foo (id, data, name) VALUES ($1, $2, $3)",
+            vec![
+                BindParam::from(&1i32),
+                ToSqlStream::new(data.len(), stream::iter(chunks)).into(),
+                ToSqlStream::from_reader(name.len(), name.as_bytes()).into(),
+            ],
+        )
+        .await
+        .unwrap();
+    assert_eq!(rows, 1);
+
+    let row = client
+        .query_one("SELECT data, name FROM foo WHERE id = 1", &[])
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, &[u8]>(0), &data[..]);
+    assert_eq!(row.get::<_, &str>(1), name);
+
+    let short = stream::iter(vec![Ok(Bytes::from_static(b"abc"))]);
+    let err = client
+        .execute_streamed(
+            "INSERT INTO foo (id, data) VALUES ($1, $2)",
+            vec![BindParam::from(&2i32), ToSqlStream::new(10, short).into()],
+        )
+        .await
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("stream ended after 3 of its 10 bytes"));
+
+    let long = stream::iter(vec![Ok(Bytes::from_static(b"abcdef"))]);
+    client
+        .execute_streamed(
+            "INSERT INTO foo (id, data) VALUES ($1, $2)",
+            vec![BindParam::from(&3i32), ToSqlStream::new(5, long).into()],
+        )
+        .await
+        .unwrap_err();
+
+    let err = client
+        .execute_streamed(
+            "INSERT INTO foo (id) VALUES ($1)",
+            vec![ToSqlStream::new(0, stream::empty()).into()],
+        )
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("error serializing parameter 0"));
+
+    let count = client
+        .query_one("SELECT COUNT(*) FROM foo", &[])
+        .await
+        .unwrap()
+        .get::<_, i64>(0);
+    assert_eq!(count, 1);
+
+    let bytes = Bytes::from(data.clone());
+    client
+        .execute_streamed(
+            "INSERT INTO foo (id, data) VALUES ($1, $2)",
+            vec![
+                BindParam::from(&4i32),
+                ToSqlStream::from_bytes(bytes).into(),
+            ],
+        )
+        .await
+        .unwrap();
+    let row = client
+        .query_one("SELECT data FROM foo WHERE id = 4", &[])
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, Bytes>(0), data);
+}
+
 #[tokio::test]
 async fn insert_select() {
     let client = connect("user=postgres").await;
@@ -352,335 +479,935 @@ async fn simple_query() {
 }
 
 #[tokio::test]
-async fn cancel_query_raw() {
+async fn simple_query_result_sets() {
     let client = connect("user=postgres").await;
 
-    let socket = TcpStream::connect("127.0.0.1:5433").await.unwrap();
-    let cancel_token = client.cancel_token();
-    let cancel = cancel_token.cancel_query_raw(socket, NoTls);
-    let cancel = time::sleep(Duration::from_millis(100)).then(|()| cancel);
-
-    let sleep = client.batch_execute("SELECT pg_sleep(100)");
-
-    match join!(sleep, cancel) {
-        (Err(ref e), Ok(())) if e.code() == Some(&SqlState::QUERY_CANCELED) => {}
-        t => panic!("unexpected return: {:?}", t),
-    }
+    let sets = client
+        .simple_query_result_sets(
+            "CREATE TEMPORARY TABLE foo (id INT);
+             INSERT INTO foo (id) VALUES (1), (2);
+             SELECT id FROM foo ORDER BY id;
+             SELECT 'x' AS a, 2 AS b WHERE false;
+             SELECT 'y' AS c",
+        )
+        .await
+        .unwrap()
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    assert_eq!(sets.len(), 5);
+
+    assert!(sets[0].columns().is_empty());
+    assert!(sets[1].columns().is_empty());
+    assert_eq!(sets[1].rows_affected(), 2);
+
+    assert_eq!(sets[2].columns().len(), 1);
+    assert_eq!(sets[2].columns()[0].name(), "id");
+    assert_eq!(sets[2].rows().len(), 2);
+    assert_eq!(sets[2].rows()[1].get(0), Some("2"));
+
+    let names = sets[3]
+        .columns()
+        .iter()
+        .map(|c| c.name())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["a", "b"]);
+    assert!(sets[3].rows().is_empty());
+
+    assert_eq!(sets[4].columns()[0].name(), "c");
+    let rows = sets.into_iter().nth(4).unwrap().into_rows();
+    assert_eq!(rows[0].get("c"), Some("y"));
 }
 
 #[tokio::test]
-async fn transaction_commit() {
+async fn call_procedure() {
     let mut client = connect("user=postgres").await;
 
     client
         .batch_execute(
-            "CREATE TEMPORARY TABLE foo(
-                id SERIAL,
-                name TEXT
-            )",
+            "CREATE TEMPORARY TABLE call_procedure_log (id INT);
+             CREATE OR REPLACE PROCEDURE pg_temp.add_one(INOUT x INT, OUT y TEXT)
+             LANGUAGE plpgsql AS $$ BEGIN x := x + 1; y := 'done'; END $$;
+             CREATE OR REPLACE PROCEDURE pg_temp.log_and_commit(a INT, b INT)
+             LANGUAGE plpgsql AS $$
+             BEGIN
+                 INSERT INTO call_procedure_log VALUES (a);
+                 COMMIT;
+                 INSERT INTO call_procedure_log VALUES (b);
+                 ROLLBACK;
+             END $$;",
         )
         .await
         .unwrap();
 
-    let transaction = client.transaction().await.unwrap();
-    transaction
-        .batch_execute("INSERT INTO foo (name) VALUES ('steven')")
+    let row = client
+        .call("pg_temp.add_one", &[&1i32, &None::<&str>])
         .await
+        .unwrap()
         .unwrap();
-    transaction.commit().await.unwrap();
+    assert_eq!(row.get::<_, i32>("x"), 2);
+    assert_eq!(row.get::<_, &str>("y"), "done");
 
-    let stmt = client.prepare("SELECT name FROM foo").await.unwrap();
-    let rows = client.query(&stmt, &[]).await.unwrap();
+    let row = client
+        .call("pg_temp.log_and_commit", &[&1i32, &2i32])
+        .await
+        .unwrap();
+    assert!(row.is_none());
+    let ids = client
+        .query("SELECT id FROM call_procedure_log", &[])
+        .await
+        .unwrap()
+        .iter()
+        .map(|r| r.get::<_, i32>(0))
+        .collect::<Vec<_>>();
+    assert_eq!(ids, [1]);
 
-    assert_eq!(rows.len(), 1);
-    assert_eq!(rows[0].get::<_, &str>(0), "steven");
+    let transaction = client.transaction().await.unwrap();
+    let err = transaction
+        .call("pg_temp.log_and_commit", &[&3i32, &4i32])
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::INVALID_TRANSACTION_TERMINATION));
+    transaction.rollback().await.unwrap();
 }
 
 #[tokio::test]
-async fn transaction_rollback() {
+async fn ref_cursor() {
     let mut client = connect("user=postgres").await;
 
     client
         .batch_execute(
-            "CREATE TEMPORARY TABLE foo(
-                id SERIAL,
-                name TEXT
-            )",
+            "CREATE PROCEDURE pg_temp.open_series(INOUT c refcursor, n INT)
+             LANGUAGE plpgsql AS $$ BEGIN OPEN c FOR SELECT generate_series(1, n) AS i; END $$;",
         )
         .await
         .unwrap();
 
     let transaction = client.transaction().await.unwrap();
-    transaction
-        .batch_execute("INSERT INTO foo (name) VALUES ('steven')")
+    let row = transaction
+        .call("pg_temp.open_series", &[&RefCursor::new("Series"), &5i32])
         .await
+        .unwrap()
         .unwrap();
-    transaction.rollback().await.unwrap();
-
-    let stmt = client.prepare("SELECT name FROM foo").await.unwrap();
-    let rows = client.query(&stmt, &[]).await.unwrap();
+    let cursor = row.get::<_, RefCursor>(0);
+    assert_eq!(cursor, RefCursor::new("Series"));
 
-    assert_eq!(rows.len(), 0);
-}
-
-#[tokio::test]
-async fn transaction_future_cancellation() {
-    let mut client = connect("user=postgres").await;
-
-    for i in 0.. {
-        let done = {
-            let txn = client.transaction();
-            let fut = Cancellable {
-                fut: txn,
-                polls_left: i,
-            };
-            fut.await
-                .map(|res| res.expect("transaction failed"))
-                .is_some()
-        };
+    let rows = cursor.fetch(&transaction, 3).await.unwrap();
+    let values = rows.iter().map(|r| r.get(0)).collect::<Vec<i32>>();
+    assert_eq!(values, [1, 2, 3]);
 
-        assert!(!in_transaction(&client).await);
+    let rows = cursor.fetch_all(&transaction).await.unwrap();
+    assert_eq!(rows.len(), 2);
 
-        if done {
-            break;
-        }
-    }
+    cursor.close(&transaction).await.unwrap();
+    assert!(cursor.fetch(&transaction, 1).await.is_err());
 }
 
+#[cfg(feature = "with-serde_json-1")]
 #[tokio::test]
-async fn transaction_commit_future_cancellation() {
-    let mut client = connect("user=postgres").await;
+async fn explain() {
+    use tokio_postgres::ExplainOptions;
 
-    for i in 0.. {
-        let done = {
-            let txn = client.transaction().await.unwrap();
-            let commit = txn.commit();
-            let fut = Cancellable {
-                fut: commit,
-                polls_left: i,
-            };
-            fut.await
-                .map(|res| res.expect("transaction failed"))
-                .is_some()
-        };
+    let client = connect("user=postgres").await;
 
-        assert!(!in_transaction(&client).await);
+    client
+        .batch_execute(
+            "CREATE TEMPORARY TABLE foo (id INT PRIMARY KEY, name TEXT);
+             INSERT INTO foo SELECT i, 'name ' || i FROM generate_series(1, 1000) i;
+             ANALYZE foo;",
+        )
+        .await
+        .unwrap();
 
-        if done {
-            break;
-        }
-    }
+    let plan = client
+        .explain(
+            "SELECT name FROM foo WHERE id = $1",
+            &[&5i32],
+            &ExplainOptions::new(),
+        )
+        .await
+        .unwrap();
+    assert!(plan
+        .nodes()
+        .any(|node| node.index_name() == Some("foo_pkey")));
+    assert_eq!(plan.root().relation_name(), Some("foo"));
+    assert!(plan.root().total_cost().is_some());
+    assert!(plan.execution_time().is_none());
+
+    let plan = client
+        .explain(
+            "SELECT count(*) FROM foo f1 JOIN foo f2 USING (name)",
+            &[],
+            &ExplainOptions::new().analyze(true).buffers(true),
+        )
+        .await
+        .unwrap();
+    assert_eq!(plan.root().actual_rows(), Some(1.));
+    assert!(plan.root().children().len() == 1);
+    assert!(plan.nodes().any(|node| node.node_type() == "Seq Scan"));
+    assert!(plan.execution_time().is_some());
 }
 
 #[tokio::test]
-async fn transaction_rollback_future_cancellation() {
+async fn session_parameters() {
     let mut client = connect("user=postgres").await;
 
-    for i in 0.. {
-        let done = {
-            let txn = client.transaction().await.unwrap();
-            let rollback = txn.rollback();
-            let fut = Cancellable {
-                fut: rollback,
-                polls_left: i,
-            };
-            fut.await
-                .map(|res| res.expect("transaction failed"))
-                .is_some()
-        };
-
-        assert!(!in_transaction(&client).await);
-
-        if done {
-            break;
-        }
+    async fn show(client: &Client, name: &str) -> String {
+        client
+            .query_one("SELECT current_setting($1)", &[&name])
+            .await
+            .unwrap()
+            .get(0)
     }
-}
 
-#[tokio::test]
-async fn transaction_rollback_drop() {
-    let mut client = connect("user=postgres").await;
+    let default_search_path = show(&client, "search_path").await;
 
     client
-        .batch_execute(
-            "CREATE TEMPORARY TABLE foo(
-                id SERIAL,
-                name TEXT
-            )",
+        .set_parameter(
+            "search_path",
+            "\"Tenant A\", public",
+            ParameterScope::Session,
         )
         .await
         .unwrap();
+    assert_eq!(show(&client, "search_path").await, "\"Tenant A\", public");
+    client
+        .set_parameter("Application_Name", "it's me", ParameterScope::Session)
+        .await
+        .unwrap();
+    assert_eq!(show(&client, "application_name").await, "it's me");
+    assert_eq!(
+        client.changed_parameters(),
+        ["application_name", "search_path"]
+    );
 
     let transaction = client.transaction().await.unwrap();
     transaction
-        .batch_execute("INSERT INTO foo (name) VALUES ('steven')")
+        .set_parameter("statement_timeout", "5s", ParameterScope::Local)
         .await
         .unwrap();
-    drop(transaction);
+    assert_eq!(show(transaction.client(), "statement_timeout").await, "5s");
+    transaction.commit().await.unwrap();
+    assert_eq!(show(&client, "statement_timeout").await, "0");
+    assert_eq!(client.changed_parameters().len(), 2);
 
-    let stmt = client.prepare("SELECT name FROM foo").await.unwrap();
-    let rows = client.query(&stmt, &[]).await.unwrap();
+    client.reset_parameter("application_name").await.unwrap();
+    assert_eq!(client.changed_parameters(), ["search_path"]);
 
-    assert_eq!(rows.len(), 0);
+    client.reset_all_parameters().await.unwrap();
+    assert!(client.changed_parameters().is_empty());
+    assert_eq!(show(&client, "search_path").await, default_search_path);
 }
 
 #[tokio::test]
-async fn transaction_builder() {
+async fn introspect() {
+    use tokio_postgres::introspect::{self, ConstraintKind, TableKind, TypeKind};
+
     let mut client = connect("user=postgres").await;
+    let transaction = client.transaction().await.unwrap();
 
-    client
+    transaction
         .batch_execute(
-            "CREATE TEMPORARY TABLE foo(
-                id SERIAL,
-                name TEXT
-            )",
+            "CREATE SCHEMA introspect_test;
+             SET LOCAL search_path = introspect_test;
+             CREATE TYPE mood AS ENUM ('sad', 'ok', 'happy');
+             CREATE TYPE pair AS (a INT, b INT);
+             CREATE TABLE owners (id INT PRIMARY KEY);
+             CREATE TABLE pets (
+                 id INT GENERATED ALWAYS AS IDENTITY,
+                 owner_id INT NOT NULL REFERENCES owners (id),
+                 name VARCHAR(20) DEFAULT 'rex',
+                 mood mood,
+                 CONSTRAINT pets_pkey PRIMARY KEY (id),
+                 CONSTRAINT pets_name_check CHECK (name <> '')
+             );
+             CREATE INDEX pets_owner_name ON pets (owner_id, lower(name));
+             CREATE VIEW pet_names AS SELECT name FROM pets;
+             COMMENT ON TABLE pets IS 'animals';",
         )
         .await
         .unwrap();
 
-    let transaction = client
-        .build_transaction()
-        .isolation_level(IsolationLevel::Serializable)
-        .read_only(true)
-        .deferrable(true)
-        .start()
+    let tables = introspect::tables(&transaction, "introspect_test")
         .await
         .unwrap();
-    transaction
-        .batch_execute("INSERT INTO foo (name) VALUES ('steven')")
+    let tables = tables
+        .iter()
+        .map(|t| (t.name(), t.kind(), t.comment()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        tables,
+        [
+            ("owners", TableKind::Table, None),
+            ("pet_names", TableKind::View, None),
+            ("pets", TableKind::Table, Some("animals")),
+        ]
+    );
+
+    let columns = introspect::columns(&transaction, "introspect_test", "pets")
         .await
         .unwrap();
-    transaction.commit().await.unwrap();
+    assert_eq!(columns.len(), 4);
+    assert!(columns[0].is_identity());
+    assert!(!columns[0].is_nullable());
+    assert_eq!(columns[1].type_oid(), Type::INT4.oid());
+    assert_eq!(columns[2].type_name(), "character varying(20)");
+    assert_eq!(columns[2].default(), Some("'rex'::character varying"));
+    assert!(columns[2].is_nullable());
+    assert_eq!(columns[3].type_name(), "mood");
+
+    let types = introspect::types(&transaction, "introspect_test")
+        .await
+        .unwrap();
+    let names = types
+        .iter()
+        .map(|t| (t.name(), t.kind()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        names,
+        [("mood", TypeKind::Enum), ("pair", TypeKind::Composite)]
+    );
+    assert_eq!(types[0].enum_labels(), ["sad", "ok", "happy"]);
 
-    let stmt = client.prepare("SELECT name FROM foo").await.unwrap();
-    let rows = client.query(&stmt, &[]).await.unwrap();
+    let indexes = introspect::indexes(&transaction, "introspect_test", "pets")
+        .await
+        .unwrap();
+    assert_eq!(indexes.len(), 2);
+    assert_eq!(indexes[0].name(), "pets_owner_name");
+    assert_eq!(indexes[0].columns(), ["owner_id"]);
+    assert!(!indexes[0].is_unique());
+    assert!(indexes[1].is_primary());
 
-    assert_eq!(rows.len(), 1);
-    assert_eq!(rows[0].get::<_, &str>(0), "steven");
+    let constraints = introspect::constraints(&transaction, "introspect_test", "pets")
+        .await
+        .unwrap();
+    let kinds = constraints
+        .iter()
+        .map(|c| (c.name(), c.kind()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        [
+            ("pets_name_check", ConstraintKind::Check),
+            ("pets_owner_id_fkey", ConstraintKind::ForeignKey),
+            ("pets_pkey", ConstraintKind::PrimaryKey),
+        ]
+    );
+    assert_eq!(constraints[1].columns(), ["owner_id"]);
+    assert_eq!(constraints[1].referenced_table(), Some("owners"));
+    assert_eq!(constraints[1].referenced_columns(), ["id"]);
+    assert_eq!(constraints[2].referenced_table(), None);
+
+    transaction.rollback().await.unwrap();
 }
 
+#[cfg(feature = "macros")]
 #[tokio::test]
-async fn copy_in() {
+async fn query_macro() {
+    let client = connect("user=postgres").await;
+
+    let name = "int4";
+    let ty = tokio_postgres::query!(
+        "SELECT oid, typname::text AS \"typname!\", typlen, typdefault FROM pg_catalog.pg_type WHERE typname = $1",
+        name
+    )
+    .fetch_one(&client)
+    .await
+    .unwrap();
+    let oid: u32 = ty.oid;
+    let typname: String = ty.typname;
+    let typlen: i16 = ty.typlen;
+    let typdefault: Option<String> = ty.typdefault;
+    assert_eq!(oid, Type::INT4.oid());
+    assert_eq!(typname, "int4");
+    assert_eq!(typlen, 4);
+    assert_eq!(typdefault, None);
+
+    let rows = tokio_postgres::query!(
+        "SELECT i AS \"i!\", i * 2 AS doubled, i::text AS \"label: String\", ARRAY[i] AS \"array!\" \
+         FROM generate_series(1, $1) i",
+        3i32
+    )
+    .fetch_all(&client)
+    .await
+    .unwrap();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[2].i, 3);
+    assert_eq!(rows[2].doubled, Some(6));
+    assert_eq!(rows[2].label.as_deref(), Some("3"));
+    assert_eq!(rows[2].array, [3]);
+
+    let count = tokio_postgres::query!(
+        "SELECT count(*) AS total FROM pg_catalog.pg_type WHERE typname = 'int4'"
+    )
+    .fetch_opt(&client)
+    .await
+    .unwrap()
+    .unwrap();
+    assert_eq!(count.total, Some(1));
+
+    tokio_postgres::query!("SET application_name = 'query_macro'")
+        .execute(&client)
+        .await
+        .unwrap();
+}
+
+#[cfg(feature = "macros")]
+#[tokio::test]
+async fn derive_from_row() {
+    use tokio_postgres::{FromRow, TypedQuery};
+
+    #[derive(FromRow, Debug, PartialEq)]
+    struct Person {
+        id: i32,
+        #[row(name = "full_name")]
+        name: String,
+        #[row(default)]
+        tags: Vec<String>,
+        email: Option<Option<String>>,
+        r#type: Option<String>,
+    }
+
     let client = connect("user=postgres").await;
 
+    let person = TypedQuery::<Person>::new(
+        "SELECT 1 AS id, 'alice' AS full_name, ARRAY['a'] AS tags, NULL::TEXT AS email, 'admin' AS type",
+        vec![],
+    )
+    .fetch_one(&client)
+    .await
+    .unwrap();
+    assert_eq!(
+        person,
+        Person {
+            id: 1,
+            name: "alice".to_string(),
+            tags: vec!["a".to_string()],
+            email: Some(None),
+            r#type: Some("admin".to_string()),
+        }
+    );
+
+    let person = TypedQuery::<Person>::new(
+        "SELECT 2 AS id, 'bob' AS full_name, NULL::TEXT AS type",
+        vec![],
+    )
+    .fetch_one(&client)
+    .await
+    .unwrap();
+    assert_eq!(person.tags, Vec::<String>::new());
+    assert_eq!(person.email, None);
+    assert_eq!(person.r#type, None);
+
+    TypedQuery::<Person>::new("SELECT 3 AS id", vec![])
+        .fetch_one(&client)
+        .await
+        .unwrap_err();
+}
+
+#[tokio::test]
+async fn set_role() {
+    let client = connect("user=postgres").await;
     client
         .batch_execute(
-            "CREATE TEMPORARY TABLE foo (
-                id INTEGER,
-                name TEXT
-            )",
+            "DROP ROLE IF EXISTS \"startup Role\";
+             CREATE ROLE \"startup Role\"",
         )
         .await
         .unwrap();
 
-    let mut stream = stream::iter(
-        vec![
-            Bytes::from_static(b"1\tjim\n"),
-            Bytes::from_static(b"2\tjoe\n"),
-        ]
-        .into_iter()
-        .map(Ok::<_, Error>),
+    let client = connect("user=postgres role='startup Role'").await;
+    let row = client
+        .query_one("SELECT current_user::text, session_user::text", &[])
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, &str>(0), "startup Role");
+    assert_eq!(row.get::<_, &str>(1), "postgres");
+
+    let err = connect_raw("user=postgres role=no_such_role")
+        .await
+        .err()
+        .unwrap();
+    assert_eq!(err.code(), Some(&SqlState::INVALID_PARAMETER_VALUE));
+}
+
+#[tokio::test]
+async fn default_transaction_characteristics() {
+    let mut client = connect(
+        "user=postgres default_transaction_isolation='serializable' default_transaction_read_only=on",
+    )
+    .await;
+    let row = client
+        .query_one(
+            "SELECT current_setting('default_transaction_isolation'), \
+             current_setting('default_transaction_read_only')",
+            &[],
+        )
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, &str>(0), "serializable");
+    assert_eq!(row.get::<_, &str>(1), "on");
+
+    let transaction = client.transaction().await.unwrap();
+    let err = transaction
+        .batch_execute("CREATE TEMPORARY TABLE foo (id INT)")
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::READ_ONLY_SQL_TRANSACTION));
+}
+
+#[tokio::test]
+async fn dropped_query_stream() {
+    let client = connect("user=postgres").await;
+
+    client.batch_execute("BEGIN").await.unwrap();
+    let mut stream = Box::pin(
+        client
+            .query_raw(
+                "SELECT generate_series(1, 100000)",
+                std::iter::empty::<i32>(),
+            )
+            .await
+            .unwrap(),
     );
-    let sink = client.copy_in("COPY foo FROM STDIN").await.unwrap();
-    pin_mut!(sink);
-    sink.send_all(&mut stream).await.unwrap();
-    let rows = sink.finish().await.unwrap();
-    assert_eq!(rows, 2);
+    let row = stream.try_next().await.unwrap().unwrap();
+    assert_eq!(row.get::<_, i32>(0), 1);
+    drop(stream);
+
+    let row = client.query_one("SELECT 1", &[]).await.unwrap();
+    assert_eq!(row.get::<_, i32>(0), 1);
+    client.batch_execute("COMMIT").await.unwrap();
+}
+
+#[tokio::test]
+async fn query_typed() {
+    let client = connect("user=postgres").await;
 
     let rows = client
-        .query("SELECT id, name FROM foo ORDER BY id", &[])
+        .query_typed(
+            "SELECT $1 || $2, $3",
+            &[
+                (&"foo", Type::TEXT),
+                (&"bar", Type::TEXT),
+                (&1i64, Type::INT8),
+            ],
+        )
         .await
         .unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].get::<_, &str>(0), "foobar");
+    assert_eq!(rows[0].get::<_, i64>(1), 1);
 
-    assert_eq!(rows.len(), 2);
-    assert_eq!(rows[0].get::<_, i32>(0), 1);
-    assert_eq!(rows[0].get::<_, &str>(1), "jim");
-    assert_eq!(rows[1].get::<_, i32>(0), 2);
-    assert_eq!(rows[1].get::<_, &str>(1), "joe");
+    // the server can't infer the type of a parameter only passed to a polymorphic function
+    client
+        .query("SELECT pg_typeof($1)::text", &[&"foo"])
+        .await
+        .unwrap_err();
+    let row = client
+        .query_typed("SELECT pg_typeof($1)::text", &[(&"foo", Type::VARCHAR)])
+        .await
+        .unwrap();
+    assert_eq!(row[0].get::<_, &str>(0), "character varying");
+
+    client
+        .query_typed("SELECT $1", &[(&"foo", Type::INT4)])
+        .await
+        .unwrap_err();
 }
 
 #[tokio::test]
-async fn copy_in_large() {
+async fn many_parameters() {
+    let client = connect("user=postgres").await;
+
+    let query = |count: usize| {
+        let placeholders = (1..=count)
+            .map(|i| format!("${}", i))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("SELECT array_length(ARRAY[{}]::INT4[], 1)", placeholders)
+    };
+
+    // parameter counts are unsigned 16 bit integers
+    let values = vec![1i32; 40_000];
+    let params = values
+        .iter()
+        .map(|v| v as &(dyn ToSql + Sync))
+        .collect::<Vec<_>>();
+    let row = client.query_one(&*query(40_000), &params).await.unwrap();
+    assert_eq!(row.get::<_, i32>(0), 40_000);
+
+    let values = vec![1i32; 70_000];
+    let params = values
+        .iter()
+        .map(|v| v as &(dyn ToSql + Sync))
+        .collect::<Vec<_>>();
+    let err = client.query(&*query(70_000), &params).await.unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("too many parameters but at most 65535 can be bound"));
+
+    let statement = client.prepare("SELECT $1::INT4").await.unwrap();
+    let err = client.query(&statement, &params).await.unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("got 70000 parameters but at most 65535 can be bound"));
+
+    let types = vec![Type::INT4; 70_000];
+    assert!(client.prepare_typed(&query(70_000), &types).await.is_err());
+}
+
+#[tokio::test]
+async fn transaction_status() {
     let client = connect("user=postgres").await;
+    assert_eq!(client.transaction_status(), TransactionStatus::Idle);
+
+    client.batch_execute("BEGIN").await.unwrap();
+    assert_eq!(
+        client.transaction_status(),
+        TransactionStatus::InTransaction
+    );
+
+    client
+        .query("SELECT 1 / $1::INT", &[&0i32])
+        .await
+        .unwrap_err();
+    assert_eq!(client.transaction_status(), TransactionStatus::InError);
+
+    client.batch_execute("ROLLBACK").await.unwrap();
+    assert_eq!(client.transaction_status(), TransactionStatus::Idle);
+
+    client
+        .batch_execute("BEGIN; SELECT 1 / 0")
+        .await
+        .unwrap_err();
+    assert_eq!(client.transaction_status(), TransactionStatus::InError);
+}
+
+#[tokio::test]
+async fn empty_query() {
+    let mut client = connect("user=postgres").await;
+
+    for &query in &["", ";", "-- comment"] {
+        assert!(client.query(query, &[]).await.unwrap().is_empty());
+        assert!(client.query_opt(query, &[]).await.unwrap().is_none());
+        assert_eq!(client.execute(query, &[]).await.unwrap(), 0);
+        client.batch_execute(query).await.unwrap();
+        let messages = client.simple_query(query).await.unwrap();
+        assert!(matches!(
+            messages[..],
+            [SimpleQueryMessage::CommandComplete(0)]
+        ));
+
+        let statement = client.prepare(query).await.unwrap();
+        assert!(statement.columns().is_empty());
+        let transaction = client.transaction().await.unwrap();
+        let portal = transaction.bind(&statement, &[]).await.unwrap();
+        assert!(transaction
+            .query_portal(&portal, 10)
+            .await
+            .unwrap()
+            .is_empty());
+        transaction.commit().await.unwrap();
+
+        let data = client
+            .copy_out(query)
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert!(data.is_empty());
+    }
+
+    client.batch_execute("SELECT 1").await.unwrap();
+}
 
+#[tokio::test]
+async fn row_text_values() {
+    let client = connect("user=postgres").await;
     client
         .batch_execute(
-            "CREATE TEMPORARY TABLE foo (
-                id INTEGER,
-                name TEXT
-            )",
+            "SET TIME ZONE 'UTC';
+             CREATE TYPE pg_temp.mood AS ENUM ('sad', 'happy')",
         )
         .await
         .unwrap();
 
-    let a = Bytes::from_static(b"0\tname0\n");
-    let mut b = BytesMut::new();
-    for i in 1..5_000 {
-        writeln!(b, "{0}\tname{0}", i).unwrap();
-    }
-    let mut c = BytesMut::new();
-    for i in 5_000..10_000 {
-        writeln!(c, "{0}\tname{0}", i).unwrap();
+    let exprs = [
+        "true",
+        "42::INT2",
+        "-7::INT8",
+        "1.5::FLOAT8",
+        "'NaN'::FLOAT4",
+        "'123.4500'::NUMERIC",
+        "'hello'::TEXT",
+        "'{\"a\": 1}'::JSONB",
+        "'\\x00ff'::BYTEA",
+        "'a0eebc99-9c0b-4ef8-bb6d-6bb9bd380a11'::UUID",
+        "'10.0.0.1'::INET",
+        "'10.0.0.0/8'::CIDR",
+        "'2024-02-29'::DATE",
+        "'0044-03-15 BC'::DATE",
+        "'13:45:01.25'::TIME",
+        "'1999-12-31 23:59:59.000001'::TIMESTAMP",
+        "'2017-07-14 02:40:00+00'::TIMESTAMPTZ",
+        "'infinity'::TIMESTAMP",
+        "'happy'::pg_temp.mood",
+        "ARRAY[[1, NULL], [3, 4]]",
+        "ARRAY['a b', '', 'NULL', 'x\"y']",
+        "'[0:1]={1,2}'::INT4[]",
+        "'{}'::TEXT[]",
+    ];
+    // the simple query protocol returns values in their text format
+    let query = format!("SELECT {}", exprs.join(", "));
+    let row = client.query_one(&*query, &[]).await.unwrap();
+    let expected = match &client.simple_query(&query).await.unwrap()[0] {
+        SimpleQueryMessage::Row(row) => (0..row.len())
+            .map(|i| row.get(i).map(str::to_string))
+            .collect::<Vec<_>>(),
+        _ => panic!("unexpected message"),
+    };
+    let values = row.into_text_vec();
+    for ((expr, value), expected) in exprs.iter().zip(&values).zip(&expected) {
+        assert_eq!(value, expected, "{}", expr);
     }
-    let mut stream = stream::iter(
-        vec![a, b.freeze(), c.freeze()]
-            .into_iter()
-            .map(Ok::<_, Error>),
+
+    let row = client
+        .query_one(
+            "SELECT 1 AS id, NULL::TEXT AS name, '(1,2)'::POINT AS point",
+            &[],
+        )
+        .await
+        .unwrap();
+    let map = row.into_map();
+    assert_eq!(map.len(), 3);
+    assert_eq!(map["id"].as_deref(), Some("1"));
+    assert_eq!(map["name"], None);
+    assert_eq!(
+        map["point"].as_deref(),
+        Some("\\x3ff00000000000004000000000000000")
     );
+}
 
-    let sink = client.copy_in("COPY foo FROM STDIN").await.unwrap();
-    pin_mut!(sink);
-    sink.send_all(&mut stream).await.unwrap();
-    let rows = sink.finish().await.unwrap();
-    assert_eq!(rows, 10_000);
+#[tokio::test]
+async fn query_fold() {
+    let mut client = connect("user=postgres").await;
+
+    let (count, sum) = client
+        .query_fold(
+            "SELECT i FROM generate_series(1, $1) i",
+            &[&1000i32],
+            (0, 0i64),
+            |(count, sum), row| Ok((count + 1, sum + i64::from(row.try_get::<_, i32>(0)?))),
+        )
+        .await
+        .unwrap();
+    assert_eq!(count, 1000);
+    assert_eq!(sum, 500_500);
+
+    let err = client
+        .query_fold(
+            "SELECT i FROM generate_series(1, 1000) i",
+            &[],
+            String::new(),
+            |acc, row| row.try_get::<_, String>(0).map(|v| acc + &v),
+        )
+        .await
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("error deserializing column 0"),
+        "{}",
+        err
+    );
+
+    // the connection is still usable after an error aborts the fold
+    let transaction = client.transaction().await.unwrap();
+    let first = transaction
+        .query_fold(
+            "SELECT i FROM generate_series(1, 100) i",
+            &[],
+            None,
+            |first, row| Ok(first.or_else(|| row.get::<_, Option<i32>>(0))),
+        )
+        .await
+        .unwrap();
+    assert_eq!(first, Some(1));
 }
 
 #[tokio::test]
-async fn copy_in_error() {
+async fn paginator() {
     let client = connect("user=postgres").await;
 
     client
         .batch_execute(
-            "CREATE TEMPORARY TABLE foo (
-                id INTEGER,
-                name TEXT
-            )",
+            "CREATE TEMPORARY TABLE paginated (id INT8 PRIMARY KEY, even BOOL NOT NULL);
+             INSERT INTO paginated SELECT i, i % 2 = 0 FROM generate_series(1, 20) i;",
         )
         .await
         .unwrap();
 
-    {
-        let sink = client.copy_in("COPY foo FROM STDIN").await.unwrap();
-        pin_mut!(sink);
-        sink.send(Bytes::from_static(b"1\tsteven")).await.unwrap();
+    let query = "SELECT id FROM paginated
+                 WHERE ($1::INT8 IS NULL OR id > $1) AND even = $3
+                 ORDER BY id
+                 LIMIT $2";
+
+    let mut paginator = Paginator::<i64>::new(query, "id").page_size(4).param(true);
+    let mut pages = vec![];
+    while let Some(page) = paginator.next_page(&client).await.unwrap() {
+        pages.push(page.iter().map(|r| r.get(0)).collect::<Vec<i64>>());
     }
+    assert_eq!(
+        pages,
+        vec![vec![2, 4, 6, 8], vec![10, 12, 14, 16], vec![18, 20]]
+    );
+    assert_eq!(paginator.last_key(), Some(&20));
+    assert!(paginator.is_done());
+
+    let rows = Paginator::<i64>::new(query, "id")
+        .page_size(3)
+        .start_after(11)
+        .param(false)
+        .rows(&client)
+        .map_ok(|r| r.get::<_, i64>(0))
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    assert_eq!(rows, vec![13, 15, 17, 19]);
 
-    let rows = client
-        .query("SELECT id, name FROM foo ORDER BY id", &[])
+    let err = Paginator::<i64>::new(query, "missing")
+        .param(true)
+        .rows(&client)
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("missing"), "{}", err);
+}
+
+#[tokio::test]
+async fn bulk_upsert() {
+    let mut client = connect("user=postgres").await;
+
+    client
+        .batch_execute(
+            "CREATE TEMPORARY TABLE upserted (id INT4 PRIMARY KEY, name TEXT NOT NULL DEFAULT '', \"Count\" INT8);
+             INSERT INTO upserted VALUES (1, 'alice', 1), (2, 'bob', 2);",
+        )
         .await
         .unwrap();
-    assert_eq!(rows.len(), 0);
+
+    let transaction = client.transaction().await.unwrap();
+    let rows: &[&[&(dyn ToSql + Sync)]] = &[
+        &[&2i32, &"robert", &20i64],
+        &[&3i32, &"carol", &None::<i64>],
+        &[&3i32, &"caroline", &30i64],
+    ];
+    let upserted = transaction
+        .bulk_upsert("upserted", &["id", "name", "Count"], &["id"], rows)
+        .await
+        .unwrap();
+    assert_eq!(upserted, 2);
+
+    // the helper can be used repeatedly within a transaction
+    let upserted = transaction
+        .bulk_upsert("upserted", &["id", "name"], &["id"], &[&[&1i32, &"bob"]])
+        .await
+        .unwrap();
+    assert_eq!(upserted, 1);
+    let upserted = transaction
+        .bulk_upsert("upserted", &["id"], &["id"], &[&[&1i32], &[&4i32]])
+        .await
+        .unwrap();
+    assert_eq!(upserted, 1);
+    transaction.commit().await.unwrap();
+
+    let rows = client
+        .query("SELECT id, name, \"Count\" FROM upserted ORDER BY id", &[])
+        .await
+        .unwrap()
+        .iter()
+        .map(|r| (r.get(0), r.get(1), r.get(2)))
+        .collect::<Vec<(i32, String, Option<i64>)>>();
+    assert_eq!(
+        rows,
+        vec![
+            (1, "bob".to_string(), Some(1)),
+            (2, "robert".to_string(), Some(20)),
+            (3, "caroline".to_string(), Some(30)),
+            (4, String::new(), None),
+        ]
+    );
 }
 
 #[tokio::test]
-async fn copy_out() {
-    let client = connect("user=postgres").await;
+async fn message_stats() {
+    let (client, connection) = connect_raw("user=postgres").await.unwrap();
+
+    let stats = connection.message_stats();
+    assert_eq!(stats.get(MessageType::Authentication).messages(), 1);
+    assert_eq!(stats.get(MessageType::ReadyForQuery).messages(), 1);
+    assert_eq!(stats.get(MessageType::DataRow).messages(), 0);
+    assert!(stats.get(MessageType::ParameterStatus).messages() > 0);
+    assert_eq!(client.message_stats(), stats);
 
+    tokio::spawn(connection.map(|r| r.unwrap()));
+
+    client
+        .query("SELECT generate_series(1, 10), 'foo'", &[])
+        .await
+        .unwrap();
     client
         .batch_execute(
-            "CREATE TEMPORARY TABLE foo (
-            id SERIAL,
-            name TEXT
-        );
+            "DO $$
+             BEGIN
+                 FOR i IN 1..3 LOOP
+                     RAISE NOTICE 'notice %', i;
+                 END LOOP;
+             END
+             $$",
+        )
+        .await
+        .unwrap();
 
-        INSERT INTO foo (name) VALUES ('jim'), ('joe');",
+    let after = client.message_stats();
+    let rows = after.get(MessageType::DataRow);
+    assert_eq!(rows.messages(), 10);
+    // each row has a 5 byte header, a column count, and two length-prefixed values
+    assert_eq!(rows.bytes(), 10 * (5 + 2 + (4 + 4) + (4 + 3)));
+    assert_eq!(after.get(MessageType::NoticeResponse).messages(), 3);
+    assert_eq!(MessageType::NoticeResponse.to_string(), "NoticeResponse");
+
+    let total = after.total();
+    assert_eq!(
+        total.messages(),
+        after.iter().map(|(_, c)| c.messages()).sum::<u64>()
+    );
+    assert!(total.bytes() > stats.total().bytes());
+}
+
+#[tokio::test]
+async fn read_only_client() {
+    let client = connect("user=postgres").await;
+    client
+        .batch_execute(
+            "CREATE TEMPORARY TABLE foo (id INT, \"update\" TEXT);
+             INSERT INTO foo VALUES (1, 'a'), (2, 'b');",
         )
         .await
         .unwrap();
 
-    let stmt = client.prepare("COPY foo TO STDOUT").await.unwrap();
+    let mut client = ReadOnlyClient::new(client).await.unwrap();
+
+    let rows = client
+        .query(
+            "SELECT id, \"update\" -- delete
+             FROM foo /* insert */ WHERE \"update\" <> 'drop table' ORDER BY id",
+            &[],
+        )
+        .await
+        .unwrap();
+    assert_eq!(rows.len(), 2);
     let data = client
-        .copy_out(&stmt)
+        .copy_out("COPY (SELECT id FROM foo) TO STDOUT")
         .await
         .unwrap()
         .try_fold(BytesMut::new(), |mut buf, chunk| async move {
@@ -689,265 +1416,1941 @@ async fn copy_out() {
         })
         .await
         .unwrap();
-    assert_eq!(&data[..], b"1\tjim\n2\tjoe\n");
+    assert_eq!(&data[..], b"1\n2\n");
+
+    for &query in &[
+        "INSERT INTO foo VALUES (3, 'c')",
+        "  update foo SET id = 3",
+        "WITH deleted AS (DELETE FROM foo RETURNING id) SELECT * FROM deleted",
+        "SELECT 1; DROP TABLE foo",
+        "COPY foo FROM STDIN",
+        "SELECT * FROM foo FOR UPDATE",
+    ] {
+        let err = client.batch_execute(query).await.unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("statement rejected by a read-only client"),
+            "{}",
+            err
+        );
+    }
+    assert!(client.prepare("CREATE TABLE bar (id INT)").await.is_err());
+    assert_eq!(
+        client
+            .query_one("SELECT count(*) FROM foo", &[])
+            .await
+            .unwrap()
+            .get::<_, i64>(0),
+        2
+    );
+
+    // the server rejects writes the client doesn't check
+    let transaction = client.transaction().await.unwrap();
+    let err = transaction
+        .batch_execute("CREATE TABLE read_only_client (id INT)")
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::READ_ONLY_SQL_TRANSACTION));
+    drop(transaction);
+
+    let client = client.into_inner();
+    let err = client
+        .batch_execute("CREATE TABLE read_only_client (id INT)")
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::READ_ONLY_SQL_TRANSACTION));
 }
 
 #[tokio::test]
-async fn notices() {
-    let long_name = "x".repeat(65);
-    let (client, mut connection) =
-        connect_raw(&format!("user=postgres application_name={}", long_name,))
-            .await
-            .unwrap();
+async fn cancel_query_raw() {
+    let client = connect("user=postgres").await;
 
-    let (tx, rx) = mpsc::unbounded();
-    let stream =
-        stream::poll_fn(move |cx| connection.poll_message(cx)).map_err(|e| panic!("{}", e));
-    let connection = stream.forward(tx).map(|r| r.unwrap());
-    tokio::spawn(connection);
+    let socket = TcpStream::connect("127.0.0.1:5433").await.unwrap();
+    let cancel_token = client.cancel_token();
+    let cancel = cancel_token.cancel_query_raw(socket, NoTls);
+    let cancel = time::sleep(Duration::from_millis(100)).then(|()| cancel);
 
-    client
-        .batch_execute("DROP DATABASE IF EXISTS noexistdb")
-        .await
-        .unwrap();
+    let sleep = client.batch_execute("SELECT pg_sleep(100)");
 
-    drop(client);
+    match join!(sleep, cancel) {
+        (Err(ref e), Ok(())) if e.code() == Some(&SqlState::QUERY_CANCELED) => {}
+        t => panic!("unexpected return: {:?}", t),
+    }
+}
 
-    let notices = rx
-        .filter_map(|m| match m {
-            AsyncMessage::Notice(n) => future::ready(Some(n)),
-            _ => future::ready(None),
-        })
-        .collect::<Vec<_>>()
-        .await;
+#[tokio::test]
+async fn transaction_commit() {
+    let mut client = connect("user=postgres").await;
+
+    client
+        .batch_execute(
+            "CREATE TEMPORARY TABLE foo(
+                id SERIAL,
+                name TEXT
+            )",
+        )
+        .await
+        .unwrap();
+
+    let transaction = client.transaction().await.unwrap();
+    transaction
+        .batch_execute("INSERT INTO foo (name) VALUES ('steven')")
+        .await
+        .unwrap();
+    transaction.commit().await.unwrap();
+
+    let stmt = client.prepare("SELECT name FROM foo").await.unwrap();
+    let rows = client.query(&stmt, &[]).await.unwrap();
+
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].get::<_, &str>(0), "steven");
+}
+
+#[tokio::test]
+async fn transaction_rollback() {
+    let mut client = connect("user=postgres").await;
+
+    client
+        .batch_execute(
+            "CREATE TEMPORARY TABLE foo(
+                id SERIAL,
+                name TEXT
+            )",
+        )
+        .await
+        .unwrap();
+
+    let transaction = client.transaction().await.unwrap();
+    transaction
+        .batch_execute("INSERT INTO foo (name) VALUES ('steven')")
+        .await
+        .unwrap();
+    transaction.rollback().await.unwrap();
+
+    let stmt = client.prepare("SELECT name FROM foo").await.unwrap();
+    let rows = client.query(&stmt, &[]).await.unwrap();
+
+    assert_eq!(rows.len(), 0);
+}
+
+#[tokio::test]
+async fn transaction_future_cancellation() {
+    let mut client = connect("user=postgres").await;
+
+    for i in 0.. {
+        let done = {
+            let txn = client.transaction();
+            let fut = Cancellable {
+                fut: txn,
+                polls_left: i,
+            };
+            fut.await
+                .map(|res| res.expect("transaction failed"))
+                .is_some()
+        };
+
+        assert!(!in_transaction(&client).await);
+
+        if done {
+            break;
+        }
+    }
+}
+
+#[tokio::test]
+async fn transaction_commit_future_cancellation() {
+    let mut client = connect("user=postgres").await;
+
+    for i in 0.. {
+        let done = {
+            let txn = client.transaction().await.unwrap();
+            let commit = txn.commit();
+            let fut = Cancellable {
+                fut: commit,
+                polls_left: i,
+            };
+            fut.await
+                .map(|res| res.expect("transaction failed"))
+                .is_some()
+        };
+
+        assert!(!in_transaction(&client).await);
+
+        if done {
+            break;
+        }
+    }
+}
+
+#[tokio::test]
+async fn transaction_rollback_future_cancellation() {
+    let mut client = connect("user=postgres").await;
+
+    for i in 0.. {
+        let done = {
+            let txn = client.transaction().await.unwrap();
+            let rollback = txn.rollback();
+            let fut = Cancellable {
+                fut: rollback,
+                polls_left: i,
+            };
+            fut.await
+                .map(|res| res.expect("transaction failed"))
+                .is_some()
+        };
+
+        assert!(!in_transaction(&client).await);
+
+        if done {
+            break;
+        }
+    }
+}
+
+#[tokio::test]
+async fn transaction_rollback_drop() {
+    let mut client = connect("user=postgres").await;
+
+    client
+        .batch_execute(
+            "CREATE TEMPORARY TABLE foo(
+                id SERIAL,
+                name TEXT
+            )",
+        )
+        .await
+        .unwrap();
+
+    let transaction = client.transaction().await.unwrap();
+    transaction
+        .batch_execute("INSERT INTO foo (name) VALUES ('steven')")
+        .await
+        .unwrap();
+    drop(transaction);
+
+    let stmt = client.prepare("SELECT name FROM foo").await.unwrap();
+    let rows = client.query(&stmt, &[]).await.unwrap();
+
+    assert_eq!(rows.len(), 0);
+}
+
+#[tokio::test]
+async fn transaction_builder() {
+    let mut client = connect("user=postgres").await;
+
+    client
+        .batch_execute(
+            "CREATE TEMPORARY TABLE foo(
+                id SERIAL,
+                name TEXT
+            )",
+        )
+        .await
+        .unwrap();
+
+    let transaction = client
+        .build_transaction()
+        .isolation_level(IsolationLevel::Serializable)
+        .read_only(true)
+        .deferrable(true)
+        .start()
+        .await
+        .unwrap();
+    transaction
+        .batch_execute("INSERT INTO foo (name) VALUES ('steven')")
+        .await
+        .unwrap();
+    transaction.commit().await.unwrap();
+
+    let stmt = client.prepare("SELECT name FROM foo").await.unwrap();
+    let rows = client.query(&stmt, &[]).await.unwrap();
+
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].get::<_, &str>(0), "steven");
+}
+
+#[tokio::test]
+async fn transaction_with_retry() {
+    let mut client = connect("user=postgres").await;
+
+    client
+        .batch_execute(
+            "CREATE TEMPORARY TABLE foo(
+                id SERIAL,
+                name TEXT
+            )",
+        )
+        .await
+        .unwrap();
+
+    let policy = RetryPolicy::new().initial_backoff(Duration::from_millis(1));
+
+    let mut attempts = 0;
+    let id = client
+        .build_transaction()
+        .isolation_level(IsolationLevel::Serializable)
+        .run_with_retry(&policy, |transaction| {
+            attempts += 1;
+            let attempt = attempts;
+            Box::pin(async move {
+                let row = transaction
+                    .query_one("INSERT INTO foo (name) VALUES ('steven') RETURNING id", &[])
+                    .await?;
+                if attempt < 3 {
+                    transaction
+                        .batch_execute(
+                            "DO $$ BEGIN RAISE EXCEPTION USING ERRCODE = 'serialization_failure'; END $$",
+                        )
+                        .await?;
+                }
+                Ok(row.get::<_, i32>(0))
+            })
+        })
+        .await
+        .unwrap();
+    assert_eq!(attempts, 3);
+
+    let rows = client.query("SELECT id FROM foo", &[]).await.unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].get::<_, i32>(0), id);
+
+    let mut attempts = 0;
+    let err = client
+        .transaction_with_retry(&policy.clone().max_retries(1), |transaction| {
+            attempts += 1;
+            Box::pin(async move {
+                transaction
+                    .batch_execute(
+                        "DO $$ BEGIN RAISE EXCEPTION USING ERRCODE = 'deadlock_detected'; END $$",
+                    )
+                    .await
+            })
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::T_R_DEADLOCK_DETECTED));
+    assert_eq!(attempts, 2);
+
+    let mut attempts = 0;
+    let err = client
+        .transaction_with_retry(&policy, |transaction| {
+            attempts += 1;
+            Box::pin(async move { transaction.batch_execute("SELECT 1/0").await })
+        })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::DIVISION_BY_ZERO));
+    assert_eq!(attempts, 1);
+    assert!(!in_transaction(&client).await);
+}
+
+#[tokio::test]
+async fn copy_in() {
+    let client = connect("user=postgres").await;
+
+    client
+        .batch_execute(
+            "CREATE TEMPORARY TABLE foo (
+                id INTEGER,
+                name TEXT
+            )",
+        )
+        .await
+        .unwrap();
+
+    let mut stream = stream::iter(
+        vec![
+            Bytes::from_static(b"1\tjim\n"),
+            Bytes::from_static(b"2\tjoe\n"),
+        ]
+        .into_iter()
+        .map(Ok::<_, Error>),
+    );
+    let sink = client.copy_in("COPY foo FROM STDIN").await.unwrap();
+    pin_mut!(sink);
+    sink.send_all(&mut stream).await.unwrap();
+    let rows = sink.finish().await.unwrap();
+    assert_eq!(rows, 2);
+
+    let rows = client
+        .query("SELECT id, name FROM foo ORDER BY id", &[])
+        .await
+        .unwrap();
+
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].get::<_, i32>(0), 1);
+    assert_eq!(rows[0].get::<_, &str>(1), "jim");
+    assert_eq!(rows[1].get::<_, i32>(0), 2);
+    assert_eq!(rows[1].get::<_, &str>(1), "joe");
+}
+
+#[tokio::test]
+async fn copy_in_large() {
+    let client = connect("user=postgres").await;
+
+    client
+        .batch_execute(
+            "CREATE TEMPORARY TABLE foo (
+                id INTEGER,
+                name TEXT
+            )",
+        )
+        .await
+        .unwrap();
+
+    let a = Bytes::from_static(b"0\tname0\n");
+    let mut b = BytesMut::new();
+    for i in 1..5_000 {
+        writeln!(b, "{0}\tname{0}", i).unwrap();
+    }
+    let mut c = BytesMut::new();
+    for i in 5_000..10_000 {
+        writeln!(c, "{0}\tname{0}", i).unwrap();
+    }
+    let mut stream = stream::iter(
+        vec![a, b.freeze(), c.freeze()]
+            .into_iter()
+            .map(Ok::<_, Error>),
+    );
+
+    let sink = client.copy_in("COPY foo FROM STDIN").await.unwrap();
+    pin_mut!(sink);
+    sink.send_all(&mut stream).await.unwrap();
+    let rows = sink.finish().await.unwrap();
+    assert_eq!(rows, 10_000);
+}
+
+#[tokio::test]
+async fn copy_in_error() {
+    let client = connect("user=postgres").await;
+
+    client
+        .batch_execute(
+            "CREATE TEMPORARY TABLE foo (
+                id INTEGER,
+                name TEXT
+            )",
+        )
+        .await
+        .unwrap();
+
+    {
+        let sink = client.copy_in("COPY foo FROM STDIN").await.unwrap();
+        pin_mut!(sink);
+        sink.send(Bytes::from_static(b"1\tsteven")).await.unwrap();
+    }
+
+    let rows = client
+        .query("SELECT id, name FROM foo ORDER BY id", &[])
+        .await
+        .unwrap();
+    assert_eq!(rows.len(), 0);
+}
+
+#[tokio::test]
+async fn copy_in_error_context() {
+    let client = connect("user=postgres").await;
+
+    client
+        .batch_execute(
+            "CREATE TEMPORARY TABLE foo (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL
+            )",
+        )
+        .await
+        .unwrap();
+
+    async fn copy(client: &Client, data: &[u8]) -> Result<u64, Error> {
+        let sink = client
+            .copy_in("COPY foo FROM STDIN (FORMAT csv, HEADER true)")
+            .await?;
+        pin_mut!(sink);
+        sink.send(Bytes::copy_from_slice(data)).await?;
+        sink.finish().await
+    }
+
+    let mut data = b"id,name\n1,jim\n2,joe\nthree,john\n4,\n5,jane\n".to_vec();
+
+    let err = copy(&client, &data).await.unwrap_err();
+    let copy_error = err.copy_error().unwrap();
+    assert_eq!(copy_error.table(), "foo");
+    assert_eq!(copy_error.line(), 4);
+    assert_eq!(copy_error.column(), Some("id"));
+    assert_eq!(copy_error.value(), Some("three"));
+    let range = copy_error.line_range(&data).unwrap();
+    assert_eq!(&data[range.clone()], b"three,john\n");
+    data.drain(range);
+
+    let err = copy(&client, &data).await.unwrap_err();
+    let copy_error = err.copy_error().unwrap();
+    assert_eq!(err.code(), Some(&SqlState::NOT_NULL_VIOLATION));
+    assert_eq!(copy_error.line(), 4);
+    assert_eq!(copy_error.column(), None);
+    let range = copy_error.line_range(&data).unwrap();
+    assert_eq!(&data[range.clone()], b"4,\n");
+    data.drain(range);
+
+    assert_eq!(copy(&client, &data).await.unwrap(), 3);
+
+    let err = copy(&client, b"id,name\n6,jack\n1,jim\n")
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::UNIQUE_VIOLATION));
+    let copy_error = err.copy_error().unwrap();
+    assert_eq!(copy_error.line(), 3);
+    assert_eq!(copy_error.column(), None);
+
+    let err = client.execute("SELECT 1/0", &[]).await.unwrap_err();
+    assert_eq!(err.copy_error(), None);
+}
+
+#[tokio::test]
+async fn copy_out() {
+    let client = connect("user=postgres").await;
+
+    client
+        .batch_execute(
+            "CREATE TEMPORARY TABLE foo (
+            id SERIAL,
+            name TEXT
+        );
+
+        INSERT INTO foo (name) VALUES ('jim'), ('joe');",
+        )
+        .await
+        .unwrap();
+
+    let stmt = client.prepare("COPY foo TO STDOUT").await.unwrap();
+    let data = client
+        .copy_out(&stmt)
+        .await
+        .unwrap()
+        .try_fold(BytesMut::new(), |mut buf, chunk| async move {
+            buf.extend_from_slice(&chunk);
+            Ok(buf)
+        })
+        .await
+        .unwrap();
+    assert_eq!(&data[..], b"1\tjim\n2\tjoe\n");
+}
+
+#[tokio::test]
+async fn copy_options() {
+    let mut client = connect("user=postgres").await;
+
+    let transaction = client.transaction().await.unwrap();
+    transaction
+        .batch_execute(
+            r#"CREATE SCHEMA "copy ""options""";
+            CREATE TABLE "copy ""options"""."my table" (id INTEGER, "the name" TEXT)"#,
+        )
+        .await
+        .unwrap();
+
+    let options = CopyOptions::new("my table")
+        .schema(r#"copy "options""#)
+        .columns(&["id", "the name"])
+        .format(CopyFormat::Csv)
+        .freeze(true)
+        .header(true)
+        .delimiter(';')
+        .null("<null>")
+        .quote('\'')
+        .escape('\\');
+    assert_eq!(
+        options.copy_in_query(),
+        r#"COPY "copy ""options"""."my table" ("id", "the name") FROM STDIN (FORMAT csv, FREEZE true, HEADER true, DELIMITER ';', NULL '<null>', QUOTE '''', ESCAPE  E'\\')"#
+    );
+
+    let sink = transaction.copy_in(&options.copy_in_query()).await.unwrap();
+    pin_mut!(sink);
+    sink.send(Bytes::from_static(b"id;the name\n1;'a;b'\n2;<null>\n"))
+        .await
+        .unwrap();
+    assert_eq!(sink.finish().await.unwrap(), 2);
+
+    let options = CopyOptions::new("my table")
+        .schema(r#"copy "options""#)
+        .format(CopyFormat::Csv)
+        .force_quote(&["the name"]);
+    let data = transaction
+        .copy_out(&options.copy_out_query())
+        .await
+        .unwrap()
+        .try_fold(BytesMut::new(), |mut buf, chunk| async move {
+            buf.extend_from_slice(&chunk);
+            Ok(buf)
+        })
+        .await
+        .unwrap();
+    assert_eq!(&data[..], b"1,\"a;b\"\n2,\n");
+}
+
+#[tokio::test]
+async fn escaping() {
+    let (client, connection) = connect_raw("user=postgres").await.unwrap();
+    let standard_conforming_strings =
+        connection.parameter("standard_conforming_strings") == Some("on");
+    let connection = connection.map(|r| r.unwrap());
+    tokio::spawn(connection);
+
+    for ident in &["foo", "Foo", "select", "abs", "f\"o o", "1a", "é"] {
+        let quoted: String = client
+            .query_one("SELECT quote_ident($1)", &[ident])
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(escape::quote_ident(ident), quoted);
+
+        let query = format!("SELECT 1 AS {}", escape::escape_identifier(ident));
+        let row = client.query_one(&*query, &[]).await.unwrap();
+        assert_eq!(row.columns()[0].name(), *ident);
+    }
+
+    for literal in &["foo", "f'oo", "f\\oo", "f\\'o\"o"] {
+        let query = format!("SELECT {}", escape::escape_literal(literal));
+        let value: String = client.query_one(&*query, &[]).await.unwrap().get(0);
+        assert_eq!(value, *literal);
+
+        let query = format!(
+            "SELECT '{}'",
+            escape::escape_string(literal, standard_conforming_strings)
+        );
+        let value: String = client.query_one(&*query, &[]).await.unwrap().get(0);
+        assert_eq!(value, *literal);
+    }
+
+    client
+        .batch_execute("SET standard_conforming_strings = off; SET escape_string_warning = off")
+        .await
+        .unwrap();
+    let query = format!("SELECT '{}'", escape::escape_string("f\\'oo", false));
+    let value: String = client.query_one(&*query, &[]).await.unwrap().get(0);
+    assert_eq!(value, "f\\'oo");
+}
+
+#[tokio::test]
+async fn copy_in_notices() {
+    let (client, mut connection) = connect_raw("user=postgres").await.unwrap();
+
+    let noticed = Arc::new(AtomicBool::new(false));
+    let connection = stream::poll_fn(move |cx| connection.poll_message(cx)).for_each({
+        let noticed = noticed.clone();
+        move |m| {
+            if let AsyncMessage::Notice(_) = m.unwrap() {
+                noticed.store(true, Ordering::SeqCst);
+            }
+            future::ready(())
+        }
+    });
+    tokio::spawn(connection);
+
+    client
+        .batch_execute(
+            "CREATE TEMPORARY TABLE foo (id INTEGER);
+             CREATE FUNCTION pg_temp.notice_first() RETURNS trigger AS $$
+             BEGIN
+                 IF NEW.id = 0 THEN
+                     RAISE NOTICE 'first row';
+                 END IF;
+                 RETURN NEW;
+             END;
+             $$ LANGUAGE plpgsql;
+             CREATE TRIGGER foo_notice BEFORE INSERT ON foo
+                 FOR EACH ROW EXECUTE FUNCTION pg_temp.notice_first()",
+        )
+        .await
+        .unwrap();
+
+    // keep sending rows until the notice raised by the first one is delivered
+    const MAX_ROWS: i32 = 1_000_000;
+    let mut stream = stream::iter(0..MAX_ROWS)
+        .take_while({
+            let noticed = noticed.clone();
+            move |_| future::ready(!noticed.load(Ordering::SeqCst))
+        })
+        .map(|i| Ok::<_, Error>(Bytes::from(format!("{}\n", i))));
+    let sink = client.copy_in("COPY foo FROM STDIN").await.unwrap();
+    pin_mut!(sink);
+    sink.send_all(&mut stream).await.unwrap();
+    let rows = sink.finish().await.unwrap();
+
+    assert!(noticed.load(Ordering::SeqCst));
+    assert!(rows < MAX_ROWS as u64);
+}
+
+#[tokio::test]
+async fn notices() {
+    let long_name = "x".repeat(65);
+    let (client, mut connection) =
+        connect_raw(&format!("user=postgres application_name={}", long_name,))
+            .await
+            .unwrap();
+
+    let (tx, rx) = mpsc::unbounded();
+    let stream =
+        stream::poll_fn(move |cx| connection.poll_message(cx)).map_err(|e| panic!("{}", e));
+    let connection = stream.forward(tx).map(|r| r.unwrap());
+    tokio::spawn(connection);
+
+    client
+        .batch_execute("DROP DATABASE IF EXISTS noexistdb")
+        .await
+        .unwrap();
+
+    drop(client);
+
+    let notices = rx
+        .filter_map(|m| match m {
+            AsyncMessage::Notice(n) => future::ready(Some(n)),
+            _ => future::ready(None),
+        })
+        .collect::<Vec<_>>()
+        .await;
     assert_eq!(notices.len(), 2);
     assert_eq!(
-        notices[0].message(),
-        "identifier \"xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx\" \
-         will be truncated to \"xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx\""
+        notices[0].message(),
+        "identifier \"xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx\" \
+         will be truncated to \"xxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx\""
+    );
+    assert_eq!(
+        notices[1].message(),
+        "database \"noexistdb\" does not exist, skipping"
+    );
+}
+
+#[tokio::test]
+async fn notifications() {
+    let (client, mut connection) = connect_raw("user=postgres").await.unwrap();
+
+    let (tx, rx) = mpsc::unbounded();
+    let stream =
+        stream::poll_fn(move |cx| connection.poll_message(cx)).map_err(|e| panic!("{}", e));
+    let connection = stream.forward(tx).map(|r| r.unwrap());
+    tokio::spawn(connection);
+
+    client
+        .batch_execute(
+            "LISTEN test_notifications;
+             NOTIFY test_notifications, 'hello';
+             NOTIFY test_notifications, 'world';",
+        )
+        .await
+        .unwrap();
+
+    drop(client);
+
+    let notifications = rx
+        .filter_map(|m| match m {
+            AsyncMessage::Notification(n) => future::ready(Some(n)),
+            _ => future::ready(None),
+        })
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(notifications.len(), 2);
+    assert_eq!(notifications[0].channel(), "test_notifications");
+    assert_eq!(notifications[0].payload(), "hello");
+    assert_eq!(notifications[1].channel(), "test_notifications");
+    assert_eq!(notifications[1].payload(), "world");
+}
+
+#[tokio::test]
+async fn query_portal() {
+    let mut client = connect("user=postgres").await;
+
+    client
+        .batch_execute(
+            "CREATE TEMPORARY TABLE foo (
+                id SERIAL,
+                name TEXT
+            );
+
+            INSERT INTO foo (name) VALUES ('alice'), ('bob'), ('charlie');",
+        )
+        .await
+        .unwrap();
+
+    let stmt = client
+        .prepare("SELECT id, name FROM foo ORDER BY id")
+        .await
+        .unwrap();
+
+    let transaction = client.transaction().await.unwrap();
+
+    let portal = transaction.bind(&stmt, &[]).await.unwrap();
+    let f1 = transaction.query_portal(&portal, 2);
+    let f2 = transaction.query_portal(&portal, 2);
+    let f3 = transaction.query_portal(&portal, 2);
+
+    let (r1, r2, r3) = try_join!(f1, f2, f3).unwrap();
+
+    assert_eq!(r1.len(), 2);
+    assert_eq!(r1[0].get::<_, i32>(0), 1);
+    assert_eq!(r1[0].get::<_, &str>(1), "alice");
+    assert_eq!(r1[1].get::<_, i32>(0), 2);
+    assert_eq!(r1[1].get::<_, &str>(1), "bob");
+
+    assert_eq!(r2.len(), 1);
+    assert_eq!(r2[0].get::<_, i32>(0), 3);
+    assert_eq!(r2[0].get::<_, &str>(1), "charlie");
+
+    assert_eq!(r3.len(), 0);
+}
+
+#[tokio::test]
+async fn query_prefetch() {
+    let mut client = connect("user=postgres").await;
+    let transaction = client.transaction().await.unwrap();
+
+    for &(count, prefetch) in &[(10, 3), (9, 3), (5, 0), (0, 2)] {
+        let stream = transaction
+            .query_prefetch("SELECT generate_series(1, $1)", &[&count], prefetch)
+            .await
+            .unwrap();
+        pin_mut!(stream);
+
+        let mut ids = vec![];
+        while let Some(row) = stream.try_next().await.unwrap() {
+            ids.push(row.get::<_, i32>(0));
+            // other queries can run while the stream is partially consumed
+            if ids.len() == 2 {
+                transaction.query_one("SELECT 1", &[]).await.unwrap();
+            }
+        }
+        assert_eq!(ids, (1..=count).collect::<Vec<_>>());
+        assert!(stream.command_tag().is_some());
+    }
+
+    let stream = transaction
+        .query_prefetch("SELECT 1 / (3 - generate_series(1, 5))", &[] as &[i32], 1)
+        .await
+        .unwrap();
+    let err = stream.try_collect::<Vec<_>>().await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::DIVISION_BY_ZERO));
+}
+
+#[tokio::test]
+async fn require_channel_binding() {
+    connect_raw("user=postgres channel_binding=require")
+        .await
+        .err()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn prefer_channel_binding() {
+    connect("user=postgres channel_binding=prefer").await;
+}
+
+#[tokio::test]
+async fn disable_channel_binding() {
+    connect("user=postgres channel_binding=disable").await;
+}
+
+#[tokio::test]
+async fn check_send() {
+    fn is_send<T: Send>(_: &T) {}
+
+    let f = connect("user=postgres");
+    is_send(&f);
+    let mut client = f.await;
+
+    let f = client.prepare("SELECT $1::TEXT");
+    is_send(&f);
+    let stmt = f.await.unwrap();
+
+    let f = client.query(&stmt, &[&"hello"]);
+    is_send(&f);
+    drop(f);
+
+    let f = client.execute(&stmt, &[&"hello"]);
+    is_send(&f);
+    drop(f);
+
+    let f = client.transaction();
+    is_send(&f);
+    let trans = f.await.unwrap();
+
+    let f = trans.query(&stmt, &[&"hello"]);
+    is_send(&f);
+    drop(f);
+
+    let f = trans.execute(&stmt, &[&"hello"]);
+    is_send(&f);
+    drop(f);
+}
+
+#[tokio::test]
+async fn query_one() {
+    let client = connect("user=postgres").await;
+
+    client
+        .batch_execute(
+            "
+                CREATE TEMPORARY TABLE foo (
+                    name TEXT
+                );
+                INSERT INTO foo (name) VALUES ('alice'), ('bob'), ('carol');
+            ",
+        )
+        .await
+        .unwrap();
+
+    client
+        .query_one("SELECT * FROM foo WHERE name = 'dave'", &[])
+        .await
+        .err()
+        .unwrap();
+    client
+        .query_one("SELECT * FROM foo WHERE name = 'alice'", &[])
+        .await
+        .unwrap();
+    client
+        .query_one("SELECT * FROM foo", &[])
+        .await
+        .err()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn get_raw() {
+    let client = connect("user=postgres").await;
+
+    let row = client
+        .query_one(
+            "SELECT 'foo'::BYTEA AS a, NULL::BYTEA AS b, 1::INT4 AS c",
+            &[],
+        )
+        .await
+        .unwrap();
+
+    let a = row.get_raw("a");
+    assert_eq!(a, Some(Bytes::from_static(b"foo")));
+    assert_eq!(row.get_raw(1), None);
+    assert_eq!(row.get_raw("c"), Some(Bytes::from_static(&[0, 0, 0, 1])));
+    row.try_get_raw("d").err().unwrap();
+
+    drop(row);
+    assert_eq!(a.unwrap(), "foo");
+}
+
+#[tokio::test]
+async fn query_opt() {
+    let client = connect("user=postgres").await;
+
+    client
+        .batch_execute(
+            "
+                CREATE TEMPORARY TABLE foo (
+                    name TEXT
+                );
+                INSERT INTO foo (name) VALUES ('alice'), ('bob'), ('carol');
+            ",
+        )
+        .await
+        .unwrap();
+
+    assert!(client
+        .query_opt("SELECT * FROM foo WHERE name = 'dave'", &[])
+        .await
+        .unwrap()
+        .is_none());
+    client
+        .query_opt("SELECT * FROM foo WHERE name = 'alice'", &[])
+        .await
+        .unwrap()
+        .unwrap();
+    client
+        .query_one("SELECT * FROM foo", &[])
+        .await
+        .err()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn deferred_constraint() {
+    let client = connect("user=postgres").await;
+
+    client
+        .batch_execute(
+            "
+            CREATE TEMPORARY TABLE t (
+                i INT,
+                UNIQUE (i) DEFERRABLE INITIALLY DEFERRED
+            );
+        ",
+        )
+        .await
+        .unwrap();
+
+    client
+        .execute("INSERT INTO t (i) VALUES (1)", &[])
+        .await
+        .unwrap();
+    client
+        .execute("INSERT INTO t (i) VALUES (1)", &[])
+        .await
+        .unwrap_err();
+}
+
+#[tokio::test]
+async fn query_in_errors() {
+    let client = connect("user=postgres").await;
+
+    let err = client.execute("SELECT 1/0", &[]).await.unwrap_err();
+    assert_eq!(err.query(), None);
+
+    client.set_query_in_errors(true);
+
+    let err = client.execute("SELECT 1/0", &[]).await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::DIVISION_BY_ZERO));
+    assert_eq!(err.query(), Some("SELECT 1/0"));
+    assert_eq!(err.params(), None);
+    assert!(err.to_string().contains("\nQUERY: SELECT 1/0"));
+
+    let err = client.query("SELEC 1", &[]).await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::SYNTAX_ERROR));
+    assert_eq!(err.query(), Some("SELEC 1"));
+
+    client.set_error_param_renderer(|ty, value| {
+        if *ty == Type::TEXT {
+            "<redacted>".to_string()
+        } else {
+            format!("{:?}", value)
+        }
+    });
+
+    let err = client
+        .query("SELECT $1::INT / $2, $3::TEXT", &[&1i32, &0i32, &"secret"])
+        .await
+        .unwrap_err();
+    assert_eq!(err.query(), Some("SELECT $1::INT / $2, $3::TEXT"));
+    assert_eq!(err.params(), Some("[1, 0, <redacted>]"));
+}
+
+#[tokio::test]
+async fn two_phase_commit() {
+    let mut client = connect("user=postgres").await;
+
+    client
+        .batch_execute(
+            "
+            DROP TABLE IF EXISTS two_phase_commit;
+            CREATE TABLE two_phase_commit (name TEXT);
+            ",
+        )
+        .await
+        .unwrap();
+
+    let transaction = client.transaction().await.unwrap();
+    transaction
+        .execute("INSERT INTO two_phase_commit (name) VALUES ('steven')", &[])
+        .await
+        .unwrap();
+    transaction
+        .prepare_two_phase("two_phase_commit 'a'")
+        .await
+        .unwrap();
+
+    let transaction = client.transaction().await.unwrap();
+    transaction
+        .execute("INSERT INTO two_phase_commit (name) VALUES ('joe')", &[])
+        .await
+        .unwrap();
+    transaction
+        .prepare_two_phase("two_phase_commit \\b")
+        .await
+        .unwrap();
+
+    let prepared = client.prepared_transactions().await.unwrap();
+    let gids = prepared
+        .iter()
+        .map(|p| p.gid())
+        .filter(|gid| gid.starts_with("two_phase_commit"))
+        .collect::<Vec<_>>();
+    assert_eq!(gids, ["two_phase_commit 'a'", "two_phase_commit \\b"]);
+    let transaction = prepared
+        .iter()
+        .find(|p| p.gid() == "two_phase_commit 'a'")
+        .unwrap();
+    assert_eq!(transaction.database(), "postgres");
+    assert_eq!(transaction.owner(), "postgres");
+
+    let rows = client
+        .query("SELECT name FROM two_phase_commit", &[])
+        .await
+        .unwrap();
+    assert_eq!(rows.len(), 0);
+
+    client
+        .commit_prepared("two_phase_commit 'a'")
+        .await
+        .unwrap();
+    client
+        .rollback_prepared("two_phase_commit \\b")
+        .await
+        .unwrap();
+
+    let rows = client
+        .query("SELECT name FROM two_phase_commit", &[])
+        .await
+        .unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].get::<_, &str>(0), "steven");
+
+    client
+        .batch_execute("DROP TABLE two_phase_commit")
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn advisory_locks() {
+    let mut client1 = connect("user=postgres").await;
+    let client2 = connect("user=postgres").await;
+
+    let guard = client1.advisory_lock(0x7e57_0001).await.unwrap();
+    assert_eq!(guard.key(), AdvisoryLockKey::Single(0x7e57_0001));
+    assert!(client2
+        .try_advisory_lock(0x7e57_0001)
+        .await
+        .unwrap()
+        .is_none());
+    drop(guard);
+    client1.batch_execute("").await.unwrap();
+
+    let guard = client2
+        .try_advisory_lock(0x7e57_0001)
+        .await
+        .unwrap()
+        .unwrap();
+    guard.unlock().await.unwrap();
+    assert!(!client2.advisory_unlock(0x7e57_0001).await.unwrap());
+
+    let transaction = client1.transaction().await.unwrap();
+    transaction.advisory_xact_lock((1, 2)).await.unwrap();
+    assert!(client2.try_advisory_lock((1, 2)).await.unwrap().is_none());
+    transaction.commit().await.unwrap();
+
+    let transaction = client1.transaction().await.unwrap();
+    assert!(transaction.try_advisory_xact_lock((1, 2)).await.unwrap());
+    transaction.rollback().await.unwrap();
+    assert!(client2.try_advisory_lock((1, 2)).await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn wal_lsns() {
+    let client = connect("user=postgres").await;
+
+    client
+        .batch_execute("CREATE TEMPORARY TABLE foo (id INT); INSERT INTO foo VALUES (1)")
+        .await
+        .unwrap();
+    let lsn = client.current_wal_insert_lsn().await.unwrap();
+    assert!(u64::from(lsn) > 0);
+
+    // the test server is a primary, so there's nothing to wait for
+    assert_eq!(client.last_wal_replay_lsn().await.unwrap(), None);
+    client
+        .wait_for_lsn(lsn, Duration::from_secs(1))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn command_tags() {
+    let client = connect("user=postgres").await;
+
+    let tag = client
+        .execute_with_tag("CREATE TEMPORARY TABLE foo (id INT)", &[])
+        .await
+        .unwrap();
+    assert_eq!(tag.kind(), CommandKind::Other);
+    assert_eq!(tag.as_str(), "CREATE TABLE");
+    assert_eq!(tag.rows(), 0);
+
+    let tag = client
+        .execute_with_tag("INSERT INTO foo VALUES (1), (2)", &[])
+        .await
+        .unwrap();
+    assert_eq!(tag.kind(), CommandKind::Insert);
+    assert_eq!(tag.rows(), 2);
+    assert_eq!(tag.oid(), Some(0));
+
+    let tag = client
+        .execute_with_tag("UPDATE foo SET id = id + 1 WHERE id = $1", &[&1i32])
+        .await
+        .unwrap();
+    assert_eq!(tag.kind(), CommandKind::Update);
+    assert_eq!(tag.rows(), 1);
+    assert_eq!(tag.oid(), None);
+
+    let stream = client
+        .query_raw("SELECT id FROM foo", Vec::<i32>::new())
+        .await
+        .unwrap();
+    pin_mut!(stream);
+    assert!(stream.command_tag().is_none());
+    while stream.try_next().await.unwrap().is_some() {}
+    let tag = stream.command_tag().unwrap();
+    assert_eq!(tag.kind(), CommandKind::Select);
+    assert_eq!(tag.rows(), 2);
+
+    let tag = CommandTag::parse("DELETE 3");
+    assert_eq!(tag.kind(), CommandKind::Delete);
+    assert_eq!(tag.rows(), 3);
+    assert_eq!(tag.to_string(), "DELETE 3");
+}
+
+#[tokio::test]
+async fn parameter_validation() {
+    let client = connect("user=postgres").await;
+
+    let stmt = client
+        .prepare("SELECT $1::INT, $2::TEXT, $3::INT")
+        .await
+        .unwrap();
+
+    let err = client.query(&stmt, &[&1i32]).await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid query parameters: expected 3 parameters but got 1"
+    );
+
+    let err = client.query(&stmt, &[&"a", &"b", &"c"]).await.unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid query parameters: parameter 0: cannot convert between the Rust type `&str` and the Postgres type \
+         `int4`; parameter 2: cannot convert between the Rust type `&str` and the Postgres type `int4`"
+    );
+
+    // a single mismatch is reported as a serialization error
+    let err = client.query(&stmt, &[&"a", &"b", &3i32]).await.unwrap_err();
+    assert!(err.source().unwrap().is::<WrongType>());
+
+    // the connection is still usable
+    let row = client
+        .query_one(&stmt, &[&1i32, &"b", &3i32])
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, i32>(2), 3);
+}
+
+#[tokio::test]
+async fn named_params() {
+    let client = connect("user=postgres").await;
+
+    let query = NamedQuery::new("SELECT :id::INT + :offset, ':id', \":id\", :id || 'x'");
+    assert_eq!(
+        query.sql(),
+        "SELECT $1::INT + $2, ':id', \":id\", $1 || 'x'"
+    );
+    assert_eq!(query.names(), ["id", "offset"]);
+
+    client
+        .batch_execute("CREATE TEMPORARY TABLE foo (id INT, name TEXT)")
+        .await
+        .unwrap();
+    let inserted = client
+        .execute_named(
+            "INSERT INTO foo (id, name) VALUES (:id, :name), (:id + 1, :name)",
+            &[("name", &"bob" as _), ("id", &1i32 as _)],
+        )
+        .await
+        .unwrap();
+    assert_eq!(inserted, 2);
+
+    let mut params = HashMap::new();
+    params.insert("min", 2i32);
+    let rows = client
+        .query_named("SELECT id FROM foo WHERE id >= :min", &params)
+        .await
+        .unwrap();
+    assert_eq!(rows.len(), 1);
+    assert_eq!(rows[0].get::<_, i32>(0), 2);
+
+    let err = client
+        .query_named("SELECT id FROM foo WHERE id >= :max", &params)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "error binding parameter `:min`: parameter is not used by the statement"
+    );
+
+    let err = client
+        .query_named("SELECT :min, :max", &params)
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "error binding parameter `:max`: no value provided"
+    );
+}
+
+#[tokio::test]
+async fn query_builder() {
+    let client = connect("user=postgres").await;
+
+    client
+        .batch_execute(
+            "CREATE TEMPORARY TABLE foo (id INT, name TEXT);
+            INSERT INTO foo VALUES (1, 'a'), (2, 'b'), (3, 'c'), (4, '$1')",
+        )
+        .await
+        .unwrap();
+
+    let ids = vec![1i32, 2, 3, 4];
+    let names = vec!["a", "c", "$1"];
+    let min = 1i32;
+    let mut query = QueryBuilder::new("SELECT id FROM foo WHERE id = ");
+    query
+        .push_any(&ids)
+        .push(" AND id > ")
+        .push_param(&min)
+        .push(" AND (name = ")
+        .push_any(&names)
+        .push_fragment(" OR name = '$1' OR name = $1)", &[&"b"])
+        .unwrap()
+        .push(" ORDER BY id");
+    assert_eq!(
+        query.sql(),
+        "SELECT id FROM foo WHERE id = ANY($1) AND id > $2 AND (name = ANY($3) OR name = '$1' OR name = $4) \
+         ORDER BY id"
     );
+
+    let rows = client.query(query.sql(), &query.params()).await.unwrap();
+    let ids = rows.iter().map(|r| r.get(0)).collect::<Vec<i32>>();
+    assert_eq!(ids, [2, 3, 4]);
+
+    let sql = query.sql().to_string();
+    query.push_fragment(" AND id = $2", &[&min]).unwrap_err();
+    query
+        .push_fragment(" AND id = $99999999999999999999", &[&min])
+        .unwrap_err();
+    assert_eq!(query.sql(), sql);
+    assert_eq!(query.params().len(), 4);
+
+    let empty: Vec<i32> = vec![];
+    let mut query = QueryBuilder::new("SELECT id FROM foo WHERE id = ");
+    query.push_any(&empty);
+    let rows = client.query(query.sql(), &query.params()).await.unwrap();
+    assert!(rows.is_empty());
+}
+
+#[tokio::test]
+async fn query_cache() {
+    let client = connect("user=postgres").await;
+
+    client
+        .batch_execute(
+            "CREATE TEMPORARY TABLE foo (id INT, name TEXT); INSERT INTO foo VALUES (1, 'a')",
+        )
+        .await
+        .unwrap();
+
+    // without a cache the statement is always executed
+    let query = "SELECT name FROM foo WHERE id = $1";
+    let rows = client
+        .query_cached(query, &[&1i32], Duration::from_secs(60))
+        .await
+        .unwrap();
+    assert_eq!(rows[0].get::<_, &str>(0), "a");
+
+    let cache = Arc::new(MemoryQueryCache::new());
+    client.set_query_cache(cache.clone());
+
+    let statement = client.prepare(query).await.unwrap();
+    let rows = client
+        .query_cached(&statement, &[&1i32], Duration::from_secs(60))
+        .await
+        .unwrap();
+    assert_eq!(rows[0].get::<_, &str>(0), "a");
+
+    client
+        .batch_execute("UPDATE foo SET name = 'b'")
+        .await
+        .unwrap();
+
+    // the raw query produces the same key as the prepared statement
+    let rows = client
+        .query_cached(query, &[&1i32], Duration::from_secs(60))
+        .await
+        .unwrap();
+    assert_eq!(rows[0].get::<_, &str>(0), "a");
+
+    // different parameters are cached separately
+    let rows = client
+        .query_cached(&statement, &[&2i32], Duration::from_secs(60))
+        .await
+        .unwrap();
+    assert!(rows.is_empty());
+
+    cache.clear();
+    let rows = client
+        .query_cached(&statement, &[&1i32], Duration::from_secs(0))
+        .await
+        .unwrap();
+    assert_eq!(rows[0].get::<_, &str>(0), "b");
+
+    // expired entries are executed again
+    client
+        .batch_execute("UPDATE foo SET name = 'c'")
+        .await
+        .unwrap();
+    let rows = client
+        .query_cached(&statement, &[&1i32], Duration::from_secs(60))
+        .await
+        .unwrap();
+    assert_eq!(rows[0].get::<_, &str>(0), "c");
+}
+
+#[tokio::test]
+async fn query_logger() {
+    let client = connect("user=postgres").await;
+
+    let events = Arc::new(Mutex::new(vec![]));
+    client.set_query_logger({
+        let events = events.clone();
+        move |event: &QueryEvent<'_>| {
+            events.lock().unwrap().push((
+                event.query().to_string(),
+                event.param_count(),
+                event.rows_affected(),
+                event.error().and_then(|e| e.code()).cloned(),
+            ))
+        }
+    });
+
+    client
+        .batch_execute("CREATE TEMPORARY TABLE foo (id INT); INSERT INTO foo VALUES (1), (2)")
+        .await
+        .unwrap();
+    client
+        .execute("INSERT INTO foo VALUES ($1)", &[&3i32])
+        .await
+        .unwrap();
+    let rows = client
+        .query("SELECT id FROM foo WHERE id > $1", &[&1i32])
+        .await
+        .unwrap();
+    assert_eq!(rows.len(), 2);
+    client.simple_query("SELECT * FROM foo").await.unwrap();
+    client.execute("SELECT 1 / 0", &[]).await.unwrap_err();
+
+    client.clear_query_logger();
+    client.batch_execute("SELECT 1").await.unwrap();
+
+    let events = events.lock().unwrap();
     assert_eq!(
-        notices[1].message(),
-        "database \"noexistdb\" does not exist, skipping"
+        *events,
+        [
+            (
+                "CREATE TEMPORARY TABLE foo (id INT); INSERT INTO foo VALUES (1), (2)".to_string(),
+                0,
+                Some(2),
+                None
+            ),
+            ("INSERT INTO foo VALUES ($1)".to_string(), 1, Some(1), None),
+            (
+                "SELECT id FROM foo WHERE id > $1".to_string(),
+                1,
+                Some(2),
+                None
+            ),
+            ("SELECT * FROM foo".to_string(), 0, Some(3), None),
+            (
+                "SELECT 1 / 0".to_string(),
+                0,
+                None,
+                Some(SqlState::DIVISION_BY_ZERO)
+            ),
+        ]
     );
 }
 
 #[tokio::test]
-async fn notifications() {
-    let (client, mut connection) = connect_raw("user=postgres").await.unwrap();
+async fn log_param_renderer() {
+    let client = connect("user=postgres").await;
 
-    let (tx, rx) = mpsc::unbounded();
-    let stream =
-        stream::poll_fn(move |cx| connection.poll_message(cx)).map_err(|e| panic!("{}", e));
-    let connection = stream.forward(tx).map(|r| r.unwrap());
-    tokio::spawn(connection);
+    let events = Arc::new(Mutex::new(vec![]));
+    client.set_query_logger({
+        let events = events.clone();
+        move |event: &QueryEvent<'_>| {
+            events
+                .lock()
+                .unwrap()
+                .push(event.params().map(|p| p.to_string()))
+        }
+    });
 
     client
-        .batch_execute(
-            "LISTEN test_notifications;
-             NOTIFY test_notifications, 'hello';
-             NOTIFY test_notifications, 'world';",
+        .execute(
+            "SELECT $1::INT, $2::BYTEA, $3::FLOAT8",
+            &[&1i32, &&b"\x01\xff"[..], &f64::NAN],
         )
         .await
         .unwrap();
 
-    drop(client);
-
-    let notifications = rx
-        .filter_map(|m| match m {
-            AsyncMessage::Notification(n) => future::ready(Some(n)),
-            _ => future::ready(None),
-        })
-        .collect::<Vec<_>>()
-        .await;
-    assert_eq!(notifications.len(), 2);
-    assert_eq!(notifications[0].channel(), "test_notifications");
-    assert_eq!(notifications[0].payload(), "hello");
-    assert_eq!(notifications[1].channel(), "test_notifications");
-    assert_eq!(notifications[1].payload(), "world");
-}
-
-#[tokio::test]
-async fn query_portal() {
-    let mut client = connect("user=postgres").await;
-
+    client.set_log_param_renderer(|ty, value| {
+        if *ty == Type::TEXT {
+            "<redacted>".to_string()
+        } else {
+            render_param(ty, value)
+        }
+    });
     client
-        .batch_execute(
-            "CREATE TEMPORARY TABLE foo (
-                id SERIAL,
-                name TEXT
-            );
-
-            INSERT INTO foo (name) VALUES ('alice'), ('bob'), ('charlie');",
+        .execute(
+            "SELECT $1::INT, $2::TEXT, $3::INT8, $4::FLOAT8",
+            &[&1i32, &"secret", &None::<i64>, &f64::NAN],
         )
         .await
         .unwrap();
-
-    let stmt = client
-        .prepare("SELECT id, name FROM foo ORDER BY id")
+    client
+        .query(
+            "SELECT $1::BYTEA, $2::VARCHAR",
+            &[&&b"\x01\xff"[..], &"it's"],
+        )
         .await
         .unwrap();
 
-    let transaction = client.transaction().await.unwrap();
+    client.clear_log_param_renderer();
+    client.execute("SELECT $1::INT", &[&1i32]).await.unwrap();
 
-    let portal = transaction.bind(&stmt, &[]).await.unwrap();
-    let f1 = transaction.query_portal(&portal, 2);
-    let f2 = transaction.query_portal(&portal, 2);
-    let f3 = transaction.query_portal(&portal, 2);
+    let events = events.lock().unwrap();
+    assert_eq!(
+        *events,
+        [
+            None,
+            Some("[1, <redacted>, NULL, 'NaN']".to_string()),
+            Some("['\\x01ff', 'it''s']".to_string()),
+            None,
+        ]
+    );
+}
 
-    let (r1, r2, r3) = try_join!(f1, f2, f3).unwrap();
+#[derive(Default)]
+struct TestMetrics {
+    handshakes: Mutex<u32>,
+    queries: Mutex<Vec<Option<u64>>>,
+    bytes_written: Mutex<usize>,
+    bytes_read: Mutex<usize>,
+    max_queue_depth: Mutex<usize>,
+}
 
-    assert_eq!(r1.len(), 2);
-    assert_eq!(r1[0].get::<_, i32>(0), 1);
-    assert_eq!(r1[0].get::<_, &str>(1), "alice");
-    assert_eq!(r1[1].get::<_, i32>(0), 2);
-    assert_eq!(r1[1].get::<_, &str>(1), "bob");
+impl Metrics for TestMetrics {
+    fn handshake_completed(&self, _: Duration) {
+        *self.handshakes.lock().unwrap() += 1;
+    }
 
-    assert_eq!(r2.len(), 1);
-    assert_eq!(r2[0].get::<_, i32>(0), 3);
-    assert_eq!(r2[0].get::<_, &str>(1), "charlie");
+    fn query_completed(&self, _: Duration, rows: u64) {
+        self.queries.lock().unwrap().push(Some(rows));
+    }
 
-    assert_eq!(r3.len(), 0);
+    fn query_failed(&self, _: Duration) {
+        self.queries.lock().unwrap().push(None);
+    }
+
+    fn bytes_written(&self, bytes: usize) {
+        *self.bytes_written.lock().unwrap() += bytes;
+    }
+
+    fn bytes_read(&self, bytes: usize) {
+        *self.bytes_read.lock().unwrap() += bytes;
+    }
+
+    fn queue_depth(&self, depth: usize) {
+        let mut max = self.max_queue_depth.lock().unwrap();
+        *max = (*max).max(depth);
+    }
 }
 
 #[tokio::test]
-async fn require_channel_binding() {
-    connect_raw("user=postgres channel_binding=require")
+async fn metrics() {
+    let metrics = Arc::new(TestMetrics::default());
+
+    let socket = TcpStream::connect("127.0.0.1:5433").await.unwrap();
+    let mut config = "user=postgres".parse::<Config>().unwrap();
+    config.metrics(metrics.clone());
+    let (client, connection) = config.connect_raw(socket, NoTls).await.unwrap();
+    assert_eq!(*metrics.handshakes.lock().unwrap(), 1);
+
+    // requests queue up until the connection is polled
+    let a = client.simple_query("SELECT 1");
+    let b = client.simple_query("SELECT 2");
+    pin_mut!(a, b);
+    assert!(futures::poll!(a.as_mut()).is_pending());
+    assert!(futures::poll!(b.as_mut()).is_pending());
+    assert_eq!(*metrics.max_queue_depth.lock().unwrap(), 2);
+
+    let connection = connection.map(|r| r.unwrap());
+    tokio::spawn(connection);
+
+    a.await.unwrap();
+    b.await.unwrap();
+    client
+        .query("SELECT * FROM generate_series(1, 3)", &[])
         .await
-        .err()
         .unwrap();
-}
+    client.execute("SELECT 1 / 0", &[]).await.unwrap_err();
 
-#[tokio::test]
-async fn prefer_channel_binding() {
-    connect("user=postgres channel_binding=prefer").await;
+    assert_eq!(
+        *metrics.queries.lock().unwrap(),
+        [Some(1), Some(1), Some(3), None]
+    );
+    assert!(*metrics.bytes_written.lock().unwrap() > 0);
+    assert!(*metrics.bytes_read.lock().unwrap() > 0);
 }
 
 #[tokio::test]
-async fn disable_channel_binding() {
-    connect("user=postgres channel_binding=disable").await;
+async fn request_queue_capacity() {
+    let metrics = Arc::new(TestMetrics::default());
+
+    let socket = TcpStream::connect("127.0.0.1:5433").await.unwrap();
+    let mut config = "user=postgres".parse::<Config>().unwrap();
+    config.metrics(metrics.clone()).request_queue_capacity(1);
+    let (client, connection) = config.connect_raw(socket, NoTls).await.unwrap();
+
+    // the second request waits for the first to be picked up by the connection
+    let a = client.simple_query("SELECT 1");
+    let b = client.simple_query("SELECT 2");
+    pin_mut!(a, b);
+    assert!(futures::poll!(a.as_mut()).is_pending());
+    assert!(futures::poll!(b.as_mut()).is_pending());
+    assert_eq!(*metrics.max_queue_depth.lock().unwrap(), 1);
+
+    let connection = connection.map(|r| r.unwrap());
+    tokio::spawn(connection);
+
+    let (a, b) = try_join!(a, b).unwrap();
+    assert_eq!(a.len(), 2);
+    assert_eq!(b.len(), 2);
 }
 
 #[tokio::test]
-async fn check_send() {
-    fn is_send<T: Send>(_: &T) {}
+async fn max_message_length() {
+    let socket = TcpStream::connect("127.0.0.1:5433").await.unwrap();
+    let mut config = "user=postgres".parse::<Config>().unwrap();
+    config.read_buffer_size(16).max_message_length(256);
+    let (client, connection) = config.connect_raw(socket, NoTls).await.unwrap();
+    let connection = tokio::spawn(connection);
 
-    let f = connect("user=postgres");
-    is_send(&f);
-    let mut client = f.await;
+    let row = client
+        .query_one("SELECT repeat('x', 100)", &[])
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, &str>(0), "x".repeat(100));
 
-    let f = client.prepare("SELECT $1::TEXT");
-    is_send(&f);
-    let stmt = f.await.unwrap();
+    client
+        .query_one("SELECT repeat('x', 1000)", &[])
+        .await
+        .unwrap_err();
+    let err = connection.await.unwrap().unwrap_err();
+    assert!(err.to_string().contains("exceeds the maximum"), "{}", err);
+}
 
-    let f = client.query(&stmt, &[&"hello"]);
-    is_send(&f);
-    drop(f);
+struct ProtocolLogger(Mutex<Vec<String>>);
 
-    let f = client.execute(&stmt, &[&"hello"]);
-    is_send(&f);
-    drop(f);
+impl log::Log for ProtocolLogger {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        metadata.target() == "tokio_postgres::protocol"
+    }
 
-    let f = client.transaction();
-    is_send(&f);
-    let trans = f.await.unwrap();
+    fn log(&self, record: &log::Record<'_>) {
+        if self.enabled(record.metadata()) {
+            self.0.lock().unwrap().push(record.args().to_string());
+        }
+    }
 
-    let f = trans.query(&stmt, &[&"hello"]);
-    is_send(&f);
-    drop(f);
+    fn flush(&self) {}
+}
 
-    let f = trans.execute(&stmt, &[&"hello"]);
-    is_send(&f);
-    drop(f);
+#[tokio::test]
+async fn trace_protocol() {
+    static LOGGER: ProtocolLogger = ProtocolLogger(Mutex::new(vec![]));
+    log::set_logger(&LOGGER).unwrap();
+    log::set_max_level(log::LevelFilter::Info);
+
+    let socket = TcpStream::connect("127.0.0.1:5433").await.unwrap();
+    let mut config = "user=postgres".parse::<Config>().unwrap();
+    config.trace_protocol(true);
+    let (client, connection) = config.connect_raw(socket, NoTls).await.unwrap();
+    let connection = connection.map(|r| r.unwrap());
+    tokio::spawn(connection);
+
+    client.batch_execute("SELECT 1 / 0").await.unwrap_err();
+    client.batch_execute("").await.unwrap();
+
+    let lines = LOGGER.0.lock().unwrap();
+    let messages = lines
+        .iter()
+        .map(|line| {
+            let mut parts = line.splitn(4, '\t').skip(1);
+            let direction = parts.next().unwrap();
+            parts.next().unwrap().parse::<usize>().unwrap();
+            format!("{} {}", direction, parts.next().unwrap())
+        })
+        .collect::<Vec<_>>();
+
+    assert!(messages[0].starts_with("F StartupMessage"));
+    assert!(messages[0].contains(" user \"postgres\""));
+    assert!(messages.contains(&"B AuthenticationOk".to_string()));
+    let query = messages
+        .iter()
+        .position(|m| m == "F Query \"SELECT 1 / 0\"")
+        .unwrap();
+    assert!(messages[query + 1].starts_with("B ErrorResponse S \"ERROR\""));
+    assert!(messages[query + 1].contains(" C \"22012\""));
+    assert!(messages[query + 2..].contains(&"B ReadyForQuery I".to_string()));
 }
 
 #[tokio::test]
-async fn query_one() {
+async fn function_call() {
     let client = connect("user=postgres").await;
 
-    client
-        .batch_execute(
-            "
-                CREATE TEMPORARY TABLE foo (
-                    name TEXT
-                );
-                INSERT INTO foo (name) VALUES ('alice'), ('bob'), ('carol');
-            ",
+    // int4pl
+    let sum = client
+        .function_call::<i32>(
+            177,
+            &[(&1i32, Type::INT4), (&2i32, Type::INT4)],
+            &Type::INT4,
         )
         .await
         .unwrap();
+    assert_eq!(sum, 3);
 
-    client
-        .query_one("SELECT * FROM foo WHERE name = 'dave'", &[])
+    // textlen
+    let len = client
+        .function_call::<i32>(1257, &[(&"hello", Type::TEXT)], &Type::INT4)
         .await
-        .err()
         .unwrap();
-    client
-        .query_one("SELECT * FROM foo WHERE name = 'alice'", &[])
+    assert_eq!(len, 5);
+
+    // int4pl is strict, so a null argument produces a null result
+    let sum = client
+        .function_call::<Option<i32>>(
+            177,
+            &[(&None::<i32>, Type::INT4), (&2i32, Type::INT4)],
+            &Type::INT4,
+        )
         .await
         .unwrap();
+    assert_eq!(sum, None);
+
+    // int4div
+    let err = client
+        .function_call::<i32>(
+            154,
+            &[(&1i32, Type::INT4), (&0i32, Type::INT4)],
+            &Type::INT4,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::DIVISION_BY_ZERO));
+
     client
-        .query_one("SELECT * FROM foo", &[])
+        .function_call::<String>(
+            177,
+            &[(&1i32, Type::INT4), (&2i32, Type::INT4)],
+            &Type::INT4,
+        )
+        .await
+        .unwrap_err();
+
+    let row = client.query_one("SELECT 1", &[]).await.unwrap();
+    assert_eq!(row.get::<_, i32>(0), 1);
+}
+
+#[tokio::test]
+async fn large_object() {
+    let mut client = connect("user=postgres").await;
+    let transaction = client.transaction().await.unwrap();
+
+    let oid = transaction.create_large_object().await.unwrap();
+    let mut object = transaction
+        .open_large_object(oid, LargeObjectMode::ReadWrite)
+        .await
+        .unwrap();
+    assert_eq!(object.oid(), oid);
+
+    let data = (0..100_000).map(|i| i as u8).collect::<Vec<_>>();
+    object.write_all(&data).await.unwrap();
+    assert_eq!(object.tell().await.unwrap(), data.len() as u64);
+
+    assert_eq!(object.seek(SeekFrom::Start(0)).await.unwrap(), 0);
+    let mut buf = vec![];
+    object.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf, data);
+
+    object.truncate(10).await.unwrap();
+    assert_eq!(object.seek(SeekFrom::End(-4)).await.unwrap(), 6);
+    let mut buf = [0; 3];
+    object.read_exact(&mut buf).await.unwrap();
+    assert_eq!(buf, [6, 7, 8]);
+    assert_eq!(object.seek(SeekFrom::Current(-2)).await.unwrap(), 7);
+    object.close().await.unwrap();
+
+    transaction.unlink_large_object(oid).await.unwrap();
+    let err = transaction
+        .open_large_object(oid, LargeObjectMode::Read)
         .await
         .err()
         .unwrap();
+    assert_eq!(err.code(), Some(&SqlState::UNDEFINED_OBJECT));
 }
 
 #[tokio::test]
-async fn query_opt() {
-    let client = connect("user=postgres").await;
+async fn large_object_write_after_read_ahead() {
+    let mut client = connect("user=postgres").await;
+    let transaction = client.transaction().await.unwrap();
 
-    client
-        .batch_execute(
-            "
-                CREATE TEMPORARY TABLE foo (
-                    name TEXT
-                );
-                INSERT INTO foo (name) VALUES ('alice'), ('bob'), ('carol');
-            ",
-        )
+    let oid = transaction.create_large_object().await.unwrap();
+    let mut object = transaction
+        .open_large_object(oid, LargeObjectMode::ReadWrite)
         .await
         .unwrap();
+    object.write_all(&[0; 10]).await.unwrap();
+    object.seek(SeekFrom::Start(0)).await.unwrap();
 
-    assert!(client
-        .query_opt("SELECT * FROM foo WHERE name = 'dave'", &[])
-        .await
-        .unwrap()
-        .is_none());
-    client
-        .query_opt("SELECT * FROM foo WHERE name = 'alice'", &[])
+    // start an 8 byte read, but only take 3 bytes of it
+    assert!(object.read(&mut [0; 8]).now_or_never().is_none());
+    let mut buf = [0; 3];
+    object.read_exact(&mut buf).await.unwrap();
+
+    object.write_all(&[1]).await.unwrap();
+    assert_eq!(object.tell().await.unwrap(), 4);
+
+    object.seek(SeekFrom::Start(0)).await.unwrap();
+    let mut buf = vec![];
+    object.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf, [0, 0, 0, 1, 0, 0, 0, 0, 0, 0]);
+}
+
+#[tokio::test]
+async fn large_object_interrupted_operations() {
+    let mut client = connect("user=postgres").await;
+    let transaction = client.transaction().await.unwrap();
+
+    let oid = transaction.create_large_object().await.unwrap();
+    let mut object = transaction
+        .open_large_object(oid, LargeObjectMode::ReadWrite)
         .await
-        .unwrap()
         .unwrap();
-    client
-        .query_one("SELECT * FROM foo", &[])
+    object
+        .write_all(&(0..10).collect::<Vec<u8>>())
         .await
-        .err()
         .unwrap();
+
+    // a read abandoned before a seek doesn't return data from the old position afterwards
+    object.seek(SeekFrom::Start(0)).await.unwrap();
+    assert!(object.read(&mut [0; 4]).now_or_never().is_none());
+    assert_eq!(object.seek(SeekFrom::Start(6)).await.unwrap(), 6);
+    let mut buf = [0; 2];
+    object.read_exact(&mut buf).await.unwrap();
+    assert_eq!(buf, [6, 7]);
+    assert_eq!(object.tell().await.unwrap(), 8);
+
+    // reads fail until an abandoned write is flushed
+    assert!(object.write(&[1]).now_or_never().is_none());
+    object.read(&mut [0; 1]).await.unwrap_err();
+    object.flush().await.unwrap();
+    assert_eq!(object.tell().await.unwrap(), 9);
 }
 
 #[tokio::test]
-async fn deferred_constraint() {
-    let client = connect("user=postgres").await;
+async fn unnamed_statements() {
+    let socket = TcpStream::connect("127.0.0.1:5433").await.unwrap();
+    let mut config = "user=postgres".parse::<Config>().unwrap();
+    config.named_statements(false);
+    let (mut client, connection) = config.connect_raw(socket, NoTls).await.unwrap();
+    let connection = connection.map(|r| r.unwrap());
+    tokio::spawn(connection);
 
-    client
-        .batch_execute(
-            "
-            CREATE TEMPORARY TABLE t (
-                i INT,
-                UNIQUE (i) DEFERRABLE INITIALLY DEFERRED
-            );
-        ",
-        )
+    // each statement is parsed again before use, so preparing another in between doesn't matter
+    let add = client.prepare("SELECT $1::INT + 1").await.unwrap();
+    let concat = client.prepare("SELECT $1::TEXT || '!'").await.unwrap();
+
+    let row = client.query_one(&add, &[&1i32]).await.unwrap();
+    assert_eq!(row.get::<_, i32>(0), 2);
+    let row = client.query_one(&concat, &[&"hi"]).await.unwrap();
+    assert_eq!(row.get::<_, &str>(0), "hi!");
+    let row = client.query_one(&add, &[&41i32]).await.unwrap();
+    assert_eq!(row.get::<_, i32>(0), 42);
+
+    let transaction = client.transaction().await.unwrap();
+    let portal = transaction.bind(&add, &[&2i32]).await.unwrap();
+    let rows = transaction.query_portal(&portal, 0).await.unwrap();
+    assert_eq!(rows[0].get::<_, i32>(0), 3);
+    transaction.commit().await.unwrap();
+
+    drop((add, concat));
+    let count = client
+        .query_one("SELECT COUNT(*) FROM pg_prepared_statements", &[])
         .await
         .unwrap();
+    assert_eq!(count.get::<_, i64>(0), 0);
+}
 
-    client
-        .execute("INSERT INTO t (i) VALUES (1)", &[])
-        .await
+#[tokio::test]
+async fn protocol_version_negotiation() {
+    let socket = TcpStream::connect("127.0.0.1:5433").await.unwrap();
+    let mut config = "user=postgres max_protocol_version=latest"
+        .parse::<Config>()
         .unwrap();
-    client
-        .execute("INSERT INTO t (i) VALUES (1)", &[])
+    config.protocol_extension("tokio_postgres_test", "on");
+    let (client, connection) = config.connect_raw(socket, NoTls).await.unwrap();
+    let connection = connection.map(|r| r.unwrap());
+    tokio::spawn(connection);
+
+    let version = client
+        .query_one("SHOW server_version_num", &[])
         .await
-        .unwrap_err();
+        .unwrap()
+        .get::<_, String>(0)
+        .parse::<i32>()
+        .unwrap();
+    let expected = if version >= 180_000 {
+        ProtocolVersion::V3_2
+    } else {
+        ProtocolVersion::V3_0
+    };
+    assert_eq!(client.protocol_version(), expected);
+
+    // the cancel key is longer under 3.2
+    let cancel_token = client.cancel_token();
+    let sleep = client.batch_execute("SELECT pg_sleep(100)");
+    let cancel = async {
+        time::sleep(Duration::from_millis(100)).await;
+        let socket = TcpStream::connect("127.0.0.1:5433").await.unwrap();
+        cancel_token.cancel_query_raw(socket, NoTls).await
+    };
+    let (sleep, cancel) = join!(sleep, cancel);
+    cancel.unwrap();
+    assert_eq!(sleep.unwrap_err().code(), Some(&SqlState::QUERY_CANCELED));
+}
+
+#[tokio::test]
+async fn unsupported_min_protocol_version() {
+    let socket = TcpStream::connect("127.0.0.1:5433").await.unwrap();
+    let mut config = "user=postgres".parse::<Config>().unwrap();
+    config.min_protocol_version(ProtocolVersion::V3_2);
+    match config.connect_raw(socket, NoTls).await {
+        Ok((client, _)) => assert_eq!(client.protocol_version(), ProtocolVersion::V3_2),
+        Err(e) => assert!(e.to_string().contains("protocol version"), "{}", e),
+    }
+}
+
+#[tokio::test]
+async fn client_encoding() {
+    // encoding names are matched the way the server matches them
+    let client = connect("user=postgres client_encoding=utf-8").await;
+    client.batch_execute("SELECT 1").await.unwrap();
+
+    // changing the encoding mid-session fails the connection rather than mis-decoding text
+    let (client, connection) = connect_raw("user=postgres").await.unwrap();
+    let connection = tokio::spawn(connection);
+    let _ = client.batch_execute("SET client_encoding = 'LATIN1'").await;
+    let err = connection.await.unwrap().unwrap_err();
+    assert!(err.to_string().contains("LATIN1"), "{}", err);
 }
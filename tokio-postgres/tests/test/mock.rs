@@ -0,0 +1,136 @@
+use futures::FutureExt;
+use tokio_postgres::error::SqlState;
+use tokio_postgres::mock::{Expectation, MockServer};
+use tokio_postgres::types::Type;
+use tokio_postgres::{Client, SimpleQueryMessage};
+
+async fn connect(mock: &MockServer) -> Client {
+    let (client, connection) = mock.connect().await.unwrap();
+    tokio::spawn(connection.map(|r| r.unwrap()));
+    client
+}
+
+#[tokio::test]
+async fn query() {
+    let mock = MockServer::new();
+    let mut expectation = Expectation::new("SELECT id, name FROM users WHERE id = $1");
+    expectation
+        .param_types(&[Type::INT4])
+        .params(&[&1i32])
+        .column("id", Type::INT4)
+        .column("name", Type::TEXT)
+        .row(&[&1i32, &"alice"])
+        .row(&[&1i32, &None::<&str>]);
+    mock.expect(expectation);
+
+    let client = connect(&mock).await;
+    let rows = client
+        .query("SELECT id, name\nFROM users WHERE id = $1", &[&1i32])
+        .await
+        .unwrap();
+
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].get::<_, i32>("id"), 1);
+    assert_eq!(rows[0].get::<_, &str>("name"), "alice");
+    assert_eq!(rows[1].get::<_, Option<&str>>(1), None);
+    mock.assert_done();
+}
+
+#[tokio::test]
+async fn execute() {
+    let mock = MockServer::new();
+    let mut expectation = Expectation::new("UPDATE users SET name = $1");
+    expectation.param_types(&[Type::TEXT]).rows_affected(3);
+    mock.expect(expectation);
+
+    let client = connect(&mock).await;
+    let modified = client
+        .execute("UPDATE users SET name = $1", &[&"bob"])
+        .await
+        .unwrap();
+
+    assert_eq!(modified, 3);
+    mock.assert_done();
+}
+
+#[tokio::test]
+async fn simple_query() {
+    let mock = MockServer::new();
+    mock.expect(Expectation::new("BEGIN"));
+    let mut expectation = Expectation::new("SELECT 1 AS one");
+    expectation.column("one", Type::INT4).text_row(&[Some("1")]);
+    mock.expect(expectation);
+
+    let client = connect(&mock).await;
+    client.batch_execute("BEGIN").await.unwrap();
+    let messages = client.simple_query("SELECT 1 AS one").await.unwrap();
+
+    match &messages[0] {
+        SimpleQueryMessage::Row(row) => assert_eq!(row.get("one"), Some("1")),
+        _ => panic!("expected a row"),
+    }
+    match messages[1] {
+        SimpleQueryMessage::CommandComplete(rows) => assert_eq!(rows, 1),
+        _ => panic!("expected command completion"),
+    }
+    mock.assert_done();
+}
+
+#[tokio::test]
+async fn error() {
+    let mock = MockServer::new();
+    let mut expectation = Expectation::new("INSERT INTO users (id) VALUES (1)");
+    expectation.error(SqlState::UNIQUE_VIOLATION, "duplicate key");
+    mock.expect(expectation);
+    mock.expect(Expectation::new("SELECT 1"));
+
+    let client = connect(&mock).await;
+    let e = client
+        .execute("INSERT INTO users (id) VALUES (1)", &[])
+        .await
+        .unwrap_err();
+    assert_eq!(e.code(), Some(&SqlState::UNIQUE_VIOLATION));
+
+    // the connection remains usable afterwards
+    client.execute("SELECT 1", &[]).await.unwrap();
+    mock.assert_done();
+}
+
+#[tokio::test]
+async fn unexpected_query() {
+    let mock = MockServer::new();
+    mock.expect(Expectation::new("SELECT 1"));
+
+    let client = connect(&mock).await;
+    let e = client.execute("SELECT 2", &[]).await.unwrap_err();
+    assert!(e.to_string().contains("unexpected query `SELECT 2`"));
+    assert_eq!(mock.remaining(), 1);
+
+    client.execute("SELECT 1", &[]).await.unwrap();
+    assert_eq!(mock.remaining(), 0);
+}
+
+#[tokio::test]
+async fn unexpected_params() {
+    let mock = MockServer::new();
+    let mut expectation = Expectation::new("SELECT $1::INT");
+    expectation.param_types(&[Type::INT4]).params(&[&1i32]);
+    mock.expect(expectation);
+
+    let client = connect(&mock).await;
+    let e = client
+        .execute("SELECT $1::INT", &[&2i32])
+        .await
+        .unwrap_err();
+    assert!(e.to_string().contains("unexpected parameters"));
+}
+
+#[tokio::test]
+#[should_panic(expected = "expected statements which were not executed")]
+async fn unmet_expectation() {
+    let mock = MockServer::new();
+    mock.expect(Expectation::new("SELECT 1"));
+
+    let _client = connect(&mock).await;
+    mock.assert_done();
+}
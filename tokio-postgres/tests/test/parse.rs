@@ -1,5 +1,8 @@
 use std::time::Duration;
-use tokio_postgres::config::{Config, TargetSessionAttrs};
+use tokio_postgres::config::{
+    Config, GssEncMode, ProtocolVersion, ReplicationMode, TargetSessionAttrs,
+};
+use tokio_postgres::IsolationLevel;
 
 fn check(s: &str, config: &Config) {
     assert_eq!(s.parse::<Config>().expect(s), *config, "`{}`", s);
@@ -36,6 +39,55 @@ fn settings() {
     );
 }
 
+#[test]
+fn role() {
+    check("role=admin", Config::new().role("admin"));
+}
+
+#[test]
+fn default_transaction_characteristics() {
+    check(
+        "default_transaction_isolation='repeatable read' default_transaction_read_only=true",
+        Config::new()
+            .default_transaction_isolation(IsolationLevel::RepeatableRead)
+            .default_transaction_read_only(true),
+    );
+}
+
+#[test]
+fn replication() {
+    check(
+        "replication=database",
+        Config::new().replication_mode(ReplicationMode::Logical),
+    );
+    check(
+        "replication=on",
+        Config::new().replication_mode(ReplicationMode::Physical),
+    );
+    check("replication=false", &Config::new());
+}
+
+#[test]
+fn protocol_versions() {
+    check(
+        "min_protocol_version=3.0 max_protocol_version=latest",
+        Config::new()
+            .min_protocol_version(ProtocolVersion::V3_0)
+            .max_protocol_version(ProtocolVersion::V3_2),
+    );
+    assert!("max_protocol_version=3.1".parse::<Config>().is_err());
+}
+
+#[test]
+fn gss_enc_mode() {
+    check(
+        "gssencmode=disable",
+        Config::new().gss_enc_mode(GssEncMode::Disable),
+    );
+    check("gssencmode=prefer", &Config::new());
+    assert!("gssencmode=allow".parse::<Config>().is_err());
+}
+
 #[test]
 fn url() {
     check("postgresql://", &Config::new());
@@ -0,0 +1,403 @@
+use crate::connect;
+use futures::{pin_mut, StreamExt};
+use std::str;
+use tokio_postgres::replication::{
+    BaseBackupMessage, LogicalReplicationMessage, LogicalReplicationStream, ReplicaIdentity,
+    ReplicationMessage, Tuple, TupleData,
+};
+use tokio_postgres::types::PgLsn;
+use tokio_postgres::{Client, SimpleQueryMessage};
+
+async fn create_slot(client: &Client, slot: &str, plugin: &str) -> PgLsn {
+    let query = format!(
+        "CREATE_REPLICATION_SLOT {} TEMPORARY LOGICAL {}",
+        slot, plugin
+    );
+    let messages = client.simple_query(&query).await.unwrap();
+    match &messages[0] {
+        SimpleQueryMessage::Row(row) => row.get("consistent_point").unwrap().parse().unwrap(),
+        _ => panic!("unexpected message"),
+    }
+}
+
+#[tokio::test]
+async fn logical_replication() {
+    let client = connect("user=postgres").await;
+    client
+        .batch_execute(
+            "
+            DROP TABLE IF EXISTS logical_replication;
+            CREATE TABLE logical_replication (id INT);
+            ",
+        )
+        .await
+        .unwrap();
+
+    let repl_client = connect("user=postgres replication=database").await;
+    let slot_lsn = create_slot(&repl_client, "logical_replication", "test_decoding").await;
+
+    client
+        .execute("INSERT INTO logical_replication (id) VALUES (1)", &[])
+        .await
+        .unwrap();
+
+    let stream = repl_client
+        .start_logical_replication(
+            "logical_replication",
+            slot_lsn,
+            &[("include-xids", "0"), ("skip-empty-xacts", "1")],
+        )
+        .await
+        .unwrap();
+    pin_mut!(stream);
+
+    // other tests may be modifying tables concurrently, so skip over their changes
+    let (change, wal_end) = loop {
+        if let ReplicationMessage::XLogData(body) = stream.next().await.unwrap().unwrap() {
+            let data = str::from_utf8(body.data()).unwrap();
+            if data.starts_with("table public.logical_replication:") {
+                break (data.to_string(), PgLsn::from(body.wal_end()));
+            }
+        }
+    };
+    assert_eq!(
+        change,
+        "table public.logical_replication: INSERT: id[integer]:1"
+    );
+    let received_lsn = stream.last_received_lsn();
+    assert!(received_lsn >= slot_lsn);
+    assert!(received_lsn >= wal_end);
+
+    stream.as_mut().set_flushed_lsn(received_lsn);
+    stream.as_mut().send_feedback(true).await.unwrap();
+    loop {
+        if let ReplicationMessage::PrimaryKeepAlive(_) = stream.next().await.unwrap().unwrap() {
+            break;
+        }
+    }
+
+    stream.as_mut().close().await.unwrap();
+    while let Some(message) = stream.next().await {
+        message.unwrap();
+    }
+
+    let row = client
+        .query_one(
+            "SELECT confirmed_flush_lsn FROM pg_replication_slots WHERE slot_name = $1",
+            &[&"logical_replication"],
+        )
+        .await
+        .unwrap();
+    assert!(row.get::<_, PgLsn>(0) >= received_lsn);
+
+    client
+        .batch_execute("DROP TABLE logical_replication")
+        .await
+        .unwrap();
+}
+
+fn text_values(tuple: &Tuple) -> String {
+    tuple
+        .tuple_data()
+        .iter()
+        .map(|data| match data {
+            TupleData::Null => "NULL",
+            TupleData::Text(text) => str::from_utf8(text).unwrap(),
+            _ => panic!("unexpected tuple data"),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[tokio::test]
+async fn pgoutput() {
+    let client = connect("user=postgres").await;
+    client
+        .batch_execute(
+            "
+            DROP TABLE IF EXISTS pgoutput;
+            CREATE TABLE pgoutput (id INT PRIMARY KEY, name TEXT);
+            DROP PUBLICATION IF EXISTS pgoutput;
+            CREATE PUBLICATION pgoutput FOR TABLE pgoutput;
+            ",
+        )
+        .await
+        .unwrap();
+
+    // a small decoding memory limit forces the large transaction below to be streamed
+    let repl_client =
+        connect("user=postgres replication=database options='-c logical_decoding_work_mem=64kB'")
+            .await;
+    let slot_lsn = create_slot(&repl_client, "pgoutput", "pgoutput").await;
+
+    client
+        .batch_execute(
+            "
+            INSERT INTO pgoutput (id, name) VALUES (1, 'steven');
+            UPDATE pgoutput SET name = 'joe' WHERE id = 1;
+            DELETE FROM pgoutput WHERE id = 1;
+            TRUNCATE pgoutput;
+            ",
+        )
+        .await
+        .unwrap();
+    client
+        .batch_execute(
+            "INSERT INTO pgoutput (id, name) SELECT i, 'bob' FROM generate_series(1, 5000) i",
+        )
+        .await
+        .unwrap();
+
+    let stream = repl_client
+        .start_logical_replication(
+            "pgoutput",
+            slot_lsn,
+            &[
+                ("proto_version", "2"),
+                ("publication_names", "pgoutput"),
+                ("streaming", "on"),
+            ],
+        )
+        .await
+        .unwrap();
+    let stream = LogicalReplicationStream::new(stream);
+    pin_mut!(stream);
+
+    let mut rel_id = None;
+    let mut changes = vec![];
+    let mut streamed_inserts = 0;
+    loop {
+        let body = match stream.next().await.unwrap().unwrap() {
+            ReplicationMessage::XLogData(body) => body,
+            _ => continue,
+        };
+        match body.data() {
+            LogicalReplicationMessage::Relation(body) => {
+                assert_eq!(body.namespace().unwrap(), "public");
+                assert_eq!(body.name().unwrap(), "pgoutput");
+                assert_eq!(body.replica_identity(), ReplicaIdentity::Default);
+                let columns = body
+                    .columns()
+                    .iter()
+                    .map(|c| (c.name().unwrap(), c.flags()))
+                    .collect::<Vec<_>>();
+                assert_eq!(columns, [("id", 1), ("name", 0)]);
+                rel_id = Some(body.rel_id());
+            }
+            LogicalReplicationMessage::Insert(body) if body.xid().is_some() => {
+                assert_eq!(Some(body.rel_id()), rel_id);
+                streamed_inserts += 1;
+            }
+            LogicalReplicationMessage::Insert(body) => {
+                assert_eq!(Some(body.rel_id()), rel_id);
+                changes.push(format!("insert {}", text_values(body.tuple())));
+            }
+            LogicalReplicationMessage::Update(body) => {
+                assert!(body.key_tuple().is_none());
+                assert!(body.old_tuple().is_none());
+                changes.push(format!("update {}", text_values(body.new_tuple())));
+            }
+            LogicalReplicationMessage::Delete(body) => {
+                changes.push(format!("delete {}", text_values(body.key_tuple().unwrap())));
+            }
+            LogicalReplicationMessage::Truncate(body) => {
+                assert_eq!(body.rel_ids(), [rel_id.unwrap()]);
+                changes.push("truncate".to_string());
+            }
+            LogicalReplicationMessage::StreamCommit(_) => break,
+            _ => {}
+        }
+    }
+
+    assert_eq!(
+        changes,
+        [
+            "insert 1 steven",
+            "update 1 joe",
+            "delete 1 NULL",
+            "truncate"
+        ]
+    );
+    assert_eq!(streamed_inserts, 5000);
+
+    client
+        .batch_execute("DROP PUBLICATION pgoutput; DROP TABLE pgoutput")
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn physical_replication() {
+    let client = connect("user=postgres").await;
+    let repl_client = connect("user=postgres replication=true").await;
+
+    let system = repl_client.identify_system().await.unwrap();
+    assert_eq!(system.dbname(), None);
+    // the initial timeline of a cluster has no history file
+    if system.timeline() == 1 {
+        repl_client.timeline_history(1).await.unwrap_err();
+    }
+
+    let stream = repl_client
+        .start_physical_replication(None, system.xlog_pos(), Some(system.timeline()))
+        .await
+        .unwrap();
+    pin_mut!(stream);
+
+    client
+        .batch_execute("CREATE TEMPORARY TABLE foo (id INT)")
+        .await
+        .unwrap();
+
+    while stream.last_received_lsn() <= system.xlog_pos() {
+        match stream.next().await.unwrap().unwrap() {
+            ReplicationMessage::XLogData(body) => {
+                assert!(body.wal_start() >= u64::from(system.xlog_pos()));
+            }
+            ReplicationMessage::PrimaryKeepAlive(_) => {}
+            _ => panic!("unexpected message"),
+        }
+    }
+
+    let received_lsn = stream.last_received_lsn();
+    stream.as_mut().set_flushed_lsn(received_lsn);
+    stream.as_mut().send_feedback(false).await.unwrap();
+
+    stream.as_mut().close().await.unwrap();
+    while let Some(message) = stream.next().await {
+        message.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn base_backup() {
+    let client = connect("user=postgres replication=true").await;
+
+    let stream = client
+        .base_backup(&[
+            ("LABEL", Some("rust-postgres")),
+            ("CHECKPOINT", Some("fast")),
+            ("WAIT", Some("false")),
+            ("PROGRESS", None),
+        ])
+        .await
+        .unwrap();
+    pin_mut!(stream);
+
+    let mut rows = vec![];
+    let mut copies = 0;
+    let mut in_copy = false;
+    let mut bytes = 0;
+    while let Some(message) = stream.next().await {
+        match message.unwrap() {
+            BaseBackupMessage::Row(row) => {
+                assert!(!in_copy);
+                rows.push(row);
+            }
+            BaseBackupMessage::CopyStart => {
+                assert!(!in_copy);
+                in_copy = true;
+                copies += 1;
+            }
+            BaseBackupMessage::CopyData(data) => {
+                assert!(in_copy);
+                bytes += data.len();
+            }
+            BaseBackupMessage::CopyDone => {
+                assert!(in_copy);
+                in_copy = false;
+            }
+            _ => panic!("unexpected message"),
+        }
+    }
+
+    assert!(copies > 0);
+    assert!(bytes > 0);
+    let start = rows[0].get("recptr").unwrap().parse::<PgLsn>().unwrap();
+    let end = rows
+        .last()
+        .unwrap()
+        .get("recptr")
+        .unwrap()
+        .parse::<PgLsn>()
+        .unwrap();
+    assert!(start <= end);
+
+    assert!(client
+        .base_backup(&[("LABEL 'x', WAIT", None)])
+        .await
+        .is_err());
+}
+
+#[cfg(feature = "wal2json")]
+#[test]
+fn wal2json() {
+    use serde_json_1::json;
+    use tokio_postgres::replication::wal2json::Wal2JsonMessage;
+
+    let begin = br#"{"action":"B","xid":571,"lsn":"0/16D30F8","nextlsn":"0/16D3188"}"#;
+    match Wal2JsonMessage::parse(begin).unwrap() {
+        Wal2JsonMessage::Begin(body) => {
+            assert_eq!(body.xid(), Some(571));
+            assert_eq!(body.lsn(), Some("0/16D30F8".parse().unwrap()));
+            assert_eq!(body.next_lsn(), Some("0/16D3188".parse().unwrap()));
+            assert_eq!(body.timestamp(), None);
+        }
+        m => panic!("unexpected message {:?}", m),
+    }
+
+    let insert = br#"{"action":"I","xid":571,"schema":"public","table":"wal2json","columns":[{"name":"id","type":"integer","typeoid":23,"value":1},{"name":"name","type":"text","typeoid":25,"value":"foo"},{"name":"active","type":"boolean","typeoid":16,"value":null}],"pk":[{"name":"id","type":"integer","typeoid":23}]}"#;
+    match Wal2JsonMessage::parse(insert).unwrap() {
+        Wal2JsonMessage::Insert(body) => {
+            assert_eq!(body.schema(), "public");
+            assert_eq!(body.table(), "wal2json");
+            let columns = body.columns();
+            assert_eq!(columns.len(), 3);
+            assert_eq!(columns[0].name(), "id");
+            assert_eq!(columns[0].type_name(), "integer");
+            assert_eq!(columns[0].type_oid(), Some(23));
+            assert_eq!(columns[0].value(), &json!(1));
+            assert_eq!(columns[1].value(), &json!("foo"));
+            assert_eq!(columns[2].value(), &json!(null));
+            assert!(body.identity().is_empty());
+            assert_eq!(body.pk()[0].name(), "id");
+        }
+        m => panic!("unexpected message {:?}", m),
+    }
+
+    let update = br#"{"action":"U","schema":"public","table":"wal2json","columns":[{"name":"id","type":"integer","value":2}],"identity":[{"name":"id","type":"integer","value":1}]}"#;
+    match Wal2JsonMessage::parse(update).unwrap() {
+        Wal2JsonMessage::Update(body) => {
+            assert_eq!(body.columns()[0].value(), &json!(2));
+            assert_eq!(body.identity()[0].value(), &json!(1));
+            assert_eq!(body.columns()[0].type_oid(), None);
+        }
+        m => panic!("unexpected message {:?}", m),
+    }
+
+    let truncate = br#"{"action":"T","schema":"public","table":"wal2json"}"#;
+    match Wal2JsonMessage::parse(truncate).unwrap() {
+        Wal2JsonMessage::Truncate(body) => assert!(body.columns().is_empty()),
+        m => panic!("unexpected message {:?}", m),
+    }
+
+    let message = br#"{"action":"M","transactional":false,"prefix":"wal2json","content":"hello"}"#;
+    match Wal2JsonMessage::parse(message).unwrap() {
+        Wal2JsonMessage::Message(body) => {
+            assert!(!body.transactional());
+            assert_eq!(body.prefix(), "wal2json");
+            assert_eq!(body.content(), "hello");
+        }
+        m => panic!("unexpected message {:?}", m),
+    }
+
+    let commit = br#"{"action":"C","xid":571,"timestamp":"2021-01-01 00:00:00.000000+00"}"#;
+    match Wal2JsonMessage::parse(commit).unwrap() {
+        Wal2JsonMessage::Commit(body) => {
+            assert_eq!(body.timestamp(), Some("2021-01-01 00:00:00.000000+00"))
+        }
+        m => panic!("unexpected message {:?}", m),
+    }
+
+    assert!(Wal2JsonMessage::parse(br#"{"action":"B","lsn":"bogus"}"#).is_err());
+}
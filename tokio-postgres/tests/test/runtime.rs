@@ -1,8 +1,21 @@
-use futures::{join, FutureExt};
-use std::time::Duration;
+use futures::future::BoxFuture;
+use futures::{future, join, pin_mut, Future, FutureExt};
+use std::io;
+use std::net::SocketAddr;
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::time;
+use tokio_postgres::config::{AddressStrategy, Host};
 use tokio_postgres::error::SqlState;
-use tokio_postgres::{Client, NoTls};
+use tokio_postgres::runtime::{CachingResolver, Resolver, Runtime, TokioRuntime};
+use tokio_postgres::{
+    AuthenticationMethod, Client, Config, ConnectedEvent, ConnectionListener, DisconnectCause,
+    Error, NoTls, ReconnectPolicy, ReconnectingClient, SimpleQueryMessage, Socket, Watchdog,
+    WatchdogEvent, WatchdogEventKind,
+};
 
 async fn connect(s: &str) -> Client {
     let (client, connection) = tokio_postgres::connect(s, NoTls).await.unwrap();
@@ -81,3 +94,701 @@ async fn cancel_query() {
         t => panic!("unexpected return: {:?}", t),
     }
 }
+
+#[derive(Default)]
+struct CountingRuntime {
+    connects: AtomicUsize,
+}
+
+impl Runtime for CountingRuntime {
+    fn lookup_host(
+        &self,
+        host: String,
+        port: u16,
+    ) -> BoxFuture<'static, io::Result<Vec<SocketAddr>>> {
+        TokioRuntime.lookup_host(host, port)
+    }
+
+    fn connect_tcp(
+        &self,
+        addr: SocketAddr,
+        keepalive: Option<Duration>,
+    ) -> BoxFuture<'static, io::Result<Socket>> {
+        self.connects.fetch_add(1, Ordering::SeqCst);
+        TokioRuntime.connect_tcp(addr, keepalive)
+    }
+
+    #[cfg(unix)]
+    fn connect_unix(&self, path: PathBuf) -> BoxFuture<'static, io::Result<Socket>> {
+        self.connects.fetch_add(1, Ordering::SeqCst);
+        TokioRuntime.connect_unix(path)
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        TokioRuntime.sleep(duration)
+    }
+}
+
+#[tokio::test]
+async fn custom_runtime() {
+    let runtime = Arc::new(CountingRuntime::default());
+    let (client, connection) = "host=localhost port=5433 user=postgres"
+        .parse::<Config>()
+        .unwrap()
+        .runtime(runtime.clone())
+        .connect(NoTls)
+        .await
+        .unwrap();
+    tokio::spawn(connection.map(|e| e.unwrap()));
+
+    let rows = client.query("SELECT 1::INT", &[]).await.unwrap();
+    assert_eq!(rows[0].get::<_, i32>(0), 1);
+    assert_eq!(runtime.connects.load(Ordering::SeqCst), 1);
+
+    client.cancel_token().cancel_query(NoTls).await.unwrap();
+    assert_eq!(runtime.connects.load(Ordering::SeqCst), 2);
+}
+
+// Connections to the discard port hang forever, like those to a host behind a firewall which drops packets.
+struct BlackholeRuntime;
+
+impl Runtime for BlackholeRuntime {
+    fn lookup_host(
+        &self,
+        host: String,
+        port: u16,
+    ) -> BoxFuture<'static, io::Result<Vec<SocketAddr>>> {
+        TokioRuntime.lookup_host(host, port)
+    }
+
+    fn connect_tcp(
+        &self,
+        addr: SocketAddr,
+        keepalive: Option<Duration>,
+    ) -> BoxFuture<'static, io::Result<Socket>> {
+        if addr.port() == 9 {
+            future::pending().boxed()
+        } else {
+            TokioRuntime.connect_tcp(addr, keepalive)
+        }
+    }
+
+    #[cfg(unix)]
+    fn connect_unix(&self, path: PathBuf) -> BoxFuture<'static, io::Result<Socket>> {
+        TokioRuntime.connect_unix(path)
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        TokioRuntime.sleep(duration)
+    }
+}
+
+struct StaticResolver(Vec<SocketAddr>);
+
+impl Resolver for StaticResolver {
+    fn lookup_host(&self, _: String, _: u16) -> BoxFuture<'static, io::Result<Vec<SocketAddr>>> {
+        future::ok(self.0.clone()).boxed()
+    }
+}
+
+#[tokio::test]
+async fn happy_eyeballs() {
+    let resolver = StaticResolver(vec![
+        "[::1]:9".parse().unwrap(),
+        "127.0.0.1:9".parse().unwrap(),
+        "127.0.0.1:5433".parse().unwrap(),
+    ]);
+    let mut config = "host=db.invalid user=postgres".parse::<Config>().unwrap();
+    config
+        .runtime(Arc::new(BlackholeRuntime))
+        .resolver(Arc::new(resolver))
+        .address_strategy(AddressStrategy::HappyEyeballs)
+        .happy_eyeballs_delay(Duration::from_millis(10));
+
+    let (client, connection) = time::timeout(Duration::from_secs(10), config.connect(NoTls))
+        .await
+        .unwrap()
+        .unwrap();
+    tokio::spawn(connection.map(|e| e.unwrap()));
+
+    let rows = client.query("SELECT 1::INT", &[]).await.unwrap();
+    assert_eq!(rows[0].get::<_, i32>(0), 1);
+}
+
+#[tokio::test]
+async fn sequential_address_timeout() {
+    let resolver = StaticResolver(vec![
+        "127.0.0.1:9".parse().unwrap(),
+        "127.0.0.1:5433".parse().unwrap(),
+    ]);
+    let mut config = "host=db.invalid user=postgres".parse::<Config>().unwrap();
+    config
+        .runtime(Arc::new(BlackholeRuntime))
+        .resolver(Arc::new(resolver))
+        .connect_timeout(Duration::from_millis(100));
+
+    let (client, connection) = config.connect(NoTls).await.unwrap();
+    tokio::spawn(connection.map(|e| e.unwrap()));
+    client.batch_execute("SELECT 1").await.unwrap();
+}
+
+// Resolves every host to a single address which can be changed, like a DNS record updated after a failover.
+struct SwitchingResolver {
+    addr: Mutex<SocketAddr>,
+    lookups: AtomicUsize,
+}
+
+impl SwitchingResolver {
+    fn new(addr: &str) -> SwitchingResolver {
+        SwitchingResolver {
+            addr: Mutex::new(addr.parse().unwrap()),
+            lookups: AtomicUsize::new(0),
+        }
+    }
+
+    fn switch(&self, addr: &str) {
+        *self.addr.lock().unwrap() = addr.parse().unwrap();
+    }
+
+    fn lookups(&self) -> usize {
+        self.lookups.load(Ordering::SeqCst)
+    }
+}
+
+impl Resolver for SwitchingResolver {
+    fn lookup_host(&self, _: String, _: u16) -> BoxFuture<'static, io::Result<Vec<SocketAddr>>> {
+        self.lookups.fetch_add(1, Ordering::SeqCst);
+        future::ok(vec![*self.addr.lock().unwrap()]).boxed()
+    }
+}
+
+#[tokio::test]
+async fn caching_resolver() {
+    let inner = Arc::new(SwitchingResolver::new("127.0.0.1:5433"));
+    let resolver = CachingResolver::with_resolver(inner.clone(), Duration::from_secs(3600));
+    let mut config = "host=db.invalid user=postgres".parse::<Config>().unwrap();
+    config.resolver(Arc::new(resolver.clone()));
+
+    config.connect(NoTls).await.map(drop).unwrap();
+    config.connect(NoTls).await.map(drop).unwrap();
+    assert_eq!(inner.lookups(), 1);
+
+    // the cached address is used until it is invalidated
+    inner.switch("127.0.0.1:1");
+    config.connect(NoTls).await.map(drop).unwrap();
+    assert_eq!(inner.lookups(), 1);
+    resolver.invalidate("db.invalid", 5432);
+    config.connect(NoTls).await.map(drop).unwrap_err();
+    assert_eq!(inner.lookups(), 2);
+
+    // addresses which can't be connected to are discarded
+    inner.switch("127.0.0.1:5433");
+    config.connect(NoTls).await.map(drop).unwrap();
+    assert_eq!(inner.lookups(), 3);
+    config.connect(NoTls).await.map(drop).unwrap();
+    assert_eq!(inner.lookups(), 3);
+
+    let resolver = CachingResolver::with_resolver(inner.clone(), Duration::from_secs(0));
+    config.resolver(Arc::new(resolver));
+    config.connect(NoTls).await.map(drop).unwrap();
+    config.connect(NoTls).await.map(drop).unwrap();
+    assert_eq!(inner.lookups(), 5);
+}
+
+#[tokio::test]
+async fn reconnecting_client_resolves_again() {
+    let inner = Arc::new(SwitchingResolver::new("127.0.0.1:5433"));
+    let resolver = CachingResolver::with_resolver(inner.clone(), Duration::from_secs(3600));
+    let mut config = "host=db.invalid user=postgres".parse::<Config>().unwrap();
+    config.resolver(Arc::new(resolver.clone()));
+
+    let client = ReconnectingClient::new(move || {
+        let config = config.clone();
+        async move {
+            let (client, connection) = config.connect(NoTls).await?;
+            tokio::spawn(connection);
+            Ok(client)
+        }
+    })
+    .resolver(resolver);
+
+    let pid = client
+        .query_one_idempotent("SELECT pg_backend_pid()", &[])
+        .await
+        .unwrap()
+        .get::<_, i32>(0);
+    assert_eq!(inner.lookups(), 1);
+
+    connect("host=localhost port=5433 user=postgres")
+        .await
+        .execute("SELECT pg_terminate_backend($1)", &[&pid])
+        .await
+        .unwrap();
+
+    let new_pid = client
+        .query_one_idempotent("SELECT pg_backend_pid()", &[])
+        .await
+        .unwrap()
+        .get::<_, i32>(0);
+    assert_ne!(pid, new_pid);
+    assert_eq!(inner.lookups(), 2);
+}
+
+#[derive(Default)]
+struct TestListener {
+    events: Mutex<Vec<String>>,
+}
+
+impl ConnectionListener for TestListener {
+    fn tls_established(&self) {
+        self.events.lock().unwrap().push("tls".to_string());
+    }
+
+    fn authenticated(&self, method: AuthenticationMethod) {
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("authenticated {:?}", method));
+    }
+
+    fn connected(&self, event: &ConnectedEvent<'_>) {
+        assert!(event.process_id() != 0);
+        assert!(event.server_version().is_some());
+        let host = match event.host() {
+            Some(Host::Tcp(host)) => host.as_str(),
+            _ => "unknown",
+        };
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("connected {}:{}", host, event.port().unwrap()));
+    }
+
+    fn disconnected(&self, error: Option<&Error>) {
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("disconnected {}", error.is_some()));
+    }
+}
+
+#[tokio::test]
+async fn connection_listener() {
+    let listener = Arc::new(TestListener::default());
+    let mut config = "host=localhost port=5433 user=postgres"
+        .parse::<Config>()
+        .unwrap();
+    config.connection_listener(listener.clone());
+
+    let (client, connection) = config.connect(NoTls).await.unwrap();
+    let connection = tokio::spawn(connection);
+    client.simple_query("SELECT 1").await.unwrap();
+    drop(client);
+    connection.await.unwrap().unwrap();
+
+    let events = listener.events.lock().unwrap().split_off(0);
+    assert_eq!(events.len(), 3);
+    assert!(events[0].starts_with("authenticated "));
+    assert_eq!(events[1], "connected localhost:5433");
+    assert_eq!(events[2], "disconnected false");
+
+    // a connection terminated by the server reports the error
+    let (client, connection) = config.connect(NoTls).await.unwrap();
+    let connection = tokio::spawn(connection);
+    let pid = client
+        .query_one("SELECT pg_backend_pid()", &[])
+        .await
+        .unwrap()
+        .get::<_, i32>(0);
+    let other = connect("host=localhost port=5433 user=postgres").await;
+    other
+        .execute("SELECT pg_terminate_backend($1)", &[&pid])
+        .await
+        .unwrap();
+    connection.await.unwrap().unwrap_err();
+
+    let events = listener.events.lock().unwrap();
+    assert_eq!(events.last().unwrap(), "disconnected true");
+}
+
+#[derive(Default)]
+struct WatchdogListener {
+    events: Mutex<Vec<WatchdogEvent>>,
+}
+
+impl ConnectionListener for WatchdogListener {
+    fn watchdog_triggered(&self, event: &WatchdogEvent) {
+        self.events.lock().unwrap().push(event.clone());
+    }
+}
+
+#[tokio::test]
+async fn watchdog() {
+    let listener = Arc::new(WatchdogListener::default());
+    let mut config = "host=localhost port=5433 user=postgres"
+        .parse::<Config>()
+        .unwrap();
+    config.connection_listener(listener.clone()).watchdog(
+        Watchdog::new()
+            .transaction_threshold(Duration::from_millis(100))
+            .query_threshold(Duration::from_millis(200))
+            .cancel_long_queries(NoTls),
+    );
+    let (client, connection) = config.connect(NoTls).await.unwrap();
+    tokio::spawn(connection);
+
+    // short transactions aren't reported
+    client.batch_execute("BEGIN; COMMIT").await.unwrap();
+
+    client.batch_execute("BEGIN").await.unwrap();
+    time::sleep(Duration::from_millis(300)).await;
+    client.batch_execute("COMMIT").await.unwrap();
+    {
+        let events = listener.events.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind(), WatchdogEventKind::LongTransaction);
+        assert!(events[0].elapsed() >= Duration::from_millis(100));
+        assert!(!events[0].canceled());
+    }
+
+    let err = client
+        .batch_execute("SELECT pg_sleep(5)")
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::QUERY_CANCELED));
+    let events = listener.events.lock().unwrap();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[1].kind(), WatchdogEventKind::LongQuery);
+    assert!(events[1].canceled());
+}
+
+#[tokio::test]
+async fn statement_cache() {
+    let mut config = "host=localhost port=5433 user=postgres"
+        .parse::<Config>()
+        .unwrap();
+    config
+        .statement_name_prefix("cached_")
+        .statement_cache_capacity(2);
+    let (client, connection) = config.connect(NoTls).await.unwrap();
+    tokio::spawn(connection);
+
+    async fn prepared_statements(client: &Client) -> Vec<String> {
+        client
+            .simple_query("SELECT name FROM pg_prepared_statements ORDER BY name")
+            .await
+            .unwrap()
+            .into_iter()
+            .filter_map(|m| match m {
+                SimpleQueryMessage::Row(row) => Some(row.get(0).unwrap().to_string()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    // repeated queries reuse the cached statement
+    for _ in 0..3 {
+        client.query("SELECT 1", &[]).await.unwrap();
+    }
+    let names = prepared_statements(&client).await;
+    assert_eq!(names.len(), 1);
+    assert!(names[0].starts_with("cached_"));
+    assert_eq!(client.statement_cache_len(), 1);
+
+    client.query("SELECT 2", &[]).await.unwrap();
+    client.query("SELECT 1", &[]).await.unwrap();
+    // evicts the least recently used statement, `SELECT 2`
+    client.query("SELECT 3", &[]).await.unwrap();
+    assert_eq!(client.statement_cache_len(), 2);
+    assert_eq!(prepared_statements(&client).await.len(), 2);
+    assert_eq!(names, prepared_statements(&client).await[..1]);
+
+    client.clear_statement_cache();
+    assert_eq!(client.statement_cache_len(), 0);
+    assert!(prepared_statements(&client).await.is_empty());
+}
+
+#[tokio::test]
+async fn cancel_on_drop() {
+    let mut config = "host=localhost port=5433 user=postgres"
+        .parse::<Config>()
+        .unwrap();
+    config.cancel_on_drop(NoTls);
+    let (client, connection) = config.connect(NoTls).await.unwrap();
+    tokio::spawn(connection);
+
+    let query = client.query("SELECT pg_sleep(10)", &[]);
+    time::timeout(Duration::from_millis(100), query)
+        .await
+        .unwrap_err();
+
+    let start = Instant::now();
+    let row = client.query_one("SELECT 1", &[]).await.unwrap();
+    assert_eq!(row.get::<_, i32>(0), 1);
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn shutdown_handle() {
+    let (client, connection) =
+        tokio_postgres::connect("host=localhost port=5433 user=postgres", NoTls)
+            .await
+            .unwrap();
+    let handle = connection.shutdown_handle();
+    let connection = tokio::spawn(connection);
+    assert!(!handle.is_closed());
+
+    let stmt = client.prepare("SELECT pg_sleep(0.1)::text").await.unwrap();
+    let query = client.query_one(&stmt, &[]);
+    pin_mut!(query);
+    assert!(futures::poll!(query.as_mut()).is_pending());
+    handle.shutdown();
+    // requests made before the shutdown was requested still complete
+    query.await.unwrap();
+
+    assert!(matches!(handle.closed().await, DisconnectCause::Shutdown));
+    assert!(matches!(
+        handle.disconnect_cause(),
+        Some(DisconnectCause::Shutdown)
+    ));
+    connection.await.unwrap().unwrap();
+    assert!(client.query("SELECT 1", &[]).await.unwrap_err().is_closed());
+
+    let (client, connection) =
+        tokio_postgres::connect("host=localhost port=5433 user=postgres", NoTls)
+            .await
+            .unwrap();
+    let handle = connection.shutdown_handle();
+    tokio::spawn(connection);
+    drop(client);
+    assert!(matches!(
+        handle.closed().await,
+        DisconnectCause::ClientDropped
+    ));
+
+    let (_client, connection) =
+        tokio_postgres::connect("host=localhost port=5433 user=postgres", NoTls)
+            .await
+            .unwrap();
+    let handle = connection.shutdown_handle();
+    drop(connection);
+    assert!(matches!(handle.closed().await, DisconnectCause::Dropped));
+}
+
+#[tokio::test]
+async fn server_settings() {
+    let mut config = "host=localhost port=5433 user=postgres options='-c statement_timeout=5s'"
+        .parse::<Config>()
+        .unwrap();
+    config
+        .server_setting("application_name", "my app\\1")
+        .server_setting("search_path", "\"my schema\", public");
+    let (client, connection) = config.connect(NoTls).await.unwrap();
+    tokio::spawn(connection);
+
+    let row = client
+        .query_one(
+            "SELECT current_setting('application_name'), current_setting('search_path'), \
+             current_setting('statement_timeout')",
+            &[],
+        )
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, &str>(0), "my app\\1");
+    assert_eq!(row.get::<_, &str>(1), "\"my schema\", public");
+    assert_eq!(row.get::<_, &str>(2), "5s");
+}
+
+#[tokio::test]
+async fn reconnecting_client() {
+    let client = ReconnectingClient::new(|| async {
+        let (client, connection) =
+            tokio_postgres::connect("host=localhost port=5433 user=postgres", NoTls).await?;
+        tokio::spawn(connection);
+        Ok(client)
+    })
+    .reconnect_policy(ReconnectPolicy::new().initial_backoff(Duration::from_millis(1)));
+    let admin = connect("host=localhost port=5433 user=postgres").await;
+
+    // terminates the client's current connection while the future is running
+    async fn terminate_during<F>(client: &ReconnectingClient, admin: &Client, f: F) -> F::Output
+    where
+        F: Future,
+    {
+        let pid = client
+            .client()
+            .await
+            .unwrap()
+            .query_one("SELECT pg_backend_pid()", &[])
+            .await
+            .unwrap()
+            .get::<_, i32>(0);
+        let terminate = async {
+            time::sleep(Duration::from_millis(50)).await;
+            admin
+                .execute("SELECT pg_terminate_backend($1)", &[&pid])
+                .await
+                .unwrap();
+        };
+        join!(f, terminate).0
+    }
+
+    let row = terminate_during(
+        &client,
+        &admin,
+        client.query_one_idempotent("SELECT pg_sleep(0.2)::text", &[]),
+    )
+    .await
+    .unwrap();
+    assert_eq!(row.get::<_, &str>(0), "");
+
+    // queries which aren't marked as idempotent fail, but the next query reconnects
+    terminate_during(&client, &admin, client.execute("SELECT pg_sleep(0.2)", &[]))
+        .await
+        .unwrap_err();
+    client.execute("SELECT 1", &[]).await.unwrap();
+
+    // queries in a transaction aren't retried, since the rest of the transaction was lost
+    client
+        .client()
+        .await
+        .unwrap()
+        .batch_execute("BEGIN")
+        .await
+        .unwrap();
+    terminate_during(
+        &client,
+        &admin,
+        client.query_idempotent("SELECT pg_sleep(0.2)", &[]),
+    )
+    .await
+    .unwrap_err();
+    client.query_idempotent("SELECT 1", &[]).await.unwrap();
+}
+
+#[tokio::test]
+async fn search_path() {
+    let admin = connect("host=localhost port=5433 user=postgres").await;
+    admin
+        .batch_execute("CREATE SCHEMA IF NOT EXISTS \"search path\"")
+        .await
+        .unwrap();
+
+    let config = "host=localhost port=5433 user=postgres search_path='search path, public'"
+        .parse::<Config>()
+        .unwrap();
+    assert_eq!(
+        config.get_search_path(),
+        Some(&["search path".to_string(), "public".to_string()][..])
+    );
+    let (client, connection) = config.connect(NoTls).await.unwrap();
+    tokio::spawn(connection);
+
+    let row = client
+        .query_one("SELECT current_setting('search_path')", &[])
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, &str>(0), "\"search path\", public");
+    assert_eq!(
+        client.search_path().await.unwrap(),
+        ["search path", "public"]
+    );
+
+    // the startup value is restored rather than the server's default
+    client
+        .batch_execute("SET search_path = pg_catalog")
+        .await
+        .unwrap();
+    client.batch_execute("DISCARD ALL").await.unwrap();
+    assert_eq!(
+        client.search_path().await.unwrap(),
+        ["search path", "public"]
+    );
+
+    let mut config = "host=localhost port=5433 user=postgres"
+        .parse::<Config>()
+        .unwrap();
+    config.search_path(vec!["$user", "missing", "public"]);
+    let (client, connection) = config.connect(NoTls).await.unwrap();
+    tokio::spawn(connection);
+
+    let row = client
+        .query_one("SELECT current_setting('search_path')", &[])
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, &str>(0), "\"$user\", missing, public");
+    assert_eq!(client.search_path().await.unwrap(), ["public"]);
+}
+
+#[tokio::test]
+async fn statement_cache_reprepare() {
+    let mut config = "host=localhost port=5433 user=postgres"
+        .parse::<Config>()
+        .unwrap();
+    config.statement_cache_capacity(2);
+    let (client, connection) = config.connect(NoTls).await.unwrap();
+    tokio::spawn(connection);
+
+    let row = client.query_one("SELECT $1::INT4", &[&1i32]).await.unwrap();
+    assert_eq!(row.get::<_, i32>(0), 1);
+
+    // simulates a connection pooler moving the session to a server session without the cached statement
+    client.batch_execute("DEALLOCATE ALL").await.unwrap();
+    let row = client.query_one("SELECT $1::INT4", &[&2i32]).await.unwrap();
+    assert_eq!(row.get::<_, i32>(0), 2);
+    client.batch_execute("DEALLOCATE ALL").await.unwrap();
+    assert_eq!(
+        client.execute("SELECT $1::INT4", &[&3i32]).await.unwrap(),
+        1
+    );
+    assert_eq!(client.statement_cache_len(), 1);
+
+    // the error aborts a transaction, so statements aren't prepared again within one
+    client.batch_execute("BEGIN; DEALLOCATE ALL").await.unwrap();
+    let err = client
+        .query_one("SELECT $1::INT4", &[&4i32])
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::INVALID_SQL_STATEMENT_NAME));
+    client.batch_execute("ROLLBACK").await.unwrap();
+
+    // statements prepared explicitly aren't prepared again either
+    let statement = client.prepare("SELECT 1").await.unwrap();
+    client.batch_execute("DEALLOCATE ALL").await.unwrap();
+    let err = client.query(&statement, &[]).await.unwrap_err();
+    assert_eq!(err.code(), Some(&SqlState::INVALID_SQL_STATEMENT_NAME));
+}
+
+#[tokio::test]
+async fn gss_enc_mode() {
+    connect("host=localhost port=5433 user=postgres gssencmode=prefer").await;
+
+    let err = tokio_postgres::connect(
+        "host=localhost port=5433 user=postgres gssencmode=require",
+        NoTls,
+    )
+    .await
+    .map(drop)
+    .unwrap_err();
+    assert!(err.to_string().contains("GSSAPI encryption"), "{}", err);
+}
+
+#[cfg(feature = "runtime-async-std")]
+#[test]
+fn async_std_runtime() {
+    use tokio_postgres::runtime::AsyncStdRuntime;
+
+    async_std::task::block_on(async {
+        let (client, connection) = "host=localhost port=5433 user=postgres"
+            .parse::<Config>()
+            .unwrap()
+            .runtime(Arc::new(AsyncStdRuntime))
+            .connect(NoTls)
+            .await
+            .unwrap();
+        async_std::task::spawn(connection.map(|e| e.unwrap()));
+
+        let rows = client.query("SELECT $1::INT", &[&1i32]).await.unwrap();
+        assert_eq!(rows[0].get::<_, i32>(0), 1);
+    });
+}
@@ -0,0 +1,55 @@
+use crate::connect;
+use tokio_postgres::testing::TestDatabase;
+use tokio_postgres::Config;
+
+fn admin() -> Config {
+    "host=localhost port=5433 user=postgres".parse().unwrap()
+}
+
+async fn database_exists(name: &str) -> bool {
+    let client = connect("user=postgres").await;
+    let row = client
+        .query_one(
+            "SELECT EXISTS (SELECT 1 FROM pg_database WHERE datname = $1)",
+            &[&name],
+        )
+        .await
+        .unwrap();
+    row.get(0)
+}
+
+#[tokio::test]
+async fn isolated_databases() {
+    let a = TestDatabase::with_config(admin()).await.unwrap();
+    let b = TestDatabase::with_config(admin()).await.unwrap();
+    assert_ne!(a.name(), b.name());
+
+    let client = a.connect().await.unwrap();
+    client
+        .batch_execute("CREATE TABLE foo (id INT)")
+        .await
+        .unwrap();
+    let row = client
+        .query_one("SELECT current_database()", &[])
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, &str>(0), a.name());
+
+    let client = b.connect().await.unwrap();
+    assert!(client.query("SELECT * FROM foo", &[]).await.is_err());
+
+    let name = a.name().to_string();
+    a.drop_database().await.unwrap();
+    assert!(!database_exists(&name).await);
+    b.drop_database().await.unwrap();
+}
+
+#[tokio::test]
+async fn dropped_with_open_connections() {
+    let db = TestDatabase::with_config(admin()).await.unwrap();
+    let name = db.name().to_string();
+    let _client = db.connect().await.unwrap();
+
+    drop(db);
+    assert!(!database_exists(&name).await);
+}
@@ -0,0 +1,43 @@
+use cidr_02::{IpCidr, IpInet};
+
+use crate::types::test_type;
+
+#[tokio::test]
+async fn test_cidr_params() {
+    test_type(
+        "CIDR",
+        &[
+            (
+                Some("127.0.0.0/8".parse::<IpCidr>().unwrap()),
+                "'127.0.0.0/8'",
+            ),
+            (
+                Some("2001:db8::/32".parse::<IpCidr>().unwrap()),
+                "'2001:db8::/32'",
+            ),
+            (Some("10.1.2.3".parse::<IpCidr>().unwrap()), "'10.1.2.3/32'"),
+            (None, "NULL"),
+        ],
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_inet_params() {
+    test_type(
+        "INET",
+        &[
+            (
+                Some("127.0.0.1/8".parse::<IpInet>().unwrap()),
+                "'127.0.0.1/8'",
+            ),
+            (
+                Some("2001:db8::1/64".parse::<IpInet>().unwrap()),
+                "'2001:db8::1/64'",
+            ),
+            (Some("10.1.2.3".parse::<IpInet>().unwrap()), "'10.1.2.3'"),
+            (None, "NULL"),
+        ],
+    )
+    .await
+}
@@ -1,22 +1,29 @@
 use postgres_types::to_sql_checked;
 use std::collections::HashMap;
+use std::convert::TryInto;
 use std::error::Error;
 use std::f32;
 use std::f64;
 use std::fmt;
 use std::net::IpAddr;
+use std::num::{NonZeroI32, NonZeroU64};
 use std::result;
 use std::str::FromStr;
-use std::time::{Duration, UNIX_EPOCH};
-use tokio_postgres::types::{FromSql, FromSqlOwned, IsNull, Kind, PgLsn, ToSql, Type, WrongType};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio_postgres::types::{
+    BpChar, FromSql, FromSqlOwned, Interval, IsNull, Kind, Lossy, PgJsonPath, PgLsn, PgMoney,
+    PgNumeric, PgSnapshot, ToSql, Type, WrongType,
+};
 
 use crate::connect;
-use bytes::BytesMut;
+use bytes::{Bytes, BytesMut};
 
 #[cfg(feature = "with-bit-vec-0_6")]
 mod bit_vec_06;
 #[cfg(feature = "with-chrono-0_4")]
 mod chrono_04;
+#[cfg(feature = "with-cidr-0_2")]
+mod cidr_02;
 #[cfg(feature = "with-eui48-0_4")]
 mod eui48_04;
 #[cfg(feature = "with-eui48-1")]
@@ -25,6 +32,8 @@ mod eui48_1;
 mod geo_types_06;
 #[cfg(feature = "with-geo-types-0_7")]
 mod geo_types_07;
+#[cfg(feature = "with-pgvector")]
+mod pgvector;
 #[cfg(feature = "with-serde_json-1")]
 mod serde_json_1;
 #[cfg(feature = "with-time-0_2")]
@@ -127,6 +136,24 @@ async fn test_oid_params() {
     .await;
 }
 
+#[tokio::test]
+async fn test_non_zero_params() {
+    test_type(
+        "INT",
+        &[
+            (NonZeroI32::new(-1), "-1"),
+            (NonZeroI32::new(i32::MAX), "2147483647"),
+            (None, "NULL"),
+        ],
+    )
+    .await;
+    test_type("BIGINT", &[(NonZeroU64::new(42), "42"), (None, "NULL")]).await;
+
+    let client = connect("user=postgres").await;
+    let row = client.query_one("SELECT 0::INT", &[]).await.unwrap();
+    assert!(row.try_get::<_, NonZeroI32>(0).is_err());
+}
+
 #[tokio::test]
 async fn test_i64_params() {
     test_type(
@@ -140,6 +167,59 @@ async fn test_i64_params() {
     .await;
 }
 
+#[tokio::test]
+async fn test_checked_integer_params() {
+    test_type(
+        "SMALLINT",
+        &[(Some(-128i8), "-128"), (Some(127), "127"), (None, "NULL")],
+    )
+    .await;
+    test_type(
+        "INT",
+        &[(Some(0u16), "0"), (Some(65535), "65535"), (None, "NULL")],
+    )
+    .await;
+    test_type(
+        "BIGINT",
+        &[(Some(4_294_967_295u32), "4294967295"), (None, "NULL")],
+    )
+    .await;
+    test_type(
+        "NUMERIC",
+        &[
+            (Some(0u64), "0"),
+            (Some(10_000), "10000"),
+            (Some(18_446_744_073_709_551_615), "18446744073709551615"),
+            (None, "NULL"),
+        ],
+    )
+    .await;
+
+    let client = connect("user=postgres").await;
+    let row = client
+        .query_one(
+            "SELECT -1::INT8, 70000::INT4, 1.5::NUMERIC, 2.000::NUMERIC, 1e20::NUMERIC",
+            &[],
+        )
+        .await
+        .unwrap();
+    assert!(row.try_get::<_, u64>(0).is_err());
+    assert!(row.try_get::<_, u16>(1).is_err());
+    assert!(row.try_get::<_, u64>(2).is_err());
+    assert_eq!(row.get::<_, u64>(3), 2);
+    assert!(row.try_get::<_, u64>(4).is_err());
+
+    let err = client
+        .execute("SELECT $1::INT2", &[&40000u16])
+        .await
+        .unwrap_err();
+    assert!(err
+        .source()
+        .unwrap()
+        .to_string()
+        .contains("out of range for type int2"));
+}
+
 #[tokio::test]
 async fn test_lsn_params() {
     test_type(
@@ -152,6 +232,155 @@ async fn test_lsn_params() {
     .await
 }
 
+#[tokio::test]
+async fn test_numeric_params() {
+    let values = [
+        "0",
+        "0.00",
+        "1.000",
+        "-0.00012",
+        "1500",
+        "123456789012.000100",
+        "-98765.4321",
+        "0.00000001",
+        "NaN",
+        "Infinity",
+        "-Infinity",
+    ];
+    let checks = values
+        .iter()
+        .map(|v| (Some(v.parse::<PgNumeric>().unwrap()), format!("'{}'", v)))
+        .chain(Some((None, "NULL".to_string())))
+        .collect::<Vec<_>>();
+    test_type("NUMERIC", &checks).await;
+
+    let client = connect("user=postgres").await;
+    for value in &["-0", "1.5e3", "12e-3", "+7.10", " 42 "] {
+        let row = client
+            .query_one(
+                &*format!("SELECT '{}'::NUMERIC, '{0}'::NUMERIC::TEXT", value),
+                &[],
+            )
+            .await
+            .unwrap();
+        let numeric = row.get::<_, PgNumeric>(0);
+        assert_eq!(numeric.to_string(), row.get::<_, &str>(1));
+        assert_eq!(value.parse::<PgNumeric>().unwrap(), numeric);
+    }
+
+    "1.2.3".parse::<PgNumeric>().unwrap_err();
+    "".parse::<PgNumeric>().unwrap_err();
+}
+
+#[tokio::test]
+async fn test_lossy_numeric() {
+    let client = connect("user=postgres").await;
+
+    let row = client
+        .query_one(
+            "SELECT 1.5::NUMERIC, -0.1::NUMERIC, 'NaN'::NUMERIC, '-Infinity'::NUMERIC, 1e400::NUMERIC, \
+             NULL::NUMERIC",
+            &[],
+        )
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, Lossy<f64>>(0), Lossy(1.5));
+    assert_eq!(row.get::<_, Lossy<f64>>(1), Lossy(-0.1));
+    assert_eq!(row.get::<_, Lossy<f32>>(1), Lossy(-0.1));
+    assert!(row.get::<_, Lossy<f64>>(2).0.is_nan());
+    assert_eq!(row.get::<_, Lossy<f64>>(3), Lossy(f64::NEG_INFINITY));
+    assert_eq!(row.get::<_, Lossy<f64>>(4), Lossy(f64::INFINITY));
+    assert_eq!(row.get::<_, Option<Lossy<f64>>>(5), None);
+
+    assert!(row.try_get::<_, f64>(0).is_err());
+}
+
+#[tokio::test]
+async fn test_money_params() {
+    let client = connect("user=postgres").await;
+    client.batch_execute("SET lc_monetary = 'C'").await.unwrap();
+
+    for value in &["$0.05", "-$0.05", "$1,234.00", "-$1,234,567.89"] {
+        let row = client
+            .query_one(
+                &*format!("SELECT '{}'::MONEY, $1::MONEY::TEXT", value),
+                &[&value.parse::<PgMoney>().unwrap()],
+            )
+            .await
+            .unwrap();
+        let money = row.get::<_, PgMoney>(0);
+        assert_eq!(money.to_string(), *value);
+        assert_eq!(row.get::<_, &str>(1), *value);
+    }
+
+    assert_eq!("12.5".parse::<PgMoney>().unwrap(), PgMoney::from(1250));
+    "$1.234".parse::<PgMoney>().unwrap_err();
+}
+
+#[tokio::test]
+async fn test_jsonpath_params() {
+    test_type(
+        "JSONPATH",
+        &[
+            (
+                Some(PgJsonPath::from(r#"$."a"[*]?(@ > 2)"#)),
+                r#"'$.a[*] ? (@ > 2)'"#,
+            ),
+            (
+                Some(PgJsonPath::from(r#"strict $."b c".size()"#)),
+                r#"'strict $."b c".size()'"#,
+            ),
+            (None, "NULL"),
+        ],
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_snapshot_params() {
+    let snapshot = PgSnapshot {
+        xmin: 10,
+        xmax: 20,
+        xip: vec![10, 14, 15],
+    };
+    for ty in &["PG_SNAPSHOT", "TXID_SNAPSHOT"] {
+        test_type(
+            ty,
+            &[
+                (Some(snapshot.clone()), "'10:20:10,14,15'"),
+                (
+                    Some(PgSnapshot {
+                        xmin: 5,
+                        xmax: 5,
+                        xip: vec![],
+                    }),
+                    "'5:5:'",
+                ),
+                (None, "NULL"),
+            ],
+        )
+        .await;
+    }
+
+    assert_eq!(snapshot.to_string(), "10:20:10,14,15");
+    assert!(snapshot.is_visible(9));
+    assert!(!snapshot.is_visible(10));
+    assert!(snapshot.is_visible(13));
+    assert!(!snapshot.is_visible(20));
+
+    let client = connect("user=postgres").await;
+    let row = client
+        .query_one(
+            "SELECT pg_current_snapshot(), pg_current_xact_id()::TEXT::BIGINT",
+            &[],
+        )
+        .await
+        .unwrap();
+    let snapshot = row.get::<_, PgSnapshot>(0);
+    let xid = row.get::<_, i64>(1) as u64;
+    assert!(snapshot.xmin <= xid && xid <= snapshot.xmax);
+}
+
 #[tokio::test]
 async fn test_f32_params() {
     test_type(
@@ -263,6 +492,25 @@ async fn test_bpchar_params() {
     );
 }
 
+#[tokio::test]
+async fn test_bpchar_trimmed() {
+    let client = connect("user=postgres").await;
+
+    let row = client
+        .query_one(
+            "SELECT 'ab'::CHAR(4), $1::CHAR(4), ''::CHAR(2), ' a'::CHAR(3), NULL::CHAR(3)",
+            &[&BpChar("cd")],
+        )
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, String>(0), "ab  ");
+    assert_eq!(row.get::<_, BpChar<String>>(0), BpChar("ab".to_string()));
+    assert_eq!(row.get::<_, BpChar<&str>>(1), BpChar("cd"));
+    assert_eq!(row.get::<_, BpChar<&str>>(2), BpChar(""));
+    assert_eq!(row.get::<_, BpChar<&str>>(3), BpChar(" a"));
+    assert_eq!(row.get::<_, Option<BpChar<String>>>(4), None);
+}
+
 #[tokio::test]
 async fn test_citext_params() {
     let client = connect("user=postgres").await;
@@ -301,6 +549,32 @@ async fn test_citext_params() {
     assert_eq!(vec!["foobar".to_string(), "FooBar".to_string()], rows,);
 }
 
+#[tokio::test]
+async fn test_citext_array() {
+    let client = connect("user=postgres").await;
+
+    let stmt = client
+        .prepare("SELECT $1::CITEXT[], 'a'::CITEXT = 'A'")
+        .await
+        .unwrap();
+    let ty = &stmt.columns()[0].type_();
+    assert_eq!(ty.name(), "_citext");
+    match ty.kind() {
+        Kind::Array(member) => {
+            assert_eq!(member.name(), "citext");
+            assert_eq!(member.kind(), &Kind::Simple);
+            assert_eq!(Type::from_oid(member.oid()), None);
+        }
+        kind => panic!("unexpected kind {:?}", kind),
+    }
+    let row = client
+        .query_one(&stmt, &[&vec!["foo", "BAR"]])
+        .await
+        .unwrap();
+    assert_eq!(row.get::<_, Vec<String>>(0), ["foo", "BAR"]);
+    assert!(row.get::<_, bool>(1));
+}
+
 #[tokio::test]
 async fn test_bytea_params() {
     test_type(
@@ -313,6 +587,26 @@ async fn test_bytea_params() {
     .await;
 }
 
+#[tokio::test]
+async fn test_bytes_params() {
+    test_type(
+        "BYTEA",
+        &[
+            (
+                Some(Bytes::from_static(&[0u8, 1, 2, 3, 254, 255])),
+                "'\\x00010203feff'",
+            ),
+            (None, "NULL"),
+        ],
+    )
+    .await;
+    test_type(
+        "BYTEA",
+        &[(Some(BytesMut::from(&b"abc"[..])), "'abc'"), (None, "NULL")],
+    )
+    .await;
+}
+
 #[tokio::test]
 async fn test_borrowed_bytea() {
     let client = connect("user=postgres").await;
@@ -616,6 +910,140 @@ async fn system_time() {
         ],
     )
     .await;
+
+    test_type(
+        "TIMESTAMPTZ",
+        &[
+            (
+                Some(UNIX_EPOCH + Duration::from_micros(1_500_000_000_123_456)),
+                "'2017-07-14 02:40:00.123456+00'",
+            ),
+            (
+                Some(UNIX_EPOCH - Duration::from_secs(86_400 * 365 * 100)),
+                "'1870-01-25 00:00:00+00'",
+            ),
+            (None, "NULL"),
+        ],
+    )
+    .await;
+
+    let client = connect("user=postgres").await;
+    let row = client
+        .query_one("SELECT 'infinity'::TIMESTAMPTZ", &[])
+        .await
+        .unwrap();
+    let err = row.try_get::<_, SystemTime>(0).unwrap_err();
+    assert!(err.to_string().contains("timestamp is infinite"), "{}", err);
+
+    let far_future = UNIX_EPOCH + Duration::from_secs(400_000 * 365 * 86_400);
+    let err = client
+        .query_one("SELECT $1::TIMESTAMPTZ", &[&far_future])
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("out of range"), "{}", err);
+}
+
+#[tokio::test]
+async fn duration() {
+    test_type(
+        "INTERVAL",
+        &[
+            (Some(Duration::from_secs(90)), "'90 seconds'"),
+            (Some(Duration::from_micros(1_500)), "'1.5 milliseconds'"),
+            (Some(Duration::from_secs(26 * 3600)), "'1 day 2 hours'"),
+            (Some(Duration::from_secs(0)), "'0'"),
+            (None, "NULL"),
+        ],
+    )
+    .await;
+
+    let client = connect("user=postgres").await;
+    let row = client
+        .query_one(
+            "SELECT '1 year 1 month 1 day'::INTERVAL, '-1 second'::INTERVAL,
+                EXTRACT(EPOCH FROM '1 year 1 month 1 day'::INTERVAL)::FLOAT8",
+            &[],
+        )
+        .await
+        .unwrap();
+    let err = row.try_get::<_, Duration>(0).unwrap_err();
+    assert!(err.to_string().contains("month component"), "{}", err);
+    let err = row.try_get::<_, Duration>(1).unwrap_err();
+    assert!(err.to_string().contains("negative"), "{}", err);
+    assert_eq!(
+        row.get::<_, Lossy<Duration>>(0),
+        Lossy(Duration::from_secs_f64(row.get(2)))
+    );
+    row.try_get::<_, Lossy<Duration>>(1).unwrap_err();
+}
+
+#[tokio::test]
+async fn special_interval() {
+    #[derive(Debug, PartialEq)]
+    struct RawInterval {
+        microseconds: i64,
+        days: i32,
+        months: i32,
+    }
+
+    impl ToSql for RawInterval {
+        fn to_sql(
+            &self,
+            _: &Type,
+            out: &mut BytesMut,
+        ) -> result::Result<IsNull, Box<dyn Error + Sync + Send>> {
+            out.extend_from_slice(&self.microseconds.to_be_bytes());
+            out.extend_from_slice(&self.days.to_be_bytes());
+            out.extend_from_slice(&self.months.to_be_bytes());
+            Ok(IsNull::No)
+        }
+
+        fn accepts(ty: &Type) -> bool {
+            *ty == Type::INTERVAL
+        }
+
+        to_sql_checked!();
+    }
+
+    impl<'a> FromSql<'a> for RawInterval {
+        fn from_sql(_: &Type, raw: &[u8]) -> result::Result<Self, Box<dyn Error + Sync + Send>> {
+            Ok(RawInterval {
+                microseconds: i64::from_be_bytes(raw[..8].try_into()?),
+                days: i32::from_be_bytes(raw[8..12].try_into()?),
+                months: i32::from_be_bytes(raw[12..].try_into()?),
+            })
+        }
+
+        fn accepts(ty: &Type) -> bool {
+            *ty == Type::INTERVAL
+        }
+    }
+
+    test_type(
+        "INTERVAL",
+        &[
+            (
+                Some(Interval::Value(RawInterval {
+                    microseconds: 3_000_000,
+                    days: 2,
+                    months: 1,
+                })),
+                "'1 mon 2 days 3 seconds'",
+            ),
+            (None, "NULL"),
+        ],
+    )
+    .await;
+
+    // older servers can't parse infinite intervals, but store them as sent
+    let client = connect("user=postgres").await;
+    for value in &[Interval::<RawInterval>::PosInfinity, Interval::NegInfinity] {
+        let row = client
+            .query_one("SELECT $1::INTERVAL", &[value])
+            .await
+            .unwrap();
+        assert_eq!(row.get::<_, Interval<RawInterval>>(0), *value);
+    }
 }
 
 #[tokio::test]
@@ -0,0 +1,68 @@
+use half_2::f16;
+use tokio_postgres::types::{HalfVector, SparseVector, Vector};
+
+use crate::connect;
+use crate::types::test_type;
+
+async fn create_extension() {
+    let client = connect("user=postgres").await;
+    client
+        .batch_execute("CREATE EXTENSION IF NOT EXISTS vector")
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_vector_params() {
+    create_extension().await;
+    test_type(
+        "VECTOR",
+        &[
+            (Some(Vector(vec![1., -2.5, 3.])), "'[1,-2.5,3]'"),
+            (Some(Vector(vec![0.])), "'[0]'"),
+            (None, "NULL"),
+        ],
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_halfvec_params() {
+    create_extension().await;
+    test_type(
+        "HALFVEC",
+        &[
+            (
+                Some(HalfVector(vec![f16::from_f32(1.), f16::from_f32(-2.5)])),
+                "'[1,-2.5]'",
+            ),
+            (None, "NULL"),
+        ],
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_sparsevec_params() {
+    create_extension().await;
+    test_type(
+        "SPARSEVEC",
+        &[
+            (
+                Some(SparseVector {
+                    dim: 5,
+                    indices: vec![0, 3],
+                    values: vec![1., -2.5],
+                }),
+                "'{1:1,4:-2.5}/5'",
+            ),
+            (Some(SparseVector::from_dense(&[0., 0.])), "'{}/2'"),
+            (None, "NULL"),
+        ],
+    )
+    .await;
+
+    let v = SparseVector::from_dense(&[0., 1., 0., 2.]);
+    assert_eq!(v.indices, [1, 3]);
+    assert_eq!(v.to_dense(), [0., 1., 0., 2.]);
+}